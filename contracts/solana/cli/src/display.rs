@@ -0,0 +1,140 @@
+//! Plain `field: value` listings of decoded accounts.
+
+use orki_gateway_client::orki_gateway::state::{FeeTier, GlobalState, Merchant, Payment, PaymentId, TokenFeeConfig};
+use solana_sdk::pubkey::Pubkey;
+
+fn or_none(key: &Pubkey) -> String {
+    if *key == Pubkey::default() {
+        "none".to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+fn token(key: &Pubkey) -> String {
+    if *key == Pubkey::default() {
+        "SOL".to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+pub fn global_state(state: &GlobalState) {
+    println!("admin: {}", state.admin);
+    println!("pending_admin: {}", state.pending_admin.map_or("none".to_string(), |k| k.to_string()));
+    println!("guardian: {}", or_none(&state.guardian));
+    println!("arbiter: {}", or_none(&state.arbiter));
+    println!("paused: {}", state.paused);
+    println!("fee_bps: {}", state.fee_bps);
+    println!("max_fee_bps: {}", state.fee_cap());
+    println!("fee_rounding: {:?}", state.fee_rounding);
+    println!("crank_reward: {}", state.crank_reward);
+    println!("admin_council: {}", state.admin_council);
+    match state.pending_fee_bps {
+        Some(bps) => println!("pending_fee_bps: {bps} (effective at {})", state.fee_effective_at),
+        None => println!("pending_fee_bps: none"),
+    }
+    println!("fee_timelock_seconds: {}", state.fee_timelock_seconds);
+    if state.fee_notice_until != 0 {
+        println!("fee_notice_until: {}", state.fee_notice_until);
+    }
+    println!("fee_wallet: {}", state.fee_wallet);
+    println!("escrow_release_delay: {}", state.escrow_release_delay);
+    println!("authorization_expiry: {}", state.authorization_expiry);
+    println!("payment_retention_seconds: {}", state.payment_retention_seconds);
+    println!("version: {}", state.version);
+}
+
+pub fn token_fee(mint: &Pubkey, config: Option<&TokenFeeConfig>) {
+    match config.and_then(TokenFeeConfig::rate) {
+        Some(bps) => println!("{}: {bps} bps", token(mint)),
+        None => println!("{}: global fee", token(mint)),
+    }
+    if let Some(TokenFeeConfig { pending_fee_bps: Some(bps), fee_effective_at, .. }) = config {
+        println!("{}: {bps} bps pending (effective at {fee_effective_at})", token(mint));
+    }
+}
+
+pub fn fee_tiers(tiers: &[FeeTier]) {
+    if tiers.is_empty() {
+        println!("fee_tiers: none");
+    }
+    for tier in tiers {
+        println!("fee_tier: {} bps from volume {}", tier.fee_bps, tier.volume_threshold);
+    }
+}
+
+pub fn merchant(address: &Pubkey, merchant: &Merchant) {
+    println!("address: {address}");
+    println!("name: {}", merchant.name);
+    println!("owner: {}", merchant.owner);
+    println!("category: {}", merchant.category);
+    println!("metadata_uri: {}", if merchant.metadata_uri.is_empty() { "none" } else { &merchant.metadata_uri });
+    println!("settlement_wallet: {}", merchant.settlement_wallet);
+    println!("settlement_token: {}", token(&merchant.settlement_token));
+    println!("accepts_any_token: {}", merchant.accepts_any_token);
+    println!("active: {}", merchant.active);
+    println!("admin_locked: {}", merchant.admin_locked);
+    println!("admin_frozen: {}", merchant.admin_frozen);
+    println!("tax_bps: {}", merchant.tax_bps);
+    println!("confirmation_message: {}", if merchant.confirmation_message.is_empty() { "none" } else { &merchant.confirmation_message });
+    println!("sandbox: {}", merchant.sandbox);
+    println!("swap_enabled: {}", merchant.swap_enabled);
+    println!("escrow_enabled: {}", merchant.escrow_enabled);
+    println!("split_enabled: {}", merchant.split_enabled);
+    println!("rewards_enabled: {} (required: {})", merchant.rewards_enabled, merchant.rewards_required);
+    println!("settlement_mode: {:?}", merchant.settlement_mode);
+    println!("accepted_tokens_enabled: {}", merchant.accepted_tokens_enabled);
+    println!(
+        "fee_bps_override: {}",
+        merchant.fee_bps_override.map_or("none".to_string(), |bps| bps.to_string())
+    );
+    println!("fee_exempt: {}", merchant.fee_exempt);
+    println!("allow_self_payments: {}", merchant.allow_self_payments);
+    println!("daily_limit: {}", if merchant.daily_limit == 0 { "unlimited".to_string() } else { merchant.daily_limit.to_string() });
+    println!(
+        "min_interval_per_payer: {}",
+        if merchant.min_interval_per_payer == 0 { "none".to_string() } else { format!("{}s", merchant.min_interval_per_payer) }
+    );
+    println!("referrer: {} ({} bps)", or_none(&merchant.referrer), merchant.referrer_bps);
+    println!("min_amount: {}", merchant.min_amount);
+    println!("max_amount: {}", merchant.max_amount);
+    println!("operators: {}", merchant.operators);
+    println!("settlement_routes: {}", merchant.settlement_routes);
+    println!("discount_codes: {}", merchant.discount_codes);
+    println!("open_authorizations: {}", merchant.open_authorizations);
+    println!("open_streams: {}", merchant.open_streams);
+    println!("open_invoices: {}", merchant.open_invoices);
+    println!("funded_escrows: {}", merchant.funded_escrows);
+    println!("previous_merchant: {}", or_none(&merchant.previous_merchant));
+    println!("version: {}", merchant.version);
+    println!("merchant_index: {}", merchant.merchant_index);
+}
+
+pub fn payment(address: &Pubkey, payment: &Payment) {
+    println!("address: {address}");
+    println!("payer: {}", payment.payer);
+    println!("merchant: {}", payment.merchant);
+    match payment.id() {
+        PaymentId::Sequence(id) => println!("payment_id: {id}"),
+        // Stored records read back as one of the two; link payments as an Order
+        id => println!("order_id: {}", id.order().iter().map(|b| format!("{b:02x}")).collect::<String>()),
+    }
+    println!("token: {}", token(&payment.mint));
+    println!("amount: {}", payment.amount);
+    println!("fee: {}", payment.fee);
+    println!("tip: {}", payment.tip);
+    println!("tax_amount: {}", payment.tax_amount);
+    if payment.discount_bps > 0 {
+        println!("pre_discount_amount: {} ({} bps off)", payment.pre_discount_amount, payment.discount_bps);
+    }
+    println!("refunded_amount: {}", payment.refunded_amount);
+    println!("status: {:?}", payment.status);
+    println!("escrowed: {}", payment.escrowed);
+    println!("sandbox: {}", payment.sandbox);
+    println!("timestamp: {}", payment.timestamp);
+    println!("memo: {}", payment.memo);
+    if payment.receipt_mint != Pubkey::default() {
+        println!("receipt_mint: {}", payment.receipt_mint);
+    }
+}
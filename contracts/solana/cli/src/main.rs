@@ -0,0 +1,587 @@
+//! `orki-cli`: admin and test commands for the gateway.
+
+mod display;
+
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::{system_program, AccountDeserialize};
+use clap::{Parser, Subcommand};
+use orki_gateway_client::errors::program_error;
+use orki_gateway_client::instructions::{
+    self, build, PaymentAsset, ProcessPaymentArgs, RegisterMerchantArgs,
+};
+use orki_gateway_client::orki_gateway::constants::MAX_FEE_BPS;
+use orki_gateway_client::orki_gateway::state::{FeeRounding, FeeTier, FeeTiersProposal, GlobalState, Merchant, Payment};
+use orki_gateway_client::orki_gateway::{self, accounts, instruction};
+use orki_gateway_client::{pda, rpc};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+
+#[derive(Parser)]
+#[command(name = "orki-cli", about = "Operate the Orki payment gateway")]
+struct Cli {
+    /// Signing keypair file
+    #[arg(long, short, env = "ORKI_KEYPAIR", default_value = "~/.config/solana/id.json", global = true)]
+    keypair: String,
+
+    /// RPC endpoint
+    #[arg(long, short, env = "ORKI_RPC_URL", default_value = "http://127.0.0.1:8899", global = true)]
+    url: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the global state with the signer as admin
+    Init {
+        #[arg(long)]
+        fee_bps: u16,
+        /// Defaults to the signer
+        #[arg(long)]
+        fee_wallet: Option<Pubkey>,
+        /// Cap on every fee rate; can only be lowered later
+        #[arg(long, default_value_t = MAX_FEE_BPS)]
+        max_fee_bps: u16,
+        /// Defaults to the signer, which pays the rent either way
+        #[arg(long)]
+        admin: Option<Pubkey>,
+        #[arg(long)]
+        guardian: Option<Pubkey>,
+    },
+    /// Propose a new protocol fee, or apply the pending one
+    SetFee {
+        #[arg(required_unless_present = "apply")]
+        fee_bps: Option<u16>,
+        #[arg(long, conflicts_with = "fee_bps")]
+        apply: bool,
+    },
+    /// Lower the fee cap; it can never be raised again
+    ReduceMaxFee {
+        max_fee_bps: u16,
+    },
+    /// Round fees down (the merchant keeps fractions) or up (the protocol does)
+    SetFeeRounding {
+        #[arg(value_parser = ["floor", "ceil"])]
+        rounding: String,
+    },
+    /// Send future protocol fees to a new wallet
+    SetFeeWallet {
+        fee_wallet: Pubkey,
+    },
+    /// Set the protocol fee for one token, apply a pending raise, or clear it
+    /// back to the global fee
+    SetTokenFee {
+        #[arg(required_unless_present_any = ["clear", "apply"])]
+        fee_bps: Option<u16>,
+        /// Defaults to SOL
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        #[arg(long, conflicts_with_all = ["fee_bps", "apply"])]
+        clear: bool,
+        #[arg(long, conflicts_with = "fee_bps")]
+        apply: bool,
+    },
+    /// Propose replacement volume fee tiers, or apply the pending ones; no
+    /// rows turns tiers off
+    SetFeeTiers {
+        /// VOLUME:BPS rows in ascending volume order
+        #[arg(value_parser = parse_fee_tier)]
+        tiers: Vec<FeeTier>,
+        #[arg(long, conflicts_with = "tiers")]
+        apply: bool,
+    },
+    /// Stop payments (admin or guardian)
+    Pause,
+    /// Resume payments (admin only)
+    Unpause,
+    /// Propose a new admin, or accept the pending transfer as the signer
+    UpdateAdmin {
+        #[arg(required_unless_present = "accept")]
+        new_admin: Option<Pubkey>,
+        #[arg(long, conflicts_with = "new_admin")]
+        accept: bool,
+    },
+    /// Register a merchant owned by the signer
+    RegisterMerchant {
+        name: String,
+        #[arg(long)]
+        settlement_wallet: Pubkey,
+        /// Defaults to SOL
+        #[arg(long)]
+        settlement_token: Option<Pubkey>,
+        #[arg(long)]
+        sandbox: bool,
+        #[arg(long)]
+        any_token: bool,
+        #[arg(long, requires = "referrer_bps")]
+        referrer: Option<Pubkey>,
+        #[arg(long, default_value_t = 0)]
+        referrer_bps: u16,
+        /// Off-chain profile, at most 128 bytes
+        #[arg(long, default_value = "")]
+        metadata_uri: String,
+        #[arg(long, default_value_t = 0)]
+        category: u8,
+    },
+    /// Update a merchant owned by the signer; omitted fields are unchanged
+    UpdateMerchant {
+        name: String,
+        #[arg(long)]
+        settlement_wallet: Option<Pubkey>,
+        #[arg(long)]
+        settlement_token: Option<Pubkey>,
+        #[arg(long)]
+        swap_enabled: Option<bool>,
+        #[arg(long)]
+        escrow_enabled: Option<bool>,
+        #[arg(long)]
+        min_amount: Option<u64>,
+        #[arg(long)]
+        max_amount: Option<u64>,
+        #[arg(long)]
+        any_token: Option<bool>,
+        #[arg(long)]
+        metadata_uri: Option<String>,
+        #[arg(long)]
+        category: Option<u8>,
+    },
+    /// Grow a merchant registered by an older program version to the current layout
+    UpgradeMerchant {
+        name: String,
+    },
+    /// Grow a payment recorded by an older program version to the current layout
+    UpgradePayment {
+        payment: Pubkey,
+    },
+    /// Print a merchant account
+    ShowMerchant {
+        owner: Pubkey,
+        name: String,
+    },
+    /// Print the global state
+    ShowState,
+    /// Pay a merchant from the signer (for testing)
+    Pay {
+        owner: Pubkey,
+        name: String,
+        amount: u64,
+        /// SPL mint; pays SOL when omitted
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        /// Pays SOL through the SPL path as wrapped SOL
+        #[arg(long, conflicts_with = "mint")]
+        wrap_sol: bool,
+        /// Defaults to the current time in milliseconds
+        #[arg(long)]
+        payment_id: Option<u64>,
+        /// Order reference such as a UUID; pays with process_payment_v2,
+        /// keyed by its SHA-256
+        #[arg(long, conflicts_with = "payment_id")]
+        order: Option<String>,
+        #[arg(long)]
+        memo: Option<String>,
+        /// Fee-free extra for the merchant
+        #[arg(long)]
+        tip: Option<u64>,
+        /// Promo code created with the merchant's discount
+        #[arg(long)]
+        discount_code: Option<String>,
+        /// Unix time after which the payment is rejected
+        #[arg(long)]
+        valid_until: Option<i64>,
+    },
+}
+
+fn parse_fee_tier(row: &str) -> Result<FeeTier, String> {
+    let (volume, bps) = row.split_once(':').ok_or("expected VOLUME:BPS")?;
+    Ok(FeeTier {
+        volume_threshold: volume.parse().map_err(|e| format!("volume: {e}"))?,
+        fee_bps: bps.parse().map_err(|e| format!("bps: {e}"))?,
+    })
+}
+
+enum Error {
+    Rpc(Box<ClientError>),
+    Other(String),
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Error::Rpc(Box::new(err))
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Rpc(err) => match program_error(err) {
+                Some(code) => write!(f, "{} ({}): {code}", code.name(), u32::from(code)),
+                None => write!(f, "{err}"),
+            },
+            Error::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+struct Context {
+    rpc: RpcClient,
+    keypair_path: String,
+}
+
+impl Context {
+    fn signer(&self) -> Result<Keypair, Error> {
+        let path = match self.keypair_path.strip_prefix("~/") {
+            Some(rest) => format!("{}/{rest}", std::env::var("HOME").unwrap_or_default()),
+            None => self.keypair_path.clone(),
+        };
+        read_keypair_file(&path).map_err(|e| Error::Other(format!("reading keypair {path}: {e}")))
+    }
+
+    fn send(&self, signer: &Keypair, ix: Instruction) -> Result<(), Error> {
+        let signature = rpc::send(&self.rpc, &[ix], signer, &[])?;
+        println!("signature: {signature}");
+        Ok(())
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey, what: &str) -> Result<T, Error> {
+        let data = self
+            .rpc
+            .get_account_data(address)
+            .map_err(|_| Error::Other(format!("{what} {address} not found")))?;
+        T::try_deserialize(&mut data.as_slice()).map_err(|e| Error::Other(format!("decoding {what}: {e}")))
+    }
+
+    fn show_state(&self) -> Result<(), Error> {
+        let state: GlobalState = self.fetch(&pda::find_global_state_address().0, "global state")?;
+        display::global_state(&state);
+        Ok(())
+    }
+
+    fn show_merchant(&self, owner: &Pubkey, name: &str) -> Result<(), Error> {
+        let address = pda::find_merchant_address(owner, name).0;
+        let merchant: Merchant = self.fetch(&address, "merchant")?;
+        display::merchant(&address, &merchant);
+        Ok(())
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Error> {
+    let ctx = Context {
+        rpc: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        keypair_path: cli.keypair,
+    };
+    let global_state = pda::find_global_state_address().0;
+    let event_authority = pda::find_event_authority_address().0;
+
+    match cli.command {
+        Command::ShowState => ctx.show_state(),
+        Command::ShowMerchant { owner, name } => ctx.show_merchant(&owner, &name),
+        Command::Init { fee_bps, fee_wallet, max_fee_bps, admin, guardian } => {
+            let payer = ctx.signer()?;
+            let admin = admin.unwrap_or(payer.pubkey());
+            let fee_wallet = fee_wallet.unwrap_or(payer.pubkey());
+            ctx.send(&payer, instructions::initialize(&payer.pubkey(), admin, fee_bps, fee_wallet, max_fee_bps, guardian))?;
+            ctx.show_state()
+        }
+        Command::SetFee { fee_bps, apply } => {
+            let admin = ctx.signer()?;
+            let ix = match (fee_bps, apply) {
+                (_, true) => build(
+                    accounts::ApplyFee { global_state, caller: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::ApplyFee {},
+                ),
+                (Some(new_fee_bps), false) => build(
+                    accounts::AdminAuth { global_state, admin: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::ProposeFee { new_fee_bps },
+                ),
+                (None, false) => unreachable!("clap requires fee_bps without --apply"),
+            };
+            ctx.send(&admin, ix)?;
+            ctx.show_state()
+        }
+        Command::ReduceMaxFee { max_fee_bps } => {
+            let admin = ctx.signer()?;
+            ctx.send(
+                &admin,
+                build(
+                    accounts::AdminAuth { global_state, admin: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::ReduceMaxFee { new_max_fee_bps: max_fee_bps },
+                ),
+            )?;
+            ctx.show_state()
+        }
+        Command::SetFeeRounding { rounding } => {
+            let admin = ctx.signer()?;
+            let new_rounding = if rounding == "ceil" { FeeRounding::Ceil } else { FeeRounding::Floor };
+            ctx.send(
+                &admin,
+                build(
+                    accounts::AdminAuth { global_state, admin: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::SetFeeRounding { new_rounding },
+                ),
+            )?;
+            ctx.show_state()
+        }
+        Command::SetFeeWallet { fee_wallet } => {
+            let admin = ctx.signer()?;
+            ctx.send(
+                &admin,
+                build(
+                    accounts::AdminAuth { global_state, admin: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::SetFeeWallet { new_fee_wallet: fee_wallet },
+                ),
+            )?;
+            ctx.show_state()
+        }
+        Command::SetTokenFee { fee_bps, mint, clear, apply } => {
+            let admin = ctx.signer()?;
+            let mint = mint.unwrap_or_default();
+            let token_fee = pda::find_token_fee_address(&mint).0;
+            let ix = if clear {
+                build(
+                    accounts::ClearTokenFee { global_state, token_fee, admin: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::ClearTokenFee {},
+                )
+            } else if apply {
+                build(
+                    accounts::ApplyTokenFee { global_state, token_fee, caller: admin.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::ApplyTokenFee {},
+                )
+            } else {
+                build(
+                    accounts::SetTokenFee {
+                        global_state,
+                        token_fee,
+                        admin: admin.pubkey(),
+                        system_program: system_program::ID,
+                        admin_proposal: None,
+                        event_authority,
+                        program: orki_gateway::ID,
+                    },
+                    instruction::SetTokenFee { mint, fee_bps: fee_bps.unwrap_or_default() },
+                )
+            };
+            ctx.send(&admin, ix)?;
+            let config = if clear { None } else { Some(ctx.fetch(&token_fee, "token fee")?) };
+            display::token_fee(&mint, config.as_ref());
+            Ok(())
+        }
+        Command::SetFeeTiers { tiers, apply } => {
+            let admin = ctx.signer()?;
+            let fee_tiers = pda::find_fee_tiers_address().0;
+            let proposal = pda::find_fee_tiers_proposal_address().0;
+            if apply {
+                let pending: FeeTiersProposal = ctx.fetch(&proposal, "fee tiers proposal")?;
+                ctx.send(
+                    &admin,
+                    build(
+                        accounts::ApplyFeeTiers {
+                            global_state,
+                            fee_tiers,
+                            proposal,
+                            proposer: pending.proposer,
+                            caller: admin.pubkey(),
+                            event_authority,
+                            program: orki_gateway::ID,
+                        },
+                        instruction::ApplyFeeTiers {},
+                    ),
+                )?;
+                display::fee_tiers(&pending.tiers);
+            } else {
+                ctx.send(
+                    &admin,
+                    build(
+                        accounts::SetFeeTiers {
+                            global_state,
+                            fee_tiers,
+                            proposal,
+                            admin: admin.pubkey(),
+                            system_program: system_program::ID,
+                            admin_proposal: None,
+                            event_authority,
+                            program: orki_gateway::ID,
+                        },
+                        instruction::SetFeeTiers { tiers: tiers.clone() },
+                    ),
+                )?;
+                let pending: FeeTiersProposal = ctx.fetch(&proposal, "fee tiers proposal")?;
+                display::fee_tiers(&tiers);
+                println!("effective_at: {}", pending.effective_at);
+            }
+            Ok(())
+        }
+        Command::Pause | Command::Unpause => {
+            let authority = ctx.signer()?;
+            let paused = matches!(cli.command, Command::Pause);
+            ctx.send(
+                &authority,
+                build(
+                    accounts::SetPaused { global_state, authority: authority.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::SetPaused { paused },
+                ),
+            )?;
+            ctx.show_state()
+        }
+        Command::UpdateAdmin { new_admin, accept } => {
+            let signer = ctx.signer()?;
+            let ix = match (new_admin, accept) {
+                (_, true) => build(
+                    accounts::AcceptAdmin { global_state, new_admin: signer.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::AcceptAdmin {},
+                ),
+                (Some(new_admin), false) => build(
+                    accounts::AdminAuth { global_state, admin: signer.pubkey(), event_authority, program: orki_gateway::ID },
+                    instruction::ProposeAdmin { new_admin },
+                ),
+                (None, false) => unreachable!("clap requires new_admin without --accept"),
+            };
+            ctx.send(&signer, ix)?;
+            ctx.show_state()
+        }
+        Command::RegisterMerchant {
+            name,
+            settlement_wallet,
+            settlement_token,
+            sandbox,
+            any_token,
+            referrer,
+            referrer_bps,
+            metadata_uri,
+            category,
+        } => {
+            let owner = ctx.signer()?;
+            let ix = instructions::register_merchant(
+                &owner.pubkey(),
+                RegisterMerchantArgs {
+                    settlement_wallet,
+                    settlement_token: settlement_token.unwrap_or_default(),
+                    name: name.clone(),
+                    sandbox,
+                    referrer,
+                    referrer_bps,
+                    accepts_any_token: any_token,
+                    metadata_uri,
+                    category,
+                    merchant_index: rpc::next_merchant_index(&ctx.rpc)?,
+                },
+            );
+            ctx.send(&owner, ix)?;
+            ctx.show_merchant(&owner.pubkey(), &name)
+        }
+        Command::UpdateMerchant {
+            name,
+            settlement_wallet,
+            settlement_token,
+            swap_enabled,
+            escrow_enabled,
+            min_amount,
+            max_amount,
+            any_token,
+            metadata_uri,
+            category,
+        } => {
+            let owner = ctx.signer()?;
+            let merchant = pda::find_merchant_address(&owner.pubkey(), &name).0;
+            let ix = build(
+                accounts::UpdateMerchant {
+                    merchant,
+                    authority: owner.pubkey(),
+                    operator: None,
+                    // Only read when the settlement wallet changes
+                    payout_book: settlement_wallet.map(|_| pda::find_payout_book_address(&merchant).0),
+                    event_authority, program: orki_gateway::ID
+                },
+                instruction::UpdateMerchant {
+                    name: name.clone(),
+                    settlement_wallet,
+                    settlement_token,
+                    swap_enabled,
+                    escrow_enabled,
+                    min_amount,
+                    max_amount,
+                    accepts_any_token: any_token,
+                    metadata_uri,
+                    category,
+                },
+            );
+            ctx.send(&owner, ix)?;
+            ctx.show_merchant(&owner.pubkey(), &name)
+        }
+        Command::UpgradeMerchant { name } => {
+            let owner = ctx.signer()?;
+            ctx.send(&owner, instructions::upgrade_merchant_account(&owner.pubkey(), &name))?;
+            ctx.show_merchant(&owner.pubkey(), &name)
+        }
+        Command::UpgradePayment { payment } => {
+            let payer = ctx.signer()?;
+            ctx.send(&payer, instructions::upgrade_payment_account(&payer.pubkey(), &payment))?;
+            let record: Payment = ctx.fetch(&payment, "payment")?;
+            display::payment(&payment, &record);
+            Ok(())
+        }
+        Command::Pay { owner, name, amount, mint, wrap_sol, payment_id, order, memo, tip, discount_code, valid_until } => {
+            let payer = ctx.signer()?;
+            let merchant: Merchant = ctx.fetch(&pda::find_merchant_address(&owner, &name).0, "merchant")?;
+            let asset = match mint {
+                None if wrap_sol => PaymentAsset::WrappedSol,
+                None => PaymentAsset::Sol,
+                Some(mint) => {
+                    let token_program = ctx
+                        .rpc
+                        .get_account(&mint)
+                        .map_err(|_| Error::Other(format!("mint {mint} not found")))?
+                        .owner;
+                    PaymentAsset::Spl { mint, token_program }
+                }
+            };
+            let payment_id = payment_id.unwrap_or_else(|| {
+                SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+            });
+            let args = ProcessPaymentArgs {
+                merchant_owner: owner,
+                merchant_name: name,
+                settlement_wallet: merchant.settlement_wallet,
+                asset,
+                amount,
+                payment_id,
+                memo,
+                tip,
+                discount_code,
+                valid_until: valid_until.unwrap_or_default(),
+            };
+            let (ix, address) = match order {
+                Some(order) => (
+                    instructions::process_payment_v2(&payer.pubkey(), pda::order_id(&order), args),
+                    pda::find_order_reference_address(&payer.pubkey(), &order).0,
+                ),
+                None => (
+                    instructions::process_payment(&payer.pubkey(), args),
+                    pda::find_payment_address(&payer.pubkey(), payment_id).0,
+                ),
+            };
+            ctx.send(&payer, ix)?;
+            let payment: Payment = ctx.fetch(&address, "payment")?;
+            display::payment(&address, &payment);
+            Ok(())
+        }
+    }
+}
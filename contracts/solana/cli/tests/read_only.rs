@@ -0,0 +1,45 @@
+//! Read-only subcommands against a local validator with the gateway deployed
+//! and initialized (`anchor localnet` plus `orki-cli init`). Run with
+//! `cargo test -p orki-cli -- --ignored`; `ORKI_RPC_URL` overrides the default.
+
+use std::process::{Command, Output};
+
+fn orki_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_orki-cli")).args(args).output().expect("run orki-cli")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+#[ignore = "needs a local validator"]
+fn show_state_prints_global_state() {
+    let output = orki_cli(&["show-state"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    for field in ["admin: ", "paused: ", "fee_bps: ", "fee_wallet: "] {
+        assert!(out.contains(field), "missing {field:?} in {out}");
+    }
+}
+
+#[test]
+#[ignore = "needs a local validator"]
+fn show_merchant_reports_missing_merchant() {
+    let owner = "11111111111111111111111111111111";
+    let output = orki_cli(&["show-merchant", owner, "no-such-merchant"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("error: merchant "), "{}", stderr(&output));
+    assert!(stderr(&output).contains("not found"));
+}
+
+#[test]
+fn show_state_fails_readably_without_a_validator() {
+    let output = orki_cli(&["show-state", "--url", "http://127.0.0.1:1"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("error: global state "), "{}", stderr(&output));
+}
@@ -0,0 +1,29 @@
+//! Generates `error_code(number)`, the reverse of the program's `ErrorCode`
+//! numbering, from the variant order in its errors.rs.
+
+use std::{env, fs, path::Path};
+
+const ERRORS: &str = "../programs/orki_gateway/src/errors.rs";
+
+fn main() {
+    println!("cargo:rerun-if-changed={ERRORS}");
+    let source = fs::read_to_string(ERRORS).expect("program errors.rs");
+    let body = source
+        .split_once("pub enum ErrorCode {")
+        .and_then(|(_, rest)| rest.split_once('}'))
+        .expect("ErrorCode enum")
+        .0;
+
+    let variants = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("#[") && !line.starts_with("//") && !line.is_empty())
+        .map(|line| line.trim_end_matches(','));
+
+    let mut out = String::from("pub fn error_code(number: u32) -> Option<ErrorCode> {\n    match number {\n");
+    for (i, variant) in variants.enumerate() {
+        out += &format!("        {} => Some(ErrorCode::{variant}),\n", 6000 + i);
+    }
+    out += "        _ => None,\n    }\n}\n";
+    fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("error_codes.rs"), out).unwrap();
+}
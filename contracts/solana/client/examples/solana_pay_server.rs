@@ -0,0 +1,111 @@
+//! Serves Solana Pay transaction requests for one hardcoded merchant.
+//!
+//!     cargo run -p orki-gateway-client --example solana_pay_server
+//!
+//! Point a QR code at `solana:http://<host>:8080/pay?amount=<lamports>&reference=<pubkey>`
+//! (URL-encoded), then watch for the payment with `find_payment_by_reference`.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+
+use orki_gateway_client::instructions::PaymentAsset;
+use orki_gateway_client::solana_pay::{
+    transaction_response, MerchantInfo, PaymentRequest, TransactionRequestBody,
+};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+const MERCHANT_OWNER: Pubkey = pubkey!("3rPVSaD844tUcJePsNjJS5q2eScXCtW3iC4aexgrm58S");
+const SETTLEMENT_WALLET: Pubkey = pubkey!("3WMVfEianfYHfmagjFNFFTVKdaU1v3GyfRrnj2jnxLUF");
+const MERCHANT_NAME: &str = "coffee-shop";
+const LABEL: &str = "Orki Coffee";
+const ICON: &str = "https://orki.example/icon.svg";
+
+fn main() -> std::io::Result<()> {
+    let url = std::env::var("RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let rpc = RpcClient::new(url);
+    let listener = TcpListener::bind("0.0.0.0:8080")?;
+    println!("listening on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let (status, body) = handle(&rpc, &mut stream).unwrap_or_else(|e| ("400 Bad Request", json_error(&e)));
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )?;
+    }
+    Ok(())
+}
+
+fn handle(rpc: &RpcClient, stream: &mut TcpStream) -> Result<(&'static str, String), String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| "bad content-length")?;
+            }
+        }
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    if path != "/pay" {
+        return Ok(("404 Not Found", json_error("not found")));
+    }
+
+    match method.as_str() {
+        "OPTIONS" => Ok(("204 No Content", String::new())),
+        "GET" => {
+            let info = MerchantInfo { label: LABEL.to_string(), icon: ICON.to_string() };
+            Ok(("200 OK", serde_json::to_string(&info).unwrap()))
+        }
+        "POST" => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+            let body: TransactionRequestBody = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+            let account = Pubkey::from_str(&body.account).map_err(|_| "bad account")?;
+
+            let request = PaymentRequest {
+                merchant_owner: MERCHANT_OWNER,
+                merchant_name: MERCHANT_NAME.to_string(),
+                settlement_wallet: SETTLEMENT_WALLET,
+                asset: PaymentAsset::Sol,
+                amount: query_param(query, "amount").ok_or("missing amount")?.parse().map_err(|_| "bad amount")?,
+                reference: query_param(query, "reference")
+                    .ok_or("missing reference")?
+                    .parse()
+                    .map_err(|_| "bad reference")?,
+                memo: None,
+            };
+            let response = transaction_response(rpc, &account, &request, Some(format!("Pay {LABEL}")))
+                .map_err(|e| e.to_string())?;
+            Ok(("200 OK", serde_json::to_string(&response).unwrap()))
+        }
+        _ => Ok(("405 Method Not Allowed", json_error("method not allowed"))),
+    }
+}
+
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&').filter_map(|pair| pair.split_once('=')).find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+fn json_error(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
@@ -0,0 +1,16 @@
+//! Program error numbers back to `ErrorCode`.
+
+use orki_gateway::errors::ErrorCode;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+
+include!(concat!(env!("OUT_DIR"), "/error_codes.rs"));
+
+/// The gateway error a failed send or simulation ended with, if it was one.
+pub fn program_error(err: &ClientError) -> Option<ErrorCode> {
+    match err.get_transaction_error()? {
+        TransactionError::InstructionError(_, InstructionError::Custom(number)) => error_code(number),
+        _ => None,
+    }
+}
@@ -0,0 +1,182 @@
+//! Typed decoding of gateway events, from program logs (`emit!`, the
+//! `log-events` build) or from event-CPI inner instructions (`emit_cpi!`).
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::Engine;
+use orki_gateway::events::*;
+use solana_sdk::pubkey::Pubkey;
+
+macro_rules! gateway_events {
+    ($($name:ident),* $(,)?) => {
+        /// Every event the gateway emits.
+        pub enum GatewayEvent {
+            $($name($name),)*
+        }
+
+        impl GatewayEvent {
+            /// Decodes `discriminator ++ borsh` event bytes. Unknown
+            /// discriminators and malformed data return None.
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                $(
+                    if let Some(mut body) = data.strip_prefix($name::DISCRIMINATOR) {
+                        return $name::deserialize(&mut body).ok().map(GatewayEvent::$name);
+                    }
+                )*
+                None
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(GatewayEvent::$name(_) => stringify!($name),)*
+                }
+            }
+        }
+    };
+}
+
+gateway_events!(
+    PaymentProcessed,
+    PaymentSwapped,
+    PaymentRefunded,
+    EscrowOpened,
+    SettlementClaimed,
+    DisputeOpened,
+    DisputeResolved,
+    SettlementModeUpdated,
+    SelfPaymentsUpdated,
+    TaxRateUpdated,
+    ConfirmationMessageUpdated,
+    PayerIntervalUpdated,
+    SettlementRouteUpdated,
+    MerchantBalanceOpened,
+    BalanceCredited,
+    BalanceWithdrawn,
+    PayoutIntervalUpdated,
+    PayoutCranked,
+    InvoiceCreated,
+    InvoicePaid,
+    InvoiceCancelled,
+    PaymentLinkCreated,
+    PaymentLinkPaid,
+    PaymentLinkDeactivated,
+    PayerBlocked,
+    PayerUnblocked,
+    DiscountCreated,
+    DiscountDeleted,
+    DiscountRedeemed,
+    MerchantStatusChanged,
+    SubscriptionCreated,
+    SubscriptionCharged,
+    SubscriptionCancelled,
+    StreamCreated,
+    StreamWithdrawn,
+    StreamCancelled,
+    DelegatedPaymentProcessed,
+    PaymentSplit,
+    SplitConfigUpdated,
+    RewardsHookUpdated,
+    AcceptedTokensUpdated,
+    PayoutAddressAdded,
+    PayoutAddressRemoved,
+    PaymentAuthorized,
+    PaymentCaptured,
+    PaymentVoided,
+    AuthorizationExpiryUpdated,
+    PaymentRetentionUpdated,
+    FinalitySlotsUpdated,
+    PaymentFinalized,
+    MaxFeeReduced,
+    FeeRoundingUpdated,
+    CrankRewardUpdated,
+    AdminCouncilCreated,
+    AdminActionProposed,
+    AdminActionApproved,
+    AdminActionExecuted,
+    PaymentClosed,
+    ReceiptMinted,
+    OperatorGranted,
+    OperatorRevoked,
+    MerchantRegistered,
+    MerchantUpdated,
+    MerchantOwnershipProposed,
+    MerchantOwnershipTransferred,
+    MerchantRenamed,
+    PreviousMerchantReleased,
+    MerchantClosed,
+    MerchantIndexed,
+    MerchantPing,
+    FeeProposed,
+    FeeUpdated,
+    FeeProposalCancelled,
+    FeeTimelockUpdated,
+    MerchantFeeExemptionUpdated,
+    MerchantReferrerUpdated,
+    MerchantFeeUpdated,
+    MerchantDailyLimitUpdated,
+    MerchantFrozen,
+    MerchantUnfrozen,
+    FeeWalletUpdated,
+    FeeStatsOpened,
+    FeesWithdrawn,
+    EscrowReleaseDelayUpdated,
+    GuardianUpdated,
+    ArbiterUpdated,
+    PausedStatusUpdated,
+    AdminUpdated,
+    AdminTransferProposed,
+    AdminTransferCancelled,
+    GlobalStateMigrated,
+    MerchantMigrated,
+    MerchantAccountUpgraded,
+    PaymentAccountUpgraded,
+    FeatureToggled,
+    GlobalStateInitialized,
+);
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// Decodes the `Program data:` lines the gateway itself logged. Invocation
+/// depth is tracked so data logged by other programs in the same
+/// transaction is skipped.
+pub fn decode_logs<S: AsRef<str>>(logs: &[S]) -> Vec<GatewayEvent> {
+    let gateway = orki_gateway::ID.to_string();
+    let mut stack: Vec<bool> = Vec::new();
+    let mut events = Vec::new();
+    for line in logs {
+        let line = line.as_ref();
+        if let Some(rest) = line.strip_prefix("Program ") {
+            if rest.contains(" invoke [") {
+                stack.push(rest.starts_with(&gateway));
+                continue;
+            }
+            if rest.ends_with(" success") || rest.contains(" failed: ") {
+                stack.pop();
+                continue;
+            }
+        }
+        if stack.last() != Some(&true) {
+            continue;
+        }
+        if let Some(encoded) = line.strip_prefix(PROGRAM_DATA) {
+            if let Some(event) = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .ok()
+                .and_then(|data| GatewayEvent::decode(&data))
+            {
+                events.push(event);
+            }
+        }
+    }
+    events
+}
+
+/// Decodes the data of an inner instruction the gateway sent to itself
+/// through `emit_cpi!`. `program_id` is the inner instruction's program;
+/// anything but the gateway returns None.
+pub fn decode_cpi_instruction(program_id: &Pubkey, data: &[u8]) -> Option<GatewayEvent> {
+    if *program_id != orki_gateway::ID {
+        return None;
+    }
+    GatewayEvent::decode(data.strip_prefix(EVENT_IX_TAG_LE)?)
+}
@@ -0,0 +1,650 @@
+//! Instruction builders. [`build`] covers every gateway instruction from its
+//! Anchor structs (`orki_gateway::accounts::*` and `orki_gateway::instruction::*`);
+//! the named builders below also derive the PDAs for the common flows.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use anchor_spl::token::spl_token;
+use orki_gateway::instructions::delegated_payment_message;
+use orki_gateway::state::{AdminAction, Discount, Merchant};
+use orki_gateway::{accounts, instruction};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_ed25519_program::new_ed25519_instruction_with_signature;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use anchor_lang::system_program;
+
+use crate::pda::*;
+
+/// Builds any gateway instruction from its typed accounts and arguments.
+pub fn build(accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: orki_gateway::ID,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// Like [`build`], with extra accounts appended (split recipients, rewards
+/// hook accounts, the test clock).
+pub fn build_with_remaining(
+    accounts: impl ToAccountMetas,
+    args: impl InstructionData,
+    remaining: Vec<AccountMeta>,
+) -> Instruction {
+    let mut ix = build(accounts, args);
+    ix.accounts.extend(remaining);
+    ix
+}
+
+/// Token a payment is made in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentAsset {
+    Sol,
+    Spl { mint: Pubkey, token_program: Pubkey },
+    /// SOL paid through the SPL path as wrapped SOL. The payer's wSOL ATA is
+    /// funded from its lamports, and closed again if the payment opened it.
+    WrappedSol,
+}
+
+impl PaymentAsset {
+    /// Key the program records for this asset; SOL is the default key.
+    pub fn mint(&self) -> Pubkey {
+        match self {
+            PaymentAsset::Sol => Pubkey::default(),
+            PaymentAsset::Spl { mint, .. } => *mint,
+            PaymentAsset::WrappedSol => spl_token::native_mint::ID,
+        }
+    }
+
+    /// Mint and token program of an asset paid through the SPL path.
+    fn spl(&self) -> Option<(Pubkey, Pubkey)> {
+        match *self {
+            PaymentAsset::Sol => None,
+            PaymentAsset::Spl { mint, token_program } => Some((mint, token_program)),
+            PaymentAsset::WrappedSol => Some((spl_token::native_mint::ID, spl_token::ID)),
+        }
+    }
+}
+
+/// `max_fee_bps` caps every fee rate from then on; it can later be lowered
+/// with reduce_max_fee but never raised. Pass `MAX_FEE_BPS` for no cap below 100%.
+/// `payer` funds the accounts; `admin` (often the same key) governs the gateway.
+pub fn initialize(
+    payer: &Pubkey,
+    admin: Pubkey,
+    fee_bps: u16,
+    fee_wallet: Pubkey,
+    max_fee_bps: u16,
+    guardian: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::Initialize {
+            global_state: find_global_state_address().0,
+            global_stats: find_global_stats_address().0,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        instruction::Initialize { fee_bps, fee_wallet, max_fee_bps, admin, guardian },
+    )
+}
+
+pub struct RegisterMerchantArgs {
+    pub settlement_wallet: Pubkey,
+    pub settlement_token: Pubkey,
+    pub name: String,
+    pub sandbox: bool,
+    pub referrer: Option<Pubkey>,
+    pub referrer_bps: u16,
+    pub accepts_any_token: bool,
+    pub metadata_uri: String,
+    pub category: u8,
+    /// Directory slot the merchant takes: the registry's `merchant_count`
+    /// plus one, as [`crate::rpc::next_merchant_index`] reads it
+    pub merchant_index: u64,
+}
+
+pub fn register_merchant(owner: &Pubkey, args: RegisterMerchantArgs) -> Instruction {
+    let merchant = find_merchant_address(owner, &args.name).0;
+    build(
+        accounts::RegisterMerchant {
+            merchant,
+            merchant_stats: find_merchant_stats_address(&merchant).0,
+            global_stats: find_global_stats_address().0,
+            merchant_registry: find_merchant_registry_address().0,
+            merchant_index: find_merchant_index_address(args.merchant_index).0,
+            merchant_name: find_merchant_name_address(owner, &args.name).0,
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::RegisterMerchant {
+            settlement_wallet: args.settlement_wallet,
+            settlement_token: args.settlement_token,
+            name: args.name,
+            sandbox: args.sandbox,
+            referrer: args.referrer,
+            referrer_bps: args.referrer_bps,
+            accepts_any_token: args.accepts_any_token,
+            metadata_uri: args.metadata_uri,
+            category: args.category,
+        },
+    )
+}
+
+/// Grows a merchant registered by an older program version to the current
+/// layout; `owner` signs and pays the extra rent.
+pub fn upgrade_merchant_account(owner: &Pubkey, name: &str) -> Instruction {
+    build(
+        accounts::UpgradeMerchantAccount {
+            merchant: find_merchant_address(owner, name).0,
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::UpgradeMerchantAccount { name: name.to_string() },
+    )
+}
+
+/// Grows a payment recorded by an older program version to the current layout;
+/// any `payer` may sign and cover the extra rent.
+pub fn upgrade_payment_account(payer: &Pubkey, payment: &Pubkey) -> Instruction {
+    build(
+        accounts::UpgradePaymentAccount {
+            payment: *payment,
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::UpgradePaymentAccount {},
+    )
+}
+
+/// Marks `payer`'s payment Finalized once it is `finality_slots` deep. Anyone
+/// may send it; escrowed payments also need their merchant's escrow balance
+/// for the payment's mint.
+pub fn finalize_payment(payer: &Pubkey, payment_id: u64, escrow_balance: Option<Pubkey>) -> Instruction {
+    build(
+        accounts::FinalizePayment {
+            global_state: find_global_state_address().0,
+            payer: *payer,
+            payment: find_payment_address(payer, payment_id).0,
+            escrow_balance,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::FinalizePayment { payment_id },
+    )
+}
+
+/// [`finalize_payment`] for a process_payment_v2 payment.
+pub fn finalize_order_payment(payer: &Pubkey, order_id: [u8; 32], escrow_balance: Option<Pubkey>) -> Instruction {
+    build(
+        accounts::FinalizePayment {
+            global_state: find_global_state_address().0,
+            payer: *payer,
+            payment: find_order_payment_address(payer, &order_id).0,
+            escrow_balance,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::FinalizePayment { payment_id: 0 },
+    )
+}
+
+/// Creates the merchant's notification queue, which payments passing it
+/// append to; `owner` signs and pays the rent.
+pub fn init_notification_queue(owner: &Pubkey, name: &str) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::InitNotificationQueue {
+            merchant,
+            notification_queue: find_notification_queue_address(&merchant).0,
+            owner: *owner,
+            system_program: system_program::ID,
+        },
+        instruction::InitNotificationQueue { name: name.to_string() },
+    )
+}
+
+/// Empties the merchant's notification queue once its entries are handled.
+pub fn clear_notifications(owner: &Pubkey, name: &str) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::ClearNotifications {
+            merchant,
+            notification_queue: find_notification_queue_address(&merchant).0,
+            owner: *owner,
+        },
+        instruction::ClearNotifications { name: name.to_string() },
+    )
+}
+
+/// Attaches `rewards_program` as the merchant's rewards hook, or replaces
+/// the one attached; `owner` signs and pays the hook's rent.
+pub fn set_rewards_hook(owner: &Pubkey, name: &str, rewards_program: Pubkey, required: bool) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::SetRewardsHook {
+            merchant,
+            rewards_hook: find_rewards_hook_address(&merchant).0,
+            rewards_program,
+            owner: *owner,
+            system_program: system_program::ID,
+        },
+        instruction::SetRewardsHook { name: name.to_string(), required },
+    )
+}
+
+/// Detaches the merchant's rewards hook, refunding its rent to `owner`.
+pub fn clear_rewards_hook(owner: &Pubkey, name: &str) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::ClearRewardsHook {
+            merchant,
+            rewards_hook: find_rewards_hook_address(&merchant).0,
+            owner: *owner,
+        },
+        instruction::ClearRewardsHook { name: name.to_string() },
+    )
+}
+
+/// Settles the merchant's payments in `mint` (Pubkey::default() for SOL) to
+/// `destination`, an active entry in its payout address book; `owner` signs
+/// and pays the route's rent.
+pub fn set_settlement_route(owner: &Pubkey, name: &str, mint: Pubkey, destination: Pubkey) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::SetSettlementRoute {
+            merchant,
+            settlement_route: find_settlement_route_address(&merchant, &mint).0,
+            payout_book: Some(find_payout_book_address(&merchant).0),
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetSettlementRoute { name: name.to_string(), mint, destination },
+    )
+}
+
+/// Removes the merchant's route for `mint`, refunding its rent to `owner`.
+pub fn clear_settlement_route(owner: &Pubkey, name: &str, mint: Pubkey) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::ClearSettlementRoute {
+            merchant,
+            settlement_route: find_settlement_route_address(&merchant, &mint).0,
+            owner: *owner,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ClearSettlementRoute { name: name.to_string(), mint },
+    )
+}
+
+/// Proposes handing the merchant to `new_owner`; the current `owner` signs and
+/// pays the proposal's rent.
+pub fn transfer_merchant_ownership(owner: &Pubkey, name: &str, new_owner: Pubkey) -> Instruction {
+    let merchant = find_merchant_address(owner, name).0;
+    build(
+        accounts::TransferMerchantOwnership {
+            merchant,
+            merchant_transfer: find_merchant_transfer_address(&merchant).0,
+            owner: *owner,
+            system_program: system_program::ID,
+        },
+        instruction::TransferMerchantOwnership { name: name.to_string(), new_owner },
+    )
+}
+
+/// Accepts a proposed transfer of `merchant`, as fetched before the call;
+/// `new_owner` signs and pays the rent of the moved accounts. The directory
+/// slot, split config, accepted-token list and name claim are passed when
+/// the merchant uses them.
+pub fn accept_merchant_ownership(new_owner: &Pubkey, merchant: &Merchant) -> Instruction {
+    let old = find_merchant_address(&merchant.owner, &merchant.name).0;
+    let new = find_merchant_address(new_owner, &merchant.name).0;
+    let split = merchant.split_enabled;
+    let accepted = merchant.accepted_tokens_enabled;
+    build(
+        accounts::AcceptMerchantOwnership {
+            merchant: old,
+            merchant_transfer: find_merchant_transfer_address(&old).0,
+            new_merchant: new,
+            merchant_stats: find_merchant_stats_address(&old).0,
+            new_merchant_stats: find_merchant_stats_address(&new).0,
+            merchant_index: (merchant.merchant_index != 0)
+                .then(|| find_merchant_index_address(merchant.merchant_index).0),
+            split_config: split.then(|| find_split_config_address(&old).0),
+            new_split_config: split.then(|| find_split_config_address(&new).0),
+            accepted_tokens: accepted.then(|| find_accepted_tokens_address(&old).0),
+            new_accepted_tokens: accepted.then(|| find_accepted_tokens_address(&new).0),
+            merchant_name: merchant.name_indexed.then(|| find_merchant_name_address(&merchant.owner, &merchant.name).0),
+            new_merchant_name: find_merchant_name_address(new_owner, &merchant.name).0,
+            owner: merchant.owner,
+            new_owner: *new_owner,
+            system_program: system_program::ID,
+        },
+        instruction::AcceptMerchantOwnership { name: merchant.name.clone() },
+    )
+}
+
+/// A payment to a plain merchant: no escrow, split, accepted-token list,
+/// referrer, recent-payments cache, notification queue, daily limit,
+/// rewards hook, per-payer interval or settlement routes. Use [`build`] for
+/// those.
+pub struct ProcessPaymentArgs {
+    pub merchant_owner: Pubkey,
+    pub merchant_name: String,
+    pub settlement_wallet: Pubkey,
+    pub asset: PaymentAsset,
+    pub amount: u64,
+    pub payment_id: u64,
+    pub memo: Option<String>,
+    /// Fee-free extra for the merchant on top of `amount`
+    pub tip: Option<u64>,
+    /// Promo code to redeem; its Discount account is derived from the hash
+    pub discount_code: Option<String>,
+    /// Unix time after which the payment is rejected, 0 for no deadline
+    pub valid_until: i64,
+}
+
+pub fn process_payment(payer: &Pubkey, args: ProcessPaymentArgs) -> Instruction {
+    let payment_history = find_payment_address(payer, args.payment_id).0;
+    build(
+        payment_accounts(payer, &args, payment_history),
+        instruction::ProcessPayment {
+            amount: args.amount,
+            payment_id: args.payment_id,
+            name: args.merchant_name,
+            memo: args.memo,
+            tip: args.tip,
+            discount_code: args.discount_code,
+            valid_until: args.valid_until,
+        },
+    )
+}
+
+/// The same payment keyed by a 32-byte order id, such as
+/// [`order_id`] of an order UUID; `args.payment_id` is not used.
+pub fn process_payment_v2(payer: &Pubkey, order_id: [u8; 32], args: ProcessPaymentArgs) -> Instruction {
+    let payment_history = find_order_payment_address(payer, &order_id).0;
+    build(
+        payment_accounts(payer, &args, payment_history),
+        instruction::ProcessPaymentV2 {
+            amount: args.amount,
+            order_id,
+            name: args.merchant_name,
+            memo: args.memo,
+            tip: args.tip,
+            discount_code: args.discount_code,
+            valid_until: args.valid_until,
+        },
+    )
+}
+
+/// Pays use `use_index` of the merchant's link `link_seed`, which must be
+/// the link's current `uses`. `args.asset` must be the link's mint; the
+/// link sets the amount, so `amount`, `payment_id`, `memo`, `tip`,
+/// `discount_code` and `valid_until` are not used.
+pub fn pay_link(payer: &Pubkey, link_seed: u64, use_index: u64, args: ProcessPaymentArgs) -> Instruction {
+    let merchant = find_merchant_address(&args.merchant_owner, &args.merchant_name).0;
+    let link = find_link_address(&merchant, link_seed).0;
+    let payment_history = find_link_payment_address(&link, payer, use_index).0;
+    let args = ProcessPaymentArgs { memo: None, discount_code: None, ..args };
+    let mut accounts = payment_accounts(payer, &args, payment_history);
+    accounts.payment_link = Some(link);
+    build(accounts, instruction::PayLink { name: args.merchant_name })
+}
+
+fn payment_accounts(payer: &Pubkey, args: &ProcessPaymentArgs, payment_history: Pubkey) -> accounts::ProcessPayment {
+    let merchant = find_merchant_address(&args.merchant_owner, &args.merchant_name).0;
+    let fee_vault = find_fee_vault_address().0;
+    let spl = args.asset.spl();
+    let ata = |owner: &Pubkey| {
+        spl.map(|(mint, token_program)| get_associated_token_address_with_program_id(owner, &mint, &token_program))
+    };
+    let memo_program = args.memo.as_ref().map(|_| anchor_spl::memo::ID);
+    let wrapped = args.asset == PaymentAsset::WrappedSol;
+
+    accounts::ProcessPayment {
+        global_state: find_global_state_address().0,
+        global_stats: find_global_stats_address().0,
+        merchant,
+        merchant_stats: find_merchant_stats_address(&merchant).0,
+        payer: *payer,
+        merchant_wallet: args.settlement_wallet,
+        fee_vault,
+        fee_stats: find_fee_stats_address(&args.asset.mint()).0,
+        system_program: system_program::ID,
+        payment_history,
+        blocklist_entry: find_blocked_address(payer).0,
+        token_fee: find_token_fee_address(&args.asset.mint()).0,
+        fee_tiers: find_fee_tiers_address().0,
+        token_program: spl.map(|(_, token_program)| token_program),
+        mint: spl.map(|(mint, _)| mint),
+        payer_token_account: ata(payer).filter(|_| !wrapped),
+        merchant_token_account: ata(&args.settlement_wallet),
+        fee_token_account: ata(&fee_vault),
+        associated_token_program: spl.map(|_| associated_token::ID),
+        feature_flags: None,
+        vault: None,
+        escrow_balance: None,
+        merchant_balance: None,
+        vault_token_account: None,
+        recent_payments: None,
+        split_config: None,
+        accepted_tokens: None,
+        memo_program,
+        referrer_wallet: None,
+        referrer_token_account: None,
+        discount: args
+            .discount_code
+            .as_ref()
+            .map(|code| find_discount_address(&merchant, &Discount::hash_code(code)).0),
+        notification_queue: None,
+        daily_volume: None,
+        rewards_hook: None,
+        rewards_program: None,
+        payer_wsol_account: ata(payer).filter(|_| wrapped),
+        payer_throttle: None,
+        settlement_route: None,
+        payment_link: None,
+        event_authority: find_event_authority_address().0,
+        program: orki_gateway::ID,
+    }
+}
+
+/// A relayed SPL payment to a plain merchant. The payer signs
+/// `orki_gateway::instructions::delegated_payment_message` off-chain and has
+/// approved [`find_payment_delegate_address`] on the ATA paid from; the
+/// relayer signs the transaction.
+pub struct DelegatedPaymentArgs {
+    pub merchant_owner: Pubkey,
+    pub merchant_name: String,
+    pub settlement_wallet: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub amount: u64,
+    pub payment_id: u64,
+    /// Unix time after which the authorization is rejected
+    pub expires_at: i64,
+}
+
+/// The ed25519 verification of the payer's `signature` followed by the
+/// payment; send both, in this order, in one transaction.
+pub fn process_payment_delegated(
+    relayer: &Pubkey,
+    payer: &Pubkey,
+    signature: &Signature,
+    args: DelegatedPaymentArgs,
+) -> [Instruction; 2] {
+    let merchant = find_merchant_address(&args.merchant_owner, &args.merchant_name).0;
+    let message = delegated_payment_message(&merchant, &args.mint, args.amount, args.payment_id, args.expires_at);
+    let verify = new_ed25519_instruction_with_signature(&message, signature.as_array(), &payer.to_bytes());
+    let ata = |owner: &Pubkey| get_associated_token_address_with_program_id(owner, &args.mint, &args.token_program);
+
+    let pay = build(
+        accounts::ProcessPaymentDelegated {
+            global_state: find_global_state_address().0,
+            global_stats: find_global_stats_address().0,
+            merchant,
+            merchant_stats: find_merchant_stats_address(&merchant).0,
+            payer: *payer,
+            relayer: *relayer,
+            payment_history: find_payment_address(payer, args.payment_id).0,
+            blocklist_entry: find_blocked_address(payer).0,
+            fee_vault: find_fee_vault_address().0,
+            fee_stats: find_fee_stats_address(&args.mint).0,
+            token_fee: find_token_fee_address(&args.mint).0,
+            fee_tiers: find_fee_tiers_address().0,
+            delegate: find_payment_delegate_address().0,
+            token_program: args.token_program,
+            mint: args.mint,
+            payer_token_account: ata(payer),
+            merchant_token_account: ata(&args.settlement_wallet),
+            fee_token_account: ata(&find_fee_vault_address().0),
+            instructions: solana_sdk::sysvar::instructions::ID,
+            system_program: system_program::ID,
+            feature_flags: None,
+            accepted_tokens: None,
+            referrer_token_account: None,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPaymentDelegated {
+            amount: args.amount,
+            payment_id: args.payment_id,
+            name: args.merchant_name,
+            expires_at: args.expires_at,
+        },
+    );
+    [verify, pay]
+}
+
+/// Read-only; simulate it and decode the return data as `PaymentQuote`.
+/// Pass the payment token's TokenFeeConfig and the FeeTiers addresses only
+/// if those accounts exist.
+pub fn quote_payment(
+    merchant_owner: &Pubkey,
+    merchant_name: &str,
+    amount: u64,
+    token_fee: Option<Pubkey>,
+    fee_tiers: Option<Pubkey>,
+) -> Instruction {
+    let merchant = find_merchant_address(merchant_owner, merchant_name).0;
+    build(
+        accounts::QuotePayment {
+            global_state: find_global_state_address().0,
+            merchant,
+            token_fee,
+            merchant_stats: find_merchant_stats_address(&merchant).0,
+            fee_tiers,
+        },
+        instruction::QuotePayment {
+            amount,
+            name: merchant_name.to_string(),
+            owner: *merchant_owner,
+        },
+    )
+}
+
+/// Pays out a merchant's scheduled balance in `asset` once it is due. Anyone
+/// can send it and collects the crank reward, if any.
+pub fn crank_payout(
+    cranker: &Pubkey,
+    merchant_owner: &Pubkey,
+    merchant_name: &str,
+    settlement_wallet: Pubkey,
+    asset: PaymentAsset,
+) -> Instruction {
+    let merchant = find_merchant_address(merchant_owner, merchant_name).0;
+    let vault = find_vault_address(&merchant).0;
+    let spl = asset.spl();
+    let ata = |owner: &Pubkey| {
+        spl.map(|(mint, token_program)| get_associated_token_address_with_program_id(owner, &mint, &token_program))
+    };
+    build(
+        accounts::CrankPayout {
+            global_state: find_global_state_address().0,
+            merchant,
+            vault,
+            merchant_balance: find_merchant_balance_address(&merchant, &asset.mint()).0,
+            settlement_wallet,
+            fee_vault: find_fee_vault_address().0,
+            cranker: *cranker,
+            token_program: spl.map(|(_, token_program)| token_program),
+            mint: spl.map(|(mint, _)| mint),
+            vault_token_account: ata(&vault),
+            destination_token_account: ata(&settlement_wallet),
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::CrankPayout { name: merchant_name.to_string(), mint: asset.mint() },
+    )
+}
+
+/// Admin-only; moves fee, fee wallet and pause changes to a council of
+/// `members`, `threshold` of whom approve each one.
+pub fn create_admin_council(admin: &Pubkey, members: Vec<Pubkey>, threshold: u8) -> Instruction {
+    build(
+        accounts::CreateAdminCouncil {
+            global_state: find_global_state_address().0,
+            admin_council: find_admin_council_address().0,
+            admin: *admin,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::CreateAdminCouncil { members, threshold },
+    )
+}
+
+/// Opens council proposal `nonce`, which must be the council's
+/// `next_nonce`; the proposing member pays its rent.
+pub fn propose_admin_action(proposer: &Pubkey, nonce: u64, action: AdminAction) -> Instruction {
+    build(
+        accounts::ProposeAdminAction {
+            global_state: find_global_state_address().0,
+            admin_council: find_admin_council_address().0,
+            admin_proposal: find_admin_proposal_address(nonce).0,
+            proposer: *proposer,
+            system_program: system_program::ID,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProposeAdminAction { nonce, action },
+    )
+}
+
+pub fn approve_admin_action(member: &Pubkey, nonce: u64) -> Instruction {
+    build(
+        accounts::ApproveAdminAction {
+            global_state: find_global_state_address().0,
+            admin_council: find_admin_council_address().0,
+            admin_proposal: find_admin_proposal_address(nonce).0,
+            member: *member,
+            event_authority: find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ApproveAdminAction {},
+    )
+}
+
+/// Admin-only; SOL fees need their stats opened before the first payment.
+pub fn open_fee_stats(admin: &Pubkey, mint: Pubkey) -> Instruction {
+    build(
+        accounts::OpenFeeStats {
+            global_state: find_global_state_address().0,
+            fee_vault: find_fee_vault_address().0,
+            fee_stats: find_fee_stats_address(&mint).0,
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::OpenFeeStats { mint },
+    )
+}
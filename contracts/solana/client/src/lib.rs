@@ -0,0 +1,19 @@
+//! Rust client for the `orki_gateway` program.
+//!
+//! - [`pda`]: addresses of every gateway account.
+//! - [`common`]: the seeds and limits the program itself is built with.
+//! - [`instructions`]: instruction builders, generic and for the common flows.
+//! - [`errors`]: program error numbers back to `ErrorCode`.
+//! - [`events`]: decoders for events in transaction logs and `emit_cpi!` inner instructions.
+//! - [`rpc`]: sending transactions and reading their events over RPC.
+//! - [`solana_pay`]: Solana Pay transaction requests and reference lookups.
+
+pub mod errors;
+pub mod events;
+pub mod instructions;
+pub mod pda;
+pub mod rpc;
+pub mod solana_pay;
+
+pub use orki_gateway;
+pub use orki_gateway_common as common;
@@ -0,0 +1,17 @@
+//! PDA derivations, shared with the program's own `orki_gateway::pda`. Both
+//! take their seeds from [`crate::common`].
+
+pub use orki_gateway::pda::*;
+use orki_gateway::state::Payment;
+use solana_sdk::pubkey::Pubkey;
+
+/// The process_payment_v2 id for an order reference such as a UUID: its
+/// SHA-256, so the same reference always maps to the same record.
+pub fn order_id(order: &str) -> [u8; 32] {
+    Payment::hash_order_id(order)
+}
+
+/// The Payment record for `payer`'s payment of the order `order`.
+pub fn find_order_reference_address(payer: &Pubkey, order: &str) -> (Pubkey, u8) {
+    find_order_payment_address(payer, &order_id(order))
+}
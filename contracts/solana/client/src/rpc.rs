@@ -0,0 +1,197 @@
+//! Thin helpers over the blocking `RpcClient`. Errors are the RPC client's own.
+
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::{AccountDeserialize, AnchorDeserialize};
+use base64::Engine;
+use orki_gateway::instructions::{PaymentQuote, PaymentReceipt};
+use orki_gateway::state::{MerchantIndex, MerchantRegistry, Notification, NotificationQueue, Payment};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status_client_types::option_serializer::OptionSerializer;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInstruction, UiTransactionEncoding, UiTransactionReturnData,
+};
+
+use crate::events::{decode_cpi_instruction, decode_logs, GatewayEvent};
+use crate::instructions::quote_payment;
+use crate::pda::{
+    find_fee_tiers_address, find_merchant_index_address, find_merchant_registry_address, find_notification_queue_address,
+    find_token_fee_address,
+};
+
+/// Signs `ixs` with `payer` (as fee payer) plus `signers` and waits for confirmation.
+pub fn send(
+    rpc: &RpcClient,
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> Result<Signature, ClientError> {
+    let blockhash = rpc.get_latest_blockhash()?;
+    let mut all: Vec<&Keypair> = vec![payer];
+    all.extend(signers.iter().copied());
+    let tx = Transaction::new_signed_with_payer(ixs, Some(&payer.pubkey()), &all, blockhash);
+    rpc.send_and_confirm_transaction(&tx)
+}
+
+/// Gateway events emitted by a confirmed transaction, in order: `emit!` events
+/// from the logs first, then `emit_cpi!` events from the inner instructions.
+pub fn events_for_signature(rpc: &RpcClient, signature: &Signature) -> Result<Vec<GatewayEvent>, ClientError> {
+    let tx = rpc.get_transaction_with_config(signature, transaction_config())?;
+    Ok(events_from_transaction(&tx))
+}
+
+/// What [`events_for_signature`] fetches transactions with; [`events_from_transaction`]
+/// needs the Base64 encoding to recover the account keys.
+pub fn transaction_config() -> RpcTransactionConfig {
+    RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    }
+}
+
+/// [`events_for_signature`] for a transaction already fetched, e.g. through
+/// the nonblocking client.
+pub fn events_from_transaction(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<GatewayEvent> {
+    let Some(meta) = &tx.transaction.meta else {
+        return Vec::new();
+    };
+
+    let mut events = match &meta.log_messages {
+        OptionSerializer::Some(logs) => decode_logs(logs),
+        _ => Vec::new(),
+    };
+
+    let mut keys = tx
+        .transaction
+        .transaction
+        .decode()
+        .map(|tx| tx.message.static_account_keys().to_vec())
+        .unwrap_or_default();
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        keys.extend(loaded.writable.iter().chain(&loaded.readonly).filter_map(|k| k.parse::<Pubkey>().ok()));
+    }
+
+    if let OptionSerializer::Some(inner) = &meta.inner_instructions {
+        for ix in inner.iter().flat_map(|set| &set.instructions) {
+            let UiInstruction::Compiled(ix) = ix else { continue };
+            let Some(program_id) = keys.get(ix.program_id_index as usize) else { continue };
+            let Ok(data) = bs58::decode(&ix.data).into_vec() else { continue };
+            events.extend(decode_cpi_instruction(program_id, &data));
+        }
+    }
+    events
+}
+
+/// Simulates `quote_payment` for a payment in `mint` (default for SOL) and
+/// decodes its return data.
+pub fn quote(
+    rpc: &RpcClient,
+    fee_payer: &Pubkey,
+    merchant_owner: &Pubkey,
+    merchant_name: &str,
+    mint: &Pubkey,
+    amount: u64,
+) -> Result<Option<PaymentQuote>, ClientError> {
+    // The program takes the fee config accounts only when they exist
+    let existing = |address: Pubkey| -> Result<Option<Pubkey>, ClientError> {
+        Ok(rpc
+            .get_account_with_commitment(&address, rpc.commitment())?
+            .value
+            .map(|_| address))
+    };
+    let token_fee = existing(find_token_fee_address(mint).0)?;
+    let fee_tiers = existing(find_fee_tiers_address().0)?;
+    let blockhash = rpc.get_latest_blockhash()?;
+    let ix = quote_payment(merchant_owner, merchant_name, amount, token_fee, fee_tiers);
+    let mut tx = Transaction::new_with_payer(&[ix], Some(fee_payer));
+    tx.message.recent_blockhash = blockhash;
+    let result = rpc.simulate_transaction(&tx)?.value;
+    Ok(decode_return_data(result.return_data))
+}
+
+/// Simulates a transaction ending in process_payment or process_payment_v2,
+/// such as one built with [`crate::instructions::process_payment`], and
+/// decodes the PaymentReceipt it returns, so wallets can preview the split
+/// and the merchant's confirmation message. Signatures are not checked.
+pub fn simulate_payment(
+    rpc: &RpcClient,
+    fee_payer: &Pubkey,
+    ixs: &[Instruction],
+) -> Result<Option<PaymentReceipt>, ClientError> {
+    let blockhash = rpc.get_latest_blockhash()?;
+    let mut tx = Transaction::new_with_payer(ixs, Some(fee_payer));
+    tx.message.recent_blockhash = blockhash;
+    let result = rpc.simulate_transaction(&tx)?.value;
+    Ok(decode_return_data(result.return_data))
+}
+
+/// A gateway instruction's return value from a simulation's return data.
+/// None when there is none, the gateway did not set it last, or it does not
+/// decode as `T`.
+pub fn decode_return_data<T: AnchorDeserialize>(return_data: Option<UiTransactionReturnData>) -> Option<T> {
+    return_data
+        .filter(|data| data.program_id == orki_gateway::ID.to_string())
+        .and_then(|data| base64::engine::general_purpose::STANDARD.decode(&data.data.0).ok())
+        .and_then(|bytes| T::deserialize(&mut bytes.as_slice()).ok())
+}
+
+/// Directory slot the next registration takes. The registry is created by the
+/// first registration, so a missing one means slot 1.
+pub fn next_merchant_index(rpc: &RpcClient) -> Result<u64, ClientError> {
+    let account = rpc
+        .get_account_with_commitment(&find_merchant_registry_address().0, rpc.commitment())?
+        .value;
+    let count = account
+        .and_then(|account| MerchantRegistry::try_deserialize(&mut account.data.as_slice()).ok())
+        .map_or(0, |registry| registry.merchant_count);
+    Ok(count + 1)
+}
+
+/// Up to `limit` directory slots from `start` (counting from 1), as
+/// `(index, merchant)` pairs in index order. Slots of closed merchants and
+/// slots past the end of the directory are left out.
+pub fn merchant_directory(rpc: &RpcClient, start: u64, limit: u64) -> Result<Vec<(u64, Pubkey)>, ClientError> {
+    let indices: Vec<u64> = (start.max(1)..start.max(1).saturating_add(limit)).collect();
+    let addresses: Vec<Pubkey> = indices.iter().map(|&i| find_merchant_index_address(i).0).collect();
+    let mut merchants = Vec::new();
+    // getMultipleAccounts takes at most 100 keys per call
+    for (chunk, keys) in indices.chunks(100).zip(addresses.chunks(100)) {
+        for (&index, account) in chunk.iter().zip(rpc.get_multiple_accounts(keys)?) {
+            let Some(account) = account else { continue };
+            let Ok(entry) = MerchantIndex::try_deserialize(&mut account.data.as_slice()) else { continue };
+            if !entry.is_tombstone() {
+                merchants.push((index, entry.merchant));
+            }
+        }
+    }
+    Ok(merchants)
+}
+
+/// The payment record at `address`, or None if there is none. Records older
+/// than the current layout need upgrade_payment_account before they decode.
+pub fn payment(rpc: &RpcClient, address: &Pubkey) -> Result<Option<Payment>, ClientError> {
+    let account = rpc.get_account_with_commitment(address, rpc.commitment())?.value;
+    Ok(account.and_then(|account| Payment::try_deserialize(&mut account.data.as_slice()).ok()))
+}
+
+/// The merchant's notification queue, or None if it never created one.
+pub fn notification_queue(rpc: &RpcClient, merchant: &Pubkey) -> Result<Option<NotificationQueue>, ClientError> {
+    let account = rpc
+        .get_account_with_commitment(&find_notification_queue_address(merchant).0, rpc.commitment())?
+        .value;
+    Ok(account.and_then(|account| NotificationQueue::try_deserialize(&mut account.data.as_slice()).ok()))
+}
+
+/// Notifications waiting in the merchant's queue, oldest first; empty if it
+/// has no queue. Clear them with `clear_notifications` once handled.
+pub fn notifications(rpc: &RpcClient, merchant: &Pubkey) -> Result<Vec<Notification>, ClientError> {
+    Ok(notification_queue(rpc, merchant)?.map_or_else(Vec::new, |queue| queue.notifications()))
+}
@@ -0,0 +1,120 @@
+//! Solana Pay transaction requests: a point-of-sale app's wallet POSTs its
+//! `account` and gets back an unsigned `process_payment` transaction carrying
+//! the request's reference key, which the merchant then polls for.
+
+#![allow(clippy::result_large_err)]
+
+use base64::Engine;
+use orki_gateway::events::PaymentProcessed;
+use serde::{Deserialize, Serialize};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as ClientError;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+use crate::events::GatewayEvent;
+use crate::instructions::{process_payment, PaymentAsset, ProcessPaymentArgs};
+use crate::rpc::events_for_signature;
+
+/// Response to the wallet's GET.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerchantInfo {
+    pub label: String,
+    pub icon: String,
+}
+
+/// Body of the wallet's POST.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransactionRequestBody {
+    pub account: String,
+}
+
+/// Response to the wallet's POST: a base64 serialized, unsigned transaction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransactionRequestResponse {
+    pub transaction: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// One checkout: who is paid, how much, and the reference the wallet's
+/// transaction is tagged with.
+#[derive(Clone, Debug)]
+pub struct PaymentRequest {
+    pub merchant_owner: Pubkey,
+    pub merchant_name: String,
+    pub settlement_wallet: Pubkey,
+    pub asset: PaymentAsset,
+    pub amount: u64,
+    pub reference: Pubkey,
+    pub memo: Option<String>,
+}
+
+/// Payment ids are per payer, so the reference (fresh per checkout) doubles as one.
+pub fn payment_id_for_reference(reference: &Pubkey) -> u64 {
+    let bytes = reference.to_bytes();
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+/// Builds the unsigned transaction for `account` to sign, with the reference
+/// attached read-only to the `process_payment` instruction.
+pub fn build_transaction(rpc: &RpcClient, account: &Pubkey, request: &PaymentRequest) -> Result<Transaction, ClientError> {
+    let mut ix = process_payment(
+        account,
+        ProcessPaymentArgs {
+            merchant_owner: request.merchant_owner,
+            merchant_name: request.merchant_name.clone(),
+            settlement_wallet: request.settlement_wallet,
+            asset: request.asset,
+            amount: request.amount,
+            payment_id: payment_id_for_reference(&request.reference),
+            memo: request.memo.clone(),
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    ix.accounts.push(AccountMeta::new_readonly(request.reference, false));
+
+    let mut tx = Transaction::new_with_payer(&[ix], Some(account));
+    tx.message.recent_blockhash = rpc.get_latest_blockhash()?;
+    Ok(tx)
+}
+
+/// Answers a POST: builds the transaction and encodes it the way wallets expect.
+pub fn transaction_response(
+    rpc: &RpcClient,
+    account: &Pubkey,
+    request: &PaymentRequest,
+    message: Option<String>,
+) -> Result<TransactionRequestResponse, ClientError> {
+    let tx = build_transaction(rpc, account, request)?;
+    let bytes = bincode::serialize(&tx).expect("transactions always serialize");
+    Ok(TransactionRequestResponse {
+        transaction: base64::engine::general_purpose::STANDARD.encode(bytes),
+        message,
+    })
+}
+
+/// Finds the oldest successful transaction tagged with `reference` and its
+/// `PaymentProcessed` event. `None` until the wallet's payment has confirmed.
+pub fn find_payment_by_reference(
+    rpc: &RpcClient,
+    reference: &Pubkey,
+) -> Result<Option<(Signature, PaymentProcessed)>, ClientError> {
+    let statuses = rpc.get_signatures_for_address(reference)?;
+    // Newest first; the first payment wins if a reference was reused
+    for status in statuses.iter().rev().filter(|s| s.err.is_none()) {
+        let Ok(signature) = status.signature.parse::<Signature>() else { continue };
+        let processed = events_for_signature(rpc, &signature)?.into_iter().find_map(|e| match e {
+            GatewayEvent::PaymentProcessed(ev) => Some(ev),
+            _ => None,
+        });
+        if let Some(ev) = processed {
+            return Ok(Some((signature, ev)));
+        }
+    }
+    Ok(None)
+}
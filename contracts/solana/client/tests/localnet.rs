@@ -0,0 +1,171 @@
+//! End-to-end tests against a local validator with the program deployed
+//! (`anchor localnet`). Run with `cargo test -p orki-gateway-client -- --ignored`.
+//! `RPC_URL` and `ANCHOR_WALLET` override the localnet defaults.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use orki_gateway_client::events::GatewayEvent;
+use orki_gateway_client::orki_gateway::constants::MAX_FEE_BPS;
+use orki_gateway_client::instructions::{self, PaymentAsset, ProcessPaymentArgs, RegisterMerchantArgs};
+use orki_gateway_client::pda;
+use orki_gateway_client::rpc;
+use orki_gateway_client::solana_pay::{self, PaymentRequest};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+
+fn connect() -> (RpcClient, Keypair) {
+    let url = std::env::var("RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let wallet = std::env::var("ANCHOR_WALLET")
+        .unwrap_or_else(|_| format!("{}/.config/solana/id.json", std::env::var("HOME").unwrap()));
+    let admin = read_keypair_file(&wallet).expect("wallet keypair");
+    (RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()), admin)
+}
+
+// Initializes the gateway and opens SOL fee stats unless an earlier run did
+fn ensure_gateway(rpc: &RpcClient, admin: &Keypair) {
+    if rpc.get_account(&pda::find_global_state_address().0).is_err() {
+        rpc::send(rpc, &[instructions::initialize(&admin.pubkey(), admin.pubkey(), 100, admin.pubkey(), MAX_FEE_BPS, None)], admin, &[]).unwrap();
+    }
+    if rpc.get_account(&pda::find_fee_stats_address(&Pubkey::default()).0).is_err() {
+        rpc::send(rpc, &[instructions::open_fee_stats(&admin.pubkey(), Pubkey::default())], admin, &[]).unwrap();
+    }
+}
+
+fn register_sol_merchant(rpc: &RpcClient, owner: &Keypair, settlement_wallet: Pubkey, name: &str) {
+    let register = instructions::register_merchant(
+        &owner.pubkey(),
+        RegisterMerchantArgs {
+            settlement_wallet,
+            settlement_token: Pubkey::default(),
+            name: name.to_string(),
+            sandbox: false,
+            referrer: None,
+            referrer_bps: 0,
+            accepts_any_token: true,
+            metadata_uri: String::new(),
+            category: 0,
+            merchant_index: rpc::next_merchant_index(rpc).unwrap(),
+        },
+    );
+    rpc::send(rpc, &[register], owner, &[]).unwrap();
+}
+
+fn unique_name(prefix: &str) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    format!("{prefix}-{}", nanos % 1_000_000_000_000)
+}
+
+#[test]
+#[ignore = "needs a local validator"]
+fn registers_pays_and_decodes_events() {
+    let (rpc, payer) = connect();
+    ensure_gateway(&rpc, &payer);
+
+    let owner = &payer;
+    let settlement_wallet = Keypair::new().pubkey();
+    let name = unique_name("rs-client");
+    let register = instructions::register_merchant(
+        &owner.pubkey(),
+        RegisterMerchantArgs {
+            settlement_wallet,
+            settlement_token: Pubkey::default(),
+            name: name.clone(),
+            sandbox: false,
+            referrer: None,
+            referrer_bps: 0,
+            accepts_any_token: true,
+            metadata_uri: String::new(),
+            category: 0,
+            merchant_index: rpc::next_merchant_index(&rpc).unwrap(),
+        },
+    );
+    let signature = rpc::send(&rpc, &[register], owner, &[]).unwrap();
+    let events = rpc::events_for_signature(&rpc, &signature).unwrap();
+    let merchant = pda::find_merchant_address(&owner.pubkey(), &name).0;
+    assert!(events.iter().any(|e| matches!(
+        e,
+        GatewayEvent::MerchantRegistered(ev) if ev.merchant == merchant && ev.name == name
+    )));
+
+    let amount = 100_000_000;
+    let quote = rpc::quote(&rpc, &payer.pubkey(), &owner.pubkey(), &name, &Pubkey::default(), amount).unwrap().expect("quote");
+    assert_eq!(quote.fee + quote.merchant_amount, amount);
+
+    let payment_id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let pay = instructions::process_payment(
+        &payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: owner.pubkey(),
+            merchant_name: name.clone(),
+            settlement_wallet,
+            asset: PaymentAsset::Sol,
+            amount,
+            payment_id,
+            memo: Some("order-42".to_string()),
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    let receipt = rpc::simulate_payment(&rpc, &payer.pubkey(), std::slice::from_ref(&pay)).unwrap().expect("receipt");
+    assert_eq!(receipt.payment_account, pda::find_payment_address(&payer.pubkey(), payment_id).0);
+    assert_eq!((receipt.fee, receipt.merchant_amount), (quote.fee, quote.merchant_amount));
+    assert_eq!(receipt.message, "");
+    let signature = rpc::send(&rpc, &[pay], &payer, &[]).unwrap();
+
+    let events = rpc::events_for_signature(&rpc, &signature).unwrap();
+    let processed = events
+        .iter()
+        .find_map(|e| match e {
+            GatewayEvent::PaymentProcessed(ev) => Some(ev),
+            _ => None,
+        })
+        .expect("PaymentProcessed");
+    assert_eq!(processed.payer, payer.pubkey());
+    assert_eq!(processed.merchant, merchant);
+    assert_eq!(processed.amount, amount);
+    assert_eq!(processed.fee, quote.fee);
+    assert_eq!(processed.token, Pubkey::default());
+    assert_eq!(processed.payment_id, payment_id);
+    assert_eq!(processed.memo, "order-42");
+    assert_eq!(processed.merchant_name, name);
+    assert_eq!(processed.payment_account, pda::find_payment_address(&payer.pubkey(), payment_id).0);
+    assert_eq!(rpc.get_balance(&settlement_wallet).unwrap(), quote.merchant_amount);
+}
+
+#[test]
+#[ignore = "needs a local validator"]
+fn solana_pay_request_is_found_by_reference() {
+    let (rpc, payer) = connect();
+    ensure_gateway(&rpc, &payer);
+
+    let settlement_wallet = Keypair::new().pubkey();
+    let name = unique_name("rs-pay");
+    register_sol_merchant(&rpc, &payer, settlement_wallet, &name);
+
+    let reference = Keypair::new().pubkey();
+    let request = PaymentRequest {
+        merchant_owner: payer.pubkey(),
+        merchant_name: name,
+        settlement_wallet,
+        asset: PaymentAsset::Sol,
+        amount: 50_000_000,
+        reference,
+        memo: None,
+    };
+    assert!(solana_pay::find_payment_by_reference(&rpc, &reference).unwrap().is_none());
+
+    // What the wallet does with the transaction-request response
+    let mut tx = solana_pay::build_transaction(&rpc, &payer.pubkey(), &request).unwrap();
+    let blockhash = tx.message.recent_blockhash;
+    tx.sign(&[&payer], blockhash);
+    let signature = rpc.send_and_confirm_transaction(&tx).unwrap();
+
+    let (found, processed) = solana_pay::find_payment_by_reference(&rpc, &reference).unwrap().expect("payment");
+    assert_eq!(found, signature);
+    assert_eq!(processed.payer, payer.pubkey());
+    assert_eq!(processed.amount, 50_000_000);
+    assert_eq!(processed.payment_id, solana_pay::payment_id_for_reference(&reference));
+}
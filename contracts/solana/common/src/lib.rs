@@ -0,0 +1,63 @@
+//! Seeds and limits shared by the orki_gateway program and its clients, so
+//! neither hardcodes its own copy. The program re-declares each with
+//! `#[constant]`, which puts them in the IDL for clients in other languages.
+
+/// Denominator of every basis-point rate: fees, referrer and split shares,
+/// discounts and tax.
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Longest merchant name, in bytes; it is also a merchant PDA seed.
+pub const MAX_NAME_LENGTH: u32 = 32;
+
+/// Longest payment or invoice memo, in bytes.
+pub const MAX_MEMO_LENGTH: u32 = 64;
+
+/// Longest merchant confirmation message, in bytes.
+pub const MAX_CONFIRMATION_MESSAGE_LENGTH: u32 = 64;
+
+/// Longest merchant metadata URI, in bytes.
+pub const MAX_METADATA_URI_LENGTH: u32 = 128;
+
+// PDA seeds, each the first seed of its account's address. Changing one
+// moves every address derived from it.
+pub const GLOBAL_STATE_SEED: &[u8] = b"global_state";
+pub const GLOBAL_STATS_SEED: &[u8] = b"global_stats";
+pub const FEATURE_FLAGS_SEED: &[u8] = b"feature_flags";
+pub const FEE_VAULT_SEED: &[u8] = b"fee_vault";
+pub const FEE_STATS_SEED: &[u8] = b"fee_stats";
+pub const TOKEN_FEE_SEED: &[u8] = b"token_fee";
+pub const FEE_TIERS_SEED: &[u8] = b"fee_tiers";
+pub const FEE_TIERS_PROPOSAL_SEED: &[u8] = b"fee_tiers_proposal";
+pub const ADMIN_COUNCIL_SEED: &[u8] = b"admin_council";
+pub const ADMIN_PROPOSAL_SEED: &[u8] = b"admin_proposal";
+pub const MERCHANT_SEED: &[u8] = b"merchant";
+pub const MERCHANT_NAME_SEED: &[u8] = b"merchant_name";
+pub const MERCHANT_REGISTRY_SEED: &[u8] = b"merchant_registry";
+pub const MERCHANT_INDEX_SEED: &[u8] = b"merchant_index";
+pub const MERCHANT_TRANSFER_SEED: &[u8] = b"merchant_transfer";
+pub const MERCHANT_STATS_SEED: &[u8] = b"stats";
+pub const MERCHANT_BALANCE_SEED: &[u8] = b"merchant_balance";
+pub const ESCROW_BALANCE_SEED: &[u8] = b"escrow_balance";
+pub const VAULT_SEED: &[u8] = b"vault";
+pub const OPERATOR_SEED: &[u8] = b"operator";
+pub const PAYOUT_BOOK_SEED: &[u8] = b"payout_book";
+pub const SETTLEMENT_ROUTE_SEED: &[u8] = b"settlement_route";
+pub const ACCEPTED_TOKENS_SEED: &[u8] = b"accepted";
+pub const SPLIT_CONFIG_SEED: &[u8] = b"split";
+pub const RECENT_PAYMENTS_SEED: &[u8] = b"recent_payments";
+pub const NOTIFICATION_QUEUE_SEED: &[u8] = b"notification_queue";
+pub const DAILY_VOLUME_SEED: &[u8] = b"daily_volume";
+pub const PAYER_THROTTLE_SEED: &[u8] = b"throttle";
+pub const REWARDS_HOOK_SEED: &[u8] = b"rewards_hook";
+pub const DISCOUNT_SEED: &[u8] = b"discount";
+pub const PAYMENT_SEED: &[u8] = b"payment";
+pub const BLOCKED_SEED: &[u8] = b"blocked";
+pub const INVOICE_SEED: &[u8] = b"invoice";
+pub const PAYMENT_LINK_SEED: &[u8] = b"link";
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+pub const SUBSCRIPTION_DELEGATE_SEED: &[u8] = b"subscription_delegate";
+pub const PAYMENT_DELEGATE_SEED: &[u8] = b"payment_delegate";
+pub const STREAM_SEED: &[u8] = b"stream";
+pub const RECEIPT_MINT_SEED: &[u8] = b"receipt";
+pub const RECEIPT_AUTHORITY_SEED: &[u8] = b"receipt_authority";
+pub const TEST_CLOCK_SEED: &[u8] = b"test_clock";
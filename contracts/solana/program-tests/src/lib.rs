@@ -0,0 +1,241 @@
+//! LiteSVM harness for orki_gateway. [`Harness::new`] loads the program built
+//! by `anchor build` into a fresh in-process SVM with the SPL programs, so
+//! each test starts from an empty ledger. Instructions come from the client
+//! crate; the helpers here cover funding, SPL setup and reading state back.
+
+use anchor_lang::{AccountDeserialize, Space};
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use litesvm::types::TransactionMetadata;
+use litesvm::LiteSVM;
+use orki_gateway::constants::MAX_FEE_BPS;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{MerchantRegistry, Payment};
+use orki_gateway_client::events::{decode_cpi_instruction, decode_logs, GatewayEvent};
+use orki_gateway_client::instructions::RegisterMerchantArgs;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::sysvar::clock::Clock;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+pub use orki_gateway_client::{instructions, pda};
+
+/// Where `anchor build` puts the program binary.
+pub const PROGRAM_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/deploy/orki_gateway.so");
+
+/// Fee LiteSVM charges per signature, as on mainnet.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+pub struct Harness {
+    pub svm: LiteSVM,
+    pub admin: Keypair,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(orki_gateway::ID, PROGRAM_PATH)
+            .unwrap_or_else(|e| panic!("loading {PROGRAM_PATH} (run `anchor build` first): {e}"));
+        let admin = Keypair::new();
+        svm.airdrop(&admin.pubkey(), 100 * LAMPORTS_PER_SOL).unwrap();
+        Self { svm, admin }
+    }
+
+    /// A new keypair holding `lamports`.
+    pub fn funded(&mut self, lamports: u64) -> Keypair {
+        let keypair = Keypair::new();
+        self.svm.airdrop(&keypair.pubkey(), lamports).unwrap();
+        keypair
+    }
+
+    /// Signs `ixs` with `payer` (as fee payer) plus `signers` and executes them.
+    /// The blockhash is rotated first so an identical resend is not deduplicated.
+    pub fn send(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<TransactionMetadata, TransactionError> {
+        let tx = self.sign(ixs, payer, signers);
+        self.svm.send_transaction(tx).map_err(|failed| failed.err)
+    }
+
+    /// Like `send`, returning the gateway events the transaction emitted:
+    /// logged ones first, then self-CPI ones, each in emission order.
+    pub fn send_for_events(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<Vec<GatewayEvent>, TransactionError> {
+        let tx = self.sign(ixs, payer, signers);
+        let keys = tx.message.account_keys.clone();
+        let meta = self.svm.send_transaction(tx).map_err(|failed| failed.err)?;
+
+        let mut events = decode_logs(&meta.logs);
+        for inner in meta.inner_instructions.iter().flatten() {
+            let ix = &inner.instruction;
+            if let Some(event) = decode_cpi_instruction(&keys[ix.program_id_index as usize], &ix.data) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    fn sign(&mut self, ixs: &[Instruction], payer: &Keypair, signers: &[&Keypair]) -> Transaction {
+        self.svm.expire_blockhash();
+        let mut all: Vec<&Keypair> = vec![payer];
+        all.extend(signers.iter().copied());
+        Transaction::new_signed_with_payer(ixs, Some(&payer.pubkey()), &all, self.svm.latest_blockhash())
+    }
+
+    /// Sends as the admin, who also pays.
+    pub fn send_as_admin(&mut self, ixs: &[Instruction]) -> Result<TransactionMetadata, TransactionError> {
+        let admin = self.admin.insecure_clone();
+        self.send(ixs, &admin, &[])
+    }
+
+    pub fn balance(&self, address: &Pubkey) -> u64 {
+        self.svm.get_balance(address).unwrap_or(0)
+    }
+
+    /// Decodes an Anchor account, panicking if it is missing.
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        let account = self.svm.get_account(address).unwrap_or_else(|| panic!("no account at {address}"));
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    /// Rent for a payment record, which the payer funds.
+    pub fn payment_rent(&self) -> u64 {
+        self.svm.minimum_balance_for_rent_exemption(8 + Payment::INIT_SPACE)
+    }
+
+    /// Pins the Clock sysvar's unix timestamp.
+    pub fn set_time(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self.svm.get_sysvar();
+        clock.unix_timestamp = unix_timestamp;
+        self.svm.set_sysvar(&clock);
+    }
+
+    // --- Gateway setup ---
+
+    /// Initializes the gateway with the admin as fee wallet and opens SOL fee stats.
+    pub fn initialize(&mut self, fee_bps: u16) {
+        let admin = self.admin.pubkey();
+        self.send_as_admin(&[
+            instructions::initialize(&admin, admin, fee_bps, admin, MAX_FEE_BPS, None),
+            instructions::open_fee_stats(&admin, Pubkey::default()),
+        ])
+        .unwrap();
+    }
+
+    /// Registers an any-token merchant and returns its address.
+    pub fn register_merchant(&mut self, owner: &Keypair, name: &str, settlement_wallet: Pubkey) -> Pubkey {
+        let ix = instructions::register_merchant(
+            &owner.pubkey(),
+            RegisterMerchantArgs {
+                settlement_wallet,
+                settlement_token: Pubkey::default(),
+                name: name.to_string(),
+                sandbox: false,
+                referrer: None,
+                referrer_bps: 0,
+                accepts_any_token: true,
+                metadata_uri: String::new(),
+                category: 0,
+                merchant_index: self.next_merchant_index(),
+            },
+        );
+        self.send(&[ix], owner, &[]).unwrap();
+        pda::find_merchant_address(&owner.pubkey(), name).0
+    }
+
+    /// Directory slot the next registration takes.
+    pub fn next_merchant_index(&self) -> u64 {
+        let registry = pda::find_merchant_registry_address().0;
+        match self.svm.get_account(&registry) {
+            Some(_) => self.account::<MerchantRegistry>(&registry).merchant_count + 1,
+            None => 1,
+        }
+    }
+
+    // --- SPL ---
+
+    /// Creates a legacy SPL mint with `authority` as mint and freeze authority.
+    pub fn create_mint(&mut self, authority: &Keypair, decimals: u8) -> Pubkey {
+        let mint = Keypair::new();
+        let lamports = self.svm.minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN);
+        #[allow(deprecated)]
+        let create = solana_sdk::system_instruction::create_account(
+            &authority.pubkey(),
+            &mint.pubkey(),
+            lamports,
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        );
+        let init = spl_token::instruction::initialize_mint2(
+            &spl_token::ID,
+            &mint.pubkey(),
+            &authority.pubkey(),
+            Some(&authority.pubkey()),
+            decimals,
+        )
+        .unwrap();
+        self.send(&[create, init], authority, &[&mint]).unwrap();
+        mint.pubkey()
+    }
+
+    /// Creates `owner`'s associated token account for `mint`; PDAs are fine owners.
+    pub fn create_ata(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let admin = self.admin.insecure_clone();
+        let create = spl_associated_token_account::instruction::create_associated_token_account(
+            &admin.pubkey(),
+            owner,
+            mint,
+            &spl_token::ID,
+        );
+        self.send(&[create], &admin, &[]).unwrap();
+        get_associated_token_address_with_program_id(owner, mint, &spl_token::ID)
+    }
+
+    pub fn mint_to(&mut self, mint: &Pubkey, authority: &Keypair, to: &Pubkey, amount: u64) {
+        let ix = spl_token::instruction::mint_to(&spl_token::ID, mint, to, &authority.pubkey(), &[], amount).unwrap();
+        self.send(&[ix], authority, &[]).unwrap();
+    }
+
+    /// Freezes `account` as the issuer would; `authority` is the mint's freeze authority.
+    pub fn freeze(&mut self, mint: &Pubkey, authority: &Keypair, account: &Pubkey) {
+        let ix = spl_token::instruction::freeze_account(&spl_token::ID, account, mint, &authority.pubkey(), &[]).unwrap();
+        self.send(&[ix], authority, &[]).unwrap();
+    }
+
+    pub fn thaw(&mut self, mint: &Pubkey, authority: &Keypair, account: &Pubkey) {
+        let ix = spl_token::instruction::thaw_account(&spl_token::ID, account, mint, &authority.pubkey(), &[]).unwrap();
+        self.send(&[ix], authority, &[]).unwrap();
+    }
+
+    pub fn token_balance(&self, token_account: &Pubkey) -> u64 {
+        let account = self.svm.get_account(token_account).unwrap_or_else(|| panic!("no token account at {token_account}"));
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+}
+
+/// The error a transaction fails with when its first instruction returns `code`.
+pub fn program_error(code: ErrorCode) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(code.into()))
+}
+
+/// SystemError::AccountAlreadyInUse, which Anchor's `init` surfaces for an
+/// account that already exists.
+pub fn already_in_use() -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(0))
+}
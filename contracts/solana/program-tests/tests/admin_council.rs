@@ -0,0 +1,203 @@
+//! Admin council: once created, fee, fee wallet, pause, fee withdrawal and
+//! admin transfer changes need `threshold` member approvals on a proposal
+//! instead of the admin's key.
+
+use orki_gateway::constants::ADMIN_PROPOSAL_LIFETIME;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{AdminAction, AdminCouncil, AdminProposal, GlobalState};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{self, build};
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const START: i64 = 1_700_000_000;
+
+struct Setup {
+    h: Harness,
+    members: Vec<Keypair>,
+}
+
+// Three members, any two of whom pass a proposal
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    let members: Vec<Keypair> = (0..3).map(|_| h.funded(LAMPORTS_PER_SOL)).collect();
+    let admin = h.admin.pubkey();
+    let keys = members.iter().map(|m| m.pubkey()).collect();
+    h.send_as_admin(&[instructions::create_admin_council(&admin, keys, 2)]).unwrap();
+    Setup { h, members }
+}
+
+fn propose(s: &mut Setup, member: usize, nonce: u64, action: AdminAction) -> Result<(), TransactionError> {
+    let member = s.members[member].insecure_clone();
+    let ix = instructions::propose_admin_action(&member.pubkey(), nonce, action);
+    s.h.send(&[ix], &member, &[]).map(|_| ())
+}
+
+fn approve(s: &mut Setup, member: usize, nonce: u64) -> Result<(), TransactionError> {
+    let member = s.members[member].insecure_clone();
+    let ix = instructions::approve_admin_action(&member.pubkey(), nonce);
+    s.h.send(&[ix], &member, &[]).map(|_| ())
+}
+
+fn state(s: &Setup) -> GlobalState {
+    s.h.account(&pda::find_global_state_address().0)
+}
+
+fn withdraw_sol(signer: &Pubkey, amount: u64, destination: Pubkey, admin_proposal: Option<u64>) -> Instruction {
+    build(
+        accounts::WithdrawFees {
+            global_state: pda::find_global_state_address().0,
+            admin: *signer,
+            admin_proposal: admin_proposal.map(|nonce| pda::find_admin_proposal_address(nonce).0),
+            fee_vault: pda::find_fee_vault_address().0,
+            destination,
+            token_program: None,
+            mint: None,
+            fee_vault_token_account: None,
+            destination_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::WithdrawFees { amount, mint: Pubkey::default() },
+    )
+}
+
+#[test]
+fn executes_only_once_the_threshold_is_met() {
+    let mut s = setup();
+    let wallet = Pubkey::new_unique();
+    propose(&mut s, 0, 0, AdminAction::SetFeeWallet { new_fee_wallet: wallet }).unwrap();
+    assert_ne!(state(&s).fee_wallet, wallet);
+    let proposal: AdminProposal = s.h.account(&pda::find_admin_proposal_address(0).0);
+    assert_eq!((proposal.approval_count(), proposal.executed), (1, false));
+
+    approve(&mut s, 2, 0).unwrap();
+    assert_eq!(state(&s).fee_wallet, wallet);
+    assert!(s.h.account::<AdminProposal>(&pda::find_admin_proposal_address(0).0).executed);
+    assert_eq!(approve(&mut s, 1, 0).unwrap_err(), program_error(ErrorCode::ProposalAlreadyExecuted));
+}
+
+#[test]
+fn a_member_cannot_approve_twice() {
+    let mut s = setup();
+    propose(&mut s, 0, 0, AdminAction::SetPaused { paused: true }).unwrap();
+    // Proposing already counted as member 0's approval
+    assert_eq!(approve(&mut s, 0, 0).unwrap_err(), program_error(ErrorCode::AlreadyApproved));
+    assert!(!state(&s).paused);
+
+    let outsider = s.h.funded(LAMPORTS_PER_SOL);
+    let ix = instructions::approve_admin_action(&outsider.pubkey(), 0);
+    let err = s.h.send(&[ix], &outsider, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NotCouncilMember));
+}
+
+#[test]
+fn expired_proposals_cannot_pass() {
+    let mut s = setup();
+    propose(&mut s, 0, 0, AdminAction::SetPaused { paused: true }).unwrap();
+    s.h.set_time(START + ADMIN_PROPOSAL_LIFETIME);
+    assert_eq!(approve(&mut s, 1, 0).unwrap_err(), program_error(ErrorCode::ProposalExpired));
+    assert!(!state(&s).paused);
+}
+
+#[test]
+fn nonces_are_used_in_order_and_once() {
+    let mut s = setup();
+    let action = AdminAction::SetPaused { paused: true };
+    let err = propose(&mut s, 0, 1, action.clone()).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidProposalNonce));
+    propose(&mut s, 0, 0, action.clone()).unwrap();
+    let err = propose(&mut s, 1, 0, action.clone()).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidProposalNonce));
+    propose(&mut s, 1, 1, action).unwrap();
+    let council: AdminCouncil = s.h.account(&pda::find_admin_council_address().0);
+    assert_eq!(council.next_nonce, 2);
+}
+
+#[test]
+fn a_council_update_voids_open_proposals() {
+    let mut s = setup();
+    propose(&mut s, 0, 0, AdminAction::SetPaused { paused: true }).unwrap();
+    let members = vec![s.members[0].pubkey(), s.members[1].pubkey()];
+    propose(&mut s, 1, 1, AdminAction::UpdateCouncil { members: members.clone(), threshold: 2 }).unwrap();
+    approve(&mut s, 2, 1).unwrap();
+
+    let council: AdminCouncil = s.h.account(&pda::find_admin_council_address().0);
+    assert_eq!(council.members, members);
+    assert_eq!(approve(&mut s, 1, 0).unwrap_err(), program_error(ErrorCode::ProposalStale));
+}
+
+#[test]
+fn the_admin_loses_the_governed_powers() {
+    let mut s = setup();
+    assert!(state(&s).admin_council);
+    let admin = s.h.admin.pubkey();
+    let admin_auth = || accounts::AdminAuth {
+        global_state: pda::find_global_state_address().0,
+        admin,
+        event_authority: pda::find_event_authority_address().0,
+        program: orki_gateway::ID,
+    };
+    let ix = build(admin_auth(), instruction::SetFeeWallet { new_fee_wallet: Pubkey::new_unique() });
+    assert_eq!(s.h.send_as_admin(&[ix]).unwrap_err(), program_error(ErrorCode::CouncilGoverned));
+    let ix = build(admin_auth(), instruction::ProposeAdmin { new_admin: Pubkey::new_unique() });
+    assert_eq!(s.h.send_as_admin(&[ix]).unwrap_err(), program_error(ErrorCode::CouncilGoverned));
+    let ix = withdraw_sol(&admin, 1, admin, None);
+    assert_eq!(s.h.send_as_admin(&[ix]).unwrap_err(), program_error(ErrorCode::CouncilGoverned));
+
+    // A fee change still waits out the timelock once the council passes it
+    propose(&mut s, 0, 0, AdminAction::SetFee { new_fee_bps: 50 }).unwrap();
+    approve(&mut s, 1, 0).unwrap();
+    assert_eq!((state(&s).fee_bps, state(&s).pending_fee_bps), (100, Some(50)));
+}
+
+#[test]
+fn the_council_transfers_the_admin_role() {
+    let mut s = setup();
+    let new_admin = Pubkey::new_unique();
+    propose(&mut s, 0, 0, AdminAction::ProposeAdmin { new_admin }).unwrap();
+    assert_eq!(state(&s).pending_admin, None);
+    approve(&mut s, 1, 0).unwrap();
+    assert_eq!(state(&s).pending_admin, Some(new_admin));
+}
+
+#[test]
+fn an_approved_withdrawal_runs_exactly_once() {
+    let mut s = setup();
+    let vault = pda::find_fee_vault_address().0;
+    s.h.svm.airdrop(&vault, LAMPORTS_PER_SOL).unwrap();
+    let destination = Pubkey::new_unique();
+    let amount = LAMPORTS_PER_SOL / 2;
+    let member = s.members[2].insecure_clone();
+    propose(&mut s, 0, 0, AdminAction::WithdrawFees { mint: Pubkey::default(), amount, destination }).unwrap();
+
+    // Not yet approved by the threshold
+    let ix = withdraw_sol(&member.pubkey(), amount, destination, Some(0));
+    let err = s.h.send(&[ix], &member, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::ProposalNotApproved));
+
+    approve(&mut s, 1, 0).unwrap();
+    // The proposal fixes the amount and destination
+    let ix = withdraw_sol(&member.pubkey(), amount + 1, destination, Some(0));
+    let err = s.h.send(&[ix], &member, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::ProposalActionMismatch));
+    let ix = withdraw_sol(&member.pubkey(), amount, member.pubkey(), Some(0));
+    let err = s.h.send(&[ix], &member, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::ProposalActionMismatch));
+
+    let ix = withdraw_sol(&member.pubkey(), amount, destination, Some(0));
+    s.h.send(&[ix], &member, &[]).unwrap();
+    assert_eq!(s.h.balance(&destination), amount);
+    assert!(s.h.account::<AdminProposal>(&pda::find_admin_proposal_address(0).0).consumed);
+
+    let ix = withdraw_sol(&member.pubkey(), amount, destination, Some(0));
+    let err = s.h.send(&[ix], &member, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::ProposalNotApproved));
+    assert_eq!(s.h.balance(&destination), amount);
+}
@@ -0,0 +1,125 @@
+//! Payment receipts: process_payment returns a PaymentReceipt with the
+//! record's address, the split and the merchant's confirmation message, for
+//! wallets simulating the payment and programs calling it.
+
+use anchor_lang::AnchorDeserialize;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::instructions::PaymentReceipt;
+use orki_gateway::state::Merchant;
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+const MESSAGE: &str = "Thanks for shopping at Joe's!";
+const AMOUNT: u64 = 1_000_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL).pubkey();
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement);
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn set_message(s: &Setup, message: &str) -> Instruction {
+    build(
+        accounts::SetConfirmationMessage {
+            merchant: s.merchant,
+            owner: s.owner.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetConfirmationMessage { name: MERCHANT.to_string(), message: message.to_string() },
+    )
+}
+
+fn args(s: &Setup, payment_id: u64, tip: Option<u64>) -> ProcessPaymentArgs {
+    ProcessPaymentArgs {
+        merchant_owner: s.owner.pubkey(),
+        merchant_name: MERCHANT.to_string(),
+        settlement_wallet: s.settlement,
+        asset: PaymentAsset::Sol,
+        amount: AMOUNT,
+        payment_id,
+        memo: None,
+        tip,
+        discount_code: None,
+        valid_until: 0,
+    }
+}
+
+fn pay(s: &mut Setup, ix: Instruction) -> PaymentReceipt {
+    let payer = s.payer.insecure_clone();
+    let meta = s.h.send(&[ix], &payer, &[]).unwrap();
+    assert_eq!(meta.return_data.program_id, orki_gateway::ID);
+    PaymentReceipt::deserialize(&mut meta.return_data.data.as_slice()).unwrap()
+}
+
+#[test]
+fn returns_the_split_and_the_message() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    let ix = set_message(&s, MESSAGE);
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).confirmation_message, MESSAGE);
+
+    let ix = instructions::process_payment(&s.payer.pubkey(), args(&s, 1, Some(500)));
+    let receipt = pay(&mut s, ix);
+    let fee = AMOUNT / 100;
+    assert_eq!(
+        receipt,
+        PaymentReceipt {
+            payment_account: pda::find_payment_address(&s.payer.pubkey(), 1).0,
+            fee,
+            // The tip goes to the merchant untouched
+            merchant_amount: AMOUNT - fee + 500,
+            message: MESSAGE.to_string(),
+        }
+    );
+}
+
+#[test]
+fn returns_only_the_numbers_without_a_message() {
+    let mut s = setup();
+    let ix = instructions::process_payment(&s.payer.pubkey(), args(&s, 1, None));
+    let receipt = pay(&mut s, ix);
+    assert_eq!(receipt.message, "");
+    assert_eq!(receipt.fee + receipt.merchant_amount, AMOUNT);
+
+    // Order-keyed payments return their record too
+    let order_id = pda::order_id("order-7");
+    let ix = instructions::process_payment_v2(&s.payer.pubkey(), order_id, args(&s, 0, None));
+    let receipt = pay(&mut s, ix);
+    assert_eq!(receipt.payment_account, pda::find_order_payment_address(&s.payer.pubkey(), &order_id).0);
+}
+
+#[test]
+fn the_message_is_limited_and_can_be_cleared() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    let ix = set_message(&s, &"m".repeat(65));
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::ConfirmationMessageTooLong));
+
+    // 64 bytes of multi-byte characters fit
+    let ix = set_message(&s, &"é".repeat(32));
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    let ix = set_message(&s, "");
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).confirmation_message, "");
+}
@@ -0,0 +1,211 @@
+//! Daily volume limit: once the admin caps a merchant, process_payment counts
+//! what it receives per UTC day and rejects the payment that would cross the
+//! cap. The counter starts over on the next day.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{DailyVolume, Merchant, SECONDS_PER_DAY};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Newcomer";
+// Midday, so a few hours either way stays within the same UTC day
+const NOON: i64 = 19_675 * SECONDS_PER_DAY + SECONDS_PER_DAY / 2;
+const LIMIT: u64 = LAMPORTS_PER_SOL;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(NOON);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn set_daily_limit(s: &Setup, admin: &Pubkey, daily_limit: u64) -> Instruction {
+    build(
+        accounts::SetMerchantDailyLimit {
+            global_state: pda::find_global_state_address().0,
+            merchant: s.merchant,
+            daily_volume: pda::find_daily_volume_address(&s.merchant).0,
+            admin: *admin,
+            system_program: system_program::ID,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetMerchantDailyLimit { name: MERCHANT.to_string(), daily_limit },
+    )
+}
+
+fn pay(s: &Setup, payment_id: u64, amount: u64, daily_volume: bool) -> Instruction {
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer: s.payer.pubkey(),
+            merchant_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&s.payer.pubkey(), payment_id).0,
+            blocklist_entry: pda::find_blocked_address(&s.payer.pubkey()).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: None,
+            escrow_balance: None,
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: daily_volume.then(|| pda::find_daily_volume_address(&s.merchant).0),
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount,
+            payment_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn volume(s: &Setup) -> DailyVolume {
+    s.h.account(&pda::find_daily_volume_address(&s.merchant).0)
+}
+
+#[test]
+fn only_the_admin_sets_the_limit() {
+    let mut s = setup();
+    let stranger = s.h.funded(LAMPORTS_PER_SOL);
+    let err = s.h.send(&[set_daily_limit(&s, &stranger.pubkey(), LIMIT)], &stranger, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).daily_limit, 0);
+
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[set_daily_limit(&s, &admin, LIMIT)]).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).daily_limit, LIMIT);
+    assert_eq!(volume(&s).merchant, s.merchant);
+}
+
+#[test]
+fn unlimited_merchants_need_no_extra_account() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, 5 * LIMIT, false)], &payer, &[]).unwrap();
+
+    // Once capped the counter has to come along
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[set_daily_limit(&s, &admin, LIMIT)]).unwrap();
+    let err = s.h.send(&[pay(&s, 2, LIMIT / 2, false)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MissingAccount));
+    s.h.send(&[pay(&s, 2, LIMIT / 2, true)], &payer, &[]).unwrap();
+}
+
+#[test]
+fn rejects_the_payment_that_crosses_the_limit() {
+    let mut s = setup();
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[set_daily_limit(&s, &admin, LIMIT)]).unwrap();
+    let payer = s.payer.insecure_clone();
+
+    s.h.send(&[pay(&s, 1, LIMIT * 3 / 4, true)], &payer, &[]).unwrap();
+    let err = s.h.send(&[pay(&s, 2, LIMIT / 2, true)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::DailyLimitExceeded));
+
+    // Landing exactly on the limit is allowed
+    s.h.send(&[pay(&s, 3, LIMIT / 4, true)], &payer, &[]).unwrap();
+    let volume = volume(&s);
+    assert_eq!(volume.day_bucket, NOON / SECONDS_PER_DAY);
+    assert_eq!(volume.volume, LIMIT);
+}
+
+#[test]
+fn the_counter_resets_at_the_utc_day_boundary() {
+    let mut s = setup();
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[set_daily_limit(&s, &admin, LIMIT)]).unwrap();
+    let payer = s.payer.insecure_clone();
+    let tomorrow = NOON - NOON % SECONDS_PER_DAY + SECONDS_PER_DAY;
+
+    s.h.set_time(tomorrow - 1);
+    s.h.send(&[pay(&s, 1, LIMIT, true)], &payer, &[]).unwrap();
+    let err = s.h.send(&[pay(&s, 2, 1, true)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::DailyLimitExceeded));
+
+    // One second later it is a new day with a fresh allowance
+    s.h.set_time(tomorrow);
+    s.h.send(&[pay(&s, 2, LIMIT, true)], &payer, &[]).unwrap();
+    let volume = volume(&s);
+    assert_eq!(volume.day_bucket, tomorrow / SECONDS_PER_DAY);
+    assert_eq!(volume.volume, LIMIT);
+}
+
+#[test]
+fn capped_merchants_cannot_rename() {
+    let mut s = setup();
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[set_daily_limit(&s, &admin, LIMIT)]).unwrap();
+
+    // The new address would start the day with a fresh allowance
+    let owner = s.owner.pubkey();
+    let new_name = "Renamed";
+    let new_merchant = pda::find_merchant_address(&owner, new_name).0;
+    let index = s.h.account::<Merchant>(&s.merchant).merchant_index;
+    let ix = build(
+        accounts::RenameMerchant {
+            merchant: s.merchant,
+            new_merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            new_merchant_stats: pda::find_merchant_stats_address(&new_merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&owner, MERCHANT).0),
+            new_merchant_name: pda::find_merchant_name_address(&owner, new_name).0,
+            owner,
+            system_program: system_program::ID,
+        },
+        instruction::RenameMerchant { name: MERCHANT.to_string(), new_name: new_name.to_string() },
+    );
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::RenameBlockedByDailyLimit));
+}
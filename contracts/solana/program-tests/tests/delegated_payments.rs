@@ -0,0 +1,162 @@
+//! Gasless payments: the payer approves the payment delegate once and signs
+//! each authorization off-chain; a relayer submits it with the ed25519
+//! verification in front and pays for the transaction.
+
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::instructions::delegated_payment_message;
+use orki_gateway::state::{Payment, PaymentStatus};
+use orki_gateway_client::instructions::DelegatedPaymentArgs;
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness, LAMPORTS_PER_SIGNATURE};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const NOW: i64 = 1_700_000_000;
+const AMOUNT: u64 = 500_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    payer: Keypair,
+    relayer: Keypair,
+    merchant: Pubkey,
+    mint: Pubkey,
+    payer_ta: Pubkey,
+    settlement_ta: Pubkey,
+    fee_ta: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(NOW);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = Pubkey::new_unique();
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement);
+    let relayer = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(LAMPORTS_PER_SOL);
+
+    let issuer = h.funded(LAMPORTS_PER_SOL);
+    let mint = h.create_mint(&issuer, 6);
+    h.send_as_admin(&[instructions::open_fee_stats(&h.admin.pubkey(), mint)]).unwrap();
+    let payer_ta = h.create_ata(&payer.pubkey(), &mint);
+    let settlement_ta = h.create_ata(&settlement, &mint);
+    let fee_ta = h.create_ata(&pda::find_fee_vault_address().0, &mint);
+    h.mint_to(&mint, &issuer, &payer_ta, AMOUNT);
+
+    // The one on-chain step the payer takes
+    let approve = spl_token::instruction::approve(
+        &spl_token::ID,
+        &payer_ta,
+        &pda::find_payment_delegate_address().0,
+        &payer.pubkey(),
+        &[],
+        AMOUNT,
+    )
+    .unwrap();
+    h.send(&[approve], &payer, &[]).unwrap();
+
+    Setup { h, owner, settlement, payer, relayer, merchant, mint, payer_ta, settlement_ta, fee_ta }
+}
+
+fn args(s: &Setup, amount: u64) -> DelegatedPaymentArgs {
+    DelegatedPaymentArgs {
+        merchant_owner: s.owner.pubkey(),
+        merchant_name: MERCHANT.to_string(),
+        settlement_wallet: s.settlement,
+        mint: s.mint,
+        token_program: spl_token::ID,
+        amount,
+        payment_id: 1,
+        expires_at: NOW + 60,
+    }
+}
+
+/// What the payer hands the relayer: its signature over the authorization.
+fn authorize(s: &Setup, amount: u64) -> Signature {
+    s.payer
+        .sign_message(&delegated_payment_message(&s.merchant, &s.mint, amount, 1, NOW + 60))
+}
+
+fn relay(s: &Setup, signature: &Signature, amount: u64) -> [Instruction; 2] {
+    instructions::process_payment_delegated(&s.relayer.pubkey(), &s.payer.pubkey(), signature, args(s, amount))
+}
+
+fn payment_error(code: ErrorCode) -> TransactionError {
+    // The ed25519 verification is instruction 0
+    TransactionError::InstructionError(1, InstructionError::Custom(code.into()))
+}
+
+#[test]
+fn relayer_pays_and_funds_move_through_the_delegate() {
+    let mut s = setup();
+    let signature = authorize(&s, AMOUNT);
+    let payer_before = s.h.balance(&s.payer.pubkey());
+    let relayer_before = s.h.balance(&s.relayer.pubkey());
+
+    let relayer = s.relayer.insecure_clone();
+    s.h.send(&relay(&s, &signature, AMOUNT), &relayer, &[]).unwrap();
+
+    // 1% fee to the vault, the rest to the settlement wallet
+    assert_eq!(s.h.token_balance(&s.payer_ta), 0);
+    assert_eq!(s.h.token_balance(&s.settlement_ta), AMOUNT - AMOUNT / 100);
+    assert_eq!(s.h.token_balance(&s.fee_ta), AMOUNT / 100);
+
+    // The payer spends no SOL; the relayer covers the record and the fee,
+    // which counts the verified ed25519 signature alongside its own
+    assert_eq!(s.h.balance(&s.payer.pubkey()), payer_before);
+    assert_eq!(
+        relayer_before - s.h.balance(&relayer.pubkey()),
+        2 * LAMPORTS_PER_SIGNATURE + s.h.payment_rent()
+    );
+
+    let payment: Payment = s.h.account(&pda::find_payment_address(&s.payer.pubkey(), 1).0);
+    assert_eq!(payment.payer, s.payer.pubkey());
+    assert_eq!(payment.merchant, s.merchant);
+    assert_eq!(payment.amount, AMOUNT);
+    assert_eq!(payment.mint, s.mint);
+    assert_eq!(payment.status, PaymentStatus::Completed);
+}
+
+#[test]
+fn rejects_an_amount_the_payer_did_not_sign() {
+    let mut s = setup();
+    let signature = authorize(&s, AMOUNT / 2);
+    let relayer = s.relayer.insecure_clone();
+
+    // A valid signature over the signed amount, paired with a larger payment
+    let [verify, _] = relay(&s, &signature, AMOUNT / 2);
+    let [_, pay] = relay(&s, &signature, AMOUNT);
+    let err = s.h.send(&[verify, pay], &relayer, &[]).unwrap_err();
+    assert_eq!(err, payment_error(ErrorCode::InvalidPayerSignature));
+
+    // Without the verification in front the payment does not go through either
+    let [_, pay] = relay(&s, &signature, AMOUNT / 2);
+    let err = s.h.send(&[pay], &relayer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidPayerSignature));
+    assert_eq!(s.h.token_balance(&s.payer_ta), AMOUNT);
+}
+
+#[test]
+fn rejects_an_expired_or_replayed_authorization() {
+    let mut s = setup();
+    let signature = authorize(&s, AMOUNT / 2);
+    let relayer = s.relayer.insecure_clone();
+
+    s.h.set_time(NOW + 61);
+    let err = s.h.send(&relay(&s, &signature, AMOUNT / 2), &relayer, &[]).unwrap_err();
+    assert_eq!(err, payment_error(ErrorCode::PaymentExpired));
+
+    s.h.set_time(NOW + 60);
+    s.h.send(&relay(&s, &signature, AMOUNT / 2), &relayer, &[]).unwrap();
+
+    // The payment record keeps the same authorization from paying twice
+    let err = s.h.send(&relay(&s, &signature, AMOUNT / 2), &relayer, &[]).unwrap_err();
+    assert_eq!(err, payment_error(ErrorCode::DuplicatePayment));
+    assert_eq!(s.h.token_balance(&s.payer_ta), AMOUNT / 2);
+}
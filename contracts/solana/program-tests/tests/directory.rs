@@ -0,0 +1,155 @@
+//! The merchant directory: registrations take sequential slots, and clients
+//! walk them by index without scanning program accounts.
+
+use anchor_lang::system_program;
+use anchor_lang::{AccountSerialize, Space};
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, MerchantIndex, MerchantRegistry};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const NAMES: [&str; 3] = ["Alpha", "Bravo", "Charlie"];
+
+fn setup() -> (Harness, Keypair, Vec<Pubkey>) {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(10 * LAMPORTS_PER_SOL);
+    let settlement = Pubkey::new_unique();
+    let merchants = NAMES.iter().map(|name| h.register_merchant(&owner, name, settlement)).collect();
+    (h, owner, merchants)
+}
+
+// What a client paging through the directory sees: (index, merchant) for
+// every slot up to merchant_count, tombstones included
+fn walk(h: &Harness) -> Vec<(u64, Pubkey)> {
+    let registry: MerchantRegistry = h.account(&pda::find_merchant_registry_address().0);
+    (1..=registry.merchant_count)
+        .map(|index| {
+            let entry: MerchantIndex = h.account(&pda::find_merchant_index_address(index).0);
+            assert_eq!(entry.index, index);
+            (index, entry.merchant)
+        })
+        .collect()
+}
+
+fn entry(h: &Harness, merchant: &Pubkey) -> Option<Pubkey> {
+    let index = h.account::<Merchant>(merchant).merchant_index;
+    (index != 0).then(|| pda::find_merchant_index_address(index).0)
+}
+
+fn close(owner: &Pubkey, name: &str, merchant_index: Option<Pubkey>) -> Instruction {
+    let merchant = pda::find_merchant_address(owner, name).0;
+    build(
+        accounts::CloseMerchant {
+            merchant,
+            merchant_stats: pda::find_merchant_stats_address(&merchant).0,
+            merchant_index,
+            merchant_name: Some(pda::find_merchant_name_address(owner, name).0),
+            owner: *owner,
+        },
+        instruction::CloseMerchant { name: name.to_string() },
+    )
+}
+
+#[test]
+fn registrations_take_sequential_slots() {
+    let (h, _, merchants) = setup();
+
+    let expected: Vec<(u64, Pubkey)> = (1..).zip(merchants.iter().copied()).collect();
+    assert_eq!(walk(&h), expected);
+    for (index, merchant) in &expected {
+        assert_eq!(h.account::<Merchant>(merchant).merchant_index, *index);
+    }
+    assert_eq!(h.next_merchant_index(), 4);
+}
+
+#[test]
+fn closing_a_merchant_tombstones_its_slot() {
+    let (mut h, owner, merchants) = setup();
+    let slot = entry(&h, &merchants[1]);
+
+    // Leaving the slot out would leave it pointing at a closed account
+    let err = h.send(&[close(&owner.pubkey(), NAMES[1], None)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MissingAccount));
+
+    h.send(&[close(&owner.pubkey(), NAMES[1], slot)], &owner, &[]).unwrap();
+    assert!(h.svm.get_account(&merchants[1]).is_none_or(|a| a.lamports == 0));
+
+    let walked = walk(&h);
+    assert_eq!(walked, vec![(1, merchants[0]), (2, Pubkey::default()), (3, merchants[2])]);
+    let entry: MerchantIndex = h.account(&slot.unwrap());
+    assert!(entry.is_tombstone());
+
+    // The slot is not reused; the next registration appends
+    let merchant = h.register_merchant(&owner, "Delta", Pubkey::new_unique());
+    assert_eq!(walk(&h).last(), Some(&(4, merchant)));
+}
+
+#[test]
+fn renaming_repoints_the_slot() {
+    let (mut h, owner, merchants) = setup();
+    let new_merchant = pda::find_merchant_address(&owner.pubkey(), "Bravo2").0;
+    let rename = build(
+        accounts::RenameMerchant {
+            merchant: merchants[1],
+            new_merchant,
+            merchant_stats: pda::find_merchant_stats_address(&merchants[1]).0,
+            new_merchant_stats: pda::find_merchant_stats_address(&new_merchant).0,
+            merchant_index: entry(&h, &merchants[1]),
+            merchant_name: Some(pda::find_merchant_name_address(&owner.pubkey(), NAMES[1]).0),
+            new_merchant_name: pda::find_merchant_name_address(&owner.pubkey(), "Bravo2").0,
+            owner: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::RenameMerchant { name: NAMES[1].to_string(), new_name: "Bravo2".to_string() },
+    );
+    h.send(&[rename], &owner, &[]).unwrap();
+
+    assert_eq!(walk(&h), vec![(1, merchants[0]), (2, new_merchant), (3, merchants[2])]);
+    assert_eq!(h.account::<Merchant>(&new_merchant).merchant_index, 2);
+}
+
+#[test]
+fn index_merchant_lists_a_merchant_from_before_the_directory() {
+    let (mut h, _, merchants) = setup();
+
+    // Zero the index, as on a merchant registered before the directory
+    let mut legacy: Merchant = h.account(&merchants[2]);
+    legacy.merchant_index = 0;
+    let mut data = Vec::with_capacity(8 + Merchant::INIT_SPACE);
+    legacy.try_serialize(&mut data).unwrap();
+    let lamports = h.svm.minimum_balance_for_rent_exemption(data.len());
+    h.svm
+        .set_account(merchants[2], Account { lamports, data, owner: orki_gateway::ID, executable: false, rent_epoch: 0 })
+        .unwrap();
+
+    let index_merchant = |h: &Harness, payer: &Pubkey| {
+        build(
+            accounts::IndexMerchant {
+                merchant: merchants[2],
+                merchant_registry: pda::find_merchant_registry_address().0,
+                merchant_index: pda::find_merchant_index_address(h.next_merchant_index()).0,
+                payer: *payer,
+                system_program: system_program::ID,
+            },
+            instruction::IndexMerchant { name: NAMES[2].to_string() },
+        )
+    };
+
+    // Anyone may pay for the slot
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let ix = index_merchant(&h, &payer.pubkey());
+    h.send(&[ix], &payer, &[]).unwrap();
+    assert_eq!(h.account::<Merchant>(&merchants[2]).merchant_index, 4);
+    assert_eq!(walk(&h)[3], (4, merchants[2]));
+
+    let ix = index_merchant(&h, &payer.pubkey());
+    let err = h.send(&[ix], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MerchantAlreadyIndexed));
+}
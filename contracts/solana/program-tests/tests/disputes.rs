@@ -0,0 +1,217 @@
+//! Disputes over escrowed payments: the payer holds the escrowed share back
+//! with open_dispute and the arbiter refunds or releases it. Both find the
+//! payment from the record's own id, so order-keyed payments dispute too.
+
+use anchor_lang::system_program;
+use orki_gateway::constants::DEFAULT_FINALITY_SLOTS;
+use orki_gateway::state::{EscrowBalance, Payment, PaymentStatus};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{instructions, pda, Harness};
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+const ORDER: &str = "0b7e8d5c-3f0a-4c1e-9a57-6a8f2d4e1b90";
+const START: i64 = 1_700_000_000;
+const AMOUNT: u64 = LAMPORTS_PER_SOL;
+// What reaches the escrow at the 1% fee setup() charges
+const MERCHANT_AMOUNT: u64 = AMOUNT * 99 / 100;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    arbiter: Keypair,
+    merchant: Pubkey,
+}
+
+// A merchant escrowing SOL payments, with an arbiter set
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let arbiter = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    let mut s = Setup { h, owner, settlement, payer, arbiter, merchant };
+
+    let open = build(
+        accounts::OpenEscrow {
+            merchant,
+            vault: pda::find_vault_address(&merchant).0,
+            escrow_balance: escrow_balance(&s),
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::OpenEscrow { name: MERCHANT.to_string(), mint: Pubkey::default() },
+    );
+    let enable = build(
+        accounts::UpdateMerchant {
+            merchant,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payout_book: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::UpdateMerchant {
+            name: MERCHANT.to_string(),
+            settlement_wallet: None,
+            settlement_token: None,
+            swap_enabled: None,
+            escrow_enabled: Some(true),
+            min_amount: None,
+            max_amount: None,
+            accepts_any_token: None,
+            metadata_uri: None,
+            category: None,
+        },
+    );
+    let set_arbiter = build(
+        accounts::AdminAuth {
+            global_state: pda::find_global_state_address().0,
+            admin: s.h.admin.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetArbiter { new_arbiter: s.arbiter.pubkey() },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[open, enable], &owner, &[]).unwrap();
+    s.h.send_as_admin(&[set_arbiter]).unwrap();
+    s
+}
+
+fn escrow_balance(s: &Setup) -> Pubkey {
+    pda::find_escrow_balance_address(&s.merchant, &Pubkey::default()).0
+}
+
+// An escrowed SOL payment keyed by `order_id`
+fn pay_order(s: &Setup, order_id: [u8; 32]) -> Instruction {
+    let payer = s.payer.pubkey();
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer,
+            merchant_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_order_payment_address(&payer, &order_id).0,
+            blocklist_entry: pda::find_blocked_address(&payer).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: Some(pda::find_vault_address(&s.merchant).0),
+            escrow_balance: Some(escrow_balance(s)),
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPaymentV2 {
+            amount: AMOUNT,
+            order_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn open_dispute(s: &Setup, payment: Pubkey) -> Instruction {
+    build(
+        accounts::OpenDispute {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            payment,
+            escrow_balance: escrow_balance(s),
+            payer: s.payer.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::OpenDispute {},
+    )
+}
+
+fn resolve_dispute(s: &Setup, payment: Pubkey, refund: bool) -> Instruction {
+    build(
+        accounts::ResolveDispute {
+            global_state: pda::find_global_state_address().0,
+            arbiter: s.arbiter.pubkey(),
+            global_stats: pda::find_global_stats_address().0,
+            payment,
+            payer: s.payer.pubkey(),
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            vault: pda::find_vault_address(&s.merchant).0,
+            escrow_balance: escrow_balance(s),
+            token_program: None,
+            mint: None,
+            vault_token_account: None,
+            payer_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ResolveDispute { refund },
+    )
+}
+
+#[test]
+fn order_payments_are_disputed_and_refunded() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let order_id = pda::order_id(ORDER);
+    let payment = pda::find_order_payment_address(&payer.pubkey(), &order_id).0;
+    s.h.send(&[pay_order(&s, order_id)], &payer, &[]).unwrap();
+    let slot = s.h.svm.get_sysvar::<Clock>().slot;
+    s.h.svm.warp_to_slot(slot + DEFAULT_FINALITY_SLOTS);
+    let finalize = instructions::finalize_order_payment(&payer.pubkey(), order_id, Some(escrow_balance(&s)));
+    s.h.send(&[finalize], &payer, &[]).unwrap();
+
+    s.h.send(&[open_dispute(&s, payment)], &payer, &[]).unwrap();
+    assert_eq!(s.h.account::<Payment>(&payment).status, PaymentStatus::Disputed);
+    let balance: EscrowBalance = s.h.account(&escrow_balance(&s));
+    assert_eq!(balance.disputed_amount, MERCHANT_AMOUNT);
+
+    let before = s.h.balance(&payer.pubkey());
+    let arbiter = s.arbiter.insecure_clone();
+    s.h.send(&[resolve_dispute(&s, payment, true)], &arbiter, &[]).unwrap();
+    assert_eq!(s.h.balance(&payer.pubkey()) - before, MERCHANT_AMOUNT);
+    let record: Payment = s.h.account(&payment);
+    assert_eq!(record.status, PaymentStatus::DisputeResolved);
+    assert_eq!(record.refunded_amount, MERCHANT_AMOUNT);
+}
@@ -0,0 +1,216 @@
+//! Event sequence numbers: every event carrying `event_seq` takes the next
+//! value from GlobalStats, whether it lands in the same transaction, the same
+//! slot or a later one.
+
+use anchor_lang::system_program;
+use orki_gateway::state::GlobalStats;
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::events::{decode_cpi_instruction, decode_logs, GatewayEvent};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, Harness};
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(10 * LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn pay(s: &Setup, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement.pubkey(),
+            asset: PaymentAsset::Sol,
+            amount: LAMPORTS_PER_SOL / 10,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn refund(s: &Setup, payment_id: u64, amount: u64) -> Instruction {
+    build(
+        accounts::RefundPartial {
+            merchant: s.merchant,
+            payment: pda::find_payment_address(&s.payer.pubkey(), payment_id).0,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            global_stats: pda::find_global_stats_address().0,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payer: s.payer.pubkey(),
+            system_program: system_program::ID,
+            token_program: None,
+            mint: None,
+            authority_token_account: None,
+            payer_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::RefundPartial { name: MERCHANT.to_string(), amount },
+    )
+}
+
+// event_seq of every event in `events` that carries one
+fn seqs(events: &[GatewayEvent]) -> Vec<u64> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            GatewayEvent::PaymentProcessed(e) => Some(e.event_seq),
+            GatewayEvent::PaymentRefunded(e) => Some(e.event_seq),
+            GatewayEvent::MerchantRegistered(e) => Some(e.event_seq),
+            GatewayEvent::DisputeOpened(e) => Some(e.event_seq),
+            GatewayEvent::DisputeResolved(e) => Some(e.event_seq),
+            _ => None,
+        })
+        .collect()
+}
+
+fn last_seq(h: &Harness) -> u64 {
+    let stats: GlobalStats = h.account(&pda::find_global_stats_address().0);
+    stats.event_seq
+}
+
+fn assert_follows(seqs: &[u64], previous: u64) {
+    let expected: Vec<u64> = (previous + 1..=previous + seqs.len() as u64).collect();
+    assert_eq!(seqs, expected.as_slice());
+}
+
+#[test]
+fn registration_takes_the_first_sequence_number() {
+    let s = setup();
+    assert_eq!(last_seq(&s.h), 1);
+}
+
+#[test]
+fn payments_in_one_transaction_are_numbered_in_order() {
+    let mut s = setup();
+    let before = last_seq(&s.h);
+    let ixs = [pay(&s, 1), pay(&s, 2), pay(&s, 3)];
+    let payer = s.payer.insecure_clone();
+    let events = s.h.send_for_events(&ixs, &payer, &[]).unwrap();
+
+    let seqs = seqs(&events);
+    assert_eq!(seqs.len(), 3);
+    assert_follows(&seqs, before);
+    assert_eq!(last_seq(&s.h), before + 3);
+}
+
+#[test]
+fn sequence_keeps_rising_within_and_across_slots() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let mut all = vec![last_seq(&s.h)];
+
+    // Two transactions in the same slot
+    let slot = s.h.svm.get_sysvar::<Clock>().slot;
+    for payment_id in 1..=2 {
+        let ix = pay(&s, payment_id);
+        all.extend(seqs(&s.h.send_for_events(&[ix], &payer, &[]).unwrap()));
+    }
+    assert_eq!(s.h.svm.get_sysvar::<Clock>().slot, slot);
+
+    // And one in each of the next slots, including a refund
+    s.h.svm.warp_to_slot(slot + 1);
+    let ix = pay(&s, 3);
+    all.extend(seqs(&s.h.send_for_events(&[ix], &payer, &[]).unwrap()));
+
+    s.h.svm.warp_to_slot(slot + 2);
+    let owner = s.owner.insecure_clone();
+    let ix = refund(&s, 3, 1_000);
+    all.extend(seqs(&s.h.send_for_events(&[ix], &owner, &[]).unwrap()));
+
+    assert_eq!(all.len(), 5);
+    assert_follows(&all[1..], all[0]);
+    assert!(all.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn failed_transactions_do_not_consume_a_number() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, 1);
+    s.h.send(std::slice::from_ref(&ix), &payer, &[]).unwrap();
+    let before = last_seq(&s.h);
+
+    // Reusing the payment id fails, so no event and no gap
+    assert!(s.h.send(&[ix], &payer, &[]).is_err());
+    assert_eq!(last_seq(&s.h), before);
+
+    let ix = pay(&s, 2);
+    assert_follows(&seqs(&s.h.send_for_events(&[ix], &payer, &[]).unwrap()), before);
+}
+
+#[test]
+fn payment_and_refund_events_name_the_merchant_and_payment_account() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let owner = s.owner.insecure_clone();
+    let payment = pda::find_payment_address(&payer.pubkey(), 1).0;
+
+    let ix = pay(&s, 1);
+    let events = s.h.send_for_events(&[ix], &payer, &[]).unwrap();
+    let processed = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::PaymentProcessed(e) => Some(e),
+            _ => None,
+        })
+        .expect("PaymentProcessed");
+    assert_eq!(processed.merchant, s.merchant);
+    assert_eq!(processed.merchant_name, MERCHANT);
+    assert_eq!(processed.payment_account, payment);
+
+    let ix = refund(&s, 1, 1_000);
+    let events = s.h.send_for_events(&[ix], &owner, &[]).unwrap();
+    let refunded = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::PaymentRefunded(e) => Some(e),
+            _ => None,
+        })
+        .expect("PaymentRefunded");
+    assert_eq!(refunded.merchant_name, MERCHANT);
+    assert_eq!(refunded.payment_account, payment);
+}
+
+#[test]
+fn refund_events_survive_log_truncation() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[pay(&s, 1)], &payer, &[]).unwrap();
+
+    // The event rides in the self-CPI's instruction data, so the logs hold none
+    let ix = refund(&s, 1, 1_000);
+    let meta = s.h.send(std::slice::from_ref(&ix), &owner, &[]).unwrap();
+    assert!(decode_logs(&meta.logs).is_empty());
+    assert!(meta
+        .inner_instructions
+        .iter()
+        .flatten()
+        .any(|inner| matches!(decode_cpi_instruction(&orki_gateway::ID, &inner.instruction.data), Some(GatewayEvent::PaymentRefunded(_)))));
+}
@@ -0,0 +1,163 @@
+//! The fee cap: set at initialize, only ever lowered by reduce_max_fee, and
+//! checked by every fee setter.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{FeeTier, GlobalState};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{self, build};
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const MERCHANT: &str = "Shop";
+
+fn setup(max_fee_bps: u16) -> Harness {
+    let mut h = Harness::new();
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::initialize(&admin, admin, 100, admin, max_fee_bps, None)]).unwrap();
+    h
+}
+
+fn admin_auth(h: &Harness) -> accounts::AdminAuth {
+    accounts::AdminAuth {
+        global_state: pda::find_global_state_address().0,
+        admin: h.admin.pubkey(),
+        event_authority: pda::find_event_authority_address().0,
+        program: orki_gateway::ID,
+    }
+}
+
+// propose_fee then apply_fee; the timelock is 0 after initialize
+fn set_fee(h: &Harness, new_fee_bps: u16) -> [Instruction; 2] {
+    [
+        build(admin_auth(h), instruction::ProposeFee { new_fee_bps }),
+        build(
+            accounts::ApplyFee {
+                global_state: pda::find_global_state_address().0,
+                caller: h.admin.pubkey(),
+                event_authority: pda::find_event_authority_address().0,
+                program: orki_gateway::ID,
+            },
+            instruction::ApplyFee {},
+        ),
+    ]
+}
+
+fn reduce_max_fee(h: &Harness, new_max_fee_bps: u16) -> Instruction {
+    build(admin_auth(h), instruction::ReduceMaxFee { new_max_fee_bps })
+}
+
+fn state(h: &Harness) -> GlobalState {
+    h.account(&pda::find_global_state_address().0)
+}
+
+#[test]
+fn set_fee_stays_under_a_cap_that_only_goes_down() {
+    let mut h = setup(500);
+    assert_eq!(state(&h).max_fee_bps, 500);
+
+    let err = h.send_as_admin(&set_fee(&h, 501)).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+
+    let err = h.send_as_admin(&[reduce_max_fee(&h, 501)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::CannotRaiseCap));
+
+    h.send_as_admin(&[reduce_max_fee(&h, 300)]).unwrap();
+    assert_eq!(state(&h).max_fee_bps, 300);
+
+    let err = h.send_as_admin(&set_fee(&h, 301)).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+    h.send_as_admin(&set_fee(&h, 300)).unwrap();
+    assert_eq!(state(&h).fee_bps, 300);
+
+    // The current fee has to come down before the cap can go below it
+    let err = h.send_as_admin(&[reduce_max_fee(&h, 299)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+    let err = h.send_as_admin(&[reduce_max_fee(&h, 0)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidFee));
+}
+
+#[test]
+fn a_proposal_above_a_lowered_cap_cannot_be_applied() {
+    let mut h = setup(500);
+    let [propose, apply] = set_fee(&h, 400);
+    h.send_as_admin(&[propose]).unwrap();
+    h.send_as_admin(&[reduce_max_fee(&h, 300)]).unwrap();
+
+    let err = h.send_as_admin(&[apply]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+    assert_eq!(state(&h).fee_bps, 100);
+}
+
+#[test]
+fn merchant_token_and_tier_fees_respect_the_cap() {
+    let mut h = setup(300);
+    let admin = h.admin.pubkey();
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, Pubkey::new_unique());
+
+    let merchant_fee = |fee_bps_override| {
+        build(
+            accounts::MerchantFeeAuth {
+                global_state: pda::find_global_state_address().0,
+                merchant,
+                admin,
+                admin_proposal: None,
+                event_authority: pda::find_event_authority_address().0,
+                program: orki_gateway::ID,
+            },
+            instruction::SetMerchantFee { name: MERCHANT.to_string(), fee_bps_override },
+        )
+    };
+    let token_fee = |fee_bps| {
+        let mint = Pubkey::default();
+        build(
+            accounts::SetTokenFee {
+                global_state: pda::find_global_state_address().0,
+                token_fee: pda::find_token_fee_address(&mint).0,
+                admin,
+                system_program: system_program::ID,
+                admin_proposal: None,
+                event_authority: pda::find_event_authority_address().0,
+                program: orki_gateway::ID,
+            },
+            instruction::SetTokenFee { mint, fee_bps },
+        )
+    };
+    let fee_tiers = |fee_bps| {
+        build(
+            accounts::SetFeeTiers {
+                global_state: pda::find_global_state_address().0,
+                fee_tiers: pda::find_fee_tiers_address().0,
+                proposal: pda::find_fee_tiers_proposal_address().0,
+                admin,
+                system_program: system_program::ID,
+                admin_proposal: None,
+                event_authority: pda::find_event_authority_address().0,
+                program: orki_gateway::ID,
+            },
+            instruction::SetFeeTiers { tiers: vec![FeeTier { volume_threshold: 1_000, fee_bps }] },
+        )
+    };
+
+    for ix in [merchant_fee(Some(301)), token_fee(301), fee_tiers(301)] {
+        let err = h.send_as_admin(&[ix]).unwrap_err();
+        assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+    }
+    h.send_as_admin(&[merchant_fee(Some(300)), token_fee(300), fee_tiers(300)]).unwrap();
+}
+
+#[test]
+fn initialize_checks_the_cap() {
+    let mut h = Harness::new();
+    let admin = h.admin.pubkey();
+    let err = h.send_as_admin(&[instructions::initialize(&admin, admin, 100, admin, 0, None)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidFee));
+    let err = h.send_as_admin(&[instructions::initialize(&admin, admin, 100, admin, 10_001, None)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidFee));
+    let err = h.send_as_admin(&[instructions::initialize(&admin, admin, 501, admin, 500, None)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+}
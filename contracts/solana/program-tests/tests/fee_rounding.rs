@@ -0,0 +1,74 @@
+//! Fee rounding: floored by default, so a micro-payment can pay no fee, and
+//! rounded up once the admin switches the policy to Ceil.
+
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{FeeRounding, GlobalState};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const MERCHANT: &str = "Shop";
+
+fn set_fee_rounding(admin: &Pubkey, new_rounding: FeeRounding) -> Instruction {
+    build(
+        accounts::AdminAuth {
+            global_state: pda::find_global_state_address().0,
+            admin: *admin,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetFeeRounding { new_rounding },
+    )
+}
+
+fn pay(payer: &Pubkey, owner: &Pubkey, settlement_wallet: &Pubkey, amount: u64, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        payer,
+        ProcessPaymentArgs {
+            merchant_owner: *owner,
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: *settlement_wallet,
+            asset: PaymentAsset::Sol,
+            amount,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+#[test]
+fn ceil_charges_a_fee_on_micro_payments() {
+    let mut h = Harness::new();
+    h.initialize(50);
+    let state: GlobalState = h.account(&pda::find_global_state_address().0);
+    assert_eq!(state.fee_rounding, FeeRounding::Floor);
+
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let fee_vault = pda::find_fee_vault_address().0;
+
+    // 199 lamports at 50 bps is 0.995 of a lamport
+    let before = h.balance(&fee_vault);
+    h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &settlement.pubkey(), 199, 1)], &payer, &[]).unwrap();
+    assert_eq!(h.balance(&fee_vault), before);
+
+    // Only the admin may switch the policy
+    let stranger = h.funded(LAMPORTS_PER_SOL);
+    let err = h.send(&[set_fee_rounding(&stranger.pubkey(), FeeRounding::Ceil)], &stranger, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+    h.send_as_admin(&[set_fee_rounding(&h.admin.pubkey(), FeeRounding::Ceil)]).unwrap();
+
+    let settlement_before = h.balance(&settlement.pubkey());
+    h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &settlement.pubkey(), 199, 2)], &payer, &[]).unwrap();
+    assert_eq!(h.balance(&fee_vault), before + 1);
+    assert_eq!(h.balance(&settlement.pubkey()) - settlement_before, 198);
+}
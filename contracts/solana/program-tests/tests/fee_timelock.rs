@@ -0,0 +1,245 @@
+//! The fee timelock: every fee change waits out the notice merchants were
+//! promised, even when the admin shortens the delay first.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{FeeTier, FeeTiers, GlobalState, TokenFeeConfig};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const START: i64 = 1_700_000_000;
+const DAY: i64 = 86_400;
+
+fn setup() -> Harness {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    h
+}
+
+fn admin_auth(h: &Harness) -> accounts::AdminAuth {
+    accounts::AdminAuth {
+        global_state: pda::find_global_state_address().0,
+        admin: h.admin.pubkey(),
+        event_authority: pda::find_event_authority_address().0,
+        program: orki_gateway::ID,
+    }
+}
+
+fn set_fee_timelock(h: &Harness, new_timelock_seconds: i64) -> Instruction {
+    build(admin_auth(h), instruction::SetFeeTimelock { new_timelock_seconds })
+}
+
+fn propose_fee(h: &Harness, new_fee_bps: u16) -> Instruction {
+    build(admin_auth(h), instruction::ProposeFee { new_fee_bps })
+}
+
+fn apply_fee(h: &Harness) -> Instruction {
+    build(
+        accounts::ApplyFee {
+            global_state: pda::find_global_state_address().0,
+            caller: h.admin.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ApplyFee {},
+    )
+}
+
+fn set_token_fee(h: &Harness, fee_bps: u16) -> Instruction {
+    let mint = Pubkey::default();
+    build(
+        accounts::SetTokenFee {
+            global_state: pda::find_global_state_address().0,
+            token_fee: pda::find_token_fee_address(&mint).0,
+            admin: h.admin.pubkey(),
+            system_program: system_program::ID,
+            admin_proposal: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetTokenFee { mint, fee_bps },
+    )
+}
+
+fn apply_token_fee(h: &Harness) -> Instruction {
+    build(
+        accounts::ApplyTokenFee {
+            global_state: pda::find_global_state_address().0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            caller: h.admin.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ApplyTokenFee {},
+    )
+}
+
+fn clear_token_fee(h: &Harness) -> Instruction {
+    build(
+        accounts::ClearTokenFee {
+            global_state: pda::find_global_state_address().0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            admin: h.admin.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ClearTokenFee {},
+    )
+}
+
+fn set_fee_tiers(h: &Harness, tiers: Vec<FeeTier>) -> Instruction {
+    build(
+        accounts::SetFeeTiers {
+            global_state: pda::find_global_state_address().0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            proposal: pda::find_fee_tiers_proposal_address().0,
+            admin: h.admin.pubkey(),
+            system_program: system_program::ID,
+            admin_proposal: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetFeeTiers { tiers },
+    )
+}
+
+fn apply_fee_tiers(h: &Harness, caller: Pubkey) -> Instruction {
+    build(
+        accounts::ApplyFeeTiers {
+            global_state: pda::find_global_state_address().0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            proposal: pda::find_fee_tiers_proposal_address().0,
+            proposer: h.admin.pubkey(),
+            caller,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ApplyFeeTiers {},
+    )
+}
+
+fn cancel_fee_tiers_proposal(h: &Harness) -> Instruction {
+    build(
+        accounts::CancelFeeTiersProposal {
+            global_state: pda::find_global_state_address().0,
+            proposal: pda::find_fee_tiers_proposal_address().0,
+            proposer: h.admin.pubkey(),
+            admin: h.admin.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::CancelFeeTiersProposal {},
+    )
+}
+
+fn fee_tiers(h: &Harness) -> FeeTiers {
+    h.account(&pda::find_fee_tiers_address().0)
+}
+
+fn token_fee(h: &Harness) -> TokenFeeConfig {
+    h.account(&pda::find_token_fee_address(&Pubkey::default()).0)
+}
+
+fn state(h: &Harness) -> GlobalState {
+    h.account(&pda::find_global_state_address().0)
+}
+
+#[test]
+fn shortening_the_timelock_keeps_the_old_notice() {
+    let mut h = setup();
+    h.send_as_admin(&[set_fee_timelock(&h, DAY)]).unwrap();
+
+    // Dropping the delay and proposing in one go still waits the old day out
+    h.send_as_admin(&[set_fee_timelock(&h, 0), propose_fee(&h, 500)]).unwrap();
+    assert_eq!(state(&h).fee_effective_at, START + DAY);
+    let err = h.send_as_admin(&[apply_fee(&h)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeTimelockNotElapsed));
+
+    h.set_time(START + DAY);
+    h.send_as_admin(&[apply_fee(&h)]).unwrap();
+    assert_eq!(state(&h).fee_bps, 500);
+
+    // Once that notice has run, the shorter delay is the only wait
+    h.send_as_admin(&[propose_fee(&h, 200), apply_fee(&h)]).unwrap();
+    assert_eq!(state(&h).fee_bps, 200);
+}
+
+#[test]
+fn lengthening_the_timelock_applies_to_the_next_proposal() {
+    let mut h = setup();
+    h.send_as_admin(&[set_fee_timelock(&h, DAY), propose_fee(&h, 500)]).unwrap();
+    assert_eq!(state(&h).fee_effective_at, START + DAY);
+    assert_eq!(state(&h).fee_notice_until, 0);
+
+    h.set_time(START + DAY - 1);
+    let err = h.send_as_admin(&[apply_fee(&h)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeTimelockNotElapsed));
+    h.set_time(START + DAY);
+    h.send_as_admin(&[apply_fee(&h)]).unwrap();
+    assert_eq!(state(&h).fee_bps, 500);
+}
+
+#[test]
+fn token_fee_raises_wait_out_the_timelock() {
+    let mut h = setup();
+    h.send_as_admin(&[set_fee_timelock(&h, DAY)]).unwrap();
+
+    // A mint's first rate is queued; the mint keeps the global rate until then
+    h.send_as_admin(&[set_token_fee(&h, 250)]).unwrap();
+    let config = token_fee(&h);
+    assert_eq!(config.rate(), None);
+    assert_eq!((config.pending_fee_bps, config.fee_effective_at), (Some(250), START + DAY));
+    let err = h.send_as_admin(&[apply_token_fee(&h)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeTimelockNotElapsed));
+    h.set_time(START + DAY);
+    h.send_as_admin(&[apply_token_fee(&h)]).unwrap();
+    assert_eq!(token_fee(&h).rate(), Some(250));
+
+    // Cuts apply at once and drop a pending raise
+    h.send_as_admin(&[set_token_fee(&h, 400)]).unwrap();
+    assert_eq!(token_fee(&h).rate(), Some(250));
+    h.send_as_admin(&[set_token_fee(&h, 50)]).unwrap();
+    let config = token_fee(&h);
+    assert_eq!((config.rate(), config.pending_fee_bps), (Some(50), None));
+    let err = h.send_as_admin(&[apply_token_fee(&h)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NoPendingFee));
+
+    // Clearing back to the higher global rate would be a raise too
+    let err = h.send_as_admin(&[clear_token_fee(&h)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeRaiseNeedsTimelock));
+    h.send_as_admin(&[set_token_fee(&h, 100)]).unwrap();
+    h.set_time(START + 2 * DAY);
+    h.send_as_admin(&[apply_token_fee(&h), clear_token_fee(&h)]).unwrap();
+}
+
+#[test]
+fn fee_tiers_wait_out_the_timelock() {
+    let mut h = setup();
+    let tiers = vec![FeeTier { volume_threshold: 1_000, fee_bps: 500 }];
+    let proposal = pda::find_fee_tiers_proposal_address().0;
+    h.send_as_admin(&[set_fee_timelock(&h, DAY), set_fee_tiers(&h, tiers.clone())]).unwrap();
+    assert!(fee_tiers(&h).tiers.is_empty());
+
+    // Anyone may apply, but only once the timelock has passed
+    let cranker = h.funded(LAMPORTS_PER_SOL);
+    let err = h.send(&[apply_fee_tiers(&h, cranker.pubkey())], &cranker, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeTimelockNotElapsed));
+    h.set_time(START + DAY);
+    let admin_before = h.balance(&h.admin.pubkey());
+    h.send(&[apply_fee_tiers(&h, cranker.pubkey())], &cranker, &[]).unwrap();
+    assert_eq!(fee_tiers(&h).tiers, tiers);
+    assert_eq!(h.balance(&proposal), 0);
+    assert!(h.balance(&h.admin.pubkey()) > admin_before);
+
+    // Turning tiers off is queued too, and can be withdrawn
+    h.send_as_admin(&[set_fee_tiers(&h, vec![])]).unwrap();
+    h.send_as_admin(&[cancel_fee_tiers_proposal(&h)]).unwrap();
+    assert_eq!(h.balance(&proposal), 0);
+    assert_eq!(fee_tiers(&h).tiers, tiers);
+}
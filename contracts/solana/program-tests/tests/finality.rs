@@ -0,0 +1,306 @@
+//! Payment finality: finalize_payment marks a payment Finalized once it is
+//! `finality_slots` deep, and escrowed shares stay out of claim_settlement
+//! until it has.
+
+use anchor_lang::system_program;
+use orki_gateway::constants::DEFAULT_FINALITY_SLOTS;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{EscrowBalance, Payment, PaymentStatus};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::events::GatewayEvent;
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = LAMPORTS_PER_SOL;
+// What reaches the merchant at the 1% fee setup() charges
+const MERCHANT_AMOUNT: u64 = AMOUNT * 99 / 100;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+// setup() with the merchant escrowing SOL payments
+fn escrow_setup() -> Setup {
+    let mut s = setup();
+    let open = build(
+        accounts::OpenEscrow {
+            merchant: s.merchant,
+            vault: pda::find_vault_address(&s.merchant).0,
+            escrow_balance: escrow_balance(&s),
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::OpenEscrow { name: MERCHANT.to_string(), mint: Pubkey::default() },
+    );
+    let enable = build(
+        accounts::UpdateMerchant {
+            merchant: s.merchant,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payout_book: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::UpdateMerchant {
+            name: MERCHANT.to_string(),
+            settlement_wallet: None,
+            settlement_token: None,
+            swap_enabled: None,
+            escrow_enabled: Some(true),
+            min_amount: None,
+            max_amount: None,
+            accepts_any_token: None,
+            metadata_uri: None,
+            category: None,
+        },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[open, enable], &owner, &[]).unwrap();
+    s
+}
+
+fn escrow_balance(s: &Setup) -> Pubkey {
+    pda::find_escrow_balance_address(&s.merchant, &Pubkey::default()).0
+}
+
+fn pay(s: &Setup, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement.pubkey(),
+            asset: PaymentAsset::Sol,
+            amount: AMOUNT,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+// The plain payment with the merchant's vault and SOL escrow balance filled in
+fn pay_escrowed(s: &Setup, payment_id: u64) -> Instruction {
+    let fee_vault = pda::find_fee_vault_address().0;
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer: s.payer.pubkey(),
+            merchant_wallet: s.settlement.pubkey(),
+            fee_vault,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&s.payer.pubkey(), payment_id).0,
+            blocklist_entry: pda::find_blocked_address(&s.payer.pubkey()).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: Some(pda::find_vault_address(&s.merchant).0),
+            escrow_balance: Some(escrow_balance(s)),
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount: AMOUNT,
+            payment_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn claim(s: &Setup) -> Instruction {
+    build(
+        accounts::ClaimSettlement {
+            global_state: pda::find_global_state_address().0,
+            merchant: s.merchant,
+            vault: pda::find_vault_address(&s.merchant).0,
+            escrow_balance: escrow_balance(s),
+            owner: s.owner.pubkey(),
+            settlement_wallet: s.settlement.pubkey(),
+            token_program: None,
+            mint: None,
+            vault_token_account: None,
+            settlement_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ClaimSettlement { name: MERCHANT.to_string() },
+    )
+}
+
+fn payment(s: &Setup, payment_id: u64) -> Payment {
+    s.h.account(&pda::find_payment_address(&s.payer.pubkey(), payment_id).0)
+}
+
+fn warp(s: &mut Setup, slots: u64) {
+    let slot = s.h.svm.get_sysvar::<Clock>().slot;
+    s.h.svm.warp_to_slot(slot + slots);
+}
+
+#[test]
+fn finalizes_once_deep_enough() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, 1);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    let paid_in = payment(&s, 1).slot;
+    assert_eq!(paid_in, s.h.svm.get_sysvar::<Clock>().slot);
+    assert_eq!(payment(&s, 1).status, PaymentStatus::Completed);
+
+    // Anyone may send it; here a stranger pays the fee
+    let crank = s.h.funded(LAMPORTS_PER_SOL);
+    let finalize = instructions::finalize_payment(&payer.pubkey(), 1, None);
+    warp(&mut s, DEFAULT_FINALITY_SLOTS - 1);
+    let err = s.h.send(std::slice::from_ref(&finalize), &crank, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FinalityNotReached));
+
+    warp(&mut s, 1);
+    let events = s.h.send_for_events(std::slice::from_ref(&finalize), &crank, &[]).unwrap();
+    assert_eq!(payment(&s, 1).status, PaymentStatus::Finalized);
+    let finalized = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::PaymentFinalized(e) => Some(e),
+            _ => None,
+        })
+        .expect("PaymentFinalized");
+    assert_eq!(finalized.merchant, s.merchant);
+    assert_eq!(finalized.slot, paid_in);
+    assert_eq!(finalized.finalized_slot, paid_in + DEFAULT_FINALITY_SLOTS);
+
+    let err = s.h.send(&[finalize], &crank, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidPaymentStatus));
+}
+
+#[test]
+fn admin_sets_the_finality_depth() {
+    let mut s = setup();
+    let set = |admin: &Pubkey, new_slots| {
+        build(
+            accounts::AdminAuth {
+                global_state: pda::find_global_state_address().0,
+                admin: *admin,
+                event_authority: pda::find_event_authority_address().0,
+                program: orki_gateway::ID,
+            },
+            instruction::SetFinalitySlots { new_slots },
+        )
+    };
+    let admin = s.h.admin.pubkey();
+    let err = s.h.send_as_admin(&[set(&admin, 0)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidAmount));
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[set(&owner.pubkey(), 4)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+    s.h.send_as_admin(&[set(&admin, 4)]).unwrap();
+
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, 1);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    warp(&mut s, 4);
+    s.h.send(&[instructions::finalize_payment(&payer.pubkey(), 1, None)], &payer, &[]).unwrap();
+    assert_eq!(payment(&s, 1).status, PaymentStatus::Finalized);
+}
+
+#[test]
+fn escrow_claims_wait_for_finality() {
+    let mut s = escrow_setup();
+    let payer = s.payer.insecure_clone();
+    let owner = s.owner.insecure_clone();
+    let ix = pay_escrowed(&s, 1);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    let balance: EscrowBalance = s.h.account(&escrow_balance(&s));
+    assert_eq!(balance.amount, MERCHANT_AMOUNT);
+    assert_eq!(balance.pending_amount, MERCHANT_AMOUNT);
+    let err = s.h.send(&[claim(&s)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NothingToClaim));
+
+    // The escrow balance must come along to release the share
+    warp(&mut s, DEFAULT_FINALITY_SLOTS);
+    let err = s.h.send(&[instructions::finalize_payment(&payer.pubkey(), 1, None)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MissingAccount));
+    let finalize = instructions::finalize_payment(&payer.pubkey(), 1, Some(escrow_balance(&s)));
+    s.h.send(&[finalize], &payer, &[]).unwrap();
+    let balance: EscrowBalance = s.h.account(&escrow_balance(&s));
+    assert_eq!(balance.pending_amount, 0);
+
+    let before = s.h.balance(&s.settlement.pubkey());
+    s.h.send(&[claim(&s)], &owner, &[]).unwrap();
+    assert_eq!(s.h.balance(&s.settlement.pubkey()) - before, MERCHANT_AMOUNT);
+}
+
+#[test]
+fn claims_pay_out_finalized_shares_and_hold_the_rest() {
+    let mut s = escrow_setup();
+    let payer = s.payer.insecure_clone();
+    let owner = s.owner.insecure_clone();
+    let ix = pay_escrowed(&s, 1);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    warp(&mut s, DEFAULT_FINALITY_SLOTS);
+    let finalize = instructions::finalize_payment(&payer.pubkey(), 1, Some(escrow_balance(&s)));
+    s.h.send(&[finalize], &payer, &[]).unwrap();
+
+    // A second payment lands after the first is final
+    let ix = pay_escrowed(&s, 2);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    let before = s.h.balance(&s.settlement.pubkey());
+    s.h.send(&[claim(&s)], &owner, &[]).unwrap();
+    assert_eq!(s.h.balance(&s.settlement.pubkey()) - before, MERCHANT_AMOUNT);
+    let balance: EscrowBalance = s.h.account(&escrow_balance(&s));
+    assert_eq!(balance.amount, MERCHANT_AMOUNT);
+    assert_eq!(balance.pending_amount, MERCHANT_AMOUNT);
+}
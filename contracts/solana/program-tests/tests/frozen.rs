@@ -0,0 +1,94 @@
+//! SPL payments into token accounts the issuer has frozen fail with
+//! DestinationFrozen before any transfer, and go through again once thawed.
+
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway_client::instructions::{PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    // Mint and freeze authority, standing in for the issuer
+    issuer: Keypair,
+    mint: Pubkey,
+    payer_ata: Pubkey,
+    settlement_ata: Pubkey,
+    fee_ata: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+
+    let issuer = h.funded(LAMPORTS_PER_SOL);
+    let mint = h.create_mint(&issuer, 6);
+    let payer_ata = h.create_ata(&payer.pubkey(), &mint);
+    let settlement_ata = h.create_ata(&settlement.pubkey(), &mint);
+    let fee_ata = h.create_ata(&pda::find_fee_vault_address().0, &mint);
+    h.mint_to(&mint, &issuer, &payer_ata, 1_000_000);
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, mint)]).unwrap();
+
+    Setup { h, owner, settlement, payer, issuer, mint, payer_ata, settlement_ata, fee_ata }
+}
+
+fn pay(s: &Setup, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement.pubkey(),
+            asset: PaymentAsset::Spl { mint: s.mint, token_program: spl_token::ID },
+            amount: 100_000,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn assert_frozen_destination(s: &mut Setup, frozen: Pubkey) {
+    let (mint, issuer) = (s.mint, s.issuer.insecure_clone());
+    let payer = s.payer.insecure_clone();
+    s.h.freeze(&mint, &issuer, &frozen);
+
+    let err = s.h.send(&[pay(s, 1)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::DestinationFrozen));
+    assert_eq!(s.h.token_balance(&s.payer_ata), 1_000_000);
+    assert!(s.h.svm.get_account(&pda::find_payment_address(&payer.pubkey(), 1).0).is_none());
+
+    s.h.thaw(&mint, &issuer, &frozen);
+    s.h.send(&[pay(s, 1)], &payer, &[]).unwrap();
+    assert_eq!(s.h.token_balance(&s.settlement_ata), 99_000);
+    assert_eq!(s.h.token_balance(&s.fee_ata), 1_000);
+}
+
+#[test]
+fn rejects_a_frozen_settlement_account() {
+    let mut s = setup();
+    let frozen = s.settlement_ata;
+    assert_frozen_destination(&mut s, frozen);
+}
+
+#[test]
+fn rejects_a_frozen_fee_account() {
+    let mut s = setup();
+    let frozen = s.fee_ata;
+    assert_frozen_destination(&mut s, frozen);
+}
@@ -0,0 +1,281 @@
+//! Core flows against the built program in LiteSVM: setup, merchants, SOL and
+//! SPL payments, and the admin and pause guards.
+
+use anchor_spl::token::spl_token;
+use orki_gateway::constants::MAX_FEE_BPS;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{GlobalState, Merchant, Payment, PaymentStatus};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{
+    already_in_use, instructions, pda, program_error, Harness, LAMPORTS_PER_SIGNATURE,
+};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+// An initialized gateway with one any-token merchant and a funded payer
+fn setup(fee_bps: u16) -> Setup {
+    let mut h = Harness::new();
+    h.initialize(fee_bps);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    // Funded so a zero-amount payout still leaves it rent-exempt
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn pay(s: &Setup, asset: PaymentAsset, amount: u64, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement.pubkey(),
+            asset,
+            amount,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn set_paused(authority: &Pubkey, paused: bool) -> Instruction {
+    build(
+        accounts::SetPaused {
+            global_state: pda::find_global_state_address().0,
+            authority: *authority,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetPaused { paused },
+    )
+}
+
+// Pays 1 SOL and checks where every lamport went
+fn assert_sol_split(fee_bps: u16) {
+    let mut s = setup(fee_bps);
+    let amount = LAMPORTS_PER_SOL;
+    let fee = amount * fee_bps as u64 / 10_000;
+    let fee_vault = pda::find_fee_vault_address().0;
+
+    let payer_before = s.h.balance(&s.payer.pubkey());
+    let settlement_before = s.h.balance(&s.settlement.pubkey());
+    let vault_before = s.h.balance(&fee_vault);
+
+    let ix = pay(&s, PaymentAsset::Sol, amount, 1);
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    assert_eq!(s.h.balance(&s.settlement.pubkey()) - settlement_before, amount - fee);
+    assert_eq!(s.h.balance(&fee_vault) - vault_before, fee);
+    assert_eq!(
+        payer_before - s.h.balance(&s.payer.pubkey()),
+        amount + s.h.payment_rent() + LAMPORTS_PER_SIGNATURE
+    );
+
+    let payment: Payment = s.h.account(&pda::find_payment_address(&s.payer.pubkey(), 1).0);
+    assert_eq!(payment.amount, amount);
+    assert_eq!(payment.fee, fee);
+    assert_eq!(payment.status, PaymentStatus::Completed);
+}
+
+#[test]
+fn initializes_global_state() {
+    let mut h = Harness::new();
+    h.initialize(250);
+
+    let state: GlobalState = h.account(&pda::find_global_state_address().0);
+    assert_eq!(state.admin, h.admin.pubkey());
+    assert_eq!(state.fee_bps, 250);
+    assert!(!state.paused);
+    assert_eq!(state.guardian, Pubkey::default());
+}
+
+#[test]
+fn a_deployer_can_initialize_for_another_admin() {
+    let mut h = Harness::new();
+    let deployer = h.funded(LAMPORTS_PER_SOL);
+    let (admin, guardian) = (h.admin.pubkey(), Pubkey::new_unique());
+    let ix = instructions::initialize(&deployer.pubkey(), admin, 100, admin, MAX_FEE_BPS, Some(guardian));
+    h.send(&[ix], &deployer, &[]).unwrap();
+
+    let state: GlobalState = h.account(&pda::find_global_state_address().0);
+    assert_eq!((state.admin, state.guardian), (admin, guardian));
+    // The deployer only paid for the accounts
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, Pubkey::default())]).unwrap();
+    let ix = instructions::open_fee_stats(&deployer.pubkey(), Pubkey::new_unique());
+    assert_eq!(h.send(&[ix], &deployer, &[]).unwrap_err(), program_error(ErrorCode::Unauthorized));
+}
+
+#[test]
+fn rejects_the_default_key_as_admin() {
+    let mut h = Harness::new();
+    let admin = h.admin.pubkey();
+    let ix = instructions::initialize(&admin, Pubkey::default(), 100, admin, MAX_FEE_BPS, None);
+    assert_eq!(h.send_as_admin(&[ix]).unwrap_err(), program_error(ErrorCode::InvalidAdmin));
+}
+
+#[test]
+fn rejects_second_initialize() {
+    let mut h = Harness::new();
+    h.initialize(100);
+
+    let admin = h.admin.pubkey();
+    let err = h.send_as_admin(&[instructions::initialize(&admin, admin, 200, admin, MAX_FEE_BPS, None)]).unwrap_err();
+    assert_eq!(err, already_in_use());
+}
+
+#[test]
+fn rejects_fee_above_100_percent() {
+    let mut h = Harness::new();
+    let admin = h.admin.pubkey();
+    let err = h.send_as_admin(&[instructions::initialize(&admin, admin, 10_001, admin, MAX_FEE_BPS, None)]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::FeeCapExceeded));
+}
+
+#[test]
+fn registers_and_updates_merchant() {
+    let mut s = setup(100);
+    let merchant: Merchant = s.h.account(&s.merchant);
+    assert_eq!(merchant.owner, s.owner.pubkey());
+    assert_eq!(merchant.settlement_wallet, s.settlement.pubkey());
+    assert_eq!(merchant.name, MERCHANT);
+    assert!(merchant.active);
+
+    let update = build(
+        accounts::UpdateMerchant {
+            merchant: s.merchant,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payout_book: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::UpdateMerchant {
+            name: MERCHANT.to_string(),
+            settlement_wallet: None,
+            settlement_token: None,
+            swap_enabled: None,
+            escrow_enabled: None,
+            min_amount: Some(1_000),
+            max_amount: Some(1_000_000),
+            accepts_any_token: None,
+            metadata_uri: Some("https://example.com/shop.json".to_string()),
+            category: Some(3),
+        },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[update], &owner, &[]).unwrap();
+
+    let merchant: Merchant = s.h.account(&s.merchant);
+    assert_eq!(merchant.min_amount, 1_000);
+    assert_eq!(merchant.max_amount, 1_000_000);
+    assert_eq!(merchant.metadata_uri, "https://example.com/shop.json");
+    assert_eq!(merchant.category, 3);
+}
+
+#[test]
+fn pays_in_sol() {
+    assert_sol_split(100);
+}
+
+#[test]
+fn pays_in_sol_at_zero_fee() {
+    assert_sol_split(0);
+}
+
+#[test]
+fn pays_in_sol_at_full_fee() {
+    assert_sol_split(10_000);
+}
+
+#[test]
+fn pays_in_spl() {
+    let mut s = setup(100);
+    let mint_authority = s.h.funded(LAMPORTS_PER_SOL);
+    let mint = s.h.create_mint(&mint_authority, 6);
+    let payer_ata = s.h.create_ata(&s.payer.pubkey(), &mint);
+    let settlement_ata = s.h.create_ata(&s.settlement.pubkey(), &mint);
+    let fee_ata = s.h.create_ata(&pda::find_fee_vault_address().0, &mint);
+    s.h.mint_to(&mint, &mint_authority, &payer_ata, 1_000_000);
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[instructions::open_fee_stats(&admin, mint)]).unwrap();
+
+    let asset = PaymentAsset::Spl { mint, token_program: spl_token::ID };
+    let ix = pay(&s, asset, 100_000, 1);
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    assert_eq!(s.h.token_balance(&payer_ata), 900_000);
+    assert_eq!(s.h.token_balance(&settlement_ata), 99_000);
+    assert_eq!(s.h.token_balance(&fee_ata), 1_000);
+
+    let payment: Payment = s.h.account(&pda::find_payment_address(&s.payer.pubkey(), 1).0);
+    assert_eq!(payment.mint, mint);
+    assert_eq!(payment.fee, 1_000);
+}
+
+#[test]
+fn rejects_payment_while_paused() {
+    let mut s = setup(100);
+    let admin = s.h.admin.pubkey();
+    s.h.send_as_admin(&[set_paused(&admin, true)]).unwrap();
+
+    let ix = pay(&s, PaymentAsset::Sol, LAMPORTS_PER_SOL, 1);
+    let payer = s.payer.insecure_clone();
+    let err = s.h.send(&[ix], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Paused));
+}
+
+#[test]
+fn rejects_duplicate_payment_id() {
+    let mut s = setup(100);
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, PaymentAsset::Sol, LAMPORTS_PER_SOL, 7);
+    s.h.send(std::slice::from_ref(&ix), &payer, &[]).unwrap();
+
+    let err = s.h.send(&[ix], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::DuplicatePayment));
+}
+
+#[test]
+fn rejects_admin_calls_from_others() {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let stranger = h.funded(LAMPORTS_PER_SOL);
+
+    let err = h.send(&[set_paused(&stranger.pubkey(), true)], &stranger, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+
+    let propose = build(
+        accounts::AdminAuth {
+            global_state: pda::find_global_state_address().0,
+            admin: stranger.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProposeFee { new_fee_bps: 500 },
+    );
+    let err = h.send(&[propose], &stranger, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+
+    let state: GlobalState = h.account(&pda::find_global_state_address().0);
+    assert!(!state.paused);
+    assert_eq!(state.pending_fee_bps, None);
+}
@@ -0,0 +1,145 @@
+//! close_merchant refuses while discount codes, a split config or an
+//! accepted-token list still hang off the merchant's address: each would
+//! outlive the account and apply again to a re-registration under the name.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Discount, Merchant, SplitRecipient};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const CODE: &str = "SPRING10";
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, owner.pubkey());
+    Setup { h, owner, merchant }
+}
+
+fn send(s: &mut Setup, ix: Instruction) -> Result<(), TransactionError> {
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+fn close(s: &Setup) -> Instruction {
+    let index = s.h.account::<Merchant>(&s.merchant).merchant_index;
+    build(
+        accounts::CloseMerchant {
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&s.owner.pubkey(), MERCHANT).0),
+            owner: s.owner.pubkey(),
+        },
+        instruction::CloseMerchant { name: MERCHANT.to_string() },
+    )
+}
+
+fn discount_address(s: &Setup) -> Pubkey {
+    pda::find_discount_address(&s.merchant, &Discount::hash_code(CODE)).0
+}
+
+#[test]
+fn discount_codes_are_deleted_before_closing() {
+    let mut s = setup();
+    let create = build(
+        accounts::CreateDiscount {
+            merchant: s.merchant,
+            discount: discount_address(&s),
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateDiscount {
+            name: MERCHANT.to_string(),
+            code_hash: Discount::hash_code(CODE),
+            discount_bps: 1_000,
+            max_redemptions: 0,
+            expires_at: 0,
+        },
+    );
+    send(&mut s, create).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).discount_codes, 1);
+    let ix = close(&s);
+    assert_eq!(send(&mut s, ix), Err(program_error(ErrorCode::MerchantHasDiscountCodes)));
+
+    let delete = build(
+        accounts::DeleteDiscount { merchant: s.merchant, discount: discount_address(&s), owner: s.owner.pubkey() },
+        instruction::DeleteDiscount { name: MERCHANT.to_string() },
+    );
+    send(&mut s, delete).unwrap();
+    assert!(s.h.svm.get_account(&discount_address(&s)).is_none_or(|a| a.lamports == 0));
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).discount_codes, 0);
+    let ix = close(&s);
+    send(&mut s, ix).unwrap();
+}
+
+#[test]
+fn split_configs_are_cleared_before_closing() {
+    let mut s = setup();
+    let split_config = pda::find_split_config_address(&s.merchant).0;
+    let set = build(
+        accounts::SetSplitConfig {
+            merchant: s.merchant,
+            split_config,
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::SetSplitConfig {
+            name: MERCHANT.to_string(),
+            recipients: vec![SplitRecipient { recipient: Pubkey::new_unique(), bps: 10_000 }],
+        },
+    );
+    send(&mut s, set).unwrap();
+    let ix = close(&s);
+    assert_eq!(send(&mut s, ix), Err(program_error(ErrorCode::MerchantHasSplitConfig)));
+
+    let clear = build(
+        accounts::ClearSplitConfig { merchant: s.merchant, split_config, owner: s.owner.pubkey() },
+        instruction::ClearSplitConfig { name: MERCHANT.to_string() },
+    );
+    send(&mut s, clear).unwrap();
+    let ix = close(&s);
+    send(&mut s, ix).unwrap();
+}
+
+#[test]
+fn accepted_tokens_are_removed_before_closing() {
+    let mut s = setup();
+    let accepted_tokens = pda::find_accepted_tokens_address(&s.merchant).0;
+    let mint = Pubkey::new_unique();
+    let add = build(
+        accounts::AddAcceptedToken {
+            merchant: s.merchant,
+            accepted_tokens,
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::AddAcceptedToken { name: MERCHANT.to_string(), mint },
+    );
+    send(&mut s, add).unwrap();
+    let ix = close(&s);
+    assert_eq!(send(&mut s, ix), Err(program_error(ErrorCode::MerchantHasAcceptedTokens)));
+
+    let remove = build(
+        accounts::RemoveAcceptedToken { merchant: s.merchant, accepted_tokens, owner: s.owner.pubkey() },
+        instruction::RemoveAcceptedToken { name: MERCHANT.to_string(), mint },
+    );
+    send(&mut s, remove).unwrap();
+    let ix = close(&s);
+    send(&mut s, ix).unwrap();
+}
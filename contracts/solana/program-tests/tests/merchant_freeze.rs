@@ -0,0 +1,145 @@
+//! Admin freeze: a frozen merchant takes no payments until the admin
+//! unfreezes it, whatever its own active flag says, while refunds to payers
+//! still go out.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, Payment};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::events::GatewayEvent;
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = LAMPORTS_PER_SOL / 10;
+const REASON: u8 = 3;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn merchant_admin(s: &Setup, admin: &Pubkey) -> accounts::MerchantAdminAuth {
+    accounts::MerchantAdminAuth {
+        global_state: pda::find_global_state_address().0,
+        merchant: s.merchant,
+        admin: *admin,
+        event_authority: pda::find_event_authority_address().0,
+        program: orki_gateway::ID,
+    }
+}
+
+fn freeze(s: &Setup) -> Instruction {
+    let admin = s.h.admin.pubkey();
+    build(merchant_admin(s, &admin), instruction::FreezeMerchant { name: MERCHANT.to_string(), reason: REASON })
+}
+
+fn unfreeze(s: &Setup, admin: &Pubkey) -> Instruction {
+    build(merchant_admin(s, admin), instruction::UnfreezeMerchant { name: MERCHANT.to_string(), reason: 0 })
+}
+
+fn pay(s: &mut Setup, payment_id: u64) -> Result<(), TransactionError> {
+    let ix = instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement.pubkey(),
+            asset: PaymentAsset::Sol,
+            amount: AMOUNT,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).map(|_| ())
+}
+
+#[test]
+fn frozen_merchants_take_no_payments_until_unfrozen() {
+    let mut s = setup();
+    let admin = s.h.admin.insecure_clone();
+    let events = s.h.send_for_events(&[freeze(&s)], &admin, &[]).unwrap();
+    let frozen = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::MerchantFrozen(e) => Some(e),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!((frozen.merchant, frozen.reason), (s.merchant, REASON));
+    assert!(s.h.account::<Merchant>(&s.merchant).admin_frozen);
+    // Still active as far as the owner's flag goes
+    assert!(s.h.account::<Merchant>(&s.merchant).active);
+    assert_eq!(pay(&mut s, 1).unwrap_err(), program_error(ErrorCode::MerchantFrozen));
+
+    s.h.send_as_admin(&[unfreeze(&s, &admin.pubkey())]).unwrap();
+    assert!(!s.h.account::<Merchant>(&s.merchant).admin_frozen);
+    pay(&mut s, 1).unwrap();
+}
+
+#[test]
+fn the_owner_cannot_unfreeze() {
+    let mut s = setup();
+    s.h.send_as_admin(&[freeze(&s)]).unwrap();
+
+    let owner = s.owner.insecure_clone();
+    let ix = unfreeze(&s, &owner.pubkey());
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+    assert!(s.h.account::<Merchant>(&s.merchant).admin_frozen);
+}
+
+#[test]
+fn refunds_go_out_while_frozen() {
+    let mut s = setup();
+    pay(&mut s, 1).unwrap();
+    s.h.send_as_admin(&[freeze(&s)]).unwrap();
+
+    let address = pda::find_payment_address(&s.payer.pubkey(), 1).0;
+    let payer_before = s.h.balance(&s.payer.pubkey());
+    let refund = build(
+        accounts::RefundPartial {
+            merchant: s.merchant,
+            payment: address,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            global_stats: pda::find_global_stats_address().0,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payer: s.payer.pubkey(),
+            system_program: system_program::ID,
+            token_program: None,
+            mint: None,
+            authority_token_account: None,
+            payer_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::RefundPartial { name: MERCHANT.to_string(), amount: AMOUNT / 2 },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[refund], &owner, &[]).unwrap();
+    assert_eq!(s.h.account::<Payment>(&address).refunded_amount, AMOUNT / 2);
+    assert_eq!(s.h.balance(&s.payer.pubkey()) - payer_before, AMOUNT / 2);
+}
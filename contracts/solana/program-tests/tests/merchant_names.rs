@@ -0,0 +1,214 @@
+//! Merchant names: register and rename reject empty names and characters that
+//! break downstream systems, and each owner claims a name once regardless of
+//! case through its MerchantName account.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, MerchantName};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, RegisterMerchantArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    Setup { h, owner }
+}
+
+fn register_ix(h: &Harness, owner: &Pubkey, name: &str) -> Instruction {
+    instructions::register_merchant(
+        owner,
+        RegisterMerchantArgs {
+            settlement_wallet: Pubkey::new_unique(),
+            settlement_token: Pubkey::default(),
+            name: name.to_string(),
+            sandbox: false,
+            referrer: None,
+            referrer_bps: 0,
+            accepts_any_token: true,
+            metadata_uri: String::new(),
+            category: 0,
+            merchant_index: h.next_merchant_index(),
+        },
+    )
+}
+
+fn register(s: &mut Setup, name: &str) -> Result<(), TransactionError> {
+    let owner = s.owner.insecure_clone();
+    let ix = register_ix(&s.h, &owner.pubkey(), name);
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+fn rename(s: &mut Setup, name: &str, new_name: &str) -> Result<(), TransactionError> {
+    let owner = s.owner.pubkey();
+    let merchant = pda::find_merchant_address(&owner, name).0;
+    let new_merchant = pda::find_merchant_address(&owner, new_name).0;
+    let index = s.h.account::<Merchant>(&merchant).merchant_index;
+    let ix = build(
+        accounts::RenameMerchant {
+            merchant,
+            new_merchant,
+            merchant_stats: pda::find_merchant_stats_address(&merchant).0,
+            new_merchant_stats: pda::find_merchant_stats_address(&new_merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&owner, name).0),
+            new_merchant_name: pda::find_merchant_name_address(&owner, new_name).0,
+            owner,
+            system_program: system_program::ID,
+        },
+        instruction::RenameMerchant { name: name.to_string(), new_name: new_name.to_string() },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+fn close(s: &mut Setup, name: &str) -> Result<(), TransactionError> {
+    let owner = s.owner.pubkey();
+    let merchant = pda::find_merchant_address(&owner, name).0;
+    let index = s.h.account::<Merchant>(&merchant).merchant_index;
+    let ix = build(
+        accounts::CloseMerchant {
+            merchant,
+            merchant_stats: pda::find_merchant_stats_address(&merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&owner, name).0),
+            owner,
+        },
+        instruction::CloseMerchant { name: name.to_string() },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+fn is_closed(h: &Harness, address: &Pubkey) -> bool {
+    h.svm.get_account(address).is_none_or(|a| a.lamports == 0)
+}
+
+#[test]
+fn rejects_empty_names_and_invisible_characters() {
+    let mut s = setup();
+    assert_eq!(register(&mut s, ""), Err(program_error(ErrorCode::EmptyName)));
+    for name in [" Shop", "Shop ", "Sh\0op", "Shop\n", "Sh\u{200B}op", "\u{202E}pohS", "\u{FEFF}Shop"] {
+        assert_eq!(register(&mut s, name), Err(program_error(ErrorCode::InvalidNameCharacters)), "{name:?}");
+    }
+    // Spaces inside a name are fine
+    register(&mut s, "Corner Shop").unwrap();
+    assert_eq!(rename(&mut s, "Corner Shop", "Corner\tShop"), Err(program_error(ErrorCode::InvalidNameCharacters)));
+}
+
+#[test]
+fn names_are_limited_in_bytes_not_characters() {
+    let mut s = setup();
+    // Eight four-byte emoji fill the 32 bytes exactly
+    let emoji = "🍩".repeat(8);
+    assert_eq!(emoji.len(), 32);
+    register(&mut s, &emoji).unwrap();
+    register(&mut s, "Café ☕").unwrap();
+    // Nine characters, 36 bytes
+    assert_eq!(register(&mut s, &"🍩".repeat(9)), Err(program_error(ErrorCode::NameTooLong)));
+
+    let claim: MerchantName = s.h.account(&pda::find_merchant_name_address(&s.owner.pubkey(), "CAFÉ ☕").0);
+    assert_eq!(claim.normalized_name, "café ☕");
+}
+
+#[test]
+fn a_name_cut_inside_a_character_does_not_deserialize() {
+    let mut s = setup();
+    // A client truncating "Crème brûlée 🍮" to 16 bytes ends mid-emoji;
+    // Borsh strings must be UTF-8, so the program never sees the name
+    let mut ix = register_ix(&s.h, &s.owner.pubkey(), "Crème brûlée 🍮");
+    let name = "Crème brûlée 🍮".as_bytes();
+    // Discriminator, settlement wallet and token, then the u32 length
+    let start = 8 + 32 + 32;
+    ix.data[start..start + 4].copy_from_slice(&16u32.to_le_bytes());
+    ix.data.splice(start + 4..start + 4 + name.len(), name[..16].iter().copied());
+    assert!(std::str::from_utf8(&name[..16]).is_err());
+
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    let code = anchor_lang::error::ErrorCode::InstructionDidNotDeserialize as u32;
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::Custom(code)));
+}
+
+#[test]
+fn an_owner_claims_a_name_once_in_any_casing() {
+    let mut s = setup();
+    register(&mut s, "Coffee Shop").unwrap();
+    let merchant = pda::find_merchant_address(&s.owner.pubkey(), "Coffee Shop").0;
+    let claim_address = pda::find_merchant_name_address(&s.owner.pubkey(), "Coffee Shop").0;
+    let claim: MerchantName = s.h.account(&claim_address);
+    assert_eq!((claim.owner, claim.merchant), (s.owner.pubkey(), merchant));
+    assert_eq!(claim.normalized_name, "coffee shop");
+    assert!(s.h.account::<Merchant>(&merchant).name_indexed);
+
+    assert_eq!(register(&mut s, "coffee shop"), Err(program_error(ErrorCode::NameAlreadyUsed)));
+    assert_eq!(register(&mut s, "COFFEE SHOP"), Err(program_error(ErrorCode::NameAlreadyUsed)));
+    register(&mut s, "Tea Shop").unwrap();
+    assert_eq!(rename(&mut s, "Tea Shop", "coffee Shop"), Err(program_error(ErrorCode::NameAlreadyUsed)));
+
+    // Another owner has its own names
+    let other = s.h.funded(LAMPORTS_PER_SOL);
+    let ix = register_ix(&s.h, &other.pubkey(), "coffee shop");
+    s.h.send(&[ix], &other, &[]).unwrap();
+}
+
+#[test]
+fn renames_move_the_claim() {
+    let mut s = setup();
+    register(&mut s, "Coffee Shop").unwrap();
+    let owner = s.owner.pubkey();
+
+    // Only the casing changes: the same claim follows the merchant
+    rename(&mut s, "Coffee Shop", "Coffee shop").unwrap();
+    let claim_address = pda::find_merchant_name_address(&owner, "coffee shop").0;
+    let claim: MerchantName = s.h.account(&claim_address);
+    assert_eq!(claim.merchant, pda::find_merchant_address(&owner, "Coffee shop").0);
+
+    // A new name is claimed and the old one released
+    rename(&mut s, "Coffee shop", "Roastery").unwrap();
+    assert!(is_closed(&s.h, &claim_address));
+    let claim: MerchantName = s.h.account(&pda::find_merchant_name_address(&owner, "Roastery").0);
+    assert_eq!(claim.merchant, pda::find_merchant_address(&owner, "Roastery").0);
+    register(&mut s, "COFFEE SHOP").unwrap();
+}
+
+#[test]
+fn closing_a_merchant_releases_its_name() {
+    let mut s = setup();
+    register(&mut s, "Coffee Shop").unwrap();
+    let claim_address = pda::find_merchant_name_address(&s.owner.pubkey(), "Coffee Shop").0;
+    close(&mut s, "Coffee Shop").unwrap();
+    assert!(is_closed(&s.h, &claim_address));
+
+    register(&mut s, "coffee shop").unwrap();
+    let claim: MerchantName = s.h.account(&claim_address);
+    assert_eq!(claim.merchant, pda::find_merchant_address(&s.owner.pubkey(), "coffee shop").0);
+}
+
+#[test]
+fn buyers_cannot_take_a_name_they_already_use() {
+    let mut s = setup();
+    register(&mut s, "Shop").unwrap();
+    let buyer = s.h.funded(LAMPORTS_PER_SOL);
+    let ix = register_ix(&s.h, &buyer.pubkey(), "SHOP");
+    s.h.send(&[ix], &buyer, &[]).unwrap();
+
+    let owner = s.owner.insecure_clone();
+    let ix = instructions::transfer_merchant_ownership(&owner.pubkey(), "Shop", buyer.pubkey());
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    let merchant: Merchant = s.h.account(&pda::find_merchant_address(&owner.pubkey(), "Shop").0);
+    let ix = instructions::accept_merchant_ownership(&buyer.pubkey(), &merchant);
+    let err = s.h.send(&[ix], &buyer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NameAlreadyUsed));
+}
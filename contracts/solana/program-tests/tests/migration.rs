@@ -0,0 +1,214 @@
+//! Accounts written by a release before layout versioning: built byte by byte,
+//! migrated, and then used for a payment.
+
+use anchor_lang::system_program;
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator};
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::layout;
+use orki_gateway::state::{GlobalState, Merchant, Payment};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const NAME: &str = "Legacy";
+
+// Size of a Merchant before category, metadata_uri, settlement_mode and version
+const LEGACY_MERCHANT_LEN: usize = 277;
+
+/// A merchant as the pre-versioning program stored it: fields through
+/// token_rule_set, then zeroed reserved bytes. It predates the token rule, so
+/// a default settlement_token means it takes any token.
+fn legacy_merchant(owner: &Pubkey, settlement_wallet: &Pubkey, bump: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(LEGACY_MERCHANT_LEN);
+    data.extend_from_slice(Merchant::DISCRIMINATOR);
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(settlement_wallet.as_ref());
+    data.extend_from_slice(Pubkey::default().as_ref()); // settlement_token
+    data.push(0); // swap_enabled
+    data.extend_from_slice(&(NAME.len() as u32).to_le_bytes());
+    data.extend_from_slice(NAME.as_bytes());
+    data.push(bump);
+    data.push(0); // sandbox
+    data.push(0); // fee_bps_override: None
+    data.push(0); // escrow_enabled
+    data.push(1); // active
+    data.push(0); // admin_locked
+    data.extend_from_slice(Pubkey::default().as_ref()); // previous_merchant
+    data.extend_from_slice(&0u32.to_le_bytes()); // open_invoices
+    data.extend_from_slice(&0u32.to_le_bytes()); // funded_escrows
+    data.push(0); // split_enabled
+    data.push(0); // accepted_tokens_enabled
+    data.extend_from_slice(&0u64.to_le_bytes()); // min_amount
+    data.extend_from_slice(&0u64.to_le_bytes()); // max_amount
+    data.extend_from_slice(&0u32.to_le_bytes()); // operators
+    data.push(0); // fee_exempt
+    data.extend_from_slice(Pubkey::default().as_ref()); // referrer
+    data.extend_from_slice(&0u16.to_le_bytes()); // referrer_bps
+    data.push(0); // accepts_any_token
+    data.push(0); // token_rule_set
+    data.resize(LEGACY_MERCHANT_LEN, 0);
+    data
+}
+
+fn store(h: &mut Harness, address: Pubkey, data: Vec<u8>) {
+    let lamports = h.svm.minimum_balance_for_rent_exemption(data.len());
+    h.svm
+        .set_account(address, Account { lamports, data, owner: orki_gateway::ID, executable: false, rent_epoch: 0 })
+        .unwrap();
+}
+
+fn pay(payer: &Pubkey, owner: &Pubkey, settlement_wallet: &Pubkey, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        payer,
+        ProcessPaymentArgs {
+            merchant_owner: *owner,
+            merchant_name: NAME.to_string(),
+            settlement_wallet: *settlement_wallet,
+            asset: PaymentAsset::Sol,
+            amount: LAMPORTS_PER_SOL,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn migrate_merchant(owner: &Pubkey, merchant: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateMerchant {
+            merchant,
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::MigrateMerchant { name: NAME.to_string() },
+    )
+}
+
+#[test]
+fn migrates_legacy_accounts_then_takes_a_payment() {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+
+    // A live GlobalState from before versioning has the version byte zeroed
+    let global_state = pda::find_global_state_address().0;
+    let mut state: GlobalState = h.account(&global_state);
+    state.version = 0;
+    let mut data = Vec::with_capacity(layout::global_state::LEN);
+    state.try_serialize(&mut data).unwrap();
+    store(&mut h, global_state, data);
+
+    let (merchant, bump) = pda::find_merchant_address(&owner.pubkey(), NAME);
+    store(&mut h, merchant, legacy_merchant(&owner.pubkey(), &settlement.pubkey(), bump));
+
+    let err = h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &settlement.pubkey(), 1)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::AccountNeedsMigration));
+
+    let migrate_state = build(
+        accounts::MigrateGlobalState {
+            global_state,
+            admin: h.admin.pubkey(),
+            system_program: system_program::ID,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::MigrateGlobalState {},
+    );
+    h.send_as_admin(&[migrate_state]).unwrap();
+    let state: GlobalState = h.account(&global_state);
+    assert_eq!(state.version, GlobalState::VERSION);
+    assert_eq!(state.fee_bps, 100);
+
+    // The gateway is current now, but the merchant still is not
+    let err = h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &settlement.pubkey(), 1)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::AccountNeedsMigration));
+
+    // Only the owner's seeds derive the merchant address
+    let stranger = h.funded(LAMPORTS_PER_SOL);
+    assert!(h.send(&[migrate_merchant(&stranger.pubkey(), merchant)], &stranger, &[]).is_err());
+
+    // A merchant this old also predates the stats account payments update
+    let init_stats = build(
+        accounts::InitMerchantStats {
+            merchant,
+            merchant_stats: pda::find_merchant_stats_address(&merchant).0,
+            owner: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::InitMerchantStats { name: NAME.to_string() },
+    );
+    h.send(&[migrate_merchant(&owner.pubkey(), merchant), init_stats], &owner, &[]).unwrap();
+
+    let info = h.svm.get_account(&merchant).unwrap();
+    assert_eq!(info.data.len(), layout::merchant::LEN);
+    assert_eq!(info.lamports, h.svm.minimum_balance_for_rent_exemption(layout::merchant::LEN));
+    let migrated = Merchant::try_deserialize(&mut info.data.as_slice()).unwrap();
+    assert_eq!(migrated.version, Merchant::VERSION);
+    assert_eq!(migrated.name, NAME);
+    assert!(migrated.token_rule_set);
+    assert!(migrated.accepts_any_token);
+    assert_eq!(migrated.metadata_uri, "");
+
+    // A second run has nothing to do
+    let err = h.send(&[migrate_merchant(&owner.pubkey(), merchant)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MerchantAlreadyMigrated));
+
+    let settlement_before = h.balance(&settlement.pubkey());
+    h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &settlement.pubkey(), 1)], &payer, &[]).unwrap();
+    assert_eq!(h.balance(&settlement.pubkey()) - settlement_before, LAMPORTS_PER_SOL * 99 / 100);
+
+    let payment: Payment = h.account(&pda::find_payment_address(&payer.pubkey(), 1).0);
+    assert_eq!(payment.merchant, merchant);
+    assert_eq!(payment.amount, LAMPORTS_PER_SOL);
+}
+
+// Size of a version 1 Merchant, before confirmation_message and the counters after it
+const V1_MERCHANT_LEN: usize = 409;
+
+#[test]
+fn grows_version_1_merchants_to_the_current_layout() {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, NAME, owner.pubkey());
+
+    // Rewrite it as version 1 stored it: the same fields in a 409-byte account
+    let mut v1: Merchant = h.account(&merchant);
+    v1.version = 1;
+    let mut data = Vec::with_capacity(V1_MERCHANT_LEN);
+    v1.try_serialize(&mut data).unwrap();
+    data.resize(V1_MERCHANT_LEN, 0);
+    store(&mut h, merchant, data);
+
+    let err = h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &owner.pubkey(), 1)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::AccountNeedsMigration));
+
+    h.send(&[migrate_merchant(&owner.pubkey(), merchant)], &owner, &[]).unwrap();
+
+    let info = h.svm.get_account(&merchant).unwrap();
+    assert_eq!(info.data.len(), layout::merchant::LEN);
+    assert_eq!(info.lamports, h.svm.minimum_balance_for_rent_exemption(layout::merchant::LEN));
+    let migrated = Merchant::try_deserialize(&mut info.data.as_slice()).unwrap();
+    assert_eq!(migrated.version, Merchant::VERSION);
+    assert_eq!(migrated.name, NAME);
+    assert_eq!(migrated.confirmation_message, "");
+    assert_eq!(migrated.min_interval_per_payer, 0);
+    assert_eq!(migrated.settlement_routes, 0);
+    assert_eq!(migrated.discount_codes, 0);
+
+    h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &owner.pubkey(), 1)], &payer, &[]).unwrap();
+    let payment: Payment = h.account(&pda::find_payment_address(&payer.pubkey(), 1).0);
+    assert_eq!(payment.merchant, merchant);
+}
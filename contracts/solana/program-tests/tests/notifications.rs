@@ -0,0 +1,170 @@
+//! The per-merchant notification queue: payments that pass it append
+//! {payment_id, amount, timestamp}, the oldest entries are overwritten once
+//! it is full, and only the owner clears it.
+
+use anchor_lang::system_program;
+use orki_gateway::state::{NotificationQueue, NOTIFICATION_QUEUE_LEN};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{already_in_use, instructions, pda, Harness};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+    queue: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    h.send(&[instructions::init_notification_queue(&owner.pubkey(), MERCHANT)], &owner, &[]).unwrap();
+    let queue = pda::find_notification_queue_address(&merchant).0;
+    Setup { h, owner, settlement, payer, merchant, queue }
+}
+
+// A plain SOL payment that also appends to the merchant's queue
+fn pay(s: &Setup, payment_id: u64, amount: u64) -> Instruction {
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer: s.payer.pubkey(),
+            merchant_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&s.payer.pubkey(), payment_id).0,
+            blocklist_entry: pda::find_blocked_address(&s.payer.pubkey()).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: None,
+            escrow_balance: None,
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: Some(s.queue),
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount,
+            payment_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn queue(s: &Setup) -> NotificationQueue {
+    s.h.account(&s.queue)
+}
+
+#[test]
+fn overflowing_the_queue_overwrites_the_oldest_entries() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let overflow = 5;
+    let payments = (NOTIFICATION_QUEUE_LEN + overflow) as u64;
+    for payment_id in 1..=payments {
+        let ix = pay(&s, payment_id, 10_000 + payment_id);
+        s.h.send(&[ix], &payer, &[]).unwrap();
+    }
+
+    let queue = queue(&s);
+    assert_eq!(queue.count as usize, NOTIFICATION_QUEUE_LEN);
+    assert_eq!(queue.total, payments);
+    assert_eq!(queue.next as usize, overflow);
+
+    // Payments 1..=5 were overwritten; the rest come back oldest first
+    let notifications = queue.notifications();
+    let ids: Vec<u64> = notifications.iter().map(|n| n.payment_id).collect();
+    let expected: Vec<u64> = (overflow as u64 + 1..=payments).collect();
+    assert_eq!(ids, expected);
+    assert!(notifications.iter().all(|n| n.amount == 10_000 + n.payment_id && n.timestamp > 0));
+}
+
+#[test]
+fn only_the_owner_clears_the_queue() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    for payment_id in 1..=3 {
+        let ix = pay(&s, payment_id, LAMPORTS_PER_SOL / 10);
+        s.h.send(&[ix], &payer, &[]).unwrap();
+    }
+
+    let clear = |owner: &Pubkey| {
+        build(
+            accounts::ClearNotifications {
+                merchant: s.merchant,
+                notification_queue: s.queue,
+                owner: *owner,
+            },
+            instruction::ClearNotifications { name: MERCHANT.to_string() },
+        )
+    };
+    let err = s.h.send(&[clear(&payer.pubkey())], &payer, &[]).unwrap_err();
+    let has_one = anchor_lang::error::ErrorCode::ConstraintHasOne as u32;
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::Custom(has_one)));
+
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[instructions::clear_notifications(&owner.pubkey(), MERCHANT)], &owner, &[]).unwrap();
+    let cleared = queue(&s);
+    assert_eq!(cleared.count, 0);
+    assert!(cleared.notifications().is_empty());
+    assert_eq!(cleared.total, 3);
+
+    // Numbering carries on after a clear
+    let ix = pay(&s, 4, LAMPORTS_PER_SOL / 10);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    let queue = queue(&s);
+    assert_eq!(queue.notifications().iter().map(|n| n.payment_id).collect::<Vec<_>>(), vec![4]);
+    assert_eq!(queue.total, 4);
+}
+
+#[test]
+fn a_queue_is_created_once() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[instructions::init_notification_queue(&owner.pubkey(), MERCHANT)], &owner, &[]).unwrap_err();
+    assert_eq!(err, already_in_use());
+    assert_eq!(queue(&s).merchant, s.merchant);
+}
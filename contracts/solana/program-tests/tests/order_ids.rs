@@ -0,0 +1,197 @@
+//! Order-keyed payments: process_payment_v2 records a payment under a 32-byte
+//! order id, typically the SHA-256 of an order UUID, next to the u64-keyed
+//! records of process_payment.
+
+use anchor_lang::system_program;
+use orki_gateway::constants::DEFAULT_FINALITY_SLOTS;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Payment, PaymentStatus};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::events::GatewayEvent;
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const ORDER: &str = "0b7e8d5c-3f0a-4c1e-9a57-6a8f2d4e1b90";
+const AMOUNT: u64 = LAMPORTS_PER_SOL / 10;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn args(s: &Setup, payment_id: u64) -> ProcessPaymentArgs {
+    ProcessPaymentArgs {
+        merchant_owner: s.owner.pubkey(),
+        merchant_name: MERCHANT.to_string(),
+        settlement_wallet: s.settlement.pubkey(),
+        asset: PaymentAsset::Sol,
+        amount: AMOUNT,
+        payment_id,
+        memo: None,
+        tip: None,
+        discount_code: None,
+        valid_until: 0,
+    }
+}
+
+fn pay_order(s: &Setup, order_id: [u8; 32]) -> Instruction {
+    instructions::process_payment_v2(&s.payer.pubkey(), order_id, args(s, 0))
+}
+
+fn send(s: &mut Setup, ix: Instruction) -> Result<(), TransactionError> {
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).map(|_| ())
+}
+
+#[test]
+fn order_references_hash_to_a_fixed_id() {
+    let id = pda::order_id(ORDER);
+    assert_eq!(id, pda::order_id(ORDER));
+    assert_ne!(id, pda::order_id("0b7e8d5c-3f0a-4c1e-9a57-6a8f2d4e1b91"));
+    let payer = Pubkey::new_unique();
+    assert_eq!(pda::find_order_reference_address(&payer, ORDER), pda::find_order_payment_address(&payer, &id));
+}
+
+#[test]
+fn records_and_emits_the_order_id() {
+    let mut s = setup();
+    let order_id = pda::order_id(ORDER);
+    let payer = s.payer.insecure_clone();
+    let events = s.h.send_for_events(&[pay_order(&s, order_id)], &payer, &[]).unwrap();
+
+    let address = pda::find_order_payment_address(&payer.pubkey(), &order_id).0;
+    let payment: Payment = s.h.account(&address);
+    assert_eq!(payment.order_id, order_id);
+    assert_eq!((payment.payment_id, payment.amount), (0, AMOUNT));
+    assert_eq!(payment.merchant, s.merchant);
+    assert_eq!(payment.status, PaymentStatus::Completed);
+
+    let processed = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::PaymentProcessed(e) => Some(e),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(processed.order_id, order_id);
+    assert_eq!(processed.payment_account, address);
+}
+
+#[test]
+fn both_id_formats_coexist_for_one_payer() {
+    let mut s = setup();
+    // An order id whose first bytes spell out u64 id 7 still lands elsewhere
+    let mut order_id = [0; 32];
+    order_id[..8].copy_from_slice(&7u64.to_le_bytes());
+    let ix = instructions::process_payment(&s.payer.pubkey(), args(&s, 7));
+    send(&mut s, ix).unwrap();
+    let ix = pay_order(&s, order_id);
+    send(&mut s, ix).unwrap();
+
+    let payer = s.payer.pubkey();
+    let by_u64 = pda::find_payment_address(&payer, 7).0;
+    let by_order = pda::find_order_payment_address(&payer, &order_id).0;
+    assert_ne!(by_u64, by_order);
+    assert_eq!(s.h.account::<Payment>(&by_u64).order_id, [0; 32]);
+    assert_eq!(s.h.account::<Payment>(&by_order).order_id, order_id);
+}
+
+#[test]
+fn rejects_a_reused_or_empty_order_id() {
+    let mut s = setup();
+    let order_id = pda::order_id(ORDER);
+    let ix = pay_order(&s, order_id);
+    send(&mut s, ix).unwrap();
+    let ix = pay_order(&s, order_id);
+    let err = send(&mut s, ix).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::DuplicatePayment));
+
+    let ix = pay_order(&s, [0; 32]);
+    let err = send(&mut s, ix).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidOrderId));
+}
+
+#[test]
+fn the_record_must_sit_at_the_order_address() {
+    let mut s = setup();
+    let order_id = pda::order_id(ORDER);
+    let mut ix = pay_order(&s, order_id);
+    // Pointing the order id at the u64 record's address
+    let expected = pda::find_order_payment_address(&s.payer.pubkey(), &order_id).0;
+    let meta = ix.accounts.iter_mut().find(|m| m.pubkey == expected).unwrap();
+    meta.pubkey = pda::find_payment_address(&s.payer.pubkey(), 1).0;
+    let err = send(&mut s, ix).unwrap_err();
+    let seeds = anchor_lang::error::ErrorCode::ConstraintSeeds as u32;
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::Custom(seeds)));
+    assert!(s.h.svm.get_account(&expected).is_none());
+}
+
+#[test]
+fn prefunded_record_addresses_still_open() {
+    // Lamports sent to the address ahead of time count towards its rent
+    let mut s = setup();
+    let order_id = pda::order_id(ORDER);
+    let address = pda::find_order_payment_address(&s.payer.pubkey(), &order_id).0;
+    s.h.svm.airdrop(&address, 1).unwrap();
+    let ix = pay_order(&s, order_id);
+    send(&mut s, ix).unwrap();
+    assert_eq!(s.h.account::<Payment>(&address).order_id, order_id);
+}
+
+#[test]
+fn order_payments_refund_and_finalize() {
+    let mut s = setup();
+    let order_id = pda::order_id(ORDER);
+    let ix = pay_order(&s, order_id);
+    send(&mut s, ix).unwrap();
+    let address = pda::find_order_payment_address(&s.payer.pubkey(), &order_id).0;
+
+    let refund = build(
+        accounts::RefundPartial {
+            merchant: s.merchant,
+            payment: address,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            global_stats: pda::find_global_stats_address().0,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payer: s.payer.pubkey(),
+            system_program: system_program::ID,
+            token_program: None,
+            mint: None,
+            authority_token_account: None,
+            payer_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::RefundPartial { name: MERCHANT.to_string(), amount: AMOUNT / 2 },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[refund], &owner, &[]).unwrap();
+    assert_eq!(s.h.account::<Payment>(&address).refunded_amount, AMOUNT / 2);
+
+    let slot = s.h.svm.get_sysvar::<Clock>().slot;
+    s.h.svm.warp_to_slot(slot + DEFAULT_FINALITY_SLOTS);
+    let ix = instructions::finalize_order_payment(&s.payer.pubkey(), order_id, None);
+    send(&mut s, ix).unwrap();
+    assert_eq!(s.h.account::<Payment>(&address).status, PaymentStatus::Finalized);
+}
@@ -0,0 +1,226 @@
+//! Merchant ownership transfer: the owner proposes, the new owner accepts, and
+//! the merchant moves to the new owner's address with its stats and directory
+//! slot while the old accounts close to the old owner.
+
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, MerchantIndex, MerchantStats};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::events::GatewayEvent;
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{already_in_use, instructions, pda, program_error, Harness};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    buyer: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let buyer = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, buyer, settlement, payer, merchant }
+}
+
+fn pay(s: &Setup, merchant_owner: Pubkey, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner,
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement.pubkey(),
+            asset: PaymentAsset::Sol,
+            amount: LAMPORTS_PER_SOL / 10,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn propose(s: &mut Setup, new_owner: Pubkey) -> Result<(), TransactionError> {
+    let owner = s.owner.insecure_clone();
+    let ix = instructions::transfer_merchant_ownership(&owner.pubkey(), MERCHANT, new_owner);
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+fn accept(s: &Setup, signer: &Pubkey) -> Instruction {
+    let merchant: Merchant = s.h.account(&s.merchant);
+    instructions::accept_merchant_ownership(signer, &merchant)
+}
+
+fn is_closed(h: &Harness, address: &Pubkey) -> bool {
+    h.svm.get_account(address).is_none_or(|a| a.lamports == 0)
+}
+
+#[test]
+fn two_step_handshake_moves_the_merchant() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, s.owner.pubkey(), 1);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    let buyer = s.buyer.insecure_clone();
+    propose(&mut s, buyer.pubkey()).unwrap();
+    let transfer = pda::find_merchant_transfer_address(&s.merchant).0;
+    let old_stats = pda::find_merchant_stats_address(&s.merchant).0;
+    let rent = s.h.balance(&s.merchant) + s.h.balance(&old_stats) + s.h.balance(&transfer);
+    let owner_before = s.h.balance(&s.owner.pubkey());
+
+    let ix = accept(&s, &buyer.pubkey());
+    let events = s.h.send_for_events(&[ix], &buyer, &[]).unwrap();
+    let new_merchant = pda::find_merchant_address(&buyer.pubkey(), MERCHANT).0;
+    let transferred = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::MerchantOwnershipTransferred(e) => Some(e),
+            _ => None,
+        })
+        .expect("MerchantOwnershipTransferred");
+    assert_eq!(transferred.old_owner, s.owner.pubkey());
+    assert_eq!(transferred.new_owner, buyer.pubkey());
+    assert_eq!(transferred.old_merchant, s.merchant);
+    assert_eq!(transferred.new_merchant, new_merchant);
+
+    let moved: Merchant = s.h.account(&new_merchant);
+    assert_eq!(moved.owner, buyer.pubkey());
+    assert_eq!(moved.name, MERCHANT);
+    assert_eq!(moved.settlement_wallet, s.settlement.pubkey());
+    assert_eq!(moved.previous_merchant, s.merchant);
+    assert_eq!(moved.bump, pda::find_merchant_address(&buyer.pubkey(), MERCHANT).1);
+
+    let stats: MerchantStats = s.h.account(&pda::find_merchant_stats_address(&new_merchant).0);
+    assert_eq!(stats.merchant, new_merchant);
+    assert_eq!(stats.payment_count, 1);
+    let entry: MerchantIndex = s.h.account(&pda::find_merchant_index_address(moved.merchant_index).0);
+    assert_eq!(entry.merchant, new_merchant);
+
+    // The old merchant, its stats and the proposal all close to the old owner
+    for address in [s.merchant, old_stats, transfer] {
+        assert!(is_closed(&s.h, &address));
+    }
+    assert_eq!(s.h.balance(&s.owner.pubkey()) - owner_before, rent);
+
+    // The merchant takes payments under its new address
+    let ix = pay(&s, buyer.pubkey(), 2);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+}
+
+#[test]
+fn only_the_named_owner_can_accept() {
+    let mut s = setup();
+    let buyer = s.buyer.insecure_clone();
+    propose(&mut s, buyer.pubkey()).unwrap();
+
+    let stranger = s.h.funded(LAMPORTS_PER_SOL);
+    let ix = accept(&s, &stranger.pubkey());
+    let err = s.h.send(&[ix], &stranger, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::Unauthorized));
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).owner, s.owner.pubkey());
+
+    // The named owner still can
+    let ix = accept(&s, &buyer.pubkey());
+    s.h.send(&[ix], &buyer, &[]).unwrap();
+}
+
+#[test]
+fn proposals_can_be_cancelled_and_are_one_at_a_time() {
+    let mut s = setup();
+    let owner_key = s.owner.pubkey();
+    assert_eq!(propose(&mut s, owner_key).unwrap_err(), program_error(ErrorCode::CannotTransferToSelf));
+
+    let buyer = s.buyer.insecure_clone();
+    propose(&mut s, buyer.pubkey()).unwrap();
+    assert_eq!(propose(&mut s, Pubkey::new_unique()).unwrap_err(), already_in_use());
+
+    let owner = s.owner.insecure_clone();
+    let transfer = pda::find_merchant_transfer_address(&s.merchant).0;
+    let cancel = build(
+        accounts::CancelMerchantOwnershipTransfer {
+            merchant: s.merchant,
+            merchant_transfer: transfer,
+            owner: owner.pubkey(),
+        },
+        instruction::CancelMerchantOwnershipTransfer { name: MERCHANT.to_string() },
+    );
+    s.h.send(&[cancel], &owner, &[]).unwrap();
+    assert!(is_closed(&s.h, &transfer));
+
+    // Nothing left to accept
+    let ix = accept(&s, &buyer.pubkey());
+    assert!(s.h.send(&[ix], &buyer, &[]).is_err());
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).owner, owner.pubkey());
+}
+
+#[test]
+fn payments_to_the_old_address_fail_after_the_transfer() {
+    let mut s = setup();
+    let buyer = s.buyer.insecure_clone();
+    propose(&mut s, buyer.pubkey()).unwrap();
+    let ix = accept(&s, &buyer.pubkey());
+    s.h.send(&[ix], &buyer, &[]).unwrap();
+
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, s.owner.pubkey(), 1);
+    let err = s.h.send(&[ix], &payer, &[]).unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(anchor_lang::error::ErrorCode::AccountNotInitialized as u32)
+        )
+    );
+    assert!(is_closed(&s.h, &pda::find_payment_address(&payer.pubkey(), 1).0));
+}
+
+#[test]
+fn a_resale_waits_until_the_previous_address_is_released() {
+    let mut s = setup();
+    let buyer = s.buyer.insecure_clone();
+    propose(&mut s, buyer.pubkey()).unwrap();
+    let ix = accept(&s, &buyer.pubkey());
+    s.h.send(&[ix], &buyer, &[]).unwrap();
+    let bought = pda::find_merchant_address(&buyer.pubkey(), MERCHANT).0;
+
+    // Selling on would leave refunds of the first owner's payments unmatched
+    let third = s.h.funded(LAMPORTS_PER_SOL);
+    let ix = instructions::transfer_merchant_ownership(&buyer.pubkey(), MERCHANT, third.pubkey());
+    s.h.send(&[ix], &buyer, &[]).unwrap();
+    let resale = |s: &Setup| instructions::accept_merchant_ownership(&third.pubkey(), &s.h.account(&bought));
+    let err = s.h.send(&[resale(&s)], &third, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::PreviousMerchantNotReleased));
+
+    let release = || build(
+        accounts::ReleasePreviousMerchant {
+            merchant: bought,
+            owner: buyer.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ReleasePreviousMerchant { name: MERCHANT.to_string() },
+    );
+    s.h.send(&[release()], &buyer, &[]).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&bought).previous_merchant, Pubkey::default());
+    let err = s.h.send(&[release()], &buyer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NoPreviousMerchant));
+
+    s.h.send(&[resale(&s)], &third, &[]).unwrap();
+    let moved: Merchant = s.h.account(&pda::find_merchant_address(&third.pubkey(), MERCHANT).0);
+    assert_eq!(moved.previous_merchant, bought);
+}
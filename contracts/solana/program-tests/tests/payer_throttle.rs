@@ -0,0 +1,195 @@
+//! Per-payer throttle: a merchant that sets min_interval_per_payer takes one
+//! payment per payer per interval, tracked in the payer's PayerThrottle, which
+//! the payer can close for its rent once the interval has passed.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, PayerThrottle};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{pda, program_error, Harness, LAMPORTS_PER_SIGNATURE};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Arcade";
+const START: i64 = 1_700_000_000;
+const INTERVAL: u32 = 60;
+const AMOUNT: u64 = 1_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn set_interval(s: &mut Setup, min_interval_per_payer: u32) {
+    let ix = build(
+        accounts::SetPayerInterval {
+            merchant: s.merchant,
+            owner: s.owner.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetPayerInterval { name: MERCHANT.to_string(), min_interval_per_payer },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[ix], &owner, &[]).unwrap();
+}
+
+fn throttle_address(s: &Setup) -> Pubkey {
+    pda::find_payer_throttle_address(&s.merchant, &s.payer.pubkey()).0
+}
+
+fn pay(s: &Setup, payment_id: u64, throttle: bool) -> Instruction {
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer: s.payer.pubkey(),
+            merchant_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&s.payer.pubkey(), payment_id).0,
+            blocklist_entry: pda::find_blocked_address(&s.payer.pubkey()).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: None,
+            escrow_balance: None,
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: throttle.then(|| throttle_address(s)),
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount: AMOUNT,
+            payment_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn close_throttle(s: &Setup) -> Instruction {
+    build(
+        accounts::ClosePayerThrottle {
+            payer_throttle: throttle_address(s),
+            merchant: s.merchant,
+            payer: s.payer.pubkey(),
+        },
+        instruction::ClosePayerThrottle {},
+    )
+}
+
+#[test]
+fn a_second_payment_waits_out_the_interval() {
+    let mut s = setup();
+    set_interval(&mut s, INTERVAL);
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).min_interval_per_payer, INTERVAL);
+    let payer = s.payer.insecure_clone();
+
+    s.h.send(&[pay(&s, 1, true)], &payer, &[]).unwrap();
+    let throttle: PayerThrottle = s.h.account(&throttle_address(&s));
+    assert_eq!((throttle.merchant, throttle.payer), (s.merchant, payer.pubkey()));
+    assert_eq!(throttle.last_payment_at, START);
+
+    s.h.set_time(START + i64::from(INTERVAL) - 1);
+    let err = s.h.send(&[pay(&s, 2, true)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::RateLimited));
+
+    s.h.set_time(START + i64::from(INTERVAL));
+    s.h.send(&[pay(&s, 2, true)], &payer, &[]).unwrap();
+    assert_eq!(s.h.account::<PayerThrottle>(&throttle_address(&s)).last_payment_at, START + i64::from(INTERVAL));
+}
+
+#[test]
+fn each_payer_has_its_own_throttle() {
+    let mut s = setup();
+    set_interval(&mut s, INTERVAL);
+    let first = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, true)], &first, &[]).unwrap();
+
+    s.payer = s.h.funded(LAMPORTS_PER_SOL);
+    let second = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, true)], &second, &[]).unwrap();
+}
+
+#[test]
+fn unthrottled_merchants_need_no_extra_account() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, false)], &payer, &[]).unwrap();
+    s.h.send(&[pay(&s, 2, false)], &payer, &[]).unwrap();
+
+    // Once throttled the payer's throttle has to come along
+    set_interval(&mut s, INTERVAL);
+    let err = s.h.send(&[pay(&s, 3, false)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MissingAccount));
+    s.h.send(&[pay(&s, 3, true)], &payer, &[]).unwrap();
+
+    // Lifting it drops the requirement again
+    set_interval(&mut s, 0);
+    s.h.send(&[pay(&s, 4, false)], &payer, &[]).unwrap();
+}
+
+#[test]
+fn the_payer_closes_the_throttle_once_the_interval_passed() {
+    let mut s = setup();
+    set_interval(&mut s, INTERVAL);
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, true)], &payer, &[]).unwrap();
+    let throttle = throttle_address(&s);
+
+    // Closing early would let the next payment skip the wait
+    let err = s.h.send(&[close_throttle(&s)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::RateLimited));
+
+    s.h.set_time(START + i64::from(INTERVAL));
+    let rent = s.h.balance(&throttle);
+    let before = s.h.balance(&payer.pubkey());
+    s.h.send(&[close_throttle(&s)], &payer, &[]).unwrap();
+    assert!(s.h.svm.get_account(&throttle).is_none_or(|a| a.lamports == 0));
+    assert_eq!(s.h.balance(&payer.pubkey()), before + rent - LAMPORTS_PER_SIGNATURE);
+}
@@ -0,0 +1,137 @@
+//! Payment links: pay_link charges the link's amount through process_payment,
+//! so the fee lands in the fee vault and the record sits at a payment PDA
+//! refund_partial can find, keyed by the link and the use it consumed.
+
+use anchor_lang::system_program;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{FeeStats, Payment, PaymentLink, PaymentStatus};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Cafe";
+const LINK_SEED: u64 = 7;
+const AMOUNT: u64 = 100_000;
+// The 1% setup() charges
+const FEE: u64 = AMOUNT / 100;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+    link: Pubkey,
+}
+
+// A merchant settling to its owner, with a two-use SOL link
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, owner.pubkey());
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let link = pda::find_link_address(&merchant, LINK_SEED).0;
+
+    let ix = build(
+        accounts::CreateLink { merchant, link, owner: owner.pubkey(), system_program: system_program::ID },
+        instruction::CreateLink {
+            name: MERCHANT.to_string(),
+            link_seed: LINK_SEED,
+            amount: AMOUNT,
+            mint: Pubkey::default(),
+            label: "Coffee".to_string(),
+            max_uses: 2,
+            expires_at: 0,
+        },
+    );
+    h.send(&[ix], &owner, &[]).unwrap();
+    Setup { h, owner, payer, merchant, link }
+}
+
+fn pay(s: &Setup, use_index: u64) -> Instruction {
+    instructions::pay_link(
+        &s.payer.pubkey(),
+        LINK_SEED,
+        use_index,
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.owner.pubkey(),
+            asset: PaymentAsset::Sol,
+            amount: 0,
+            payment_id: 0,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn refund(s: &Setup, payment: Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::RefundPartial {
+            merchant: s.merchant,
+            payment,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            global_stats: pda::find_global_stats_address().0,
+            authority: s.owner.pubkey(),
+            operator: None,
+            payer: s.payer.pubkey(),
+            system_program: system_program::ID,
+            token_program: None,
+            mint: None,
+            authority_token_account: None,
+            payer_token_account: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::RefundPartial { name: MERCHANT.to_string(), amount },
+    )
+}
+
+#[test]
+fn link_payments_pay_the_fee_vault_and_are_refunded() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let fee_stats = pda::find_fee_stats_address(&Pubkey::default()).0;
+    let fees_before = s.h.account::<FeeStats>(&fee_stats).total_fees_collected;
+    let vault_before = s.h.balance(&pda::find_fee_vault_address().0);
+
+    s.h.send(&[pay(&s, 0)], &payer, &[]).unwrap();
+    assert_eq!(s.h.account::<PaymentLink>(&s.link).uses, 1);
+    assert_eq!(s.h.balance(&pda::find_fee_vault_address().0) - vault_before, FEE);
+    assert_eq!(s.h.account::<FeeStats>(&fee_stats).total_fees_collected - fees_before, FEE);
+
+    let payment = pda::find_link_payment_address(&s.link, &payer.pubkey(), 0).0;
+    let record: Payment = s.h.account(&payment);
+    assert_eq!((record.amount, record.fee, record.payment_id), (AMOUNT, FEE, 0));
+    assert_eq!(record.status, PaymentStatus::Completed);
+
+    let before = s.h.balance(&payer.pubkey());
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[refund(&s, payment, AMOUNT / 2)], &owner, &[]).unwrap();
+    assert_eq!(s.h.balance(&payer.pubkey()) - before, AMOUNT / 2);
+    assert_eq!(s.h.account::<Payment>(&payment).refunded_amount, AMOUNT / 2);
+}
+
+#[test]
+fn each_use_is_paid_once() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 0)], &payer, &[]).unwrap();
+
+    // A payment built for a use someone else already took misses its record
+    let err = s.h.send(&[pay(&s, 0)], &payer, &[]).unwrap_err();
+    let seeds = anchor_lang::error::ErrorCode::ConstraintSeeds as u32;
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::Custom(seeds)));
+
+    s.h.send(&[pay(&s, 1)], &payer, &[]).unwrap();
+    let err = s.h.send(&[pay(&s, 2)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::LinkExhausted));
+}
@@ -0,0 +1,216 @@
+//! Scheduled payouts: an Accrue-mode merchant sets a payout interval on its
+//! balance and anyone may crank the payout once the interval has passed,
+//! collecting the gateway's crank reward from the fee vault.
+
+use anchor_lang::system_program;
+use orki_gateway::constants::MIN_PAYOUT_INTERVAL;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{MerchantBalance, SettlementMode};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const START: i64 = 1_700_000_000;
+const INTERVAL: u32 = 6 * MIN_PAYOUT_INTERVAL;
+const AMOUNT: u64 = LAMPORTS_PER_SOL;
+// 1% of AMOUNT stays in the fee vault
+const CREDITED: u64 = AMOUNT - AMOUNT / 100;
+const REWARD: u64 = 10_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    payer: Keypair,
+    cranker: Keypair,
+    merchant: Pubkey,
+}
+
+fn balance_address(s: &Setup) -> Pubkey {
+    pda::find_merchant_balance_address(&s.merchant, &Pubkey::default()).0
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL).pubkey();
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let cranker = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement);
+    let mut s = Setup { h, owner, settlement, payer, cranker, merchant };
+
+    let accrue = build(
+        accounts::SetSettlementMode {
+            merchant: s.merchant,
+            owner: s.owner.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetSettlementMode { name: MERCHANT.to_string(), settlement_mode: SettlementMode::Accrue },
+    );
+    let open = build(
+        accounts::OpenMerchantBalance {
+            merchant: s.merchant,
+            vault: pda::find_vault_address(&s.merchant).0,
+            merchant_balance: balance_address(&s),
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::OpenMerchantBalance { name: MERCHANT.to_string(), mint: Pubkey::default() },
+    );
+    let schedule = set_payout_interval(&s, INTERVAL);
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[accrue, open, schedule], &owner, &[]).unwrap();
+    let admin = s.h.admin.pubkey();
+    let reward = build(
+        accounts::AdminAuth {
+            global_state: pda::find_global_state_address().0,
+            admin,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetCrankReward { new_reward: REWARD },
+    );
+    s.h.send_as_admin(&[reward]).unwrap();
+
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    assert_eq!(s.h.account::<MerchantBalance>(&balance_address(&s)).amount, CREDITED);
+    s
+}
+
+fn set_payout_interval(s: &Setup, payout_interval: u32) -> Instruction {
+    build(
+        accounts::SetPayoutInterval {
+            merchant: s.merchant,
+            merchant_balance: balance_address(s),
+            owner: s.owner.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetPayoutInterval { name: MERCHANT.to_string(), mint: Pubkey::default(), payout_interval },
+    )
+}
+
+fn pay(s: &Setup) -> Instruction {
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer: s.payer.pubkey(),
+            merchant_wallet: s.settlement,
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&s.payer.pubkey(), 1).0,
+            blocklist_entry: pda::find_blocked_address(&s.payer.pubkey()).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: Some(pda::find_vault_address(&s.merchant).0),
+            escrow_balance: None,
+            merchant_balance: Some(balance_address(s)),
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount: AMOUNT,
+            payment_id: 1,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+// The payer covers the transaction fee so the cranker's balance moves by the
+// reward alone
+fn crank(s: &mut Setup) -> Result<(), TransactionError> {
+    let ix = instructions::crank_payout(
+        &s.cranker.pubkey(),
+        &s.owner.pubkey(),
+        MERCHANT,
+        s.settlement,
+        PaymentAsset::Sol,
+    );
+    let (payer, cranker) = (s.payer.insecure_clone(), s.cranker.insecure_clone());
+    s.h.send(&[ix], &payer, &[&cranker]).map(|_| ())
+}
+
+#[test]
+fn premature_cranks_are_rejected() {
+    let mut s = setup();
+    s.h.set_time(START + i64::from(INTERVAL) - 1);
+    assert_eq!(crank(&mut s).unwrap_err(), program_error(ErrorCode::PayoutNotDue));
+    assert_eq!(s.h.account::<MerchantBalance>(&balance_address(&s)).amount, CREDITED);
+}
+
+#[test]
+fn a_due_crank_pays_out_and_rewards_the_caller() {
+    let mut s = setup();
+    let due = START + i64::from(INTERVAL);
+    s.h.set_time(due);
+    let (settlement_before, cranker_before) = (s.h.balance(&s.settlement), s.h.balance(&s.cranker.pubkey()));
+    crank(&mut s).unwrap();
+
+    assert_eq!(s.h.balance(&s.settlement) - settlement_before, CREDITED);
+    assert_eq!(s.h.balance(&s.cranker.pubkey()) - cranker_before, REWARD);
+    let balance: MerchantBalance = s.h.account(&balance_address(&s));
+    assert_eq!((balance.amount, balance.last_payout_at), (0, due));
+
+    // The next interval starts at the crank, so a second one has to wait
+    s.h.set_time(due + 1);
+    assert_eq!(crank(&mut s).unwrap_err(), program_error(ErrorCode::PayoutNotDue));
+}
+
+#[test]
+fn schedules_shorter_than_the_minimum_are_rejected() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    let ix = set_payout_interval(&s, MIN_PAYOUT_INTERVAL - 1);
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidPayoutInterval));
+
+    // Turning the schedule off stops cranks altogether
+    let ix = set_payout_interval(&s, 0);
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    s.h.set_time(START + i64::from(INTERVAL));
+    assert_eq!(crank(&mut s).unwrap_err(), program_error(ErrorCode::PayoutNotDue));
+}
@@ -0,0 +1,138 @@
+//! Refunds go back in the asset that was paid: refund_partial rejects another
+//! mint, or lamports for a token payment, before anything counts towards the
+//! payment's refunded amount.
+
+use anchor_lang::system_program;
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::Payment;
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = 100_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+    usdc: Pubkey,
+    junk: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    // The owner settles to itself, so it holds what it refunds
+    let merchant = h.register_merchant(&owner, MERCHANT, owner.pubkey());
+    let payer = h.funded(LAMPORTS_PER_SOL);
+
+    let issuer = h.funded(LAMPORTS_PER_SOL);
+    let usdc = h.create_mint(&issuer, 6);
+    let junk = h.create_mint(&issuer, 6);
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, usdc)]).unwrap();
+    let payer_ata = h.create_ata(&payer.pubkey(), &usdc);
+    h.mint_to(&usdc, &issuer, &payer_ata, AMOUNT);
+    // Worthless tokens the owner could try to refund with
+    let owner_junk = h.create_ata(&owner.pubkey(), &junk);
+    h.mint_to(&junk, &issuer, &owner_junk, AMOUNT);
+    h.create_ata(&payer.pubkey(), &junk);
+
+    Setup { h, owner, payer, merchant, usdc, junk }
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address(owner, mint)
+}
+
+fn pay(s: &mut Setup, payment_id: u64, asset: PaymentAsset) {
+    let ix = instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.owner.pubkey(),
+            asset,
+            amount: AMOUNT,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).unwrap();
+}
+
+/// A refund of payment `payment_id` in `mint`, or in SOL when None.
+fn refund(s: &Setup, payment_id: u64, mint: Option<Pubkey>, amount: u64) -> Instruction {
+    let owner = s.owner.pubkey();
+    let payer = s.payer.pubkey();
+    build(
+        accounts::RefundPartial {
+            merchant: s.merchant,
+            payment: pda::find_payment_address(&payer, payment_id).0,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            global_stats: pda::find_global_stats_address().0,
+            authority: owner,
+            operator: None,
+            payer,
+            system_program: system_program::ID,
+            token_program: mint.map(|_| spl_token::ID),
+            mint,
+            authority_token_account: mint.map(|mint| ata(&owner, &mint)),
+            payer_token_account: mint.map(|mint| ata(&payer, &mint)),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::RefundPartial { name: MERCHANT.to_string(), amount },
+    )
+}
+
+fn send_refund(s: &mut Setup, ix: Instruction) -> Result<(), TransactionError> {
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+#[test]
+fn token_payments_are_refunded_in_their_token_only() {
+    let mut s = setup();
+    let usdc = s.usdc;
+    pay(&mut s, 1, PaymentAsset::Spl { mint: usdc, token_program: spl_token::ID });
+    let payment = pda::find_payment_address(&s.payer.pubkey(), 1).0;
+
+    let ix = refund(&s, 1, Some(s.junk), AMOUNT / 2);
+    assert_eq!(send_refund(&mut s, ix), Err(program_error(ErrorCode::InvalidToken)));
+    let ix = refund(&s, 1, None, AMOUNT / 2);
+    assert_eq!(send_refund(&mut s, ix), Err(program_error(ErrorCode::InvalidToken)));
+    assert_eq!(s.h.account::<Payment>(&payment).refunded_amount, 0);
+
+    let ix = refund(&s, 1, Some(usdc), AMOUNT / 2);
+    send_refund(&mut s, ix).unwrap();
+    assert_eq!(s.h.token_balance(&ata(&s.payer.pubkey(), &usdc)), AMOUNT / 2);
+    assert_eq!(s.h.account::<Payment>(&payment).refunded_amount, AMOUNT / 2);
+}
+
+#[test]
+fn sol_payments_are_not_refunded_in_tokens() {
+    let mut s = setup();
+    pay(&mut s, 1, PaymentAsset::Sol);
+    let ix = refund(&s, 1, Some(s.junk), AMOUNT / 2);
+    assert_eq!(send_refund(&mut s, ix), Err(program_error(ErrorCode::InvalidToken)));
+
+    let before = s.h.balance(&s.payer.pubkey());
+    let ix = refund(&s, 1, None, AMOUNT / 2);
+    send_refund(&mut s, ix).unwrap();
+    assert_eq!(s.h.balance(&s.payer.pubkey()) - before, AMOUNT / 2);
+}
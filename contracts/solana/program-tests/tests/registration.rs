@@ -0,0 +1,88 @@
+//! Retried registrations: an identical register_merchant succeeds without
+//! doing anything, and one with different settings fails with
+//! MerchantAlreadyExists rather than the system program's "already in use".
+
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, MerchantRegistry};
+use orki_gateway_client::instructions::{self, RegisterMerchantArgs};
+use orki_gateway_program_tests::{pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const NAME: &str = "Shop";
+
+fn register(h: &Harness, owner: &Keypair, args: RegisterMerchantArgs) -> Instruction {
+    instructions::register_merchant(&owner.pubkey(), RegisterMerchantArgs { merchant_index: h.next_merchant_index(), ..args })
+}
+
+fn args(settlement_wallet: Pubkey) -> RegisterMerchantArgs {
+    RegisterMerchantArgs {
+        settlement_wallet,
+        settlement_token: Pubkey::default(),
+        name: NAME.to_string(),
+        sandbox: false,
+        referrer: None,
+        referrer_bps: 0,
+        accepts_any_token: true,
+        metadata_uri: "https://shop.example/orki.json".to_string(),
+        category: 3,
+        merchant_index: 0,
+    }
+}
+
+fn setup() -> (Harness, Keypair, Pubkey) {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(10 * LAMPORTS_PER_SOL);
+    let settlement = Pubkey::new_unique();
+    let ix = register(&h, &owner, args(settlement));
+    h.send(&[ix], &owner, &[]).unwrap();
+    (h, owner, settlement)
+}
+
+fn merchant_count(h: &Harness) -> u64 {
+    h.account::<MerchantRegistry>(&pda::find_merchant_registry_address().0).merchant_count
+}
+
+#[test]
+fn identical_retry_succeeds_without_changes() {
+    let (mut h, owner, settlement) = setup();
+    let merchant = pda::find_merchant_address(&owner.pubkey(), NAME).0;
+    let before = h.svm.get_account(&merchant).unwrap();
+    let next_slot = pda::find_merchant_index_address(h.next_merchant_index()).0;
+
+    let ix = register(&h, &owner, args(settlement));
+    let events = h.send_for_events(&[ix], &owner, &[]).unwrap();
+
+    assert!(events.is_empty());
+    assert_eq!(h.svm.get_account(&merchant).unwrap(), before);
+    assert_eq!(merchant_count(&h), 1);
+    assert_eq!(h.account::<Merchant>(&merchant).merchant_index, 1);
+    // The slot opened for the retry was closed again, rent returned
+    assert!(h.svm.get_account(&next_slot).is_none_or(|a| a.lamports == 0));
+
+    // And the next merchant takes that slot as usual
+    let other = h.register_merchant(&owner, "Other", settlement);
+    assert_eq!(h.account::<Merchant>(&other).merchant_index, 2);
+}
+
+#[test]
+fn retry_with_different_settings_is_rejected() {
+    let (mut h, owner, settlement) = setup();
+    let conflicts = [
+        RegisterMerchantArgs { settlement_wallet: Pubkey::new_unique(), ..args(settlement) },
+        RegisterMerchantArgs { settlement_token: Pubkey::new_unique(), accepts_any_token: false, ..args(settlement) },
+        RegisterMerchantArgs { sandbox: true, ..args(settlement) },
+        RegisterMerchantArgs { referrer: Some(Pubkey::new_unique()), referrer_bps: 10, ..args(settlement) },
+        RegisterMerchantArgs { metadata_uri: String::new(), ..args(settlement) },
+        RegisterMerchantArgs { category: 4, ..args(settlement) },
+    ];
+    for conflict in conflicts {
+        let ix = register(&h, &owner, conflict);
+        let err = h.send(&[ix], &owner, &[]).unwrap_err();
+        assert_eq!(err, program_error(ErrorCode::MerchantAlreadyExists));
+    }
+    assert_eq!(merchant_count(&h), 1);
+}
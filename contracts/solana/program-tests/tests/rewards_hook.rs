@@ -0,0 +1,256 @@
+//! Rewards hook: after each payment the gateway calls the merchant's rewards
+//! program, here the example orki_rewards, which awards the payer a point
+//! per 1000 lamports. Tolerant merchants skip the call when the accounts are
+//! left out; required ones reject the payment.
+
+use anchor_lang::{system_program, Discriminator, InstructionData, ToAccountMetas};
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::instructions::ON_PAYMENT_DISCRIMINATOR;
+use orki_gateway::state::{Merchant, Payment, RewardsHook};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, build_with_remaining};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use orki_rewards::Points;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Where `anchor build` puts the example rewards program.
+const REWARDS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/deploy/orki_rewards.so");
+const MERCHANT: &str = "Cafe";
+const AMOUNT: u64 = 250_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+    points: Pubkey,
+}
+
+fn setup(required: bool) -> Setup {
+    let mut h = Harness::new();
+    h.svm
+        .add_program_from_file(orki_rewards::ID, REWARDS_PATH)
+        .unwrap_or_else(|e| panic!("loading {REWARDS_PATH} (run `anchor build` first): {e}"));
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    h.send(&[instructions::set_rewards_hook(&owner.pubkey(), MERCHANT, orki_rewards::ID, required)], &owner, &[])
+        .unwrap();
+
+    let points = Pubkey::find_program_address(
+        &[b"points", merchant.as_ref(), payer.pubkey().as_ref()],
+        &orki_rewards::ID,
+    )
+    .0;
+    Setup { h, owner, settlement, payer, merchant, points }
+}
+
+fn open_points(s: &mut Setup) {
+    let ix = Instruction {
+        program_id: orki_rewards::ID,
+        accounts: orki_rewards::accounts::OpenPoints {
+            payer: s.payer.pubkey(),
+            merchant: s.merchant,
+            points: s.points,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: orki_rewards::instruction::OpenPoints {}.data(),
+    };
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).unwrap();
+}
+
+/// A SOL payment, passing the hook, its program and the payer's points
+/// account when `with_hook` is set.
+fn pay(s: &Setup, payment_id: u64, with_hook: bool) -> Instruction {
+    build_with_remaining(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer: s.payer.pubkey(),
+            merchant_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&Pubkey::default()).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&s.payer.pubkey(), payment_id).0,
+            blocklist_entry: pda::find_blocked_address(&s.payer.pubkey()).0,
+            token_fee: pda::find_token_fee_address(&Pubkey::default()).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: None,
+            escrow_balance: None,
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: with_hook.then(|| pda::find_rewards_hook_address(&s.merchant).0),
+            rewards_program: with_hook.then_some(orki_rewards::ID),
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount: AMOUNT,
+            payment_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+        if with_hook { vec![AccountMeta::new(s.points, false)] } else { vec![] },
+    )
+}
+
+fn points(s: &Setup) -> Points {
+    s.h.account(&s.points)
+}
+
+#[test]
+fn the_example_program_implements_on_payment() {
+    assert_eq!(orki_rewards::instruction::OnPayment::DISCRIMINATOR, ON_PAYMENT_DISCRIMINATOR);
+}
+
+#[test]
+fn payments_award_points_through_the_hook() {
+    let mut s = setup(false);
+    let hook: RewardsHook = s.h.account(&pda::find_rewards_hook_address(&s.merchant).0);
+    assert_eq!(hook.program, orki_rewards::ID);
+    let merchant: Merchant = s.h.account(&s.merchant);
+    assert!(merchant.rewards_enabled && !merchant.rewards_required);
+
+    open_points(&mut s);
+    let payer = s.payer.insecure_clone();
+    for payment_id in 1..=2 {
+        s.h.send(&[pay(&s, payment_id, true)], &payer, &[]).unwrap();
+    }
+    let points = points(&s);
+    assert_eq!(points.balance, 2 * AMOUNT / 1_000);
+    assert_eq!((points.payments, points.last_payment_id), (2, 2));
+}
+
+#[test]
+fn tolerant_merchants_are_paid_without_the_hook() {
+    let mut s = setup(false);
+    open_points(&mut s);
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, false)], &payer, &[]).unwrap();
+    assert_eq!(s.h.account::<Payment>(&pda::find_payment_address(&payer.pubkey(), 1).0).amount, AMOUNT);
+    assert_eq!(points(&s).balance, 0);
+}
+
+#[test]
+fn required_merchants_reject_payments_without_the_hook() {
+    let mut s = setup(true);
+    open_points(&mut s);
+    let payer = s.payer.insecure_clone();
+    let err = s.h.send(&[pay(&s, 1, false)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::RewardsProgramMissing));
+
+    s.h.send(&[pay(&s, 1, true)], &payer, &[]).unwrap();
+    assert_eq!(points(&s).payments, 1);
+}
+
+#[test]
+fn a_failing_hook_fails_the_payment() {
+    // Without an open points account the rewards program errors, and a
+    // failed CPI can't be caught, even for a tolerant merchant
+    let mut s = setup(false);
+    let payer = s.payer.insecure_clone();
+    assert!(s.h.send(&[pay(&s, 1, true)], &payer, &[]).is_err());
+    assert!(s.h.svm.get_account(&pda::find_payment_address(&payer.pubkey(), 1).0).is_none());
+}
+
+#[test]
+fn points_are_only_awarded_through_the_gateway() {
+    let mut s = setup(false);
+    open_points(&mut s);
+    let payer = s.payer.insecure_clone();
+    let forged = Instruction {
+        program_id: orki_rewards::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(pda::find_rewards_hook_address(&s.merchant).0, false),
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new_readonly(s.merchant, false),
+            AccountMeta::new(s.points, false),
+        ],
+        data: orki_rewards::instruction::OnPayment { amount: AMOUNT, payment_id: 1 }.data(),
+    };
+    assert!(s.h.send(&[forged], &payer, &[]).is_err());
+    assert_eq!(points(&s).balance, 0);
+}
+
+#[test]
+fn only_the_owner_attaches_a_deployed_program() {
+    let mut s = setup(false);
+    let stranger = s.h.funded(LAMPORTS_PER_SOL);
+    let ix = instructions::set_rewards_hook(&stranger.pubkey(), MERCHANT, orki_rewards::ID, true);
+    assert!(s.h.send(&[ix], &stranger, &[]).is_err());
+
+    let owner = s.owner.insecure_clone();
+    let ix = instructions::set_rewards_hook(&owner.pubkey(), MERCHANT, orki_gateway::ID, true);
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidRewardsProgram));
+    let ix = instructions::set_rewards_hook(&owner.pubkey(), MERCHANT, s.settlement.pubkey(), true);
+    assert!(s.h.send(&[ix], &owner, &[]).is_err());
+
+    // Clearing detaches it, so payments no longer call it
+    s.h.send(&[instructions::clear_rewards_hook(&owner.pubkey(), MERCHANT)], &owner, &[]).unwrap();
+    let merchant: Merchant = s.h.account(&s.merchant);
+    assert!(!merchant.rewards_enabled && !merchant.rewards_required);
+    assert!(s.h.svm.get_account(&pda::find_rewards_hook_address(&s.merchant).0).is_none_or(|a| a.lamports == 0));
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[pay(&s, 1, false)], &payer, &[]).unwrap();
+}
+
+#[test]
+fn hooked_merchants_cannot_rename() {
+    let mut s = setup(false);
+    // The hook PDA and the points it awards are keyed by the old address
+    let owner = s.owner.pubkey();
+    let new_name = "Bistro";
+    let new_merchant = pda::find_merchant_address(&owner, new_name).0;
+    let index = s.h.account::<Merchant>(&s.merchant).merchant_index;
+    let ix = build(
+        accounts::RenameMerchant {
+            merchant: s.merchant,
+            new_merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            new_merchant_stats: pda::find_merchant_stats_address(&new_merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&owner, MERCHANT).0),
+            new_merchant_name: pda::find_merchant_name_address(&owner, new_name).0,
+            owner,
+            system_program: system_program::ID,
+        },
+        instruction::RenameMerchant { name: MERCHANT.to_string(), new_name: new_name.to_string() },
+    );
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[ix], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::RenameBlockedByRewardsHook));
+}
@@ -0,0 +1,76 @@
+//! Addresses derived straight from orki-gateway-common's seeds are the ones
+//! the program creates its accounts at, and the seeds stay pinned to the
+//! strings deployed addresses were derived from.
+
+use orki_gateway::state::{
+    FeeStats, FeeVault, GlobalState, GlobalStats, Merchant, MerchantIndex, MerchantRegistry, MerchantStats, Payment,
+};
+use orki_gateway_client::common::*;
+use orki_gateway_client::instructions::{PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, Harness};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const MERCHANT: &str = "Shop";
+
+fn derive(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &orki_gateway::ID).0
+}
+
+#[test]
+fn common_seeds_derive_the_program_accounts() {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL).pubkey();
+    h.register_merchant(&owner, MERCHANT, settlement);
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let pay = instructions::process_payment(
+        &payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: settlement,
+            asset: PaymentAsset::Sol,
+            amount: LAMPORTS_PER_SOL / 10,
+            payment_id: 7,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    h.send(&[pay], &payer, &[]).unwrap();
+
+    // Each account decodes as its type only if the program created it there
+    let merchant = derive(&[MERCHANT_SEED, owner.pubkey().as_ref(), MERCHANT.as_bytes()]);
+    assert_eq!(h.account::<Merchant>(&merchant).name, MERCHANT);
+    h.account::<GlobalState>(&derive(&[GLOBAL_STATE_SEED]));
+    h.account::<GlobalStats>(&derive(&[GLOBAL_STATS_SEED]));
+    h.account::<FeeVault>(&derive(&[FEE_VAULT_SEED]));
+    h.account::<FeeStats>(&derive(&[FEE_STATS_SEED, Pubkey::default().as_ref()]));
+    h.account::<MerchantStats>(&derive(&[MERCHANT_STATS_SEED, merchant.as_ref()]));
+    h.account::<MerchantRegistry>(&derive(&[MERCHANT_REGISTRY_SEED]));
+    let index: MerchantIndex = h.account(&derive(&[MERCHANT_INDEX_SEED, &1u64.to_le_bytes()]));
+    assert_eq!(index.merchant, merchant);
+    let payment = derive(&[PAYMENT_SEED, payer.pubkey().as_ref(), &7u64.to_le_bytes()]);
+    assert_eq!(h.account::<Payment>(&payment).merchant, merchant);
+
+    // The client's helpers agree
+    assert_eq!(pda::find_merchant_address(&owner.pubkey(), MERCHANT).0, merchant);
+    assert_eq!(pda::find_payment_address(&payer.pubkey(), 7).0, payment);
+}
+
+#[test]
+fn seeds_and_limits_keep_their_deployed_values() {
+    assert_eq!(GLOBAL_STATE_SEED, b"global_state");
+    assert_eq!(MERCHANT_SEED, b"merchant");
+    assert_eq!(MERCHANT_STATS_SEED, b"stats");
+    assert_eq!(PAYMENT_SEED, b"payment");
+    assert_eq!(FEE_VAULT_SEED, b"fee_vault");
+    assert_eq!(BPS_DENOMINATOR, orki_gateway::constants::MAX_FEE_BPS);
+    assert_eq!(MAX_NAME_LENGTH, 32);
+    assert_eq!(MAX_MEMO_LENGTH, 64);
+    assert_eq!(MAX_METADATA_URI_LENGTH, 128);
+}
@@ -0,0 +1,247 @@
+//! Settlement routes: a merchant can settle one token to another of its
+//! payout addresses, so USDC lands in a treasury while SOL keeps going to the
+//! settlement wallet. Payments must bring the payment token's route PDA.
+
+use anchor_lang::system_program;
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use orki_gateway::constants::PAYOUT_ADDRESS_DELAY;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, SettlementRoute};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const START: i64 = 1_700_000_000;
+const AMOUNT: u64 = 100_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    treasury: Pubkey,
+    payer: Keypair,
+    merchant: Pubkey,
+    usdc: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL).pubkey();
+    let treasury = Pubkey::new_unique();
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement);
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let issuer = h.funded(LAMPORTS_PER_SOL);
+    let usdc = h.create_mint(&issuer, 6);
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, usdc)]).unwrap();
+    let payer_ata = h.create_ata(&payer.pubkey(), &usdc);
+    h.mint_to(&usdc, &issuer, &payer_ata, 10 * AMOUNT);
+
+    let mut s = Setup { h, owner, settlement, treasury, payer, merchant, usdc };
+    // The treasury becomes a usable payout address a day later
+    let ix = build(
+        accounts::AddPayoutAddress {
+            merchant,
+            payout_book: pda::find_payout_book_address(&merchant).0,
+            owner: s.owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::AddPayoutAddress {
+            name: MERCHANT.to_string(),
+            address: treasury,
+            label: "Treasury".to_string(),
+        },
+    );
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    s
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address(owner, mint)
+}
+
+fn route_address(s: &Setup, mint: &Pubkey) -> Pubkey {
+    pda::find_settlement_route_address(&s.merchant, mint).0
+}
+
+fn set_route(s: &mut Setup, mint: Pubkey, destination: Pubkey) -> Result<(), TransactionError> {
+    let owner = s.owner.insecure_clone();
+    let ix = instructions::set_settlement_route(&owner.pubkey(), MERCHANT, mint, destination);
+    s.h.send(&[ix], &owner, &[]).map(|_| ())
+}
+
+/// A payment in `mint` (None for SOL) settling to `wallet`, with the route
+/// PDA at `route` when given.
+fn pay(s: &Setup, payment_id: u64, mint: Option<Pubkey>, wallet: Pubkey, route: Option<Pubkey>) -> Instruction {
+    let payer = s.payer.pubkey();
+    let fee_vault = pda::find_fee_vault_address().0;
+    let token = mint.unwrap_or_default();
+    build(
+        accounts::ProcessPayment {
+            global_state: pda::find_global_state_address().0,
+            global_stats: pda::find_global_stats_address().0,
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            payer,
+            merchant_wallet: wallet,
+            fee_vault,
+            fee_stats: pda::find_fee_stats_address(&token).0,
+            system_program: system_program::ID,
+            payment_history: pda::find_payment_address(&payer, payment_id).0,
+            blocklist_entry: pda::find_blocked_address(&payer).0,
+            token_fee: pda::find_token_fee_address(&token).0,
+            fee_tiers: pda::find_fee_tiers_address().0,
+            token_program: mint.map(|_| spl_token::ID),
+            mint,
+            payer_token_account: mint.map(|mint| ata(&payer, &mint)),
+            merchant_token_account: mint.map(|mint| ata(&wallet, &mint)),
+            fee_token_account: mint.map(|mint| ata(&fee_vault, &mint)),
+            associated_token_program: mint.map(|_| associated_token::ID),
+            feature_flags: None,
+            vault: None,
+            escrow_balance: None,
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: route,
+            payment_link: None,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::ProcessPayment {
+            amount: AMOUNT,
+            payment_id,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn send(s: &mut Setup, ix: Instruction) -> Result<(), TransactionError> {
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).map(|_| ())
+}
+
+#[test]
+fn usdc_goes_to_the_treasury_and_sol_to_the_settlement_wallet() {
+    let mut s = setup();
+    s.h.set_time(START + PAYOUT_ADDRESS_DELAY);
+    let (usdc, treasury) = (s.usdc, s.treasury);
+    set_route(&mut s, usdc, treasury).unwrap();
+    let route: SettlementRoute = s.h.account(&route_address(&s, &usdc));
+    assert_eq!((route.merchant, route.mint, route.destination), (s.merchant, usdc, treasury));
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).settlement_routes, 1);
+
+    // SOL has no route, but its (empty) route PDA still comes along
+    let before = s.h.balance(&s.settlement);
+    let ix = pay(&s, 1, None, s.settlement, Some(route_address(&s, &Pubkey::default())));
+    send(&mut s, ix).unwrap();
+    let fee = AMOUNT / 100;
+    assert_eq!(s.h.balance(&s.settlement) - before, AMOUNT - fee);
+
+    let ix = pay(&s, 2, Some(usdc), treasury, Some(route_address(&s, &usdc)));
+    send(&mut s, ix).unwrap();
+    assert_eq!(s.h.token_balance(&ata(&treasury, &usdc)), AMOUNT - fee);
+    assert!(s.h.svm.get_account(&ata(&s.settlement, &usdc)).is_none());
+}
+
+#[test]
+fn routed_payments_cannot_skip_or_swap_the_route() {
+    let mut s = setup();
+    s.h.set_time(START + PAYOUT_ADDRESS_DELAY);
+    let (usdc, treasury) = (s.usdc, s.treasury);
+    set_route(&mut s, usdc, treasury).unwrap();
+
+    // The default wallet no longer takes USDC
+    let ix = pay(&s, 1, Some(usdc), s.settlement, Some(route_address(&s, &usdc)));
+    let err = send(&mut s, ix).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidMerchantWallet));
+
+    // Leaving the route out is not a way back to it
+    let ix = pay(&s, 1, Some(usdc), s.settlement, None);
+    let err = send(&mut s, ix).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MissingAccount));
+
+    // Nor is passing another token's route
+    let ix = pay(&s, 1, Some(usdc), s.settlement, Some(route_address(&s, &Pubkey::default())));
+    let err = send(&mut s, ix).unwrap_err();
+    let seeds = anchor_lang::error::ErrorCode::ConstraintSeeds as u32;
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::Custom(seeds)));
+}
+
+#[test]
+fn routes_only_lead_to_active_payout_addresses() {
+    let mut s = setup();
+    let (usdc, treasury) = (s.usdc, s.treasury);
+    assert_eq!(set_route(&mut s, usdc, treasury), Err(program_error(ErrorCode::PayoutAddressNotActive)));
+    let stranger = Pubkey::new_unique();
+    assert_eq!(set_route(&mut s, usdc, stranger), Err(program_error(ErrorCode::PayoutAddressNotListed)));
+
+    // The settlement wallet itself needs no entry
+    let settlement = s.settlement;
+    set_route(&mut s, usdc, settlement).unwrap();
+    s.h.set_time(START + PAYOUT_ADDRESS_DELAY);
+    set_route(&mut s, usdc, treasury).unwrap();
+    // Repointing a route doesn't count it twice
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).settlement_routes, 1);
+}
+
+#[test]
+fn clearing_the_route_restores_the_settlement_wallet() {
+    let mut s = setup();
+    s.h.set_time(START + PAYOUT_ADDRESS_DELAY);
+    let (usdc, treasury) = (s.usdc, s.treasury);
+    set_route(&mut s, usdc, treasury).unwrap();
+
+    let owner = s.owner.insecure_clone();
+    let index = s.h.account::<Merchant>(&s.merchant).merchant_index;
+    let close = build(
+        accounts::CloseMerchant {
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&owner.pubkey(), MERCHANT).0),
+            owner: owner.pubkey(),
+        },
+        instruction::CloseMerchant { name: MERCHANT.to_string() },
+    );
+    let err = s.h.send(&[close], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MerchantHasSettlementRoutes));
+
+    let ix = instructions::clear_settlement_route(&owner.pubkey(), MERCHANT, usdc);
+    s.h.send(&[ix], &owner, &[]).unwrap();
+    assert!(s.h.svm.get_account(&route_address(&s, &usdc)).is_none_or(|a| a.lamports == 0));
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).settlement_routes, 0);
+
+    // Unrouted merchants need no route account
+    let ix = pay(&s, 1, Some(usdc), s.settlement, None);
+    send(&mut s, ix).unwrap();
+    assert_eq!(s.h.token_balance(&ata(&s.settlement, &usdc)), AMOUNT - AMOUNT / 100);
+}
@@ -0,0 +1,309 @@
+//! Payment streams: a deposit vests to the merchant per second, the merchant
+//! withdraws what has vested less the fee, and the payer cancels for the
+//! unvested remainder. The clock is pinned so every amount is exact.
+
+use anchor_lang::system_program;
+use anchor_lang::Space;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, PaymentStream};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::build;
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness, LAMPORTS_PER_SIGNATURE};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Compute";
+const START: i64 = 1_700_000_000;
+const RATE: u64 = 1_000;
+const DEPOSIT: u64 = 1_000_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Keypair,
+    payer: Keypair,
+    merchant: Pubkey,
+    stream: Pubkey,
+}
+
+// Token accounts for an SPL stream; None for SOL
+struct Tokens {
+    mint: Pubkey,
+    stream: Pubkey,
+    settlement: Pubkey,
+    fee: Pubkey,
+    payer: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    h.set_time(START);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let payer = h.funded(10 * LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    let stream = pda::find_stream_address(&merchant, &payer.pubkey(), 1).0;
+    Setup { h, owner, settlement, payer, merchant, stream }
+}
+
+fn create(s: &Setup, tokens: Option<&Tokens>, accepted_tokens: Option<Pubkey>) -> Instruction {
+    build(
+        accounts::CreateStream {
+            global_state: pda::find_global_state_address().0,
+            merchant: s.merchant,
+            stream: s.stream,
+            payer: s.payer.pubkey(),
+            system_program: system_program::ID,
+            token_program: tokens.map(|_| spl_token::ID),
+            mint: tokens.map(|t| t.mint),
+            payer_token_account: tokens.map(|t| t.payer),
+            stream_token_account: tokens.map(|t| t.stream),
+            feature_flags: None,
+            accepted_tokens,
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::CreateStream {
+            name: MERCHANT.to_string(),
+            stream_id: 1,
+            rate_per_second: RATE,
+            mint: tokens.map_or(Pubkey::default(), |t| t.mint),
+            deposit: DEPOSIT,
+        },
+    )
+}
+
+fn withdraw(s: &Setup, owner: &Pubkey, tokens: Option<&Tokens>) -> Instruction {
+    build(
+        accounts::WithdrawStream {
+            global_state: pda::find_global_state_address().0,
+            merchant: pda::find_merchant_address(owner, MERCHANT).0,
+            stream: s.stream,
+            owner: *owner,
+            settlement_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&tokens.map_or(Pubkey::default(), |t| t.mint)).0,
+            token_program: tokens.map(|_| spl_token::ID),
+            mint: tokens.map(|t| t.mint),
+            stream_token_account: tokens.map(|t| t.stream),
+            settlement_token_account: tokens.map(|t| t.settlement),
+            fee_token_account: tokens.map(|t| t.fee),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::WithdrawStream { name: MERCHANT.to_string() },
+    )
+}
+
+fn cancel(s: &Setup, payer: &Pubkey, tokens: Option<&Tokens>) -> Instruction {
+    build(
+        accounts::CancelStream {
+            global_state: pda::find_global_state_address().0,
+            merchant: s.merchant,
+            stream: s.stream,
+            payer: *payer,
+            settlement_wallet: s.settlement.pubkey(),
+            fee_vault: pda::find_fee_vault_address().0,
+            fee_stats: pda::find_fee_stats_address(&tokens.map_or(Pubkey::default(), |t| t.mint)).0,
+            token_program: tokens.map(|_| spl_token::ID),
+            mint: tokens.map(|t| t.mint),
+            stream_token_account: tokens.map(|t| t.stream),
+            settlement_token_account: tokens.map(|t| t.settlement),
+            fee_token_account: tokens.map(|t| t.fee),
+            payer_token_account: tokens.map(|t| t.payer),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::CancelStream { name: MERCHANT.to_string() },
+    )
+}
+
+fn close(s: &Setup) -> Instruction {
+    let index = s.h.account::<Merchant>(&s.merchant).merchant_index;
+    build(
+        accounts::CloseMerchant {
+            merchant: s.merchant,
+            merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+            merchant_index: Some(pda::find_merchant_index_address(index).0),
+            merchant_name: Some(pda::find_merchant_name_address(&s.owner.pubkey(), MERCHANT).0),
+            owner: s.owner.pubkey(),
+        },
+        instruction::CloseMerchant { name: MERCHANT.to_string() },
+    )
+}
+
+fn sol_stream() -> Setup {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let ix = create(&s, None, None);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    s
+}
+
+fn token_stream() -> (Setup, Tokens) {
+    let mut s = setup();
+    let issuer = s.h.funded(LAMPORTS_PER_SOL);
+    let mint = s.h.create_mint(&issuer, 6);
+    s.h.send_as_admin(&[instructions::open_fee_stats(&s.h.admin.pubkey(), mint)]).unwrap();
+    let tokens = Tokens {
+        mint,
+        stream: s.h.create_ata(&s.stream, &mint),
+        settlement: s.h.create_ata(&s.settlement.pubkey(), &mint),
+        fee: s.h.create_ata(&pda::find_fee_vault_address().0, &mint),
+        payer: s.h.create_ata(&s.payer.pubkey(), &mint),
+    };
+    assert_eq!(tokens.stream, get_associated_token_address_with_program_id(&s.stream, &mint, &spl_token::ID));
+    s.h.mint_to(&mint, &issuer, &tokens.payer, DEPOSIT);
+
+    let payer = s.payer.insecure_clone();
+    let ix = create(&s, Some(&tokens), None);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    (s, tokens)
+}
+
+#[test]
+fn withdrawals_pay_exactly_what_has_vested() {
+    let mut s = sol_stream();
+    let owner = s.owner.insecure_clone();
+    let fee_vault = pda::find_fee_vault_address().0;
+    let stream: PaymentStream = s.h.account(&s.stream);
+    assert_eq!((stream.start_at, stream.last_withdrawn_at, stream.withdrawn), (START, START, 0));
+
+    for (elapsed, vested) in [(10, 10_000), (260, 250_000)] {
+        s.h.set_time(START + elapsed);
+        let settlement_before = s.h.balance(&s.settlement.pubkey());
+        let fees_before = s.h.balance(&fee_vault);
+        s.h.send(&[withdraw(&s, &owner.pubkey(), None)], &owner, &[]).unwrap();
+
+        // 1% fee at withdrawal time
+        assert_eq!(s.h.balance(&s.settlement.pubkey()) - settlement_before, vested * 99 / 100);
+        assert_eq!(s.h.balance(&fee_vault) - fees_before, vested / 100);
+        let stream: PaymentStream = s.h.account(&s.stream);
+        assert_eq!(stream.last_withdrawn_at, START + elapsed);
+    }
+    assert_eq!(s.h.account::<PaymentStream>(&s.stream).withdrawn, 260_000);
+
+    // Nothing more vests within the same second
+    let err = s.h.send(&[withdraw(&s, &owner.pubkey(), None)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NothingToClaim));
+}
+
+#[test]
+fn vesting_stops_at_the_deposit() {
+    let mut s = sol_stream();
+    let owner = s.owner.insecure_clone();
+    let settlement_before = s.h.balance(&s.settlement.pubkey());
+
+    // Well past the 1000 seconds the deposit covers
+    s.h.set_time(START + 5_000);
+    s.h.send(&[withdraw(&s, &owner.pubkey(), None)], &owner, &[]).unwrap();
+    assert_eq!(s.h.balance(&s.settlement.pubkey()) - settlement_before, DEPOSIT * 99 / 100);
+    assert_eq!(s.h.account::<PaymentStream>(&s.stream).withdrawn, DEPOSIT);
+
+    // Only the rent reserve is left
+    let rent = s.h.svm.minimum_balance_for_rent_exemption(8 + PaymentStream::INIT_SPACE);
+    assert_eq!(s.h.balance(&s.stream), rent);
+    s.h.set_time(START + 6_000);
+    let err = s.h.send(&[withdraw(&s, &owner.pubkey(), None)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::NothingToClaim));
+}
+
+#[test]
+fn cancel_pays_the_vested_part_and_refunds_the_rest() {
+    let mut s = sol_stream();
+    let owner = s.owner.insecure_clone();
+    s.h.set_time(START + 100);
+    s.h.send(&[withdraw(&s, &owner.pubkey(), None)], &owner, &[]).unwrap();
+
+    s.h.set_time(START + 400);
+    let payer = s.payer.insecure_clone();
+    let payer_before = s.h.balance(&payer.pubkey());
+    let settlement_before = s.h.balance(&s.settlement.pubkey());
+    let stream_balance = s.h.balance(&s.stream);
+    s.h.send(&[cancel(&s, &payer.pubkey(), None)], &payer, &[]).unwrap();
+
+    // 300 seconds vested since the withdrawal; the other 600 come back
+    assert_eq!(s.h.balance(&s.settlement.pubkey()) - settlement_before, 300_000 * 99 / 100);
+    assert_eq!(
+        s.h.balance(&payer.pubkey()) + LAMPORTS_PER_SIGNATURE - payer_before,
+        stream_balance - 300_000
+    );
+    assert!(s.h.svm.get_account(&s.stream).is_none_or(|a| a.lamports == 0));
+}
+
+#[test]
+fn only_the_merchant_withdraws_and_only_the_payer_cancels() {
+    let mut s = sol_stream();
+    s.h.set_time(START + 10);
+
+    // A stranger's merchant address does not derive from the stream's merchant
+    let stranger = s.h.funded(LAMPORTS_PER_SOL);
+    assert!(s.h.send(&[withdraw(&s, &stranger.pubkey(), None)], &stranger, &[]).is_err());
+    assert!(s.h.send(&[cancel(&s, &stranger.pubkey(), None)], &stranger, &[]).is_err());
+    assert_eq!(s.h.account::<PaymentStream>(&s.stream).withdrawn, 0);
+}
+
+#[test]
+fn token_streams_vest_and_cancel_the_same_way() {
+    let (mut s, tokens) = token_stream();
+    assert_eq!(s.h.token_balance(&tokens.stream), DEPOSIT);
+    let owner = s.owner.insecure_clone();
+
+    s.h.set_time(START + 123);
+    s.h.send(&[withdraw(&s, &owner.pubkey(), Some(&tokens))], &owner, &[]).unwrap();
+    assert_eq!(s.h.token_balance(&tokens.settlement), 123_000 - 1_230);
+    assert_eq!(s.h.token_balance(&tokens.fee), 1_230);
+
+    s.h.set_time(START + 200);
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[cancel(&s, &payer.pubkey(), Some(&tokens))], &payer, &[]).unwrap();
+    assert_eq!(s.h.token_balance(&tokens.settlement), 200_000 - 2_000);
+    assert_eq!(s.h.token_balance(&tokens.fee), 2_000);
+    assert_eq!(s.h.token_balance(&tokens.payer), DEPOSIT - 200_000);
+    assert!(s.h.svm.get_account(&tokens.stream).is_none_or(|a| a.lamports == 0));
+}
+
+#[test]
+fn open_streams_keep_the_merchant_from_closing() {
+    let mut s = sol_stream();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).open_streams, 1);
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[close(&s)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MerchantHasOpenStreams));
+
+    // Closing would have left the payer no merchant to cancel against
+    s.h.set_time(START + 10);
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[cancel(&s, &payer.pubkey(), None)], &payer, &[]).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).open_streams, 0);
+    s.h.send(&[close(&s)], &owner, &[]).unwrap();
+}
+
+#[test]
+fn streams_take_only_tokens_the_merchant_accepts() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    let accepted_tokens = pda::find_accepted_tokens_address(&s.merchant).0;
+    let add = build(
+        accounts::AddAcceptedToken {
+            merchant: s.merchant,
+            accepted_tokens,
+            owner: owner.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::AddAcceptedToken { name: MERCHANT.to_string(), mint: Pubkey::new_unique() },
+    );
+    s.h.send(&[add], &owner, &[]).unwrap();
+
+    // The list replaces the settlement token rule and leaves SOL out
+    let payer = s.payer.insecure_clone();
+    let err = s.h.send(&[create(&s, None, None)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::MissingAccount));
+    let err = s.h.send(&[create(&s, None, Some(accepted_tokens))], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidToken));
+}
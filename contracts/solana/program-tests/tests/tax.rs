@@ -0,0 +1,108 @@
+//! Merchant tax rates: process_payment records the tax included in each
+//! amount on the Payment and in PaymentProcessed without moving any of it.
+
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, Payment};
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::events::GatewayEvent;
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = 1_000_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    payer: Keypair,
+    merchant: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL).pubkey();
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement);
+    Setup { h, owner, settlement, payer, merchant }
+}
+
+fn set_tax_rate(s: &Setup, tax_bps: u16) -> Instruction {
+    build(
+        accounts::SetTaxRate {
+            merchant: s.merchant,
+            owner: s.owner.pubkey(),
+            event_authority: pda::find_event_authority_address().0,
+            program: orki_gateway::ID,
+        },
+        instruction::SetTaxRate { name: MERCHANT.to_string(), tax_bps },
+    )
+}
+
+// Pays AMOUNT and returns the recorded and the emitted tax
+fn pay(s: &mut Setup, payment_id: u64) -> (u64, u64) {
+    let ix = instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement,
+            asset: PaymentAsset::Sol,
+            amount: AMOUNT,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    let payer = s.payer.insecure_clone();
+    let events = s.h.send_for_events(&[ix], &payer, &[]).unwrap();
+    let emitted = events
+        .iter()
+        .find_map(|event| match event {
+            GatewayEvent::PaymentProcessed(e) => Some(e.tax_amount),
+            _ => None,
+        })
+        .unwrap();
+    let payment: Payment = s.h.account(&pda::find_payment_address(&payer.pubkey(), payment_id).0);
+    (payment.tax_amount, emitted)
+}
+
+#[test]
+fn records_tax_without_moving_it() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[set_tax_rate(&s, 1_900)], &owner, &[]).unwrap();
+    assert_eq!(s.h.account::<Merchant>(&s.merchant).tax_bps, 1_900);
+
+    let before = s.h.balance(&s.settlement);
+    assert_eq!(pay(&mut s, 1), (190_000, 190_000));
+    // The merchant still receives everything but the gateway fee
+    assert_eq!(s.h.balance(&s.settlement) - before, AMOUNT - AMOUNT / 100);
+}
+
+#[test]
+fn a_zero_rate_records_no_tax() {
+    let mut s = setup();
+    assert_eq!(pay(&mut s, 1), (0, 0));
+
+    let owner = s.owner.insecure_clone();
+    s.h.send(&[set_tax_rate(&s, 1_900)], &owner, &[]).unwrap();
+    s.h.send(&[set_tax_rate(&s, 0)], &owner, &[]).unwrap();
+    assert_eq!(pay(&mut s, 2), (0, 0));
+}
+
+#[test]
+fn rates_above_100_percent_are_rejected() {
+    let mut s = setup();
+    let owner = s.owner.insecure_clone();
+    let err = s.h.send(&[set_tax_rate(&s, 10_001)], &owner, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidTaxRate));
+}
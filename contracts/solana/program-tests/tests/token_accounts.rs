@@ -0,0 +1,212 @@
+//! SPL payments with a mis-supplied token account fail with an error naming
+//! what is wrong with it, before any tokens move.
+
+use anchor_lang::system_program;
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, RegisterMerchantArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = 100_000;
+
+struct Setup {
+    h: Harness,
+    settlement: Pubkey,
+    referrer: Pubkey,
+    payer: Keypair,
+    issuer: Keypair,
+    merchant: Pubkey,
+    mint: Pubkey,
+    payer_ata: Pubkey,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let register = instructions::register_merchant(
+        &owner.pubkey(),
+        RegisterMerchantArgs {
+            settlement_wallet: settlement,
+            settlement_token: Pubkey::default(),
+            name: MERCHANT.to_string(),
+            sandbox: false,
+            referrer: Some(referrer),
+            referrer_bps: 1_000,
+            accepts_any_token: true,
+            metadata_uri: String::new(),
+            category: 0,
+            merchant_index: h.next_merchant_index(),
+        },
+    );
+    h.send(&[register], &owner, &[]).unwrap();
+    let merchant = pda::find_merchant_address(&owner.pubkey(), MERCHANT).0;
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let issuer = h.funded(LAMPORTS_PER_SOL);
+    let mint = h.create_mint(&issuer, 6);
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, mint)]).unwrap();
+    let payer_ata = h.create_ata(&payer.pubkey(), &mint);
+    h.mint_to(&mint, &issuer, &payer_ata, AMOUNT);
+    h.create_ata(&referrer, &mint);
+
+    Setup { h, settlement, referrer, payer, issuer, merchant, mint, payer_ata }
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address(owner, mint)
+}
+
+/// A payment with the canonical token accounts, after `edit` has swapped any
+/// of them out.
+fn pay(s: &Setup, edit: impl FnOnce(&mut accounts::ProcessPayment)) -> Instruction {
+    let payer = s.payer.pubkey();
+    let fee_vault = pda::find_fee_vault_address().0;
+    let mut accounts = accounts::ProcessPayment {
+        global_state: pda::find_global_state_address().0,
+        global_stats: pda::find_global_stats_address().0,
+        merchant: s.merchant,
+        merchant_stats: pda::find_merchant_stats_address(&s.merchant).0,
+        payer,
+        merchant_wallet: s.settlement,
+        fee_vault,
+        fee_stats: pda::find_fee_stats_address(&s.mint).0,
+        system_program: system_program::ID,
+        payment_history: pda::find_payment_address(&payer, 1).0,
+        blocklist_entry: pda::find_blocked_address(&payer).0,
+        token_fee: pda::find_token_fee_address(&s.mint).0,
+        fee_tiers: pda::find_fee_tiers_address().0,
+        token_program: Some(spl_token::ID),
+        mint: Some(s.mint),
+        payer_token_account: Some(s.payer_ata),
+        merchant_token_account: Some(ata(&s.settlement, &s.mint)),
+        fee_token_account: Some(ata(&fee_vault, &s.mint)),
+        associated_token_program: Some(associated_token::ID),
+        feature_flags: None,
+        vault: None,
+        escrow_balance: None,
+        merchant_balance: None,
+        vault_token_account: None,
+        recent_payments: None,
+        split_config: None,
+        accepted_tokens: None,
+        memo_program: None,
+        referrer_wallet: None,
+        referrer_token_account: Some(ata(&s.referrer, &s.mint)),
+        discount: None,
+        notification_queue: None,
+        daily_volume: None,
+        rewards_hook: None,
+        rewards_program: None,
+        payer_wsol_account: None,
+        payer_throttle: None,
+        settlement_route: None,
+        payment_link: None,
+        event_authority: pda::find_event_authority_address().0,
+        program: orki_gateway::ID,
+    };
+    edit(&mut accounts);
+    build(
+        accounts,
+        instruction::ProcessPayment {
+            amount: AMOUNT,
+            payment_id: 1,
+            name: MERCHANT.to_string(),
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn send(s: &mut Setup, ix: Instruction) -> Result<(), TransactionError> {
+    let payer = s.payer.insecure_clone();
+    s.h.send(&[ix], &payer, &[]).map(|_| ())
+}
+
+/// A funded token account of `mint` held by someone other than the payer.
+fn foreign_account(s: &mut Setup, mint: &Pubkey) -> Pubkey {
+    let (issuer, stranger) = (s.issuer.insecure_clone(), Pubkey::new_unique());
+    let account = s.h.create_ata(&stranger, mint);
+    s.h.mint_to(mint, &issuer, &account, AMOUNT);
+    account
+}
+
+fn anchor_error(code: anchor_lang::error::ErrorCode) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(code as u32))
+}
+
+#[test]
+fn canonical_accounts_pay_the_merchant_fee_and_referrer() {
+    let mut s = setup();
+    let ix = pay(&s, |_| {});
+    send(&mut s, ix).unwrap();
+    assert_eq!(s.h.token_balance(&s.payer_ata), 0);
+    assert_eq!(s.h.token_balance(&ata(&s.settlement, &s.mint)), 99_000);
+    // The referrer's 10% comes out of the 1% fee
+    assert_eq!(s.h.token_balance(&ata(&s.referrer, &s.mint)), 100);
+}
+
+#[test]
+fn payer_account_of_another_mint() {
+    let mut s = setup();
+    let issuer = s.issuer.insecure_clone();
+    let other_mint = s.h.create_mint(&issuer, 6);
+    let other = s.h.create_ata(&s.payer.pubkey(), &other_mint);
+    let ix = pay(&s, |a| a.payer_token_account = Some(other));
+    assert_eq!(send(&mut s, ix).unwrap_err(), program_error(ErrorCode::MintMismatch));
+}
+
+#[test]
+fn payer_account_held_by_someone_else() {
+    let mut s = setup();
+    let mint = s.mint;
+    let foreign = foreign_account(&mut s, &mint);
+    let ix = pay(&s, |a| a.payer_token_account = Some(foreign));
+    assert_eq!(send(&mut s, ix).unwrap_err(), program_error(ErrorCode::InvalidPayerTokenAccount));
+    assert_eq!(s.h.token_balance(&foreign), AMOUNT);
+}
+
+#[test]
+fn referrer_account_of_another_mint_or_wallet() {
+    let mut s = setup();
+    let issuer = s.issuer.insecure_clone();
+    let other_mint = s.h.create_mint(&issuer, 6);
+    let other = s.h.create_ata(&s.referrer, &other_mint);
+    let ix = pay(&s, |a| a.referrer_token_account = Some(other));
+    assert_eq!(send(&mut s, ix).unwrap_err(), program_error(ErrorCode::MintMismatch));
+
+    let mint = s.mint;
+    let foreign = foreign_account(&mut s, &mint);
+    let ix = pay(&s, |a| a.referrer_token_account = Some(foreign));
+    assert_eq!(send(&mut s, ix).unwrap_err(), program_error(ErrorCode::InvalidReferrer));
+}
+
+#[test]
+fn merchant_and_fee_accounts_must_be_their_atas() {
+    // Anchor's ATA constraints reject these before the handler runs, with
+    // the account name and both owners in the log
+    let mut s = setup();
+    let mint = s.mint;
+    let foreign = foreign_account(&mut s, &mint);
+    let ix = pay(&s, |a| a.merchant_token_account = Some(foreign));
+    let err = send(&mut s, ix).unwrap_err();
+    assert_eq!(err, anchor_error(anchor_lang::error::ErrorCode::ConstraintTokenOwner));
+
+    let ix = pay(&s, |a| a.fee_token_account = Some(foreign));
+    let err = send(&mut s, ix).unwrap_err();
+    assert_eq!(err, anchor_error(anchor_lang::error::ErrorCode::ConstraintTokenOwner));
+    assert_eq!(s.h.token_balance(&s.payer_ata), AMOUNT);
+}
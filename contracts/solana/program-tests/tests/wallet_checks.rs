@@ -0,0 +1,111 @@
+//! Sanity checks process_payment applies to the settlement wallet: it may not
+//! be the gateway fee wallet unless the merchant is the gateway's own, and it
+//! may not pay itself unless the merchant allows self-payments.
+
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::Merchant;
+use orki_gateway::{accounts, instruction};
+use orki_gateway_client::instructions::{build, PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+
+fn pay(payer: &Pubkey, owner: &Pubkey, settlement_wallet: &Pubkey, payment_id: u64) -> Instruction {
+    instructions::process_payment(
+        payer,
+        ProcessPaymentArgs {
+            merchant_owner: *owner,
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: *settlement_wallet,
+            asset: PaymentAsset::Sol,
+            amount: LAMPORTS_PER_SOL / 10,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+fn set_allow_self_payments(owner: &Pubkey, allowed: bool) -> Instruction {
+    let (event_authority, _) = pda::find_event_authority_address();
+    build(
+        accounts::SetAllowSelfPayments {
+            merchant: pda::find_merchant_address(owner, MERCHANT).0,
+            owner: *owner,
+            event_authority,
+            program: orki_gateway::ID,
+        },
+        instruction::SetAllowSelfPayments { name: MERCHANT.to_string(), allowed },
+    )
+}
+
+fn setup() -> (Harness, Keypair) {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    (h, owner)
+}
+
+#[test]
+fn rejects_a_merchant_settling_to_the_fee_wallet() {
+    let (mut h, owner) = setup();
+    // Harness::initialize makes the admin the fee wallet
+    let fee_wallet = h.admin.pubkey();
+    h.register_merchant(&owner, MERCHANT, fee_wallet);
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let err = h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &fee_wallet, 1)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::SettlementIsFeeWallet));
+}
+
+#[test]
+fn the_gateways_own_merchant_may_settle_to_the_fee_wallet() {
+    let (mut h, _) = setup();
+    let admin = h.admin.insecure_clone();
+    h.register_merchant(&admin, MERCHANT, admin.pubkey());
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    h.send(&[pay(&payer.pubkey(), &admin.pubkey(), &admin.pubkey(), 1)], &payer, &[]).unwrap();
+}
+
+#[test]
+fn rejects_a_merchant_settling_to_the_fee_vault() {
+    let (mut h, owner) = setup();
+    let fee_vault = pda::find_fee_vault_address().0;
+    h.register_merchant(&owner, MERCHANT, fee_vault);
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let err = h.send(&[pay(&payer.pubkey(), &owner.pubkey(), &fee_vault, 1)], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::SettlementIsFeeWallet));
+}
+
+#[test]
+fn self_payments_need_the_merchant_flag() {
+    let (mut h, owner) = setup();
+    let settlement = h.funded(LAMPORTS_PER_SOL);
+    let merchant = h.register_merchant(&owner, MERCHANT, settlement.pubkey());
+    assert!(!h.account::<Merchant>(&merchant).allow_self_payments);
+
+    let ix = pay(&settlement.pubkey(), &owner.pubkey(), &settlement.pubkey(), 1);
+    let err = h.send(std::slice::from_ref(&ix), &settlement, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::SelfPayment));
+
+    // Only the owner can opt in
+    let stranger = h.funded(LAMPORTS_PER_SOL);
+    assert!(h.send(&[set_allow_self_payments(&stranger.pubkey(), true)], &stranger, &[]).is_err());
+
+    h.send(&[set_allow_self_payments(&owner.pubkey(), true)], &owner, &[]).unwrap();
+    assert!(h.account::<Merchant>(&merchant).allow_self_payments);
+    h.send(&[ix], &settlement, &[]).unwrap();
+
+    h.send(&[set_allow_self_payments(&owner.pubkey(), false)], &owner, &[]).unwrap();
+    let ix = pay(&settlement.pubkey(), &owner.pubkey(), &settlement.pubkey(), 2);
+    let err = h.send(&[ix], &settlement, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::SelfPayment));
+}
@@ -0,0 +1,126 @@
+//! SOL paid as wrapped SOL: process_payment funds the payer's wSOL account
+//! from its lamports and settles through the SPL path, closing the account
+//! again when the payment opened it.
+
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway_client::instructions::{PaymentAsset, ProcessPaymentArgs, RegisterMerchantArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness, LAMPORTS_PER_SIGNATURE};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = LAMPORTS_PER_SOL / 10;
+const NATIVE_MINT: Pubkey = spl_token::native_mint::ID;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    payer: Keypair,
+}
+
+fn setup() -> Setup {
+    let mut h = Harness::new();
+    h.initialize(100);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = Pubkey::new_unique();
+    let register = instructions::register_merchant(
+        &owner.pubkey(),
+        RegisterMerchantArgs {
+            settlement_wallet: settlement,
+            settlement_token: NATIVE_MINT,
+            name: MERCHANT.to_string(),
+            sandbox: false,
+            referrer: None,
+            referrer_bps: 0,
+            accepts_any_token: false,
+            metadata_uri: String::new(),
+            category: 0,
+            merchant_index: h.next_merchant_index(),
+        },
+    );
+    h.send(&[register], &owner, &[]).unwrap();
+
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, NATIVE_MINT)]).unwrap();
+    // Opened up front, so the payer's balance moves only by what it pays
+    h.create_ata(&settlement, &NATIVE_MINT);
+    h.create_ata(&pda::find_fee_vault_address().0, &NATIVE_MINT);
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    Setup { h, owner, settlement, payer }
+}
+
+fn wsol_ata(owner: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address(owner, &NATIVE_MINT)
+}
+
+fn pay(s: &Setup, asset: PaymentAsset) -> Instruction {
+    instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement,
+            asset,
+            amount: AMOUNT,
+            payment_id: 1,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    )
+}
+
+#[test]
+fn wraps_the_payment_and_closes_the_temporary_account() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let before = s.h.balance(&payer.pubkey());
+    let ix = pay(&s, PaymentAsset::WrappedSol);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    let fee = AMOUNT / 100;
+    assert_eq!(s.h.token_balance(&wsol_ata(&s.settlement)), AMOUNT - fee);
+    assert_eq!(s.h.token_balance(&wsol_ata(&pda::find_fee_vault_address().0)), fee);
+    // The wSOL account's rent came back when it closed
+    assert!(s.h.svm.get_account(&wsol_ata(&payer.pubkey())).is_none());
+    assert_eq!(
+        before - s.h.balance(&payer.pubkey()),
+        AMOUNT + s.h.payment_rent() + LAMPORTS_PER_SIGNATURE
+    );
+}
+
+#[test]
+fn tops_up_and_keeps_an_existing_wsol_account() {
+    let mut s = setup();
+    let payer = s.payer.insecure_clone();
+    let payer_ata = s.h.create_ata(&payer.pubkey(), &NATIVE_MINT);
+    let before = s.h.balance(&payer.pubkey());
+    let ix = pay(&s, PaymentAsset::WrappedSol);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    assert_eq!(s.h.token_balance(&payer_ata), 0);
+    assert_eq!(
+        before - s.h.balance(&payer.pubkey()),
+        AMOUNT + s.h.payment_rent() + LAMPORTS_PER_SIGNATURE
+    );
+}
+
+#[test]
+fn the_wsol_account_must_be_the_payers() {
+    let mut s = setup();
+    let mut ix = pay(&s, PaymentAsset::WrappedSol);
+    // Pointing it at the settlement wallet's wSOL account
+    let payer_ata = wsol_ata(&s.payer.pubkey());
+    let meta = ix.accounts.iter_mut().find(|m| m.pubkey == payer_ata).unwrap();
+    meta.pubkey = wsol_ata(&s.settlement);
+    let payer = s.payer.insecure_clone();
+    let err = s.h.send(&[ix], &payer, &[]).unwrap_err();
+    assert_eq!(err, program_error(ErrorCode::InvalidPayerTokenAccount));
+}
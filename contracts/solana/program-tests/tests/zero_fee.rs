@@ -0,0 +1,119 @@
+//! With no fee due, process_payment moves nothing to the fee vault and the
+//! fee token account may be left out of SPL payments.
+
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::FeeStats;
+use orki_gateway_client::instructions::{PaymentAsset, ProcessPaymentArgs};
+use orki_gateway_program_tests::{instructions, pda, program_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MERCHANT: &str = "Shop";
+const AMOUNT: u64 = 100_000;
+
+struct Setup {
+    h: Harness,
+    owner: Keypair,
+    settlement: Pubkey,
+    payer: Keypair,
+    mint: Pubkey,
+    payer_ata: Pubkey,
+}
+
+fn setup(fee_bps: u16) -> Setup {
+    let mut h = Harness::new();
+    h.initialize(fee_bps);
+    let owner = h.funded(LAMPORTS_PER_SOL);
+    let settlement = h.funded(LAMPORTS_PER_SOL).pubkey();
+    h.register_merchant(&owner, MERCHANT, settlement);
+
+    let payer = h.funded(LAMPORTS_PER_SOL);
+    let issuer = h.funded(LAMPORTS_PER_SOL);
+    let mint = h.create_mint(&issuer, 6);
+    let admin = h.admin.pubkey();
+    h.send_as_admin(&[instructions::open_fee_stats(&admin, mint)]).unwrap();
+    let payer_ata = h.create_ata(&payer.pubkey(), &mint);
+    h.mint_to(&mint, &issuer, &payer_ata, AMOUNT);
+    Setup { h, owner, settlement, payer, mint, payer_ata }
+}
+
+fn fee_ata(mint: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address(&pda::find_fee_vault_address().0, mint)
+}
+
+/// A payment built by the client, minus the fee token account when
+/// `with_fee_account` is unset.
+fn pay(s: &Setup, asset: PaymentAsset, with_fee_account: bool) -> Instruction {
+    let mut ix = instructions::process_payment(
+        &s.payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: s.owner.pubkey(),
+            merchant_name: MERCHANT.to_string(),
+            settlement_wallet: s.settlement,
+            asset,
+            amount: AMOUNT,
+            payment_id: 1,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    if !with_fee_account {
+        // An omitted optional account is passed as the program id
+        let fee_ata = fee_ata(&s.mint);
+        let meta = ix.accounts.iter_mut().find(|m| m.pubkey == fee_ata).unwrap();
+        meta.pubkey = orki_gateway::ID;
+        meta.is_writable = false;
+    }
+    ix
+}
+
+fn spl(s: &Setup) -> PaymentAsset {
+    PaymentAsset::Spl { mint: s.mint, token_program: spl_token::ID }
+}
+
+#[test]
+fn spl_payments_need_no_fee_account_at_a_zero_rate() {
+    let mut s = setup(0);
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, spl(&s), false);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    let settlement_ata = associated_token::get_associated_token_address(&s.settlement, &s.mint);
+    assert_eq!(s.h.token_balance(&settlement_ata), AMOUNT);
+    assert_eq!(s.h.token_balance(&s.payer_ata), 0);
+    // Nothing created the fee vault's account for the mint
+    assert!(s.h.svm.get_account(&fee_ata(&s.mint)).is_none());
+    let stats: FeeStats = s.h.account(&pda::find_fee_stats_address(&s.mint).0);
+    assert_eq!(stats.total_fees_collected, 0);
+}
+
+#[test]
+fn spl_payments_still_need_it_when_a_fee_is_due() {
+    let mut s = setup(100);
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, spl(&s), false);
+    assert_eq!(s.h.send(&[ix], &payer, &[]).unwrap_err(), program_error(ErrorCode::MissingAccount));
+
+    let ix = pay(&s, spl(&s), true);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+    assert_eq!(s.h.token_balance(&fee_ata(&s.mint)), AMOUNT / 100);
+}
+
+#[test]
+fn sol_payments_leave_the_fee_vault_untouched_at_a_zero_rate() {
+    let mut s = setup(0);
+    let fee_vault = pda::find_fee_vault_address().0;
+    let (vault_before, settlement_before) = (s.h.balance(&fee_vault), s.h.balance(&s.settlement));
+    let payer = s.payer.insecure_clone();
+    let ix = pay(&s, PaymentAsset::Sol, true);
+    s.h.send(&[ix], &payer, &[]).unwrap();
+
+    assert_eq!(s.h.balance(&fee_vault), vault_before);
+    assert_eq!(s.h.balance(&s.settlement) - settlement_before, AMOUNT);
+}
@@ -0,0 +1,191 @@
+//! Example caller: a checkout program that pays gateway merchants from a
+//! per-user buyer PDA, then confirms those payments the way a rewards program
+//! would. Shows the CPI surface other programs build on: `orki_gateway::cpi`
+//! for the instruction, `orki_gateway::cpi::accounts` for its accounts and
+//! `orki_gateway::pda` for the addresses.
+
+use anchor_lang::prelude::*;
+use orki_gateway::cpi::accounts::{ProcessPayment, VerifyPayment};
+use orki_gateway::instructions::PaymentReceipt;
+use orki_gateway::program::OrkiGateway;
+
+declare_id!("H5kYkkteRKYTTZihuEffSV2mNYyAF2bJxFxzeMWZ55wb");
+
+#[program]
+pub mod orki_checkout {
+    use super::*;
+
+    /// Pays `amount` lamports to a gateway merchant out of the caller's buyer
+    /// PDA, which signs the gateway's process_payment through its seeds, and
+    /// passes on the gateway's receipt.
+    pub fn pay(ctx: Context<Pay>, amount: u64, payment_id: u64, merchant_name: String) -> Result<PaymentReceipt> {
+        let authority = ctx.accounts.authority.key();
+        let seeds: &[&[u8]] = &[b"buyer", authority.as_ref(), &[ctx.bumps.buyer]];
+
+        let accounts = ProcessPayment {
+            global_state: ctx.accounts.global_state.to_account_info(),
+            global_stats: ctx.accounts.global_stats.to_account_info(),
+            merchant: ctx.accounts.merchant.to_account_info(),
+            merchant_stats: ctx.accounts.merchant_stats.to_account_info(),
+            payer: ctx.accounts.buyer.to_account_info(),
+            merchant_wallet: ctx.accounts.merchant_wallet.to_account_info(),
+            fee_vault: ctx.accounts.fee_vault.to_account_info(),
+            fee_stats: ctx.accounts.fee_stats.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            payment_history: ctx.accounts.payment_history.to_account_info(),
+            blocklist_entry: ctx.accounts.blocklist_entry.to_account_info(),
+            token_fee: ctx.accounts.token_fee.to_account_info(),
+            fee_tiers: ctx.accounts.fee_tiers.to_account_info(),
+            // SOL only: every SPL and merchant-feature account is left out
+            token_program: None,
+            mint: None,
+            payer_token_account: None,
+            merchant_token_account: None,
+            fee_token_account: None,
+            associated_token_program: None,
+            feature_flags: None,
+            vault: None,
+            escrow_balance: None,
+            merchant_balance: None,
+            vault_token_account: None,
+            recent_payments: None,
+            split_config: None,
+            accepted_tokens: None,
+            memo_program: None,
+            referrer_wallet: None,
+            referrer_token_account: None,
+            discount: None,
+            notification_queue: None,
+            daily_volume: None,
+            rewards_hook: None,
+            rewards_program: None,
+            payer_wsol_account: None,
+            payer_throttle: None,
+            settlement_route: None,
+            payment_link: None,
+            event_authority: ctx.accounts.event_authority.to_account_info(),
+            program: ctx.accounts.gateway_program.to_account_info(),
+        };
+
+        let receipt = orki_gateway::cpi::process_payment(
+            CpiContext::new_with_signer(
+                ctx.accounts.gateway_program.to_account_info(),
+                accounts,
+                &[seeds],
+            ),
+            amount,
+            payment_id,
+            merchant_name,
+            None,
+            None,
+            None,
+            0,
+        )?
+        .get();
+
+        msg!("Paid {} to {}: {}", receipt.merchant_amount, ctx.accounts.merchant.key(), receipt.message);
+        Ok(receipt)
+    }
+
+    /// Confirms through the gateway's verify_payment that the caller's buyer
+    /// PDA paid `merchant` at least `min_amount` under `payment_id`, and
+    /// returns what was paid net of refunds. A rewards program would credit
+    /// points here; the gateway's PaymentNotFound and AmountTooSmall errors
+    /// abort the transaction instead.
+    pub fn confirm_payment(ctx: Context<ConfirmPayment>, payment_id: u64, min_amount: u64) -> Result<u64> {
+        let accounts = VerifyPayment {
+            payer: ctx.accounts.buyer.to_account_info(),
+            merchant: ctx.accounts.merchant.to_account_info(),
+            payment: ctx.accounts.payment.to_account_info(),
+        };
+        let payment = orki_gateway::cpi::verify_payment(
+            CpiContext::new(ctx.accounts.gateway_program.to_account_info(), accounts),
+            payment_id,
+            min_amount,
+        )?
+        .get();
+
+        let paid = payment.amount.saturating_sub(payment.refunded_amount);
+        msg!("Confirmed payment {} of {} to {}", payment.payment_id, paid, payment.merchant);
+        Ok(paid)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, payment_id: u64, merchant_name: String)]
+pub struct Pay<'info> {
+    pub authority: Signer<'info>,
+
+    // Funded by the authority beforehand; pays the amount and the record's rent
+    #[account(
+        mut,
+        seeds = [b"buyer", authority.key().as_ref()],
+        bump
+    )]
+    pub buyer: SystemAccount<'info>,
+
+    /// CHECK: Validated by the gateway
+    pub global_state: UncheckedAccount<'info>,
+    /// CHECK: Validated by the gateway
+    #[account(mut)]
+    pub global_stats: UncheckedAccount<'info>,
+    /// CHECK: Validated by the gateway
+    #[account(mut)]
+    pub merchant: UncheckedAccount<'info>,
+    /// CHECK: Validated by the gateway
+    #[account(mut)]
+    pub merchant_stats: UncheckedAccount<'info>,
+    /// CHECK: Validated by the gateway
+    #[account(mut)]
+    pub merchant_wallet: UncheckedAccount<'info>,
+    /// CHECK: Validated by the gateway
+    #[account(mut)]
+    pub fee_vault: UncheckedAccount<'info>,
+    /// CHECK: Validated by the gateway
+    #[account(mut)]
+    pub fee_stats: UncheckedAccount<'info>,
+
+    /// CHECK: Created by the gateway under the buyer PDA and payment_id
+    #[account(
+        mut,
+        address = orki_gateway::pda::find_payment_address(&buyer.key(), payment_id).0
+    )]
+    pub payment_history: UncheckedAccount<'info>,
+
+    /// CHECK: The buyer PDA's gateway blocklist entry; validated by the gateway
+    pub blocklist_entry: UncheckedAccount<'info>,
+
+    /// CHECK: The gateway's SOL fee config PDA; validated by the gateway
+    pub token_fee: UncheckedAccount<'info>,
+
+    /// CHECK: The gateway's fee tiers PDA; validated by the gateway
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: The gateway's event authority
+    #[account(address = orki_gateway::pda::find_event_authority_address().0)]
+    pub event_authority: UncheckedAccount<'info>,
+
+    pub gateway_program: Program<'info, OrkiGateway>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(payment_id: u64)]
+pub struct ConfirmPayment<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"buyer", authority.key().as_ref()],
+        bump
+    )]
+    pub buyer: SystemAccount<'info>,
+
+    /// CHECK: Compared with the payment's merchant by the gateway
+    pub merchant: UncheckedAccount<'info>,
+
+    /// CHECK: The buyer's payment record; the gateway checks the address and
+    /// fails with PaymentNotFound when nothing was recorded there
+    pub payment: UncheckedAccount<'info>,
+
+    pub gateway_program: Program<'info, OrkiGateway>,
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+/// Current unix timestamp for deadline and delay checks.
+///
+/// With the `test-clock` feature, a `TestClock` PDA passed as a remaining
+/// account overrides the sysvar so tests can pin "now". Release builds never
+/// compile that path and ignore `remaining_accounts`.
+#[cfg_attr(not(feature = "test-clock"), allow(unused_variables))]
+pub fn now(remaining_accounts: &[AccountInfo]) -> Result<i64> {
+    #[cfg(feature = "test-clock")]
+    {
+        use crate::constants::TEST_CLOCK_SEED;
+        use crate::state::TestClock;
+        let (test_clock, _) = Pubkey::find_program_address(&[TEST_CLOCK_SEED], &crate::ID);
+        if let Some(info) = remaining_accounts.iter().find(|a| a.key == &test_clock && a.owner == &crate::ID) {
+            let data = info.try_borrow_data()?;
+            return Ok(TestClock::try_deserialize(&mut &data[..])?.unix_timestamp);
+        }
+    }
+    Ok(Clock::get()?.unix_timestamp)
+}
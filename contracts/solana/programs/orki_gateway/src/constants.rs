@@ -1,4 +1,177 @@
 use anchor_lang::prelude::*;
+use orki_gateway_common as common;
 
 #[constant]
 pub const SEED: &str = "anchor";
+
+#[constant]
+pub const DEFAULT_AUTHORIZATION_EXPIRY: i64 = 7 * 24 * 60 * 60;
+
+#[constant]
+pub const DEFAULT_PAYMENT_RETENTION: i64 = 90 * 24 * 60 * 60;
+
+// Roughly the depth at which a vote-locked slot can no longer be forked away
+#[constant]
+pub const DEFAULT_FINALITY_SLOTS: u64 = 32;
+
+// Fee cap for a GlobalState that predates max_fee_bps, and the ceiling any
+// cap starts from
+#[constant]
+pub const MAX_FEE_BPS: u16 = common::BPS_DENOMINATOR;
+
+// Highest referrer share a merchant can pick for itself at registration;
+// anything above needs set_merchant_referrer
+#[constant]
+pub const MAX_REGISTRATION_REFERRER_BPS: u16 = 5000;
+
+// Shortest crank_payout schedule, so crank rewards can't be farmed off a
+// merchant paying itself out every few seconds
+#[constant]
+pub const MIN_PAYOUT_INTERVAL: u32 = 60 * 60;
+
+// How long an admin council proposal stays open for approvals
+#[constant]
+pub const ADMIN_PROPOSAL_LIFETIME: i64 = 3 * 24 * 60 * 60;
+
+#[constant]
+pub const PAYOUT_ADDRESS_DELAY: i64 = 24 * 60 * 60;
+
+// Shared with clients through orki-gateway-common and re-declared here so
+// they appear in the IDL
+#[constant]
+pub const BPS_DENOMINATOR: u16 = common::BPS_DENOMINATOR;
+
+#[constant]
+pub const MAX_NAME_LENGTH: u32 = common::MAX_NAME_LENGTH;
+
+#[constant]
+pub const MAX_MEMO_LENGTH: u32 = common::MAX_MEMO_LENGTH;
+
+#[constant]
+pub const MAX_CONFIRMATION_MESSAGE_LENGTH: u32 = common::MAX_CONFIRMATION_MESSAGE_LENGTH;
+
+#[constant]
+pub const MAX_METADATA_URI_LENGTH: u32 = common::MAX_METADATA_URI_LENGTH;
+
+#[constant]
+pub const GLOBAL_STATE_SEED: &[u8] = common::GLOBAL_STATE_SEED;
+
+#[constant]
+pub const GLOBAL_STATS_SEED: &[u8] = common::GLOBAL_STATS_SEED;
+
+#[constant]
+pub const FEATURE_FLAGS_SEED: &[u8] = common::FEATURE_FLAGS_SEED;
+
+#[constant]
+pub const FEE_VAULT_SEED: &[u8] = common::FEE_VAULT_SEED;
+
+#[constant]
+pub const FEE_STATS_SEED: &[u8] = common::FEE_STATS_SEED;
+
+#[constant]
+pub const TOKEN_FEE_SEED: &[u8] = common::TOKEN_FEE_SEED;
+
+#[constant]
+pub const FEE_TIERS_SEED: &[u8] = common::FEE_TIERS_SEED;
+
+#[constant]
+pub const FEE_TIERS_PROPOSAL_SEED: &[u8] = common::FEE_TIERS_PROPOSAL_SEED;
+
+#[constant]
+pub const ADMIN_COUNCIL_SEED: &[u8] = common::ADMIN_COUNCIL_SEED;
+
+#[constant]
+pub const ADMIN_PROPOSAL_SEED: &[u8] = common::ADMIN_PROPOSAL_SEED;
+
+#[constant]
+pub const MERCHANT_SEED: &[u8] = common::MERCHANT_SEED;
+
+#[constant]
+pub const MERCHANT_NAME_SEED: &[u8] = common::MERCHANT_NAME_SEED;
+
+#[constant]
+pub const MERCHANT_REGISTRY_SEED: &[u8] = common::MERCHANT_REGISTRY_SEED;
+
+#[constant]
+pub const MERCHANT_INDEX_SEED: &[u8] = common::MERCHANT_INDEX_SEED;
+
+#[constant]
+pub const MERCHANT_TRANSFER_SEED: &[u8] = common::MERCHANT_TRANSFER_SEED;
+
+#[constant]
+pub const MERCHANT_STATS_SEED: &[u8] = common::MERCHANT_STATS_SEED;
+
+#[constant]
+pub const MERCHANT_BALANCE_SEED: &[u8] = common::MERCHANT_BALANCE_SEED;
+
+#[constant]
+pub const ESCROW_BALANCE_SEED: &[u8] = common::ESCROW_BALANCE_SEED;
+
+#[constant]
+pub const VAULT_SEED: &[u8] = common::VAULT_SEED;
+
+#[constant]
+pub const OPERATOR_SEED: &[u8] = common::OPERATOR_SEED;
+
+#[constant]
+pub const PAYOUT_BOOK_SEED: &[u8] = common::PAYOUT_BOOK_SEED;
+
+#[constant]
+pub const SETTLEMENT_ROUTE_SEED: &[u8] = common::SETTLEMENT_ROUTE_SEED;
+
+#[constant]
+pub const ACCEPTED_TOKENS_SEED: &[u8] = common::ACCEPTED_TOKENS_SEED;
+
+#[constant]
+pub const SPLIT_CONFIG_SEED: &[u8] = common::SPLIT_CONFIG_SEED;
+
+#[constant]
+pub const RECENT_PAYMENTS_SEED: &[u8] = common::RECENT_PAYMENTS_SEED;
+
+#[constant]
+pub const NOTIFICATION_QUEUE_SEED: &[u8] = common::NOTIFICATION_QUEUE_SEED;
+
+#[constant]
+pub const DAILY_VOLUME_SEED: &[u8] = common::DAILY_VOLUME_SEED;
+
+#[constant]
+pub const PAYER_THROTTLE_SEED: &[u8] = common::PAYER_THROTTLE_SEED;
+
+#[constant]
+pub const REWARDS_HOOK_SEED: &[u8] = common::REWARDS_HOOK_SEED;
+
+#[constant]
+pub const DISCOUNT_SEED: &[u8] = common::DISCOUNT_SEED;
+
+#[constant]
+pub const PAYMENT_SEED: &[u8] = common::PAYMENT_SEED;
+
+#[constant]
+pub const BLOCKED_SEED: &[u8] = common::BLOCKED_SEED;
+
+#[constant]
+pub const INVOICE_SEED: &[u8] = common::INVOICE_SEED;
+
+#[constant]
+pub const PAYMENT_LINK_SEED: &[u8] = common::PAYMENT_LINK_SEED;
+
+#[constant]
+pub const SUBSCRIPTION_SEED: &[u8] = common::SUBSCRIPTION_SEED;
+
+#[constant]
+pub const SUBSCRIPTION_DELEGATE_SEED: &[u8] = common::SUBSCRIPTION_DELEGATE_SEED;
+
+#[constant]
+pub const PAYMENT_DELEGATE_SEED: &[u8] = common::PAYMENT_DELEGATE_SEED;
+
+#[constant]
+pub const STREAM_SEED: &[u8] = common::STREAM_SEED;
+
+#[constant]
+pub const RECEIPT_MINT_SEED: &[u8] = common::RECEIPT_MINT_SEED;
+
+#[constant]
+pub const RECEIPT_AUTHORITY_SEED: &[u8] = common::RECEIPT_AUTHORITY_SEED;
+
+#[constant]
+pub const TEST_CLOCK_SEED: &[u8] = common::TEST_CLOCK_SEED;
@@ -8,7 +8,7 @@ pub enum ErrorCode {
     Unauthorized,
     #[msg("Invalid token for this merchant")]
     InvalidToken,
-    #[msg("Invalid fee amount (must be 0-10000)")]
+    #[msg("Invalid fee amount (must not exceed the configured max fee)")]
     InvalidFee,
     #[msg("Missing mint account")]
     MissingMint,
@@ -30,4 +30,260 @@ pub enum ErrorCode {
     NameTooLong,
     #[msg("Duplicate payment detected")]
     DuplicatePayment,
+    #[msg("Payment does not belong to this merchant")]
+    InvalidPayment,
+    #[msg("Payer does not match the payment")]
+    InvalidPayer,
+    #[msg("Refund amount must be greater than zero")]
+    InvalidRefundAmount,
+    #[msg("Refund exceeds the remaining payment amount")]
+    RefundExceedsPayment,
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+    #[msg("No admin transfer is pending")]
+    NoPendingAdmin,
+    #[msg("Account is not owned by this program or has the wrong type")]
+    InvalidAccountData,
+    #[msg("Feature is disabled")]
+    FeatureDisabled,
+    #[msg("Token-2022 mints with a transfer fee are not supported")]
+    TransferFeeMintNotSupported,
+    #[msg("Escrow funds are still within the release delay")]
+    EscrowLocked,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Memo too long")]
+    MemoTooLong,
+    #[msg("Invoice has expired")]
+    InvoiceExpired,
+    #[msg("Invoice has already been paid")]
+    InvoiceAlreadyPaid,
+    #[msg("Invoice is not open")]
+    InvoiceNotOpen,
+    #[msg("Merchant is inactive")]
+    MerchantInactive,
+    #[msg("Merchant was deactivated by the admin")]
+    MerchantAdminLocked,
+    #[msg("Claim escrow and disable it before renaming")]
+    RenameBlockedByEscrow,
+    #[msg("Merchant still has open invoices")]
+    MerchantHasOpenInvoices,
+    #[msg("Merchant still has unclaimed escrow")]
+    MerchantHasEscrowBalance,
+    #[msg("Interval must be positive")]
+    InvalidInterval,
+    #[msg("Subscription delegate not approved for the amount")]
+    DelegateNotApproved,
+    #[msg("Subscription is not due yet")]
+    SubscriptionNotDue,
+    #[msg("Split needs 1-5 recipients with non-zero shares summing to 10000 bps")]
+    InvalidSplit,
+    #[msg("Split payments and escrow cannot be combined")]
+    SplitEscrowConflict,
+    #[msg("Clear the split config before renaming")]
+    RenameBlockedBySplit,
+    #[msg("Payment is not in a valid status for this action")]
+    InvalidPaymentStatus,
+    #[msg("Authorization has not expired yet")]
+    AuthorizationNotExpired,
+    #[msg("Not supported for escrow or split merchants")]
+    UnsupportedSettlementMode,
+    #[msg("Payout label too long")]
+    LabelTooLong,
+    #[msg("Payout address already in the book")]
+    PayoutAddressExists,
+    #[msg("Payout address book is full")]
+    PayoutBookFull,
+    #[msg("Settlement wallet is not in the payout address book")]
+    PayoutAddressNotListed,
+    #[msg("Payout address is still inside its activation delay")]
+    PayoutAddressNotActive,
+    #[msg("Token is already accepted")]
+    TokenAlreadyAccepted,
+    #[msg("Too many accepted tokens")]
+    TooManyAcceptedTokens,
+    #[msg("Token is not in the accepted list")]
+    TokenNotAccepted,
+    #[msg("Cannot rename a merchant with an accepted-token list")]
+    RenameBlockedByAcceptedTokens,
+    #[msg("Guardian can only pause")]
+    GuardianCannotUnpause,
+    #[msg("No pending fee proposal")]
+    NoPendingFee,
+    #[msg("Fee timelock has not elapsed")]
+    FeeTimelockNotElapsed,
+    #[msg("Payment amount is below the required minimum")]
+    AmountTooSmall,
+    #[msg("Payment amount is above the merchant maximum")]
+    AmountTooLarge,
+    #[msg("Minimum amount exceeds maximum amount")]
+    InvalidAmountLimits,
+    #[msg("Merchant has not enabled swap settlement")]
+    SwapNotEnabled,
+    #[msg("Swap returned less than the minimum amount out")]
+    SlippageExceeded,
+    #[msg("Swap spent more than the payment amount")]
+    SwapOverspent,
+    #[msg("Payment link is not active")]
+    LinkInactive,
+    #[msg("Payment link has expired")]
+    LinkExpired,
+    #[msg("Payment link has reached its maximum uses")]
+    LinkExhausted,
+    #[msg("Payment is still inside its retention window")]
+    PaymentRetentionActive,
+    #[msg("Unknown operator permission bits")]
+    InvalidPermissions,
+    #[msg("Merchant has operators; revoke them first")]
+    MerchantHasOperators,
+    #[msg("Referrer share is above the allowed maximum")]
+    ReferrerShareTooHigh,
+    #[msg("Referrer account does not match the merchant's referrer")]
+    InvalidReferrer,
+    #[msg("Merchant already uses the explicit token rule")]
+    MerchantAlreadyMigrated,
+    #[msg("Discount must be between 1 and 9999 bps")]
+    InvalidDiscount,
+    #[msg("Discount code does not match")]
+    DiscountCodeMismatch,
+    #[msg("Discount code has expired")]
+    DiscountExpired,
+    #[msg("Discount code has reached its maximum redemptions")]
+    DiscountExhausted,
+    #[msg("Payer address is blocked")]
+    AddressBlocked,
+    #[msg("Merchant metadata URI too long")]
+    MetadataUriTooLong,
+    #[msg("Fee tiers must be at most 5 rows in ascending threshold order")]
+    InvalidFeeTiers,
+    #[msg("Payment already has a receipt")]
+    ReceiptAlreadyMinted,
+    #[msg("No arbiter is set for escrow disputes")]
+    ArbiterNotSet,
+    #[msg("Payment was not paid into escrow")]
+    PaymentNotEscrowed,
+    #[msg("Escrowed funds for this payment were already claimed")]
+    DisputeWindowClosed,
+    #[msg("Accrued settlement cannot be combined with escrow or split payments")]
+    SettlementModeConflict,
+    #[msg("Switch back to instant settlement before renaming")]
+    RenameBlockedByAccrual,
+    #[msg("Payment deadline has passed")]
+    PaymentExpired,
+    #[msg("Account was created by an older program version; run its migration first")]
+    AccountNeedsMigration,
+    #[msg("Payment token is not accepted; only process_payment_with_swap converts it")]
+    SwapNotAvailable,
+    #[msg("No payment record exists for this payer and payment ID")]
+    PaymentNotFound,
+    #[msg("Merchant is already listed in the merchant directory")]
+    MerchantAlreadyIndexed,
+    #[msg("Destination token account is frozen")]
+    DestinationFrozen,
+    #[msg("A merchant with this owner and name is already registered with different settings")]
+    MerchantAlreadyExists,
+    #[msg("Payment must be finalized first; see finalize_payment")]
+    PaymentNotFinalized,
+    #[msg("Payment has not aged past the finality depth yet")]
+    FinalityNotReached,
+    #[msg("Fee is above the gateway's fee cap")]
+    FeeCapExceeded,
+    #[msg("The fee cap can only be lowered")]
+    CannotRaiseCap,
+    #[msg("New owner must differ from the current owner")]
+    CannotTransferToSelf,
+    #[msg("Claim escrow and disable it before transferring the merchant")]
+    TransferBlockedByEscrow,
+    #[msg("Switch back to instant settlement before transferring the merchant")]
+    TransferBlockedByAccrual,
+    #[msg("Merchant settlement wallet is the gateway fee wallet; re-register the merchant with its own wallet")]
+    SettlementIsFeeWallet,
+    #[msg("Payer is the merchant's settlement wallet; enable allow_self_payments if this is intended")]
+    SelfPayment,
+    #[msg("Delegated payment needs the payer's ed25519 signature over its authorization in the instruction before it")]
+    InvalidPayerSignature,
+    #[msg("Payment would take the merchant past its daily volume limit")]
+    DailyLimitExceeded,
+    #[msg("Ask the admin to lift the merchant's daily limit before transferring it")]
+    TransferBlockedByDailyLimit,
+    #[msg("Payer token account is not owned by the payer")]
+    InvalidPayerTokenAccount,
+    #[msg("Merchant token account is not owned by the merchant's settlement wallet")]
+    InvalidMerchantTokenAccount,
+    #[msg("Fee token account is not owned by the fee wallet")]
+    InvalidFeeTokenAccount,
+    #[msg("Token account holds a different mint than the payment")]
+    MintMismatch,
+    #[msg("Token account is not owned by the expected account")]
+    OwnerMismatch,
+    #[msg("Rewards program must be a deployed program other than the gateway")]
+    InvalidRewardsProgram,
+    #[msg("Merchant requires its rewards hook; pass the hook and its program")]
+    RewardsProgramMissing,
+    #[msg("Clear the merchant's rewards hook before transferring it")]
+    TransferBlockedByRewardsHook,
+    #[msg("Admin must be a real key, not the default pubkey")]
+    InvalidAdmin,
+    #[msg("Order id must not be all zero")]
+    InvalidOrderId,
+    #[msg("Merchant is frozen by the admin")]
+    MerchantFrozen,
+    #[msg("Payout interval must be 0 or at least MIN_PAYOUT_INTERVAL seconds")]
+    InvalidPayoutInterval,
+    #[msg("No payout is scheduled, or its interval has not elapsed")]
+    PayoutNotDue,
+    #[msg("Council needs 1 to 5 distinct members and a threshold between 1 and the member count")]
+    InvalidCouncil,
+    #[msg("Signer is not a council member")]
+    NotCouncilMember,
+    #[msg("Proposal nonce must be the council's next nonce")]
+    InvalidProposalNonce,
+    #[msg("Member already approved this proposal")]
+    AlreadyApproved,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Proposal has expired")]
+    ProposalExpired,
+    #[msg("Proposal predates the current council membership")]
+    ProposalStale,
+    #[msg("Action is governed by the admin council")]
+    CouncilGoverned,
+    #[msg("Tax rate cannot exceed 10000 bps")]
+    InvalidTaxRate,
+    #[msg("Merchant name cannot be empty")]
+    EmptyName,
+    #[msg("Merchant name has control or invisible characters, or whitespace at either end")]
+    InvalidNameCharacters,
+    #[msg("The owner already has a merchant by this name, ignoring case")]
+    NameAlreadyUsed,
+    #[msg("Confirmation message too long")]
+    ConfirmationMessageTooLong,
+    #[msg("The merchant takes one payment per payer per interval; try again later")]
+    RateLimited,
+    #[msg("Merchant has settlement routes; clear them first")]
+    MerchantHasSettlementRoutes,
+    #[msg("Ask the admin to lift the merchant's daily limit before renaming it")]
+    RenameBlockedByDailyLimit,
+    #[msg("Clear the merchant's rewards hook before renaming it")]
+    RenameBlockedByRewardsHook,
+    #[msg("Merchant has discount codes; delete them first")]
+    MerchantHasDiscountCodes,
+    #[msg("Merchant has a split config; clear it first")]
+    MerchantHasSplitConfig,
+    #[msg("Merchant has an accepted-token list; remove its tokens first")]
+    MerchantHasAcceptedTokens,
+    #[msg("Merchant has open authorizations; capture or void them first")]
+    MerchantHasOpenAuthorizations,
+    #[msg("Merchant has open payment streams; they must be cancelled first")]
+    MerchantHasOpenStreams,
+    #[msg("Raising a fee has to wait out the fee timelock")]
+    FeeRaiseNeedsTimelock,
+    #[msg("Admin proposal has not been approved, or was already used")]
+    ProposalNotApproved,
+    #[msg("Admin proposal approves a different action")]
+    ProposalActionMismatch,
+    #[msg("Refunds still match payments made under the merchant's previous address; release it first")]
+    PreviousMerchantNotReleased,
+    #[msg("Merchant has no previous address to release")]
+    NoPreviousMerchant,
 }
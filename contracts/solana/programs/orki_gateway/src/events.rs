@@ -1,6 +1,7 @@
 
 
 use anchor_lang::prelude::*;
+use crate::state::{AdminAction, Feature, FeeRounding, FeeSource, FeeTier, SettlementMode, SplitRecipient};
 
 #[event]
 pub struct PaymentProcessed {
@@ -11,6 +12,629 @@ pub struct PaymentProcessed {
     pub token: Pubkey,
     pub payment_id: u64,
     pub timestamp: i64,
+    pub sandbox: bool,
+    pub memo: String,
+    // Part of `fee` paid to the merchant's referrer
+    pub referrer_amount: u64,
+    // Fee-free extra the merchant received on top of `amount`
+    pub tip: u64,
+    // Rate `fee` was charged at and which setting it came from
+    pub fee_bps: u16,
+    pub fee_source: FeeSource,
+    // Deadline the payer signed against, 0 when there was none
+    pub valid_until: i64,
+    // From GlobalStats; strictly increasing across every event that carries it
+    pub event_seq: u64,
+    // So indexers can label the shop and link the receipt without fetching
+    pub merchant_name: String,
+    pub payment_account: Pubkey,
+    // The process_payment_v2 id, with `payment_id` 0; all zero otherwise
+    pub order_id: [u8; 32],
+    // Tax included in `amount` at the merchant's tax_bps
+    pub tax_amount: u64,
+}
+
+#[event]
+pub struct PaymentSwapped {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    // Input left after the fee, and what the merchant received for it
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentRefunded {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub refunded_amount: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+    pub merchant_name: String,
+    pub payment_account: Pubkey,
+}
+
+#[event]
+pub struct EscrowOpened {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SettlementClaimed {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InvoiceCreated {
+    pub merchant: Pubkey,
+    pub invoice: Pubkey,
+    pub invoice_id: u64,
+    pub amount: u64,
+    pub mint: Pubkey,
+    pub expires_at: i64,
+    pub memo: String,
+    pub timestamp: i64,
+    pub merchant_name: String,
+}
+
+#[event]
+pub struct InvoicePaid {
+    pub merchant: Pubkey,
+    pub invoice: Pubkey,
+    pub invoice_id: u64,
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub mint: Pubkey,
+    pub timestamp: i64,
+    pub merchant_name: String,
+}
+
+#[event]
+pub struct InvoiceCancelled {
+    pub merchant: Pubkey,
+    pub invoice: Pubkey,
+    pub invoice_id: u64,
+    pub timestamp: i64,
+    pub merchant_name: String,
+}
+
+#[event]
+pub struct PaymentLinkCreated {
+    pub merchant: Pubkey,
+    pub link: Pubkey,
+    pub link_seed: u64,
+    pub amount: u64,
+    pub mint: Pubkey,
+    pub max_uses: u64,
+    pub expires_at: i64,
+    pub label: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentLinkPaid {
+    pub merchant: Pubkey,
+    pub link: Pubkey,
+    pub payer: Pubkey,
+    pub payment: Pubkey,
+    pub use_index: u64,
+    pub amount: u64,
+    pub fee: u64,
+    pub mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentLinkDeactivated {
+    pub merchant: Pubkey,
+    pub link: Pubkey,
+    pub uses: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayerBlocked {
+    pub admin: Pubkey,
+    pub address: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayerUnblocked {
+    pub admin: Pubkey,
+    pub address: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenFeeSet {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenFeeProposed {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    // None while the mint has no rate of its own yet
+    pub current_fee_bps: Option<u16>,
+    pub pending_fee_bps: u16,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenFeeCleared {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeTiersUpdated {
+    pub admin: Pubkey,
+    pub tiers: Vec<FeeTier>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeTiersProposed {
+    pub admin: Pubkey,
+    pub tiers: Vec<FeeTier>,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeTiersProposalCancelled {
+    pub admin: Pubkey,
+    pub tiers: Vec<FeeTier>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DiscountCreated {
+    pub merchant: Pubkey,
+    pub discount: Pubkey,
+    pub code_hash: [u8; 32],
+    pub discount_bps: u16,
+    pub max_redemptions: u64,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DiscountDeleted {
+    pub merchant: Pubkey,
+    pub discount: Pubkey,
+    pub code_hash: [u8; 32],
+    pub redemptions: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DiscountRedeemed {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub discount: Pubkey,
+    pub payment_id: u64,
+    // Listed price, and what was charged after the discount
+    pub pre_discount_amount: u64,
+    pub amount: u64,
+    pub discount_bps: u16,
+    pub redemptions: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantStatusChanged {
+    pub merchant: Pubkey,
+    pub active: bool,
+    pub admin_locked: bool,
+    pub changed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionCreated {
+    pub merchant: Pubkey,
+    pub subscriber: Pubkey,
+    pub subscription: Pubkey,
+    pub sub_id: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub interval_seconds: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionCharged {
+    pub merchant: Pubkey,
+    pub subscriber: Pubkey,
+    pub subscription: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub charges_count: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionCancelled {
+    pub merchant: Pubkey,
+    pub subscriber: Pubkey,
+    pub subscription: Pubkey,
+    pub charges_count: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamCreated {
+    pub merchant: Pubkey,
+    pub payer: Pubkey,
+    pub stream: Pubkey,
+    pub stream_id: u64,
+    pub mint: Pubkey,
+    pub rate_per_second: u64,
+    pub deposit: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamWithdrawn {
+    pub merchant: Pubkey,
+    pub payer: Pubkey,
+    pub stream: Pubkey,
+    // Gross vested amount; the merchant received amount - fee
+    pub amount: u64,
+    pub fee: u64,
+    pub withdrawn: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamCancelled {
+    pub merchant: Pubkey,
+    pub payer: Pubkey,
+    pub stream: Pubkey,
+    // Vested amount paid to the merchant on cancel, fee included
+    pub vested: u64,
+    pub fee: u64,
+    // Unvested remainder returned to the payer
+    pub refunded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DelegatedPaymentProcessed {
+    pub payer: Pubkey,
+    // Submitted the transaction and paid for the payment record
+    pub relayer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub token: Pubkey,
+    pub payment_id: u64,
+    pub timestamp: i64,
+    pub sandbox: bool,
+    pub referrer_amount: u64,
+    pub fee_bps: u16,
+    pub fee_source: FeeSource,
+    // Deadline of the payer's signed authorization
+    pub expires_at: i64,
+    pub event_seq: u64,
+    pub payment_account: Pubkey,
+}
+
+#[event]
+pub struct PaymentSplit {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    pub token: Pubkey,
+    pub recipients: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SplitConfigUpdated {
+    pub merchant: Pubkey,
+    pub recipients: Vec<SplitRecipient>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsHookUpdated {
+    pub merchant: Pubkey,
+    // None once cleared
+    pub program: Option<Pubkey>,
+    pub required: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AcceptedTokensUpdated {
+    pub merchant: Pubkey,
+    pub mints: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayoutAddressAdded {
+    pub merchant: Pubkey,
+    pub address: Pubkey,
+    pub label: String,
+    pub activates_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayoutAddressRemoved {
+    pub merchant: Pubkey,
+    pub address: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentAuthorized {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub token: Pubkey,
+    pub payment_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentCaptured {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub token: Pubkey,
+    pub payment_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentVoided {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub token: Pubkey,
+    pub payment_id: u64,
+    pub voided_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorizationExpiryUpdated {
+    pub admin: Pubkey,
+    pub old_expiry: i64,
+    pub new_expiry: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentRetentionUpdated {
+    pub admin: Pubkey,
+    pub old_retention: i64,
+    pub new_retention: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FinalitySlotsUpdated {
+    pub admin: Pubkey,
+    pub old_slots: u64,
+    pub new_slots: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MaxFeeReduced {
+    pub admin: Pubkey,
+    pub old_max_fee_bps: u16,
+    pub new_max_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeRoundingUpdated {
+    pub admin: Pubkey,
+    pub old_rounding: FeeRounding,
+    pub new_rounding: FeeRounding,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminCouncilCreated {
+    pub admin: Pubkey,
+    pub council: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminActionProposed {
+    pub proposal: Pubkey,
+    pub nonce: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminActionApproved {
+    pub proposal: Pubkey,
+    pub nonce: u64,
+    pub member: Pubkey,
+    // Approvals so far, the proposer's included
+    pub approvals: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminActionExecuted {
+    pub proposal: Pubkey,
+    pub nonce: u64,
+    pub action: AdminAction,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CrankRewardUpdated {
+    pub admin: Pubkey,
+    pub old_reward: u64,
+    pub new_reward: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentFinalized {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    // Slot the funds moved in, and the slot finalize_payment ran in
+    pub slot: u64,
+    pub finalized_slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SettlementModeUpdated {
+    pub merchant: Pubkey,
+    pub settlement_mode: SettlementMode,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantBalanceOpened {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BalanceCredited {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BalanceWithdrawn {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayoutIntervalUpdated {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    // 0 when the schedule was turned off
+    pub payout_interval: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayoutCranked {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub cranker: Pubkey,
+    // Lamports paid to the cranker; 0 when the fee vault was short
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DisputeOpened {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub event_seq: u64,
+}
+
+#[event]
+pub struct DisputeResolved {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub refunded: bool,
+    pub arbiter: Pubkey,
+    pub timestamp: i64,
+    pub event_seq: u64,
+}
+
+#[event]
+pub struct ReceiptMinted {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentClosed {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_id: u64,
+    pub closed_by: Pubkey,
+    pub rent_returned: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OperatorGranted {
+    pub merchant: Pubkey,
+    pub operator: Pubkey,
+    pub permissions: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OperatorRevoked {
+    pub merchant: Pubkey,
+    pub operator: Pubkey,
+    pub timestamp: i64,
 }
 
 #[event]
@@ -21,28 +645,218 @@ pub struct MerchantRegistered {
     pub settlement_token: Pubkey,
     pub name: String,
     pub timestamp: i64,
+    pub sandbox: bool,
+    pub referrer: Option<Pubkey>,
+    pub referrer_bps: u16,
+    pub accepts_any_token: bool,
+    pub metadata_uri: String,
+    pub category: u8,
+    pub merchant_index: u64,
+    pub event_seq: u64,
 }
 
 #[event]
 pub struct MerchantUpdated {
     pub owner: Pubkey,
+    pub updated_by: Pubkey,
     pub merchant: Pubkey,
-    pub old_name: String,
-    pub new_name: Option<String>,
+    pub name: String,
     pub settlement_wallet: Option<Pubkey>,
     pub settlement_token: Option<Pubkey>,
     pub swap_enabled: Option<bool>,
+    pub escrow_enabled: Option<bool>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    pub accepts_any_token: Option<bool>,
     pub timestamp: i64,
+    pub metadata_uri: Option<String>,
+    pub category: Option<u8>,
 }
 
 #[event]
-pub struct FeeUpdated {
+pub struct MerchantOwnershipProposed {
+    pub owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub merchant: Pubkey,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantOwnershipTransferred {
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub old_merchant: Pubkey,
+    pub new_merchant: Pubkey,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantRenamed {
+    pub owner: Pubkey,
+    pub old_merchant: Pubkey,
+    pub new_merchant: Pubkey,
+    pub old_name: String,
+    pub new_name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantIndexed {
+    pub merchant: Pubkey,
+    pub merchant_index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantClosed {
+    pub owner: Pubkey,
+    pub merchant: Pubkey,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SelfPaymentsUpdated {
+    pub merchant: Pubkey,
+    pub allowed: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PreviousMerchantReleased {
+    pub owner: Pubkey,
+    pub merchant: Pubkey,
+    pub previous_merchant: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TaxRateUpdated {
+    pub merchant: Pubkey,
+    pub tax_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConfirmationMessageUpdated {
+    pub merchant: Pubkey,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayerIntervalUpdated {
+    pub merchant: Pubkey,
+    pub min_interval_per_payer: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SettlementRouteUpdated {
+    pub merchant: Pubkey,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    // None once cleared
+    pub destination: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantPing {
+    pub merchant: Pubkey,
+    pub owner: Pubkey,
+    pub nonce: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeProposed {
     pub admin: Pubkey,
+    pub current_fee_bps: u16,
+    pub pending_fee_bps: u16,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeUpdated {
+    // apply_fee is permissionless once the timelock passes
+    pub applied_by: Pubkey,
     pub old_fee_bps: u16,
     pub new_fee_bps: u16,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct FeeProposalCancelled {
+    pub admin: Pubkey,
+    pub pending_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeTimelockUpdated {
+    pub admin: Pubkey,
+    pub old_timelock_seconds: i64,
+    pub new_timelock_seconds: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantFeeExemptionUpdated {
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    pub fee_exempt: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantReferrerUpdated {
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    pub referrer: Option<Pubkey>,
+    pub referrer_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantFeeUpdated {
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    pub old_fee_bps_override: Option<u16>,
+    pub new_fee_bps_override: Option<u16>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantDailyLimitUpdated {
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    pub old_limit: u64,
+    // 0 is unlimited
+    pub new_limit: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantFrozen {
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    // Off-chain reason code; the program only records it
+    pub reason: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantUnfrozen {
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    pub reason: u8,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct FeeWalletUpdated {
     pub admin: Pubkey,
@@ -52,8 +866,50 @@ pub struct FeeWalletUpdated {
 }
 
 #[event]
-pub struct PausedStatusUpdated {
+pub struct FeeStatsOpened {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub fee_vault: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowReleaseDelayUpdated {
+    pub admin: Pubkey,
+    pub old_delay: i64,
+    pub new_delay: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuardianUpdated {
+    pub admin: Pubkey,
+    pub old_guardian: Pubkey,
+    pub new_guardian: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ArbiterUpdated {
     pub admin: Pubkey,
+    pub old_arbiter: Pubkey,
+    pub new_arbiter: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PausedStatusUpdated {
+    // Admin or guardian that signed
+    pub authority: Pubkey,
     pub paused: bool,
     pub timestamp: i64,
 }
@@ -65,10 +921,89 @@ pub struct AdminUpdated {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct AdminTransferProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferCancelled {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GlobalStateMigrated {
+    pub admin: Pubkey,
+    pub old_len: u64,
+    pub new_len: u64,
+    pub version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantMigrated {
+    pub merchant: Pubkey,
+    pub accepts_any_token: bool,
+    pub old_len: u64,
+    pub new_len: u64,
+    pub version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerchantAccountUpgraded {
+    pub merchant: Pubkey,
+    pub owner: Pubkey,
+    pub old_len: u64,
+    pub new_len: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentAccountUpgraded {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub old_len: u64,
+    pub new_len: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeatureToggled {
+    pub admin: Pubkey,
+    pub feature: Feature,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct GlobalStateInitialized {
     pub admin: Pubkey,
     pub fee_bps: u16,
     pub fee_wallet: Pubkey,
     pub timestamp: i64,
-}
\ No newline at end of file
+    pub max_fee_bps: u16,
+    pub payer: Pubkey,
+    pub guardian: Pubkey,
+}
+/// Emits `$event` through a self-CPI (`emit_cpi!`), so it survives in the
+/// transaction's inner instructions even when logs are truncated. The accounts
+/// struct needs `#[event_cpi]`. Building with `log-events` falls back to a plain
+/// `emit!` for localnet tooling that still parses logs.
+macro_rules! emit_event {
+    ($ctx:ident, $event:expr) => {{
+        let event = $event;
+        #[cfg(not(feature = "log-events"))]
+        {
+            let ctx = &$ctx;
+            anchor_lang::prelude::emit_cpi!(event);
+        }
+        #[cfg(feature = "log-events")]
+        anchor_lang::prelude::emit!(event);
+    }};
+}
+pub(crate) use emit_event;
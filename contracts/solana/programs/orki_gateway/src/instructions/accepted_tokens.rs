@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+use crate::state::{AcceptedTokens, Merchant, MAX_ACCEPTED_TOKENS};
+use crate::events::AcceptedTokensUpdated;
+use crate::errors::ErrorCode;
+use crate::constants::{ACCEPTED_TOKENS_SEED, MERCHANT_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct AddAcceptedToken<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AcceptedTokens::INIT_SPACE,
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub accepted_tokens: Account<'info, AcceptedTokens>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Pass `Pubkey::default()` to accept native SOL. While the list is non-empty
+/// it replaces `settlement_token` for process_payment.
+pub fn add_accepted_token(ctx: Context<AddAcceptedToken>, _name: String, mint: Pubkey) -> Result<()> {
+    let accepted = &mut ctx.accounts.accepted_tokens;
+    require!(!accepted.accepts(&mint), ErrorCode::TokenAlreadyAccepted);
+    require!(accepted.mints.len() < MAX_ACCEPTED_TOKENS, ErrorCode::TooManyAcceptedTokens);
+
+    accepted.merchant = ctx.accounts.merchant.key();
+    accepted.bump = ctx.bumps.accepted_tokens;
+    accepted.mints.push(mint);
+
+    // process_payment refuses to pay such a merchant without the list
+    ctx.accounts.merchant.accepted_tokens_enabled = true;
+
+    // Emit event
+    emit!(AcceptedTokensUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        mints: accepted.mints.clone(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RemoveAcceptedToken<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump = accepted_tokens.bump
+    )]
+    pub accepted_tokens: Account<'info, AcceptedTokens>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Removing the last mint falls back to the single `settlement_token` rule.
+pub fn remove_accepted_token(ctx: Context<RemoveAcceptedToken>, _name: String, mint: Pubkey) -> Result<()> {
+    let accepted = &mut ctx.accounts.accepted_tokens;
+    let index = accepted
+        .mints
+        .iter()
+        .position(|m| m == &mint)
+        .ok_or(ErrorCode::TokenNotAccepted)?;
+    accepted.mints.remove(index);
+
+    ctx.accounts.merchant.accepted_tokens_enabled = !accepted.mints.is_empty();
+
+    // Emit event
+    emit!(AcceptedTokensUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        mints: accepted.mints.clone(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -1,42 +1,209 @@
 use anchor_lang::prelude::*;
-use crate::state::GlobalState;
+use crate::state::{AdminAction, AdminProposal, DailyVolume, FeeRounding, GlobalState, Merchant, Operator, OperatorPermission};
 use crate::errors::ErrorCode;
 use crate::events::*; 
+use crate::clock;
+use crate::constants::{ADMIN_PROPOSAL_SEED, BPS_DENOMINATOR, DAILY_VOLUME_SEED, GLOBAL_STATE_SEED, MERCHANT_SEED, OPERATOR_SEED};
+use crate::instructions::authorize_admin_action;
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct AdminAuth<'info> {
     #[account(
         mut,
-        seeds = [b"global_state"],
+        seeds = [GLOBAL_STATE_SEED],
         bump = global_state.bump,
-        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
     )]
     pub global_state: Account<'info, GlobalState>,
     pub admin: Signer<'info>,
 }
 
-pub fn set_fee(ctx: Context<AdminAuth>, new_fee_bps: u16) -> Result<()> {
-    require!(new_fee_bps <= 10000, ErrorCode::InvalidFee);
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct MerchantAdminAuth<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub admin: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct MerchantFeeAuth<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    // The admin, or anyone carrying out a council-approved change
+    pub admin: Signer<'info>,
+
+    // Required once an admin council governs; see authorize_admin_action
+    #[account(
+        mut,
+        seeds = [ADMIN_PROPOSAL_SEED, &admin_proposal.nonce.to_le_bytes()],
+        bump = admin_proposal.bump
+    )]
+    pub admin_proposal: Option<Account<'info, AdminProposal>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetMerchantActive<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    // The merchant owner, an operator with Pause, or the global admin
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, merchant.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+}
+
+/// Fee changes are two-step: the proposal can only be applied once
+/// `fee_timelock_seconds` have passed, giving merchants notice. A new proposal
+/// replaces any pending one and restarts the timelock.
+pub fn propose_fee(ctx: Context<AdminAuth>, new_fee_bps: u16) -> Result<()> {
+    require!(!ctx.accounts.global_state.admin_council, ErrorCode::CouncilGoverned);
+    ctx.accounts.global_state.check_fee(new_fee_bps)?;
+    let now = clock::now(ctx.remaining_accounts)?;
     let state = &mut ctx.accounts.global_state;
     
+    let effective_at = state.fee_effective_time(now)?;
+    state.pending_fee_bps = Some(new_fee_bps);
+    state.fee_effective_at = effective_at;
+    
+    // Emit event
+    emit_event!(ctx, FeeProposed {
+        admin: ctx.accounts.admin.key(),
+        current_fee_bps: state.fee_bps,
+        pending_fee_bps: new_fee_bps,
+        effective_at,
+        timestamp: now,
+    });
+    
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApplyFee<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    // Anyone may apply a proposal whose timelock has passed
+    pub caller: Signer<'info>,
+}
+
+pub fn apply_fee(ctx: Context<ApplyFee>) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let state = &mut ctx.accounts.global_state;
+    
+    let new_fee_bps = state.pending_fee_bps.ok_or(ErrorCode::NoPendingFee)?;
+    require!(now >= state.fee_effective_at, ErrorCode::FeeTimelockNotElapsed);
+    // The cap may have been lowered since the proposal
+    state.check_fee(new_fee_bps)?;
+    
     // Store old value for event
     let old_fee_bps = state.fee_bps;
     
     state.fee_bps = new_fee_bps;
+    state.pending_fee_bps = None;
+    state.fee_effective_at = 0;
     
     // Emit event
-    emit!(FeeUpdated {
-        admin: ctx.accounts.admin.key(),
+    emit_event!(ctx, FeeUpdated {
+        applied_by: ctx.accounts.caller.key(),
         old_fee_bps,
         new_fee_bps,
+        timestamp: now,
+    });
+    
+    Ok(())
+}
+
+pub fn cancel_fee_proposal(ctx: Context<AdminAuth>) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    require!(!state.admin_council, ErrorCode::CouncilGoverned);
+    let pending_fee_bps = state.pending_fee_bps.take().ok_or(ErrorCode::NoPendingFee)?;
+    state.fee_effective_at = 0;
+    
+    // Emit event
+    emit_event!(ctx, FeeProposalCancelled {
+        admin: ctx.accounts.admin.key(),
+        pending_fee_bps,
         timestamp: Clock::get()?.unix_timestamp,
     });
     
     Ok(())
 }
 
+/// See GlobalState::set_fee_timelock.
+pub fn set_fee_timelock(ctx: Context<AdminAuth>, new_timelock_seconds: i64) -> Result<()> {
+    require!(!ctx.accounts.global_state.admin_council, ErrorCode::CouncilGoverned);
+    let now = clock::now(ctx.remaining_accounts)?;
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_timelock_seconds = state.fee_timelock_seconds;
+    
+    state.set_fee_timelock(new_timelock_seconds, now)?;
+    
+    // Emit event
+    emit_event!(ctx, FeeTimelockUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_timelock_seconds,
+        new_timelock_seconds,
+        timestamp: now,
+    });
+    
+    Ok(())
+}
+
 pub fn set_fee_wallet(ctx: Context<AdminAuth>, new_fee_wallet: Pubkey) -> Result<()> {
     let state = &mut ctx.accounts.global_state;
+    require!(!state.admin_council, ErrorCode::CouncilGoverned);
     
     // Store old value for event
     let old_fee_wallet = state.fee_wallet;
@@ -44,7 +211,7 @@ pub fn set_fee_wallet(ctx: Context<AdminAuth>, new_fee_wallet: Pubkey) -> Result
     state.fee_wallet = new_fee_wallet;
     
     // Emit event
-    emit!(FeeWalletUpdated {
+    emit_event!(ctx, FeeWalletUpdated {
         admin: ctx.accounts.admin.key(),
         old_fee_wallet,
         new_fee_wallet,
@@ -54,13 +221,221 @@ pub fn set_fee_wallet(ctx: Context<AdminAuth>, new_fee_wallet: Pubkey) -> Result
     Ok(())
 }
 
-pub fn set_paused(ctx: Context<AdminAuth>, paused: bool) -> Result<()> {
+pub fn set_escrow_release_delay(ctx: Context<AdminAuth>, new_delay: i64) -> Result<()> {
+    require!(new_delay >= 0, ErrorCode::InvalidAmount);
     let state = &mut ctx.accounts.global_state;
-    state.paused = paused;
+    
+    // Store old value for event
+    let old_delay = state.escrow_release_delay;
+    
+    state.escrow_release_delay = new_delay;
+    
+    // Emit event
+    emit_event!(ctx, EscrowReleaseDelayUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_delay,
+        new_delay,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_payment_retention(ctx: Context<AdminAuth>, new_retention: i64) -> Result<()> {
+    require!(new_retention > 0, ErrorCode::InvalidAmount);
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_retention = state.payment_retention_seconds;
+    
+    state.payment_retention_seconds = new_retention;
+    
+    // Emit event
+    emit_event!(ctx, PaymentRetentionUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_retention,
+        new_retention,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_finality_slots(ctx: Context<AdminAuth>, new_slots: u64) -> Result<()> {
+    require!(new_slots > 0, ErrorCode::InvalidAmount);
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_slots = state.finality_slots;
+    
+    state.finality_slots = new_slots;
+    
+    // Emit event
+    emit_event!(ctx, FinalitySlotsUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_slots,
+        new_slots,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Lowers the fee cap that every fee setter validates against. There is no way
+/// to raise it again, so merchants can rely on it as a ceiling. The global fee
+/// must already be at or below the new cap; a pending proposal above it can no
+/// longer be applied. Merchant overrides, token fees and tiers set earlier are
+/// left as they are.
+pub fn reduce_max_fee(ctx: Context<AdminAuth>, new_max_fee_bps: u16) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    require!(new_max_fee_bps > 0, ErrorCode::InvalidFee);
+    require!(new_max_fee_bps <= state.fee_cap(), ErrorCode::CannotRaiseCap);
+    require!(state.fee_bps <= new_max_fee_bps, ErrorCode::FeeCapExceeded);
+    
+    // Store old value for event
+    let old_max_fee_bps = state.fee_cap();
+    
+    state.max_fee_bps = new_max_fee_bps;
+    
+    // Emit event
+    emit_event!(ctx, MaxFeeReduced {
+        admin: ctx.accounts.admin.key(),
+        old_max_fee_bps,
+        new_max_fee_bps,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Switches how every fee rounds a fraction of a base unit from now on.
+/// Payments already made keep the fee they were charged.
+pub fn set_fee_rounding(ctx: Context<AdminAuth>, new_rounding: FeeRounding) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_rounding = state.fee_rounding;
+    
+    state.fee_rounding = new_rounding;
+    
+    // Emit event
+    emit_event!(ctx, FeeRoundingUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_rounding,
+        new_rounding,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Sets what the fee vault pays per due crank_payout; 0 stops rewards.
+pub fn set_crank_reward(ctx: Context<AdminAuth>, new_reward: u64) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_reward = state.crank_reward;
+    
+    state.crank_reward = new_reward;
+    
+    // Emit event
+    emit_event!(ctx, CrankRewardUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_reward,
+        new_reward,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_authorization_expiry(ctx: Context<AdminAuth>, new_expiry: i64) -> Result<()> {
+    require!(new_expiry > 0, ErrorCode::InvalidAmount);
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_expiry = state.authorization_expiry;
+    
+    state.authorization_expiry = new_expiry;
+    
+    // Emit event
+    emit_event!(ctx, AuthorizationExpiryUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_expiry,
+        new_expiry,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_guardian(ctx: Context<AdminAuth>, new_guardian: Pubkey) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    
+    // Store old value for event
+    let old_guardian = state.guardian;
+    
+    state.guardian = new_guardian;
     
     // Emit event
-    emit!(PausedStatusUpdated {
+    emit_event!(ctx, GuardianUpdated {
         admin: ctx.accounts.admin.key(),
+        old_guardian,
+        new_guardian,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Sets who resolves escrow disputes; the default key stops new disputes.
+pub fn set_arbiter(ctx: Context<AdminAuth>, new_arbiter: Pubkey) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    let old_arbiter = state.arbiter;
+    state.arbiter = new_arbiter;
+
+    emit_event!(ctx, ArbiterUpdated {
+        admin: ctx.accounts.admin.key(),
+        old_arbiter,
+        new_arbiter,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    // The admin, or the guardian when pausing
+    pub authority: Signer<'info>,
+}
+
+pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    let authority = ctx.accounts.authority.key();
+    if authority == state.admin {
+        // The guardian may still pause alongside a council
+        require!(!state.admin_council, ErrorCode::CouncilGoverned);
+    } else {
+        require!(
+            state.guardian != Pubkey::default() && authority == state.guardian,
+            ErrorCode::Unauthorized
+        );
+        require!(paused, ErrorCode::GuardianCannotUnpause);
+    }
+    state.paused = paused;
+    
+    // Emit event
+    emit_event!(ctx, PausedStatusUpdated {
+        authority,
         paused,
         timestamp: Clock::get()?.unix_timestamp,
     });
@@ -68,20 +443,267 @@ pub fn set_paused(ctx: Context<AdminAuth>, paused: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn update_admin(ctx: Context<AdminAuth>, new_admin: Pubkey) -> Result<()> {
+pub fn propose_admin(ctx: Context<AdminAuth>, new_admin: Pubkey) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    require!(!state.admin_council, ErrorCode::CouncilGoverned);
+    state.pending_admin = Some(new_admin);
+    
+    // Emit event
+    emit_event!(ctx, AdminTransferProposed {
+        admin: ctx.accounts.admin.key(),
+        pending_admin: new_admin,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn cancel_admin_transfer(ctx: Context<AdminAuth>) -> Result<()> {
+    let state = &mut ctx.accounts.global_state;
+    require!(!state.admin_council, ErrorCode::CouncilGoverned);
+    let pending_admin = state.pending_admin.take().ok_or(ErrorCode::NoPendingAdmin)?;
+    
+    // Emit event
+    emit_event!(ctx, AdminTransferCancelled {
+        admin: ctx.accounts.admin.key(),
+        pending_admin,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.pending_admin == Some(new_admin.key()) @ ErrorCode::NotPendingAdmin,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub new_admin: Signer<'info>,
+}
+
+pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
     let state = &mut ctx.accounts.global_state;
     
     // Store old value for event
     let old_admin = state.admin;
     
-    state.admin = new_admin;
+    state.admin = ctx.accounts.new_admin.key();
+    state.pending_admin = None;
     
     // Emit event
-    emit!(AdminUpdated {
+    emit_event!(ctx, AdminUpdated {
         old_admin,
-        new_admin,
+        new_admin: state.admin,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_merchant_fee_exempt(
+    ctx: Context<MerchantFeeAuth>,
+    _name: String,
+    fee_exempt: bool,
+) -> Result<()> {
+    authorize_admin_action(
+        &ctx.accounts.global_state,
+        &ctx.accounts.admin.key(),
+        ctx.accounts.admin_proposal.as_deref_mut(),
+        AdminAction::SetMerchantFeeExempt { merchant: ctx.accounts.merchant.key(), fee_exempt },
+    )?;
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.fee_exempt = fee_exempt;
+    
+    // Emit event
+    emit_event!(ctx, MerchantFeeExemptionUpdated {
+        admin: ctx.accounts.admin.key(),
+        merchant: merchant.key(),
+        fee_exempt,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_merchant_referrer(
+    ctx: Context<MerchantAdminAuth>,
+    _name: String,
+    referrer: Option<Pubkey>,
+    referrer_bps: u16,
+) -> Result<()> {
+    require!(referrer.is_some() || referrer_bps == 0, ErrorCode::InvalidReferrer);
+    require!(referrer_bps <= BPS_DENOMINATOR, ErrorCode::ReferrerShareTooHigh);
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.referrer = referrer.unwrap_or_default();
+    merchant.referrer_bps = referrer_bps;
+    
+    // Emit event
+    emit_event!(ctx, MerchantReferrerUpdated {
+        admin: ctx.accounts.admin.key(),
+        merchant: merchant.key(),
+        referrer,
+        referrer_bps,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+pub fn set_merchant_fee(
+    ctx: Context<MerchantFeeAuth>,
+    _name: String,
+    fee_bps_override: Option<u16>,
+) -> Result<()> {
+    authorize_admin_action(
+        &ctx.accounts.global_state,
+        &ctx.accounts.admin.key(),
+        ctx.accounts.admin_proposal.as_deref_mut(),
+        AdminAction::SetMerchantFee { merchant: ctx.accounts.merchant.key(), fee_bps_override },
+    )?;
+    if let Some(bps) = fee_bps_override {
+        ctx.accounts.global_state.check_fee(bps)?;
+    }
+    let merchant = &mut ctx.accounts.merchant;
+    
+    // Store old value for event
+    let old_fee_bps_override = merchant.fee_bps_override;
+    
+    merchant.fee_bps_override = fee_bps_override;
+    
+    // Emit event
+    emit_event!(ctx, MerchantFeeUpdated {
+        admin: ctx.accounts.admin.key(),
+        merchant: merchant.key(),
+        old_fee_bps_override,
+        new_fee_bps_override: fee_bps_override,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetMerchantDailyLimit<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    // Opened the first time a limit is set, with the admin paying its rent
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + DailyVolume::INIT_SPACE,
+        seeds = [DAILY_VOLUME_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub daily_volume: Account<'info, DailyVolume>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Caps what the merchant may receive through process_payment per UTC day;
+/// 0 lifts the cap. Lowering it mid-day counts what was already received.
+pub fn set_merchant_daily_limit(
+    ctx: Context<SetMerchantDailyLimit>,
+    _name: String,
+    daily_limit: u64,
+) -> Result<()> {
+    let daily_volume = &mut ctx.accounts.daily_volume;
+    daily_volume.merchant = ctx.accounts.merchant.key();
+    daily_volume.bump = ctx.bumps.daily_volume;
+
+    let merchant = &mut ctx.accounts.merchant;
+    
+    // Store old value for event
+    let old_limit = merchant.daily_limit;
+    
+    merchant.daily_limit = daily_limit;
+    
+    // Emit event
+    emit_event!(ctx, MerchantDailyLimitUpdated {
+        admin: ctx.accounts.admin.key(),
+        merchant: merchant.key(),
+        old_limit,
+        new_limit: daily_limit,
         timestamp: Clock::get()?.unix_timestamp,
     });
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Stops the merchant taking payments or moving funds out of the program
+/// until the admin unfreezes it. The owner cannot lift it, and refunds to
+/// payers stay open.
+pub fn freeze_merchant(ctx: Context<MerchantAdminAuth>, _name: String, reason: u8) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.admin_frozen = true;
+
+    emit_event!(ctx, MerchantFrozen {
+        admin: ctx.accounts.admin.key(),
+        merchant: merchant.key(),
+        reason,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+pub fn unfreeze_merchant(ctx: Context<MerchantAdminAuth>, _name: String, reason: u8) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.admin_frozen = false;
+
+    emit_event!(ctx, MerchantUnfrozen {
+        admin: ctx.accounts.admin.key(),
+        merchant: merchant.key(),
+        reason,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+pub fn set_merchant_active(ctx: Context<SetMerchantActive>, _name: String, active: bool) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    let is_admin = authority == ctx.accounts.global_state.admin;
+    let merchant = &mut ctx.accounts.merchant;
+
+    if is_admin {
+        // Admin deactivation locks the merchant out of reactivating itself
+        merchant.admin_locked = !active;
+    } else {
+        Operator::authorize(merchant, &authority, ctx.accounts.operator.as_deref(), OperatorPermission::Pause)?;
+        require!(!merchant.admin_locked, ErrorCode::MerchantAdminLocked);
+    }
+
+    merchant.active = active;
+
+    // Emit event
+    emit_event!(ctx, MerchantStatusChanged {
+        merchant: merchant.key(),
+        active,
+        admin_locked: merchant.admin_locked,
+        changed_by: authority,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use anchor_lang::prelude::*;
+use crate::state::{AdminAction, AdminCouncil, AdminProposal, FeeTiers, GlobalState};
+use crate::errors::ErrorCode;
+use crate::events::{emit_event, AdminActionApproved, AdminActionExecuted, AdminActionProposed, AdminCouncilCreated};
+use crate::constants::{ADMIN_COUNCIL_SEED, ADMIN_PROPOSAL_LIFETIME, ADMIN_PROPOSAL_SEED, GLOBAL_STATE_SEED};
+use crate::clock;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateAdminCouncil<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + AdminCouncil::INIT_SPACE,
+        seeds = [ADMIN_COUNCIL_SEED],
+        bump
+    )]
+    pub admin_council: Account<'info, AdminCouncil>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Hands the fee setting, fee wallet, pause, fee withdrawal and admin
+/// transfer powers (see AdminAction) from the single admin to a council of
+/// `members`, `threshold` of whom must approve each change. There is no way
+/// back to single-admin mode; the council can only be reshaped.
+pub fn create_admin_council(ctx: Context<CreateAdminCouncil>, members: Vec<Pubkey>, threshold: u8) -> Result<()> {
+    AdminCouncil::validate(&members, threshold)?;
+    let council = &mut ctx.accounts.admin_council;
+    council.members = members.clone();
+    council.threshold = threshold;
+    council.next_nonce = 0;
+    council.min_nonce = 0;
+    council.bump = ctx.bumps.admin_council;
+    ctx.accounts.global_state.admin_council = true;
+
+    emit_event!(ctx, AdminCouncilCreated {
+        admin: ctx.accounts.admin.key(),
+        council: ctx.accounts.admin_council.key(),
+        members,
+        threshold,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct ProposeAdminAction<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [ADMIN_COUNCIL_SEED],
+        bump = admin_council.bump,
+        constraint = admin_council.next_nonce == nonce @ ErrorCode::InvalidProposalNonce
+    )]
+    pub admin_council: Account<'info, AdminCouncil>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + AdminProposal::INIT_SPACE,
+        seeds = [ADMIN_PROPOSAL_SEED, &nonce.to_le_bytes()],
+        bump
+    )]
+    pub admin_proposal: Account<'info, AdminProposal>,
+
+    // A council member; the proposal counts as their approval
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens a proposal for `action` under the council's next nonce. It stays
+/// open for ADMIN_PROPOSAL_LIFETIME and executes as soon as enough members
+/// have approved, which with a threshold of 1 is right away.
+pub fn propose_admin_action(ctx: Context<ProposeAdminAction>, nonce: u64, action: AdminAction) -> Result<()> {
+    check_action(&ctx.accounts.global_state, &action)?;
+    let now = clock::now(ctx.remaining_accounts)?;
+    let proposer = ctx.accounts.proposer.key();
+    let council = &mut ctx.accounts.admin_council;
+    let index = council.member_index(&proposer)?;
+    council.next_nonce = nonce.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+
+    let expires_at = now
+        .checked_add(ADMIN_PROPOSAL_LIFETIME)
+        .ok_or(ErrorCode::CalculationError)?;
+    let proposal = &mut ctx.accounts.admin_proposal;
+    proposal.nonce = nonce;
+    proposal.proposer = proposer;
+    proposal.approvals = 1 << index;
+    proposal.expires_at = expires_at;
+    proposal.executed = false;
+    proposal.consumed = false;
+    proposal.bump = ctx.bumps.admin_proposal;
+    proposal.action = action.clone();
+
+    emit_event!(ctx, AdminActionProposed {
+        proposal: ctx.accounts.admin_proposal.key(),
+        nonce,
+        proposer,
+        action,
+        expires_at,
+        timestamp: now,
+    });
+
+    if ctx.accounts.admin_proposal.approval_count() >= ctx.accounts.admin_council.threshold {
+        execute(
+            &mut ctx.accounts.global_state,
+            &mut ctx.accounts.admin_council,
+            &mut ctx.accounts.admin_proposal,
+            now,
+        )?;
+        emit_event!(ctx, AdminActionExecuted {
+            proposal: ctx.accounts.admin_proposal.key(),
+            nonce,
+            action: ctx.accounts.admin_proposal.action.clone(),
+            timestamp: now,
+        });
+    }
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApproveAdminAction<'info> {
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [ADMIN_COUNCIL_SEED],
+        bump = admin_council.bump
+    )]
+    pub admin_council: Account<'info, AdminCouncil>,
+
+    #[account(
+        mut,
+        seeds = [ADMIN_PROPOSAL_SEED, &admin_proposal.nonce.to_le_bytes()],
+        bump = admin_proposal.bump
+    )]
+    pub admin_proposal: Account<'info, AdminProposal>,
+
+    pub member: Signer<'info>,
+}
+
+/// Adds the signing member's approval, executing the proposal once it
+/// reaches the council's threshold.
+pub fn approve_admin_action(ctx: Context<ApproveAdminAction>) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let member = ctx.accounts.member.key();
+    let council = &ctx.accounts.admin_council;
+    let proposal = &mut ctx.accounts.admin_proposal;
+    require!(!proposal.executed, ErrorCode::ProposalAlreadyExecuted);
+    require!(proposal.nonce >= council.min_nonce, ErrorCode::ProposalStale);
+    require!(now < proposal.expires_at, ErrorCode::ProposalExpired);
+    let bit = 1 << council.member_index(&member)?;
+    require!(proposal.approvals & bit == 0, ErrorCode::AlreadyApproved);
+    proposal.approvals |= bit;
+
+    let approvals = proposal.approval_count();
+    let nonce = proposal.nonce;
+    emit_event!(ctx, AdminActionApproved {
+        proposal: ctx.accounts.admin_proposal.key(),
+        nonce,
+        member,
+        approvals,
+        timestamp: now,
+    });
+
+    if approvals >= ctx.accounts.admin_council.threshold {
+        execute(
+            &mut ctx.accounts.global_state,
+            &mut ctx.accounts.admin_council,
+            &mut ctx.accounts.admin_proposal,
+            now,
+        )?;
+        emit_event!(ctx, AdminActionExecuted {
+            proposal: ctx.accounts.admin_proposal.key(),
+            nonce,
+            action: ctx.accounts.admin_proposal.action.clone(),
+            timestamp: now,
+        });
+    }
+
+    Ok(())
+}
+
+// Rejects an action up front that could never execute
+fn check_action(state: &GlobalState, action: &AdminAction) -> Result<()> {
+    match action {
+        AdminAction::SetFee { new_fee_bps } => state.check_fee(*new_fee_bps),
+        AdminAction::UpdateCouncil { members, threshold } => AdminCouncil::validate(members, *threshold),
+        AdminAction::SetFeeTimelock { new_timelock_seconds } => {
+            require!(*new_timelock_seconds >= 0, ErrorCode::InvalidAmount);
+            Ok(())
+        }
+        AdminAction::WithdrawFees { amount, .. } => {
+            require!(*amount > 0, ErrorCode::InvalidAmount);
+            Ok(())
+        }
+        AdminAction::SetTokenFee { fee_bps, .. } => state.check_fee(*fee_bps),
+        AdminAction::SetFeeTiers { tiers } => FeeTiers::validate(tiers, state.fee_cap()),
+        AdminAction::SetMerchantFee { fee_bps_override, .. } => {
+            fee_bps_override.map_or(Ok(()), |bps| state.check_fee(bps))
+        }
+        AdminAction::SetFeeWallet { .. }
+        | AdminAction::SetPaused { .. }
+        | AdminAction::ProposeAdmin { .. }
+        | AdminAction::SetMerchantFeeExempt { .. } => Ok(()),
+    }
+}
+
+fn execute(
+    state: &mut GlobalState,
+    council: &mut AdminCouncil,
+    proposal: &mut AdminProposal,
+    now: i64,
+) -> Result<()> {
+    // State may have moved on since the proposal was checked
+    check_action(state, &proposal.action)?;
+    proposal.executed = true;
+    match &proposal.action {
+        AdminAction::SetFee { new_fee_bps } => {
+            state.pending_fee_bps = Some(*new_fee_bps);
+            state.fee_effective_at = state.fee_effective_time(now)?;
+        }
+        AdminAction::SetFeeWallet { new_fee_wallet } => state.fee_wallet = *new_fee_wallet,
+        AdminAction::SetPaused { paused } => state.paused = *paused,
+        AdminAction::UpdateCouncil { members, threshold } => {
+            council.members = members.clone();
+            council.threshold = *threshold;
+            // Approval bits index the old membership
+            council.min_nonce = council.next_nonce;
+        }
+        AdminAction::ProposeAdmin { new_admin } => state.pending_admin = Some(*new_admin),
+        AdminAction::SetFeeTimelock { new_timelock_seconds } => {
+            state.set_fee_timelock(*new_timelock_seconds, now)?;
+        }
+        // Carried out by the action's own instruction; see authorize_admin_action
+        AdminAction::WithdrawFees { .. }
+        | AdminAction::SetTokenFee { .. }
+        | AdminAction::SetFeeTiers { .. }
+        | AdminAction::SetMerchantFee { .. }
+        | AdminAction::SetMerchantFeeExempt { .. } => {}
+    }
+    Ok(())
+}
+
+/// Gatekeeper for the instructions behind deferred AdminActions. Without a
+/// council the admin signs directly; with one, `proposal` must be an executed
+/// proposal for exactly `action`, and it is used up here so the same
+/// approval cannot run twice.
+pub fn authorize_admin_action(
+    state: &GlobalState,
+    signer: &Pubkey,
+    proposal: Option<&mut AdminProposal>,
+    action: AdminAction,
+) -> Result<()> {
+    let Some(proposal) = proposal else {
+        require!(!state.admin_council, ErrorCode::CouncilGoverned);
+        require!(*signer == state.admin, ErrorCode::Unauthorized);
+        return Ok(());
+    };
+    require!(proposal.executed && !proposal.consumed, ErrorCode::ProposalNotApproved);
+    require!(proposal.action == action, ErrorCode::ProposalActionMismatch);
+    proposal.consumed = true;
+    Ok(())
+}
@@ -0,0 +1,549 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{Feature, FeatureFlags, FeeStats, FeeTiers, FeeVault, GlobalState, GlobalStats, Merchant, MerchantStats, Payment, PaymentId, PaymentStatus, TokenFeeConfig};
+use crate::events::{emit_event, PaymentAuthorized, PaymentCaptured, PaymentVoided};
+use crate::errors::ErrorCode;
+use crate::constants::{DEFAULT_AUTHORIZATION_EXPIRY, FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_TIERS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, PAYMENT_SEED, TOKEN_FEE_SEED};
+use crate::instructions::{reject_transfer_fee_mint, require_unfrozen, PaymentQuote};
+use crate::clock;
+
+// Authorized funds are held by the Payment PDA itself: SOL as lamports on the
+// account, SPL in a token account whose authority is the Payment PDA.
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, payment_id: u64, name: String)]
+pub struct AuthorizePayment<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [PAYMENT_SEED, payer.key().as_ref(), &PaymentId::Sequence(payment_id).seed()],
+        bump
+    )]
+    pub payment_history: Account<'info, Payment>,
+
+    pub system_program: Program<'info, System>,
+
+    // --- Optional Accounts for SPL (classic Token or Token-2022) ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Token account owned by the Payment PDA, created by the client beforehand
+    #[account(mut)]
+    pub hold_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Required only for Token-2022 payments while that rollout is gated
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+}
+
+pub fn authorize_payment(
+    ctx: Context<AuthorizePayment>,
+    amount: u64,
+    payment_id: u64,
+    _name: String,
+) -> Result<()> {
+    let state = &ctx.accounts.global_state;
+    let merchant = &ctx.accounts.merchant;
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    require!(!ctx.accounts.payment_history.is_recorded(), ErrorCode::DuplicatePayment);
+    require!(!state.paused, ErrorCode::Paused);
+    require!(merchant.active, ErrorCode::MerchantInactive);
+    require!(!merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    merchant.check_amount(amount)?;
+    // Capture pays the settlement wallet directly
+    require!(
+        !merchant.escrow_enabled && !merchant.split_enabled,
+        ErrorCode::UnsupportedSettlementMode
+    );
+
+    let token = ctx.accounts.mint.as_ref().map(|m| m.key()).unwrap_or_default();
+
+    if let Some(token_program) = ctx.accounts.token_program.as_ref() {
+        // --- SPL TOKEN HOLD ---
+        let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let hold_ta = ctx.accounts.hold_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        if token_program.key() == anchor_spl::token_2022::ID {
+            FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+            reject_transfer_fee_mint(mint)?;
+        }
+
+        merchant.check_token(&mint.key())?;
+
+        require!(payer_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(payer_ta.owner == ctx.accounts.payer.key(), ErrorCode::InvalidPayerTokenAccount);
+        require!(hold_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(
+            hold_ta.owner == ctx.accounts.payment_history.key(),
+            ErrorCode::OwnerMismatch
+        );
+        require!(payer_ta.amount >= amount, ErrorCode::InsufficientBalance);
+
+        if !merchant.sandbox {
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: payer_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: hold_ta.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
+    } else {
+        // --- NATIVE SOL HOLD ---
+        merchant.check_token(&Pubkey::default())?;
+        require!(ctx.accounts.payer.lamports() >= amount, ErrorCode::InsufficientBalance);
+
+        if !merchant.sandbox {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: ctx.accounts.payment_history.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+    }
+
+    let payment = &mut ctx.accounts.payment_history;
+    payment.payer = ctx.accounts.payer.key();
+    payment.merchant = merchant.key();
+    payment.amount = amount;
+    payment.payment_id = payment_id;
+    payment.timestamp = now;
+    payment.bump = ctx.bumps.payment_history;
+    payment.sandbox = merchant.sandbox;
+    payment.status = PaymentStatus::Authorized;
+    payment.mint = token;
+    payment.slot = Clock::get()?.slot;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_authorizations = merchant.open_authorizations.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+
+    emit_event!(ctx, PaymentAuthorized {
+        payer: payment.payer,
+        merchant: payment.merchant,
+        amount,
+        token,
+        payment_id,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CapturePayment<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payment.payer.as_ref(), &payment.id().seed()],
+        bump = payment.bump,
+        constraint = payment.merchant == merchant.key() @ ErrorCode::InvalidPayment
+    )]
+    pub payment: Account<'info, Payment>,
+
+    pub owner: Signer<'info>,
+
+    /// CHECK: Original payer, receives the hold token account's rent
+    #[account(
+        mut,
+        constraint = payer.key() == payment.payer @ ErrorCode::InvalidPayer
+    )]
+    pub payer: AccountInfo<'info>,
+
+    /// CHECK: Merchant wallet to receive funds (For SOL capture)
+    #[account(
+        mut,
+        constraint = merchant_wallet.key() == merchant.settlement_wallet @ ErrorCode::InvalidMerchantWallet
+    )]
+    pub merchant_wallet: AccountInfo<'info>,
+
+    // Receives the fee, as in process_payment: SOL directly, SPL through
+    // its token account for the mint
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, payment.mint.as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    /// CHECK: The payment token's fee config PDA, as in process_payment
+    #[account(
+        seeds = [TOKEN_FEE_SEED, payment.mint.as_ref()],
+        bump
+    )]
+    pub token_fee: UncheckedAccount<'info>,
+
+    /// CHECK: The volume fee tiers PDA, as in process_payment
+    #[account(
+        seeds = [FEE_TIERS_SEED],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    // --- Optional Accounts for SPL (classic Token or Token-2022) ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub hold_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub merchant_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // --- Optional Accounts for merchants with a referrer ---
+    /// CHECK: Must be merchant.referrer; receives its fee share (For SOL capture)
+    #[account(mut)]
+    pub referrer_wallet: Option<AccountInfo<'info>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+pub fn capture_payment(ctx: Context<CapturePayment>, _name: String) -> Result<()> {
+    let state = &ctx.accounts.global_state;
+    let merchant = &ctx.accounts.merchant;
+    let payment = &ctx.accounts.payment;
+    require!(payment.status == PaymentStatus::Authorized, ErrorCode::InvalidPaymentStatus);
+
+    let amount = payment.amount;
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    // Same rates and referrer cut as process_payment, so quote_payment holds
+    // for captured holds too
+    let token_fee_bps = TokenFeeConfig::load_bps(&ctx.accounts.token_fee)?;
+    let tier_fee_bps = FeeTiers::load_bps(&ctx.accounts.fee_tiers, ctx.accounts.merchant_stats.total_volume)?;
+    let PaymentQuote { fee, merchant_amount, .. } =
+        PaymentQuote::new(merchant, token_fee_bps, tier_fee_bps, state, amount)?;
+    let referrer_amount = merchant.referrer_share(fee)?;
+    let protocol_fee = fee
+        .checked_sub(referrer_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    if payment.mint != Pubkey::default() {
+        let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let merchant_ta = ctx.accounts.merchant_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let fee_ta = ctx.accounts.fee_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(merchant_ta.mint == payment.mint, ErrorCode::MintMismatch);
+        require!(merchant_ta.owner == merchant.settlement_wallet, ErrorCode::InvalidMerchantTokenAccount);
+        require!(fee_ta.mint == payment.mint, ErrorCode::MintMismatch);
+        require!(fee_ta.owner == ctx.accounts.fee_vault.key(), ErrorCode::InvalidFeeTokenAccount);
+
+        let mut payouts = vec![
+            (fee_ta.to_account_info(), protocol_fee),
+            (merchant_ta.to_account_info(), merchant_amount),
+        ];
+        if referrer_amount > 0 {
+            let referrer_ta = ctx.accounts.referrer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            require!(referrer_ta.mint == payment.mint, ErrorCode::MintMismatch);
+            require!(referrer_ta.owner == merchant.referrer, ErrorCode::InvalidReferrer);
+            require_unfrozen(referrer_ta)?;
+            payouts.push((referrer_ta.to_account_info(), referrer_amount));
+        }
+
+        release_tokens(
+            payment,
+            ctx.accounts.token_program.as_ref(),
+            mint,
+            ctx.accounts.hold_token_account.as_ref(),
+            &payouts,
+            &ctx.accounts.payer,
+        )?;
+    } else if !payment.sandbox {
+        let fee_vault = ctx.accounts.fee_vault.to_account_info();
+        let mut payouts = vec![(&fee_vault, protocol_fee), (&ctx.accounts.merchant_wallet, merchant_amount)];
+        if referrer_amount > 0 {
+            let referrer_wallet = ctx.accounts.referrer_wallet.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            require!(referrer_wallet.key() == merchant.referrer, ErrorCode::InvalidReferrer);
+            payouts.push((referrer_wallet, referrer_amount));
+        }
+        release_lamports(&payment.to_account_info(), &payouts)?;
+    }
+
+    // Protocol totals count only what reached the fee vault
+    if !payment.sandbox {
+        ctx.accounts.global_stats.record_payment(amount, protocol_fee)?;
+        let fee_stats = &mut ctx.accounts.fee_stats;
+        fee_stats.total_fees_collected = fee_stats
+            .total_fees_collected
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+    ctx.accounts.merchant_stats.record_payment(amount, fee, now)?;
+
+    let payment = &mut ctx.accounts.payment;
+    payment.status = PaymentStatus::Captured;
+    payment.fee = fee;
+    // Finality counts from when the held funds actually moved
+    payment.slot = Clock::get()?.slot;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_authorizations = merchant.open_authorizations.saturating_sub(1);
+
+    emit_event!(ctx, PaymentCaptured {
+        payer: payment.payer,
+        merchant: payment.merchant,
+        amount,
+        fee,
+        token: payment.mint,
+        payment_id: payment.payment_id,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct VoidPayment<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: The payment's merchant, read and updated by hand because it may
+    /// be gone: a merchant closed with a hold from before holds were counted
+    /// leaves only the payer to void it
+    #[account(mut, address = payment.merchant @ ErrorCode::InvalidPayment)]
+    pub merchant: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payment.payer.as_ref(), &payment.id().seed()],
+        bump = payment.bump
+    )]
+    pub payment: Account<'info, Payment>,
+
+    // The merchant owner at any time, or the payer once the authorization expires
+    pub authority: Signer<'info>,
+
+    /// CHECK: Original payer receiving the held funds (For SOL void)
+    #[account(
+        mut,
+        constraint = payer.key() == payment.payer @ ErrorCode::InvalidPayer
+    )]
+    pub payer: AccountInfo<'info>,
+
+    // --- Optional Accounts for SPL (classic Token or Token-2022) ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub hold_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+pub fn void_payment(ctx: Context<VoidPayment>, _name: String) -> Result<()> {
+    let payment = &ctx.accounts.payment;
+    require!(payment.status == PaymentStatus::Authorized, ErrorCode::InvalidPaymentStatus);
+
+    // Only a current-layout merchant can have counted the hold, so an older
+    // one, like a closed one, has nothing to update
+    let merchant_info = ctx.accounts.merchant.to_account_info();
+    let mut merchant = None;
+    if merchant_info.owner == &crate::ID && !merchant_info.data_is_empty() {
+        let decoded = Merchant::try_deserialize(&mut &merchant_info.try_borrow_data()?[..])?;
+        if decoded.version == Merchant::VERSION {
+            merchant = Some(decoded);
+        }
+    }
+
+    let authority = ctx.accounts.authority.key();
+    let now = clock::now(ctx.remaining_accounts)?;
+    if merchant.as_ref().map(|m| m.owner) != Some(authority) {
+        require!(authority == payment.payer, ErrorCode::Unauthorized);
+        let expiry = match ctx.accounts.global_state.authorization_expiry {
+            0 => DEFAULT_AUTHORIZATION_EXPIRY,
+            e => e,
+        };
+        let expires_at = payment.timestamp.checked_add(expiry).ok_or(ErrorCode::CalculationError)?;
+        require!(now >= expires_at, ErrorCode::AuthorizationNotExpired);
+    }
+
+    let amount = payment.amount;
+    if payment.mint != Pubkey::default() {
+        let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(payer_ta.mint == payment.mint, ErrorCode::MintMismatch);
+        require!(payer_ta.owner == payment.payer, ErrorCode::InvalidPayerTokenAccount);
+
+        release_tokens(
+            payment,
+            ctx.accounts.token_program.as_ref(),
+            mint,
+            ctx.accounts.hold_token_account.as_ref(),
+            &[(payer_ta.to_account_info(), amount)],
+            &ctx.accounts.payer,
+        )?;
+    } else if !payment.sandbox {
+        release_lamports(&payment.to_account_info(), &[(&ctx.accounts.payer, amount)])?;
+    }
+
+    if let Some(mut merchant) = merchant {
+        merchant.open_authorizations = merchant.open_authorizations.saturating_sub(1);
+        merchant.try_serialize(&mut &mut merchant_info.try_borrow_mut_data()?[..])?;
+    }
+
+    let payment = &mut ctx.accounts.payment;
+    payment.status = PaymentStatus::Voided;
+
+    emit_event!(ctx, PaymentVoided {
+        payer: payment.payer,
+        merchant: payment.merchant,
+        amount,
+        token: payment.mint,
+        payment_id: payment.payment_id,
+        voided_by: authority,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+// The Payment PDA is program-owned, so held lamports move without a CPI
+fn release_lamports(hold: &AccountInfo, payouts: &[(&AccountInfo, u64)]) -> Result<()> {
+    for (to, lamports) in payouts {
+        **hold.try_borrow_mut_lamports()? = hold
+            .lamports()
+            .checked_sub(*lamports)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        **to.try_borrow_mut_lamports()? = to
+            .lamports()
+            .checked_add(*lamports)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+    Ok(())
+}
+
+// Pays out of the hold token account with the Payment PDA as signer, then
+// closes it and returns its rent to the payer who funded it.
+fn release_tokens<'info>(
+    payment: &Account<'info, Payment>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+    mint: &InterfaceAccount<'info, Mint>,
+    hold_ta: Option<&InterfaceAccount<'info, TokenAccount>>,
+    payouts: &[(AccountInfo<'info>, u64)],
+    payer: &AccountInfo<'info>,
+) -> Result<()> {
+    let token_program = token_program.ok_or(ErrorCode::MissingAccount)?;
+    let hold_ta = hold_ta.ok_or(ErrorCode::MissingAccount)?;
+    require!(mint.key() == payment.mint, ErrorCode::InvalidToken);
+    require!(hold_ta.mint == payment.mint, ErrorCode::MintMismatch);
+    require!(hold_ta.owner == payment.key(), ErrorCode::OwnerMismatch);
+
+    let payment_id = payment.id().seed();
+    let signer_seeds: &[&[&[u8]]] = &[&[PAYMENT_SEED, payment.payer.as_ref(), &payment_id, &[payment.bump]]];
+
+    // Sandbox holds never received funds
+    if !payment.sandbox {
+        for (to, amount) in payouts {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: hold_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: to.clone(),
+                        authority: payment.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                *amount,
+                mint.decimals,
+            )?;
+        }
+    }
+
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: hold_ta.to_account_info(),
+            destination: payer.clone(),
+            authority: payment.to_account_info(),
+        },
+        signer_seeds,
+    ))
+}
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use crate::state::{Blocked, GlobalState};
+use crate::events::{emit_event, PayerBlocked, PayerUnblocked};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{BLOCKED_SEED, GLOBAL_STATE_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct BlockAddress<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Blocked::INIT_SPACE,
+        seeds = [BLOCKED_SEED, address.as_ref()],
+        bump
+    )]
+    pub blocked: Account<'info, Blocked>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Bars `address` from process_payment until unblocked.
+pub fn block_address(ctx: Context<BlockAddress>, address: Pubkey) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    let blocked = &mut ctx.accounts.blocked;
+    blocked.address = address;
+    blocked.blocked_at = now;
+    blocked.bump = ctx.bumps.blocked;
+
+    emit_event!(ctx, PayerBlocked {
+        admin: ctx.accounts.admin.key(),
+        address,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnblockAddress<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [BLOCKED_SEED, blocked.address.as_ref()],
+        bump = blocked.bump
+    )]
+    pub blocked: Account<'info, Blocked>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+/// Closes the entry, returning its rent to the admin.
+pub fn unblock_address(ctx: Context<UnblockAddress>) -> Result<()> {
+    emit_event!(ctx, PayerUnblocked {
+        admin: ctx.accounts.admin.key(),
+        address: ctx.accounts.blocked.address,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
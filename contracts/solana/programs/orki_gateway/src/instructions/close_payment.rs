@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+use crate::state::{GlobalState, Payment, PaymentStatus};
+use crate::events::{emit_event, PaymentClosed};
+use crate::errors::ErrorCode;
+use crate::constants::{DEFAULT_PAYMENT_RETENTION, GLOBAL_STATE_SEED};
+use crate::clock;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClosePayment<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    // No seeds: invoice, link and plain payments all close the same way, and
+    // the discriminator already proves this is one of ours
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer @ ErrorCode::InvalidPayer
+    )]
+    pub payment: Account<'info, Payment>,
+
+    /// CHECK: Original payer who funded the record; always receives its rent
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    // Anyone may close an aged payment, e.g. a crank sweeping old records
+    pub closer: Signer<'info>,
+}
+
+/// Closes a Payment once the retention window has passed since it was made,
+/// returning its rent to the payer. Refunds are no longer possible afterwards.
+/// Authorized holds keep their funds on the record and can never be closed,
+/// and neither can payments with an open dispute.
+pub fn close_payment(ctx: Context<ClosePayment>) -> Result<()> {
+    let payment = &ctx.accounts.payment;
+    require!(
+        !matches!(payment.status, PaymentStatus::Authorized | PaymentStatus::Disputed),
+        ErrorCode::InvalidPaymentStatus
+    );
+    // Its escrowed share would stay pending, and unclaimable, for good
+    require!(
+        !(payment.escrowed && payment.slot != 0 && payment.status == PaymentStatus::Completed),
+        ErrorCode::PaymentNotFinalized
+    );
+
+    let retention = match ctx.accounts.global_state.payment_retention_seconds {
+        0 => DEFAULT_PAYMENT_RETENTION,
+        seconds => seconds,
+    };
+    let closable_at = payment
+        .timestamp
+        .checked_add(retention)
+        .ok_or(ErrorCode::CalculationError)?;
+    let now = clock::now(ctx.remaining_accounts)?;
+    require!(now >= closable_at, ErrorCode::PaymentRetentionActive);
+
+    emit_event!(ctx, PaymentClosed {
+        payment: payment.key(),
+        payer: payment.payer,
+        merchant: payment.merchant,
+        payment_id: payment.payment_id,
+        closed_by: ctx.accounts.closer.key(),
+        rent_returned: payment.to_account_info().lamports(),
+        timestamp: now,
+    });
+
+    Ok(())
+}
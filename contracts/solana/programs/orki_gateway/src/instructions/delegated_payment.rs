@@ -0,0 +1,351 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use solana_instructions_sysvar::{load_current_index_checked, load_instruction_at_checked};
+use solana_sdk_ids::ed25519_program;
+use crate::state::{AcceptedTokens, Blocked, Feature, FeatureFlags, FeeStats, FeeTiers, FeeVault, GlobalState, GlobalStats, Merchant, MerchantStats, Payment, PaymentId, PaymentStatus, TokenFeeConfig};
+use crate::events::{emit_event, DelegatedPaymentProcessed};
+use crate::instructions::{reject_transfer_fee_mint, require_unfrozen, PaymentQuote};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ACCEPTED_TOKENS_SEED, BLOCKED_SEED, FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_TIERS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, PAYMENT_DELEGATE_SEED, PAYMENT_SEED, TOKEN_FEE_SEED};
+
+/// What the payer signs off-chain to authorize one delegated payment. The
+/// program id keeps a signature for this gateway from being replayed against
+/// another deployment.
+pub fn delegated_payment_message(
+    merchant: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+    payment_id: u64,
+    expires_at: i64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(120);
+    message.extend_from_slice(crate::ID.as_ref());
+    message.extend_from_slice(merchant.as_ref());
+    message.extend_from_slice(mint.as_ref());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&payment_id.to_le_bytes());
+    message.extend_from_slice(&expires_at.to_le_bytes());
+    message
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, payment_id: u64, name: String)]
+pub struct ProcessPaymentDelegated<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    /// CHECK: Does not sign; authorizes through the ed25519 instruction
+    /// checked in the handler
+    pub payer: UncheckedAccount<'info>,
+
+    // Submits the transaction and pays its fee and the payment record's rent
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [PAYMENT_SEED, payer.key().as_ref(), &PaymentId::Sequence(payment_id).seed()],
+        bump
+    )]
+    pub payment_history: Account<'info, Payment>,
+
+    /// CHECK: The payer's blocklist PDA, as in process_payment
+    #[account(
+        seeds = [BLOCKED_SEED, payer.key().as_ref()],
+        bump
+    )]
+    pub blocklist_entry: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, mint.key().as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    /// CHECK: The token's fee config PDA, as in process_payment
+    #[account(
+        seeds = [TOKEN_FEE_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub token_fee: UncheckedAccount<'info>,
+
+    /// CHECK: The volume fee tiers PDA, as in process_payment
+    #[account(
+        seeds = [FEE_TIERS_SEED],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: PDA signing as the payer's token delegate
+    #[account(seeds = [PAYMENT_DELEGATE_SEED], bump)]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = payer_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = payer_token_account.owner == payer.key() @ ErrorCode::InvalidPayerTokenAccount
+    )]
+    pub payer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = merchant_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = merchant_token_account.owner == merchant.settlement_wallet @ ErrorCode::InvalidMerchantTokenAccount
+    )]
+    pub merchant_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = fee_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = fee_token_account.owner == fee_vault.key() @ ErrorCode::InvalidFeeTokenAccount
+    )]
+    pub fee_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The instructions sysvar, read for the ed25519 instruction
+    #[account(address = solana_instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    // Required only for Token-2022 mints while that rollout is gated
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+
+    // Required for merchants with an accepted-token list
+    #[account(
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump = accepted_tokens.bump
+    )]
+    pub accepted_tokens: Option<Account<'info, AcceptedTokens>>,
+
+    // Required when the merchant has a referrer
+    #[account(mut)]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// A gasless SPL payment: the relayer signs and pays, the payer only signs
+/// the authorization off-chain. The instruction just before this one must
+/// be the ed25519 program verifying the payer's signature over
+/// [`delegated_payment_message`], and the funds move through the payer's
+/// approval of the delegate PDA. Plain merchants only: escrow, accrual and
+/// split merchants keep using process_payment.
+pub fn process_payment_delegated(
+    ctx: Context<ProcessPaymentDelegated>,
+    amount: u64,
+    payment_id: u64,
+    _name: String,
+    expires_at: i64,
+) -> Result<()> {
+    let state = &ctx.accounts.global_state;
+    let merchant = &ctx.accounts.merchant;
+    let mint = &ctx.accounts.mint;
+    let payer = ctx.accounts.payer.key();
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    require!(!ctx.accounts.payment_history.is_recorded(), ErrorCode::DuplicatePayment);
+    require!(!state.paused, ErrorCode::Paused);
+    require!(merchant.active, ErrorCode::MerchantInactive);
+    require!(!merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    require!(!Blocked::is_blocked(&ctx.accounts.blocklist_entry), ErrorCode::AddressBlocked);
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(now <= expires_at, ErrorCode::PaymentExpired);
+
+    let message = delegated_payment_message(&merchant.key(), &mint.key(), amount, payment_id, expires_at);
+    require_payer_signature(&ctx.accounts.instructions, &payer, &message)?;
+
+    merchant.check_amount(amount)?;
+    require!(
+        !merchant.pays_into_vault() && !merchant.split_enabled,
+        ErrorCode::UnsupportedSettlementMode
+    );
+    require!(
+        merchant.settlement_wallet != state.fee_wallet || merchant.owner == state.admin,
+        ErrorCode::SettlementIsFeeWallet
+    );
+    require!(payer != merchant.settlement_wallet || merchant.allow_self_payments, ErrorCode::SelfPayment);
+
+    if merchant.accepted_tokens_enabled {
+        let accepted = ctx.accounts.accepted_tokens.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(accepted.accepts(&mint.key()), ErrorCode::InvalidToken);
+    } else {
+        merchant.check_token(&mint.key())?;
+    }
+
+    if ctx.accounts.token_program.key() == anchor_spl::token_2022::ID {
+        FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+        reject_transfer_fee_mint(mint)?;
+    }
+
+    // Same rates as process_payment, so quote_payment holds for relayed payments
+    let token_fee_bps = TokenFeeConfig::load_bps(&ctx.accounts.token_fee)?;
+    let tier_fee_bps = FeeTiers::load_bps(&ctx.accounts.fee_tiers, ctx.accounts.merchant_stats.total_volume)?;
+    let PaymentQuote { fee, merchant_amount, fee_bps_applied, fee_source } =
+        PaymentQuote::new(merchant, token_fee_bps, tier_fee_bps, state, amount)?;
+    let referrer_amount = merchant.referrer_share(fee)?;
+    let protocol_fee = fee
+        .checked_sub(referrer_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let payer_ta = &ctx.accounts.payer_token_account;
+    require!(
+        payer_ta.delegate == Some(ctx.accounts.delegate.key()).into() && payer_ta.delegated_amount >= amount,
+        ErrorCode::DelegateNotApproved
+    );
+    require!(payer_ta.amount >= amount, ErrorCode::InsufficientBalance);
+    require_unfrozen(&ctx.accounts.merchant_token_account)?;
+    require_unfrozen(&ctx.accounts.fee_token_account)?;
+    let referrer_ta = if referrer_amount > 0 {
+        let referrer_ta = ctx.accounts.referrer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(referrer_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(referrer_ta.owner == merchant.referrer, ErrorCode::InvalidReferrer);
+        require_unfrozen(referrer_ta)?;
+        Some(referrer_ta)
+    } else {
+        None
+    };
+
+    if !merchant.sandbox {
+        let signer_seeds: &[&[&[u8]]] = &[&[PAYMENT_DELEGATE_SEED, &[ctx.bumps.delegate]]];
+        let transfers = [
+            (ctx.accounts.fee_token_account.to_account_info(), protocol_fee),
+            (ctx.accounts.merchant_token_account.to_account_info(), merchant_amount),
+        ]
+        .into_iter()
+        .chain(referrer_ta.map(|ta| (ta.to_account_info(), referrer_amount)));
+
+        for (to, share) in transfers.filter(|(_, share)| *share > 0) {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: payer_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to,
+                        authority: ctx.accounts.delegate.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                share,
+                mint.decimals,
+            )?;
+        }
+
+        ctx.accounts.global_stats.record_payment(amount, protocol_fee)?;
+        let fee_stats = &mut ctx.accounts.fee_stats;
+        fee_stats.total_fees_collected = fee_stats
+            .total_fees_collected
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+
+    ctx.accounts.merchant_stats.record_payment(amount, fee, now)?;
+
+    let payment = &mut ctx.accounts.payment_history;
+    payment.payer = payer;
+    payment.merchant = merchant.key();
+    payment.amount = amount;
+    payment.payment_id = payment_id;
+    payment.timestamp = now;
+    payment.bump = ctx.bumps.payment_history;
+    payment.sandbox = merchant.sandbox;
+    payment.status = PaymentStatus::Completed;
+    payment.mint = mint.key();
+    payment.fee = fee;
+    payment.slot = Clock::get()?.slot;
+
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
+    emit_event!(ctx, DelegatedPaymentProcessed {
+        payer,
+        relayer: ctx.accounts.relayer.key(),
+        merchant: merchant.key(),
+        amount,
+        fee,
+        token: mint.key(),
+        payment_id,
+        timestamp: now,
+        sandbox: merchant.sandbox,
+        referrer_amount,
+        fee_bps: fee_bps_applied,
+        fee_source,
+        expires_at,
+        event_seq,
+        payment_account: ctx.accounts.payment_history.key(),
+    });
+
+    Ok(())
+}
+
+/// Requires the instruction before the current one to be the ed25519
+/// program verifying one signature by `payer` over exactly `message`. The
+/// precompile has already checked the signature itself by the time this runs;
+/// what is left is making sure it covered this payer and this payment.
+fn require_payer_signature(instructions: &AccountInfo, payer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, ErrorCode::InvalidPayerSignature);
+    let ix = load_instruction_at_checked(usize::from(current - 1), instructions)?;
+    require!(
+        ix.program_id == ed25519_program::ID && ix.accounts.is_empty(),
+        ErrorCode::InvalidPayerSignature
+    );
+
+    // [count, padding], then the offsets of the one signature
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidPayerSignature);
+    let read = |at: usize| usize::from(u16::from_le_bytes([data[at], data[at + 1]]));
+    // u16::MAX points at the ed25519 instruction's own data; any other index
+    // would let the key or message come from somewhere this check never reads
+    require!(
+        [4, 8, 14].iter().all(|&at| read(at) == usize::from(u16::MAX)),
+        ErrorCode::InvalidPayerSignature
+    );
+
+    let (key_at, message_at, message_len) = (read(6), read(10), read(12));
+    let signer = data.get(key_at..key_at + 32).ok_or(ErrorCode::InvalidPayerSignature)?;
+    let signed = data
+        .get(message_at..message_at + message_len)
+        .ok_or(ErrorCode::InvalidPayerSignature)?;
+    require!(signer == payer.as_ref() && signed == message, ErrorCode::InvalidPayerSignature);
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use crate::state::{Discount, Merchant};
+use crate::events::{DiscountCreated, DiscountDeleted};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{BPS_DENOMINATOR, DISCOUNT_SEED, MERCHANT_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String, code_hash: [u8; 32])]
+pub struct CreateDiscount<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Discount::INIT_SPACE,
+        seeds = [DISCOUNT_SEED, merchant.key().as_ref(), code_hash.as_ref()],
+        bump
+    )]
+    pub discount: Account<'info, Discount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens a promo code, identified by its SHA-256 hash, that payers redeem by
+/// passing the code to process_payment. A `max_redemptions` or `expires_at`
+/// of 0 leaves that limit off.
+pub fn create_discount(
+    ctx: Context<CreateDiscount>,
+    _name: String,
+    code_hash: [u8; 32],
+    discount_bps: u16,
+    max_redemptions: u64,
+    expires_at: i64,
+) -> Result<()> {
+    require!(discount_bps > 0 && discount_bps < BPS_DENOMINATOR, ErrorCode::InvalidDiscount);
+
+    let now = clock::now(ctx.remaining_accounts)?;
+    require!(expires_at == 0 || expires_at > now, ErrorCode::DiscountExpired);
+
+    let discount = &mut ctx.accounts.discount;
+    discount.merchant = ctx.accounts.merchant.key();
+    discount.code_hash = code_hash;
+    discount.discount_bps = discount_bps;
+    discount.max_redemptions = max_redemptions;
+    discount.redemptions = 0;
+    discount.expires_at = expires_at;
+    discount.bump = ctx.bumps.discount;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.discount_codes = merchant.discount_codes.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+
+    emit!(DiscountCreated {
+        merchant: discount.merchant,
+        discount: discount.key(),
+        code_hash,
+        discount_bps,
+        max_redemptions,
+        expires_at,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DeleteDiscount<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [DISCOUNT_SEED, merchant.key().as_ref(), discount.code_hash.as_ref()],
+        bump = discount.bump,
+        close = owner
+    )]
+    pub discount: Account<'info, Discount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+/// Retires a promo code, refunding its rent to `owner`. Payments passing
+/// the code fail from then on.
+pub fn delete_discount(ctx: Context<DeleteDiscount>, _name: String) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.discount_codes = merchant.discount_codes.saturating_sub(1);
+
+    let discount = &ctx.accounts.discount;
+    emit!(DiscountDeleted {
+        merchant: discount.merchant,
+        discount: discount.key(),
+        code_hash: discount.code_hash,
+        redemptions: discount.redemptions,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
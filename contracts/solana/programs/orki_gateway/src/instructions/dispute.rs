@@ -0,0 +1,262 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{EscrowBalance, GlobalState, GlobalStats, Merchant, MerchantStats, Payment, PaymentStatus, Vault};
+use crate::events::{emit_event, DisputeOpened, DisputeResolved};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ESCROW_BALANCE_SEED, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, MERCHANT_STATS_SEED, PAYMENT_SEED, VAULT_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    // Derived from the record's own id, so process_payment_v2 records can be
+    // disputed too
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payer.key().as_ref(), &payment.id().seed()],
+        bump = payment.bump,
+        has_one = payer @ ErrorCode::InvalidPayer
+    )]
+    pub payment: Account<'info, Payment>,
+
+    #[account(
+        mut,
+        seeds = [ESCROW_BALANCE_SEED, payment.merchant.as_ref(), payment.mint.as_ref()],
+        bump = escrow_balance.bump
+    )]
+    pub escrow_balance: Account<'info, EscrowBalance>,
+
+    pub payer: Signer<'info>,
+}
+
+/// Holds an escrowed payment's share back from claim_settlement until the
+/// arbiter resolves it. Only the payer can open one, once the payment is
+/// finalized and only while the funds are still in escrow: once the merchant
+/// has claimed after the release delay, the payment is settled and disputes
+/// are rejected.
+pub fn open_dispute(ctx: Context<OpenDispute>) -> Result<()> {
+    require!(ctx.accounts.global_state.arbiter != Pubkey::default(), ErrorCode::ArbiterNotSet);
+
+    let payment = &ctx.accounts.payment;
+    require!(payment.escrowed, ErrorCode::PaymentNotEscrowed);
+    // Finalizing first moves the share out of the escrow's pending amount
+    require!(payment.status != PaymentStatus::Completed, ErrorCode::PaymentNotFinalized);
+    // A merchant refund already returned part of it outside the escrow, and
+    // the arbiter's ruling on an earlier dispute is final
+    require!(
+        payment.status == PaymentStatus::Finalized && payment.refunded_amount == 0,
+        ErrorCode::InvalidPaymentStatus
+    );
+
+    let escrow_balance = &ctx.accounts.escrow_balance;
+    require!(escrow_balance.last_claim_at < payment.timestamp, ErrorCode::DisputeWindowClosed);
+
+    let amount = payment.escrowed_amount()?;
+    let disputed_amount = escrow_balance
+        .disputed_amount
+        .checked_add(amount)
+        .ok_or(ErrorCode::CalculationError)?;
+    let held = disputed_amount
+        .checked_add(escrow_balance.pending_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(held <= escrow_balance.amount, ErrorCode::InsufficientBalance);
+
+    ctx.accounts.escrow_balance.disputed_amount = disputed_amount;
+    ctx.accounts.payment.status = PaymentStatus::Disputed;
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
+    let payment = &ctx.accounts.payment;
+
+    emit_event!(ctx, DisputeOpened {
+        payment: payment.key(),
+        payer: payment.payer,
+        merchant: payment.merchant,
+        payment_id: payment.payment_id,
+        amount,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+        event_seq,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.arbiter == arbiter.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub arbiter: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    // Derived from the record's own id, so process_payment_v2 records can be
+    // disputed too
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payer.key().as_ref(), &payment.id().seed()],
+        bump = payment.bump,
+        has_one = payer @ ErrorCode::InvalidPayer,
+        has_one = merchant @ ErrorCode::InvalidPayment
+    )]
+    pub payment: Account<'info, Payment>,
+
+    /// CHECK: Original payer; receives SOL refunds or owns the payer token account
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [ESCROW_BALANCE_SEED, merchant.key().as_ref(), payment.mint.as_ref()],
+        bump = escrow_balance.bump
+    )]
+    pub escrow_balance: Account<'info, EscrowBalance>,
+
+    // --- Optional Accounts for SPL refunds ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Settles a dispute. With `refund` the escrowed share goes back to the payer
+/// from the vault and the payment counts as fully refunded; otherwise it is
+/// released to the merchant's claimable escrow balance. The gateway fee is
+/// not returned either way. The payment ends up DisputeResolved, so the payer
+/// can't open another dispute on it.
+pub fn resolve_dispute(ctx: Context<ResolveDispute>, refund: bool) -> Result<()> {
+    let payment = &ctx.accounts.payment;
+    require!(payment.status == PaymentStatus::Disputed, ErrorCode::InvalidPaymentStatus);
+    let amount = payment.escrowed_amount()?;
+
+    let escrow_balance = &ctx.accounts.escrow_balance;
+    let disputed_amount = escrow_balance
+        .disputed_amount
+        .checked_sub(amount)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    if refund {
+        if payment.mint == Pubkey::default() {
+            // --- NATIVE SOL REFUND ---
+            // The vault is program-owned, so lamports move without a CPI
+            let vault_info = ctx.accounts.vault.to_account_info();
+            let payer_info = ctx.accounts.payer.to_account_info();
+            **vault_info.try_borrow_mut_lamports()? = vault_info
+                .lamports()
+                .checked_sub(amount)
+                .ok_or(ErrorCode::InsufficientBalance)?;
+            **payer_info.try_borrow_mut_lamports()? = payer_info
+                .lamports()
+                .checked_add(amount)
+                .ok_or(ErrorCode::CalculationError)?;
+        } else {
+            // --- SPL TOKEN REFUND ---
+            let token_program = ctx.accounts.token_program.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+            let vault_ta = ctx.accounts.vault_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+            require!(mint.key() == payment.mint, ErrorCode::InvalidToken);
+            require!(vault_ta.mint == mint.key(), ErrorCode::MintMismatch);
+            require!(vault_ta.owner == ctx.accounts.vault.key(), ErrorCode::OwnerMismatch);
+            require!(payer_ta.mint == mint.key(), ErrorCode::MintMismatch);
+            require!(payer_ta.owner == payment.payer, ErrorCode::InvalidPayerTokenAccount);
+
+            let merchant_key = ctx.accounts.merchant.key();
+            let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, merchant_key.as_ref(), &[ctx.accounts.vault.bump]]];
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: vault_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: payer_ta.to_account_info(),
+                        authority: ctx.accounts.vault.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
+
+        let escrow_balance = &mut ctx.accounts.escrow_balance;
+        escrow_balance.amount = escrow_balance
+            .amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        if escrow_balance.amount == 0 {
+            let merchant = &mut ctx.accounts.merchant;
+            merchant.funded_escrows = merchant.funded_escrows.saturating_sub(1);
+        }
+
+        // Only the escrowed share went back; the fee stays with the gateway
+        ctx.accounts.payment.refunded_amount = amount;
+        ctx.accounts.merchant_stats.record_refund(amount)?;
+    }
+
+    ctx.accounts.escrow_balance.disputed_amount = disputed_amount;
+    ctx.accounts.payment.status = PaymentStatus::DisputeResolved;
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
+    let payment = &ctx.accounts.payment;
+
+    emit_event!(ctx, DisputeResolved {
+        payment: payment.key(),
+        payer: payment.payer,
+        merchant: payment.merchant,
+        payment_id: payment.payment_id,
+        amount,
+        refunded: refund,
+        arbiter: ctx.accounts.arbiter.key(),
+        timestamp: clock::now(ctx.remaining_accounts)?,
+        event_seq,
+    });
+
+    Ok(())
+}
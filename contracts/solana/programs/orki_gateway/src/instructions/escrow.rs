@@ -0,0 +1,207 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{EscrowBalance, GlobalState, Merchant, Vault};
+use crate::events::{emit_event, EscrowOpened, SettlementClaimed};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ESCROW_BALANCE_SEED, GLOBAL_STATE_SEED, MERCHANT_SEED, VAULT_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String, mint: Pubkey)]
+pub struct OpenEscrow<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Vault::INIT_SPACE,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + EscrowBalance::INIT_SPACE,
+        seeds = [ESCROW_BALANCE_SEED, merchant.key().as_ref(), mint.as_ref()],
+        bump
+    )]
+    pub escrow_balance: Account<'info, EscrowBalance>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the vault and the per-mint escrow balance a merchant needs before
+/// escrowed payments in that mint (Pubkey::default() for SOL) can land.
+pub fn open_escrow(ctx: Context<OpenEscrow>, _name: String, mint: Pubkey) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    vault.merchant = ctx.accounts.merchant.key();
+    vault.bump = ctx.bumps.vault;
+
+    let escrow_balance = &mut ctx.accounts.escrow_balance;
+    escrow_balance.merchant = ctx.accounts.merchant.key();
+    escrow_balance.mint = mint;
+    escrow_balance.amount = 0;
+    escrow_balance.last_deposit_at = 0;
+    escrow_balance.bump = ctx.bumps.escrow_balance;
+
+    // Emit event
+    emit!(EscrowOpened {
+        merchant: ctx.accounts.merchant.key(),
+        mint,
+        vault: ctx.accounts.vault.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClaimSettlement<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [ESCROW_BALANCE_SEED, merchant.key().as_ref(), escrow_balance.mint.as_ref()],
+        bump = escrow_balance.bump
+    )]
+    pub escrow_balance: Account<'info, EscrowBalance>,
+
+    pub owner: Signer<'info>,
+
+    /// CHECK: Merchant settlement wallet receiving the funds (For SOL claims)
+    #[account(
+        mut,
+        constraint = settlement_wallet.key() == merchant.settlement_wallet @ ErrorCode::InvalidMerchantWallet
+    )]
+    pub settlement_wallet: AccountInfo<'info>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub settlement_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Pays out the merchant's escrow balance for one mint, minus whatever open
+/// disputes and payments not yet finalized hold back, once the latest deposit
+/// has aged past the release delay.
+pub fn claim_settlement(ctx: Context<ClaimSettlement>, _name: String) -> Result<()> {
+    require!(!ctx.accounts.merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    let escrow_balance = &ctx.accounts.escrow_balance;
+    let held = escrow_balance.held_amount()?;
+    let amount = escrow_balance
+        .amount
+        .checked_sub(held)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(amount > 0, ErrorCode::NothingToClaim);
+
+    // Funds stay locked until the most recent deposit has aged past the delay
+    let now = clock::now(ctx.remaining_accounts)?;
+    let release_at = escrow_balance
+        .last_deposit_at
+        .checked_add(ctx.accounts.global_state.escrow_release_delay)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(now >= release_at, ErrorCode::EscrowLocked);
+
+    if escrow_balance.mint == Pubkey::default() {
+        // --- NATIVE SOL CLAIM ---
+        // The vault is program-owned, so lamports move without a CPI
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let wallet_info = ctx.accounts.settlement_wallet.to_account_info();
+        **vault_info.try_borrow_mut_lamports()? = vault_info
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        **wallet_info.try_borrow_mut_lamports()? = wallet_info
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+    } else {
+        // --- SPL TOKEN CLAIM ---
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let vault_ta = ctx.accounts.vault_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let settlement_ta = ctx.accounts.settlement_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        require!(mint.key() == escrow_balance.mint, ErrorCode::InvalidToken);
+        require!(vault_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(vault_ta.owner == ctx.accounts.vault.key(), ErrorCode::OwnerMismatch);
+        require!(settlement_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(
+            settlement_ta.owner == ctx.accounts.merchant.settlement_wallet,
+            ErrorCode::InvalidMerchantTokenAccount
+        );
+
+        let merchant_key = ctx.accounts.merchant.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, merchant_key.as_ref(), &[ctx.accounts.vault.bump]]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: vault_ta.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: settlement_ta.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            mint.decimals,
+        )?;
+    }
+
+    let escrow_balance = &mut ctx.accounts.escrow_balance;
+    escrow_balance.amount = held;
+    escrow_balance.last_claim_at = now;
+    if escrow_balance.amount == 0 {
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.funded_escrows = merchant.funded_escrows.saturating_sub(1);
+    }
+
+    emit_event!(ctx, SettlementClaimed {
+        merchant: ctx.accounts.merchant.key(),
+        mint: escrow_balance.mint,
+        amount,
+        destination: ctx.accounts.merchant.settlement_wallet,
+        timestamp: now,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+use crate::state::{Feature, FeatureFlags, GlobalState};
+use crate::errors::ErrorCode;
+use crate::events::FeatureToggled;
+use crate::constants::{FEATURE_FLAGS_SEED, GLOBAL_STATE_SEED};
+
+#[derive(Accounts)]
+pub struct SetFeature<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeatureFlags::INIT_SPACE,
+        seeds = [FEATURE_FLAGS_SEED],
+        bump
+    )]
+    pub feature_flags: Account<'info, FeatureFlags>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_feature(ctx: Context<SetFeature>, feature: Feature, enabled: bool) -> Result<()> {
+    let flags = &mut ctx.accounts.feature_flags;
+    flags.bump = ctx.bumps.feature_flags;
+    
+    if enabled {
+        flags.enabled |= feature.mask();
+    } else {
+        flags.enabled &= !feature.mask();
+    }
+    
+    // Emit event
+    emit!(FeatureToggled {
+        admin: ctx.accounts.admin.key(),
+        feature,
+        enabled,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use anchor_lang::prelude::*;
+use crate::state::{AdminAction, AdminProposal, FeeTier, FeeTiers, FeeTiersProposal, GlobalState};
+use crate::events::{emit_event, FeeTiersProposalCancelled, FeeTiersProposed, FeeTiersUpdated};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ADMIN_PROPOSAL_SEED, FEE_TIERS_PROPOSAL_SEED, FEE_TIERS_SEED, GLOBAL_STATE_SEED};
+use crate::instructions::authorize_admin_action;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetFeeTiers<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    // Opened empty on first use so apply_fee_tiers never has to pay for it
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeeTiers::INIT_SPACE,
+        seeds = [FEE_TIERS_SEED],
+        bump
+    )]
+    pub fee_tiers: Account<'info, FeeTiers>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeeTiersProposal::INIT_SPACE,
+        seeds = [FEE_TIERS_PROPOSAL_SEED],
+        bump
+    )]
+    pub proposal: Account<'info, FeeTiersProposal>,
+
+    // The admin, or anyone carrying out council-approved tiers
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+
+    // Required once an admin council governs; see authorize_admin_action
+    #[account(
+        mut,
+        seeds = [ADMIN_PROPOSAL_SEED, &admin_proposal.nonce.to_le_bytes()],
+        bump = admin_proposal.bump
+    )]
+    pub admin_proposal: Option<Account<'info, AdminProposal>>,
+}
+
+/// Queues replacement volume tiers; rows must be in strictly ascending
+/// threshold order and an empty list turns tiers off. Like the global rate,
+/// they wait out the fee timelock and are committed by apply_fee_tiers. A new
+/// proposal replaces any pending one and restarts the timelock.
+pub fn set_fee_tiers(ctx: Context<SetFeeTiers>, tiers: Vec<FeeTier>) -> Result<()> {
+    authorize_admin_action(
+        &ctx.accounts.global_state,
+        &ctx.accounts.admin.key(),
+        ctx.accounts.admin_proposal.as_deref_mut(),
+        AdminAction::SetFeeTiers { tiers: tiers.clone() },
+    )?;
+    FeeTiers::validate(&tiers, ctx.accounts.global_state.fee_cap())?;
+    let now = clock::now(ctx.remaining_accounts)?;
+    let effective_at = ctx.accounts.global_state.fee_effective_time(now)?;
+
+    ctx.accounts.fee_tiers.bump = ctx.bumps.fee_tiers;
+
+    let proposal = &mut ctx.accounts.proposal;
+    proposal.proposer = ctx.accounts.admin.key();
+    proposal.tiers = tiers.clone();
+    proposal.effective_at = effective_at;
+    proposal.bump = ctx.bumps.proposal;
+
+    emit_event!(ctx, FeeTiersProposed {
+        admin: ctx.accounts.admin.key(),
+        tiers,
+        effective_at,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApplyFeeTiers<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [FEE_TIERS_SEED],
+        bump = fee_tiers.bump
+    )]
+    pub fee_tiers: Account<'info, FeeTiers>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [FEE_TIERS_PROPOSAL_SEED],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, FeeTiersProposal>,
+
+    /// CHECK: Only receives the proposal's rent back
+    #[account(mut, address = proposal.proposer @ ErrorCode::Unauthorized)]
+    pub proposer: UncheckedAccount<'info>,
+
+    // Anyone may apply tiers whose timelock has passed
+    pub caller: Signer<'info>,
+}
+
+pub fn apply_fee_tiers(ctx: Context<ApplyFeeTiers>) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let proposal = &ctx.accounts.proposal;
+
+    require!(now >= proposal.effective_at, ErrorCode::FeeTimelockNotElapsed);
+    // The cap may have been lowered since the proposal
+    FeeTiers::validate(&proposal.tiers, ctx.accounts.global_state.fee_cap())?;
+
+    let tiers = proposal.tiers.clone();
+    ctx.accounts.fee_tiers.tiers = tiers.clone();
+
+    emit_event!(ctx, FeeTiersUpdated {
+        admin: ctx.accounts.caller.key(),
+        tiers,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelFeeTiersProposal<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [FEE_TIERS_PROPOSAL_SEED],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, FeeTiersProposal>,
+
+    /// CHECK: Only receives the proposal's rent back
+    #[account(mut, address = proposal.proposer @ ErrorCode::Unauthorized)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub admin: Signer<'info>,
+}
+
+pub fn cancel_fee_tiers_proposal(ctx: Context<CancelFeeTiersProposal>) -> Result<()> {
+    require!(!ctx.accounts.global_state.admin_council, ErrorCode::CouncilGoverned);
+    emit_event!(ctx, FeeTiersProposalCancelled {
+        admin: ctx.accounts.admin.key(),
+        tiers: ctx.accounts.proposal.tiers.clone(),
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
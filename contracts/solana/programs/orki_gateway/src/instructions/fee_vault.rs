@@ -0,0 +1,182 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{AdminAction, AdminProposal, FeeStats, FeeVault, GlobalState};
+use crate::events::{emit_event, FeeStatsOpened, FeesWithdrawn};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ADMIN_PROPOSAL_SEED, FEE_STATS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED};
+use crate::instructions::authorize_admin_action;
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct OpenFeeStats<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeeVault::INIT_SPACE,
+        seeds = [FEE_VAULT_SEED],
+        bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FeeStats::INIT_SPACE,
+        seeds = [FEE_STATS_SEED, mint.as_ref()],
+        bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the fee vault and the per-mint stats process_payment needs before
+/// payments in that mint (Pubkey::default() for SOL) can land. SPL fees also
+/// need the vault's token account for the mint, which anyone can create.
+pub fn open_fee_stats(ctx: Context<OpenFeeStats>, mint: Pubkey) -> Result<()> {
+    let fee_vault = &mut ctx.accounts.fee_vault;
+    fee_vault.bump = ctx.bumps.fee_vault;
+
+    let fee_stats = &mut ctx.accounts.fee_stats;
+    fee_stats.mint = mint;
+    fee_stats.total_fees_collected = 0;
+    fee_stats.bump = ctx.bumps.fee_stats;
+
+    // Emit event
+    emit!(FeeStatsOpened {
+        admin: ctx.accounts.admin.key(),
+        mint,
+        fee_vault: ctx.accounts.fee_vault.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    // The admin, or anyone carrying out a council-approved withdrawal
+    pub admin: Signer<'info>,
+
+    // Required once an admin council governs; see authorize_admin_action
+    #[account(
+        mut,
+        seeds = [ADMIN_PROPOSAL_SEED, &admin_proposal.nonce.to_le_bytes()],
+        bump = admin_proposal.bump
+    )]
+    pub admin_proposal: Option<Account<'info, AdminProposal>>,
+
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    /// CHECK: Any wallet chosen by the admin; receives SOL or owns the destination token account
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub fee_vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Moves `amount` of collected fees in `mint` (Pubkey::default() for SOL) out
+/// of the fee vault. SOL withdrawals never dip into the vault's rent reserve.
+pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64, mint: Pubkey) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    authorize_admin_action(
+        &ctx.accounts.global_state,
+        &ctx.accounts.admin.key(),
+        ctx.accounts.admin_proposal.as_deref_mut(),
+        AdminAction::WithdrawFees { mint, amount, destination: ctx.accounts.destination.key() },
+    )?;
+
+    if mint == Pubkey::default() {
+        // --- NATIVE SOL WITHDRAWAL ---
+        // The vault is program-owned, so lamports move without a CPI
+        let vault_info = ctx.accounts.fee_vault.to_account_info();
+        let destination_info = ctx.accounts.destination.to_account_info();
+        let reserve = Rent::get()?.minimum_balance(vault_info.data_len());
+        let available = vault_info.lamports().saturating_sub(reserve);
+        require!(amount <= available, ErrorCode::InsufficientBalance);
+
+        **vault_info.try_borrow_mut_lamports()? = vault_info
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        **destination_info.try_borrow_mut_lamports()? = destination_info
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+    } else {
+        // --- SPL TOKEN WITHDRAWAL ---
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let mint_account = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let vault_ta = ctx.accounts.fee_vault_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let destination_ta = ctx.accounts.destination_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        require!(mint_account.key() == mint, ErrorCode::InvalidToken);
+        require!(vault_ta.mint == mint, ErrorCode::MintMismatch);
+        require!(vault_ta.owner == ctx.accounts.fee_vault.key(), ErrorCode::OwnerMismatch);
+        require!(destination_ta.mint == mint, ErrorCode::MintMismatch);
+        require!(
+            destination_ta.owner == ctx.accounts.destination.key(),
+            ErrorCode::OwnerMismatch
+        );
+        require!(vault_ta.amount >= amount, ErrorCode::InsufficientBalance);
+
+        let signer_seeds: &[&[&[u8]]] = &[&[FEE_VAULT_SEED, &[ctx.accounts.fee_vault.bump]]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: vault_ta.to_account_info(),
+                    mint: mint_account.to_account_info(),
+                    to: destination_ta.to_account_info(),
+                    authority: ctx.accounts.fee_vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            mint_account.decimals,
+        )?;
+    }
+
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    emit_event!(ctx, FeesWithdrawn {
+        admin: ctx.accounts.admin.key(),
+        mint,
+        amount,
+        destination: ctx.accounts.destination.key(),
+        timestamp: now,
+    });
+
+    Ok(())
+}
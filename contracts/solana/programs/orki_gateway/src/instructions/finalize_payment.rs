@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use crate::state::{EscrowBalance, GlobalState, Payment, PaymentStatus};
+use crate::events::{emit_event, PaymentFinalized};
+use crate::errors::ErrorCode;
+use crate::constants::{DEFAULT_FINALITY_SLOTS, ESCROW_BALANCE_SEED, GLOBAL_STATE_SEED, PAYMENT_SEED};
+use crate::clock;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(payment_id: u64)]
+pub struct FinalizePayment<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Only its address is used, to derive the payment record
+    pub payer: UncheckedAccount<'info>,
+
+    // Derived from the record's own id, so process_payment_v2 records, which
+    // pass 0 as `payment_id`, finalize too
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payer.key().as_ref(), &payment.id().seed()],
+        bump = payment.bump,
+        constraint = payment.payment_id == payment_id @ ErrorCode::InvalidPayment
+    )]
+    pub payment: Account<'info, Payment>,
+
+    // Required for escrowed payments, whose share it releases for claiming
+    #[account(
+        mut,
+        seeds = [ESCROW_BALANCE_SEED, payment.merchant.as_ref(), payment.mint.as_ref()],
+        bump = escrow_balance.bump
+    )]
+    pub escrow_balance: Option<Account<'info, EscrowBalance>>,
+}
+
+/// Marks a completed or captured payment Finalized once `finality_slots` have
+/// passed since its funds moved, after which it can no longer be forked away.
+/// Permissionless, so merchants, payers or a crank can call it. Escrow claims,
+/// disputes and receipts wait for it.
+pub fn finalize_payment(ctx: Context<FinalizePayment>, payment_id: u64) -> Result<()> {
+    let payment = &ctx.accounts.payment;
+    require!(
+        matches!(payment.status, PaymentStatus::Completed | PaymentStatus::Captured),
+        ErrorCode::InvalidPaymentStatus
+    );
+
+    let finality_slots = match ctx.accounts.global_state.finality_slots {
+        0 => DEFAULT_FINALITY_SLOTS,
+        slots => slots,
+    };
+    let current_slot = Clock::get()?.slot;
+    let final_at = payment
+        .slot
+        .checked_add(finality_slots)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(current_slot >= final_at, ErrorCode::FinalityNotReached);
+
+    // Records from before finality never added to pending_amount
+    if payment.escrowed && payment.slot != 0 {
+        let amount = payment.escrowed_amount()?;
+        let escrow_balance = ctx.accounts.escrow_balance.as_mut().ok_or(ErrorCode::MissingAccount)?;
+        escrow_balance.pending_amount = escrow_balance
+            .pending_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+
+    let payment = &mut ctx.accounts.payment;
+    payment.status = PaymentStatus::Finalized;
+
+    emit_event!(ctx, PaymentFinalized {
+        payment: payment.key(),
+        payer: payment.payer,
+        merchant: payment.merchant,
+        payment_id,
+        slot: payment.slot,
+        finalized_slot: current_slot,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
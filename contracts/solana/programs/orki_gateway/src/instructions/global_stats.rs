@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use crate::state::{GlobalState, GlobalStats};
+use crate::errors::ErrorCode;
+use crate::constants::{GLOBAL_STATE_SEED, GLOBAL_STATS_SEED};
+
+#[derive(Accounts)]
+pub struct InitGlobalStats<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + GlobalStats::INIT_SPACE,
+        seeds = [GLOBAL_STATS_SEED],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// One-time migration for deployments initialized before GlobalStats existed;
+/// `initialize` creates it for new ones. Counting starts from zero.
+pub fn init_global_stats(ctx: Context<InitGlobalStats>) -> Result<()> {
+    ctx.accounts.global_stats.bump = ctx.bumps.global_stats;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetStats<'info> {
+    #[account(
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+}
+
+/// Read-only; Anchor hands the struct to CPI callers through return data.
+pub fn get_stats(ctx: Context<GetStats>) -> Result<GlobalStats> {
+    Ok(GlobalStats::clone(&ctx.accounts.global_stats))
+}
@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
-use crate::state::GlobalState;
+use crate::state::{GlobalState, GlobalStats};
 use crate::errors::ErrorCode;
+use crate::constants::{DEFAULT_AUTHORIZATION_EXPIRY, DEFAULT_FINALITY_SLOTS, DEFAULT_PAYMENT_RETENTION, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, MAX_FEE_BPS};
 use crate::events::GlobalStateInitialized; // Add this import
 
 
@@ -8,36 +9,62 @@ use crate::events::GlobalStateInitialized; // Add this import
 pub struct Initialize<'info> {
     #[account(
         init,
-        payer = admin,
+        payer = payer,
         space = 8 + GlobalState::INIT_SPACE,
-        seeds = [b"global_state"],
+        seeds = [GLOBAL_STATE_SEED],
         bump
     )]
     pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + GlobalStats::INIT_SPACE,
+        seeds = [GLOBAL_STATS_SEED],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    /// Pays the rent; it only becomes the admin when passed as `admin`
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 pub fn initialize(
     ctx: Context<Initialize>, 
     fee_bps: u16,
-    fee_wallet: Pubkey
+    fee_wallet: Pubkey,
+    max_fee_bps: u16,
+    admin: Pubkey,
+    guardian: Option<Pubkey>,
 ) -> Result<()> {
-    require!(fee_bps <= 10000, ErrorCode::InvalidFee);
+    require!(admin != Pubkey::default(), ErrorCode::InvalidAdmin);
+    require!(max_fee_bps > 0 && max_fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+    require!(fee_bps <= max_fee_bps, ErrorCode::FeeCapExceeded);
     let state = &mut ctx.accounts.global_state;
-    state.admin = ctx.accounts.admin.key();
+    state.admin = admin;
     state.fee_bps = fee_bps;
     state.fee_wallet = fee_wallet;
     state.paused = false;
     state.bump = ctx.bumps.global_state;
+    state.pending_admin = None;
+    state.escrow_release_delay = 0;
+    state.authorization_expiry = DEFAULT_AUTHORIZATION_EXPIRY;
+    state.payment_retention_seconds = DEFAULT_PAYMENT_RETENTION;
+    state.finality_slots = DEFAULT_FINALITY_SLOTS;
+    state.max_fee_bps = max_fee_bps;
+    state.guardian = guardian.unwrap_or_default();
+    state.version = GlobalState::VERSION;
+    ctx.accounts.global_stats.bump = ctx.bumps.global_stats;
     
     // Emit event
     emit!(GlobalStateInitialized {
-        admin: ctx.accounts.admin.key(),
+        admin,
         fee_bps,
         fee_wallet,
         timestamp: Clock::get()?.unix_timestamp,
+        max_fee_bps,
+        payer: ctx.accounts.payer.key(),
+        guardian: state.guardian,
     });
     
     Ok(())
@@ -0,0 +1,357 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{Feature, FeatureFlags, FeeStats, FeeVault, GlobalState, Invoice, InvoiceStatus, Merchant, Operator, OperatorPermission};
+use crate::events::{emit_event, InvoiceCancelled, InvoiceCreated, InvoicePaid};
+use crate::errors::ErrorCode;
+use crate::math::calc_fee;
+use crate::clock;
+use crate::instructions::reject_transfer_fee_mint;
+use crate::constants::{FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, INVOICE_SEED, MAX_MEMO_LENGTH, MERCHANT_SEED, OPERATOR_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, invoice_id: u64)]
+pub struct CreateInvoice<'info> {
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Invoice::INIT_SPACE,
+        seeds = [INVOICE_SEED, merchant.key().as_ref(), &invoice_id.to_le_bytes()],
+        bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    // The merchant owner, or an operator with CreateInvoices
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, merchant.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_invoice(
+    ctx: Context<CreateInvoice>,
+    _name: String,
+    invoice_id: u64,
+    amount: u64,
+    mint: Pubkey,
+    expires_at: i64,
+    memo: String,
+) -> Result<()> {
+    Operator::authorize(
+        &ctx.accounts.merchant,
+        &ctx.accounts.authority.key(),
+        ctx.accounts.operator.as_deref(),
+        OperatorPermission::CreateInvoices,
+    )?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(memo.len() <= MAX_MEMO_LENGTH as usize, ErrorCode::MemoTooLong);
+
+    let now = clock::now(ctx.remaining_accounts)?;
+    require!(expires_at == 0 || expires_at > now, ErrorCode::InvoiceExpired);
+
+    let invoice = &mut ctx.accounts.invoice;
+    invoice.merchant = ctx.accounts.merchant.key();
+    invoice.invoice_id = invoice_id;
+    invoice.amount = amount;
+    invoice.mint = mint;
+    invoice.expires_at = expires_at;
+    invoice.memo = memo.clone();
+    invoice.status = InvoiceStatus::Open;
+    invoice.payer = Pubkey::default();
+    invoice.paid_at = 0;
+    invoice.bump = ctx.bumps.invoice;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_invoices = merchant.open_invoices.saturating_add(1);
+
+    emit_event!(ctx, InvoiceCreated {
+        merchant: invoice.merchant,
+        invoice: invoice.key(),
+        invoice_id,
+        amount,
+        mint,
+        expires_at,
+        memo,
+        timestamp: now,
+        merchant_name: ctx.accounts.merchant.name.clone(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct PayInvoice<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [INVOICE_SEED, merchant.key().as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Merchant wallet to receive funds (For SOL payment)
+    #[account(mut)]
+    pub merchant_wallet: AccountInfo<'info>,
+
+    // Receives the fee: SOL directly, SPL through its token account for the mint
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    // The invoice token's fee totals; the handler checks the mint
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, fee_stats.mint.as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    pub system_program: Program<'info, System>,
+
+    // --- Optional Accounts for SPL (classic Token or Token-2022) ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub merchant_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // The fee vault's token account for the mint
+    #[account(mut)]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+}
+
+/// Pays an open invoice. Amount and mint come from the invoice, never the caller.
+pub fn pay_invoice(ctx: Context<PayInvoice>, _name: String) -> Result<()> {
+    let state = &ctx.accounts.global_state;
+    let merchant = &ctx.accounts.merchant;
+    let invoice = &ctx.accounts.invoice;
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    require!(!state.paused, ErrorCode::Paused);
+    require!(merchant.active, ErrorCode::MerchantInactive);
+    require!(!merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    require!(invoice.status != InvoiceStatus::Paid, ErrorCode::InvoiceAlreadyPaid);
+    require!(invoice.status == InvoiceStatus::Open, ErrorCode::InvoiceNotOpen);
+    require!(invoice.expires_at == 0 || now <= invoice.expires_at, ErrorCode::InvoiceExpired);
+
+    let amount = invoice.amount;
+    require!(ctx.accounts.fee_stats.mint == invoice.mint, ErrorCode::InvalidToken);
+
+    // Calculate Fee, preferring the merchant's negotiated rate
+    let fee_bps = merchant.fee_bps(state.fee_bps);
+    let (fee, merchant_amount) = calc_fee(amount, fee_bps, state.fee_rounding)?;
+
+    if invoice.mint != Pubkey::default() {
+        // --- SPL TOKEN PAYMENT ---
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let merchant_ta = ctx.accounts.merchant_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let fee_ta = ctx.accounts.fee_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        require!(mint.key() == invoice.mint, ErrorCode::InvalidToken);
+
+        if token_program.key() == anchor_spl::token_2022::ID {
+            FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+            reject_transfer_fee_mint(mint)?;
+        }
+
+        // Validate token accounts
+        require!(payer_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(merchant_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(fee_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(payer_ta.owner == ctx.accounts.payer.key(), ErrorCode::InvalidPayerTokenAccount);
+        require!(merchant_ta.owner == merchant.settlement_wallet, ErrorCode::InvalidMerchantTokenAccount);
+        require!(fee_ta.owner == ctx.accounts.fee_vault.key(), ErrorCode::InvalidFeeTokenAccount);
+
+        // Check payer has enough balance
+        require!(payer_ta.amount >= amount, ErrorCode::InsufficientBalance);
+
+        // Sandbox merchants run every check above but move no funds
+        if !merchant.sandbox {
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: payer_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: fee_ta.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                fee,
+                mint.decimals,
+            )?;
+
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: payer_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: merchant_ta.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                merchant_amount,
+                mint.decimals,
+            )?;
+        }
+    } else {
+        // --- NATIVE SOL PAYMENT ---
+        require!(ctx.accounts.token_program.is_none(), ErrorCode::InvalidToken);
+
+        // Validate merchant wallet
+        require!(
+            ctx.accounts.merchant_wallet.key() == merchant.settlement_wallet,
+            ErrorCode::InvalidMerchantWallet
+        );
+
+        // Check payer has enough SOL
+        require!(ctx.accounts.payer.lamports() >= amount, ErrorCode::InsufficientBalance);
+
+        if !merchant.sandbox {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: ctx.accounts.fee_vault.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: ctx.accounts.merchant_wallet.to_account_info(),
+                    },
+                ),
+                merchant_amount,
+            )?;
+        }
+    }
+
+    if !ctx.accounts.merchant.sandbox {
+        let fee_stats = &mut ctx.accounts.fee_stats;
+        fee_stats.total_fees_collected = fee_stats
+            .total_fees_collected
+            .checked_add(fee)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_invoices = merchant.open_invoices.saturating_sub(1);
+
+    let invoice = &mut ctx.accounts.invoice;
+    invoice.status = InvoiceStatus::Paid;
+    invoice.payer = ctx.accounts.payer.key();
+    invoice.paid_at = now;
+
+    emit_event!(ctx, InvoicePaid {
+        merchant: invoice.merchant,
+        invoice: invoice.key(),
+        invoice_id: invoice.invoice_id,
+        payer: invoice.payer,
+        amount,
+        fee,
+        mint: invoice.mint,
+        timestamp: now,
+        merchant_name: merchant.name.clone(),
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CancelInvoice<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [INVOICE_SEED, merchant.key().as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub owner: Signer<'info>,
+}
+
+pub fn cancel_invoice(ctx: Context<CancelInvoice>, _name: String) -> Result<()> {
+    let invoice = &mut ctx.accounts.invoice;
+    require!(invoice.status != InvoiceStatus::Paid, ErrorCode::InvoiceAlreadyPaid);
+    require!(invoice.status == InvoiceStatus::Open, ErrorCode::InvoiceNotOpen);
+
+    invoice.status = InvoiceStatus::Cancelled;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_invoices = merchant.open_invoices.saturating_sub(1);
+
+    emit_event!(ctx, InvoiceCancelled {
+        merchant: invoice.merchant,
+        invoice: invoice.key(),
+        invoice_id: invoice.invoice_id,
+        timestamp: Clock::get()?.unix_timestamp,
+        merchant_name: merchant.name.clone(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,433 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{FeeVault, GlobalState, Merchant, MerchantBalance, SettlementMode, Vault};
+use crate::events::{
+    emit_event, BalanceWithdrawn, MerchantBalanceOpened, PayoutCranked, PayoutIntervalUpdated, SettlementModeUpdated,
+};
+use crate::errors::ErrorCode;
+use crate::constants::{FEE_VAULT_SEED, GLOBAL_STATE_SEED, MERCHANT_BALANCE_SEED, MERCHANT_SEED, MIN_PAYOUT_INTERVAL, VAULT_SEED};
+use crate::clock;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetSettlementMode<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// Switches between paying each payment out and accruing into per-mint
+/// balances. Balances left over after switching back stay withdrawable.
+pub fn set_settlement_mode(ctx: Context<SetSettlementMode>, _name: String, settlement_mode: SettlementMode) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    if settlement_mode == SettlementMode::Accrue {
+        require!(
+            !merchant.escrow_enabled && !merchant.split_enabled,
+            ErrorCode::SettlementModeConflict
+        );
+    }
+    merchant.settlement_mode = settlement_mode;
+
+    emit_event!(ctx, SettlementModeUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        settlement_mode,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, mint: Pubkey)]
+pub struct OpenMerchantBalance<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Vault::INIT_SPACE,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + MerchantBalance::INIT_SPACE,
+        seeds = [MERCHANT_BALANCE_SEED, merchant.key().as_ref(), mint.as_ref()],
+        bump
+    )]
+    pub merchant_balance: Account<'info, MerchantBalance>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the per-mint balance (Pubkey::default() for SOL) an Accrue-mode
+/// merchant needs before payments in that mint can land, along with the vault
+/// on first use. SPL balances also need the vault's associated token account
+/// for the mint, which anyone can create.
+pub fn open_merchant_balance(ctx: Context<OpenMerchantBalance>, _name: String, mint: Pubkey) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    vault.merchant = ctx.accounts.merchant.key();
+    vault.bump = ctx.bumps.vault;
+
+    let balance = &mut ctx.accounts.merchant_balance;
+    balance.merchant = ctx.accounts.merchant.key();
+    balance.mint = mint;
+    balance.amount = 0;
+    balance.bump = ctx.bumps.merchant_balance;
+
+    emit_event!(ctx, MerchantBalanceOpened {
+        merchant: ctx.accounts.merchant.key(),
+        mint,
+        vault: ctx.accounts.vault.key(),
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct WithdrawBalance<'info> {
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.settlement_wallet == settlement_wallet.key() @ ErrorCode::InvalidMerchantWallet,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_BALANCE_SEED, merchant.key().as_ref(), merchant_balance.mint.as_ref()],
+        bump = merchant_balance.bump
+    )]
+    pub merchant_balance: Account<'info, MerchantBalance>,
+
+    // Receives SOL withdrawals and owns the destination token account
+    #[account(mut)]
+    pub settlement_wallet: Signer<'info>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Pays `amount` of the merchant's accrued balance in `mint` out of the vault
+/// to the settlement wallet, which must sign. There is no release delay:
+/// accrued funds belong to the merchant as soon as they land.
+pub fn withdraw_balance(ctx: Context<WithdrawBalance>, _name: String, mint: Pubkey, amount: u64) -> Result<()> {
+    require!(!ctx.accounts.merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(ctx.accounts.merchant_balance.mint == mint, ErrorCode::InvalidToken);
+    let remaining = ctx
+        .accounts
+        .merchant_balance
+        .amount
+        .checked_sub(amount)
+        .ok_or(ErrorCode::InsufficientBalance)?;
+
+    pay_out_balance(
+        &ctx.accounts.merchant,
+        &ctx.accounts.vault,
+        (ctx.accounts.settlement_wallet.as_ref(), ctx.accounts.destination_token_account.as_ref()),
+        PayoutTokens {
+            token_program: ctx.accounts.token_program.as_ref(),
+            mint: ctx.accounts.mint.as_ref(),
+            vault_token_account: ctx.accounts.vault_token_account.as_ref(),
+        },
+        mint,
+        amount,
+    )?;
+
+    ctx.accounts.merchant_balance.amount = remaining;
+    if remaining == 0 {
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.funded_escrows = merchant.funded_escrows.saturating_sub(1);
+    }
+
+    emit_event!(ctx, BalanceWithdrawn {
+        merchant: ctx.accounts.merchant.key(),
+        mint,
+        amount,
+        balance: remaining,
+        destination: ctx.accounts.settlement_wallet.key(),
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+// The optional SPL accounts a vault payout draws on
+struct PayoutTokens<'a, 'info> {
+    token_program: Option<&'a Interface<'info, TokenInterface>>,
+    mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    vault_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Moves `amount` of `mint` out of the merchant's vault: lamports straight to
+/// the settlement wallet, tokens to a token account the wallet owns.
+fn pay_out_balance<'info>(
+    merchant: &Account<'info, Merchant>,
+    vault: &Account<'info, Vault>,
+    settlement: (&AccountInfo<'info>, Option<&InterfaceAccount<'info, TokenAccount>>),
+    tokens: PayoutTokens<'_, 'info>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let (wallet_info, destination_ta) = settlement;
+    if mint == Pubkey::default() {
+        // --- NATIVE SOL PAYOUT ---
+        // The vault is program-owned, so lamports move without a CPI
+        let vault_info = vault.to_account_info();
+        **vault_info.try_borrow_mut_lamports()? = vault_info
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        **wallet_info.try_borrow_mut_lamports()? = wallet_info
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        return Ok(());
+    }
+
+    // --- SPL TOKEN PAYOUT ---
+    let token_program = tokens.token_program.ok_or(ErrorCode::MissingAccount)?;
+    let mint_account = tokens.mint.ok_or(ErrorCode::MissingMint)?;
+    let vault_ta = tokens.vault_token_account.ok_or(ErrorCode::MissingAccount)?;
+    let destination_ta = destination_ta.ok_or(ErrorCode::MissingAccount)?;
+
+    require!(mint_account.key() == mint, ErrorCode::InvalidToken);
+    require!(vault_ta.mint == mint, ErrorCode::MintMismatch);
+    require!(vault_ta.owner == vault.key(), ErrorCode::OwnerMismatch);
+    require!(destination_ta.mint == mint, ErrorCode::MintMismatch);
+    require!(destination_ta.owner == wallet_info.key(), ErrorCode::InvalidMerchantTokenAccount);
+
+    let merchant_key = merchant.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, merchant_key.as_ref(), &[vault.bump]]];
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault_ta.to_account_info(),
+                mint: mint_account.to_account_info(),
+                to: destination_ta.to_account_info(),
+                authority: vault.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint_account.decimals,
+    )
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, mint: Pubkey)]
+pub struct SetPayoutInterval<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_BALANCE_SEED, merchant.key().as_ref(), mint.as_ref()],
+        bump = merchant_balance.bump
+    )]
+    pub merchant_balance: Account<'info, MerchantBalance>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Has crank_payout pay the balance out every `payout_interval` seconds,
+/// counted from now; 0 turns the schedule off.
+pub fn set_payout_interval(
+    ctx: Context<SetPayoutInterval>,
+    _name: String,
+    mint: Pubkey,
+    payout_interval: u32,
+) -> Result<()> {
+    require!(
+        payout_interval == 0 || payout_interval >= MIN_PAYOUT_INTERVAL,
+        ErrorCode::InvalidPayoutInterval
+    );
+    let now = clock::now(ctx.remaining_accounts)?;
+    let balance = &mut ctx.accounts.merchant_balance;
+    balance.payout_interval = payout_interval;
+    balance.last_payout_at = now;
+
+    emit_event!(ctx, PayoutIntervalUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        mint,
+        payout_interval,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CrankPayout<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.settlement_wallet == settlement_wallet.key() @ ErrorCode::InvalidMerchantWallet,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_BALANCE_SEED, merchant.key().as_ref(), merchant_balance.mint.as_ref()],
+        bump = merchant_balance.bump
+    )]
+    pub merchant_balance: Account<'info, MerchantBalance>,
+
+    /// CHECK: The merchant's settlement wallet, checked above; receives SOL
+    /// payouts and owns the destination token account
+    #[account(mut)]
+    pub settlement_wallet: UncheckedAccount<'info>,
+
+    // Pays the crank reward
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    // Anyone; receives the crank reward
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Pays the whole balance in `mint` out to the settlement wallet once its
+/// payout interval has passed since the last payout. Anyone may call it; the
+/// fee vault pays the caller `GlobalState::crank_reward` lamports when it holds
+/// that much above its rent reserve, and nothing otherwise.
+pub fn crank_payout(ctx: Context<CrankPayout>, _name: String, mint: Pubkey) -> Result<()> {
+    require!(!ctx.accounts.merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    let now = clock::now(ctx.remaining_accounts)?;
+    let balance = &ctx.accounts.merchant_balance;
+    require!(balance.mint == mint, ErrorCode::InvalidToken);
+    require!(balance.payout_interval > 0, ErrorCode::PayoutNotDue);
+    let due_at = balance
+        .last_payout_at
+        .checked_add(i64::from(balance.payout_interval))
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(now >= due_at, ErrorCode::PayoutNotDue);
+    let amount = balance.amount;
+    require!(amount > 0, ErrorCode::NothingToClaim);
+
+    pay_out_balance(
+        &ctx.accounts.merchant,
+        &ctx.accounts.vault,
+        (ctx.accounts.settlement_wallet.as_ref(), ctx.accounts.destination_token_account.as_ref()),
+        PayoutTokens {
+            token_program: ctx.accounts.token_program.as_ref(),
+            mint: ctx.accounts.mint.as_ref(),
+            vault_token_account: ctx.accounts.vault_token_account.as_ref(),
+        },
+        mint,
+        amount,
+    )?;
+
+    let balance = &mut ctx.accounts.merchant_balance;
+    balance.amount = 0;
+    // A second crank within the same interval finds the payout not yet due
+    balance.last_payout_at = now;
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.funded_escrows = merchant.funded_escrows.saturating_sub(1);
+
+    let fee_vault = ctx.accounts.fee_vault.to_account_info();
+    let cranker = ctx.accounts.cranker.to_account_info();
+    let reserve = Rent::get()?.minimum_balance(fee_vault.data_len());
+    let crank_reward = ctx.accounts.global_state.crank_reward;
+    let reward = if fee_vault.lamports().saturating_sub(reserve) >= crank_reward { crank_reward } else { 0 };
+    if reward > 0 {
+        **fee_vault.try_borrow_mut_lamports()? = fee_vault
+            .lamports()
+            .checked_sub(reward)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        **cranker.try_borrow_mut_lamports()? = cranker
+            .lamports()
+            .checked_add(reward)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+
+    emit_event!(ctx, PayoutCranked {
+        merchant: ctx.accounts.merchant.key(),
+        mint,
+        amount,
+        destination: ctx.accounts.settlement_wallet.key(),
+        cranker: ctx.accounts.cranker.key(),
+        reward,
+        timestamp: now,
+    });
+
+    Ok(())
+}
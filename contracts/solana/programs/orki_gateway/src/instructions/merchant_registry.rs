@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, MerchantIndex, MerchantRegistry};
+use crate::errors::ErrorCode;
+use crate::events::MerchantIndexed;
+use crate::clock;
+use crate::constants::{MERCHANT_INDEX_SEED, MERCHANT_REGISTRY_SEED, MERCHANT_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct IndexMerchant<'info> {
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + MerchantRegistry::INIT_SPACE,
+        seeds = [MERCHANT_REGISTRY_SEED],
+        bump
+    )]
+    pub merchant_registry: Account<'info, MerchantRegistry>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MerchantIndex::INIT_SPACE,
+        seeds = [MERCHANT_INDEX_SEED, &(merchant_registry.merchant_count + 1).to_le_bytes()],
+        bump
+    )]
+    pub merchant_index: Account<'info, MerchantIndex>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Lists a merchant registered before the directory existed; newer merchants
+/// get their slot at registration. Anyone may pay for it, since the slot only
+/// points at the merchant.
+pub fn index_merchant(ctx: Context<IndexMerchant>, _name: String) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    require!(merchant.merchant_index == 0, ErrorCode::MerchantAlreadyIndexed);
+
+    let registry = &mut ctx.accounts.merchant_registry;
+    registry.bump = ctx.bumps.merchant_registry;
+    merchant.merchant_index = registry.next_index()?;
+    ctx.accounts.merchant_index.set_inner(MerchantIndex {
+        index: merchant.merchant_index,
+        merchant: merchant.key(),
+        bump: ctx.bumps.merchant_index,
+        reserved: [0; 32],
+    });
+
+    emit!(MerchantIndexed {
+        merchant: merchant.key(),
+        merchant_index: merchant.merchant_index,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
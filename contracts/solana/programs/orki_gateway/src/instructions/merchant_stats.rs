@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, MerchantStats};
+use crate::errors::ErrorCode;
+use crate::constants::{MERCHANT_SEED, MERCHANT_STATS_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct InitMerchantStats<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + MerchantStats::INIT_SPACE,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Backfills the stats account for merchants registered before it existed;
+/// newer merchants get one at registration. Counting starts from zero.
+pub fn init_merchant_stats(ctx: Context<InitMerchantStats>, _name: String) -> Result<()> {
+    let stats = &mut ctx.accounts.merchant_stats;
+    stats.merchant = ctx.accounts.merchant.key();
+    stats.bump = ctx.bumps.merchant_stats;
+    Ok(())
+}
@@ -0,0 +1,332 @@
+use anchor_lang::prelude::*;
+use crate::state::{AcceptedTokens, Merchant, MerchantIndex, MerchantName, MerchantStats, MerchantTransfer, SplitConfig};
+use crate::errors::ErrorCode;
+use crate::events::{MerchantOwnershipProposed, MerchantOwnershipTransferred};
+use crate::clock;
+use crate::instructions::register_merchant::claim_merchant_name;
+use crate::constants::{ACCEPTED_TOKENS_SEED, MERCHANT_INDEX_SEED, MERCHANT_NAME_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, MERCHANT_TRANSFER_SEED, SPLIT_CONFIG_SEED};
+
+/// How a merchant moves to a new address, which picks the error to report.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MerchantMove {
+    Rename,
+    Transfer,
+}
+
+/// What cannot follow a merchant to a new address: vault balances, invoices,
+/// operator grants, settlement routes, discount codes, authorization holds,
+/// payment streams, the daily volume and the rewards hook are keyed by the
+/// old one. Shared by
+/// rename_merchant and the ownership transfer, which checks it on proposal
+/// for early feedback and again on acceptance.
+pub(crate) fn check_movable(merchant: &Merchant, by: MerchantMove) -> Result<()> {
+    let blocked = |rename: ErrorCode, transfer: ErrorCode| match by {
+        MerchantMove::Rename => rename,
+        MerchantMove::Transfer => transfer,
+    };
+    require!(
+        !merchant.escrow_enabled,
+        blocked(ErrorCode::RenameBlockedByEscrow, ErrorCode::TransferBlockedByEscrow)
+    );
+    require!(
+        !merchant.accrues(),
+        blocked(ErrorCode::RenameBlockedByAccrual, ErrorCode::TransferBlockedByAccrual)
+    );
+    require!(merchant.open_invoices == 0, ErrorCode::MerchantHasOpenInvoices);
+    require!(merchant.operators == 0, ErrorCode::MerchantHasOperators);
+    require!(merchant.settlement_routes == 0, ErrorCode::MerchantHasSettlementRoutes);
+    require!(merchant.discount_codes == 0, ErrorCode::MerchantHasDiscountCodes);
+    require!(merchant.open_authorizations == 0, ErrorCode::MerchantHasOpenAuthorizations);
+    require!(merchant.open_streams == 0, ErrorCode::MerchantHasOpenStreams);
+    // The day's volume is counted against the merchant's address
+    require!(
+        merchant.daily_limit == 0,
+        blocked(ErrorCode::RenameBlockedByDailyLimit, ErrorCode::TransferBlockedByDailyLimit)
+    );
+    // So is the rewards hook, which also signs for it
+    require!(
+        !merchant.rewards_enabled,
+        blocked(ErrorCode::RenameBlockedByRewardsHook, ErrorCode::TransferBlockedByRewardsHook)
+    );
+    Ok(())
+}
+
+/// Refunds only follow a merchant one move back, through previous_merchant,
+/// so moving to `new_merchant` must not drop an address payers may still
+/// hold payments against. Moving back to that address drops nothing: its
+/// payments match directly again.
+pub(crate) fn check_previous_merchant(merchant: &Merchant, new_merchant: &Pubkey) -> Result<()> {
+    require!(
+        merchant.previous_merchant == Pubkey::default() || merchant.previous_merchant == *new_merchant,
+        ErrorCode::PreviousMerchantNotReleased
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct TransferMerchantOwnership<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + MerchantTransfer::INIT_SPACE,
+        seeds = [MERCHANT_TRANSFER_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_transfer: Account<'info, MerchantTransfer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// First half of a merchant sale: the current owner names the new owner, who
+/// then calls accept_merchant_ownership. One proposal at a time; cancel it to
+/// name someone else.
+pub fn transfer_merchant_ownership(
+    ctx: Context<TransferMerchantOwnership>,
+    name: String,
+    new_owner: Pubkey,
+) -> Result<()> {
+    let owner = ctx.accounts.owner.key();
+    require!(new_owner != owner && new_owner != Pubkey::default(), ErrorCode::CannotTransferToSelf);
+    check_movable(&ctx.accounts.merchant, MerchantMove::Transfer)?;
+
+    let now = clock::now(ctx.remaining_accounts)?;
+    let transfer = &mut ctx.accounts.merchant_transfer;
+    transfer.merchant = ctx.accounts.merchant.key();
+    transfer.owner = owner;
+    transfer.new_owner = new_owner;
+    transfer.proposed_at = now;
+    transfer.bump = ctx.bumps.merchant_transfer;
+
+    emit!(MerchantOwnershipProposed {
+        owner,
+        new_owner,
+        merchant: ctx.accounts.merchant.key(),
+        name,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CancelMerchantOwnershipTransfer<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_TRANSFER_SEED, merchant.key().as_ref()],
+        bump = merchant_transfer.bump,
+        close = owner
+    )]
+    pub merchant_transfer: Account<'info, MerchantTransfer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+pub fn cancel_merchant_ownership_transfer(_ctx: Context<CancelMerchantOwnershipTransfer>, _name: String) -> Result<()> {
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct AcceptMerchantOwnership<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        close = owner,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Box<Account<'info, Merchant>>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_TRANSFER_SEED, merchant.key().as_ref()],
+        bump = merchant_transfer.bump,
+        close = owner,
+        constraint = merchant_transfer.new_owner == new_owner.key() @ ErrorCode::Unauthorized
+    )]
+    pub merchant_transfer: Account<'info, MerchantTransfer>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = 8 + Merchant::INIT_SPACE,
+        seeds = [MERCHANT_SEED, new_owner.key().as_ref(), name.as_bytes()],
+        bump
+    )]
+    pub new_merchant: Box<Account<'info, Merchant>>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump,
+        close = owner
+    )]
+    pub merchant_stats: Box<Account<'info, MerchantStats>>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = 8 + MerchantStats::INIT_SPACE,
+        seeds = [MERCHANT_STATS_SEED, new_merchant.key().as_ref()],
+        bump
+    )]
+    pub new_merchant_stats: Box<Account<'info, MerchantStats>>,
+    // Directory slot; required unless the merchant was never indexed
+    #[account(
+        mut,
+        seeds = [MERCHANT_INDEX_SEED, &merchant.merchant_index.to_le_bytes()],
+        bump = merchant_index.bump
+    )]
+    pub merchant_index: Option<Account<'info, MerchantIndex>>,
+    // Both split accounts are required for split merchants
+    #[account(
+        mut,
+        seeds = [SPLIT_CONFIG_SEED, merchant.key().as_ref()],
+        bump = split_config.bump,
+        close = owner
+    )]
+    pub split_config: Option<Box<Account<'info, SplitConfig>>>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = 8 + SplitConfig::INIT_SPACE,
+        seeds = [SPLIT_CONFIG_SEED, new_merchant.key().as_ref()],
+        bump
+    )]
+    pub new_split_config: Option<Box<Account<'info, SplitConfig>>>,
+    // Both accepted-token accounts are required while the list is in use
+    #[account(
+        mut,
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump = accepted_tokens.bump,
+        close = owner
+    )]
+    pub accepted_tokens: Option<Box<Account<'info, AcceptedTokens>>>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = 8 + AcceptedTokens::INIT_SPACE,
+        seeds = [ACCEPTED_TOKENS_SEED, new_merchant.key().as_ref()],
+        bump
+    )]
+    pub new_accepted_tokens: Option<Box<Account<'info, AcceptedTokens>>>,
+    // The old owner's claim on the name; required once the merchant holds one
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [MERCHANT_NAME_SEED, owner.key().as_ref(), merchant_name.normalized_name.as_bytes()],
+        bump = merchant_name.bump,
+        close = owner
+    )]
+    pub merchant_name: Option<Box<Account<'info, MerchantName>>>,
+    /// CHECK: The new owner's claim on the name, derived and opened by the
+    /// handler; taken if the new owner already runs it in another casing
+    #[account(mut)]
+    pub new_merchant_name: UncheckedAccount<'info>,
+    /// CHECK: The current owner, matched by has_one; receives the rent of the closed accounts
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Second half of a merchant sale, signed by the new owner. The owner is part
+/// of the merchant PDA seeds, so like a rename this moves the merchant, its
+/// stats, directory slot, split config, accepted-token list and name claim
+/// to `[b"merchant", new_owner, name]` and closes the old accounts to the old
+/// owner. Payments against the old address fail from then on. The settlement
+/// wallet carries over; the new owner changes it through the new merchant's
+/// payout book and its delay. Recent payments, the notification queue, the
+/// payout book and discount codes stay with the old address.
+pub fn accept_merchant_ownership(ctx: Context<AcceptMerchantOwnership>, name: String) -> Result<()> {
+    let old = &ctx.accounts.merchant;
+    check_movable(old, MerchantMove::Transfer)?;
+    check_previous_merchant(old, &ctx.accounts.new_merchant.key())?;
+    require!(
+        old.merchant_index == 0 || ctx.accounts.merchant_index.is_some(),
+        ErrorCode::MissingAccount
+    );
+    require!(
+        !old.split_enabled || (ctx.accounts.split_config.is_some() && ctx.accounts.new_split_config.is_some()),
+        ErrorCode::MissingAccount
+    );
+    require!(
+        !old.accepted_tokens_enabled
+            || (ctx.accounts.accepted_tokens.is_some() && ctx.accounts.new_accepted_tokens.is_some()),
+        ErrorCode::MissingAccount
+    );
+    require!(!old.name_indexed || ctx.accounts.merchant_name.is_some(), ErrorCode::MissingAccount);
+
+    let old_merchant = old.key();
+    let new_owner = ctx.accounts.new_owner.key();
+    let new_merchant = ctx.accounts.new_merchant.key();
+    ctx.accounts.new_merchant.set_inner(Merchant {
+        owner: new_owner,
+        bump: ctx.bumps.new_merchant,
+        // Lets the new owner refund payments made under the old address
+        previous_merchant: old_merchant,
+        name_indexed: true,
+        ..Merchant::clone(old)
+    });
+    claim_merchant_name(
+        &ctx.accounts.new_merchant_name,
+        &ctx.accounts.new_owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        new_owner,
+        new_merchant,
+        &name,
+    )?;
+    // Lifetime counters carry over to the new address
+    ctx.accounts.new_merchant_stats.set_inner(MerchantStats {
+        merchant: new_merchant,
+        bump: ctx.bumps.new_merchant_stats,
+        ..MerchantStats::clone(&ctx.accounts.merchant_stats)
+    });
+    if let (Some(old_split), Some(new_split)) =
+        (ctx.accounts.split_config.as_ref(), ctx.accounts.new_split_config.as_mut())
+    {
+        new_split.set_inner(SplitConfig {
+            merchant: new_merchant,
+            bump: ctx.bumps.new_split_config.ok_or(ErrorCode::MissingAccount)?,
+            ..SplitConfig::clone(old_split)
+        });
+    }
+    if let (Some(old_tokens), Some(new_tokens)) =
+        (ctx.accounts.accepted_tokens.as_ref(), ctx.accounts.new_accepted_tokens.as_mut())
+    {
+        new_tokens.set_inner(AcceptedTokens {
+            merchant: new_merchant,
+            bump: ctx.bumps.new_accepted_tokens.ok_or(ErrorCode::MissingAccount)?,
+            ..AcceptedTokens::clone(old_tokens)
+        });
+    }
+    // The directory slot follows the merchant to its new address
+    if let Some(entry) = ctx.accounts.merchant_index.as_mut() {
+        entry.merchant = new_merchant;
+    }
+
+    emit!(MerchantOwnershipTransferred {
+        old_owner: ctx.accounts.owner.key(),
+        new_owner,
+        old_merchant,
+        new_merchant,
+        name,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use crate::state::{GlobalState, Merchant, Payment};
+use crate::errors::ErrorCode;
+use crate::constants::{DEFAULT_AUTHORIZATION_EXPIRY, DEFAULT_FINALITY_SLOTS, DEFAULT_PAYMENT_RETENTION, GLOBAL_STATE_SEED, MAX_FEE_BPS, MERCHANT_SEED};
+use crate::events::{emit_event, GlobalStateMigrated, MerchantAccountUpgraded, MerchantMigrated, PaymentAccountUpgraded};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateGlobalState<'info> {
+    /// CHECK: Loaded by hand because an older layout can't deserialize as GlobalState
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATE_SEED],
+        bump
+    )]
+    pub global_state: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Brings a GlobalState created by an older program version up to the current
+/// layout: grows it to the current size, fills in the defaults initialize
+/// would have set, and stamps the version every other instruction checks.
+/// Fields appended by the resize are zero-initialized, which decodes as their
+/// defaults. Safe to rerun.
+pub fn migrate_global_state(ctx: Context<MigrateGlobalState>) -> Result<()> {
+    let info = ctx.accounts.global_state.to_account_info();
+
+    {
+        let data = info.try_borrow_data()?;
+        require!(data.len() >= 8 + 32, ErrorCode::InvalidAccountData);
+        // admin is the first field in every GlobalState layout
+        let admin = Pubkey::try_from(&data[8..40]).map_err(|_| ErrorCode::InvalidAccountData)?;
+        require!(admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    }
+
+    let (old_len, new_len) = migrate_account::<GlobalState>(
+        &info,
+        &ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let mut state = {
+        let data = info.try_borrow_data()?;
+        GlobalState::try_deserialize(&mut &data[..])?
+    };
+    if state.authorization_expiry == 0 {
+        state.authorization_expiry = DEFAULT_AUTHORIZATION_EXPIRY;
+    }
+    if state.payment_retention_seconds == 0 {
+        state.payment_retention_seconds = DEFAULT_PAYMENT_RETENTION;
+    }
+    if state.finality_slots == 0 {
+        state.finality_slots = DEFAULT_FINALITY_SLOTS;
+    }
+    if state.max_fee_bps == 0 {
+        state.max_fee_bps = MAX_FEE_BPS;
+    }
+    state.version = GlobalState::VERSION;
+    state.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+    // Emit event
+    emit_event!(ctx, GlobalStateMigrated {
+        admin: ctx.accounts.admin.key(),
+        old_len: old_len as u64,
+        new_len: new_len as u64,
+        version: GlobalState::VERSION,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct MigrateMerchant<'info> {
+    /// CHECK: Loaded by hand; a pre-upgrade Merchant may be too short to deserialize
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump
+    )]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Brings a merchant registered by an older program version up to the current
+/// layout, with the owner paying any extra rent. Grows the account to the
+/// current size, then backfills `accepts_any_token` for merchants registered
+/// before the flag existed: those used a default settlement_token to mean "any
+/// token", so the flag is set from that. Finally stamps the version every
+/// other merchant instruction checks. The seeds tie the account to the signer,
+/// so only the owner can migrate it.
+pub fn migrate_merchant(ctx: Context<MigrateMerchant>, _name: String) -> Result<()> {
+    let info = ctx.accounts.merchant.to_account_info();
+
+    let (old_len, new_len) = migrate_account::<Merchant>(
+        &info,
+        &ctx.accounts.owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let mut merchant = {
+        let data = info.try_borrow_data()?;
+        Merchant::try_deserialize(&mut &data[..])?
+    };
+    require!(merchant.version < Merchant::VERSION, ErrorCode::MerchantAlreadyMigrated);
+
+    if !merchant.token_rule_set {
+        merchant.accepts_any_token = merchant.settlement_token == Pubkey::default();
+        merchant.token_rule_set = true;
+    }
+    merchant.version = Merchant::VERSION;
+    merchant.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+    // Emit event
+    emit_event!(ctx, MerchantMigrated {
+        merchant: info.key(),
+        accepts_any_token: merchant.accepts_any_token,
+        old_len: old_len as u64,
+        new_len: new_len as u64,
+        version: Merchant::VERSION,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct UpgradeMerchantAccount<'info> {
+    /// CHECK: Loaded by hand; a pre-upgrade Merchant may be too short to deserialize
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump
+    )]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a merchant registered before `metadata_uri` and `category` existed,
+/// or one whose name and metadata URI leave too little slack for
+/// `confirmation_message` and what follows it, to the current size, with the
+/// owner paying the extra rent. The seeds tie the account to the signer, so
+/// only the owner can upgrade it. Only resizes: migrate_merchant does the same
+/// and also bumps the version.
+pub fn upgrade_merchant_account(ctx: Context<UpgradeMerchantAccount>, _name: String) -> Result<()> {
+    let info = ctx.accounts.merchant.to_account_info();
+
+    let (old_len, new_len) = migrate_account::<Merchant>(
+        &info,
+        &ctx.accounts.owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // Emit event
+    emit_event!(ctx, MerchantAccountUpgraded {
+        merchant: info.key(),
+        owner: ctx.accounts.owner.key(),
+        old_len: old_len as u64,
+        new_len: new_len as u64,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpgradePaymentAccount<'info> {
+    /// CHECK: Loaded by hand; a pre-upgrade Payment with a long memo is too short to deserialize
+    #[account(mut)]
+    pub payment: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a payment recorded before `receipt_mint` or `slot` existed to the
+/// current size. Permissionless, like close_payment: any signer may pay the
+/// extra rent, which goes back to the original payer when the record is
+/// closed. Payments made before memos existed, or whose memo is too long to
+/// leave room for the newer fields, need it before they can be loaded again.
+pub fn upgrade_payment_account(ctx: Context<UpgradePaymentAccount>) -> Result<()> {
+    let info = ctx.accounts.payment.to_account_info();
+
+    let (old_len, new_len) = migrate_account::<Payment>(
+        &info,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // Emit event
+    emit_event!(ctx, PaymentAccountUpgraded {
+        payment: info.key(),
+        payer: ctx.accounts.payer.key(),
+        old_len: old_len as u64,
+        new_len: new_len as u64,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Reallocs a program-owned account of type `T` up to `8 + T::INIT_SPACE`, with
+/// `payer` topping up rent. Callers authorize the payer before calling.
+/// Returns the (old, new) data lengths; accounts already at size are left alone.
+pub fn migrate_account<'info, T: Discriminator + Space>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<(usize, usize)> {
+    require!(info.owner == &crate::ID, ErrorCode::InvalidAccountData);
+    {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == *T::DISCRIMINATOR,
+            ErrorCode::InvalidAccountData
+        );
+    }
+
+    let old_len = info.data_len();
+    let new_len = 8 + T::INIT_SPACE;
+    if old_len < new_len {
+        let required = Rent::get()?.minimum_balance(new_len);
+        let top_up = required.saturating_sub(info.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    anchor_lang::system_program::Transfer {
+                        from: payer.clone(),
+                        to: info.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        info.resize(new_len)?;
+    }
+
+    Ok((old_len, info.data_len()))
+}
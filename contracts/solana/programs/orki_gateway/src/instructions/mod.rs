@@ -3,9 +3,91 @@ pub mod register_merchant;
 pub mod update_merchant;
 pub mod admin;
 pub mod process_payment;
+pub mod refund;
+pub mod migrate;
+pub mod feature_flags;
+pub mod escrow;
+pub mod invoice;
+pub mod recent_payments;
+pub mod subscription;
+pub mod split;
+pub mod authorization;
+pub mod payout_book;
+pub mod accepted_tokens;
+pub mod fee_vault;
+pub mod merchant_stats;
+pub mod global_stats;
+pub mod payment_link;
+pub mod close_payment;
+pub mod operator;
+pub mod quote;
+pub mod discount;
+pub mod blocklist;
+pub mod token_fee;
+pub mod fee_tiers;
+pub mod dispute;
+pub mod merchant_balance;
+pub mod verify_payment;
+pub mod merchant_registry;
+pub mod finalize_payment;
+pub mod notification_queue;
+pub mod merchant_transfer;
+pub mod stream;
+pub mod delegated_payment;
+pub mod rewards_hook;
+pub mod admin_council;
+pub mod payer_throttle;
+pub mod settlement_route;
+#[cfg(feature = "swap")]
+pub mod swap;
+#[cfg(feature = "receipts")]
+pub mod receipt;
+#[cfg(feature = "test-clock")]
+pub mod test_clock;
 
 pub use initialize::*;
 pub use register_merchant::*;
 pub use update_merchant::*;
 pub use admin::*;
 pub use process_payment::*;
+pub use refund::*;
+pub use migrate::*;
+pub use feature_flags::*;
+pub use escrow::*;
+pub use invoice::*;
+pub use recent_payments::*;
+pub use subscription::*;
+pub use split::*;
+pub use authorization::*;
+pub use payout_book::*;
+pub use accepted_tokens::*;
+pub use fee_vault::*;
+pub use merchant_stats::*;
+pub use global_stats::*;
+pub use payment_link::*;
+pub use close_payment::*;
+pub use operator::*;
+pub use quote::*;
+pub use discount::*;
+pub use blocklist::*;
+pub use token_fee::*;
+pub use fee_tiers::*;
+pub use dispute::*;
+pub use merchant_balance::*;
+pub use verify_payment::*;
+pub use merchant_registry::*;
+pub use finalize_payment::*;
+pub use notification_queue::*;
+pub use merchant_transfer::*;
+pub use stream::*;
+pub use delegated_payment::*;
+pub use rewards_hook::*;
+pub use admin_council::*;
+pub use payer_throttle::*;
+pub use settlement_route::*;
+#[cfg(feature = "swap")]
+pub use swap::*;
+#[cfg(feature = "receipts")]
+pub use receipt::*;
+#[cfg(feature = "test-clock")]
+pub use test_clock::*;
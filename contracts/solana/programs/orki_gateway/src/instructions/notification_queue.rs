@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, NotificationQueue};
+use crate::errors::ErrorCode;
+use crate::constants::{MERCHANT_SEED, NOTIFICATION_QUEUE_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct InitNotificationQueue<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + NotificationQueue::INIT_SPACE,
+        seeds = [NOTIFICATION_QUEUE_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub notification_queue: Box<Account<'info, NotificationQueue>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opts a merchant into on-chain payment notifications. Once created,
+/// process_payment appends to the queue whenever the account is passed.
+pub fn init_notification_queue(ctx: Context<InitNotificationQueue>, _name: String) -> Result<()> {
+    let queue = &mut ctx.accounts.notification_queue;
+    queue.merchant = ctx.accounts.merchant.key();
+    queue.bump = ctx.bumps.notification_queue;
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClearNotifications<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [NOTIFICATION_QUEUE_SEED, merchant.key().as_ref()],
+        bump = notification_queue.bump
+    )]
+    pub notification_queue: Box<Account<'info, NotificationQueue>>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Empties the merchant's queue once its backend has handled the entries.
+/// `total` keeps counting across clears.
+pub fn clear_notifications(ctx: Context<ClearNotifications>, _name: String) -> Result<()> {
+    ctx.accounts.notification_queue.clear();
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, Operator, OperatorPermission};
+use crate::events::{OperatorGranted, OperatorRevoked};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{MERCHANT_SEED, OPERATOR_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String, operator: Pubkey)]
+pub struct GrantOperator<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    // Granting again replaces the permissions
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Operator::INIT_SPACE,
+        seeds = [OPERATOR_SEED, merchant.key().as_ref(), operator.as_ref()],
+        bump
+    )]
+    pub operator_account: Account<'info, Operator>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Lets a hot key, e.g. a cashier app, act for the merchant within
+/// `permissions` (OperatorPermission bits) without holding the owner key.
+pub fn grant_operator(
+    ctx: Context<GrantOperator>,
+    _name: String,
+    operator: Pubkey,
+    permissions: u8,
+) -> Result<()> {
+    require!(
+        permissions != 0 && permissions & !OperatorPermission::ALL == 0,
+        ErrorCode::InvalidPermissions
+    );
+
+    let merchant = &mut ctx.accounts.merchant;
+    let operator_account = &mut ctx.accounts.operator_account;
+    if operator_account.operator == Pubkey::default() {
+        merchant.operators = merchant.operators.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+    }
+    operator_account.merchant = merchant.key();
+    operator_account.operator = operator;
+    operator_account.permissions = permissions;
+    operator_account.bump = ctx.bumps.operator_account;
+
+    emit!(OperatorGranted {
+        merchant: merchant.key(),
+        operator,
+        permissions,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RevokeOperator<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [OPERATOR_SEED, merchant.key().as_ref(), operator_account.operator.as_ref()],
+        bump = operator_account.bump,
+        close = owner
+    )]
+    pub operator_account: Account<'info, Operator>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+pub fn revoke_operator(ctx: Context<RevokeOperator>, _name: String) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.operators = merchant.operators.saturating_sub(1);
+
+    emit!(OperatorRevoked {
+        merchant: merchant.key(),
+        operator: ctx.accounts.operator_account.operator,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
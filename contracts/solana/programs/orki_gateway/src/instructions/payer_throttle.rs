@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, PayerThrottle};
+use crate::events::{emit_event, PayerIntervalUpdated};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{MERCHANT_SEED, PAYER_THROTTLE_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetPayerInterval<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// Sets how many seconds must pass between two payments from the same payer,
+/// blunting spam of tiny payments at the merchant; 0 lifts the throttle.
+/// Payments then need the payer's PayerThrottle, which the first one opens.
+pub fn set_payer_interval(ctx: Context<SetPayerInterval>, _name: String, min_interval_per_payer: u32) -> Result<()> {
+    ctx.accounts.merchant.min_interval_per_payer = min_interval_per_payer;
+
+    emit_event!(ctx, PayerIntervalUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        min_interval_per_payer,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClosePayerThrottle<'info> {
+    #[account(
+        mut,
+        has_one = payer,
+        seeds = [PAYER_THROTTLE_SEED, payer_throttle.merchant.as_ref(), payer.key().as_ref()],
+        bump = payer_throttle.bump,
+        close = payer
+    )]
+    pub payer_throttle: Account<'info, PayerThrottle>,
+    /// CHECK: The throttled merchant, read for its interval; a closed or
+    /// renamed merchant no longer throttles anyone
+    #[account(address = payer_throttle.merchant)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+/// Hands the throttle's rent back to the payer. Only once the merchant's
+/// interval has passed since the last payment, so closing can't be used to
+/// skip the wait.
+pub fn close_payer_throttle(ctx: Context<ClosePayerThrottle>) -> Result<()> {
+    let merchant = &ctx.accounts.merchant;
+    let interval = if merchant.owner == &crate::ID {
+        Merchant::try_deserialize(&mut &merchant.try_borrow_data()?[..])
+            .map_or(0, |merchant| merchant.min_interval_per_payer)
+    } else {
+        0
+    };
+    ctx.accounts.payer_throttle.check(clock::now(ctx.remaining_accounts)?, interval)
+}
@@ -0,0 +1,141 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, PaymentId, PaymentLink};
+use crate::events::{PaymentLinkCreated, PaymentLinkDeactivated};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::instructions::{process_payment, PaymentReceipt, ProcessPayment};
+use crate::constants::{MERCHANT_SEED, PAYMENT_LINK_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String, link_seed: u64)]
+pub struct CreateLink<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PaymentLink::INIT_SPACE,
+        seeds = [PAYMENT_LINK_SEED, merchant.key().as_ref(), &link_seed.to_le_bytes()],
+        bump
+    )]
+    pub link: Account<'info, PaymentLink>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens a reusable fixed-price link, e.g. for a printed QR code. A
+/// `max_uses` or `expires_at` of 0 leaves that limit off.
+#[allow(clippy::too_many_arguments)]
+pub fn create_link(
+    ctx: Context<CreateLink>,
+    _name: String,
+    link_seed: u64,
+    amount: u64,
+    mint: Pubkey,
+    label: String,
+    max_uses: u64,
+    expires_at: i64,
+) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(label.len() <= 32, ErrorCode::LabelTooLong);
+
+    let now = clock::now(ctx.remaining_accounts)?;
+    require!(expires_at == 0 || expires_at > now, ErrorCode::LinkExpired);
+
+    let link = &mut ctx.accounts.link;
+    link.merchant = ctx.accounts.merchant.key();
+    link.link_seed = link_seed;
+    link.amount = amount;
+    link.mint = mint;
+    link.max_uses = max_uses;
+    link.uses = 0;
+    link.expires_at = expires_at;
+    link.active = true;
+    link.bump = ctx.bumps.link;
+    link.label = label.clone();
+
+    emit!(PaymentLinkCreated {
+        merchant: link.merchant,
+        link: link.key(),
+        link_seed,
+        amount,
+        mint,
+        max_uses,
+        expires_at,
+        label,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+/// Charges the payer the link's amount in the link's mint. The payment goes
+/// through process_payment with the link's next use as its id, so it pays
+/// the fee vault, passes the merchant's checks and can be refunded and
+/// finalized like any other. Of two payers racing for the same use, the
+/// second fails on its payment record, as `uses` has already moved on.
+pub fn pay_link<'info>(
+    ctx: Context<'_, '_, '_, 'info, ProcessPayment<'info>>,
+    name: String,
+) -> Result<PaymentReceipt> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let token = ctx.accounts.mint.as_ref().map(|m| m.key()).unwrap_or_default();
+    let link = ctx.accounts.payment_link.as_mut().ok_or(ErrorCode::MissingAccount)?;
+    require!(link.active, ErrorCode::LinkInactive);
+    require!(link.expires_at == 0 || now <= link.expires_at, ErrorCode::LinkExpired);
+    require!(!link.exhausted(), ErrorCode::LinkExhausted);
+    require!(token == link.mint, ErrorCode::InvalidToken);
+
+    let use_index = link.uses;
+    link.uses = use_index.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+    let amount = link.amount;
+
+    let id = PaymentId::Link { link: link.key(), use_index };
+    process_payment(ctx, amount, id, name, None, None, None, 0)
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DeactivateLink<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [PAYMENT_LINK_SEED, merchant.key().as_ref(), &link.link_seed.to_le_bytes()],
+        bump = link.bump
+    )]
+    pub link: Account<'info, PaymentLink>,
+
+    pub owner: Signer<'info>,
+}
+
+pub fn deactivate_link(ctx: Context<DeactivateLink>, _name: String) -> Result<()> {
+    let link = &mut ctx.accounts.link;
+    require!(link.active, ErrorCode::LinkInactive);
+
+    link.active = false;
+
+    emit!(PaymentLinkDeactivated {
+        merchant: link.merchant,
+        link: link.key(),
+        uses: link.uses,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
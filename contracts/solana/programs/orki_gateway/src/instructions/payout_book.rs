@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, PayoutAddress, PayoutBook, MAX_PAYOUT_ADDRESSES};
+use crate::events::{PayoutAddressAdded, PayoutAddressRemoved};
+use crate::errors::ErrorCode;
+use crate::constants::{MERCHANT_SEED, PAYOUT_ADDRESS_DELAY, PAYOUT_BOOK_SEED};
+use crate::clock;
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct AddPayoutAddress<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + PayoutBook::INIT_SPACE,
+        seeds = [PAYOUT_BOOK_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub payout_book: Account<'info, PayoutBook>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// New destinations only become usable after `PAYOUT_ADDRESS_DELAY`, so a
+/// stolen owner key can't redirect settlement in a single transaction.
+pub fn add_payout_address(
+    ctx: Context<AddPayoutAddress>,
+    _name: String,
+    address: Pubkey,
+    label: String,
+) -> Result<()> {
+    require!(label.len() <= 32, ErrorCode::LabelTooLong);
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    let book = &mut ctx.accounts.payout_book;
+    require!(book.find(&address).is_none(), ErrorCode::PayoutAddressExists);
+    require!(book.entries.len() < MAX_PAYOUT_ADDRESSES, ErrorCode::PayoutBookFull);
+
+    let activates_at = now.checked_add(PAYOUT_ADDRESS_DELAY).ok_or(ErrorCode::CalculationError)?;
+    book.merchant = ctx.accounts.merchant.key();
+    book.bump = ctx.bumps.payout_book;
+    book.entries.push(PayoutAddress {
+        address,
+        label: label.clone(),
+        activates_at,
+    });
+
+    // Emit event
+    emit!(PayoutAddressAdded {
+        merchant: ctx.accounts.merchant.key(),
+        address,
+        label,
+        activates_at,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RemovePayoutAddress<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [PAYOUT_BOOK_SEED, merchant.key().as_ref()],
+        bump = payout_book.bump
+    )]
+    pub payout_book: Account<'info, PayoutBook>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Removing an entry doesn't touch `settlement_wallet`; it only stops
+/// update_merchant from switching (back) to that address.
+pub fn remove_payout_address(
+    ctx: Context<RemovePayoutAddress>,
+    _name: String,
+    address: Pubkey,
+) -> Result<()> {
+    let book = &mut ctx.accounts.payout_book;
+    let index = book
+        .entries
+        .iter()
+        .position(|e| e.address == address)
+        .ok_or(ErrorCode::PayoutAddressNotListed)?;
+    book.entries.remove(index);
+
+    // Emit event
+    emit!(PayoutAddressRemoved {
+        merchant: ctx.accounts.merchant.key(),
+        address,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
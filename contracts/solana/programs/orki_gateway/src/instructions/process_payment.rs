@@ -1,207 +1,1098 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
-use crate::state::{GlobalState, Merchant, Payment};
-use crate::events::PaymentProcessed;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::associated_token::{self, AssociatedToken};
+use anchor_spl::memo::{self, BuildMemo, Memo};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::{self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{AcceptedTokens, Blocked, DailyVolume, Discount, EscrowBalance, Feature, FeatureFlags, FeeStats, FeeTiers, FeeVault, GlobalState, GlobalStats, Merchant, MerchantBalance, MerchantStats, Notification, NotificationQueue, PayerThrottle, Payment, PaymentId, PaymentLink, PaymentStatus, RecentPayment, RecentPayments, RewardsHook, SettlementRoute, SplitConfig, TokenFeeConfig, Vault};
+use crate::events::{emit_event, BalanceCredited, DiscountRedeemed, PaymentLinkPaid, PaymentProcessed, PaymentSplit};
+use crate::instructions::{invoke_rewards_hook, split_destinations, PaymentQuote};
 use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ACCEPTED_TOKENS_SEED, BLOCKED_SEED, DAILY_VOLUME_SEED, DISCOUNT_SEED, ESCROW_BALANCE_SEED, FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_TIERS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, MAX_MEMO_LENGTH, MERCHANT_BALANCE_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, NOTIFICATION_QUEUE_SEED, PAYER_THROTTLE_SEED, PAYMENT_LINK_SEED, PAYMENT_SEED, RECENT_PAYMENTS_SEED, REWARDS_HOOK_SEED, SETTLEMENT_ROUTE_SEED, SPLIT_CONFIG_SEED, TOKEN_FEE_SEED, VAULT_SEED};
 
+// Shared by process_payment, process_payment_v2 and pay_link, whose payment
+// ids differ in type, so no constraint here reads the instruction arguments
+#[event_cpi]
 #[derive(Accounts)]
-// We add 'name' here so we can use it in the seeds constraint for the merchant account
-#[instruction(amount: u64, payment_id: u64, name: String)] 
 pub struct ProcessPayment<'info> {
     #[account(
-        seeds = [b"global_state"],
-        bump = global_state.bump
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
     )]
     pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
     
     #[account(
         mut,
-        // The PDA is now derived using the owner and the specific shop name
-        seeds = [b"merchant", merchant.owner.as_ref(), name.as_bytes()],
-        bump = merchant.bump
+        // The handler checks the `name` argument against the stored name
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), merchant.name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
     )]
     pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
     
+    // A wallet, or a PDA whose program signs the CPI with invoke_signed, as
+    // orki_checkout's buyer PDA does. SOL payers must be system-owned and hold
+    // no data, since the system program moves their lamports.
     #[account(mut)]
     pub payer: Signer<'info>,
     
-    /// CHECK: Merchant wallet to receive funds (For SOL payment)
+    /// CHECK: Merchant wallet to receive funds (For SOL payment); the
+    /// payment token's settlement route destination when it has one
     #[account(mut)]
     pub merchant_wallet: AccountInfo<'info>,
     
-    /// CHECK: Fee wallet to receive fees (For SOL payment)
-    #[account(mut)]
-    pub fee_wallet: AccountInfo<'info>,
+    // Receives the fee: SOL directly, SPL through its token account for the mint
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, fee_stats.mint.as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
     
     pub system_program: Program<'info, System>,
 
+    /// CHECK: The payer's Payment record at `[b"payment", payer, id]`; the
+    /// handler derives it for the instruction's id type and opens it
+    #[account(mut)]
+    pub payment_history: UncheckedAccount<'info>,
+
+    /// CHECK: The payer's blocklist PDA, required so it can't be skipped;
+    /// normally empty, and payments fail while it holds an entry
     #[account(
-        init,
-        payer = payer,
-        space = 8 + Payment::INIT_SPACE, 
-        seeds = [b"payment", payer.key().as_ref(), &payment_id.to_le_bytes()],
+        seeds = [BLOCKED_SEED, payer.key().as_ref()],
+        bump
+    )]
+    pub blocklist_entry: UncheckedAccount<'info>,
+
+    /// CHECK: The payment token's fee config PDA (fee_stats is already tied
+    /// to the token), required so a configured rate can't be skipped; may
+    /// be empty, which leaves the global rate
+    #[account(
+        seeds = [TOKEN_FEE_SEED, fee_stats.mint.as_ref()],
         bump
     )]
-    pub payment_history: Account<'info, Payment>,
+    pub token_fee: UncheckedAccount<'info>,
 
-    // --- Optional Accounts for SPL ---
-    pub token_program: Option<Program<'info, Token>>,
-    pub mint: Option<Account<'info, Mint>>,
+    /// CHECK: The volume fee tiers PDA, required for the same reason; empty
+    /// until the admin sets tiers
+    #[account(
+        seeds = [FEE_TIERS_SEED],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    // --- Optional Accounts for SPL (classic Token or Token-2022) ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
     
     #[account(mut)]
-    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
-    #[account(mut)]
-    pub merchant_token_account: Option<Account<'info, TokenAccount>>,
+    // Settlement wallet's ATA for the mint; created on the merchant's first
+    // payment in it, with the payer covering rent
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = merchant_wallet,
+        associated_token::token_program = token_program,
+    )]
+    pub merchant_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
+    // Fee vault's ATA for the mint, created the same way; may be left out
+    // when no fee is due
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = fee_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Required with either token account above
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    // Required only for Token-2022 payments while that rollout is gated
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+
+    // --- Optional Accounts for escrow and Accrue-mode merchants ---
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, merchant.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Option<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        seeds = [ESCROW_BALANCE_SEED, merchant.key().as_ref(), escrow_balance.mint.as_ref()],
+        bump = escrow_balance.bump
+    )]
+    pub escrow_balance: Option<Account<'info, EscrowBalance>>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_BALANCE_SEED, merchant.key().as_ref(), merchant_balance.mint.as_ref()],
+        bump = merchant_balance.bump
+    )]
+    pub merchant_balance: Option<Account<'info, MerchantBalance>>,
+
+    #[account(mut)]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Merchants that opted into the recent payments cache
+    #[account(
+        mut,
+        seeds = [RECENT_PAYMENTS_SEED, merchant.key().as_ref()],
+        bump = recent_payments.bump
+    )]
+    pub recent_payments: Option<Account<'info, RecentPayments>>,
+
+    // Required for split merchants; recipient wallets or token accounts
+    // follow as remaining accounts in config order
+    #[account(
+        seeds = [SPLIT_CONFIG_SEED, merchant.key().as_ref()],
+        bump = split_config.bump
+    )]
+    pub split_config: Option<Account<'info, SplitConfig>>,
+
+    // Required for merchants with an accepted-token list
+    #[account(
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump = accepted_tokens.bump
+    )]
+    pub accepted_tokens: Option<Account<'info, AcceptedTokens>>,
+
+    // Required when a memo is given; echoes it on the transaction for explorers
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    // --- Optional Accounts for merchants with a referrer ---
+    /// CHECK: Must be merchant.referrer; receives its fee share (For SOL payment)
+    #[account(mut)]
+    pub referrer_wallet: Option<AccountInfo<'info>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // --- Optional Account when redeeming a discount code ---
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [DISCOUNT_SEED, merchant.key().as_ref(), discount.code_hash.as_ref()],
+        bump = discount.bump
+    )]
+    pub discount: Option<Account<'info, Discount>>,
+
+    // Merchants that opted into on-chain notifications. Boxed, as its
+    // 64 entries would not fit on the stack
+    #[account(
+        mut,
+        seeds = [NOTIFICATION_QUEUE_SEED, merchant.key().as_ref()],
+        bump = notification_queue.bump
+    )]
+    pub notification_queue: Option<Box<Account<'info, NotificationQueue>>>,
+
+    // Required for merchants with a daily limit
+    #[account(
+        mut,
+        seeds = [DAILY_VOLUME_SEED, merchant.key().as_ref()],
+        bump = daily_volume.bump
+    )]
+    pub daily_volume: Option<Box<Account<'info, DailyVolume>>>,
+
+    // Merchants with a rewards hook; process_payment calls the program last,
+    // passing on the remaining accounts after any split recipients
+    #[account(
+        seeds = [REWARDS_HOOK_SEED, merchant.key().as_ref()],
+        bump = rewards_hook.bump
+    )]
+    pub rewards_hook: Option<Box<Account<'info, RewardsHook>>>,
+
+    /// CHECK: Must be rewards_hook.program; skipped otherwise
+    pub rewards_program: Option<UncheckedAccount<'info>>,
+
+    // --- Optional Account for SOL paid through wrapped SOL ---
+    /// CHECK: The payer's wSOL ATA. Supplying it with the native mint routes
+    /// the payment through the SPL path in place of payer_token_account; the
+    /// handler creates it if missing and checks it once funded
     #[account(mut)]
-    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+    pub payer_wsol_account: Option<UncheckedAccount<'info>>,
+
+    // --- Optional Account for merchants throttling each payer ---
+    // Required when merchant.min_interval_per_payer is set; the payer's first
+    // payment to the merchant opens it at the payer's expense
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PayerThrottle::INIT_SPACE,
+        seeds = [PAYER_THROTTLE_SEED, merchant.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    pub payer_throttle: Option<Box<Account<'info, PayerThrottle>>>,
+
+    // --- Optional Account for merchants settling some tokens elsewhere ---
+    /// CHECK: The payment token's SettlementRoute PDA (fee_stats is already
+    /// tied to the token), required while the merchant has any route so it
+    /// can't be skipped; empty when this token has none
+    #[account(
+        seeds = [SETTLEMENT_ROUTE_SEED, merchant.key().as_ref(), fee_stats.mint.as_ref()],
+        bump
+    )]
+    pub settlement_route: Option<UncheckedAccount<'info>>,
+
+    // --- Optional Account for pay_link ---
+    // The link being paid; pay_link requires it and takes its next use
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [PAYMENT_LINK_SEED, merchant.key().as_ref(), &payment_link.link_seed.to_le_bytes()],
+        bump = payment_link.bump
+    )]
+    pub payment_link: Option<Box<Account<'info, PaymentLink>>>,
+}
+
+/// Rejects Token-2022 mints carrying a transfer fee: the merchant and fee
+/// wallet would receive less than the amounts recorded on the Payment.
+pub fn reject_transfer_fee_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let info = mint.to_account_info();
+    if *info.owner != anchor_spl::token_2022::ID {
+        return Ok(());
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<anchor_spl::token_2022::spl_token_2022::state::Mint>::unpack(&data)?;
+    require!(
+        state.get_extension::<TransferFeeConfig>().is_err(),
+        ErrorCode::TransferFeeMintNotSupported
+    );
+    Ok(())
+}
+
+/// Rejects a frozen destination up front, so the payment fails with
+/// DestinationFrozen rather than the token program's own error mid-transfer.
+pub fn require_unfrozen(token_account: &TokenAccount) -> Result<()> {
+    require!(!token_account.is_frozen(), ErrorCode::DestinationFrozen);
+    Ok(())
+}
+
+/// Checks a supplied token account against the payment mint and the wallet
+/// that should own it. Logs which account failed and both values before
+/// returning, since the error code alone doesn't say.
+fn check_token_account(
+    name: &str,
+    token_account: &InterfaceAccount<TokenAccount>,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    wrong_owner: ErrorCode,
+) -> Result<()> {
+    if token_account.mint != *mint {
+        msg!("{} {}: expected mint {}, got {}", name, token_account.key(), mint, token_account.mint);
+        return err!(ErrorCode::MintMismatch);
+    }
+    if token_account.owner != *owner {
+        msg!("{} {}: expected owner {}, got {}", name, token_account.key(), owner, token_account.owner);
+        return Err(error!(wrong_owner));
+    }
+    Ok(())
+}
+
+/// Creates a `space`-byte account at a handler-derived PDA as Anchor's
+/// `init` would, with the payer covering rent; used for Payment records and
+/// MerchantName claims. Lamports someone sent to the address beforehand
+/// count towards the rent rather than blocking the creation.
+pub(crate) fn open_program_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[seeds];
+    if account.lamports() == 0 {
+        anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount { from: payer.clone(), to: account.clone() },
+                signer,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+        return Ok(());
+    }
+
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer { from: payer.clone(), to: account.clone() },
+            ),
+            top_up,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate { account_to_allocate: account.clone() },
+            signer,
+        ),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Assign { account_to_assign: account.clone() },
+            signer,
+        ),
+        &crate::ID,
+    )
+}
+
+/// Funds the payer's wSOL account for a wrapped SOL payment: creates the ATA
+/// if it does not exist, then moves in whatever lamports its token balance
+/// is short of `total` and syncs it. Returns the token balance and whether
+/// the account was created here.
+fn wrap_sol<'info>(
+    payer_wsol: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    associated_token_program: Option<&AccountInfo<'info>>,
+    total: u64,
+) -> Result<(u64, bool)> {
+    let created = payer_wsol.data_is_empty();
+    if created {
+        let associated_token_program = associated_token_program.ok_or(ErrorCode::MissingAccount)?;
+        associated_token::create(CpiContext::new(
+            associated_token_program.clone(),
+            associated_token::Create {
+                payer: payer.clone(),
+                associated_token: payer_wsol.clone(),
+                authority: payer.clone(),
+                mint: mint.clone(),
+                system_program: system_program.clone(),
+                token_program: token_program.clone(),
+            },
+        ))?;
+    }
+
+    require!(payer_wsol.owner == token_program.key, ErrorCode::InvalidPayerTokenAccount);
+    let held = {
+        let data = payer_wsol.try_borrow_data()?;
+        let account = TokenAccount::try_deserialize(&mut &data[..])?;
+        if account.mint != spl_token::native_mint::ID {
+            msg!("payer_wsol_account {}: expected mint {}, got {}", payer_wsol.key(), spl_token::native_mint::ID, account.mint);
+            return err!(ErrorCode::MintMismatch);
+        }
+        if account.owner != payer.key() {
+            msg!("payer_wsol_account {}: expected owner {}, got {}", payer_wsol.key(), payer.key(), account.owner);
+            return err!(ErrorCode::InvalidPayerTokenAccount);
+        }
+        account.amount
+    };
+
+    let shortfall = total.saturating_sub(held);
+    if shortfall > 0 {
+        // As on the native path, the payer must stay rent-exempt
+        let required = shortfall
+            .checked_add(Rent::get()?.minimum_balance(0))
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(payer.lamports() >= required, ErrorCode::InsufficientBalance);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer { from: payer.clone(), to: payer_wsol.clone() },
+            ),
+            shortfall,
+        )?;
+        token_interface::sync_native(CpiContext::new(
+            token_program.clone(),
+            SyncNative { account: payer_wsol.clone() },
+        ))?;
+    }
+    Ok((held + shortfall, created))
+}
+
+/// What process_payment returns, as return data wallets read from a
+/// simulation and CPI callers from `Return::get`: the record's address, how
+/// the payment split, and the merchant's confirmation message, empty when
+/// it has none.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentReceipt {
+    pub payment_account: Pubkey,
+    pub fee: u64,
+    // Tip included, as credited to the merchant
+    pub merchant_amount: u64,
+    pub message: String,
 }
 
-pub fn process_payment(
-    ctx: Context<ProcessPayment>,
+/// process_payment_v2: the same payment, keyed by a 32-byte order id such as
+/// the SHA-256 of an order UUID (see [`Payment::hash_order_id`]).
+#[allow(clippy::too_many_arguments)]
+pub fn process_payment_v2<'info>(
+    ctx: Context<'_, '_, '_, 'info, ProcessPayment<'info>>,
     amount: u64,
-    payment_id: u64,
+    order_id: [u8; 32],
     name: String,
-) -> Result<()> {
+    memo: Option<String>,
+    tip: Option<u64>,
+    discount_code: Option<String>,
+    valid_until: i64,
+) -> Result<PaymentReceipt> {
+    // All zero is how u64-keyed records read back
+    require!(order_id != [0; 32], ErrorCode::InvalidOrderId);
+    process_payment(ctx, amount, PaymentId::Order(order_id), name, memo, tip, discount_code, valid_until)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_payment<'info>(
+    ctx: Context<'_, '_, '_, 'info, ProcessPayment<'info>>,
+    amount: u64,
+    id: PaymentId,
+    name: String,
+    memo: Option<String>,
+    tip: Option<u64>,
+    discount_code: Option<String>,
+    valid_until: i64,
+) -> Result<PaymentReceipt> {
     let state = &ctx.accounts.global_state;
     let merchant = &ctx.accounts.merchant;
+    let payment_id = id.sequence();
 
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    if name != merchant.name {
+        return Err(error!(anchor_lang::error::ErrorCode::ConstraintSeeds).with_account_name("merchant"));
+    }
+    let payer_key = ctx.accounts.payer.key();
+    let id_seed = id.seed();
+    let (record_address, record_bump) =
+        Pubkey::find_program_address(&[PAYMENT_SEED, payer_key.as_ref(), &id_seed], &crate::ID);
+    if ctx.accounts.payment_history.key() != record_address {
+        return Err(error!(anchor_lang::error::ErrorCode::ConstraintSeeds)
+            .with_account_name("payment_history")
+            .with_pubkeys((ctx.accounts.payment_history.key(), record_address)));
+    }
+    // A record already there is a reused id
+    require!(ctx.accounts.payment_history.data_is_empty(), ErrorCode::DuplicatePayment);
     require!(!state.paused, ErrorCode::Paused);
+    require!(merchant.active, ErrorCode::MerchantInactive);
+    require!(!merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    require!(!Blocked::is_blocked(&ctx.accounts.blocklist_entry), ErrorCode::AddressBlocked);
     require!(amount > 0, ErrorCode::InvalidAmount);
+    // A quote signed with a deadline must not land after it; 0 means no deadline
+    require!(valid_until == 0 || now <= valid_until, ErrorCode::PaymentExpired);
+
+    // A discount code lowers what is charged; the bounds and the fee then
+    // apply to the discounted amount
+    let pre_discount_amount = amount;
+    let (amount, discount_bps) = match discount_code {
+        Some(code) => {
+            let discount = ctx.accounts.discount.as_mut().ok_or(ErrorCode::MissingAccount)?;
+            require!(discount.code_hash == Discount::hash_code(&code), ErrorCode::DiscountCodeMismatch);
+            require!(discount.expires_at == 0 || now <= discount.expires_at, ErrorCode::DiscountExpired);
+            require!(!discount.exhausted(), ErrorCode::DiscountExhausted);
+            discount.redemptions = discount.redemptions.saturating_add(1);
+            (discount.apply(amount)?, discount.discount_bps)
+        }
+        None => (amount, 0),
+    };
+
+    merchant.check_amount(amount)?;
+
+    let memo = memo.unwrap_or_default();
+    require!(memo.len() <= MAX_MEMO_LENGTH as usize, ErrorCode::MemoTooLong);
+    if !memo.is_empty() {
+        let memo_program = ctx.accounts.memo_program.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        memo::build_memo(
+            CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+            memo.as_bytes(),
+        )?;
+    }
     
-    
-    // Calculate Fee
-    let fee = (amount as u128)
-        .checked_mul(state.fee_bps as u128)
-        .ok_or(ErrorCode::CalculationError)?
-        .checked_div(10000)
-        .ok_or(ErrorCode::CalculationError)? as u64;
-    
-    let merchant_amount = amount
-        .checked_sub(fee)
+    // Calculate Fee: the merchant's negotiated rate, else the token's, else
+    // the tier its volume so far has reached, else the global one;
+    // quote_payment returns this same split
+    let token_fee_bps = TokenFeeConfig::load_bps(&ctx.accounts.token_fee)?;
+    let tier_fee_bps = FeeTiers::load_bps(&ctx.accounts.fee_tiers, ctx.accounts.merchant_stats.total_volume)?;
+    let PaymentQuote { fee, merchant_amount, fee_bps_applied, fee_source } =
+        PaymentQuote::new(merchant, token_fee_bps, tier_fee_bps, state, amount)?;
+
+    // Tips skip the fee and go to the merchant with their share
+    let tip = tip.unwrap_or(0);
+    let merchant_amount = merchant_amount
+        .checked_add(tip)
+        .ok_or(ErrorCode::CalculationError)?;
+    let total = amount
+        .checked_add(tip)
         .ok_or(ErrorCode::CalculationError)?;
 
+    // The referrer's cut comes out of the fee, never the merchant's amount
+    let referrer_amount = merchant.referrer_share(fee)?;
+    // Recorded only; the tax stays in what the merchant receives. Tips are
+    // not taxed here
+    let tax_amount = merchant.tax_on(amount)?;
+    let protocol_fee = fee
+        .checked_sub(referrer_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    if merchant.min_interval_per_payer > 0 {
+        let throttle = ctx.accounts.payer_throttle.as_mut().ok_or(ErrorCode::MissingAccount)?;
+        throttle.check(now, merchant.min_interval_per_payer)?;
+        let bump = ctx.bumps.payer_throttle.ok_or(ErrorCode::MissingAccount)?;
+        throttle.record(merchant.key(), ctx.accounts.payer.key(), bump, now);
+    }
+
+    if merchant.daily_limit > 0 {
+        let daily_volume = ctx.accounts.daily_volume.as_mut().ok_or(ErrorCode::MissingAccount)?;
+        daily_volume.record(now, total, merchant.daily_limit)?;
+    }
+
+    // Escrow merchants need the vault and a balance opened for this token
+    let token = ctx.accounts.mint.as_ref().map(|m| m.key()).unwrap_or_default();
+    require!(ctx.accounts.fee_stats.mint == token, ErrorCode::InvalidToken);
+
+    // An accepted-token list replaces the single settlement_token rule
+    if merchant.accepted_tokens_enabled {
+        let accepted = ctx.accounts.accepted_tokens.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(accepted.accepts(&token), ErrorCode::InvalidToken);
+    } else {
+        merchant.check_token(&token)?;
+    }
+    if merchant.escrow_enabled {
+        let escrow_balance = ctx.accounts.escrow_balance.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(escrow_balance.mint == token, ErrorCode::InvalidToken);
+        require!(ctx.accounts.vault.is_some(), ErrorCode::MissingAccount);
+    }
+    // Accrue-mode merchants likewise need their balance for this token
+    if merchant.accrues() {
+        let balance = ctx.accounts.merchant_balance.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(balance.mint == token, ErrorCode::InvalidToken);
+        require!(ctx.accounts.vault.is_some(), ErrorCode::MissingAccount);
+    }
+
+    // Split merchants have the merchant share divided across their recipients
+    let split_shares = if merchant.split_enabled {
+        require!(!merchant.escrow_enabled, ErrorCode::SplitEscrowConflict);
+        let config = ctx.accounts.split_config.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        Some(config.shares(merchant_amount)?)
+    } else {
+        None
+    };
+
+    // A route for the payment token replaces the settlement wallet
+    let settlement_wallet = if merchant.settlement_routes > 0 {
+        let route = ctx.accounts.settlement_route.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        SettlementRoute::load_destination(route)?.unwrap_or(merchant.settlement_wallet)
+    } else {
+        merchant.settlement_wallet
+    };
+
+    // Validate merchant wallet; it is also the authority of the merchant's ATA
+    require!(
+        ctx.accounts.merchant_wallet.key() == settlement_wallet,
+        ErrorCode::InvalidMerchantWallet
+    );
+    // Sanity checks against misconfigured clients. The fee wallet as
+    // settlement wallet only makes sense for the gateway's own merchants.
+    require!(
+        settlement_wallet != state.fee_wallet || merchant.owner == state.admin,
+        ErrorCode::SettlementIsFeeWallet
+    );
+    require!(
+        settlement_wallet != ctx.accounts.fee_vault.key(),
+        ErrorCode::SettlementIsFeeWallet
+    );
+    require!(
+        ctx.accounts.payer.key() != settlement_wallet || merchant.allow_self_payments,
+        ErrorCode::SelfPayment
+    );
+
     // Check if using SPL tokens
-    if ctx.accounts.token_program.is_some() {
+    if let Some(token_program) = ctx.accounts.token_program.as_ref() {
         // --- SPL TOKEN PAYMENT ---
         
         // Get required accounts
-        let token_program = ctx.accounts.token_program.as_ref().unwrap();
         let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
-        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
-        let merchant_ta = ctx.accounts.merchant_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
-        let fee_ta = ctx.accounts.fee_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
-
-        // Validate mint matches merchant's settlement token
-        if merchant.settlement_token != Pubkey::default() {
+        // A wrapped SOL payment draws on the payer's wSOL account instead,
+        // which is checked as it is funded below
+        let payer_wsol = ctx.accounts.payer_wsol_account.as_ref();
+        if payer_wsol.is_some() {
             require!(
-                mint.key() == merchant.settlement_token,
+                mint.key() == spl_token::native_mint::ID && token_program.key() == spl_token::ID,
                 ErrorCode::InvalidToken
             );
         }
+        // With no fee due (an exempt merchant, a zero rate, or a referrer
+        // taking all of it) the fee token account may be left out
+        let fee_ta = if protocol_fee == 0 {
+            None
+        } else {
+            Some(ctx.accounts.fee_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?)
+        };
 
-        // Validate token accounts
-        require!(payer_ta.mint == mint.key(), ErrorCode::InvalidTokenAccount);
-        require!(merchant_ta.mint == mint.key(), ErrorCode::InvalidTokenAccount);
-        require!(fee_ta.mint == mint.key(), ErrorCode::InvalidTokenAccount);
-        require!(payer_ta.owner == ctx.accounts.payer.key(), ErrorCode::InvalidTokenAccount);
+        if token_program.key() == anchor_spl::token_2022::ID {
+            FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+            reject_transfer_fee_mint(mint)?;
+        }
+
+        // Validate token accounts; the merchant and fee accounts are ATAs,
+        // so their mint and owner are already pinned by the constraints,
+        // which report the account name and both keys themselves
+        let payer_ta = match payer_wsol {
+            Some(_) => None,
+            None => {
+                let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+                check_token_account(
+                    "payer_token_account",
+                    payer_ta,
+                    &mint.key(),
+                    &ctx.accounts.payer.key(),
+                    ErrorCode::InvalidPayerTokenAccount,
+                )?;
+                Some(payer_ta)
+            }
+        };
+        let referrer_ta = if referrer_amount > 0 {
+            let referrer_ta = ctx.accounts.referrer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            check_token_account(
+                "referrer_token_account",
+                referrer_ta,
+                &mint.key(),
+                &merchant.referrer,
+                ErrorCode::InvalidReferrer,
+            )?;
+            require_unfrozen(referrer_ta)?;
+            Some(referrer_ta)
+        } else {
+            None
+        };
+        // A closed merchant or fee ATA was recreated by init_if_needed above,
+        // but the issuer may have frozen either. Frozen settlement is not
+        // redirected into escrow: that would route around the issuer's freeze.
+        if let Some(fee_ta) = fee_ta {
+            require_unfrozen(fee_ta)?;
+        }
+
+        // Escrow and Accrue-mode merchants are paid into the vault's token account instead
+        let merchant_dest = if merchant.pays_into_vault() {
+            let vault_ta = ctx.accounts.vault_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            let vault = ctx.accounts.vault.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            check_token_account("vault_token_account", vault_ta, &mint.key(), &vault.key(), ErrorCode::OwnerMismatch)?;
+            require_unfrozen(vault_ta)?;
+            vault_ta.to_account_info()
+        } else {
+            let merchant_ta = ctx.accounts.merchant_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            require_unfrozen(merchant_ta)?;
+            merchant_ta.to_account_info()
+        };
+
+        let split_dests = match (&split_shares, ctx.accounts.split_config.as_ref()) {
+            (Some(_), Some(config)) => Some(split_destinations(
+                config,
+                ctx.remaining_accounts,
+                Some((token_program.key(), mint.key())),
+            )?),
+            _ => None,
+        };
+
+        // Wrapped SOL is moved into the payer's wSOL account first
+        let (payer_ta, payer_balance, wrapped_here) = match (payer_ta, payer_wsol) {
+            (Some(payer_ta), _) => (payer_ta.to_account_info(), payer_ta.amount, false),
+            (None, Some(payer_wsol)) => {
+                let (balance, created) = wrap_sol(
+                    payer_wsol,
+                    &ctx.accounts.payer.to_account_info(),
+                    &mint.to_account_info(),
+                    &token_program.to_account_info(),
+                    &ctx.accounts.system_program.to_account_info(),
+                    ctx.accounts.associated_token_program.as_ref().map(|p| p.as_ref()),
+                    total,
+                )?;
+                (payer_wsol.to_account_info(), balance, created)
+            }
+            (None, None) => return err!(ErrorCode::MissingAccount),
+        };
 
         // Check payer has enough balance
-        require!(payer_ta.amount >= amount, ErrorCode::InsufficientBalance);
+        require!(payer_balance >= total, ErrorCode::InsufficientBalance);
 
-        // Transfer Fee to Fee Vault
-        token::transfer(
-            CpiContext::new(
-                token_program.to_account_info(),
-                Transfer {
-                    from: payer_ta.to_account_info(),
-                    to: fee_ta.to_account_info(),
-                    authority: ctx.accounts.payer.to_account_info(),
-                },
-            ),
-            fee,
-        )?;
+        // Sandbox merchants run every check above but move no funds
+        if !merchant.sandbox {
+            // Transfer Fee to Fee Vault
+            if let Some(fee_ta) = fee_ta {
+                token_interface::transfer_checked(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        TransferChecked {
+                            from: payer_ta.clone(),
+                            mint: mint.to_account_info(),
+                            to: fee_ta.to_account_info(),
+                            authority: ctx.accounts.payer.to_account_info(),
+                        },
+                    ),
+                    protocol_fee,
+                    mint.decimals,
+                )?;
+            }
 
-        // Transfer Amount to Merchant
-        token::transfer(
-            CpiContext::new(
+            // Transfer the referrer's share of the fee
+            if let Some(referrer_ta) = referrer_ta {
+                token_interface::transfer_checked(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        TransferChecked {
+                            from: payer_ta.clone(),
+                            mint: mint.to_account_info(),
+                            to: referrer_ta.to_account_info(),
+                            authority: ctx.accounts.payer.to_account_info(),
+                        },
+                    ),
+                    referrer_amount,
+                    mint.decimals,
+                )?;
+            }
+
+            // Transfer Amount to Merchant, or to each split recipient
+            let payouts = match (&split_dests, &split_shares) {
+                (Some(dests), Some(shares)) => dests.iter().cloned().zip(shares.iter().copied()).collect(),
+                _ => vec![(merchant_dest, merchant_amount)],
+            };
+            for (to, share) in payouts {
+                token_interface::transfer_checked(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        TransferChecked {
+                            from: payer_ta.clone(),
+                            mint: mint.to_account_info(),
+                            to,
+                            authority: ctx.accounts.payer.to_account_info(),
+                        },
+                    ),
+                    share,
+                    mint.decimals,
+                )?;
+            }
+        }
+
+        // A wSOL account opened for this payment alone is closed again,
+        // returning its rent (and, for sandbox merchants, the wrapped SOL)
+        if wrapped_here {
+            token_interface::close_account(CpiContext::new(
                 token_program.to_account_info(),
-                Transfer {
-                    from: payer_ta.to_account_info(),
-                    to: merchant_ta.to_account_info(),
+                CloseAccount {
+                    account: payer_ta,
+                    destination: ctx.accounts.payer.to_account_info(),
                     authority: ctx.accounts.payer.to_account_info(),
                 },
-            ),
-            merchant_amount,
-        )?;
-
+            ))?;
+        }
     } else {
         // --- NATIVE SOL PAYMENT ---
         
-        // If merchant expects specific token but got SOL
-        if merchant.settlement_token != Pubkey::default() && !merchant.swap_enabled {
-            return Err(ErrorCode::InvalidToken.into());
+        // Check payer has enough SOL and stays rent-exempt afterwards. The
+        // transaction fee is already gone by the time this runs; the
+        // payment_history rent is taken once the transfers are done
+        let rent = Rent::get()?;
+        let required = total
+            .checked_add(rent.minimum_balance(0))
+            .and_then(|r| r.checked_add(rent.minimum_balance(8 + Payment::INIT_SPACE)))
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(ctx.accounts.payer.lamports() >= required, ErrorCode::InsufficientBalance);
+
+        let referrer_wallet = if referrer_amount > 0 {
+            let referrer_wallet = ctx.accounts.referrer_wallet.as_ref().ok_or(ErrorCode::MissingAccount)?;
+            require!(referrer_wallet.key() == merchant.referrer, ErrorCode::InvalidReferrer);
+            Some(referrer_wallet)
+        } else {
+            None
+        };
+
+        // Escrow and Accrue-mode merchants are paid into the vault instead
+        let merchant_dest = match ctx.accounts.vault.as_ref() {
+            Some(vault) if merchant.pays_into_vault() => vault.to_account_info(),
+            _ => ctx.accounts.merchant_wallet.to_account_info(),
+        };
+
+        let split_dests = match (&split_shares, ctx.accounts.split_config.as_ref()) {
+            (Some(_), Some(config)) => Some(split_destinations(config, ctx.remaining_accounts, None)?),
+            _ => None,
+        };
+
+        // Sandbox merchants run every check above but move no funds
+        if !merchant.sandbox {
+            // Transfer Fee, if any is due
+            if protocol_fee > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: ctx.accounts.fee_vault.to_account_info(),
+                        },
+                    ),
+                    protocol_fee,
+                )?;
+            }
+
+            // Transfer the referrer's share of the fee
+            if let Some(referrer_wallet) = referrer_wallet {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: referrer_wallet.to_account_info(),
+                        },
+                    ),
+                    referrer_amount,
+                )?;
+            }
+
+            // Transfer Merchant Amount, or each split recipient's share
+            let payouts = match (&split_dests, &split_shares) {
+                (Some(dests), Some(shares)) => dests.iter().cloned().zip(shares.iter().copied()).collect(),
+                _ => vec![(merchant_dest, merchant_amount)],
+            };
+            for (to, share) in payouts {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to,
+                        },
+                    ),
+                    share,
+                )?;
+            }
         }
+    }
 
-        // Validate merchant wallet
-        require!(
-            ctx.accounts.merchant_wallet.key() == merchant.settlement_wallet,
-            ErrorCode::InvalidMerchantWallet
-        );
+    // Protocol totals count only what reached the fee vault
+    if !merchant.sandbox {
+        ctx.accounts.global_stats.record_payment(amount, protocol_fee)?;
+        let fee_stats = &mut ctx.accounts.fee_stats;
+        fee_stats.total_fees_collected = fee_stats
+            .total_fees_collected
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
 
-        // Validate fee wallet
-        require!(
-            ctx.accounts.fee_wallet.key() == state.fee_wallet,
-            ErrorCode::InvalidFeeWallet
-        );
+    // Credit the escrowed share so the merchant can claim it later
+    let mut newly_funded = false;
+    if merchant.escrow_enabled && !merchant.sandbox {
+        if let Some(escrow_balance) = ctx.accounts.escrow_balance.as_mut() {
+            newly_funded = escrow_balance.amount == 0;
+            escrow_balance.amount = escrow_balance
+                .amount
+                .checked_add(merchant_amount)
+                .ok_or(ErrorCode::CalculationError)?;
+            escrow_balance.last_deposit_at = now;
+            // Claimable once finalize_payment confirms the payment stuck
+            escrow_balance.pending_amount = escrow_balance
+                .pending_amount
+                .checked_add(merchant_amount)
+                .ok_or(ErrorCode::CalculationError)?;
+        }
+    }
 
-        // Check payer has enough SOL
-        require!(ctx.accounts.payer.lamports() >= amount, ErrorCode::InsufficientBalance);
+    // Or to the merchant's accrued balance, withdrawn with withdraw_balance
+    let mut accrued_balance = None;
+    if merchant.accrues() && !merchant.sandbox {
+        if let Some(balance) = ctx.accounts.merchant_balance.as_mut() {
+            newly_funded = balance.amount == 0;
+            balance.amount = balance
+                .amount
+                .checked_add(merchant_amount)
+                .ok_or(ErrorCode::CalculationError)?;
+            accrued_balance = Some(balance.amount);
+        }
+    }
 
-        // Transfer Fee
-        anchor_lang::system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.payer.to_account_info(),
-                    to: ctx.accounts.fee_wallet.to_account_info(),
-                },
-            ),
-            fee,
-        )?;
+    ctx.accounts.merchant_stats.record_payment(amount, fee, now)?;
 
-        // Transfer Merchant Amount
-        anchor_lang::system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.payer.to_account_info(),
-                    to: ctx.accounts.merchant_wallet.to_account_info(),
-                },
-            ),
-            merchant_amount,
-        )?;
+    if let Some(recent) = ctx.accounts.recent_payments.as_mut() {
+        recent.push(RecentPayment {
+            payment_id,
+            amount,
+            mint: token,
+            timestamp: now,
+        });
+    }
+
+    if let Some(queue) = ctx.accounts.notification_queue.as_mut() {
+        queue.push(Notification {
+            payment_id,
+            amount,
+            timestamp: now,
+        });
     }
 
-    // Mark payment as processed
-    let payment = &mut ctx.accounts.payment_history;
-    payment.payer = ctx.accounts.payer.key();
-    payment.merchant = ctx.accounts.merchant.key();
-    payment.amount = amount;
-    payment.payment_id = payment_id;
-    payment.timestamp = Clock::get()?.unix_timestamp;
-    payment.bump = ctx.bumps.payment_history;
+    // Opened only now that every check has passed
+    open_program_account(
+        &ctx.accounts.payment_history,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &[PAYMENT_SEED, payer_key.as_ref(), &id_seed, &[record_bump]],
+        8 + Payment::INIT_SPACE,
+    )?;
 
-    emit!(PaymentProcessed {
+    // Mark payment as processed. Boxed to keep it off the handler's stack.
+    let payment = Box::new(Payment {
+        payer: payer_key,
+        merchant: merchant.key(),
+        amount,
+        payment_id,
+        timestamp: now,
+        bump: record_bump,
+        sandbox: merchant.sandbox,
+        refunded_amount: 0,
+        status: PaymentStatus::Completed,
+        mint: token,
+        fee,
+        memo: memo.clone(),
+        tip,
+        pre_discount_amount: if discount_bps > 0 { pre_discount_amount } else { 0 },
+        discount_bps,
+        receipt_mint: Pubkey::default(),
+        escrowed: merchant.escrow_enabled && !merchant.sandbox,
+        slot: Clock::get()?.slot,
+        order_id: id.order(),
+        tax_amount,
+        reserved: [],
+    });
+    payment.try_serialize(&mut &mut ctx.accounts.payment_history.try_borrow_mut_data()?[..])?;
+
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
+    emit_event!(ctx, PaymentProcessed {
         payer: ctx.accounts.payer.key(),
         merchant: merchant.key(),
         amount,
         fee,
-        token: ctx.accounts.mint.as_ref().map(|m| m.key()).unwrap_or(Pubkey::default()),
+        token,
         payment_id,
-        timestamp: Clock::get()?.unix_timestamp,
+        timestamp: now,
+        sandbox: merchant.sandbox,
+        memo,
+        referrer_amount,
+        tip,
+        fee_bps: fee_bps_applied,
+        fee_source,
+        valid_until,
+        event_seq,
+        merchant_name: merchant.name.clone(),
+        payment_account: ctx.accounts.payment_history.key(),
+        order_id: id.order(),
+        tax_amount,
     });
 
-    Ok(())
+    if let Some(discount) = ctx.accounts.discount.as_ref().filter(|_| discount_bps > 0) {
+        emit_event!(ctx, DiscountRedeemed {
+            payer: ctx.accounts.payer.key(),
+            merchant: merchant.key(),
+            discount: discount.key(),
+            payment_id,
+            pre_discount_amount,
+            amount,
+            discount_bps,
+            redemptions: discount.redemptions,
+            timestamp: now,
+        });
+    }
+
+    if let (Some(shares), Some(config)) = (split_shares, ctx.accounts.split_config.as_ref()) {
+        emit_event!(ctx, PaymentSplit {
+            payer: ctx.accounts.payer.key(),
+            merchant: merchant.key(),
+            payment_id,
+            token,
+            recipients: config.recipients.iter().map(|r| r.recipient).collect(),
+            amounts: shares,
+            timestamp: now,
+        });
+    }
+
+    if let Some(balance) = accrued_balance {
+        emit_event!(ctx, BalanceCredited {
+            merchant: merchant.key(),
+            mint: token,
+            payment_id,
+            amount: merchant_amount,
+            balance,
+            timestamp: now,
+        });
+    }
+
+    if let PaymentId::Link { link, use_index } = id {
+        emit_event!(ctx, PaymentLinkPaid {
+            merchant: merchant.key(),
+            link,
+            payer: ctx.accounts.payer.key(),
+            payment: ctx.accounts.payment_history.key(),
+            use_index,
+            amount,
+            fee,
+            mint: token,
+            timestamp: now,
+        });
+    }
+
+    if newly_funded {
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.funded_escrows = merchant.funded_escrows.saturating_add(1);
+    }
+
+    if ctx.accounts.merchant.rewards_enabled {
+        let split_recipients = ctx.accounts.split_config.as_ref().map_or(0, |c| c.recipients.len());
+        invoke_rewards_hook(
+            &ctx.accounts.merchant,
+            ctx.accounts.rewards_hook.as_deref(),
+            ctx.accounts.rewards_program.as_ref(),
+            &ctx.accounts.payer.to_account_info(),
+            ctx.remaining_accounts.get(split_recipients..).unwrap_or_default(),
+            amount,
+            payment_id,
+        )?;
+    }
+
+    Ok(PaymentReceipt {
+        payment_account: ctx.accounts.payment_history.key(),
+        fee,
+        merchant_amount,
+        message: ctx.accounts.merchant.confirmation_message.clone(),
+    })
 }
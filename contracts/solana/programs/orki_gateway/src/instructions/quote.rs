@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use crate::state::{FeeSource, FeeTiers, GlobalState, Merchant, MerchantStats, TokenFeeConfig};
+use crate::errors::ErrorCode;
+use crate::math::calc_fee;
+use crate::constants::{FEE_TIERS_SEED, GLOBAL_STATE_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, TOKEN_FEE_SEED};
+
+/// What a payment of `amount` to a merchant splits into right now.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentQuote {
+    pub fee: u64,
+    pub merchant_amount: u64,
+    pub fee_bps_applied: u16,
+    pub fee_source: FeeSource,
+}
+
+impl PaymentQuote {
+    /// The fee math process_payment charges with, rounded as `state` says.
+    /// `token_fee_bps` is the payment token's TokenFeeConfig rate and
+    /// `tier_fee_bps` the merchant's volume tier, if any.
+    pub fn new(
+        merchant: &Merchant,
+        token_fee_bps: Option<u16>,
+        tier_fee_bps: Option<u16>,
+        state: &GlobalState,
+        amount: u64,
+    ) -> Result<Self> {
+        let (fee_bps_applied, fee_source) = merchant.fee_rate(token_fee_bps, tier_fee_bps, state.fee_bps);
+        let (fee, merchant_amount) = calc_fee(amount, fee_bps_applied, state.fee_rounding)?;
+        Ok(Self { fee, merchant_amount, fee_bps_applied, fee_source })
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, name: String, owner: Pubkey)]
+pub struct QuotePayment<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [MERCHANT_SEED, owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    // The payment token's config, when it has one; omitting it quotes the
+    // merchant or global rate
+    #[account(
+        seeds = [TOKEN_FEE_SEED, token_fee.mint.as_ref()],
+        bump = token_fee.bump
+    )]
+    pub token_fee: Option<Account<'info, TokenFeeConfig>>,
+    #[account(
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+    // Required once the admin has set tiers, like token_fee
+    #[account(
+        seeds = [FEE_TIERS_SEED],
+        bump = fee_tiers.bump
+    )]
+    pub fee_tiers: Option<Account<'info, FeeTiers>>,
+}
+
+/// Read-only quote for simulateTransaction and CPI callers, so clients stop
+/// re-deriving the fee off-chain. Anchor hands the result back through
+/// return data. Amount limits are checked too; pause and merchant status are not.
+pub fn quote_payment(ctx: Context<QuotePayment>, amount: u64, _name: String, _owner: Pubkey) -> Result<PaymentQuote> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    ctx.accounts.merchant.check_amount(amount)?;
+    let token_fee_bps = ctx.accounts.token_fee.as_ref().and_then(|config| config.rate());
+    let tier_fee_bps = ctx.accounts.fee_tiers.as_ref()
+        .and_then(|tiers| tiers.fee_bps_for(ctx.accounts.merchant_stats.total_volume));
+    PaymentQuote::new(&ctx.accounts.merchant, token_fee_bps, tier_fee_bps, &ctx.accounts.global_state, amount)
+}
@@ -0,0 +1,255 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, CreateAccount};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token_2022::spl_token_2022::extension::ExtensionType;
+use anchor_spl::token_2022::spl_token_2022::instruction::AuthorityType;
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::spl_pod::optional_keys::OptionalNonZeroPubkey;
+use anchor_spl::token_interface::spl_token_metadata_interface::state::{Field, TokenMetadata};
+use anchor_spl::token_interface::{
+    self, InitializeMint2, MetadataPointerInitialize, MintTo, NonTransferableMintInitialize, SetAuthority,
+    Token2022, TokenMetadataInitialize, TokenMetadataUpdateAuthority, TokenMetadataUpdateField,
+};
+use crate::state::{Merchant, Payment};
+use crate::events::{emit_event, ReceiptMinted};
+use crate::errors::ErrorCode;
+use crate::instructions::migrate_account;
+use crate::clock;
+use crate::constants::{RECEIPT_AUTHORITY_SEED, RECEIPT_MINT_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MintReceipt<'info> {
+    // No seeds: plain, invoice and link payments all get receipts the same way
+    #[account(
+        mut,
+        has_one = payer @ ErrorCode::InvalidPayer,
+        has_one = merchant @ ErrorCode::InvalidPayment
+    )]
+    pub payment: Account<'info, Payment>,
+
+    #[account(
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: PDA that signs as mint and metadata authority; holds no data
+    #[account(
+        seeds = [RECEIPT_AUTHORITY_SEED],
+        bump
+    )]
+    pub receipt_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Created here; Anchor's mint constraints can't add NonTransferable
+    #[account(
+        mut,
+        seeds = [RECEIPT_MINT_SEED, payment.key().as_ref()],
+        bump
+    )]
+    pub receipt_mint: UncheckedAccount<'info>,
+
+    /// CHECK: The payer's Token-2022 associated account for the receipt, created here
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &payer.key(),
+            &receipt_mint.key(),
+            &token_program.key()
+        ) @ ErrorCode::InvalidTokenAccount
+    )]
+    pub payer_receipt_account: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Mints the payer a single non-transferable Token-2022 receipt for a
+/// finalized payment. The mint carries its own metadata (merchant, amount, token,
+/// payment_id, timestamp) with the merchant's metadata URI, and both the mint
+/// and update authorities are dropped afterwards so the receipt is final.
+/// One receipt per payment: the mint is a PDA of the payment record.
+pub fn mint_receipt(ctx: Context<MintReceipt>) -> Result<()> {
+    let payment = &ctx.accounts.payment;
+    require!(payment.status.is_final(), ErrorCode::PaymentNotFinalized);
+    require!(payment.receipt_mint == Pubkey::default(), ErrorCode::ReceiptAlreadyMinted);
+
+    // Records written before receipt_mint existed are too short to store it
+    migrate_account::<Payment>(
+        &ctx.accounts.payment.to_account_info(),
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let payment = &ctx.accounts.payment;
+    let payment_key = payment.key();
+    let mint_key = ctx.accounts.receipt_mint.key();
+    let authority_key = ctx.accounts.receipt_authority.key();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let mint_info = ctx.accounts.receipt_mint.to_account_info();
+    let authority_info = ctx.accounts.receipt_authority.to_account_info();
+
+    let metadata = TokenMetadata {
+        update_authority: OptionalNonZeroPubkey(authority_key),
+        mint: mint_key,
+        name: format!("{} receipt", ctx.accounts.merchant.name),
+        symbol: "ORKI".to_string(),
+        uri: ctx.accounts.merchant.metadata_uri.clone(),
+        additional_metadata: vec![
+            ("merchant".to_string(), payment.merchant.to_string()),
+            ("amount".to_string(), payment.amount.to_string()),
+            ("mint".to_string(), payment.mint.to_string()),
+            ("payment_id".to_string(), payment.payment_id.to_string()),
+            ("timestamp".to_string(), payment.timestamp.to_string()),
+        ],
+    };
+
+    // The metadata is appended by Token-2022 after initialization, so the
+    // account is sized for the extensions but funded for both
+    let space = ExtensionType::try_calculate_account_len::<MintState>(&[
+        ExtensionType::MetadataPointer,
+        ExtensionType::NonTransferable,
+    ])?;
+    let metadata_len = metadata.tlv_size_of()?;
+    let lamports = Rent::get()?.minimum_balance(space + metadata_len);
+
+    let mint_seeds: &[&[&[u8]]] = &[&[RECEIPT_MINT_SEED, payment_key.as_ref(), &[ctx.bumps.receipt_mint]]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            CreateAccount {
+                from: ctx.accounts.payer.to_account_info(),
+                to: mint_info.clone(),
+            },
+            mint_seeds,
+        ),
+        lamports,
+        space as u64,
+        &token_program.key(),
+    )?;
+
+    token_interface::metadata_pointer_initialize(
+        CpiContext::new(
+            token_program.clone(),
+            MetadataPointerInitialize {
+                token_program_id: token_program.clone(),
+                mint: mint_info.clone(),
+            },
+        ),
+        None,
+        Some(mint_key),
+    )?;
+    token_interface::non_transferable_mint_initialize(CpiContext::new(
+        token_program.clone(),
+        NonTransferableMintInitialize {
+            token_program_id: token_program.clone(),
+            mint: mint_info.clone(),
+        },
+    ))?;
+    token_interface::initialize_mint2(
+        CpiContext::new(token_program.clone(), InitializeMint2 { mint: mint_info.clone() }),
+        0,
+        &authority_key,
+        None,
+    )?;
+
+    let authority_seeds: &[&[&[u8]]] = &[&[RECEIPT_AUTHORITY_SEED, &[ctx.bumps.receipt_authority]]];
+    token_interface::token_metadata_initialize(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TokenMetadataInitialize {
+                program_id: token_program.clone(),
+                metadata: mint_info.clone(),
+                update_authority: authority_info.clone(),
+                mint_authority: authority_info.clone(),
+                mint: mint_info.clone(),
+            },
+            authority_seeds,
+        ),
+        metadata.name.clone(),
+        metadata.symbol.clone(),
+        metadata.uri.clone(),
+    )?;
+    for (key, value) in &metadata.additional_metadata {
+        token_interface::token_metadata_update_field(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                TokenMetadataUpdateField {
+                    program_id: token_program.clone(),
+                    metadata: mint_info.clone(),
+                    update_authority: authority_info.clone(),
+                },
+                authority_seeds,
+            ),
+            Field::Key(key.clone()),
+            value.clone(),
+        )?;
+    }
+    token_interface::token_metadata_update_authority(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TokenMetadataUpdateAuthority {
+                program_id: token_program.clone(),
+                metadata: mint_info.clone(),
+                current_authority: authority_info.clone(),
+                new_authority: authority_info.clone(),
+            },
+            authority_seeds,
+        ),
+        OptionalNonZeroPubkey::default(),
+    )?;
+
+    associated_token::create(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        associated_token::Create {
+            payer: ctx.accounts.payer.to_account_info(),
+            associated_token: ctx.accounts.payer_receipt_account.to_account_info(),
+            authority: ctx.accounts.payer.to_account_info(),
+            mint: mint_info.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: token_program.clone(),
+        },
+    ))?;
+    token_interface::mint_to(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            MintTo {
+                mint: mint_info.clone(),
+                to: ctx.accounts.payer_receipt_account.to_account_info(),
+                authority: authority_info.clone(),
+            },
+            authority_seeds,
+        ),
+        1,
+    )?;
+    token_interface::set_authority(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            SetAuthority {
+                current_authority: authority_info,
+                account_or_mint: mint_info,
+            },
+            authority_seeds,
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )?;
+
+    ctx.accounts.payment.receipt_mint = mint_key;
+    let payment = &ctx.accounts.payment;
+
+    emit_event!(ctx, ReceiptMinted {
+        payment: payment_key,
+        payer: payment.payer,
+        merchant: payment.merchant,
+        receipt_mint: mint_key,
+        payment_id: payment.payment_id,
+        amount: payment.amount,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, RecentPayments};
+use crate::errors::ErrorCode;
+use crate::constants::{MERCHANT_SEED, RECENT_PAYMENTS_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct InitRecentPayments<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + RecentPayments::INIT_SPACE,
+        seeds = [RECENT_PAYMENTS_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub recent_payments: Account<'info, RecentPayments>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opts a merchant into the on-chain recent payments cache. Once created,
+/// process_payment records into it whenever the account is passed.
+pub fn init_recent_payments(ctx: Context<InitRecentPayments>, _name: String) -> Result<()> {
+    let recent = &mut ctx.accounts.recent_payments;
+    recent.merchant = ctx.accounts.merchant.key();
+    recent.bump = ctx.bumps.recent_payments;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{GlobalStats, Merchant, MerchantStats, Operator, OperatorPermission, Payment};
+use crate::events::{emit_event, PaymentRefunded};
+use crate::errors::ErrorCode;
+use crate::constants::{GLOBAL_STATS_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, OPERATOR_SEED, PAYMENT_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RefundPartial<'info> {
+    #[account(
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payment.payer.as_ref(), &payment.id().seed()],
+        bump = payment.bump,
+        constraint = payment.merchant == merchant.key()
+            || (merchant.previous_merchant != Pubkey::default() && payment.merchant == merchant.previous_merchant)
+            @ ErrorCode::InvalidPayment
+    )]
+    pub payment: Account<'info, Payment>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    // The merchant owner, or an operator with Refund; funds the refund
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, merchant.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    /// CHECK: Original payer receiving the refund (For SOL refund)
+    #[account(
+        mut,
+        constraint = payer.key() == payment.payer @ ErrorCode::InvalidPayer
+    )]
+    pub payer: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    // --- Optional Accounts for SPL (classic Token or Token-2022) ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+pub fn refund_partial(ctx: Context<RefundPartial>, _name: String, amount: u64) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidRefundAmount);
+    Operator::authorize(
+        &ctx.accounts.merchant,
+        &ctx.accounts.authority.key(),
+        ctx.accounts.operator.as_deref(),
+        OperatorPermission::Refund,
+    )?;
+
+    let payment = &ctx.accounts.payment;
+    // Held or voided authorizations never reached the merchant
+    require!(
+        payment.status.is_settled(),
+        ErrorCode::InvalidPaymentStatus
+    );
+    let refunded_amount = payment
+        .refunded_amount
+        .checked_add(amount)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(refunded_amount <= payment.amount, ErrorCode::RefundExceedsPayment);
+
+    if let Some(token_program) = ctx.accounts.token_program.as_ref() {
+        // --- SPL TOKEN REFUND ---
+        let mint = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        // Refunds go back in the token that was paid
+        require!(mint.key() == payment.mint, ErrorCode::InvalidToken);
+        let authority_ta = ctx.accounts.authority_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        require!(authority_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(payer_ta.mint == mint.key(), ErrorCode::MintMismatch);
+        require!(authority_ta.owner == ctx.accounts.authority.key(), ErrorCode::OwnerMismatch);
+        require!(payer_ta.owner == payment.payer, ErrorCode::InvalidPayerTokenAccount);
+        require!(authority_ta.amount >= amount, ErrorCode::InsufficientBalance);
+
+        // Sandbox payments never moved funds, so neither do their refunds
+        if !payment.sandbox {
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: authority_ta.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: payer_ta.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
+    } else {
+        // --- NATIVE SOL REFUND ---
+        require!(payment.mint == Pubkey::default(), ErrorCode::InvalidToken);
+        require!(ctx.accounts.authority.lamports() >= amount, ErrorCode::InsufficientBalance);
+
+        if !payment.sandbox {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+    }
+
+    let payment = &mut ctx.accounts.payment;
+    payment.refunded_amount = refunded_amount;
+    ctx.accounts.merchant_stats.record_refund(amount)?;
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
+
+    emit_event!(ctx, PaymentRefunded {
+        payer: payment.payer,
+        merchant: payment.merchant,
+        payment_id: payment.payment_id,
+        amount,
+        refunded_amount,
+        timestamp: Clock::get()?.unix_timestamp,
+        event_seq,
+        merchant_name: ctx.accounts.merchant.name.clone(),
+        payment_account: payment.key(),
+    });
+
+    Ok(())
+}
@@ -1,33 +1,137 @@
 use anchor_lang::prelude::*;
-use crate::state::Merchant;
+use crate::state::{GlobalStats, Merchant, MerchantIndex, MerchantName, MerchantRegistry, MerchantStats, SettlementMode};
 use crate::errors::ErrorCode;
-use crate::events::MerchantRegistered; // ADD THIS LINE
+use crate::constants::{GLOBAL_STATS_SEED, MAX_METADATA_URI_LENGTH, MAX_NAME_LENGTH, MAX_REGISTRATION_REFERRER_BPS, MERCHANT_INDEX_SEED, MERCHANT_NAME_SEED, MERCHANT_REGISTRY_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED};
+use crate::events::{emit_event, MerchantRegistered};
+use crate::instructions::process_payment::open_program_account;
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(settlement_wallet: Pubkey, settlement_token: Pubkey, name: String)]
+#[instruction(settlement_wallet: Pubkey, settlement_token: Pubkey, name: String, sandbox: bool)]
 pub struct RegisterMerchant<'info> {
+    // init_if_needed so a retried registration reaches the handler, which
+    // accepts an identical one and rejects anything else
     #[account(
-        init,
+        init_if_needed,
         payer = owner,
         space = 8 + Merchant::INIT_SPACE,
         // Added 'name' to the seeds to allow multiple profiles
-        seeds = [b"merchant", owner.key().as_ref(), name.as_bytes()], 
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()], 
         bump
     )]
     pub merchant: Account<'info, Merchant>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + MerchantStats::INIT_SPACE,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + MerchantRegistry::INIT_SPACE,
+        seeds = [MERCHANT_REGISTRY_SEED],
+        bump
+    )]
+    pub merchant_registry: Account<'info, MerchantRegistry>,
+    // Next free directory slot; clients derive it from merchant_count + 1
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + MerchantIndex::INIT_SPACE,
+        seeds = [MERCHANT_INDEX_SEED, &(merchant_registry.merchant_count + 1).to_le_bytes()],
+        bump
+    )]
+    pub merchant_index: Account<'info, MerchantIndex>,
+    /// CHECK: The owner's MerchantName claim at `[b"merchant_name", owner,
+    /// lowercased name]`; the handler derives and opens it
+    #[account(mut)]
+    pub merchant_name: UncheckedAccount<'info>,
     #[account(mut)]
     pub owner: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+/// Opens `claim` as `owner`'s MerchantName for `name`, held by `merchant`,
+/// with `payer` covering rent. An open claim means another of the owner's
+/// merchants already goes by the name in some casing.
+pub(crate) fn claim_merchant_name<'info>(
+    claim: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    owner: Pubkey,
+    merchant: Pubkey,
+    name: &str,
+) -> Result<()> {
+    let normalized_name = MerchantName::normalize(name);
+    require!(normalized_name.len() <= MAX_NAME_LENGTH as usize, ErrorCode::NameTooLong);
+    let (address, bump) = Pubkey::find_program_address(
+        &[MERCHANT_NAME_SEED, owner.as_ref(), normalized_name.as_bytes()],
+        &crate::ID,
+    );
+    if claim.key() != address {
+        return Err(error!(anchor_lang::error::ErrorCode::ConstraintSeeds)
+            .with_account_name("merchant_name")
+            .with_pubkeys((claim.key(), address)));
+    }
+    require!(claim.data_is_empty(), ErrorCode::NameAlreadyUsed);
+    open_program_account(
+        claim,
+        payer,
+        system_program,
+        &[MERCHANT_NAME_SEED, owner.as_ref(), normalized_name.as_bytes(), &[bump]],
+        8 + MerchantName::INIT_SPACE,
+    )?;
+    MerchantName { owner, merchant, normalized_name, bump, reserved: [0; 32] }
+        .try_serialize(&mut &mut claim.try_borrow_mut_data()?[..])
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn register_merchant(
     ctx: Context<RegisterMerchant>,
     settlement_wallet: Pubkey,
     settlement_token: Pubkey,
     name: String,
+    sandbox: bool,
+    referrer: Option<Pubkey>,
+    referrer_bps: u16,
+    accepts_any_token: bool,
+    metadata_uri: String,
+    category: u8,
 ) -> Result<()> {
-    require!(name.len() <= 32, ErrorCode::NameTooLong);
-    
+    Merchant::check_name(&name)?;
+    require!(metadata_uri.len() <= MAX_METADATA_URI_LENGTH as usize, ErrorCode::MetadataUriTooLong);
+    // A share without a referrer would never be paid out; reject it early
+    require!(referrer.is_some() || referrer_bps == 0, ErrorCode::InvalidReferrer);
+    require!(referrer_bps <= MAX_REGISTRATION_REFERRER_BPS, ErrorCode::ReferrerShareTooHigh);
+
+    let registry = &mut ctx.accounts.merchant_registry;
+    registry.bump = ctx.bumps.merchant_registry;
+
+    if ctx.accounts.merchant.is_registered() {
+        let merchant = &ctx.accounts.merchant;
+        let same = merchant.settlement_wallet == settlement_wallet
+            && merchant.settlement_token == settlement_token
+            && merchant.sandbox == sandbox
+            && merchant.referrer == referrer.unwrap_or_default()
+            && merchant.referrer_bps == referrer_bps
+            && merchant.accepts_any_token == accepts_any_token
+            && merchant.metadata_uri == metadata_uri
+            && merchant.category == category;
+        require!(same, ErrorCode::MerchantAlreadyExists);
+        // A retry of this registration: the merchant keeps its slot, so hand
+        // back the one init_if_needed just opened past the end of the directory
+        return ctx.accounts.merchant_index.close(ctx.accounts.owner.to_account_info());
+    }
+
     let merchant = &mut ctx.accounts.merchant;
     merchant.owner = ctx.accounts.owner.key();
     merchant.settlement_wallet = settlement_wallet;
@@ -35,15 +139,64 @@ pub fn register_merchant(
     merchant.swap_enabled = false; 
     merchant.name = name.clone(); // Use clone for event
     merchant.bump = ctx.bumps.merchant;
+    // Sandbox can only be chosen here; it is never toggled on a live merchant
+    merchant.sandbox = sandbox;
+    merchant.fee_bps_override = None;
+    merchant.escrow_enabled = false;
+    merchant.active = true;
+    merchant.admin_locked = false;
+    merchant.previous_merchant = Pubkey::default();
+    merchant.open_invoices = 0;
+    merchant.funded_escrows = 0;
+    merchant.split_enabled = false;
+    merchant.referrer = referrer.unwrap_or_default();
+    merchant.referrer_bps = referrer_bps;
+    merchant.accepts_any_token = accepts_any_token;
+    merchant.token_rule_set = true;
+    merchant.category = category;
+    merchant.metadata_uri = metadata_uri.clone();
+    merchant.settlement_mode = SettlementMode::Instant;
+    merchant.version = Merchant::VERSION;
+    merchant.name_indexed = true;
+    claim_merchant_name(
+        &ctx.accounts.merchant_name,
+        &ctx.accounts.owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        merchant.owner,
+        merchant.key(),
+        &name,
+    )?;
+
+    merchant.merchant_index = ctx.accounts.merchant_registry.next_index()?;
+    ctx.accounts.merchant_index.set_inner(MerchantIndex {
+        index: merchant.merchant_index,
+        merchant: merchant.key(),
+        bump: ctx.bumps.merchant_index,
+        reserved: [0; 32],
+    });
+
+    let stats = &mut ctx.accounts.merchant_stats;
+    stats.merchant = merchant.key();
+    stats.bump = ctx.bumps.merchant_stats;
+    ctx.accounts.global_stats.record_merchant()?;
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
     
     // Emit event
-    emit!(MerchantRegistered {
+    emit_event!(ctx, MerchantRegistered {
         owner: ctx.accounts.owner.key(),
         merchant: merchant.key(),
         settlement_wallet,
         settlement_token,
         name,
         timestamp: Clock::get()?.unix_timestamp,
+        sandbox,
+        referrer,
+        referrer_bps,
+        accepts_any_token,
+        metadata_uri,
+        category,
+        merchant_index: merchant.merchant_index,
+        event_seq,
     });
     
     Ok(())
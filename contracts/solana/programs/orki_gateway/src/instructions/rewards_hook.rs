@@ -0,0 +1,165 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use crate::state::{Merchant, RewardsHook};
+use crate::events::RewardsHookUpdated;
+use crate::errors::ErrorCode;
+use crate::constants::{MERCHANT_SEED, REWARDS_HOOK_SEED};
+
+/// Anchor discriminator of `on_payment`, the instruction a rewards program
+/// implements: sha256("global:on_payment")[..8]. Its arguments follow as
+/// `amount: u64, payment_id: u64`.
+pub const ON_PAYMENT_DISCRIMINATOR: [u8; 8] = [109, 220, 129, 184, 2, 252, 9, 79];
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetRewardsHook<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + RewardsHook::INIT_SPACE,
+        seeds = [REWARDS_HOOK_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub rewards_hook: Account<'info, RewardsHook>,
+
+    /// CHECK: Any deployed program other than the gateway; it only ever
+    /// receives the on_payment call
+    #[account(
+        executable,
+        constraint = rewards_program.key() != crate::ID @ ErrorCode::InvalidRewardsProgram
+    )]
+    pub rewards_program: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Attaches a rewards program to the merchant, or replaces the one attached.
+/// With `required`, payments that don't supply the program fail instead of
+/// skipping the call.
+pub fn set_rewards_hook(ctx: Context<SetRewardsHook>, _name: String, required: bool) -> Result<()> {
+    let rewards_hook = &mut ctx.accounts.rewards_hook;
+    rewards_hook.merchant = ctx.accounts.merchant.key();
+    rewards_hook.program = ctx.accounts.rewards_program.key();
+    rewards_hook.bump = ctx.bumps.rewards_hook;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.rewards_enabled = true;
+    merchant.rewards_required = required;
+
+    // Emit event
+    emit!(RewardsHookUpdated {
+        merchant: merchant.key(),
+        program: Some(rewards_hook.program),
+        required,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClearRewardsHook<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [REWARDS_HOOK_SEED, merchant.key().as_ref()],
+        bump = rewards_hook.bump,
+        close = owner
+    )]
+    pub rewards_hook: Account<'info, RewardsHook>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+pub fn clear_rewards_hook(ctx: Context<ClearRewardsHook>, _name: String) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.rewards_enabled = false;
+    merchant.rewards_required = false;
+
+    // Emit event
+    emit!(RewardsHookUpdated {
+        merchant: merchant.key(),
+        program: None,
+        required: false,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Calls the merchant's rewards program with `on_payment(amount, payment_id)`.
+///
+/// The accounts are the hook, signing so the program can tell the call came
+/// from the gateway, then the payer and merchant, then `accounts` as passed
+/// to process_payment. None of them is passed as a signer: the payer's
+/// signature never reaches a program the merchant picked.
+///
+/// A failing call fails the payment with it, since Solana can't catch a CPI
+/// error. What the gateway can tolerate is a call it can't make: unless the
+/// merchant set `rewards_required`, a payment without the hook or program
+/// accounts logs that and skips the call.
+pub fn invoke_rewards_hook<'info>(
+    merchant: &Account<'info, Merchant>,
+    rewards_hook: Option<&Account<'info, RewardsHook>>,
+    rewards_program: Option<&UncheckedAccount<'info>>,
+    payer: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    amount: u64,
+    payment_id: u64,
+) -> Result<()> {
+    let hook = rewards_hook.zip(rewards_program).filter(|(hook, program)| program.key() == hook.program);
+    let Some((hook, program)) = hook else {
+        require!(!merchant.rewards_required, ErrorCode::RewardsProgramMissing);
+        msg!("Rewards hook skipped: hook or program account not supplied");
+        return Ok(());
+    };
+
+    let mut metas = vec![
+        AccountMeta::new_readonly(hook.key(), true),
+        AccountMeta::new_readonly(payer.key(), false),
+        AccountMeta::new_readonly(merchant.key(), false),
+    ];
+    metas.extend(accounts.iter().map(|a| AccountMeta {
+        pubkey: a.key(),
+        is_signer: false,
+        is_writable: a.is_writable,
+    }));
+    let mut data = ON_PAYMENT_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&payment_id.to_le_bytes());
+
+    let mut infos = vec![hook.to_account_info(), payer.clone(), merchant.to_account_info()];
+    infos.extend_from_slice(accounts);
+    infos.push(program.to_account_info());
+
+    let merchant_key = merchant.key();
+    let seeds: &[&[u8]] = &[REWARDS_HOOK_SEED, merchant_key.as_ref(), &[hook.bump]];
+    invoke_signed(
+        &Instruction { program_id: program.key(), accounts: metas, data },
+        &infos,
+        &[seeds],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use crate::state::{Merchant, PayoutBook, SettlementRoute};
+use crate::events::{emit_event, SettlementRouteUpdated};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{MERCHANT_SEED, PAYOUT_BOOK_SEED, SETTLEMENT_ROUTE_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, mint: Pubkey)]
+pub struct SetSettlementRoute<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SettlementRoute::INIT_SPACE,
+        seeds = [SETTLEMENT_ROUTE_SEED, merchant.key().as_ref(), mint.as_ref()],
+        bump
+    )]
+    pub settlement_route: Account<'info, SettlementRoute>,
+
+    // Required unless the destination is the settlement wallet
+    #[account(
+        seeds = [PAYOUT_BOOK_SEED, merchant.key().as_ref()],
+        bump = payout_book.bump
+    )]
+    pub payout_book: Option<Account<'info, PayoutBook>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Settles the merchant's payments in `mint` (Pubkey::default() for SOL) to
+/// `destination` instead of its settlement wallet, or points an existing
+/// route elsewhere. As with update_merchant, the destination must be an
+/// active payout book entry, so a stolen owner key can't redirect one token
+/// any faster than all of them.
+pub fn set_settlement_route(
+    ctx: Context<SetSettlementRoute>,
+    _name: String,
+    mint: Pubkey,
+    destination: Pubkey,
+) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let merchant = &mut ctx.accounts.merchant;
+    if destination != merchant.settlement_wallet {
+        PayoutBook::require_active(ctx.accounts.payout_book.as_deref(), &destination, now)?;
+    }
+
+    let route = &mut ctx.accounts.settlement_route;
+    if route.merchant == Pubkey::default() {
+        merchant.settlement_routes = merchant.settlement_routes.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+    }
+    route.merchant = merchant.key();
+    route.mint = mint;
+    route.destination = destination;
+    route.bump = ctx.bumps.settlement_route;
+
+    emit_event!(ctx, SettlementRouteUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        mint,
+        destination: Some(destination),
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, mint: Pubkey)]
+pub struct ClearSettlementRoute<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [SETTLEMENT_ROUTE_SEED, merchant.key().as_ref(), mint.as_ref()],
+        bump = settlement_route.bump,
+        close = owner
+    )]
+    pub settlement_route: Account<'info, SettlementRoute>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+/// Sends the merchant's payments in `mint` back to its settlement wallet,
+/// refunding the route's rent to `owner`.
+pub fn clear_settlement_route(ctx: Context<ClearSettlementRoute>, _name: String, mint: Pubkey) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.settlement_routes = merchant.settlement_routes.saturating_sub(1);
+
+    emit_event!(ctx, SettlementRouteUpdated {
+        merchant: merchant.key(),
+        mint,
+        destination: None,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
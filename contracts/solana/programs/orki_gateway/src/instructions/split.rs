@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+use crate::state::{Merchant, SplitConfig, SplitRecipient};
+use crate::events::SplitConfigUpdated;
+use crate::errors::ErrorCode;
+use crate::instructions::require_unfrozen;
+use crate::constants::{MERCHANT_SEED, SPLIT_CONFIG_SEED};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetSplitConfig<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SplitConfig::INIT_SPACE,
+        seeds = [SPLIT_CONFIG_SEED, merchant.key().as_ref()],
+        bump
+    )]
+    pub split_config: Account<'info, SplitConfig>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_split_config(
+    ctx: Context<SetSplitConfig>,
+    _name: String,
+    recipients: Vec<SplitRecipient>,
+) -> Result<()> {
+    SplitConfig::validate(&recipients)?;
+    // Escrow holds the whole merchant share in one vault, so it can't be split
+    require!(!ctx.accounts.merchant.escrow_enabled, ErrorCode::SplitEscrowConflict);
+    require!(!ctx.accounts.merchant.accrues(), ErrorCode::SettlementModeConflict);
+
+    let split_config = &mut ctx.accounts.split_config;
+    split_config.merchant = ctx.accounts.merchant.key();
+    split_config.recipients = recipients.clone();
+    split_config.bump = ctx.bumps.split_config;
+
+    // process_payment refuses to pay a split merchant without its config
+    ctx.accounts.merchant.split_enabled = true;
+
+    // Emit event
+    emit!(SplitConfigUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        recipients,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClearSplitConfig<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [SPLIT_CONFIG_SEED, merchant.key().as_ref()],
+        bump = split_config.bump,
+        close = owner
+    )]
+    pub split_config: Account<'info, SplitConfig>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+pub fn clear_split_config(ctx: Context<ClearSplitConfig>, _name: String) -> Result<()> {
+    ctx.accounts.merchant.split_enabled = false;
+
+    // Emit event
+    emit!(SplitConfigUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        recipients: vec![],
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Matches the leading remaining accounts to the split recipients, in config
+/// order. SOL splits expect the recipient wallets themselves; SPL splits expect
+/// token accounts of `mint` owned by each recipient.
+pub fn split_destinations<'info>(
+    config: &SplitConfig,
+    remaining_accounts: &[AccountInfo<'info>],
+    token: Option<(Pubkey, Pubkey)>,
+) -> Result<Vec<AccountInfo<'info>>> {
+    require!(
+        remaining_accounts.len() >= config.recipients.len(),
+        ErrorCode::MissingAccount
+    );
+
+    let mut destinations = Vec::with_capacity(config.recipients.len());
+    for (recipient, info) in config.recipients.iter().zip(remaining_accounts) {
+        match token {
+            Some((token_program, mint)) => {
+                // A closed account is back with the system program, empty
+                require!(info.lamports() > 0 && !info.data_is_empty(), ErrorCode::MissingAccount);
+                require!(info.owner == &token_program, ErrorCode::InvalidTokenAccount);
+                let data = info.try_borrow_data()?;
+                let token_account = TokenAccount::try_deserialize(&mut &data[..])?;
+                require!(token_account.mint == mint, ErrorCode::MintMismatch);
+                require!(token_account.owner == recipient.recipient, ErrorCode::OwnerMismatch);
+                require_unfrozen(&token_account)?;
+            }
+            None => {
+                require!(info.key() == recipient.recipient, ErrorCode::InvalidSplit);
+                require!(info.is_writable, ErrorCode::InvalidSplit);
+            }
+        }
+        destinations.push(info.clone());
+    }
+    Ok(destinations)
+}
@@ -0,0 +1,527 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{self, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{AcceptedTokens, Feature, FeatureFlags, FeeStats, FeeVault, GlobalState, Merchant, PaymentStream};
+use crate::events::{emit_event, StreamCancelled, StreamCreated, StreamWithdrawn};
+use crate::errors::ErrorCode;
+use crate::math::calc_fee;
+use crate::instructions::reject_transfer_fee_mint;
+use crate::clock;
+use crate::constants::{ACCEPTED_TOKENS_SEED, FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, MERCHANT_SEED, STREAM_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, stream_id: u64)]
+pub struct CreateStream<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PaymentStream::INIT_SPACE,
+        seeds = [STREAM_SEED, merchant.key().as_ref(), payer.key().as_ref(), &stream_id.to_le_bytes()],
+        bump
+    )]
+    pub stream: Account<'info, PaymentStream>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Token account owned by the stream PDA, created by the client beforehand
+    #[account(mut)]
+    pub stream_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Required only for Token-2022 mints while that rollout is gated
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+
+    // Required for merchants with an accepted-token list
+    #[account(
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump = accepted_tokens.bump
+    )]
+    pub accepted_tokens: Option<Account<'info, AcceptedTokens>>,
+}
+
+/// Escrows `deposit` in the new stream, from which it vests to the merchant
+/// at `rate_per_second` until used up.
+pub fn create_stream(
+    ctx: Context<CreateStream>,
+    _name: String,
+    stream_id: u64,
+    rate_per_second: u64,
+    mint: Pubkey,
+    deposit: u64,
+) -> Result<()> {
+    require!(rate_per_second > 0 && deposit > 0, ErrorCode::InvalidAmount);
+    require!(!ctx.accounts.global_state.paused, ErrorCode::Paused);
+    require!(ctx.accounts.merchant.active, ErrorCode::MerchantInactive);
+    require!(!ctx.accounts.merchant.admin_frozen, ErrorCode::MerchantFrozen);
+
+    // The same token rule as every other way of paying the merchant
+    let merchant = &ctx.accounts.merchant;
+    if merchant.accepted_tokens_enabled {
+        let accepted = ctx.accounts.accepted_tokens.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(accepted.accepts(&mint), ErrorCode::InvalidToken);
+    } else {
+        merchant.check_token(&mint)?;
+    }
+
+    if mint == Pubkey::default() {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.stream.to_account_info(),
+                },
+            ),
+            deposit,
+        )?;
+    } else {
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let mint_account = ctx.accounts.mint.as_ref().ok_or(ErrorCode::MissingMint)?;
+        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        let stream_ta = ctx.accounts.stream_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        require!(mint_account.key() == mint, ErrorCode::InvalidToken);
+        require!(payer_ta.mint == mint, ErrorCode::MintMismatch);
+        require!(payer_ta.owner == ctx.accounts.payer.key(), ErrorCode::InvalidPayerTokenAccount);
+        require!(stream_ta.mint == mint, ErrorCode::MintMismatch);
+        require!(stream_ta.owner == ctx.accounts.stream.key(), ErrorCode::OwnerMismatch);
+        if token_program.key() == anchor_spl::token_2022::ID {
+            FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+            reject_transfer_fee_mint(mint_account)?;
+        }
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: payer_ta.to_account_info(),
+                    mint: mint_account.to_account_info(),
+                    to: stream_ta.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            ),
+            deposit,
+            mint_account.decimals,
+        )?;
+    }
+
+    let now = clock::now(ctx.remaining_accounts)?;
+    let stream = &mut ctx.accounts.stream;
+    stream.merchant = ctx.accounts.merchant.key();
+    stream.payer = ctx.accounts.payer.key();
+    stream.stream_id = stream_id;
+    stream.mint = mint;
+    stream.rate_per_second = rate_per_second;
+    stream.deposit = deposit;
+    stream.withdrawn = 0;
+    stream.start_at = now;
+    stream.last_withdrawn_at = now;
+    stream.bump = ctx.bumps.stream;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_streams = merchant.open_streams.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+
+    emit_event!(ctx, StreamCreated {
+        merchant: stream.merchant,
+        payer: stream.payer,
+        stream: stream.key(),
+        stream_id,
+        mint,
+        rate_per_second,
+        deposit,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct WithdrawStream<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [STREAM_SEED, merchant.key().as_ref(), stream.payer.as_ref(), &stream.stream_id.to_le_bytes()],
+        bump = stream.bump
+    )]
+    pub stream: Account<'info, PaymentStream>,
+
+    pub owner: Signer<'info>,
+
+    /// CHECK: Merchant settlement wallet receiving the funds (For SOL streams)
+    #[account(
+        mut,
+        constraint = settlement_wallet.key() == merchant.settlement_wallet @ ErrorCode::InvalidMerchantWallet
+    )]
+    pub settlement_wallet: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, stream.mint.as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub stream_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub settlement_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Pays the merchant what has vested since the last withdrawal, less the
+/// protocol fee at the rate in force now.
+pub fn withdraw_stream(ctx: Context<WithdrawStream>, _name: String) -> Result<()> {
+    require!(!ctx.accounts.merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    let now = clock::now(ctx.remaining_accounts)?;
+    let state = &ctx.accounts.global_state;
+    let stream = &ctx.accounts.stream;
+    let amount = stream.vested(now)?;
+    require!(amount > 0, ErrorCode::NothingToClaim);
+    let (fee, merchant_amount) = calc_fee(amount, ctx.accounts.merchant.fee_bps(state.fee_bps), state.fee_rounding)?;
+
+    let tokens = StreamTokens::load(
+        stream,
+        ctx.accounts.token_program.as_ref(),
+        ctx.accounts.mint.as_ref(),
+        ctx.accounts.stream_token_account.as_ref(),
+    )?;
+    pay_out(
+        stream,
+        tokens.as_ref(),
+        (&ctx.accounts.settlement_wallet, ctx.accounts.settlement_token_account.as_ref()),
+        (&ctx.accounts.fee_vault.to_account_info(), ctx.accounts.fee_token_account.as_ref()),
+        merchant_amount,
+        fee,
+    )?;
+
+    let fee_stats = &mut ctx.accounts.fee_stats;
+    fee_stats.total_fees_collected = fee_stats
+        .total_fees_collected
+        .checked_add(fee)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let stream = &mut ctx.accounts.stream;
+    stream.withdrawn = stream.withdrawn.checked_add(amount).ok_or(ErrorCode::CalculationError)?;
+    stream.last_withdrawn_at = now;
+
+    emit_event!(ctx, StreamWithdrawn {
+        merchant: stream.merchant,
+        payer: stream.payer,
+        stream: stream.key(),
+        amount,
+        fee,
+        withdrawn: stream.withdrawn,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CancelStream<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        has_one = payer,
+        seeds = [STREAM_SEED, merchant.key().as_ref(), payer.key().as_ref(), &stream.stream_id.to_le_bytes()],
+        bump = stream.bump,
+        close = payer
+    )]
+    pub stream: Account<'info, PaymentStream>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Merchant settlement wallet receiving the vested part (For SOL streams)
+    #[account(
+        mut,
+        constraint = settlement_wallet.key() == merchant.settlement_wallet @ ErrorCode::InvalidMerchantWallet
+    )]
+    pub settlement_wallet: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, stream.mint.as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    // --- Optional Accounts for SPL ---
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub stream_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub settlement_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// Ends the stream: the merchant gets what has vested so far, less the fee,
+/// and the payer gets back the unvested remainder and the stream's rent.
+pub fn cancel_stream(ctx: Context<CancelStream>, _name: String) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let state = &ctx.accounts.global_state;
+    let stream = &ctx.accounts.stream;
+    let vested = stream.vested(now)?;
+    let refunded = stream.remaining()?.checked_sub(vested).ok_or(ErrorCode::CalculationError)?;
+    let (fee, merchant_amount) = calc_fee(vested, ctx.accounts.merchant.fee_bps(state.fee_bps), state.fee_rounding)?;
+
+    let tokens = StreamTokens::load(
+        stream,
+        ctx.accounts.token_program.as_ref(),
+        ctx.accounts.mint.as_ref(),
+        ctx.accounts.stream_token_account.as_ref(),
+    )?;
+    pay_out(
+        stream,
+        tokens.as_ref(),
+        (&ctx.accounts.settlement_wallet, ctx.accounts.settlement_token_account.as_ref()),
+        (&ctx.accounts.fee_vault.to_account_info(), ctx.accounts.fee_token_account.as_ref()),
+        merchant_amount,
+        fee,
+    )?;
+
+    // SOL left in the stream goes back with its rent when Anchor closes it;
+    // tokens are returned and the emptied token account closed to the payer
+    if let Some(tokens) = tokens.as_ref() {
+        let payer_ta = ctx.accounts.payer_token_account.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(payer_ta.mint == stream.mint, ErrorCode::MintMismatch);
+        require!(payer_ta.owner == stream.payer, ErrorCode::InvalidPayerTokenAccount);
+        tokens.transfer(stream, payer_ta, refunded)?;
+        tokens.close(stream, &ctx.accounts.payer.to_account_info())?;
+    }
+
+    let fee_stats = &mut ctx.accounts.fee_stats;
+    fee_stats.total_fees_collected = fee_stats
+        .total_fees_collected
+        .checked_add(fee)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let merchant = &mut ctx.accounts.merchant;
+    merchant.open_streams = merchant.open_streams.saturating_sub(1);
+
+    emit_event!(ctx, StreamCancelled {
+        merchant: stream.merchant,
+        payer: stream.payer,
+        stream: stream.key(),
+        vested,
+        fee,
+        refunded,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+/// The token side of an SPL stream, checked against the stream.
+struct StreamTokens<'a, 'info> {
+    token_program: &'a Interface<'info, TokenInterface>,
+    mint: &'a InterfaceAccount<'info, Mint>,
+    stream_token_account: &'a InterfaceAccount<'info, TokenAccount>,
+}
+
+impl<'a, 'info> StreamTokens<'a, 'info> {
+    // None for SOL streams; SPL streams need all three accounts
+    fn load(
+        stream: &Account<'info, PaymentStream>,
+        token_program: Option<&'a Interface<'info, TokenInterface>>,
+        mint: Option<&'a InterfaceAccount<'info, Mint>>,
+        stream_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    ) -> Result<Option<Self>> {
+        if stream.mint == Pubkey::default() {
+            return Ok(None);
+        }
+        let token_program = token_program.ok_or(ErrorCode::MissingAccount)?;
+        let mint = mint.ok_or(ErrorCode::MissingMint)?;
+        let stream_token_account = stream_token_account.ok_or(ErrorCode::MissingAccount)?;
+        require!(mint.key() == stream.mint, ErrorCode::InvalidToken);
+        require!(stream_token_account.mint == stream.mint, ErrorCode::MintMismatch);
+        require!(stream_token_account.owner == stream.key(), ErrorCode::OwnerMismatch);
+        Ok(Some(Self { token_program, mint, stream_token_account }))
+    }
+
+    fn transfer(
+        &self,
+        stream: &Account<'info, PaymentStream>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let stream_id = stream.stream_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            STREAM_SEED,
+            stream.merchant.as_ref(),
+            stream.payer.as_ref(),
+            &stream_id,
+            &[stream.bump],
+        ]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.stream_token_account.to_account_info(),
+                    mint: self.mint.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: stream.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            self.mint.decimals,
+        )
+    }
+
+    fn close(&self, stream: &Account<'info, PaymentStream>, destination: &AccountInfo<'info>) -> Result<()> {
+        let stream_id = stream.stream_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            STREAM_SEED,
+            stream.merchant.as_ref(),
+            stream.payer.as_ref(),
+            &stream_id,
+            &[stream.bump],
+        ]];
+        token_interface::close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.stream_token_account.to_account_info(),
+                destination: destination.clone(),
+                authority: stream.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+}
+
+/// Sends `merchant_amount` to the settlement wallet and `fee` to the fee
+/// vault, in lamports straight from the stream or through the token accounts
+/// given as (wallet, token account) pairs.
+fn pay_out<'info>(
+    stream: &Account<'info, PaymentStream>,
+    tokens: Option<&StreamTokens<'_, 'info>>,
+    settlement: (&AccountInfo<'info>, Option<&InterfaceAccount<'info, TokenAccount>>),
+    fee_vault: (&AccountInfo<'info>, Option<&InterfaceAccount<'info, TokenAccount>>),
+    merchant_amount: u64,
+    fee: u64,
+) -> Result<()> {
+    let Some(tokens) = tokens else {
+        // The stream is program-owned, so lamports move without a CPI
+        let stream_info = stream.to_account_info();
+        for (to, amount) in [(settlement.0, merchant_amount), (fee_vault.0, fee)] {
+            **stream_info.try_borrow_mut_lamports()? = stream_info
+                .lamports()
+                .checked_sub(amount)
+                .ok_or(ErrorCode::InsufficientBalance)?;
+            **to.try_borrow_mut_lamports()? = to
+                .lamports()
+                .checked_add(amount)
+                .ok_or(ErrorCode::CalculationError)?;
+        }
+        return Ok(());
+    };
+
+    if merchant_amount > 0 {
+        let settlement_ta = settlement.1.ok_or(ErrorCode::MissingAccount)?;
+        require!(settlement_ta.mint == stream.mint, ErrorCode::MintMismatch);
+        require!(settlement_ta.owner == settlement.0.key(), ErrorCode::InvalidMerchantTokenAccount);
+        tokens.transfer(stream, settlement_ta, merchant_amount)?;
+    }
+    if fee > 0 {
+        let fee_ta = fee_vault.1.ok_or(ErrorCode::MissingAccount)?;
+        require!(fee_ta.mint == stream.mint, ErrorCode::MintMismatch);
+        require!(fee_ta.owner == fee_vault.0.key(), ErrorCode::InvalidFeeTokenAccount);
+        tokens.transfer(stream, fee_ta, fee)?;
+    }
+    Ok(())
+}
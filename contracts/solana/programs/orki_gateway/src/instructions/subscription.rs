@@ -0,0 +1,281 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{Feature, FeatureFlags, GlobalState, Merchant, Subscription};
+use crate::events::{emit_event, SubscriptionCancelled, SubscriptionCharged, SubscriptionCreated};
+use crate::errors::ErrorCode;
+use crate::math::calc_fee;
+use crate::instructions::reject_transfer_fee_mint;
+use crate::clock;
+use crate::constants::{FEATURE_FLAGS_SEED, GLOBAL_STATE_SEED, MERCHANT_SEED, SUBSCRIPTION_DELEGATE_SEED, SUBSCRIPTION_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String, sub_id: u64)]
+pub struct CreateSubscription<'info> {
+    #[account(
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = subscriber,
+        space = 8 + Subscription::INIT_SPACE,
+        seeds = [SUBSCRIPTION_SEED, merchant.key().as_ref(), subscriber.key().as_ref(), &sub_id.to_le_bytes()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        constraint = subscriber_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = subscriber_token_account.owner == subscriber.key() @ ErrorCode::InvalidPayerTokenAccount
+    )]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA only used as the token delegate
+    #[account(seeds = [SUBSCRIPTION_DELEGATE_SEED], bump)]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_subscription(
+    ctx: Context<CreateSubscription>,
+    _name: String,
+    sub_id: u64,
+    amount: u64,
+    interval_seconds: i64,
+    mint: Pubkey,
+) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(interval_seconds > 0, ErrorCode::InvalidInterval);
+    require!(mint == ctx.accounts.mint.key(), ErrorCode::InvalidToken);
+    require_delegate(&ctx.accounts.subscriber_token_account, ctx.accounts.delegate.key(), amount)?;
+
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.merchant = ctx.accounts.merchant.key();
+    subscription.subscriber = ctx.accounts.subscriber.key();
+    subscription.sub_id = sub_id;
+    subscription.mint = mint;
+    subscription.subscriber_token_account = ctx.accounts.subscriber_token_account.key();
+    subscription.amount = amount;
+    subscription.interval_seconds = interval_seconds;
+    subscription.last_charged = 0;
+    subscription.charges_count = 0;
+    subscription.bump = ctx.bumps.subscription;
+
+    emit_event!(ctx, SubscriptionCreated {
+        merchant: subscription.merchant,
+        subscriber: subscription.subscriber,
+        subscription: subscription.key(),
+        sub_id,
+        mint,
+        amount,
+        interval_seconds,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ChargeSubscription<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        has_one = merchant,
+        has_one = mint,
+        has_one = subscriber_token_account,
+        seeds = [
+            SUBSCRIPTION_SEED,
+            merchant.key().as_ref(),
+            subscription.subscriber.as_ref(),
+            &subscription.sub_id.to_le_bytes()
+        ],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    /// CHECK: PDA signing as the subscriber's token delegate
+    #[account(seeds = [SUBSCRIPTION_DELEGATE_SEED], bump)]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = merchant_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = merchant_token_account.owner == merchant.settlement_wallet @ ErrorCode::InvalidMerchantTokenAccount
+    )]
+    pub merchant_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = fee_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = fee_token_account.owner == global_state.fee_wallet @ ErrorCode::InvalidFeeTokenAccount
+    )]
+    pub fee_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Required only for Token-2022 mints while that rollout is gated
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+}
+
+/// Pulls one period's amount through the delegate PDA. Anyone may call it,
+/// so a crank can drive billing; the schedule is enforced here.
+pub fn charge_subscription(ctx: Context<ChargeSubscription>, _name: String) -> Result<()> {
+    let state = &ctx.accounts.global_state;
+    let merchant = &ctx.accounts.merchant;
+    let subscription = &ctx.accounts.subscription;
+    let mint = &ctx.accounts.mint;
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    require!(!state.paused, ErrorCode::Paused);
+    require!(merchant.active, ErrorCode::MerchantInactive);
+    require!(!merchant.admin_frozen, ErrorCode::MerchantFrozen);
+
+    let due_at = subscription
+        .last_charged
+        .checked_add(subscription.interval_seconds)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(subscription.last_charged == 0 || now >= due_at, ErrorCode::SubscriptionNotDue);
+
+    let amount = subscription.amount;
+    // Fail with a clear error instead of a token program one if the approval was revoked
+    require_delegate(&ctx.accounts.subscriber_token_account, ctx.accounts.delegate.key(), amount)?;
+    require!(ctx.accounts.subscriber_token_account.amount >= amount, ErrorCode::InsufficientBalance);
+
+    if ctx.accounts.token_program.key() == anchor_spl::token_2022::ID {
+        FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+        reject_transfer_fee_mint(mint)?;
+    }
+
+    // Calculate Fee, preferring the merchant's negotiated rate
+    let fee_bps = merchant.fee_bps(state.fee_bps);
+    let (fee, merchant_amount) = calc_fee(amount, fee_bps, state.fee_rounding)?;
+
+    if !merchant.sandbox {
+        let signer_seeds: &[&[&[u8]]] = &[&[SUBSCRIPTION_DELEGATE_SEED, &[ctx.bumps.delegate]]];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.subscriber_token_account.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: ctx.accounts.fee_token_account.to_account_info(),
+                    authority: ctx.accounts.delegate.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            fee,
+            mint.decimals,
+        )?;
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.subscriber_token_account.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: ctx.accounts.merchant_token_account.to_account_info(),
+                    authority: ctx.accounts.delegate.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            merchant_amount,
+            mint.decimals,
+        )?;
+    }
+
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.last_charged = now;
+    subscription.charges_count = subscription
+        .charges_count
+        .checked_add(1)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    emit_event!(ctx, SubscriptionCharged {
+        merchant: subscription.merchant,
+        subscriber: subscription.subscriber,
+        subscription: subscription.key(),
+        amount,
+        fee,
+        charges_count: subscription.charges_count,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        has_one = subscriber,
+        seeds = [
+            SUBSCRIPTION_SEED,
+            subscription.merchant.as_ref(),
+            subscriber.key().as_ref(),
+            &subscription.sub_id.to_le_bytes()
+        ],
+        bump = subscription.bump,
+        close = subscriber
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+}
+
+pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+    let subscription = &ctx.accounts.subscription;
+
+    emit_event!(ctx, SubscriptionCancelled {
+        merchant: subscription.merchant,
+        subscriber: subscription.subscriber,
+        subscription: subscription.key(),
+        charges_count: subscription.charges_count,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn require_delegate(token_account: &TokenAccount, delegate: Pubkey, amount: u64) -> Result<()> {
+    require!(
+        token_account.delegate == Some(delegate).into() && token_account.delegated_amount >= amount,
+        ErrorCode::DelegateNotApproved
+    );
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::state::{AcceptedTokens, Feature, FeatureFlags, FeeStats, FeeVault, GlobalState, GlobalStats, Merchant, MerchantStats, Payment, PaymentId, PaymentStatus};
+use crate::events::{emit_event, PaymentProcessed, PaymentSwapped};
+use crate::instructions::reject_transfer_fee_mint;
+use crate::jupiter_interface;
+use crate::errors::ErrorCode;
+use crate::math::calc_fee;
+use crate::clock;
+use crate::constants::{ACCEPTED_TOKENS_SEED, FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, PAYMENT_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, payment_id: u64, name: String)]
+pub struct ProcessPaymentWithSwap<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_STATS_SEED],
+        bump = global_stats.bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [PAYMENT_SEED, payer.key().as_ref(), &PaymentId::Sequence(payment_id).seed()],
+        bump
+    )]
+    pub payment_history: Account<'info, Payment>,
+
+    #[account(
+        seeds = [FEE_VAULT_SEED],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    // Stats for the input mint; the fee is taken before the swap
+    #[account(
+        mut,
+        seeds = [FEE_STATS_SEED, fee_stats.mint.as_ref()],
+        bump = fee_stats.bump
+    )]
+    pub fee_stats: Account<'info, FeeStats>,
+
+    // Pay SOL as wrapped SOL; the route only moves SPL tokens
+    pub token_program: Interface<'info, TokenInterface>,
+    pub input_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub payer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub fee_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Route destination: the merchant's settlement_token account
+    #[account(mut)]
+    pub merchant_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [FEATURE_FLAGS_SEED],
+        bump = feature_flags.bump
+    )]
+    pub feature_flags: Option<Account<'info, FeatureFlags>>,
+
+    #[account(
+        seeds = [ACCEPTED_TOKENS_SEED, merchant.key().as_ref()],
+        bump = accepted_tokens.bump
+    )]
+    pub accepted_tokens: Option<Account<'info, AcceptedTokens>>,
+
+    /// CHECK: Address-checked; executability is left to the route CPI so
+    /// pre-swap checks still run on clusters without Jupiter
+    #[account(address = jupiter_interface::ID)]
+    pub jupiter_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Settles a swap_enabled merchant in its settlement_token. The fee comes out
+/// of the input first; the rest is swapped through the Jupiter route given as
+/// `route_data` plus remaining accounts, straight into the merchant's account.
+/// Receiving less than `min_amount_out` reverts the whole payment.
+#[allow(clippy::too_many_arguments)]
+pub fn process_payment_with_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, ProcessPaymentWithSwap<'info>>,
+    amount: u64,
+    payment_id: u64,
+    _name: String,
+    min_amount_out: u64,
+    route_data: Vec<u8>,
+) -> Result<()> {
+    let state = &ctx.accounts.global_state;
+    let merchant = &ctx.accounts.merchant;
+
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    require!(!ctx.accounts.payment_history.is_recorded(), ErrorCode::DuplicatePayment);
+    require!(!state.paused, ErrorCode::Paused);
+    require!(merchant.active, ErrorCode::MerchantInactive);
+    require!(!merchant.admin_frozen, ErrorCode::MerchantFrozen);
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    merchant.check_amount(amount)?;
+    FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::SwapSettlement)?;
+    require!(merchant.swap_enabled, ErrorCode::SwapNotEnabled);
+    // Escrow and split payouts are credited in the token paid, not a swapped one
+    require!(
+        !merchant.escrow_enabled && !merchant.split_enabled,
+        ErrorCode::UnsupportedSettlementMode
+    );
+
+    let input_mint = &ctx.accounts.input_mint;
+    let settlement_token = merchant.settlement_token;
+    // Paying in the settlement token itself needs no swap; use process_payment
+    require!(
+        settlement_token != Pubkey::default() && input_mint.key() != settlement_token,
+        ErrorCode::InvalidToken
+    );
+    if merchant.accepted_tokens_enabled {
+        let accepted = ctx.accounts.accepted_tokens.as_ref().ok_or(ErrorCode::MissingAccount)?;
+        require!(accepted.accepts(&input_mint.key()), ErrorCode::InvalidToken);
+    }
+    if ctx.accounts.token_program.key() == anchor_spl::token_2022::ID {
+        FeatureFlags::require(ctx.accounts.feature_flags.as_deref(), Feature::Token2022)?;
+        reject_transfer_fee_mint(input_mint)?;
+    }
+    require!(ctx.accounts.fee_stats.mint == input_mint.key(), ErrorCode::InvalidToken);
+
+    let payer_ta = &ctx.accounts.payer_token_account;
+    let fee_ta = &ctx.accounts.fee_token_account;
+    let merchant_ta = &ctx.accounts.merchant_token_account;
+    require!(payer_ta.mint == input_mint.key(), ErrorCode::MintMismatch);
+    require!(payer_ta.owner == ctx.accounts.payer.key(), ErrorCode::InvalidPayerTokenAccount);
+    require!(fee_ta.mint == input_mint.key(), ErrorCode::MintMismatch);
+    require!(fee_ta.owner == ctx.accounts.fee_vault.key(), ErrorCode::InvalidFeeTokenAccount);
+    require!(merchant_ta.mint == settlement_token, ErrorCode::MintMismatch);
+    require!(merchant_ta.owner == merchant.settlement_wallet, ErrorCode::InvalidMerchantTokenAccount);
+    require!(payer_ta.amount >= amount, ErrorCode::InsufficientBalance);
+
+    // Calculate Fee on the input, preferring the merchant's negotiated rate
+    let (fee_bps, fee_source) = merchant.fee_rate(None, None, state.fee_bps);
+    let (fee, amount_in) = calc_fee(amount, fee_bps, state.fee_rounding)?;
+
+    // Sandbox merchants run every check above but move no funds
+    let mut amount_out = 0;
+    if !merchant.sandbox {
+        let payer_before = payer_ta.amount;
+        let merchant_before = merchant_ta.amount;
+
+        // Transfer Fee to Fee Vault
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: payer_ta.to_account_info(),
+                    mint: input_mint.to_account_info(),
+                    to: fee_ta.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            ),
+            fee,
+            input_mint.decimals,
+        )?;
+
+        // The route comes from the Jupiter API; the payer's signature on the
+        // outer transaction is what authorizes it to move the input tokens
+        let metas = ctx
+            .remaining_accounts
+            .iter()
+            .map(|a| AccountMeta {
+                pubkey: *a.key,
+                is_signer: a.is_signer,
+                is_writable: a.is_writable,
+            })
+            .collect();
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.jupiter_program.to_account_info());
+        invoke(
+            &Instruction {
+                program_id: jupiter_interface::ID,
+                accounts: metas,
+                data: route_data,
+            },
+            &infos,
+        )?;
+
+        // Judge the route by balances, not by what its data claims
+        ctx.accounts.payer_token_account.reload()?;
+        ctx.accounts.merchant_token_account.reload()?;
+        let spent = payer_before
+            .checked_sub(ctx.accounts.payer_token_account.amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(spent <= amount, ErrorCode::SwapOverspent);
+        amount_out = ctx
+            .accounts
+            .merchant_token_account
+            .amount
+            .checked_sub(merchant_before)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(amount_out >= min_amount_out, ErrorCode::SlippageExceeded);
+
+        ctx.accounts.global_stats.record_payment(amount, fee)?;
+        let fee_stats = &mut ctx.accounts.fee_stats;
+        fee_stats.total_fees_collected = fee_stats
+            .total_fees_collected
+            .checked_add(fee)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+
+    ctx.accounts.merchant_stats.record_payment(amount, fee, now)?;
+
+    let token = ctx.accounts.input_mint.key();
+    let merchant = &ctx.accounts.merchant;
+    let tax_amount = merchant.tax_on(amount)?;
+
+    // Mark payment as processed; amounts are in the input token
+    let payment = &mut ctx.accounts.payment_history;
+    payment.payer = ctx.accounts.payer.key();
+    payment.merchant = merchant.key();
+    payment.amount = amount;
+    payment.payment_id = payment_id;
+    payment.timestamp = now;
+    payment.bump = ctx.bumps.payment_history;
+    payment.sandbox = merchant.sandbox;
+    payment.status = PaymentStatus::Completed;
+    payment.mint = token;
+    payment.fee = fee;
+    payment.slot = Clock::get()?.slot;
+    payment.tax_amount = tax_amount;
+
+    let event_seq = ctx.accounts.global_stats.next_event_seq()?;
+    emit_event!(ctx, PaymentProcessed {
+        payer: ctx.accounts.payer.key(),
+        merchant: merchant.key(),
+        amount,
+        fee,
+        token,
+        payment_id,
+        timestamp: now,
+        sandbox: merchant.sandbox,
+        memo: String::new(),
+        referrer_amount: 0,
+        tip: 0,
+        fee_bps,
+        fee_source,
+        valid_until: 0,
+        event_seq,
+        merchant_name: merchant.name.clone(),
+        payment_account: ctx.accounts.payment_history.key(),
+        order_id: [0; 32],
+        tax_amount,
+    });
+
+    emit_event!(ctx, PaymentSwapped {
+        payer: ctx.accounts.payer.key(),
+        merchant: merchant.key(),
+        payment_id,
+        input_mint: token,
+        output_mint: settlement_token,
+        amount_in,
+        amount_out,
+        timestamp: now,
+    });
+
+    Ok(())
+}
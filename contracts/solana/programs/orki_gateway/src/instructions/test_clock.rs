@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use crate::state::{GlobalState, TestClock};
+use crate::errors::ErrorCode;
+use crate::constants::{GLOBAL_STATE_SEED, TEST_CLOCK_SEED};
+
+#[derive(Accounts)]
+pub struct SetTestClock<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + TestClock::INIT_SPACE,
+        seeds = [TEST_CLOCK_SEED],
+        bump
+    )]
+    pub test_clock: Account<'info, TestClock>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_test_clock(ctx: Context<SetTestClock>, unix_timestamp: i64) -> Result<()> {
+    let test_clock = &mut ctx.accounts.test_clock;
+    test_clock.unix_timestamp = unix_timestamp;
+    test_clock.bump = ctx.bumps.test_clock;
+    Ok(())
+}
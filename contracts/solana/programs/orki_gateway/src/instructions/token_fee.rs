@@ -0,0 +1,182 @@
+use anchor_lang::prelude::*;
+use crate::state::{AdminAction, AdminProposal, GlobalState, TokenFeeConfig};
+use crate::events::{emit_event, TokenFeeCleared, TokenFeeProposed, TokenFeeSet};
+use crate::errors::ErrorCode;
+use crate::clock;
+use crate::constants::{ADMIN_PROPOSAL_SEED, GLOBAL_STATE_SEED, TOKEN_FEE_SEED};
+use crate::instructions::authorize_admin_action;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetTokenFee<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + TokenFeeConfig::INIT_SPACE,
+        seeds = [TOKEN_FEE_SEED, mint.as_ref()],
+        bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+
+    // The admin, or anyone carrying out a council-approved rate
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+
+    // Required once an admin council governs; see authorize_admin_action
+    #[account(
+        mut,
+        seeds = [ADMIN_PROPOSAL_SEED, &admin_proposal.nonce.to_le_bytes()],
+        bump = admin_proposal.bump
+    )]
+    pub admin_proposal: Option<Account<'info, AdminProposal>>,
+}
+
+/// Sets the protocol fee for payments in `mint` (Pubkey::default() for SOL).
+/// Merchant overrides still take precedence. Lowering an existing rate takes
+/// effect immediately and drops any pending raise; a raise, or a mint's first
+/// rate, waits out the fee timelock like the global rate and is committed by
+/// apply_token_fee.
+pub fn set_token_fee(ctx: Context<SetTokenFee>, mint: Pubkey, fee_bps: u16) -> Result<()> {
+    authorize_admin_action(
+        &ctx.accounts.global_state,
+        &ctx.accounts.admin.key(),
+        ctx.accounts.admin_proposal.as_deref_mut(),
+        AdminAction::SetTokenFee { mint, fee_bps },
+    )?;
+    ctx.accounts.global_state.check_fee(fee_bps)?;
+    let now = clock::now(ctx.remaining_accounts)?;
+
+    let config = &mut ctx.accounts.token_fee;
+    // init_if_needed left a fresh account zeroed, and find_program_address
+    // never settles on bump 0 in practice
+    if config.bump == 0 {
+        config.mint = mint;
+        config.bump = ctx.bumps.token_fee;
+        config.inactive = true;
+    }
+
+    if config.rate().is_some_and(|current| fee_bps <= current) {
+        config.fee_bps = fee_bps;
+        config.pending_fee_bps = None;
+        config.fee_effective_at = 0;
+
+        emit_event!(ctx, TokenFeeSet {
+            admin: ctx.accounts.admin.key(),
+            mint,
+            fee_bps,
+            timestamp: now,
+        });
+        return Ok(());
+    }
+
+    let effective_at = ctx.accounts.global_state.fee_effective_time(now)?;
+    let current_fee_bps = config.rate();
+    config.pending_fee_bps = Some(fee_bps);
+    config.fee_effective_at = effective_at;
+
+    emit_event!(ctx, TokenFeeProposed {
+        admin: ctx.accounts.admin.key(),
+        mint,
+        current_fee_bps,
+        pending_fee_bps: fee_bps,
+        effective_at,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApplyTokenFee<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [TOKEN_FEE_SEED, token_fee.mint.as_ref()],
+        bump = token_fee.bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+
+    // Anyone may apply a rate whose timelock has passed
+    pub caller: Signer<'info>,
+}
+
+pub fn apply_token_fee(ctx: Context<ApplyTokenFee>) -> Result<()> {
+    let now = clock::now(ctx.remaining_accounts)?;
+    let config = &mut ctx.accounts.token_fee;
+
+    let fee_bps = config.pending_fee_bps.ok_or(ErrorCode::NoPendingFee)?;
+    require!(now >= config.fee_effective_at, ErrorCode::FeeTimelockNotElapsed);
+    // The cap may have been lowered since the rate was queued
+    ctx.accounts.global_state.check_fee(fee_bps)?;
+
+    config.fee_bps = fee_bps;
+    config.pending_fee_bps = None;
+    config.fee_effective_at = 0;
+    config.inactive = false;
+
+    emit_event!(ctx, TokenFeeSet {
+        admin: ctx.accounts.caller.key(),
+        mint: config.mint,
+        fee_bps,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClearTokenFee<'info> {
+    #[account(
+        seeds = [GLOBAL_STATE_SEED],
+        bump = global_state.bump,
+        constraint = global_state.admin == admin.key() @ ErrorCode::Unauthorized,
+        constraint = global_state.version == GlobalState::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [TOKEN_FEE_SEED, token_fee.mint.as_ref()],
+        bump = token_fee.bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+/// Closes the config so the mint falls back to the global rate. That is only
+/// immediate when it does not raise the mint's rate; otherwise queue the
+/// global rate with set_token_fee first.
+pub fn clear_token_fee(ctx: Context<ClearTokenFee>) -> Result<()> {
+    require!(!ctx.accounts.global_state.admin_council, ErrorCode::CouncilGoverned);
+    if let Some(fee_bps) = ctx.accounts.token_fee.rate() {
+        require!(ctx.accounts.global_state.fee_bps <= fee_bps, ErrorCode::FeeRaiseNeedsTimelock);
+    }
+
+    emit_event!(ctx, TokenFeeCleared {
+        admin: ctx.accounts.admin.key(),
+        mint: ctx.accounts.token_fee.mint,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
@@ -1,43 +1,92 @@
 use anchor_lang::prelude::*;
-use crate::state::Merchant;
-use crate::events::MerchantUpdated; 
+use crate::state::{Merchant, MerchantIndex, MerchantName, MerchantStats, Operator, OperatorPermission, PayoutBook};
+use crate::errors::ErrorCode;
+use crate::events::{
+    emit_event, ConfirmationMessageUpdated, MerchantClosed, MerchantPing, MerchantRenamed, MerchantUpdated,
+    PreviousMerchantReleased, SelfPaymentsUpdated, TaxRateUpdated,
+};
+use crate::clock;
+use crate::instructions::register_merchant::claim_merchant_name;
+use crate::instructions::merchant_transfer::{check_movable, check_previous_merchant, MerchantMove};
+use crate::constants::{BPS_DENOMINATOR, MAX_CONFIRMATION_MESSAGE_LENGTH, MAX_METADATA_URI_LENGTH, MERCHANT_INDEX_SEED, MERCHANT_NAME_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, OPERATOR_SEED, PAYOUT_BOOK_SEED};
 
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(name: String)]
 pub struct UpdateMerchant<'info> {
     #[account(
         mut,
-        has_one = owner,
-        seeds = [b"merchant", owner.key().as_ref(), name.as_bytes()],
-        bump = merchant.bump
+        seeds = [MERCHANT_SEED, merchant.owner.as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
     )]
     pub merchant: Account<'info, Merchant>,
-    pub owner: Signer<'info>,
+    // The merchant owner, or an operator with UpdateSettlement for the
+    // settlement wallet and token alone
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, merchant.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    // Required when changing settlement_wallet
+    #[account(
+        seeds = [PAYOUT_BOOK_SEED, merchant.key().as_ref()],
+        bump = payout_book.bump
+    )]
+    pub payout_book: Option<Account<'info, PayoutBook>>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_merchant(
     ctx: Context<UpdateMerchant>,
-    name: String,
-    new_name: Option<String>,
+    _name: String,
     settlement_wallet: Option<Pubkey>,
     settlement_token: Option<Pubkey>,
     swap_enabled: Option<bool>,
+    escrow_enabled: Option<bool>,
+    min_amount: Option<u64>,
+    max_amount: Option<u64>,
+    accepts_any_token: Option<bool>,
+    metadata_uri: Option<String>,
+    category: Option<u8>,
 ) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    Operator::authorize(
+        &ctx.accounts.merchant,
+        &authority,
+        ctx.accounts.operator.as_deref(),
+        OperatorPermission::UpdateSettlement,
+    )?;
+    // Operators may only move settlement; the rest of the profile is the owner's
+    if authority != ctx.accounts.merchant.owner {
+        require!(
+            swap_enabled.is_none()
+                && escrow_enabled.is_none()
+                && min_amount.is_none()
+                && max_amount.is_none()
+                && accepts_any_token.is_none()
+                && metadata_uri.is_none()
+                && category.is_none(),
+            ErrorCode::Unauthorized
+        );
+    }
+
     let merchant = &mut ctx.accounts.merchant;
     
-    // Store old values for event
-    let old_name = merchant.name.clone();
-    let old_settlement_wallet = merchant.settlement_wallet;
-    let old_settlement_token = merchant.settlement_token;
-    let old_swap_enabled = merchant.swap_enabled;
-    
     // Update fields
-    if let Some(n) = &new_name {
-        merchant.name = n.clone();
-    }
-    
     if let Some(wallet) = settlement_wallet {
+        if wallet != merchant.settlement_wallet {
+            // Only active payout book entries can receive settlement
+            PayoutBook::require_active(
+                ctx.accounts.payout_book.as_deref(),
+                &wallet,
+                clock::now(ctx.remaining_accounts)?,
+            )?;
+        }
         merchant.settlement_wallet = wallet;
     }
     
@@ -49,17 +98,417 @@ pub fn update_merchant(
         merchant.swap_enabled = enabled;
     }
     
+    if let Some(enabled) = escrow_enabled {
+        require!(!(enabled && merchant.split_enabled), ErrorCode::SplitEscrowConflict);
+        require!(!(enabled && merchant.accrues()), ErrorCode::SettlementModeConflict);
+        merchant.escrow_enabled = enabled;
+    }
+    
+    if let Some(min) = min_amount {
+        merchant.min_amount = min;
+    }
+    
+    if let Some(max) = max_amount {
+        merchant.max_amount = max;
+    }
+
+    if let Some(any) = accepts_any_token {
+        merchant.accepts_any_token = any;
+        merchant.token_rule_set = true;
+    }
+
+    if let Some(uri) = &metadata_uri {
+        require!(uri.len() <= MAX_METADATA_URI_LENGTH as usize, ErrorCode::MetadataUriTooLong);
+        merchant.metadata_uri = uri.clone();
+    }
+
+    if let Some(category) = category {
+        merchant.category = category;
+    }
+    require!(
+        merchant.max_amount == 0 || merchant.min_amount <= merchant.max_amount,
+        ErrorCode::InvalidAmountLimits
+    );
+    
     // Emit event
-    emit!(MerchantUpdated {
-        owner: ctx.accounts.owner.key(),
+    emit_event!(ctx, MerchantUpdated {
+        owner: merchant.owner,
+        updated_by: authority,
         merchant: merchant.key(),
-        old_name,
-        new_name,
+        name: merchant.name.clone(),
         settlement_wallet,
         settlement_token,
         swap_enabled,
+        escrow_enabled,
+        min_amount,
+        max_amount,
+        accepts_any_token,
         timestamp: Clock::get()?.unix_timestamp,
+        metadata_uri,
+        category,
     });
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, new_name: String)]
+pub struct RenameMerchant<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        close = owner,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Merchant::INIT_SPACE,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), new_name.as_bytes()],
+        bump
+    )]
+    pub new_merchant: Account<'info, Merchant>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump,
+        close = owner
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + MerchantStats::INIT_SPACE,
+        seeds = [MERCHANT_STATS_SEED, new_merchant.key().as_ref()],
+        bump
+    )]
+    pub new_merchant_stats: Account<'info, MerchantStats>,
+    // Directory slot; required unless the merchant was never indexed
+    #[account(
+        mut,
+        seeds = [MERCHANT_INDEX_SEED, &merchant.merchant_index.to_le_bytes()],
+        bump = merchant_index.bump
+    )]
+    pub merchant_index: Option<Account<'info, MerchantIndex>>,
+    // Claim on the current name; required once the merchant holds one
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [MERCHANT_NAME_SEED, owner.key().as_ref(), merchant_name.normalized_name.as_bytes()],
+        bump = merchant_name.bump
+    )]
+    pub merchant_name: Option<Account<'info, MerchantName>>,
+    /// CHECK: The claim on the new name, derived by the handler; the same
+    /// account as merchant_name when only the casing changes
+    #[account(mut)]
+    pub new_merchant_name: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The name is part of the merchant PDA seeds, so a rename moves the state to a
+/// new PDA and closes the old one. Vaults and invoices are keyed by the merchant
+/// address and do not follow, so merchants that couldn't change owners (see
+/// check_movable) or that have a split config or an accepted-token list
+/// cannot rename. The payout address book stays behind as
+/// well; approved destinations have to be re-added, with their delay, after a
+/// rename. A merchant that already moved once can only move back to its
+/// previous address until the owner runs release_previous_merchant.
+pub fn rename_merchant(ctx: Context<RenameMerchant>, name: String, new_name: String) -> Result<()> {
+    Merchant::check_name(&new_name)?;
+    let old = &ctx.accounts.merchant;
+    check_movable(old, MerchantMove::Rename)?;
+    check_previous_merchant(old, &ctx.accounts.new_merchant.key())?;
+    // Unlike an ownership transfer, a rename doesn't carry these over
+    require!(!old.split_enabled, ErrorCode::RenameBlockedBySplit);
+    require!(!old.accepted_tokens_enabled, ErrorCode::RenameBlockedByAcceptedTokens);
+    require!(
+        old.merchant_index == 0 || ctx.accounts.merchant_index.is_some(),
+        ErrorCode::MissingAccount
+    );
+    require!(!old.name_indexed || ctx.accounts.merchant_name.is_some(), ErrorCode::MissingAccount);
+
+    let old_merchant = old.key();
+    ctx.accounts.new_merchant.set_inner(Merchant {
+        name: new_name.clone(),
+        bump: ctx.bumps.new_merchant,
+        // Lets refunds still match payments made under the old address
+        previous_merchant: old_merchant,
+        name_indexed: true,
+        ..Merchant::clone(old)
+    });
+    // A change of case alone keeps the claim; otherwise the new name is
+    // claimed and the old one released
+    let new_merchant = ctx.accounts.new_merchant.key();
+    match ctx.accounts.merchant_name.as_mut() {
+        Some(claim) if claim.key() == ctx.accounts.new_merchant_name.key() => claim.merchant = new_merchant,
+        claim => {
+            claim_merchant_name(
+                &ctx.accounts.new_merchant_name,
+                &ctx.accounts.owner.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.owner.key(),
+                new_merchant,
+                &new_name,
+            )?;
+            if let Some(claim) = claim {
+                claim.close(ctx.accounts.owner.to_account_info())?;
+            }
+        }
+    }
+    // Lifetime counters carry over to the new address
+    ctx.accounts.new_merchant_stats.set_inner(MerchantStats {
+        merchant: ctx.accounts.new_merchant.key(),
+        bump: ctx.bumps.new_merchant_stats,
+        ..MerchantStats::clone(&ctx.accounts.merchant_stats)
+    });
+    // The directory slot follows the merchant to its new address
+    if let Some(entry) = ctx.accounts.merchant_index.as_mut() {
+        entry.merchant = ctx.accounts.new_merchant.key();
+    }
+
+    // Emit event
+    emit!(MerchantRenamed {
+        owner: ctx.accounts.owner.key(),
+        old_merchant,
+        new_merchant: ctx.accounts.new_merchant.key(),
+        old_name: name,
+        new_name,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CloseMerchant<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        close = owner,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        mut,
+        seeds = [MERCHANT_STATS_SEED, merchant.key().as_ref()],
+        bump = merchant_stats.bump,
+        close = owner
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+    // Directory slot; required unless the merchant was never indexed
+    #[account(
+        mut,
+        seeds = [MERCHANT_INDEX_SEED, &merchant.merchant_index.to_le_bytes()],
+        bump = merchant_index.bump
+    )]
+    pub merchant_index: Option<Account<'info, MerchantIndex>>,
+    // Claim on the name, released with the merchant; required once it holds one
+    #[account(
+        mut,
+        has_one = merchant,
+        seeds = [MERCHANT_NAME_SEED, owner.key().as_ref(), merchant_name.normalized_name.as_bytes()],
+        bump = merchant_name.bump,
+        close = owner
+    )]
+    pub merchant_name: Option<Account<'info, MerchantName>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+pub fn close_merchant(ctx: Context<CloseMerchant>, name: String) -> Result<()> {
+    let merchant = &ctx.accounts.merchant;
+    require!(merchant.funded_escrows == 0, ErrorCode::MerchantHasEscrowBalance);
+    require!(merchant.open_invoices == 0, ErrorCode::MerchantHasOpenInvoices);
+    // Operator PDAs would otherwise outlive the merchant and apply to a re-registration
+    require!(merchant.operators == 0, ErrorCode::MerchantHasOperators);
+    // Routes too, being keyed by the merchant's address
+    require!(merchant.settlement_routes == 0, ErrorCode::MerchantHasSettlementRoutes);
+    // And discount codes, which would be redeemable again after a re-registration
+    require!(merchant.discount_codes == 0, ErrorCode::MerchantHasDiscountCodes);
+    // As would the split config and accepted-token list, keyed the same way
+    require!(!merchant.split_enabled, ErrorCode::MerchantHasSplitConfig);
+    require!(!merchant.accepted_tokens_enabled, ErrorCode::MerchantHasAcceptedTokens);
+    // Capture and void need the merchant, so a hold would strand the payer's funds
+    require!(merchant.open_authorizations == 0, ErrorCode::MerchantHasOpenAuthorizations);
+    // Likewise cancelling a stream, which returns the payer's unvested deposit
+    require!(merchant.open_streams == 0, ErrorCode::MerchantHasOpenStreams);
+    require!(
+        merchant.merchant_index == 0 || ctx.accounts.merchant_index.is_some(),
+        ErrorCode::MissingAccount
+    );
+    require!(!merchant.name_indexed || ctx.accounts.merchant_name.is_some(), ErrorCode::MissingAccount);
+    // Tombstone the directory slot so it never points at a closed account
+    if let Some(entry) = ctx.accounts.merchant_index.as_mut() {
+        entry.merchant = Pubkey::default();
+    }
+
+    // Emit event
+    emit!(MerchantClosed {
+        owner: ctx.accounts.owner.key(),
+        merchant: merchant.key(),
+        name,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct PingMerchant<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// No-op that only emits MerchantPing, so merchants can test their
+/// notification setup without making a payment.
+pub fn ping_merchant(ctx: Context<PingMerchant>, _name: String, nonce: u64) -> Result<()> {
+    emit!(MerchantPing {
+        merchant: ctx.accounts.merchant.key(),
+        owner: ctx.accounts.owner.key(),
+        nonce,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetAllowSelfPayments<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// Lets the settlement wallet pay the merchant, as in a merchant testing its
+/// own checkout. process_payment rejects such payments with SelfPayment
+/// otherwise.
+pub fn set_allow_self_payments(ctx: Context<SetAllowSelfPayments>, _name: String, allowed: bool) -> Result<()> {
+    ctx.accounts.merchant.allow_self_payments = allowed;
+
+    emit_event!(ctx, SelfPaymentsUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        allowed,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetTaxRate<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// Sets the tax rate payments record their tax at; 0 records none. Payments
+/// already made keep the tax they were recorded with.
+pub fn set_tax_rate(ctx: Context<SetTaxRate>, _name: String, tax_bps: u16) -> Result<()> {
+    require!(tax_bps <= BPS_DENOMINATOR, ErrorCode::InvalidTaxRate);
+    ctx.accounts.merchant.tax_bps = tax_bps;
+
+    emit_event!(ctx, TaxRateUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        tax_bps,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetConfirmationMessage<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// Sets the message process_payment hands back to wallets in its
+/// PaymentReceipt, such as "Thanks for shopping at Joe's!"; empty clears it.
+pub fn set_confirmation_message(ctx: Context<SetConfirmationMessage>, _name: String, message: String) -> Result<()> {
+    require!(
+        message.len() <= MAX_CONFIRMATION_MESSAGE_LENGTH as usize,
+        ErrorCode::ConfirmationMessageTooLong
+    );
+    ctx.accounts.merchant.confirmation_message = message.clone();
+
+    emit_event!(ctx, ConfirmationMessageUpdated {
+        merchant: ctx.accounts.merchant.key(),
+        message,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ReleasePreviousMerchant<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MERCHANT_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump = merchant.bump,
+        constraint = merchant.version == Merchant::VERSION @ ErrorCode::AccountNeedsMigration
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub owner: Signer<'info>,
+}
+
+/// Stops refunds of payments made under the address the merchant was last
+/// renamed or sold from, which frees it to move again. Run it once those
+/// payments are refunded or past refunding.
+pub fn release_previous_merchant(ctx: Context<ReleasePreviousMerchant>, _name: String) -> Result<()> {
+    let merchant = &mut ctx.accounts.merchant;
+    require!(merchant.previous_merchant != Pubkey::default(), ErrorCode::NoPreviousMerchant);
+    let previous_merchant = std::mem::take(&mut merchant.previous_merchant);
+
+    emit_event!(ctx, PreviousMerchantReleased {
+        owner: ctx.accounts.owner.key(),
+        merchant: merchant.key(),
+        previous_merchant,
+        timestamp: clock::now(ctx.remaining_accounts)?,
+    });
+
+    Ok(())
+}
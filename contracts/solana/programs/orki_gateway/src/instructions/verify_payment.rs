@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use crate::state::Payment;
+use crate::errors::ErrorCode;
+use crate::constants::PAYMENT_SEED;
+
+#[derive(Accounts)]
+pub struct VerifyPayment<'info> {
+    /// CHECK: Only its address is used, to derive the payment record
+    pub payer: UncheckedAccount<'info>,
+    /// CHECK: Only its address is used, compared with the record's merchant
+    pub merchant: UncheckedAccount<'info>,
+    /// CHECK: Loaded by hand so a missing record fails with PaymentNotFound;
+    /// its address is checked against the record's own id once loaded
+    pub payment: UncheckedAccount<'info>,
+}
+
+/// Read-only proof for CPI callers that `payer` paid `merchant` at least
+/// `min_amount` under `payment_id`, which is 0 for process_payment_v2 order
+/// ids. Counts completed, captured and finalized
+/// payments, net of refunds; held, voided and disputed ones fail. Callers that
+/// must not act on a payment that could still be forked away check
+/// `status.is_final()` on the returned record. Anchor hands the record to the
+/// caller through return data.
+pub fn verify_payment(ctx: Context<VerifyPayment>, payment_id: u64, min_amount: u64) -> Result<Payment> {
+    let info = ctx.accounts.payment.to_account_info();
+    require!(
+        info.owner == &crate::ID && !info.data_is_empty(),
+        ErrorCode::PaymentNotFound
+    );
+    let payment = {
+        let data = info.try_borrow_data()?;
+        Payment::try_deserialize(&mut &data[..])?
+    };
+
+    // As in finalize_payment, the seeds come from the record, so order ids verify too
+    let payer = ctx.accounts.payer.key();
+    let address = Pubkey::create_program_address(
+        &[PAYMENT_SEED, payer.as_ref(), &payment.id().seed(), &[payment.bump]],
+        &crate::ID,
+    )
+    .map_err(|_| ErrorCode::PaymentNotFound)?;
+    require!(
+        info.key() == address && payment.payment_id == payment_id,
+        ErrorCode::PaymentNotFound
+    );
+    require!(payment.merchant == ctx.accounts.merchant.key(), ErrorCode::InvalidPayment);
+    require!(payment.status.is_settled(), ErrorCode::InvalidPaymentStatus);
+    let paid = payment
+        .amount
+        .checked_sub(payment.refunded_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(paid >= min_amount, ErrorCode::AmountTooSmall);
+
+    Ok(payment)
+}
@@ -1 +1,7 @@
+use anchor_lang::prelude::*;
+
+// Jupiter v6 aggregator. Routes arrive as opaque instruction data from the
+// Jupiter quote API, so the program id is all this crate needs to know.
+pub const ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
 pub struct JupiterSwap;
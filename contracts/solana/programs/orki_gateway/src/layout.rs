@@ -0,0 +1,374 @@
+//! Account layout policy.
+//!
+//! Every `#[account]` struct ends in a `reserved` byte array so later releases
+//! can add fields without resizing live accounts:
+//!
+//! - New fields go directly before `reserved`, never between existing fields.
+//! - `reserved` shrinks by the new field's `INIT_SPACE`, so `LEN` stays fixed.
+//! - Zeroed reserved bytes decode as the new field's default (false, 0, None).
+//! - If `reserved` runs out, grow the account through [`migrate_account`].
+//! - `GlobalState` and `Merchant` also carry a `version`, which instructions
+//!   require to equal the type's `VERSION`. A field whose zero value is not a
+//!   safe default bumps `VERSION`, and the type's migrate instruction
+//!   backfills it.
+//!
+//! The offsets below include the 8-byte discriminator and are what off-chain
+//! `memcmp` filters rely on. Borsh encodes `String` and `Option` with variable
+//! width, so offsets are only pinned up to a type's first such field.
+//! `tests/layout.ts` snapshots them against the IDL encoder.
+//!
+//! [`migrate_account`]: crate::instructions::migrate_account
+
+use anchor_lang::Space;
+use crate::state::*;
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+pub mod global_state {
+    pub const LEN: usize = 245;
+    pub const ADMIN: usize = 8;
+    pub const FEE_BPS: usize = 40;
+    pub const FEE_WALLET: usize = 42;
+    pub const PAUSED: usize = 74;
+    pub const BUMP: usize = 75;
+    pub const PENDING_ADMIN: usize = 76;
+}
+
+pub mod merchant {
+    pub const LEN: usize = 494;
+    pub const OWNER: usize = 8;
+    pub const SETTLEMENT_WALLET: usize = 40;
+    pub const SETTLEMENT_TOKEN: usize = 72;
+    pub const SWAP_ENABLED: usize = 104;
+    pub const NAME: usize = 105;
+}
+
+pub mod payment {
+    pub const LEN: usize = 314;
+    pub const PAYER: usize = 8;
+    pub const MERCHANT: usize = 40;
+    pub const AMOUNT: usize = 72;
+    pub const PAYMENT_ID: usize = 80;
+    pub const TIMESTAMP: usize = 88;
+    pub const BUMP: usize = 96;
+    pub const SANDBOX: usize = 97;
+    pub const REFUNDED_AMOUNT: usize = 98;
+    pub const STATUS: usize = 106;
+    pub const MINT: usize = 107;
+    pub const FEE: usize = 139;
+    pub const MEMO: usize = 147;
+}
+
+pub mod invoice {
+    pub const LEN: usize = 270;
+    pub const MERCHANT: usize = 8;
+    pub const INVOICE_ID: usize = 40;
+    pub const AMOUNT: usize = 48;
+    pub const MINT: usize = 56;
+    pub const EXPIRES_AT: usize = 88;
+    pub const MEMO: usize = 96;
+}
+
+pub mod payment_link {
+    pub const LEN: usize = 214;
+    pub const MERCHANT: usize = 8;
+    pub const LINK_SEED: usize = 40;
+    pub const AMOUNT: usize = 48;
+    pub const MINT: usize = 56;
+    pub const MAX_USES: usize = 88;
+    pub const USES: usize = 96;
+    pub const EXPIRES_AT: usize = 104;
+    pub const ACTIVE: usize = 112;
+    pub const BUMP: usize = 113;
+    pub const LABEL: usize = 114;
+}
+
+pub mod operator {
+    pub const LEN: usize = 106;
+    pub const MERCHANT: usize = 8;
+    pub const OPERATOR: usize = 40;
+    pub const PERMISSIONS: usize = 72;
+    pub const BUMP: usize = 73;
+}
+
+pub mod discount {
+    pub const LEN: usize = 163;
+    pub const MERCHANT: usize = 8;
+    pub const CODE_HASH: usize = 40;
+    pub const DISCOUNT_BPS: usize = 72;
+    pub const MAX_REDEMPTIONS: usize = 74;
+    pub const REDEMPTIONS: usize = 82;
+    pub const EXPIRES_AT: usize = 90;
+    pub const BUMP: usize = 98;
+}
+
+pub mod blocked {
+    pub const LEN: usize = 81;
+    pub const ADDRESS: usize = 8;
+    pub const BLOCKED_AT: usize = 40;
+    pub const BUMP: usize = 48;
+}
+
+pub mod token_fee {
+    pub const LEN: usize = 75;
+    pub const MINT: usize = 8;
+    pub const FEE_BPS: usize = 40;
+    pub const BUMP: usize = 42;
+}
+
+pub mod fee_tiers {
+    pub const LEN: usize = 135;
+    pub const TIERS: usize = 8;
+}
+
+pub mod fee_tiers_proposal {
+    pub const LEN: usize = 175;
+    pub const PROPOSER: usize = 8;
+    pub const TIERS: usize = 40;
+}
+
+pub mod feature_flags {
+    pub const LEN: usize = 49;
+    pub const ENABLED: usize = 8;
+    pub const BUMP: usize = 16;
+}
+
+pub mod vault {
+    pub const LEN: usize = 73;
+    pub const MERCHANT: usize = 8;
+    pub const BUMP: usize = 40;
+}
+
+pub mod merchant_balance {
+    pub const LEN: usize = 113;
+    pub const MERCHANT: usize = 8;
+    pub const MINT: usize = 40;
+    pub const AMOUNT: usize = 72;
+    pub const BUMP: usize = 80;
+    pub const PAYOUT_INTERVAL: usize = 81;
+    pub const LAST_PAYOUT_AT: usize = 85;
+}
+
+pub mod escrow_balance {
+    pub const LEN: usize = 121;
+    pub const MERCHANT: usize = 8;
+    pub const MINT: usize = 40;
+    pub const AMOUNT: usize = 72;
+    pub const LAST_DEPOSIT_AT: usize = 80;
+    pub const BUMP: usize = 88;
+    pub const DISPUTED_AMOUNT: usize = 89;
+    pub const LAST_CLAIM_AT: usize = 97;
+    pub const PENDING_AMOUNT: usize = 105;
+}
+
+pub mod recent_payments {
+    pub const LEN: usize = 523;
+    pub const MERCHANT: usize = 8;
+    pub const NEXT: usize = 40;
+    pub const COUNT: usize = 41;
+    pub const BUMP: usize = 42;
+    pub const ENTRIES: usize = 43;
+}
+
+pub mod notification_queue {
+    pub const LEN: usize = 1619;
+    pub const MERCHANT: usize = 8;
+    pub const NEXT: usize = 40;
+    pub const COUNT: usize = 41;
+    pub const BUMP: usize = 42;
+    pub const TOTAL: usize = 43;
+    pub const ENTRIES: usize = 51;
+}
+
+pub mod daily_volume {
+    pub const LEN: usize = 89;
+    pub const MERCHANT: usize = 8;
+    pub const DAY_BUCKET: usize = 40;
+    pub const VOLUME: usize = 48;
+    pub const BUMP: usize = 56;
+}
+
+pub mod rewards_hook {
+    pub const LEN: usize = 105;
+    pub const MERCHANT: usize = 8;
+    pub const PROGRAM: usize = 40;
+    pub const BUMP: usize = 72;
+}
+
+pub mod subscription {
+    pub const LEN: usize = 209;
+    pub const MERCHANT: usize = 8;
+    pub const SUBSCRIBER: usize = 40;
+    pub const SUB_ID: usize = 72;
+    pub const MINT: usize = 80;
+    pub const SUBSCRIBER_TOKEN_ACCOUNT: usize = 112;
+    pub const AMOUNT: usize = 144;
+    pub const INTERVAL_SECONDS: usize = 152;
+    pub const LAST_CHARGED: usize = 160;
+    pub const CHARGES_COUNT: usize = 168;
+    pub const BUMP: usize = 176;
+}
+
+pub mod payment_stream {
+    pub const LEN: usize = 185;
+    pub const MERCHANT: usize = 8;
+    pub const PAYER: usize = 40;
+    pub const STREAM_ID: usize = 72;
+    pub const MINT: usize = 80;
+    pub const RATE_PER_SECOND: usize = 112;
+    pub const DEPOSIT: usize = 120;
+    pub const WITHDRAWN: usize = 128;
+    pub const START_AT: usize = 136;
+    pub const LAST_WITHDRAWN_AT: usize = 144;
+    pub const BUMP: usize = 152;
+}
+
+pub mod split_config {
+    pub const LEN: usize = 247;
+    pub const MERCHANT: usize = 8;
+    pub const RECIPIENTS: usize = 40;
+}
+
+pub mod accepted_tokens {
+    pub const LEN: usize = 397;
+    pub const MERCHANT: usize = 8;
+    pub const MINTS: usize = 40;
+}
+
+pub mod payout_book {
+    pub const LEN: usize = 457;
+    pub const MERCHANT: usize = 8;
+    pub const ENTRIES: usize = 40;
+}
+
+pub mod fee_vault {
+    pub const LEN: usize = 41;
+    pub const BUMP: usize = 8;
+}
+
+pub mod fee_stats {
+    pub const LEN: usize = 81;
+    pub const MINT: usize = 8;
+    pub const TOTAL_FEES_COLLECTED: usize = 40;
+    pub const BUMP: usize = 48;
+}
+
+pub mod merchant_stats {
+    pub const LEN: usize = 137;
+    pub const MERCHANT: usize = 8;
+    pub const PAYMENT_COUNT: usize = 40;
+    pub const TOTAL_VOLUME: usize = 48;
+    pub const TOTAL_FEES_PAID: usize = 64;
+    pub const TOTAL_REFUNDED: usize = 80;
+    pub const LAST_PAYMENT_AT: usize = 96;
+    pub const BUMP: usize = 104;
+}
+
+pub mod global_stats {
+    pub const LEN: usize = 89;
+    pub const MERCHANTS_REGISTERED: usize = 8;
+    pub const PAYMENTS_PROCESSED: usize = 16;
+    pub const TOTAL_VOLUME: usize = 24;
+    pub const TOTAL_FEE_REVENUE: usize = 40;
+    pub const BUMP: usize = 56;
+    pub const EVENT_SEQ: usize = 57;
+}
+
+pub mod merchant_registry {
+    pub const LEN: usize = 49;
+    pub const MERCHANT_COUNT: usize = 8;
+    pub const BUMP: usize = 16;
+}
+
+pub mod merchant_index {
+    pub const LEN: usize = 81;
+    pub const INDEX: usize = 8;
+    pub const MERCHANT: usize = 16;
+    pub const BUMP: usize = 48;
+}
+
+pub mod merchant_name {
+    pub const LEN: usize = 141;
+    pub const OWNER: usize = 8;
+    pub const MERCHANT: usize = 40;
+    pub const NORMALIZED_NAME: usize = 72;
+}
+
+pub mod payer_throttle {
+    pub const LEN: usize = 113;
+    pub const MERCHANT: usize = 8;
+    pub const PAYER: usize = 40;
+    pub const LAST_PAYMENT_AT: usize = 72;
+    pub const BUMP: usize = 80;
+}
+
+pub mod settlement_route {
+    pub const LEN: usize = 137;
+    pub const MERCHANT: usize = 8;
+    pub const MINT: usize = 40;
+    pub const DESTINATION: usize = 72;
+    pub const BUMP: usize = 104;
+}
+
+pub mod merchant_transfer {
+    pub const LEN: usize = 145;
+    pub const MERCHANT: usize = 8;
+    pub const OWNER: usize = 40;
+    pub const NEW_OWNER: usize = 72;
+    pub const PROPOSED_AT: usize = 104;
+    pub const BUMP: usize = 112;
+}
+
+pub mod admin_council {
+    pub const LEN: usize = 222;
+    pub const MEMBERS: usize = 8;
+}
+
+pub mod admin_proposal {
+    pub const LEN: usize = 257;
+    pub const NONCE: usize = 8;
+    pub const PROPOSER: usize = 16;
+    pub const APPROVALS: usize = 48;
+    pub const EXPIRES_AT: usize = 49;
+    pub const EXECUTED: usize = 57;
+    pub const BUMP: usize = 58;
+    pub const ACTION: usize = 59;
+}
+
+// Adding a field without shrinking `reserved` to match fails the build here.
+const _: () = assert!(DISCRIMINATOR_LEN + GlobalState::INIT_SPACE == global_state::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Merchant::INIT_SPACE == merchant::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Payment::INIT_SPACE == payment::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Invoice::INIT_SPACE == invoice::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + PaymentLink::INIT_SPACE == payment_link::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Operator::INIT_SPACE == operator::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Discount::INIT_SPACE == discount::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Blocked::INIT_SPACE == blocked::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + TokenFeeConfig::INIT_SPACE == token_fee::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + FeeTiers::INIT_SPACE == fee_tiers::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + FeeTiersProposal::INIT_SPACE == fee_tiers_proposal::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + FeatureFlags::INIT_SPACE == feature_flags::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Vault::INIT_SPACE == vault::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + EscrowBalance::INIT_SPACE == escrow_balance::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + MerchantBalance::INIT_SPACE == merchant_balance::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + RecentPayments::INIT_SPACE == recent_payments::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + NotificationQueue::INIT_SPACE == notification_queue::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + DailyVolume::INIT_SPACE == daily_volume::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + RewardsHook::INIT_SPACE == rewards_hook::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + Subscription::INIT_SPACE == subscription::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + PaymentStream::INIT_SPACE == payment_stream::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + SplitConfig::INIT_SPACE == split_config::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + AcceptedTokens::INIT_SPACE == accepted_tokens::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + PayoutBook::INIT_SPACE == payout_book::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + FeeVault::INIT_SPACE == fee_vault::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + FeeStats::INIT_SPACE == fee_stats::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + MerchantStats::INIT_SPACE == merchant_stats::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + GlobalStats::INIT_SPACE == global_stats::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + MerchantRegistry::INIT_SPACE == merchant_registry::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + MerchantIndex::INIT_SPACE == merchant_index::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + MerchantName::INIT_SPACE == merchant_name::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + PayerThrottle::INIT_SPACE == payer_throttle::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + SettlementRoute::INIT_SPACE == settlement_route::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + MerchantTransfer::INIT_SPACE == merchant_transfer::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + AdminCouncil::INIT_SPACE == admin_council::LEN);
+const _: () = assert!(DISCRIMINATOR_LEN + AdminProposal::INIT_SPACE == admin_proposal::LEN);
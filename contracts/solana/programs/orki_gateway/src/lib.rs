@@ -1,12 +1,26 @@
+// The generated cpi:: wrappers don't carry the per-handler allows
+#![cfg_attr(feature = "cpi", allow(clippy::too_many_arguments))]
+
 use anchor_lang::prelude::*;
 
+pub mod clock;
 pub mod constants;
 pub mod errors;
 pub mod events; 
 pub mod instructions;
+#[cfg(feature = "swap")]
+pub mod jupiter_interface;
+pub mod layout;
+pub mod math;
+pub mod pda;
 pub mod state;
 
 use instructions::*;
+use state::{AdminAction, Feature, FeeRounding, FeeTier, GlobalStats, Payment, PaymentId, SettlementMode, SplitRecipient};
+
+// Deterministic time must never reach a deployed program
+#[cfg(all(feature = "test-clock", feature = "mainnet"))]
+compile_error!("the test-clock feature cannot be combined with mainnet");
 
 declare_id!("C9k2E4oE3SWB7wuCm5YwaLeYJg5DCqxBXFUDoDpzdDp9");
 
@@ -17,53 +31,652 @@ pub mod orki_gateway {
     pub fn initialize(
         ctx: Context<Initialize>, 
         fee_bps: u16,
-        fee_wallet: Pubkey
+        fee_wallet: Pubkey,
+        max_fee_bps: u16,
+        admin: Pubkey,
+        guardian: Option<Pubkey>,
     ) -> Result<()> {
-        instructions::initialize(ctx, fee_bps, fee_wallet)
+        instructions::initialize(ctx, fee_bps, fee_wallet, max_fee_bps, admin, guardian)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn register_merchant(
         ctx: Context<RegisterMerchant>,
         settlement_wallet: Pubkey,
         settlement_token: Pubkey,
         name: String,
+        sandbox: bool,
+        referrer: Option<Pubkey>,
+        referrer_bps: u16,
+        accepts_any_token: bool,
+        metadata_uri: String,
+        category: u8,
     ) -> Result<()> {
-        instructions::register_merchant(ctx, settlement_wallet, settlement_token, name)
+        instructions::register_merchant(
+            ctx,
+            settlement_wallet,
+            settlement_token,
+            name,
+            sandbox,
+            referrer,
+            referrer_bps,
+            accepts_any_token,
+            metadata_uri,
+            category,
+        )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update_merchant(
         ctx: Context<UpdateMerchant>,
         name: String,
-        new_name: Option<String>,
         settlement_wallet: Option<Pubkey>,
         settlement_token: Option<Pubkey>,
         swap_enabled: Option<bool>,
+        escrow_enabled: Option<bool>,
+        min_amount: Option<u64>,
+        max_amount: Option<u64>,
+        accepts_any_token: Option<bool>,
+        metadata_uri: Option<String>,
+        category: Option<u8>,
+    ) -> Result<()> {
+        instructions::update_merchant(
+            ctx,
+            name,
+            settlement_wallet,
+            settlement_token,
+            swap_enabled,
+            escrow_enabled,
+            min_amount,
+            max_amount,
+            accepts_any_token,
+            metadata_uri,
+            category,
+        )
+    }
+
+    pub fn add_accepted_token(ctx: Context<AddAcceptedToken>, name: String, mint: Pubkey) -> Result<()> {
+        instructions::add_accepted_token(ctx, name, mint)
+    }
+
+    pub fn remove_accepted_token(ctx: Context<RemoveAcceptedToken>, name: String, mint: Pubkey) -> Result<()> {
+        instructions::remove_accepted_token(ctx, name, mint)
+    }
+
+    pub fn add_payout_address(
+        ctx: Context<AddPayoutAddress>,
+        name: String,
+        address: Pubkey,
+        label: String,
+    ) -> Result<()> {
+        instructions::add_payout_address(ctx, name, address, label)
+    }
+
+    pub fn remove_payout_address(
+        ctx: Context<RemovePayoutAddress>,
+        name: String,
+        address: Pubkey,
+    ) -> Result<()> {
+        instructions::remove_payout_address(ctx, name, address)
+    }
+
+    pub fn set_settlement_route(
+        ctx: Context<SetSettlementRoute>,
+        name: String,
+        mint: Pubkey,
+        destination: Pubkey,
     ) -> Result<()> {
-        instructions::update_merchant(ctx, name, new_name, settlement_wallet, settlement_token, swap_enabled)
+        instructions::set_settlement_route(ctx, name, mint, destination)
+    }
+
+    pub fn clear_settlement_route(ctx: Context<ClearSettlementRoute>, name: String, mint: Pubkey) -> Result<()> {
+        instructions::clear_settlement_route(ctx, name, mint)
+    }
+
+    pub fn rename_merchant(ctx: Context<RenameMerchant>, name: String, new_name: String) -> Result<()> {
+        instructions::rename_merchant(ctx, name, new_name)
+    }
+
+    pub fn set_allow_self_payments(ctx: Context<SetAllowSelfPayments>, name: String, allowed: bool) -> Result<()> {
+        instructions::set_allow_self_payments(ctx, name, allowed)
+    }
+
+    pub fn release_previous_merchant(ctx: Context<ReleasePreviousMerchant>, name: String) -> Result<()> {
+        instructions::release_previous_merchant(ctx, name)
+    }
+
+    pub fn set_tax_rate(ctx: Context<SetTaxRate>, name: String, tax_bps: u16) -> Result<()> {
+        instructions::set_tax_rate(ctx, name, tax_bps)
+    }
+
+    pub fn set_confirmation_message(ctx: Context<SetConfirmationMessage>, name: String, message: String) -> Result<()> {
+        instructions::set_confirmation_message(ctx, name, message)
+    }
+
+    pub fn set_payer_interval(ctx: Context<SetPayerInterval>, name: String, min_interval_per_payer: u32) -> Result<()> {
+        instructions::set_payer_interval(ctx, name, min_interval_per_payer)
+    }
+
+    pub fn close_payer_throttle(ctx: Context<ClosePayerThrottle>) -> Result<()> {
+        instructions::close_payer_throttle(ctx)
+    }
+
+    pub fn transfer_merchant_ownership(
+        ctx: Context<TransferMerchantOwnership>,
+        name: String,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        instructions::transfer_merchant_ownership(ctx, name, new_owner)
+    }
+
+    pub fn cancel_merchant_ownership_transfer(ctx: Context<CancelMerchantOwnershipTransfer>, name: String) -> Result<()> {
+        instructions::cancel_merchant_ownership_transfer(ctx, name)
+    }
+
+    pub fn accept_merchant_ownership(ctx: Context<AcceptMerchantOwnership>, name: String) -> Result<()> {
+        instructions::accept_merchant_ownership(ctx, name)
+    }
+
+    pub fn close_merchant(ctx: Context<CloseMerchant>, name: String) -> Result<()> {
+        instructions::close_merchant(ctx, name)
+    }
+
+    pub fn ping_merchant(ctx: Context<PingMerchant>, name: String, nonce: u64) -> Result<()> {
+        instructions::ping_merchant(ctx, name, nonce)
+    }
+
+    pub fn grant_operator(
+        ctx: Context<GrantOperator>,
+        name: String,
+        operator: Pubkey,
+        permissions: u8,
+    ) -> Result<()> {
+        instructions::grant_operator(ctx, name, operator, permissions)
+    }
+
+    pub fn revoke_operator(ctx: Context<RevokeOperator>, name: String) -> Result<()> {
+        instructions::revoke_operator(ctx, name)
+    }
+
+    pub fn init_recent_payments(ctx: Context<InitRecentPayments>, name: String) -> Result<()> {
+        instructions::init_recent_payments(ctx, name)
+    }
+
+    pub fn init_notification_queue(ctx: Context<InitNotificationQueue>, name: String) -> Result<()> {
+        instructions::init_notification_queue(ctx, name)
+    }
+
+    pub fn clear_notifications(ctx: Context<ClearNotifications>, name: String) -> Result<()> {
+        instructions::clear_notifications(ctx, name)
+    }
+
+    pub fn init_merchant_stats(ctx: Context<InitMerchantStats>, name: String) -> Result<()> {
+        instructions::init_merchant_stats(ctx, name)
+    }
+
+    pub fn index_merchant(ctx: Context<IndexMerchant>, name: String) -> Result<()> {
+        instructions::index_merchant(ctx, name)
+    }
+
+    pub fn set_split_config(
+        ctx: Context<SetSplitConfig>,
+        name: String,
+        recipients: Vec<SplitRecipient>,
+    ) -> Result<()> {
+        instructions::set_split_config(ctx, name, recipients)
+    }
+
+    pub fn clear_split_config(ctx: Context<ClearSplitConfig>, name: String) -> Result<()> {
+        instructions::clear_split_config(ctx, name)
+    }
+
+    pub fn set_rewards_hook(ctx: Context<SetRewardsHook>, name: String, required: bool) -> Result<()> {
+        instructions::set_rewards_hook(ctx, name, required)
+    }
+
+    pub fn clear_rewards_hook(ctx: Context<ClearRewardsHook>, name: String) -> Result<()> {
+        instructions::clear_rewards_hook(ctx, name)
+    }
+
+    pub fn propose_fee(ctx: Context<AdminAuth>, new_fee_bps: u16) -> Result<()> {
+        instructions::propose_fee(ctx, new_fee_bps)
+    }
+
+    pub fn apply_fee(ctx: Context<ApplyFee>) -> Result<()> {
+        instructions::apply_fee(ctx)
     }
 
-    pub fn set_fee(ctx: Context<AdminAuth>, new_fee_bps: u16) -> Result<()> {
-        instructions::set_fee(ctx, new_fee_bps)
+    pub fn cancel_fee_proposal(ctx: Context<AdminAuth>) -> Result<()> {
+        instructions::cancel_fee_proposal(ctx)
+    }
+
+    pub fn set_fee_timelock(ctx: Context<AdminAuth>, new_timelock_seconds: i64) -> Result<()> {
+        instructions::set_fee_timelock(ctx, new_timelock_seconds)
+    }
+
+    pub fn reduce_max_fee(ctx: Context<AdminAuth>, new_max_fee_bps: u16) -> Result<()> {
+        instructions::reduce_max_fee(ctx, new_max_fee_bps)
     }
 
     pub fn set_fee_wallet(ctx: Context<AdminAuth>, new_fee_wallet: Pubkey) -> Result<()> {
         instructions::set_fee_wallet(ctx, new_fee_wallet)
     }
 
-    pub fn set_paused(ctx: Context<AdminAuth>, paused: bool) -> Result<()> {
+    pub fn set_merchant_fee(
+        ctx: Context<MerchantFeeAuth>,
+        name: String,
+        fee_bps_override: Option<u16>,
+    ) -> Result<()> {
+        instructions::set_merchant_fee(ctx, name, fee_bps_override)
+    }
+
+    pub fn set_merchant_daily_limit(
+        ctx: Context<SetMerchantDailyLimit>,
+        name: String,
+        daily_limit: u64,
+    ) -> Result<()> {
+        instructions::set_merchant_daily_limit(ctx, name, daily_limit)
+    }
+
+    pub fn set_merchant_fee_exempt(
+        ctx: Context<MerchantFeeAuth>,
+        name: String,
+        fee_exempt: bool,
+    ) -> Result<()> {
+        instructions::set_merchant_fee_exempt(ctx, name, fee_exempt)
+    }
+
+    pub fn set_merchant_referrer(
+        ctx: Context<MerchantAdminAuth>,
+        name: String,
+        referrer: Option<Pubkey>,
+        referrer_bps: u16,
+    ) -> Result<()> {
+        instructions::set_merchant_referrer(ctx, name, referrer, referrer_bps)
+    }
+
+    pub fn freeze_merchant(ctx: Context<MerchantAdminAuth>, name: String, reason: u8) -> Result<()> {
+        instructions::freeze_merchant(ctx, name, reason)
+    }
+
+    pub fn unfreeze_merchant(ctx: Context<MerchantAdminAuth>, name: String, reason: u8) -> Result<()> {
+        instructions::unfreeze_merchant(ctx, name, reason)
+    }
+
+    pub fn set_merchant_active(ctx: Context<SetMerchantActive>, name: String, active: bool) -> Result<()> {
+        instructions::set_merchant_active(ctx, name, active)
+    }
+
+    pub fn set_escrow_release_delay(ctx: Context<AdminAuth>, new_delay: i64) -> Result<()> {
+        instructions::set_escrow_release_delay(ctx, new_delay)
+    }
+
+    pub fn set_payment_retention(ctx: Context<AdminAuth>, new_retention: i64) -> Result<()> {
+        instructions::set_payment_retention(ctx, new_retention)
+    }
+
+    pub fn set_finality_slots(ctx: Context<AdminAuth>, new_slots: u64) -> Result<()> {
+        instructions::set_finality_slots(ctx, new_slots)
+    }
+
+    pub fn set_fee_rounding(ctx: Context<AdminAuth>, new_rounding: FeeRounding) -> Result<()> {
+        instructions::set_fee_rounding(ctx, new_rounding)
+    }
+
+    pub fn set_crank_reward(ctx: Context<AdminAuth>, new_reward: u64) -> Result<()> {
+        instructions::set_crank_reward(ctx, new_reward)
+    }
+
+    pub fn set_authorization_expiry(ctx: Context<AdminAuth>, new_expiry: i64) -> Result<()> {
+        instructions::set_authorization_expiry(ctx, new_expiry)
+    }
+
+    pub fn set_guardian(ctx: Context<AdminAuth>, new_guardian: Pubkey) -> Result<()> {
+        instructions::set_guardian(ctx, new_guardian)
+    }
+
+    pub fn set_arbiter(ctx: Context<AdminAuth>, new_arbiter: Pubkey) -> Result<()> {
+        instructions::set_arbiter(ctx, new_arbiter)
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
         instructions::set_paused(ctx, paused)
     }
 
-    pub fn update_admin(ctx: Context<AdminAuth>, new_admin: Pubkey) -> Result<()> {
-        instructions::update_admin(ctx, new_admin)
+    pub fn block_address(ctx: Context<BlockAddress>, address: Pubkey) -> Result<()> {
+        instructions::block_address(ctx, address)
+    }
+
+    pub fn unblock_address(ctx: Context<UnblockAddress>) -> Result<()> {
+        instructions::unblock_address(ctx)
+    }
+
+    pub fn set_token_fee(ctx: Context<SetTokenFee>, mint: Pubkey, fee_bps: u16) -> Result<()> {
+        instructions::set_token_fee(ctx, mint, fee_bps)
+    }
+
+    pub fn apply_token_fee(ctx: Context<ApplyTokenFee>) -> Result<()> {
+        instructions::apply_token_fee(ctx)
+    }
+
+    pub fn clear_token_fee(ctx: Context<ClearTokenFee>) -> Result<()> {
+        instructions::clear_token_fee(ctx)
+    }
+
+    pub fn set_fee_tiers(ctx: Context<SetFeeTiers>, tiers: Vec<FeeTier>) -> Result<()> {
+        instructions::set_fee_tiers(ctx, tiers)
+    }
+
+    pub fn apply_fee_tiers(ctx: Context<ApplyFeeTiers>) -> Result<()> {
+        instructions::apply_fee_tiers(ctx)
+    }
+
+    pub fn cancel_fee_tiers_proposal(ctx: Context<CancelFeeTiersProposal>) -> Result<()> {
+        instructions::cancel_fee_tiers_proposal(ctx)
+    }
+
+    pub fn create_admin_council(ctx: Context<CreateAdminCouncil>, members: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        instructions::create_admin_council(ctx, members, threshold)
     }
 
-    pub fn process_payment(
-        ctx: Context<ProcessPayment>,
+    pub fn propose_admin_action(ctx: Context<ProposeAdminAction>, nonce: u64, action: AdminAction) -> Result<()> {
+        instructions::propose_admin_action(ctx, nonce, action)
+    }
+
+    pub fn approve_admin_action(ctx: Context<ApproveAdminAction>) -> Result<()> {
+        instructions::approve_admin_action(ctx)
+    }
+
+    pub fn propose_admin(ctx: Context<AdminAuth>, new_admin: Pubkey) -> Result<()> {
+        instructions::propose_admin(ctx, new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        instructions::accept_admin(ctx)
+    }
+
+    pub fn cancel_admin_transfer(ctx: Context<AdminAuth>) -> Result<()> {
+        instructions::cancel_admin_transfer(ctx)
+    }
+
+    pub fn set_feature(ctx: Context<SetFeature>, feature: Feature, enabled: bool) -> Result<()> {
+        instructions::set_feature(ctx, feature, enabled)
+    }
+
+    pub fn migrate_global_state(ctx: Context<MigrateGlobalState>) -> Result<()> {
+        instructions::migrate_global_state(ctx)
+    }
+
+    pub fn migrate_merchant(ctx: Context<MigrateMerchant>, name: String) -> Result<()> {
+        instructions::migrate_merchant(ctx, name)
+    }
+
+    pub fn upgrade_merchant_account(ctx: Context<UpgradeMerchantAccount>, name: String) -> Result<()> {
+        instructions::upgrade_merchant_account(ctx, name)
+    }
+
+    pub fn upgrade_payment_account(ctx: Context<UpgradePaymentAccount>) -> Result<()> {
+        instructions::upgrade_payment_account(ctx)
+    }
+
+    pub fn init_global_stats(ctx: Context<InitGlobalStats>) -> Result<()> {
+        instructions::init_global_stats(ctx)
+    }
+
+    pub fn get_stats(ctx: Context<GetStats>) -> Result<GlobalStats> {
+        instructions::get_stats(ctx)
+    }
+
+    pub fn quote_payment(
+        ctx: Context<QuotePayment>,
+        amount: u64,
+        name: String,
+        owner: Pubkey,
+    ) -> Result<PaymentQuote> {
+        instructions::quote_payment(ctx, amount, name, owner)
+    }
+
+    pub fn verify_payment(ctx: Context<VerifyPayment>, payment_id: u64, min_amount: u64) -> Result<Payment> {
+        instructions::verify_payment(ctx, payment_id, min_amount)
+    }
+
+    pub fn finalize_payment(ctx: Context<FinalizePayment>, payment_id: u64) -> Result<()> {
+        instructions::finalize_payment(ctx, payment_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessPayment<'info>>,
+        amount: u64,
+        payment_id: u64,
+        name: String,
+        memo: Option<String>,
+        tip: Option<u64>,
+        discount_code: Option<String>,
+        valid_until: i64,
+    ) -> Result<PaymentReceipt> {
+        instructions::process_payment(ctx, amount, PaymentId::Sequence(payment_id), name, memo, tip, discount_code, valid_until)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_payment_v2<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessPayment<'info>>,
+        amount: u64,
+        order_id: [u8; 32],
+        name: String,
+        memo: Option<String>,
+        tip: Option<u64>,
+        discount_code: Option<String>,
+        valid_until: i64,
+    ) -> Result<PaymentReceipt> {
+        instructions::process_payment_v2(ctx, amount, order_id, name, memo, tip, discount_code, valid_until)
+    }
+
+    pub fn process_payment_delegated(
+        ctx: Context<ProcessPaymentDelegated>,
+        amount: u64,
+        payment_id: u64,
+        name: String,
+        expires_at: i64,
+    ) -> Result<()> {
+        instructions::process_payment_delegated(ctx, amount, payment_id, name, expires_at)
+    }
+
+    #[cfg(feature = "swap")]
+    pub fn process_payment_with_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessPaymentWithSwap<'info>>,
+        amount: u64,
+        payment_id: u64,
+        name: String,
+        min_amount_out: u64,
+        route_data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::process_payment_with_swap(ctx, amount, payment_id, name, min_amount_out, route_data)
+    }
+
+    #[cfg(feature = "receipts")]
+    pub fn mint_receipt(ctx: Context<MintReceipt>) -> Result<()> {
+        instructions::mint_receipt(ctx)
+    }
+
+    pub fn refund_partial(
+        ctx: Context<RefundPartial>,
+        name: String,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::refund_partial(ctx, name, amount)
+    }
+
+    pub fn close_payment(ctx: Context<ClosePayment>) -> Result<()> {
+        instructions::close_payment(ctx)
+    }
+
+    pub fn open_escrow(ctx: Context<OpenEscrow>, name: String, mint: Pubkey) -> Result<()> {
+        instructions::open_escrow(ctx, name, mint)
+    }
+
+    pub fn claim_settlement(ctx: Context<ClaimSettlement>, name: String) -> Result<()> {
+        instructions::claim_settlement(ctx, name)
+    }
+
+    pub fn set_settlement_mode(ctx: Context<SetSettlementMode>, name: String, settlement_mode: SettlementMode) -> Result<()> {
+        instructions::set_settlement_mode(ctx, name, settlement_mode)
+    }
+
+    pub fn open_merchant_balance(ctx: Context<OpenMerchantBalance>, name: String, mint: Pubkey) -> Result<()> {
+        instructions::open_merchant_balance(ctx, name, mint)
+    }
+
+    pub fn withdraw_balance(ctx: Context<WithdrawBalance>, name: String, mint: Pubkey, amount: u64) -> Result<()> {
+        instructions::withdraw_balance(ctx, name, mint, amount)
+    }
+
+    pub fn set_payout_interval(
+        ctx: Context<SetPayoutInterval>,
+        name: String,
+        mint: Pubkey,
+        payout_interval: u32,
+    ) -> Result<()> {
+        instructions::set_payout_interval(ctx, name, mint, payout_interval)
+    }
+
+    pub fn crank_payout(ctx: Context<CrankPayout>, name: String, mint: Pubkey) -> Result<()> {
+        instructions::crank_payout(ctx, name, mint)
+    }
+
+    pub fn open_dispute(ctx: Context<OpenDispute>) -> Result<()> {
+        instructions::open_dispute(ctx)
+    }
+
+    pub fn resolve_dispute(ctx: Context<ResolveDispute>, refund: bool) -> Result<()> {
+        instructions::resolve_dispute(ctx, refund)
+    }
+
+    pub fn open_fee_stats(ctx: Context<OpenFeeStats>, mint: Pubkey) -> Result<()> {
+        instructions::open_fee_stats(ctx, mint)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64, mint: Pubkey) -> Result<()> {
+        instructions::withdraw_fees(ctx, amount, mint)
+    }
+
+    pub fn create_invoice(
+        ctx: Context<CreateInvoice>,
+        name: String,
+        invoice_id: u64,
+        amount: u64,
+        mint: Pubkey,
+        expires_at: i64,
+        memo: String,
+    ) -> Result<()> {
+        instructions::create_invoice(ctx, name, invoice_id, amount, mint, expires_at, memo)
+    }
+
+    pub fn pay_invoice(ctx: Context<PayInvoice>, name: String) -> Result<()> {
+        instructions::pay_invoice(ctx, name)
+    }
+
+    pub fn cancel_invoice(ctx: Context<CancelInvoice>, name: String) -> Result<()> {
+        instructions::cancel_invoice(ctx, name)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_link(
+        ctx: Context<CreateLink>,
+        name: String,
+        link_seed: u64,
+        amount: u64,
+        mint: Pubkey,
+        label: String,
+        max_uses: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        instructions::create_link(ctx, name, link_seed, amount, mint, label, max_uses, expires_at)
+    }
+
+    pub fn pay_link<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessPayment<'info>>,
+        name: String,
+    ) -> Result<PaymentReceipt> {
+        instructions::pay_link(ctx, name)
+    }
+
+    pub fn deactivate_link(ctx: Context<DeactivateLink>, name: String) -> Result<()> {
+        instructions::deactivate_link(ctx, name)
+    }
+
+    pub fn create_discount(
+        ctx: Context<CreateDiscount>,
+        name: String,
+        code_hash: [u8; 32],
+        discount_bps: u16,
+        max_redemptions: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        instructions::create_discount(ctx, name, code_hash, discount_bps, max_redemptions, expires_at)
+    }
+
+    pub fn delete_discount(ctx: Context<DeleteDiscount>, name: String) -> Result<()> {
+        instructions::delete_discount(ctx, name)
+    }
+
+    pub fn authorize_payment(
+        ctx: Context<AuthorizePayment>,
         amount: u64,
         payment_id: u64,
         name: String,
     ) -> Result<()> {
-        instructions::process_payment(ctx, amount, payment_id, name)
+        instructions::authorize_payment(ctx, amount, payment_id, name)
+    }
+
+    pub fn capture_payment(ctx: Context<CapturePayment>, name: String) -> Result<()> {
+        instructions::capture_payment(ctx, name)
+    }
+
+    pub fn void_payment(ctx: Context<VoidPayment>, name: String) -> Result<()> {
+        instructions::void_payment(ctx, name)
+    }
+
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        name: String,
+        sub_id: u64,
+        amount: u64,
+        interval_seconds: i64,
+        mint: Pubkey,
+    ) -> Result<()> {
+        instructions::create_subscription(ctx, name, sub_id, amount, interval_seconds, mint)
+    }
+
+    pub fn charge_subscription(ctx: Context<ChargeSubscription>, name: String) -> Result<()> {
+        instructions::charge_subscription(ctx, name)
+    }
+
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        instructions::cancel_subscription(ctx)
+    }
+
+    pub fn create_stream(
+        ctx: Context<CreateStream>,
+        name: String,
+        stream_id: u64,
+        rate_per_second: u64,
+        mint: Pubkey,
+        deposit: u64,
+    ) -> Result<()> {
+        instructions::create_stream(ctx, name, stream_id, rate_per_second, mint, deposit)
+    }
+
+    pub fn withdraw_stream(ctx: Context<WithdrawStream>, name: String) -> Result<()> {
+        instructions::withdraw_stream(ctx, name)
+    }
+
+    pub fn cancel_stream(ctx: Context<CancelStream>, name: String) -> Result<()> {
+        instructions::cancel_stream(ctx, name)
+    }
+
+    #[cfg(feature = "test-clock")]
+    pub fn set_test_clock(ctx: Context<SetTestClock>, unix_timestamp: i64) -> Result<()> {
+        instructions::set_test_clock(ctx, unix_timestamp)
     }
 }
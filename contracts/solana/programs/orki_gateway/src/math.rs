@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+use crate::state::FeeRounding;
+use crate::constants::BPS_DENOMINATOR;
+
+/// `bps` basis points of `amount`, rounded down. The product is taken in u128
+/// so no u64 amount can overflow it; the result only exceeds u64 for rates
+/// above 100%, which fail instead of truncating.
+pub fn bps_share(amount: u64, bps: u16) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_div(BPS_DENOMINATOR as u128)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok(u64::try_from(share).map_err(|_| ErrorCode::CalculationError)?)
+}
+
+/// Splits `amount` into `(fee, merchant_amount)` at `fee_bps`, rounding the
+/// fee as GlobalState::fee_rounding says. The merchant gets the remainder, so
+/// the two always add back up to `amount`; even rounded up, the fee reaches
+/// `amount` only at 100%. Every payment path charges its fee through this.
+pub fn calc_fee(amount: u64, fee_bps: u16, rounding: FeeRounding) -> Result<(u64, u64)> {
+    let fee = match rounding {
+        FeeRounding::Floor => bps_share(amount, fee_bps)?,
+        FeeRounding::Ceil => {
+            let fee = (amount as u128)
+                .checked_mul(fee_bps as u128)
+                .and_then(|product| product.checked_add(BPS_DENOMINATOR as u128 - 1))
+                .ok_or(ErrorCode::CalculationError)?
+                / BPS_DENOMINATOR as u128;
+            u64::try_from(fee).map_err(|_| ErrorCode::CalculationError)?
+        }
+    };
+    let merchant_amount = amount
+        .checked_sub(fee)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok((fee, merchant_amount))
+}
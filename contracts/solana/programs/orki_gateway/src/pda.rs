@@ -0,0 +1,192 @@
+//! Address derivations for the gateway's PDAs, for off-chain clients and
+//! programs that CPI into the gateway. Seeds match the `#[account]` constraints.
+
+use anchor_lang::prelude::*;
+use crate::state::{MerchantName, PaymentLink};
+use crate::constants::{ACCEPTED_TOKENS_SEED, ADMIN_COUNCIL_SEED, ADMIN_PROPOSAL_SEED, BLOCKED_SEED, DAILY_VOLUME_SEED, DISCOUNT_SEED, ESCROW_BALANCE_SEED, FEATURE_FLAGS_SEED, FEE_STATS_SEED, FEE_TIERS_PROPOSAL_SEED, FEE_TIERS_SEED, FEE_VAULT_SEED, GLOBAL_STATE_SEED, GLOBAL_STATS_SEED, INVOICE_SEED, MERCHANT_BALANCE_SEED, MERCHANT_INDEX_SEED, MERCHANT_NAME_SEED, MERCHANT_REGISTRY_SEED, MERCHANT_SEED, MERCHANT_STATS_SEED, MERCHANT_TRANSFER_SEED, NOTIFICATION_QUEUE_SEED, OPERATOR_SEED, PAYER_THROTTLE_SEED, PAYMENT_DELEGATE_SEED, PAYMENT_LINK_SEED, PAYMENT_SEED, PAYOUT_BOOK_SEED, RECEIPT_AUTHORITY_SEED, RECEIPT_MINT_SEED, RECENT_PAYMENTS_SEED, REWARDS_HOOK_SEED, SETTLEMENT_ROUTE_SEED, SPLIT_CONFIG_SEED, STREAM_SEED, SUBSCRIPTION_SEED, TOKEN_FEE_SEED, VAULT_SEED};
+
+pub fn find_global_state_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GLOBAL_STATE_SEED], &crate::ID)
+}
+
+pub fn find_global_stats_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GLOBAL_STATS_SEED], &crate::ID)
+}
+
+pub fn find_admin_council_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ADMIN_COUNCIL_SEED], &crate::ID)
+}
+
+pub fn find_admin_proposal_address(nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ADMIN_PROPOSAL_SEED, &nonce.to_le_bytes()], &crate::ID)
+}
+
+pub fn find_merchant_address(owner: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MERCHANT_SEED, owner.as_ref(), name.as_bytes()], &crate::ID)
+}
+
+/// The owner's claim on `name` in any casing.
+pub fn find_merchant_name_address(owner: &Pubkey, name: &str) -> (Pubkey, u8) {
+    let normalized_name = MerchantName::normalize(name);
+    Pubkey::find_program_address(&[MERCHANT_NAME_SEED, owner.as_ref(), normalized_name.as_bytes()], &crate::ID)
+}
+
+pub fn find_merchant_registry_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MERCHANT_REGISTRY_SEED], &crate::ID)
+}
+
+/// Directory slot `index`, counting from 1.
+pub fn find_merchant_index_address(index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MERCHANT_INDEX_SEED, &index.to_le_bytes()], &crate::ID)
+}
+
+pub fn find_merchant_transfer_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MERCHANT_TRANSFER_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_merchant_stats_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MERCHANT_STATS_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_payment_address(payer: &Pubkey, payment_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYMENT_SEED, payer.as_ref(), &payment_id.to_le_bytes()], &crate::ID)
+}
+
+/// A process_payment_v2 record, keyed by a 32-byte order id.
+pub fn find_order_payment_address(payer: &Pubkey, order_id: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYMENT_SEED, payer.as_ref(), order_id], &crate::ID)
+}
+
+/// Payment recorded by pay_link for the link's `uses` count at the time,
+/// keyed like a v2 record by [`PaymentLink::payment_order_id`].
+pub fn find_link_payment_address(link: &Pubkey, payer: &Pubkey, use_index: u64) -> (Pubkey, u8) {
+    find_order_payment_address(payer, &PaymentLink::payment_order_id(link, use_index))
+}
+
+pub fn find_feature_flags_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEATURE_FLAGS_SEED], &crate::ID)
+}
+
+pub fn find_operator_address(merchant: &Pubkey, operator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[OPERATOR_SEED, merchant.as_ref(), operator.as_ref()], &crate::ID)
+}
+
+pub fn find_payout_book_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYOUT_BOOK_SEED, merchant.as_ref()], &crate::ID)
+}
+
+/// The merchant's route for `mint`, Pubkey::default() for SOL.
+pub fn find_settlement_route_address(merchant: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SETTLEMENT_ROUTE_SEED, merchant.as_ref(), mint.as_ref()], &crate::ID)
+}
+
+pub fn find_accepted_tokens_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ACCEPTED_TOKENS_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_split_config_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SPLIT_CONFIG_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_recent_payments_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECENT_PAYMENTS_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_notification_queue_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NOTIFICATION_QUEUE_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_daily_volume_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DAILY_VOLUME_SEED, merchant.as_ref()], &crate::ID)
+}
+
+/// When `payer` last paid `merchant`, for merchants with a per-payer interval.
+pub fn find_payer_throttle_address(merchant: &Pubkey, payer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYER_THROTTLE_SEED, merchant.as_ref(), payer.as_ref()], &crate::ID)
+}
+
+pub fn find_rewards_hook_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REWARDS_HOOK_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_vault_address(merchant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, merchant.as_ref()], &crate::ID)
+}
+
+pub fn find_merchant_balance_address(merchant: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MERCHANT_BALANCE_SEED, merchant.as_ref(), mint.as_ref()], &crate::ID)
+}
+
+pub fn find_escrow_balance_address(merchant: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ESCROW_BALANCE_SEED, merchant.as_ref(), mint.as_ref()], &crate::ID)
+}
+
+pub fn find_invoice_address(merchant: &Pubkey, invoice_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[INVOICE_SEED, merchant.as_ref(), &invoice_id.to_le_bytes()], &crate::ID)
+}
+
+pub fn find_link_address(merchant: &Pubkey, link_seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYMENT_LINK_SEED, merchant.as_ref(), &link_seed.to_le_bytes()], &crate::ID)
+}
+
+pub fn find_blocked_address(address: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BLOCKED_SEED, address.as_ref()], &crate::ID)
+}
+
+pub fn find_token_fee_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_FEE_SEED, mint.as_ref()], &crate::ID)
+}
+
+pub fn find_fee_tiers_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_TIERS_SEED], &crate::ID)
+}
+
+pub fn find_fee_tiers_proposal_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_TIERS_PROPOSAL_SEED], &crate::ID)
+}
+
+/// Token-2022 mint of the receipt for `payment`; see `mint_receipt`.
+pub fn find_receipt_mint_address(payment: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECEIPT_MINT_SEED, payment.as_ref()], &crate::ID)
+}
+
+pub fn find_receipt_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECEIPT_AUTHORITY_SEED], &crate::ID)
+}
+
+pub fn find_discount_address(merchant: &Pubkey, code_hash: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DISCOUNT_SEED, merchant.as_ref(), code_hash.as_ref()], &crate::ID)
+}
+
+pub fn find_subscription_address(merchant: &Pubkey, subscriber: &Pubkey, sub_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SUBSCRIPTION_SEED, merchant.as_ref(), subscriber.as_ref(), &sub_id.to_le_bytes()],
+        &crate::ID,
+    )
+}
+
+pub fn find_stream_address(merchant: &Pubkey, payer: &Pubkey, stream_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[STREAM_SEED, merchant.as_ref(), payer.as_ref(), &stream_id.to_le_bytes()],
+        &crate::ID,
+    )
+}
+
+/// The delegate payers approve on their token account for process_payment_delegated.
+pub fn find_payment_delegate_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYMENT_DELEGATE_SEED], &crate::ID)
+}
+
+pub fn find_fee_vault_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_VAULT_SEED], &crate::ID)
+}
+
+/// Fee stats are keyed by mint; SOL uses the default key.
+pub fn find_fee_stats_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_STATS_SEED, mint.as_ref()], &crate::ID)
+}
+
+/// Signer for `#[event_cpi]` instructions; passed as `event_authority`.
+pub fn find_event_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &crate::ID)
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+pub const MAX_ACCEPTED_TOKENS: usize = 10;
+
+#[account]
+#[derive(InitSpace)]
+pub struct AcceptedTokens {
+    pub merchant: Pubkey,
+    // Pubkey::default() stands for native SOL
+    #[max_len(10)]
+    pub mints: Vec<Pubkey>,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl AcceptedTokens {
+    pub fn accepts(&self, mint: &Pubkey) -> bool {
+        self.mints.contains(mint)
+    }
+}
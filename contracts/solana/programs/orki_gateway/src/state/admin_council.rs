@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+use crate::state::FeeTier;
+
+pub const MAX_COUNCIL_MEMBERS: usize = 5;
+
+// Multisig that takes over the AdminAction powers from the single admin once
+// create_admin_council runs; a proposal passes at `threshold` approvals
+#[account]
+#[derive(InitSpace)]
+pub struct AdminCouncil {
+    #[max_len(5)]
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    // Nonce the next proposal must be opened with; each proposal's PDA is
+    // keyed by its nonce, so none can be opened twice
+    pub next_nonce: u64,
+    // Proposals below this nonce were opened under an earlier membership and
+    // can no longer pass
+    pub min_nonce: u64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl AdminCouncil {
+    pub fn validate(members: &[Pubkey], threshold: u8) -> Result<()> {
+        require!(
+            !members.is_empty() && members.len() <= MAX_COUNCIL_MEMBERS,
+            ErrorCode::InvalidCouncil
+        );
+        require!(
+            threshold > 0 && threshold as usize <= members.len(),
+            ErrorCode::InvalidCouncil
+        );
+        require!(members.iter().all(|m| *m != Pubkey::default()), ErrorCode::InvalidCouncil);
+        require!(
+            members.iter().enumerate().all(|(i, m)| !members[..i].contains(m)),
+            ErrorCode::InvalidCouncil
+        );
+        Ok(())
+    }
+
+    /// Position of `member` in `members`, which is its bit in
+    /// `AdminProposal::approvals`.
+    pub fn member_index(&self, member: &Pubkey) -> Result<usize> {
+        self.members
+            .iter()
+            .position(|m| m == member)
+            .ok_or_else(|| error!(ErrorCode::NotCouncilMember))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, InitSpace)]
+pub enum AdminAction {
+    // Queued like propose_fee, so fee_timelock_seconds still applies
+    SetFee { new_fee_bps: u16 },
+    SetFeeWallet { new_fee_wallet: Pubkey },
+    SetPaused { paused: bool },
+    // Replaces the membership and voids every proposal still open
+    UpdateCouncil {
+        #[max_len(5)]
+        members: Vec<Pubkey>,
+        threshold: u8,
+    },
+    ProposeAdmin { new_admin: Pubkey },
+    SetFeeTimelock { new_timelock_seconds: i64 },
+    // The rest touch accounts the proposal does not carry: executing only
+    // approves them, and the matching instruction consumes the proposal once
+    WithdrawFees { mint: Pubkey, amount: u64, destination: Pubkey },
+    SetTokenFee { mint: Pubkey, fee_bps: u16 },
+    SetFeeTiers {
+        #[max_len(5)]
+        tiers: Vec<FeeTier>,
+    },
+    SetMerchantFee { merchant: Pubkey, fee_bps_override: Option<u16> },
+    SetMerchantFeeExempt { merchant: Pubkey, fee_exempt: bool },
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AdminProposal {
+    pub nonce: u64,
+    pub proposer: Pubkey,
+    // Bit i is set once the council's members[i] approved; proposing counts
+    // as the proposer's approval
+    pub approvals: u8,
+    pub expires_at: i64,
+    pub executed: bool,
+    pub bump: u8,
+    pub action: AdminAction,
+    // Set once a deferred action's instruction has run, so one approval
+    // cannot be replayed
+    pub consumed: bool,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 31],
+}
+
+impl AdminProposal {
+    pub fn approval_count(&self) -> u8 {
+        self.approvals.count_ones() as u8
+    }
+}
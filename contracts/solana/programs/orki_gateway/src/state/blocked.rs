@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+// Exists while `address` is barred from paying; closed on unblock
+#[account]
+#[derive(InitSpace)]
+pub struct Blocked {
+    pub address: Pubkey,
+    pub blocked_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl Blocked {
+    /// Whether `entry`, already constrained to this payer's blocklist PDA,
+    /// holds a live entry. Lamports sent to the empty address leave it owned
+    /// by the system program, so only a program-owned account counts.
+    pub fn is_blocked(entry: &AccountInfo) -> bool {
+        entry.owner == &crate::ID && !entry.data_is_empty()
+    }
+}
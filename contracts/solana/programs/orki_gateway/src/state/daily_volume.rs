@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+// What a merchant under a daily limit has received in the current UTC day.
+// Opened by set_merchant_daily_limit; process_payment adds to it.
+#[account]
+#[derive(InitSpace)]
+pub struct DailyVolume {
+    pub merchant: Pubkey,
+    // Days since the Unix epoch that `volume` counts
+    pub day_bucket: i64,
+    pub volume: u64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl DailyVolume {
+    /// Adds `amount` to the day `now` falls in, starting the count over when
+    /// the day has changed, and fails if that takes it past `limit`.
+    pub fn record(&mut self, now: i64, amount: u64, limit: u64) -> Result<()> {
+        let day = now.div_euclid(SECONDS_PER_DAY);
+        if day != self.day_bucket {
+            self.day_bucket = day;
+            self.volume = 0;
+        }
+        let volume = self
+            .volume
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(volume <= limit, ErrorCode::DailyLimitExceeded);
+        self.volume = volume;
+        Ok(())
+    }
+}
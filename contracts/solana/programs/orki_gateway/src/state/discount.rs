@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hash;
+use crate::math::calc_fee;
+use crate::state::FeeRounding;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Discount {
+    pub merchant: Pubkey,
+    // SHA-256 of the promo code, so the code itself stays off-chain until used
+    pub code_hash: [u8; 32],
+    pub discount_bps: u16,
+    // 0 means the code can be redeemed any number of times
+    pub max_redemptions: u64,
+    pub redemptions: u64,
+    // 0 means the code never expires
+    pub expires_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 64],
+}
+
+impl Discount {
+    pub fn hash_code(code: &str) -> [u8; 32] {
+        hash(code.as_bytes()).to_bytes()
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.max_redemptions != 0 && self.redemptions >= self.max_redemptions
+    }
+
+    /// `amount` less the discount, rounded so the merchant keeps any dust.
+    pub fn apply(&self, amount: u64) -> Result<u64> {
+        let (_, discounted) = calc_fee(amount, self.discount_bps, FeeRounding::Floor)?;
+        Ok(discounted)
+    }
+}
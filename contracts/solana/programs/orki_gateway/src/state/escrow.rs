@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+// Program-owned vault per merchant. Holds escrowed SOL above its rent
+// reserve and is the authority of the vault token accounts.
+#[account]
+#[derive(InitSpace)]
+pub struct Vault {
+    pub merchant: Pubkey,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct EscrowBalance {
+    pub merchant: Pubkey,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub last_deposit_at: i64,
+    pub bump: u8,
+    // Part of `amount` held back by open disputes; claim_settlement leaves it
+    pub disputed_amount: u64,
+    // Set by claim_settlement; payments made before it are no longer in escrow
+    pub last_claim_at: i64,
+    // Part of `amount` from payments not yet finalized; claim_settlement leaves it
+    pub pending_amount: u64,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 8],
+}
+
+impl EscrowBalance {
+    /// What claim_settlement leaves in escrow: disputed plus pending.
+    pub fn held_amount(&self) -> Result<u64> {
+        self.disputed_amount
+            .checked_add(self.pending_amount)
+            .ok_or(ErrorCode::CalculationError.into())
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Feature {
+    SwapSettlement,
+    Token2022,
+    BitmapReplayProtection,
+}
+
+impl Feature {
+    pub fn mask(self) -> u64 {
+        1 << (self as u8)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct FeatureFlags {
+    // One bit per Feature; unset bits mean the feature is off
+    pub enabled: u64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl FeatureFlags {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.enabled & feature.mask() != 0
+    }
+
+    /// Instructions take the flags account as optional; a missing account means every flag is off.
+    pub fn require(flags: Option<&Self>, feature: Feature) -> Result<()> {
+        require!(
+            flags.is_some_and(|f| f.is_enabled(feature)),
+            ErrorCode::FeatureDisabled
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+pub const MAX_FEE_TIERS: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct FeeTier {
+    // Lifetime MerchantStats::total_volume at which this rate starts
+    pub volume_threshold: u128,
+    pub fee_bps: u16,
+}
+
+// Volume discounts replacing the global fee, set by the admin; rows are
+// sorted by threshold. Empty means every merchant pays the global rate
+#[account]
+#[derive(InitSpace)]
+pub struct FeeTiers {
+    #[max_len(5)]
+    pub tiers: Vec<FeeTier>,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+// Tiers queued by set_fee_tiers; apply_fee_tiers moves them into FeeTiers
+// once effective_at passes and closes this account back to the proposer
+#[account]
+#[derive(InitSpace)]
+pub struct FeeTiersProposal {
+    pub proposer: Pubkey,
+    #[max_len(5)]
+    pub tiers: Vec<FeeTier>,
+    pub effective_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl FeeTiers {
+    pub fn validate(tiers: &[FeeTier], max_fee_bps: u16) -> Result<()> {
+        require!(tiers.len() <= MAX_FEE_TIERS, ErrorCode::InvalidFeeTiers);
+        require!(tiers.iter().all(|t| t.fee_bps <= max_fee_bps), ErrorCode::FeeCapExceeded);
+        require!(
+            tiers.windows(2).all(|w| w[0].volume_threshold < w[1].volume_threshold),
+            ErrorCode::InvalidFeeTiers
+        );
+        Ok(())
+    }
+
+    /// Rate of the highest tier `volume` has reached, if any.
+    pub fn fee_bps_for(&self, volume: u128) -> Option<u16> {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|t| volume >= t.volume_threshold)
+            .map(|t| t.fee_bps)
+    }
+
+    /// `fee_bps_for` on the tiers stored in `info`, already constrained to
+    /// the fee_tiers PDA; None while they were never set. As with Blocked,
+    /// only a program-owned account counts.
+    pub fn load_bps(info: &AccountInfo, volume: u128) -> Result<Option<u16>> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(None);
+        }
+        let data = info.try_borrow_data()?;
+        Ok(FeeTiers::try_deserialize(&mut &data[..])?.fee_bps_for(volume))
+    }
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+// Program-owned vault for gateway fees. Holds SOL fees above its rent
+// reserve and is the authority of the fee token accounts.
+#[account]
+#[derive(InitSpace)]
+pub struct FeeVault {
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+// Running fee total for one mint; process_payment requires it for that mint
+#[account]
+#[derive(InitSpace)]
+pub struct FeeStats {
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    pub total_fees_collected: u64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
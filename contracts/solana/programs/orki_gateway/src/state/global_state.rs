@@ -1,4 +1,6 @@
 use anchor_lang::prelude::*;
+use crate::constants::MAX_FEE_BPS;
+use crate::errors::ErrorCode;
 
 #[account]
 #[derive(InitSpace)]
@@ -8,4 +10,92 @@ pub struct GlobalState {
     pub fee_wallet: Pubkey,
     pub paused: bool,
     pub bump: u8,
+    // Set by propose_admin, cleared on accept or cancel
+    pub pending_admin: Option<Pubkey>,
+    // Seconds the latest escrow deposit must age before claim_settlement
+    pub escrow_release_delay: i64,
+    // Seconds after which a payer may void their own authorization; 0 uses the default
+    pub authorization_expiry: i64,
+    // May pause (never unpause) alongside the admin; default means none
+    pub guardian: Pubkey,
+    // Set by propose_fee; apply_fee commits it once fee_effective_at passes
+    pub pending_fee_bps: Option<u16>,
+    pub fee_effective_at: i64,
+    // Seconds between propose_fee and the earliest apply_fee
+    pub fee_timelock_seconds: i64,
+    // Seconds a Payment must age before close_payment; 0 uses the default
+    pub payment_retention_seconds: i64,
+    // Resolves escrow disputes; default means disputes cannot be opened
+    pub arbiter: Pubkey,
+    // Layout version; 0 until migrate_global_state runs on a pre-versioning state
+    pub version: u8,
+    // Slots a payment must age before finalize_payment; 0 uses the default
+    pub finality_slots: u64,
+    // Highest rate any fee may be set to; only reduce_max_fee changes it, and
+    // only downward. 0 uses MAX_FEE_BPS
+    pub max_fee_bps: u16,
+    // How calc_fee rounds a fraction of a base unit; set_fee_rounding changes it
+    pub fee_rounding: FeeRounding,
+    // Lamports the fee vault pays whoever runs a due crank_payout
+    pub crank_reward: u64,
+    // Set by create_admin_council; from then on only council proposals set
+    // the fee, the fee wallet and the pause flag
+    pub admin_council: bool,
+    // Set when set_fee_timelock shortens the delay: no proposal takes effect
+    // before the notice the old delay promised has run out
+    pub fee_notice_until: i64,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 0],
+}
+
+// Floor is first so states written before the setting existed decode as it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum FeeRounding {
+    // Fractions go to the merchant; tiny payments can pay no fee at all
+    Floor,
+    // Fractions go to the protocol; any nonzero rate charges at least 1
+    Ceil,
+}
+
+impl GlobalState {
+    /// Layout version written by initialize and migrate_global_state.
+    pub const VERSION: u8 = 1;
+
+    /// The fee cap every fee setter validates against.
+    pub fn fee_cap(&self) -> u16 {
+        match self.max_fee_bps {
+            0 => MAX_FEE_BPS,
+            bps => bps,
+        }
+    }
+
+    pub fn check_fee(&self, fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= self.fee_cap(), ErrorCode::FeeCapExceeded);
+        Ok(())
+    }
+
+    /// When a fee proposed at `now` may be applied: the timelock from now, but
+    /// never before the notice a since-shortened timelock had promised.
+    pub fn fee_effective_time(&self, now: i64) -> Result<i64> {
+        let effective_at = now
+            .checked_add(self.fee_timelock_seconds)
+            .ok_or(ErrorCode::CalculationError)?;
+        Ok(effective_at.max(self.fee_notice_until))
+    }
+
+    /// Only affects later proposals; a pending one keeps its
+    /// `fee_effective_at`. Shortening the delay does not shorten the notice
+    /// already promised: until the old delay has run from now, proposals
+    /// still wait that long.
+    pub fn set_fee_timelock(&mut self, new_timelock_seconds: i64, now: i64) -> Result<()> {
+        require!(new_timelock_seconds >= 0, ErrorCode::InvalidAmount);
+        if new_timelock_seconds < self.fee_timelock_seconds {
+            let notice_until = now
+                .checked_add(self.fee_timelock_seconds)
+                .ok_or(ErrorCode::CalculationError)?;
+            self.fee_notice_until = self.fee_notice_until.max(notice_until);
+        }
+        self.fee_timelock_seconds = new_timelock_seconds;
+        Ok(())
+    }
 }
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+// Protocol-wide totals, kept beside GlobalState so that account never has to
+// grow. Volume and fees are summed in base units across every mint.
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalStats {
+    pub merchants_registered: u64,
+    // Sandbox payments move no funds and are not counted
+    pub payments_processed: u64,
+    pub total_volume: u128,
+    pub total_fee_revenue: u128,
+    pub bump: u8,
+    // Last sequence number handed to an event carrying event_seq; starts at 1
+    pub event_seq: u64,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 24],
+}
+
+impl GlobalStats {
+    pub fn record_merchant(&mut self) -> Result<()> {
+        self.merchants_registered = self
+            .merchants_registered
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        Ok(())
+    }
+
+    /// Next number for an event's `event_seq`. Strictly increasing across the
+    /// whole program, so indexers can order, dedupe and spot missed events.
+    pub fn next_event_seq(&mut self) -> Result<u64> {
+        self.event_seq = self
+            .event_seq
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        Ok(self.event_seq)
+    }
+
+    pub fn record_payment(&mut self, amount: u64, fee: u64) -> Result<()> {
+        self.payments_processed = self
+            .payments_processed
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.total_volume = self
+            .total_volume
+            .checked_add(amount as u128)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.total_fee_revenue = self
+            .total_fee_revenue
+            .checked_add(fee as u128)
+            .ok_or(ErrorCode::CalculationError)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+use crate::constants::MAX_MEMO_LENGTH;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum InvoiceStatus {
+    Open,
+    Paid,
+    Cancelled,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Invoice {
+    pub merchant: Pubkey,
+    pub invoice_id: u64,
+    pub amount: u64,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    // 0 means the invoice never expires
+    pub expires_at: i64,
+    #[max_len(MAX_MEMO_LENGTH as usize)]
+    pub memo: String,
+    pub status: InvoiceStatus,
+    pub payer: Pubkey,
+    pub paid_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 64],
+}
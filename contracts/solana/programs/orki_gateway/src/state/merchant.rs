@@ -1,4 +1,8 @@
 use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+use crate::math::bps_share;
+use crate::state::FeeSource;
+use crate::constants::{MAX_CONFIRMATION_MESSAGE_LENGTH, MAX_METADATA_URI_LENGTH, MAX_NAME_LENGTH};
 
 #[account]
 #[derive(InitSpace)]
@@ -6,8 +10,218 @@ pub struct Merchant {
     pub owner: Pubkey,
     pub settlement_wallet: Pubkey,
     pub settlement_token: Pubkey,
+    /// Allows process_payment_with_swap to convert other tokens into
+    /// settlement_token. Never widens what process_payment accepts: a token
+    /// outside the merchant's rule fails with SwapNotAvailable there.
     pub swap_enabled: bool,
-    #[max_len(32)]
+    #[max_len(MAX_NAME_LENGTH as usize)]
     pub name: String,
     pub bump: u8,
+    pub sandbox: bool,
+    // Replaces the global fee_bps for this merchant when set
+    pub fee_bps_override: Option<u16>,
+    // Merchant share is held in the program vault until claimed
+    pub escrow_enabled: bool,
+    // Merchant-level pause; payments fail while false
+    pub active: bool,
+    // Set when the admin deactivates; the owner cannot reactivate until cleared
+    pub admin_locked: bool,
+    // Merchant PDA this one was renamed from, or default
+    pub previous_merchant: Pubkey,
+    // Open invoices and non-empty escrow or accrued balances; both must be zero to close
+    pub open_invoices: u32,
+    pub funded_escrows: u32,
+    // Set while a SplitConfig is attached; payments must then include it
+    pub split_enabled: bool,
+    // Set while an AcceptedTokens list is non-empty; payments must then include it
+    pub accepted_tokens_enabled: bool,
+    // Per-payment amount bounds in the payment token's base units; 0 = unlimited
+    pub min_amount: u64,
+    pub max_amount: u64,
+    // Operator PDAs granted and not yet revoked; must be zero to rename or close
+    pub operators: u32,
+    // Set by the admin only; payments to this merchant carry no protocol fee
+    pub fee_exempt: bool,
+    // Partner credited with referrer_bps of each protocol fee; default means none
+    pub referrer: Pubkey,
+    pub referrer_bps: u16,
+    // Takes any mint or SOL; otherwise payments must be in settlement_token,
+    // where the default key means SOL
+    pub accepts_any_token: bool,
+    // Set once accepts_any_token was chosen explicitly; migrate_merchant
+    // backfills it for merchants registered before the flag existed
+    pub token_rule_set: bool,
+    // Free-form storefront category; the program gives no meaning to the value
+    pub category: u8,
+    // Off-chain profile (logo, site, support contact); empty when unset
+    #[max_len(MAX_METADATA_URI_LENGTH as usize)]
+    pub metadata_uri: String,
+    // How the merchant share is paid out; Instant unless the owner opts in
+    pub settlement_mode: SettlementMode,
+    // Layout version; 0 for merchants registered before versioning, which
+    // must run migrate_merchant before any other instruction accepts them
+    pub version: u8,
+    // Slot in the MerchantRegistry directory, from 1; 0 until index_merchant
+    // lists a merchant registered before the directory existed
+    pub merchant_index: u64,
+    // Lets the settlement wallet pay this merchant; off by default because a
+    // self-payment is almost always an integration bug
+    pub allow_self_payments: bool,
+    // Most the merchant may receive per UTC day, tips included, counted in
+    // its DailyVolume; 0 is unlimited. Only the admin sets it
+    pub daily_limit: u64,
+    // Set while a RewardsHook is attached; process_payment then calls it
+    pub rewards_enabled: bool,
+    // Fail payments the hook can't be called for, instead of skipping it
+    pub rewards_required: bool,
+    // Set by the admin's freeze_merchant. Unlike an admin deactivation it
+    // also stops escrow claims and balance withdrawals; refunds still go out
+    pub admin_frozen: bool,
+    // Tax rate, such as VAT, included in each payment's amount. Bookkeeping
+    // only: payments record the share as Payment::tax_amount but move nothing
+    pub tax_bps: u16,
+    // Holds the MerchantName claim on its name; false for merchants
+    // registered before names were claimed, until a rename or sale
+    pub name_indexed: bool,
+    // Shown by wallets after a payment: process_payment returns it in its
+    // PaymentReceipt. Empty when unset. Merchants whose name and metadata URI
+    // leave too little slack for it need upgrade_merchant_account
+    #[max_len(MAX_CONFIRMATION_MESSAGE_LENGTH as usize)]
+    pub confirmation_message: String,
+    // Least time, in seconds, between two payments from the same payer,
+    // tracked in their PayerThrottle; 0 leaves payers unthrottled
+    pub min_interval_per_payer: u32,
+    // SettlementRoute PDAs set and not yet cleared; must be zero to rename,
+    // transfer or close
+    pub settlement_routes: u32,
+    // Discount PDAs created and not yet deleted, which are keyed by the
+    // merchant's address; must be zero to rename, transfer or close
+    pub discount_codes: u32,
+    // Authorization holds not yet captured or voided; must be zero to rename,
+    // transfer or close, since capture and void need the merchant's address
+    pub open_authorizations: u32,
+    // PaymentStreams created and not yet cancelled; must be zero to rename,
+    // transfer or close, since cancelling a stream needs the merchant's address
+    pub open_streams: u32,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 0],
+}
+
+// Instant is first so merchants registered before the mode existed decode as it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum SettlementMode {
+    // Each payment transfers straight to the settlement wallet
+    Instant,
+    // Payments credit a MerchantBalance in the vault; withdraw_balance pays out
+    Accrue,
+}
+
+impl Merchant {
+    /// Layout version written by register_merchant and migrate_merchant. 2
+    /// added confirmation_message, min_interval_per_payer, settlement_routes
+    /// discount_codes, open_authorizations and open_streams, which outgrew the
+    /// 409-byte version 1 account.
+    pub const VERSION: u8 = 2;
+
+    /// False for an account `init_if_needed` has only just created; every
+    /// registered merchant has its owner set.
+    pub fn is_registered(&self) -> bool {
+        self.owner != Pubkey::default()
+    }
+
+    /// Names are PDA seeds and end up in explorers, receipts and webhook
+    /// payloads, so they must be non-empty, fit MAX_NAME_LENGTH bytes, and
+    /// hold no control or invisible formatting characters nor whitespace at
+    /// either end. Spaces inside a name are fine.
+    pub fn check_name(name: &str) -> Result<()> {
+        require!(!name.is_empty(), ErrorCode::EmptyName);
+        require!(name.len() <= MAX_NAME_LENGTH as usize, ErrorCode::NameTooLong);
+        require!(
+            name.trim() == name && name.chars().all(is_printable),
+            ErrorCode::InvalidNameCharacters
+        );
+        Ok(())
+    }
+
+    /// Whether payments credit a MerchantBalance instead of paying out.
+    pub fn accrues(&self) -> bool {
+        self.settlement_mode == SettlementMode::Accrue
+    }
+
+    /// Whether the merchant share is paid into the vault rather than the
+    /// settlement wallet: escrowed until claimed, or accrued until withdrawn.
+    pub fn pays_into_vault(&self) -> bool {
+        self.escrow_enabled || self.accrues()
+    }
+
+    /// Fee rate for this merchant: zero when exempt, else its negotiated
+    /// override, else the global rate.
+    pub fn fee_bps(&self, global_fee_bps: u16) -> u16 {
+        self.fee_rate(None, None, global_fee_bps).0
+    }
+
+    /// Like `fee_bps`, with the payment token's configured rate and then the
+    /// merchant's volume tier between its own rate and the global one, and
+    /// which of them applied.
+    pub fn fee_rate(&self, token_fee_bps: Option<u16>, tier_fee_bps: Option<u16>, global_fee_bps: u16) -> (u16, FeeSource) {
+        if self.fee_exempt {
+            return (0, FeeSource::Merchant);
+        }
+        if let Some(bps) = self.fee_bps_override {
+            return (bps, FeeSource::Merchant);
+        }
+        match (token_fee_bps, tier_fee_bps) {
+            (Some(bps), _) => (bps, FeeSource::Token),
+            (None, Some(bps)) => (bps, FeeSource::Tier),
+            (None, None) => (global_fee_bps, FeeSource::Global),
+        }
+    }
+
+    /// Referrer's cut of a protocol fee, rounded down so dust stays with the protocol.
+    pub fn referrer_share(&self, fee: u64) -> Result<u64> {
+        if self.referrer == Pubkey::default() {
+            return Ok(0);
+        }
+        bps_share(fee, self.referrer_bps)
+    }
+
+    /// Tax included in a payment of `amount`, rounded down. The product is
+    /// taken in u128 and tax_bps is capped at 100%, so it never overflows.
+    pub fn tax_on(&self, amount: u64) -> Result<u64> {
+        bps_share(amount, self.tax_bps)
+    }
+
+    /// Single-token rule for a payment in `token` (default for SOL). Merchants
+    /// with an accepted-token list check that list instead.
+    pub fn accepts_token(&self, token: &Pubkey) -> bool {
+        self.accepts_any_token || *token == self.settlement_token
+    }
+
+    /// `accepts_token` as an error. A swap_enabled merchant gets
+    /// SwapNotAvailable, since only process_payment_with_swap converts.
+    pub fn check_token(&self, token: &Pubkey) -> Result<()> {
+        if self.accepts_token(token) {
+            return Ok(());
+        }
+        if self.swap_enabled {
+            return err!(ErrorCode::SwapNotAvailable);
+        }
+        err!(ErrorCode::InvalidToken)
+    }
+
+    pub fn check_amount(&self, amount: u64) -> Result<()> {
+        require!(amount >= self.min_amount, ErrorCode::AmountTooSmall);
+        require!(self.max_amount == 0 || amount <= self.max_amount, ErrorCode::AmountTooLarge);
+        Ok(())
+    }
+}
+
+// Control characters, plus the zero-width, line separator and bidi override
+// characters that render as nothing yet make two names differ
+fn is_printable(c: char) -> bool {
+    !c.is_control()
+        && !matches!(
+            c,
+            '\u{200B}'..='\u{200F}' | '\u{2028}'..='\u{202E}' | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}'
+        )
 }
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+// Accrued settlements for an Accrue-mode merchant in one mint, held in the
+// merchant's vault until the settlement wallet withdraws them
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantBalance {
+    pub merchant: Pubkey,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+    // Seconds between crank_payout runs; 0 leaves payouts to withdraw_balance
+    pub payout_interval: u32,
+    // When the current payout interval started: the last crank_payout, or
+    // set_payout_interval
+    pub last_payout_at: i64,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 20],
+}
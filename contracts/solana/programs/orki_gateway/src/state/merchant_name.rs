@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+use crate::constants::MAX_NAME_LENGTH;
+
+// An owner's claim on a merchant name regardless of case, at
+// `[b"merchant_name", owner, normalized_name]`, so one owner can't run
+// "Coffee Shop" next to "coffee shop". Follows the merchant through renames
+// and sales, and closes with it.
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantName {
+    pub owner: Pubkey,
+    pub merchant: Pubkey,
+    // The name lowercased, as in the address seeds
+    #[max_len(MAX_NAME_LENGTH as usize)]
+    pub normalized_name: String,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl MerchantName {
+    /// The form names are compared in. Lowercasing can lengthen a name
+    /// ('İ' becomes two characters), so claims also check this against
+    /// MAX_NAME_LENGTH.
+    pub fn normalize(name: &str) -> String {
+        name.to_lowercase()
+    }
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+// Directory of merchants, so clients can page through them with plain
+// account reads instead of a getProgramAccounts scan. Created by the first
+// registration after it was introduced.
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantRegistry {
+    // Index entries handed out so far; closed merchants still count
+    pub merchant_count: u64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl MerchantRegistry {
+    /// Takes the next directory index. Indices start at 1, so a merchant's
+    /// zero `merchant_index` means it is not listed.
+    pub fn next_index(&mut self) -> Result<u64> {
+        self.merchant_count = self
+            .merchant_count
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        Ok(self.merchant_count)
+    }
+}
+
+// One directory slot. Follows the merchant through renames; closing the
+// merchant leaves the slot with a default `merchant` rather than a dangling key.
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantIndex {
+    pub index: u64,
+    pub merchant: Pubkey,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl MerchantIndex {
+    /// Whether the merchant this slot pointed to has been closed.
+    pub fn is_tombstone(&self) -> bool {
+        self.merchant == Pubkey::default()
+    }
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+// Lifetime counters for one merchant, kept so dashboards need not replay
+// history. Amounts are summed in base units across every mint paid in.
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantStats {
+    pub merchant: Pubkey,
+    pub payment_count: u64,
+    pub total_volume: u128,
+    pub total_fees_paid: u128,
+    // Refunds are tallied here; total_volume is never reduced
+    pub total_refunded: u128,
+    pub last_payment_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl MerchantStats {
+    pub fn record_payment(&mut self, amount: u64, fee: u64, now: i64) -> Result<()> {
+        self.payment_count = self.payment_count.checked_add(1).ok_or(ErrorCode::CalculationError)?;
+        self.total_volume = self
+            .total_volume
+            .checked_add(amount as u128)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.total_fees_paid = self
+            .total_fees_paid
+            .checked_add(fee as u128)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.last_payment_at = now;
+        Ok(())
+    }
+
+    pub fn record_refund(&mut self, amount: u64) -> Result<()> {
+        self.total_refunded = self
+            .total_refunded
+            .checked_add(amount as u128)
+            .ok_or(ErrorCode::CalculationError)?;
+        Ok(())
+    }
+}
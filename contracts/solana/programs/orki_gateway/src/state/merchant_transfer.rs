@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+// Pending handover of a merchant to a new owner: opened by the current owner,
+// consumed by accept_merchant_ownership or closed by the cancel.
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantTransfer {
+    pub merchant: Pubkey,
+    pub owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub proposed_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
@@ -1,7 +1,67 @@
 pub mod global_state;
 pub mod merchant;
 pub mod payment;
+pub mod feature_flags;
+pub mod escrow;
+pub mod invoice;
+pub mod recent_payments;
+pub mod subscription;
+pub mod split;
+pub mod payout_book;
+pub mod accepted_tokens;
+pub mod fee_vault;
+pub mod merchant_stats;
+pub mod global_stats;
+pub mod payment_link;
+pub mod operator;
+pub mod discount;
+pub mod blocked;
+pub mod token_fee;
+pub mod fee_tiers;
+pub mod merchant_balance;
+pub mod merchant_registry;
+pub mod notification_queue;
+pub mod merchant_transfer;
+pub mod stream;
+pub mod daily_volume;
+pub mod rewards_hook;
+pub mod admin_council;
+pub mod merchant_name;
+pub mod payer_throttle;
+pub mod settlement_route;
+#[cfg(feature = "test-clock")]
+pub mod test_clock;
 
 pub use global_state::*;
 pub use merchant::*;
 pub use payment::*;
+pub use feature_flags::*;
+pub use escrow::*;
+pub use invoice::*;
+pub use recent_payments::*;
+pub use subscription::*;
+pub use split::*;
+pub use payout_book::*;
+pub use accepted_tokens::*;
+pub use fee_vault::*;
+pub use merchant_stats::*;
+pub use global_stats::*;
+pub use payment_link::*;
+pub use operator::*;
+pub use discount::*;
+pub use blocked::*;
+pub use token_fee::*;
+pub use fee_tiers::*;
+pub use merchant_balance::*;
+pub use merchant_registry::*;
+pub use notification_queue::*;
+pub use merchant_transfer::*;
+pub use stream::*;
+pub use daily_volume::*;
+pub use rewards_hook::*;
+pub use admin_council::*;
+pub use merchant_name::*;
+pub use payer_throttle::*;
+pub use settlement_route::*;
+#[cfg(feature = "test-clock")]
+pub use test_clock::*;
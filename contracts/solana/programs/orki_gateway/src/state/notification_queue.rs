@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+pub const NOTIFICATION_QUEUE_LEN: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct Notification {
+    pub payment_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+// Ring buffer of payment notifications a merchant's backend polls in place of
+// an off-chain webhook, and clears once it has handled them.
+#[account]
+#[derive(InitSpace)]
+pub struct NotificationQueue {
+    pub merchant: Pubkey,
+    // Slot the next entry is written to; the oldest entry once the buffer is full
+    pub next: u8,
+    pub count: u8,
+    pub bump: u8,
+    // Notifications ever pushed, never reset; a poller that sees it move by
+    // more than the buffer holds knows it missed some
+    pub total: u64,
+    pub entries: [Notification; NOTIFICATION_QUEUE_LEN],
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl NotificationQueue {
+    pub fn push(&mut self, entry: Notification) {
+        self.entries[self.next as usize] = entry;
+        self.next = ((self.next as usize + 1) % NOTIFICATION_QUEUE_LEN) as u8;
+        if (self.count as usize) < NOTIFICATION_QUEUE_LEN {
+            self.count += 1;
+        }
+        self.total = self.total.saturating_add(1);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [Notification::default(); NOTIFICATION_QUEUE_LEN];
+        self.next = 0;
+        self.count = 0;
+    }
+
+    /// The buffered notifications, oldest first.
+    pub fn notifications(&self) -> Vec<Notification> {
+        let start = (self.next as usize + NOTIFICATION_QUEUE_LEN - self.count as usize) % NOTIFICATION_QUEUE_LEN;
+        (0..self.count as usize)
+            .map(|i| self.entries[(start + i) % NOTIFICATION_QUEUE_LEN])
+            .collect()
+    }
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+use crate::state::Merchant;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OperatorPermission {
+    UpdateSettlement,
+    Refund,
+    CreateInvoices,
+    Pause,
+}
+
+impl OperatorPermission {
+    pub const ALL: u8 = 0b1111;
+
+    pub fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Operator {
+    pub merchant: Pubkey,
+    pub operator: Pubkey,
+    // One bit per OperatorPermission
+    pub permissions: u8,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl Operator {
+    pub fn allows(&self, permission: OperatorPermission) -> bool {
+        self.permissions & permission.mask() != 0
+    }
+
+    /// The merchant owner may do anything; anyone else needs their operator
+    /// PDA for this merchant, granting `permission`. Instructions constrain
+    /// the PDA's seeds to the signer, so a missing account means no grant.
+    pub fn authorize(
+        merchant: &Merchant,
+        authority: &Pubkey,
+        operator: Option<&Self>,
+        permission: OperatorPermission,
+    ) -> Result<()> {
+        if *authority == merchant.owner {
+            return Ok(());
+        }
+        require!(
+            operator.is_some_and(|op| op.allows(permission)),
+            ErrorCode::Unauthorized
+        );
+        Ok(())
+    }
+}
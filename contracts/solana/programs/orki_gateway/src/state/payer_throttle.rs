@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+// When a payer last paid a merchant that spaces out each payer's payments
+// (Merchant::min_interval_per_payer), at `[b"throttle", merchant, payer]`.
+// Opened by the payer's first such payment, at the payer's expense, and
+// closed by the payer through close_payer_throttle.
+#[account]
+#[derive(InitSpace)]
+pub struct PayerThrottle {
+    pub merchant: Pubkey,
+    pub payer: Pubkey,
+    pub last_payment_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl PayerThrottle {
+    /// Fails unless `interval` seconds have passed since the last payment.
+    pub fn check(&self, now: i64, interval: u32) -> Result<()> {
+        let next = self
+            .last_payment_at
+            .checked_add(i64::from(interval))
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(now >= next, ErrorCode::RateLimited);
+        Ok(())
+    }
+
+    /// Records a payment at `now`, after [`Self::check`] has passed.
+    pub fn record(&mut self, merchant: Pubkey, payer: Pubkey, bump: u8, now: i64) {
+        self.merchant = merchant;
+        self.payer = payer;
+        self.bump = bump;
+        self.last_payment_at = now;
+    }
+}
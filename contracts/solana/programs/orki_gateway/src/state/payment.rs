@@ -1,4 +1,43 @@
 use anchor_lang::prelude::*;
+use solana_sha256_hasher::hash;
+use crate::errors::ErrorCode;
+use crate::state::PaymentLink;
+use crate::constants::MAX_MEMO_LENGTH;
+
+// Completed is first so records written before statuses existed decode as it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum PaymentStatus {
+    Completed,
+    Authorized,
+    Captured,
+    Voided,
+    // Escrowed share held back from claim_settlement until the arbiter decides
+    Disputed,
+    // Completed or Captured and aged past finality_slots; see finalize_payment
+    Finalized,
+    // Finalized after the arbiter ruled on its dispute; can't be disputed again
+    DisputeResolved,
+}
+
+impl PaymentStatus {
+    /// Whether the payer's funds reached the merchant: completed, captured or
+    /// finalized, including after a resolved dispute. Held, voided and
+    /// disputed payments are not settled.
+    pub fn is_settled(&self) -> bool {
+        matches!(
+            self,
+            PaymentStatus::Completed
+                | PaymentStatus::Captured
+                | PaymentStatus::Finalized
+                | PaymentStatus::DisputeResolved
+        )
+    }
+
+    /// Finalized, whether or not it has since been through a dispute.
+    pub fn is_final(&self) -> bool {
+        matches!(self, PaymentStatus::Finalized | PaymentStatus::DisputeResolved)
+    }
+}
 
 #[account]
 #[derive(InitSpace)]
@@ -9,4 +48,109 @@ pub struct Payment {
     pub payment_id: u64,
     pub timestamp: i64,
     pub bump: u8,
+    pub sandbox: bool,
+    pub refunded_amount: u64,
+    pub status: PaymentStatus,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    // Gateway fee taken out of `amount`; 0 until an authorization is captured
+    pub fee: u64,
+    // Order reference from the payer, or empty. The length prefix took four
+    // reserved bytes, so records written before memos existed decode as empty.
+    #[max_len(MAX_MEMO_LENGTH as usize)]
+    pub memo: String,
+    // Paid to the merchant on top of `amount`, with no fee taken
+    pub tip: u64,
+    // Listed amount and rate when a discount code lowered `amount`; 0 otherwise
+    pub pre_discount_amount: u64,
+    pub discount_bps: u16,
+    // Non-transferable receipt token from mint_receipt; default until one is minted
+    pub receipt_mint: Pubkey,
+    // Merchant share was credited to an escrow balance rather than paid out
+    pub escrowed: bool,
+    // Slot the funds moved in: the payment, or the capture of an authorization.
+    // 0 on records from before finality, which finalize at once.
+    pub slot: u64,
+    // 32-byte id from process_payment_v2, which then keys the record in place
+    // of `payment_id` (left 0); all zero for u64-keyed payments
+    pub order_id: [u8; 32],
+    // Part of `amount` the merchant owes as tax at its tax_bps, for accounting
+    // exports; 0 on records from before tax rates
+    pub tax_amount: u64,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 0],
+}
+
+/// What a payment record is keyed by after its payer: a u64 from
+/// process_payment, or a 32-byte order id from process_payment_v2. The two
+/// seed widths differ, so the same payer can't collide across them. A
+/// pay_link payment is keyed by the link and the use it consumed, hashed
+/// into an order id (see [`PaymentLink::payment_order_id`]), so the stored
+/// record reads back as an Order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaymentId {
+    Sequence(u64),
+    Order([u8; 32]),
+    Link { link: Pubkey, use_index: u64 },
+}
+
+impl PaymentId {
+    /// The id's bytes in the Payment PDA seeds.
+    pub fn seed(&self) -> Vec<u8> {
+        match self {
+            PaymentId::Sequence(id) => id.to_le_bytes().to_vec(),
+            PaymentId::Order(id) => id.to_vec(),
+            PaymentId::Link { .. } => self.order().to_vec(),
+        }
+    }
+
+    /// The u64 stored and emitted as `payment_id`; 0 for order ids, the use
+    /// index for link payments.
+    pub fn sequence(&self) -> u64 {
+        match self {
+            PaymentId::Sequence(id) => *id,
+            PaymentId::Order(_) => 0,
+            PaymentId::Link { use_index, .. } => *use_index,
+        }
+    }
+
+    /// The order id stored and emitted as `order_id`; zero for u64 ids.
+    pub fn order(&self) -> [u8; 32] {
+        match self {
+            PaymentId::Sequence(_) => [0; 32],
+            PaymentId::Order(id) => *id,
+            PaymentId::Link { link, use_index } => PaymentLink::payment_order_id(link, *use_index),
+        }
+    }
+}
+
+impl Payment {
+    /// False for an account `init_if_needed` has only just created; every
+    /// stored payment has its payer set.
+    pub fn is_recorded(&self) -> bool {
+        self.payer != Pubkey::default()
+    }
+
+    /// The id the record was created under.
+    pub fn id(&self) -> PaymentId {
+        if self.order_id == [0; 32] {
+            PaymentId::Sequence(self.payment_id)
+        } else {
+            PaymentId::Order(self.order_id)
+        }
+    }
+
+    /// Order id for an arbitrary order reference, such as a UUID: its SHA-256.
+    pub fn hash_order_id(order: &str) -> [u8; 32] {
+        hash(order.as_bytes()).to_bytes()
+    }
+
+    /// What process_payment credited to the merchant's escrow balance: the
+    /// amount after fees, plus any tip.
+    pub fn escrowed_amount(&self) -> Result<u64> {
+        self.amount
+            .checked_sub(self.fee)
+            .and_then(|net| net.checked_add(self.tip))
+            .ok_or(ErrorCode::CalculationError.into())
+    }
 }
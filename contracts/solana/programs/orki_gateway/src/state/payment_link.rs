@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hashv;
+
+#[account]
+#[derive(InitSpace)]
+pub struct PaymentLink {
+    pub merchant: Pubkey,
+    pub link_seed: u64,
+    pub amount: u64,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    // 0 means the link can be paid any number of times
+    pub max_uses: u64,
+    pub uses: u64,
+    // 0 means the link never expires
+    pub expires_at: i64,
+    pub active: bool,
+    pub bump: u8,
+    #[max_len(32)]
+    pub label: String,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 64],
+}
+
+impl PaymentLink {
+    pub fn exhausted(&self) -> bool {
+        self.max_uses != 0 && self.uses >= self.max_uses
+    }
+
+    /// Order id of the payment that took use `use_index` of `link`. Hashed,
+    /// as the link and index together would not fit in one seed.
+    pub fn payment_order_id(link: &Pubkey, use_index: u64) -> [u8; 32] {
+        hashv(&[b"link", link.as_ref(), &use_index.to_le_bytes()]).to_bytes()
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+pub const MAX_PAYOUT_ADDRESSES: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, InitSpace)]
+pub struct PayoutAddress {
+    pub address: Pubkey,
+    #[max_len(32)]
+    pub label: String,
+    // update_merchant may switch to this address from here on
+    pub activates_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PayoutBook {
+    pub merchant: Pubkey,
+    #[max_len(5)]
+    pub entries: Vec<PayoutAddress>,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl PayoutBook {
+    pub fn find(&self, address: &Pubkey) -> Option<&PayoutAddress> {
+        self.entries.iter().find(|e| &e.address == address)
+    }
+
+    /// Fails unless `address` is in `book` and its delay has passed; `book`
+    /// is None for merchants that never opened one.
+    pub fn require_active(book: Option<&PayoutBook>, address: &Pubkey, now: i64) -> Result<()> {
+        let entry = book
+            .and_then(|book| book.find(address))
+            .ok_or(ErrorCode::PayoutAddressNotListed)?;
+        require!(now >= entry.activates_at, ErrorCode::PayoutAddressNotActive);
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+pub const RECENT_PAYMENTS_LEN: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct RecentPayment {
+    pub payment_id: u64,
+    pub amount: u64,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    pub timestamp: i64,
+}
+
+// Ring buffer of a merchant's latest payments, for clients without an indexer.
+#[account]
+#[derive(InitSpace)]
+pub struct RecentPayments {
+    pub merchant: Pubkey,
+    // Slot the next entry is written to; the oldest entry once the buffer is full
+    pub next: u8,
+    pub count: u8,
+    pub bump: u8,
+    pub entries: [RecentPayment; RECENT_PAYMENTS_LEN],
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl RecentPayments {
+    pub fn push(&mut self, entry: RecentPayment) {
+        self.entries[self.next as usize] = entry;
+        self.next = ((self.next as usize + 1) % RECENT_PAYMENTS_LEN) as u8;
+        if (self.count as usize) < RECENT_PAYMENTS_LEN {
+            self.count += 1;
+        }
+    }
+}
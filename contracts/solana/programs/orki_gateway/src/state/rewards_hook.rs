@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+// A merchant's loyalty program, called by process_payment after each
+// payment. Set by the owner with set_rewards_hook; the account also signs
+// the call, so the program can tell it came from the gateway.
+#[account]
+#[derive(InitSpace)]
+pub struct RewardsHook {
+    pub merchant: Pubkey,
+    // Program invoked with the on_payment interface
+    pub program: Pubkey,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
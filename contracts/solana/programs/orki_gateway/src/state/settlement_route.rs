@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+// Where a merchant's payments in one token settle instead of its
+// settlement_wallet, at `[b"settlement_route", merchant, mint]` with
+// Pubkey::default() as the mint for SOL. Set by the owner with
+// set_settlement_route and counted in Merchant::settlement_routes.
+#[account]
+#[derive(InitSpace)]
+pub struct SettlementRoute {
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    // Wallet paid in SOL, or the owner of the ATA paid in the token
+    pub destination: Pubkey,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl SettlementRoute {
+    /// Destination stored in `route`, already constrained to the payment
+    /// token's route PDA, or None while the token has no route. As with
+    /// Blocked, only a program-owned account counts.
+    pub fn load_destination(route: &AccountInfo) -> Result<Option<Pubkey>> {
+        if route.owner != &crate::ID || route.data_is_empty() {
+            return Ok(None);
+        }
+        let data = route.try_borrow_data()?;
+        Ok(Some(SettlementRoute::try_deserialize(&mut &data[..])?.destination))
+    }
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+use crate::math::bps_share;
+use crate::constants::BPS_DENOMINATOR;
+
+pub const MAX_SPLIT_RECIPIENTS: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct SplitRecipient {
+    // Wallet for SOL payments; owner of the receiving token account for SPL
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SplitConfig {
+    pub merchant: Pubkey,
+    #[max_len(5)]
+    pub recipients: Vec<SplitRecipient>,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl SplitConfig {
+    pub fn validate(recipients: &[SplitRecipient]) -> Result<()> {
+        require!(
+            !recipients.is_empty() && recipients.len() <= MAX_SPLIT_RECIPIENTS,
+            ErrorCode::InvalidSplit
+        );
+        require!(recipients.iter().all(|r| r.bps > 0), ErrorCode::InvalidSplit);
+        let total: u32 = recipients.iter().map(|r| r.bps as u32).sum();
+        require!(total == BPS_DENOMINATOR as u32, ErrorCode::InvalidSplit);
+        Ok(())
+    }
+
+    /// Each recipient's cut of `total`, in config order. Rounding dust goes to the first.
+    pub fn shares(&self, total: u64) -> Result<Vec<u64>> {
+        let mut shares = Vec::with_capacity(self.recipients.len());
+        for r in &self.recipients {
+            shares.push(bps_share(total, r.bps)?);
+        }
+        let distributed = shares.iter().try_fold(0u64, |acc, s| acc.checked_add(*s))
+            .ok_or(ErrorCode::CalculationError)?;
+        let dust = total.checked_sub(distributed).ok_or(ErrorCode::CalculationError)?;
+        shares[0] = shares[0].checked_add(dust).ok_or(ErrorCode::CalculationError)?;
+        Ok(shares)
+    }
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+use crate::errors::ErrorCode;
+
+// A deposit that vests to the merchant at a fixed rate per second. The
+// stream account itself holds SOL deposits above its rent reserve and is the
+// authority of the stream token account for SPL ones.
+#[account]
+#[derive(InitSpace)]
+pub struct PaymentStream {
+    pub merchant: Pubkey,
+    pub payer: Pubkey,
+    pub stream_id: u64,
+    // Pubkey::default() for SOL
+    pub mint: Pubkey,
+    pub rate_per_second: u64,
+    pub deposit: u64,
+    // Gross amount paid out so far, fees included
+    pub withdrawn: u64,
+    pub start_at: i64,
+    // Vesting runs from here; start_at until the first withdrawal
+    pub last_withdrawn_at: i64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
+
+impl PaymentStream {
+    /// Gross amount vested since the last withdrawal and not yet paid out:
+    /// elapsed seconds times the rate, capped at what is left of the deposit.
+    pub fn vested(&self, now: i64) -> Result<u64> {
+        let elapsed = now.saturating_sub(self.last_withdrawn_at).max(0) as u128;
+        let remaining = self.remaining()?;
+        let accrued = elapsed
+            .checked_mul(self.rate_per_second as u128)
+            .ok_or(ErrorCode::CalculationError)?;
+        // Capped at a u64, so the cast back cannot truncate
+        Ok(accrued.min(remaining as u128) as u64)
+    }
+
+    /// Deposit not yet paid out, vested or not.
+    pub fn remaining(&self) -> Result<u64> {
+        self.deposit
+            .checked_sub(self.withdrawn)
+            .ok_or(ErrorCode::CalculationError.into())
+    }
+}
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Subscription {
+    pub merchant: Pubkey,
+    pub subscriber: Pubkey,
+    pub sub_id: u64,
+    pub mint: Pubkey,
+    // Token account the subscriber approved the delegate PDA on
+    pub subscriber_token_account: Pubkey,
+    pub amount: u64,
+    pub interval_seconds: i64,
+    // 0 until the first charge, which is due immediately
+    pub last_charged: i64,
+    pub charges_count: u64,
+    pub bump: u8,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 32],
+}
@@ -0,0 +1,9 @@
+use anchor_lang::prelude::*;
+
+// Only compiled with the `test-clock` feature; see crate::clock::now
+#[account]
+#[derive(InitSpace)]
+pub struct TestClock {
+    pub unix_timestamp: i64,
+    pub bump: u8,
+}
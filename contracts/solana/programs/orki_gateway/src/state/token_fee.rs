@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+// Protocol fee for payments in one mint; Pubkey::default() configures SOL.
+// Sits between a merchant's own rate and the global one
+#[account]
+#[derive(InitSpace)]
+pub struct TokenFeeConfig {
+    pub mint: Pubkey,
+    pub fee_bps: u16,
+    pub bump: u8,
+    // Set by set_token_fee for a raise; apply_token_fee commits it once
+    // fee_effective_at passes
+    pub pending_fee_bps: Option<u16>,
+    pub fee_effective_at: i64,
+    // Set while the config's first rate is still pending; until then the
+    // mint keeps the tier or global rate
+    pub inactive: bool,
+    // Spare bytes for future fields; see crate::layout
+    pub reserved: [u8; 20],
+}
+
+impl TokenFeeConfig {
+    /// Rate stored in `config`, already constrained to the mint's token_fee
+    /// PDA, or None while the mint has no config. As with Blocked, only a
+    /// program-owned account counts.
+    pub fn load_bps(config: &AccountInfo) -> Result<Option<u16>> {
+        if config.owner != &crate::ID || config.data_is_empty() {
+            return Ok(None);
+        }
+        let data = config.try_borrow_data()?;
+        Ok(TokenFeeConfig::try_deserialize(&mut &data[..])?.rate())
+    }
+
+    /// Rate payments in the mint are charged, or None while the first one
+    /// is still waiting out the timelock.
+    pub fn rate(&self) -> Option<u16> {
+        (!self.inactive).then_some(self.fee_bps)
+    }
+}
+
+// Which rate a payment's fee was charged at
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum FeeSource {
+    Global,
+    Token,
+    Merchant,
+    Tier,
+}
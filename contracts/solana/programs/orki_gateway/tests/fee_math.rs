@@ -0,0 +1,204 @@
+//! Property tests for the fee, tax, discount and split arithmetic in
+//! `orki_gateway::math`. Inputs are the boundary values plus a seeded random
+//! sample, so a failure reproduces on every run.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Result;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::math::{bps_share, calc_fee};
+use orki_gateway::state::{Discount, FeeRounding, SplitConfig, SplitRecipient};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const SAMPLES: usize = 20_000;
+
+const EDGE_AMOUNTS: [u64; 9] = [0, 1, 2, 9_999, 10_000, 10_001, u64::MAX / 10_000, u64::MAX - 1, u64::MAX];
+const EDGE_BPS: [u16; 6] = [0, 1, 5_000, 9_999, 10_000, 10_001];
+const ROUNDINGS: [FeeRounding; 2] = [FeeRounding::Floor, FeeRounding::Ceil];
+
+fn rng() -> StdRng {
+    StdRng::seed_from_u64(0x4f52_4b49)
+}
+
+// Mostly full-range amounts, with a share of small ones where rounding shows
+fn amount(rng: &mut StdRng) -> u64 {
+    if rng.gen_bool(0.5) {
+        rng.gen()
+    } else {
+        rng.gen_range(0..1_000_000)
+    }
+}
+
+fn amounts(rng: &mut StdRng) -> Vec<u64> {
+    EDGE_AMOUNTS.iter().copied().chain((0..SAMPLES).map(|_| amount(rng))).collect()
+}
+
+// The inline math every payment path used before calc_fee
+fn legacy_fee(amount: u64, fee_bps: u16) -> Result<(u64, u64)> {
+    let fee = (amount as u128)
+        .checked_mul(fee_bps as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::CalculationError)? as u64;
+    let merchant_amount = amount
+        .checked_sub(fee)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok((fee, merchant_amount))
+}
+
+#[test]
+fn fee_and_merchant_amount_add_up_to_amount() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        for bps in EDGE_BPS.iter().copied().filter(|b| *b <= 10_000).chain([rng.gen_range(0..=10_000)]) {
+            for rounding in ROUNDINGS {
+                let (fee, merchant_amount) = calc_fee(amount, bps, rounding).unwrap();
+                assert!(fee <= amount, "fee {fee} above amount {amount} at {bps} bps, {rounding:?}");
+                assert_eq!(fee + merchant_amount, amount, "amount {amount} at {bps} bps, {rounding:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn fee_matches_previous_inline_math() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        for bps in EDGE_BPS.iter().copied().filter(|b| *b <= 10_000).chain([rng.gen_range(0..=10_000)]) {
+            assert_eq!(calc_fee(amount, bps, FeeRounding::Floor).unwrap(), legacy_fee(amount, bps).unwrap());
+        }
+    }
+}
+
+#[test]
+fn fee_never_decreases_as_bps_rises() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        let low = rng.gen_range(0..=10_000);
+        let high = rng.gen_range(low..=10_000);
+        for rounding in ROUNDINGS {
+            let (low_fee, _) = calc_fee(amount, low, rounding).unwrap();
+            let (high_fee, _) = calc_fee(amount, high, rounding).unwrap();
+            assert!(low_fee <= high_fee, "amount {amount}: {low} bps -> {low_fee}, {high} bps -> {high_fee}");
+        }
+    }
+}
+
+#[test]
+fn fee_at_the_bounds() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        for rounding in ROUNDINGS {
+            assert_eq!(calc_fee(amount, 0, rounding).unwrap(), (0, amount));
+            assert_eq!(calc_fee(amount, 10_000, rounding).unwrap(), (amount, 0));
+        }
+    }
+}
+
+#[test]
+fn ceil_rounds_up_by_at_most_one() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        let bps = rng.gen_range(0..=10_000);
+        let (floor, _) = calc_fee(amount, bps, FeeRounding::Floor).unwrap();
+        let (ceil, _) = calc_fee(amount, bps, FeeRounding::Ceil).unwrap();
+        let exact = ((amount as u128) * (bps as u128)).is_multiple_of(10_000);
+        assert_eq!(ceil, if exact { floor } else { floor + 1 }, "amount {amount} at {bps} bps");
+    }
+}
+
+#[test]
+fn ceil_charges_micro_payments() {
+    // 1-199 lamports at 50 bps floor to no fee at all
+    for amount in 1..200 {
+        assert_eq!(calc_fee(amount, 50, FeeRounding::Floor).unwrap(), (0, amount));
+        assert_eq!(calc_fee(amount, 50, FeeRounding::Ceil).unwrap(), (1, amount - 1));
+    }
+}
+
+// Rates above 100% are rejected before they reach here, but the math must
+// still fail cleanly rather than wrap or charge more than the amount
+#[test]
+fn rates_above_100_percent_never_overcharge() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        let bps = rng.gen_range(10_001..=u16::MAX);
+        for rounding in ROUNDINGS {
+            if let Ok((fee, merchant_amount)) = calc_fee(amount, bps, rounding) {
+                assert_eq!(fee + merchant_amount, amount);
+            }
+        }
+    }
+    assert!(calc_fee(u64::MAX, u16::MAX, FeeRounding::Floor).is_err());
+    assert!(calc_fee(u64::MAX, u16::MAX, FeeRounding::Ceil).is_err());
+}
+
+#[test]
+fn referrer_share_stays_within_fee() {
+    let mut rng = rng();
+    for fee in amounts(&mut rng) {
+        let share = bps_share(fee, rng.gen_range(0..=10_000)).unwrap();
+        assert!(share <= fee);
+    }
+}
+
+#[test]
+fn tax_stays_within_the_amount() {
+    // Merchant::tax_on is a bps_share capped at 100%, so even u64::MAX is safe
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        assert!(bps_share(amount, rng.gen_range(0..=10_000)).unwrap() <= amount);
+    }
+    assert_eq!(bps_share(u64::MAX, 10_000).unwrap(), u64::MAX);
+    assert_eq!(bps_share(u64::MAX, 1_900).unwrap(), (u64::MAX as u128 * 1_900 / 10_000) as u64);
+}
+
+#[test]
+fn discount_never_raises_the_amount() {
+    let mut rng = rng();
+    for amount in amounts(&mut rng) {
+        let discount = Discount {
+            merchant: Pubkey::default(),
+            code_hash: [0; 32],
+            discount_bps: rng.gen_range(0..=10_000),
+            max_redemptions: 0,
+            redemptions: 0,
+            expires_at: 0,
+            bump: 0,
+            reserved: [0; 64],
+        };
+        let discounted = discount.apply(amount).unwrap();
+        assert!(discounted <= amount);
+        // The fee is then charged on what the payer actually pays
+        let (fee, merchant_amount) = calc_fee(discounted, rng.gen_range(0..=10_000), FeeRounding::Ceil).unwrap();
+        assert_eq!(fee + merchant_amount, discounted);
+    }
+}
+
+#[test]
+fn split_shares_add_up_to_total() {
+    let mut rng = rng();
+    for total in amounts(&mut rng) {
+        // Random cut points give 1-5 positive shares summing to 10000
+        let count = rng.gen_range(1..=5);
+        let mut cuts: Vec<u16> = (1..count).map(|_| rng.gen_range(1..10_000)).collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        cuts.insert(0, 0);
+        cuts.push(10_000);
+        let recipients: Vec<SplitRecipient> = cuts
+            .windows(2)
+            .map(|w| SplitRecipient { recipient: Pubkey::new_unique(), bps: w[1] - w[0] })
+            .collect();
+        SplitConfig::validate(&recipients).unwrap();
+
+        let config = SplitConfig { merchant: Pubkey::default(), recipients, bump: 0, reserved: [0; 32] };
+        let shares = config.shares(total).unwrap();
+        assert_eq!(shares.len(), config.recipients.len());
+        assert_eq!(shares.iter().map(|s| *s as u128).sum::<u128>(), total as u128);
+        // Only the first recipient picks up rounding dust
+        for (share, r) in shares.iter().zip(&config.recipients).skip(1) {
+            assert_eq!(*share, bps_share(total, r.bps).unwrap());
+        }
+    }
+}
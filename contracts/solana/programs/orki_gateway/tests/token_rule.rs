@@ -0,0 +1,115 @@
+//! The single-token rule process_payment and authorize_payment apply, over
+//! every combination of accepts_any_token, swap_enabled and settlement_token
+//! against SOL, the settlement mint and some other mint.
+
+use anchor_lang::error::Error;
+use anchor_lang::prelude::Pubkey;
+use orki_gateway::errors::ErrorCode;
+use orki_gateway::state::{Merchant, SettlementMode};
+
+const SOL: Pubkey = Pubkey::new_from_array([0; 32]);
+const USDC: Pubkey = Pubkey::new_from_array([1; 32]);
+const OTHER: Pubkey = Pubkey::new_from_array([2; 32]);
+
+fn merchant(settlement_token: Pubkey, accepts_any_token: bool, swap_enabled: bool) -> Merchant {
+    Merchant {
+        owner: Pubkey::new_unique(),
+        settlement_wallet: Pubkey::new_unique(),
+        settlement_token,
+        swap_enabled,
+        name: "Shop".to_string(),
+        bump: 0,
+        sandbox: false,
+        fee_bps_override: None,
+        escrow_enabled: false,
+        active: true,
+        admin_locked: false,
+        previous_merchant: Pubkey::default(),
+        open_invoices: 0,
+        funded_escrows: 0,
+        split_enabled: false,
+        accepted_tokens_enabled: false,
+        min_amount: 0,
+        max_amount: 0,
+        operators: 0,
+        fee_exempt: false,
+        referrer: Pubkey::default(),
+        referrer_bps: 0,
+        accepts_any_token,
+        token_rule_set: true,
+        category: 0,
+        metadata_uri: String::new(),
+        settlement_mode: SettlementMode::Instant,
+        version: Merchant::VERSION,
+        merchant_index: 1,
+        allow_self_payments: false,
+        daily_limit: 0,
+        rewards_enabled: false,
+        rewards_required: false,
+        admin_frozen: false,
+        tax_bps: 0,
+        name_indexed: true,
+        confirmation_message: String::new(),
+        min_interval_per_payer: 0,
+        settlement_routes: 0,
+        discount_codes: 0,
+        open_authorizations: 0,
+        open_streams: 0,
+        reserved: [],
+    }
+}
+
+// The custom error number, or None when the token is accepted
+fn outcome(merchant: &Merchant, token: &Pubkey) -> Option<u32> {
+    match merchant.check_token(token) {
+        Ok(()) => None,
+        Err(Error::AnchorError(e)) => Some(e.error_code_number),
+        Err(e) => panic!("unexpected error {e:?}"),
+    }
+}
+
+fn code(e: ErrorCode) -> Option<u32> {
+    Some(e.into())
+}
+
+#[test]
+fn every_flag_combination() {
+    for settlement_token in [SOL, USDC] {
+        for accepts_any_token in [false, true] {
+            for swap_enabled in [false, true] {
+                let m = merchant(settlement_token, accepts_any_token, swap_enabled);
+                for token in [SOL, USDC, OTHER] {
+                    let expected = if accepts_any_token || token == settlement_token {
+                        None
+                    } else if swap_enabled {
+                        code(ErrorCode::SwapNotAvailable)
+                    } else {
+                        code(ErrorCode::InvalidToken)
+                    };
+                    assert_eq!(
+                        outcome(&m, &token),
+                        expected,
+                        "settlement_token {settlement_token}, accepts_any_token {accepts_any_token}, \
+                         swap_enabled {swap_enabled}, paying {token}"
+                    );
+                    assert_eq!(m.accepts_token(&token), expected.is_none());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn swap_enabled_never_lets_sol_reach_a_token_merchant() {
+    let m = merchant(USDC, false, true);
+    assert_eq!(outcome(&m, &SOL), code(ErrorCode::SwapNotAvailable));
+    assert_eq!(outcome(&m, &USDC), None);
+}
+
+#[test]
+fn swap_enabled_does_not_change_an_any_token_merchant() {
+    for token in [SOL, USDC, OTHER] {
+        assert_eq!(outcome(&merchant(USDC, true, true), &token), None);
+        assert_eq!(outcome(&merchant(USDC, true, false), &token), None);
+    }
+}
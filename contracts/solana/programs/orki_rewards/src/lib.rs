@@ -0,0 +1,101 @@
+//! Example rewards program: awards a payer points for each payment to a
+//! merchant that attached it with the gateway's set_rewards_hook. Shows the
+//! hook's side of the interface: an `on_payment(amount, payment_id)`
+//! instruction whose first account is the merchant's RewardsHook, signing.
+
+use anchor_lang::prelude::*;
+
+declare_id!("HtBvZ8NHEqCCXUHmbydKPQ9vrvY6emS21936mFmeEBno");
+
+/// Base units of the payment per point awarded.
+pub const UNITS_PER_POINT: u64 = 1_000;
+
+#[program]
+pub mod orki_rewards {
+    use super::*;
+
+    /// Opens the payer's points balance with a merchant. The gateway passes
+    /// no signer that could pay rent, so this has to happen before the first
+    /// payment that earns points.
+    pub fn open_points(ctx: Context<OpenPoints>) -> Result<()> {
+        let points = &mut ctx.accounts.points;
+        points.payer = ctx.accounts.payer.key();
+        points.merchant = ctx.accounts.merchant.key();
+        points.bump = ctx.bumps.points;
+        Ok(())
+    }
+
+    /// Called by the gateway after each payment to the merchant.
+    pub fn on_payment(ctx: Context<OnPayment>, amount: u64, payment_id: u64) -> Result<()> {
+        let points = &mut ctx.accounts.points;
+        let earned = amount / UNITS_PER_POINT;
+        points.balance = points.balance.checked_add(earned).ok_or(RewardsError::Overflow)?;
+        points.payments = points.payments.checked_add(1).ok_or(RewardsError::Overflow)?;
+        points.last_payment_id = payment_id;
+        msg!("Awarded {} points for payment {}", earned, payment_id);
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Points {
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub balance: u64,
+    pub payments: u64,
+    pub last_payment_id: u64,
+    pub bump: u8,
+}
+
+#[derive(Accounts)]
+pub struct OpenPoints<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Only a seed; points count for whichever merchant is named
+    pub merchant: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Points::INIT_SPACE,
+        seeds = [b"points", merchant.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    pub points: Account<'info, Points>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OnPayment<'info> {
+    // Only the gateway can sign for the merchant's hook, so a direct call
+    // can't award points for a payment that never happened
+    #[account(
+        owner = orki_gateway::ID,
+        address = orki_gateway::pda::find_rewards_hook_address(&merchant.key()).0 @ RewardsError::NotFromGateway
+    )]
+    pub hook: Signer<'info>,
+
+    /// CHECK: The payer the gateway passed on
+    pub payer: UncheckedAccount<'info>,
+
+    /// CHECK: The merchant the hook belongs to
+    pub merchant: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"points", merchant.key().as_ref(), payer.key().as_ref()],
+        bump = points.bump
+    )]
+    pub points: Account<'info, Points>,
+}
+
+#[error_code]
+pub enum RewardsError {
+    #[msg("on_payment must be called by the gateway through the merchant's rewards hook")]
+    NotFromGateway,
+    #[msg("Points balance overflow")]
+    Overflow,
+}
@@ -29,10 +29,11 @@ describe("Admin Operations", () => {
         const [globalStatePda] = await helper.getGlobalState();
         try {
             await program.methods
-                .initialize(100, feeWallet.publicKey)
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
                 .accountsStrict({
                     globalState: globalStatePda,
-                    admin: admin.publicKey,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
                     systemProgram: SystemProgram.programId,
                 })
                 .signers([admin])
@@ -45,21 +46,100 @@ describe("Admin Operations", () => {
     });
 
     describe("Fee Management", () => {
-        it("should update fee basis points", async () => {
+        const applyFee = () =>
+            program.methods
+                .applyFee()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    caller: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        const setFeeTimelock = (seconds: number) =>
+            program.methods
+                .setFeeTimelock(new anchor.BN(seconds))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        it("should update fee basis points through propose and apply", async () => {
             const [globalStatePda] = await helper.getGlobalState();
             const newFeeBps = 200; // 2%
 
             await program.methods
-                .setFee(newFeeBps)
+                .proposeFee(newFeeBps)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
                     admin: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
 
-            const state = await program.account.globalState.fetch(globalStatePda);
+            let state = await program.account.globalState.fetch(globalStatePda);
+            assert.equal(state.pendingFeeBps, newFeeBps);
+            assert.notEqual(state.feeBps, newFeeBps);
+
+            await applyFee();
+
+            state = await program.account.globalState.fetch(globalStatePda);
             assert.equal(state.feeBps, newFeeBps);
+            assert.equal(state.pendingFeeBps, null);
+        });
+
+        // Lowering the timelock back keeps the old delay's notice running, so
+        // keep it short and wait it out before later tests set fees
+        it("should reject apply_fee before the timelock elapses", async () => {
+            const timelock = 3;
+            await setFeeTimelock(timelock);
+            try {
+                await program.methods
+                    .proposeFee(1000)
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        admin: admin.publicKey,
+                        ...helper.eventCpi,
+                    })
+                    .signers([admin])
+                    .rpc();
+
+                try {
+                    await applyFee();
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.ok(e.message.includes("FeeTimelockNotElapsed"));
+                }
+
+                await program.methods
+                    .cancelFeeProposal()
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        admin: admin.publicKey,
+                        ...helper.eventCpi,
+                    })
+                    .signers([admin])
+                    .rpc();
+
+                const state = await program.account.globalState.fetch(helper.globalStatePda);
+                assert.equal(state.pendingFeeBps, null);
+                assert.equal(state.feeBps, 200);
+
+                try {
+                    await applyFee();
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.ok(e.message.includes("NoPendingFee"));
+                }
+            } finally {
+                await setFeeTimelock(0);
+                await new Promise((resolve) => setTimeout(resolve, (timelock + 1) * 1000));
+            }
         });
         it("should fail if non-admin tries to update fee", async () => {
             const impostor = Keypair.generate();
@@ -67,10 +147,11 @@ describe("Admin Operations", () => {
 
             try {
                 await program.methods
-                    .setFee(300)
+                    .proposeFee(300)
                     .accountsStrict({
                         globalState: helper.globalStatePda,
                         admin: impostor.publicKey,
+                        ...helper.eventCpi,
                     })
                     .signers([impostor])
                     .rpc();
@@ -80,19 +161,41 @@ describe("Admin Operations", () => {
             }
         });
 
-        it("should fail if fee exceeds 10000 bps", async () => {
+        it("should fail if fee exceeds the fee cap", async () => {
+            try {
+                await program.methods
+                    .proposeFee(10001)
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        admin: admin.publicKey,
+                        ...helper.eventCpi,
+                    })
+                    .signers([admin])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("FeeCapExceeded"));
+            }
+        });
+
+        // Lowering the cap is permanent on the shared validator, so only the
+        // rejected direction is exercised here
+        it("should refuse to raise the fee cap", async () => {
+            const state = await program.account.globalState.fetch(helper.globalStatePda);
+            const cap = state.maxFeeBps || 10000;
             try {
                 await program.methods
-                    .setFee(10001)
+                    .reduceMaxFee(cap + 1)
                     .accountsStrict({
                         globalState: helper.globalStatePda,
                         admin: admin.publicKey,
+                        ...helper.eventCpi,
                     })
                     .signers([admin])
                     .rpc();
                 assert.fail("Should have failed");
             } catch (e: any) {
-                assert.ok(e.message.includes("InvalidFee"));
+                assert.ok(e.message.includes("CannotRaiseCap"));
             }
         });
     });
@@ -104,6 +207,7 @@ describe("Admin Operations", () => {
                 .accountsStrict({
                     globalState: helper.globalStatePda,
                     admin: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
@@ -119,7 +223,8 @@ describe("Admin Operations", () => {
                 .setPaused(true)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
-                    admin: admin.publicKey,
+                    authority: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
@@ -131,43 +236,205 @@ describe("Admin Operations", () => {
         it("should unpause the contract", async () => {
             await program.methods
                 .setPaused(false)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    authority: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            const state = await program.account.globalState.fetch(helper.globalStatePda);
+            assert.equal(state.paused, false);
+        });
+    });
+
+    describe("Guardian", () => {
+        const guardian = Keypair.generate();
+
+        const setPaused = (paused: boolean, signer: Keypair) =>
+            program.methods
+                .setPaused(paused)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    authority: signer.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        before(async () => {
+            await helper.airdrop(guardian.publicKey);
+            await program.methods
+                .setGuardian(guardian.publicKey)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+        });
+
+        after(async () => {
+            await setPaused(false, admin);
+            await program.methods
+                .setGuardian(PublicKey.default)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
                     admin: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
+        });
+
+        it("should let the guardian pause", async () => {
+            await setPaused(true, guardian);
+
+            const state = await program.account.globalState.fetch(helper.globalStatePda);
+            assert.equal(state.paused, true);
+            assert.ok(state.guardian.equals(guardian.publicKey));
+        });
+
+        it("should not let the guardian unpause", async () => {
+            try {
+                await setPaused(false, guardian);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("GuardianCannotUnpause"));
+            }
 
+            await setPaused(false, admin);
             const state = await program.account.globalState.fetch(helper.globalStatePda);
             assert.equal(state.paused, false);
         });
+
+        it("should not let the guardian change fees", async () => {
+            try {
+                await program.methods
+                    .proposeFee(1000)
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        admin: guardian.publicKey,
+                        ...helper.eventCpi,
+                    })
+                    .signers([guardian])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should reject a pause from anyone else", async () => {
+            const stranger = Keypair.generate();
+            await helper.airdrop(stranger.publicKey);
+            try {
+                await setPaused(true, stranger);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
     });
 
     describe("Admin Management", () => {
-        it("should transfer admin rights", async () => {
+        it("should propose a new admin without handing over control", async () => {
             await program.methods
-                .updateAdmin(newAdmin.publicKey)
+                .proposeAdmin(newAdmin.publicKey)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
                     admin: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
 
             const state = await program.account.globalState.fetch(helper.globalStatePda);
-            assert.ok(state.admin.equals(newAdmin.publicKey));
+            assert.ok(state.admin.equals(admin.publicKey));
+            assert.ok(state.pendingAdmin.equals(newAdmin.publicKey));
         });
 
-        it("new admin should be able to perform admin actions", async () => {
+        it("should fail if someone other than the pending admin accepts", async () => {
+            const impostor = Keypair.generate();
+            await helper.airdrop(impostor.publicKey);
+
+            try {
+                await program.methods
+                    .acceptAdmin()
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        newAdmin: impostor.publicKey,
+                        ...helper.eventCpi,
+                    })
+                    .signers([impostor])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("NotPendingAdmin"));
+            }
+        });
+
+        it("should cancel a pending admin transfer", async () => {
             await program.methods
-                .setFee(150)
+                .cancelAdminTransfer()
                 .accountsStrict({
                     globalState: helper.globalStatePda,
-                    admin: newAdmin.publicKey,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            const state = await program.account.globalState.fetch(helper.globalStatePda);
+            assert.equal(state.pendingAdmin, null);
+
+            try {
+                await program.methods
+                    .acceptAdmin()
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        newAdmin: newAdmin.publicKey,
+                        ...helper.eventCpi,
+                    })
+                    .signers([newAdmin])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("NotPendingAdmin"));
+            }
+        });
+
+        it("should transfer admin rights once accepted", async () => {
+            await program.methods
+                .proposeAdmin(newAdmin.publicKey)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            await program.methods
+                .acceptAdmin()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    newAdmin: newAdmin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([newAdmin])
                 .rpc();
 
+            const state = await program.account.globalState.fetch(helper.globalStatePda);
+            assert.ok(state.admin.equals(newAdmin.publicKey));
+            assert.equal(state.pendingAdmin, null);
+        });
+
+        it("new admin should be able to perform admin actions", async () => {
+            await helper.setFee(newAdmin, 150);
+
             const state = await program.account.globalState.fetch(helper.globalStatePda);
             assert.equal(state.feeBps, 150);
         });
@@ -175,10 +442,11 @@ describe("Admin Operations", () => {
         it("old admin should no longer have rights", async () => {
             try {
                 await program.methods
-                    .setFee(250)
+                    .proposeFee(250)
                     .accountsStrict({
                         globalState: helper.globalStatePda,
                         admin: admin.publicKey,
+                        ...helper.eventCpi,
                     })
                     .signers([admin])
                     .rpc();
@@ -192,16 +460,67 @@ describe("Admin Operations", () => {
         after(async () => {
             console.log("Restoring admin rights to shared Admin...");
             await program.methods
-                .updateAdmin(admin.publicKey)
+                .proposeAdmin(admin.publicKey)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
                     admin: newAdmin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([newAdmin])
                 .rpc();
+            await program.methods
+                .acceptAdmin()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    newAdmin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
 
             const state = await program.account.globalState.fetch(helper.globalStatePda);
             assert.ok(state.admin.equals(admin.publicKey), "Admin restoration failed");
         });
     });
+
+    describe("Feature Flags", () => {
+        const [featureFlagsPda] = PublicKey.findProgramAddressSync(
+            [Buffer.from("feature_flags")],
+            program.programId
+        );
+
+        const setFeature = (signer: Keypair, enabled: boolean) =>
+            program.methods
+                .setFeature({ token2022: {} }, enabled)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    featureFlags: featureFlagsPda,
+                    admin: signer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([signer])
+                .rpc();
+
+        it("should enable and disable a feature", async () => {
+            await setFeature(admin, true);
+            let flags = await program.account.featureFlags.fetch(featureFlagsPda);
+            assert.ok(!flags.enabled.and(new anchor.BN(1 << 1)).isZero());
+
+            await setFeature(admin, false);
+            flags = await program.account.featureFlags.fetch(featureFlagsPda);
+            assert.ok(flags.enabled.and(new anchor.BN(1 << 1)).isZero());
+        });
+
+        it("should fail if non-admin toggles a feature", async () => {
+            const impostor = Keypair.generate();
+            await helper.airdrop(impostor.publicKey);
+
+            try {
+                await setFeature(impostor, true);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+    });
 });
\ No newline at end of file
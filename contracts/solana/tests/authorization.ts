@@ -0,0 +1,459 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+import { TOKEN_PROGRAM_ID, getAccount, getOrCreateAssociatedTokenAccount } from "@solana/spl-token";
+
+describe("Payment Authorization", () => {
+    const testId = TestHelper.generateTestId("authorization");
+    console.log(`Running authorization tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+    const provider = anchor.getProvider();
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const payer = Keypair.generate();
+
+    const merchantName = "AuthShop";
+
+    let mint: PublicKey;
+    let payerTokenAccount: PublicKey;
+    let merchantTokenAccount: PublicKey;
+    let feeTokenAccount: PublicKey;
+
+    let merchantPda: PublicKey;
+    let nextId = Date.now();
+    const [feeVaultPda] = helper.getFeeVaultPda();
+
+    const authorizeSol = async (amount: anchor.BN) => {
+        const paymentId = new anchor.BN(nextId++);
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+        await program.methods
+            .authorizePayment(amount, paymentId, merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                merchant: merchantPda,
+                payer: payer.publicKey,
+                paymentHistory: paymentPda,
+                systemProgram: SystemProgram.programId,
+                tokenProgram: null,
+                mint: null,
+                payerTokenAccount: null,
+                holdTokenAccount: null,
+                featureFlags: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+        return paymentPda;
+    };
+
+    const captureSol = (paymentPda: PublicKey) =>
+        program.methods
+            .capturePayment(merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payment: paymentPda,
+                owner: merchantOwner.publicKey,
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: feeVaultPda,
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                tokenProgram: null,
+                mint: null,
+                holdTokenAccount: null,
+                merchantTokenAccount: null,
+                feeTokenAccount: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+    const voidSol = (paymentPda: PublicKey, authority: Keypair) =>
+        program.methods
+            .voidPayment(merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                merchant: merchantPda,
+                payment: paymentPda,
+                authority: authority.publicKey,
+                payer: payer.publicKey,
+                tokenProgram: null,
+                mint: null,
+                holdTokenAccount: null,
+                payerTokenAccount: null,
+                ...helper.eventCpi,
+            })
+            .signers([authority])
+            .rpc();
+
+    before(async () => {
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(merchantWallet.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(payer.publicKey, 5 * LAMPORTS_PER_SOL);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                console.log("Global State exists. Updating config...");
+                await helper.setFee(admin, 100);
+                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+
+        // Register an any-token merchant
+        [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+        // Setup SPL token
+        mint = await helper.createTokenMint(payer);
+        payerTokenAccount = await helper.createTokenAccount(mint, payer.publicKey, payer);
+        merchantTokenAccount = await helper.createTokenAccount(mint, merchantWallet.publicKey, payer);
+        feeTokenAccount = await helper.getFeeVaultTokenAccount(mint, payer);
+        await helper.mintTokens(mint, payerTokenAccount, 1_000_000, payer);
+        await helper.openFeeStats(admin, PublicKey.default);
+        await helper.openFeeStats(admin, mint);
+    });
+
+    it("should hold a SOL authorization and pay out on capture", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 2);
+        const fee = amount.muln(100).divn(10000);
+        const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+        const [feeStatsPda] = helper.getFeeStatsPda(PublicKey.default);
+        const feeBefore = await provider.connection.getBalance(feeVaultPda);
+        const collectedBefore = (await program.account.feeStats.fetch(feeStatsPda)).totalFeesCollected;
+
+        const paymentPda = await authorizeSol(amount);
+
+        let payment = await program.account.payment.fetch(paymentPda);
+        assert.ok("authorized" in payment.status);
+        assert.ok(payment.mint.equals(PublicKey.default));
+        assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), walletBefore);
+
+        await captureSol(paymentPda);
+
+        payment = await program.account.payment.fetch(paymentPda);
+        assert.ok("captured" in payment.status);
+        assert.equal(payment.fee.toString(), fee.toString());
+        assert.equal(
+            await provider.connection.getBalance(merchantWallet.publicKey),
+            walletBefore + amount.sub(fee).toNumber()
+        );
+        // The fee lands in the fee vault and is credited like a direct payment's
+        assert.equal(await provider.connection.getBalance(feeVaultPda), feeBefore + fee.toNumber());
+        const collected = (await program.account.feeStats.fetch(feeStatsPda)).totalFeesCollected;
+        assert.equal(collected.sub(collectedBefore).toString(), fee.toString());
+    });
+
+    it("should charge a captured hold the merchant's rate, as a direct payment would", async () => {
+        const setFeeExempt = (exempt: boolean) =>
+            program.methods
+                .setMerchantFeeExempt(merchantName, exempt)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: merchantPda,
+                    admin: admin.publicKey,
+                    adminProposal: null,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+        const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+        const paymentPda = await authorizeSol(amount);
+        await setFeeExempt(true);
+        try {
+            await captureSol(paymentPda);
+        } finally {
+            await setFeeExempt(false);
+        }
+
+        const payment = await program.account.payment.fetch(paymentPda);
+        assert.equal(payment.fee.toNumber(), 0);
+        assert.equal(
+            await provider.connection.getBalance(merchantWallet.publicKey),
+            walletBefore + amount.toNumber()
+        );
+    });
+
+    it("should reject capturing the same authorization twice", async () => {
+        const paymentPda = await authorizeSol(new anchor.BN(LAMPORTS_PER_SOL / 10));
+        await captureSol(paymentPda);
+
+        try {
+            await captureSol(paymentPda);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("InvalidPaymentStatus"));
+        }
+    });
+
+    it("should return held SOL when the merchant voids", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+        const paymentPda = await authorizeSol(amount);
+        const payerBefore = await provider.connection.getBalance(payer.publicKey);
+
+        await voidSol(paymentPda, merchantOwner);
+
+        const payment = await program.account.payment.fetch(paymentPda);
+        assert.ok("voided" in payment.status);
+        assert.equal(await provider.connection.getBalance(payer.publicKey), payerBefore + amount.toNumber());
+
+        try {
+            await captureSol(paymentPda);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("InvalidPaymentStatus"));
+        }
+    });
+
+    it("should not let the payer void before the authorization expires", async () => {
+        const paymentPda = await authorizeSol(new anchor.BN(LAMPORTS_PER_SOL / 10));
+
+        try {
+            await voidSol(paymentPda, payer);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("AuthorizationNotExpired"));
+        }
+
+        await voidSol(paymentPda, merchantOwner);
+    });
+
+    it("should count open holds and refuse to close the merchant over them", async () => {
+        const openBefore = (await program.account.merchant.fetch(merchantPda)).openAuthorizations;
+        const paymentPda = await authorizeSol(new anchor.BN(LAMPORTS_PER_SOL / 10));
+        assert.equal((await program.account.merchant.fetch(merchantPda)).openAuthorizations, openBefore + 1);
+
+        try {
+            await program.methods
+                .closeMerchant(merchantName)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    merchantIndex: await helper.getMerchantIndexPdaOf(merchantPda),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                    owner: merchantOwner.publicKey,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("MerchantHasOpenAuthorizations"));
+        }
+
+        await voidSol(paymentPda, merchantOwner);
+        assert.equal((await program.account.merchant.fetch(merchantPda)).openAuthorizations, openBefore);
+    });
+
+    it("should not let a refund touch an uncaptured authorization", async () => {
+        const paymentPda = await authorizeSol(new anchor.BN(LAMPORTS_PER_SOL / 10));
+
+        try {
+            await program.methods
+                .refundPartial(merchantName, new anchor.BN(1))
+                .accountsStrict({
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payment: paymentPda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payer: payer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    authorityTokenAccount: null,
+                    payerTokenAccount: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("InvalidPaymentStatus"));
+        }
+
+        await voidSol(paymentPda, merchantOwner);
+    });
+
+    it("should hold SPL tokens in a payment-owned account and pay out on capture", async () => {
+        const amount = new anchor.BN(200_000);
+        const fee = amount.muln(100).divn(10000);
+        const paymentId = new anchor.BN(nextId++);
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+        const holdTokenAccount = (
+            await getOrCreateAssociatedTokenAccount(provider.connection, payer, mint, paymentPda, true)
+        ).address;
+
+        await program.methods
+            .authorizePayment(amount, paymentId, merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                merchant: merchantPda,
+                payer: payer.publicKey,
+                paymentHistory: paymentPda,
+                systemProgram: SystemProgram.programId,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                payerTokenAccount: payerTokenAccount,
+                holdTokenAccount: holdTokenAccount,
+                featureFlags: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+
+        const held = await getAccount(provider.connection, holdTokenAccount);
+        assert.equal(held.amount.toString(), amount.toString());
+
+        await program.methods
+            .capturePayment(merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payment: paymentPda,
+                owner: merchantOwner.publicKey,
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: feeVaultPda,
+                feeStats: helper.getFeeStatsPda(mint)[0],
+                tokenFee: helper.getTokenFeePda(mint)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                holdTokenAccount: holdTokenAccount,
+                merchantTokenAccount: merchantTokenAccount,
+                feeTokenAccount: feeTokenAccount,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+        const merchantAcct = await getAccount(provider.connection, merchantTokenAccount);
+        assert.equal(merchantAcct.amount.toString(), amount.sub(fee).toString());
+        const feeAcct = await getAccount(provider.connection, feeTokenAccount);
+        assert.equal(feeAcct.amount.toString(), fee.toString());
+        // The hold account is closed back to the payer
+        assert.isNull(await provider.connection.getAccountInfo(holdTokenAccount));
+    });
+
+
+    describe("Closing Payments", () => {
+        const DEFAULT_RETENTION = 90 * 24 * 60 * 60;
+        let capturedPda: PublicKey;
+
+        const setRetention = (seconds: number) =>
+            program.methods
+                .setPaymentRetention(new anchor.BN(seconds))
+                .accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi })
+                .signers([admin])
+                .rpc();
+
+        const closePayment = (paymentPda: PublicKey, closer: Keypair) =>
+            program.methods
+                .closePayment()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    payment: paymentPda,
+                    payer: payer.publicKey,
+                    closer: closer.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([closer])
+                .rpc();
+
+        before(async () => {
+            await setRetention(DEFAULT_RETENTION);
+            capturedPda = await authorizeSol(new anchor.BN(LAMPORTS_PER_SOL / 100));
+            await captureSol(capturedPda);
+        });
+
+        after(async () => {
+            await setRetention(DEFAULT_RETENTION);
+        });
+
+        it("should not close a payment inside its retention window", async () => {
+            try {
+                await closePayment(capturedPda, payer);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PaymentRetentionActive"));
+            }
+        });
+
+        it("should never close an authorized hold", async () => {
+            await setRetention(1);
+            const heldPda = await authorizeSol(new anchor.BN(LAMPORTS_PER_SOL / 100));
+            await new Promise((resolve) => setTimeout(resolve, 3000));
+
+            try {
+                await closePayment(heldPda, payer);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidPaymentStatus"));
+            }
+        });
+
+        it("should let anyone close an aged payment and return rent to the payer", async () => {
+            const crank = Keypair.generate();
+            await helper.airdrop(crank.publicKey);
+            const rent = await provider.connection.getBalance(capturedPda);
+            const payerBefore = await provider.connection.getBalance(payer.publicKey);
+
+            await closePayment(capturedPda, crank);
+
+            assert.isNull(await provider.connection.getAccountInfo(capturedPda));
+            assert.equal(await provider.connection.getBalance(payer.publicKey), payerBefore + rent);
+        });
+    });
+});
@@ -0,0 +1,175 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { OrkiCheckout } from "../target/types/orki_checkout";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+
+// orki_checkout is the example caller in programs/; it pays through the
+// gateway's process_payment via CPI, signing as its own buyer PDA, and
+// confirms payments through verify_payment.
+describe("Checkout CPI", () => {
+    const testId = TestHelper.generateTestId("checkout");
+    console.log(`Running checkout CPI tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const checkout = anchor.workspace.OrkiCheckout as Program<OrkiCheckout>;
+    const helper = new TestHelper(program, testId);
+    const provider = anchor.getProvider();
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const shopper = Keypair.generate();
+
+    const merchantName = "CheckoutShop";
+    const [feeVaultPda] = helper.getFeeVaultPda();
+    const [buyerPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyer"), shopper.publicKey.toBuffer()],
+        checkout.programId
+    );
+    let merchantPda: PublicKey;
+
+    const pay = (amount: anchor.BN, paymentId: anchor.BN, signer: Keypair = shopper, buyer: PublicKey = buyerPda) =>
+        checkout.methods
+            .pay(amount, paymentId, merchantName)
+            .accountsStrict({
+                authority: signer.publicKey,
+                buyer,
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: feeVaultPda,
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                paymentHistory: helper.getPaymentPda(buyer, paymentId)[0],
+                blocklistEntry: helper.getBlockedPda(buyer)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                eventAuthority: helper.eventCpi.eventAuthority,
+                gatewayProgram: program.programId,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([signer])
+            .rpc();
+
+    const confirm = (paymentId: anchor.BN, minAmount: anchor.BN) =>
+        checkout.methods
+            .confirmPayment(paymentId, minAmount)
+            .accountsStrict({
+                authority: shopper.publicKey,
+                buyer: buyerPda,
+                merchant: merchantPda,
+                payment: helper.getPaymentPda(buyerPda, paymentId)[0],
+                gatewayProgram: program.programId,
+            })
+            .signers([shopper])
+            .view();
+
+    before(async () => {
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(shopper.publicKey);
+        // The buyer PDA holds the shopper's checkout balance
+        await helper.airdrop(buyerPda, 2 * LAMPORTS_PER_SOL);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                await helper.setFee(admin, 100);
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+        await helper.openFeeStats(admin, PublicKey.default);
+
+        [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+    });
+
+    it("should settle a payment made by a program PDA through CPI", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+        const fee = amount.muln(100).divn(10000);
+        const paymentId = new anchor.BN(Date.now());
+        const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+        const vaultBefore = await provider.connection.getBalance(feeVaultPda);
+
+        await pay(amount, paymentId);
+
+        assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), walletBefore + amount.sub(fee).toNumber());
+        assert.equal(await provider.connection.getBalance(feeVaultPda), vaultBefore + fee.toNumber());
+
+        // The record is keyed by, and belongs to, the calling program's PDA
+        const payment = await program.account.payment.fetch(helper.getPaymentPda(buyerPda, paymentId)[0]);
+        assert.ok(payment.payer.equals(buyerPda));
+        assert.ok(payment.merchant.equals(merchantPda));
+        assert.equal(payment.amount.toString(), amount.toString());
+    });
+
+    it("should confirm a payment through verify_payment", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 20);
+        const paymentId = new anchor.BN(Date.now() + 1);
+        await pay(amount, paymentId);
+
+        const paid = await confirm(paymentId, amount);
+        assert.equal(paid.toString(), amount.toString());
+
+        try {
+            await confirm(paymentId, amount.addn(1));
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("AmountTooSmall") || JSON.stringify(e).includes("AmountTooSmall"));
+        }
+    });
+
+    it("should report a payment that was never made as not found", async () => {
+        try {
+            await confirm(new anchor.BN(Date.now() + 2), new anchor.BN(1));
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("PaymentNotFound") || JSON.stringify(e).includes("PaymentNotFound"));
+        }
+    });
+
+    it("should not let another signer spend from someone else's buyer PDA", async () => {
+        const intruder = Keypair.generate();
+        await helper.airdrop(intruder.publicKey);
+        try {
+            await pay(new anchor.BN(1_000_000), new anchor.BN(Date.now()), intruder, buyerPda);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("ConstraintSeeds"));
+        }
+    });
+});
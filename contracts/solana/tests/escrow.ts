@@ -0,0 +1,606 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+import { TOKEN_PROGRAM_ID, getAccount, getOrCreateAssociatedTokenAccount, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
+
+describe("Escrow Settlement", () => {
+    const testId = TestHelper.generateTestId("escrow");
+    console.log(`Running escrow tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+    const provider = anchor.getProvider();
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const payer = Keypair.generate();
+
+    const merchantName = "EscrowShop";
+
+    let mint: PublicKey;
+    let payerTokenAccount: PublicKey;
+    let feeTokenAccount: PublicKey;
+    let settlementTokenAccount: PublicKey;
+    let vaultTokenAccount: PublicKey;
+
+    let merchantPda: PublicKey;
+    let vaultPda: PublicKey;
+    let solEscrowPda: PublicKey;
+    let splEscrowPda: PublicKey;
+
+    const setDelay = (delay: number) =>
+        program.methods
+            .setEscrowReleaseDelay(new anchor.BN(delay))
+            .accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi })
+            .signers([admin])
+            .rpc();
+
+    const setFinality = (slots: number) =>
+        program.methods
+            .setFinalitySlots(new anchor.BN(slots))
+            .accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi })
+            .signers([admin])
+            .rpc();
+
+    // Waits out the finality depth, then releases the payment's escrowed share
+    const finalize = async (paymentId: anchor.BN, escrowPda: PublicKey) => {
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+        const { slot } = await program.account.payment.fetch(paymentPda);
+        const { finalitySlots } = await program.account.globalState.fetch(helper.globalStatePda);
+        while ((await provider.connection.getSlot()) < slot.add(finalitySlots).toNumber()) {
+            await new Promise((resolve) => setTimeout(resolve, 400));
+        }
+        await program.methods
+            .finalizePayment(paymentId)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                payer: payer.publicKey,
+                payment: paymentPda,
+                escrowBalance: escrowPda,
+                ...helper.eventCpi,
+            })
+            .rpc();
+    };
+
+    const claimSol = () =>
+        program.methods
+            .claimSettlement(merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                merchant: merchantPda,
+                vault: vaultPda,
+                escrowBalance: solEscrowPda,
+                owner: merchantOwner.publicKey,
+                settlementWallet: merchantWallet.publicKey,
+                tokenProgram: null,
+                mint: null,
+                vaultTokenAccount: null,
+                settlementTokenAccount: null,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+    before(async () => {
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(payer.publicKey, 5 * LAMPORTS_PER_SOL);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                console.log("Global State exists. Updating config...");
+                await helper.setFee(admin, 100);
+                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+        await setDelay(0);
+        await setFinality(1);
+
+        // Register an any-token merchant and switch it to escrow mode
+        [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+        [vaultPda] = helper.getVaultPda(merchantPda);
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+        await program.methods
+            .updateMerchant(merchantName, null, null, null, true, null, null, null, null, null)
+            .accountsStrict({
+                merchant: merchantPda,
+                authority: merchantOwner.publicKey,
+                operator: null,
+                payoutBook: null,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+        // Setup SPL token
+        mint = await helper.createTokenMint(payer);
+        payerTokenAccount = await helper.createTokenAccount(mint, payer.publicKey, payer);
+        feeTokenAccount = await helper.getFeeVaultTokenAccount(mint, payer);
+        settlementTokenAccount = await helper.createTokenAccount(mint, merchantWallet.publicKey, payer);
+        await helper.mintTokens(mint, payerTokenAccount, 1_000_000, payer);
+        await helper.openFeeStats(admin, PublicKey.default);
+        await helper.openFeeStats(admin, mint);
+
+        // Open SOL and SPL escrow balances; the vault is created on first use
+        [solEscrowPda] = helper.getEscrowBalancePda(merchantPda, PublicKey.default);
+        [splEscrowPda] = helper.getEscrowBalancePda(merchantPda, mint);
+        for (const [escrowMint, escrowPda] of [[PublicKey.default, solEscrowPda], [mint, splEscrowPda]]) {
+            await program.methods
+                .openEscrow(merchantName, escrowMint)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    vault: vaultPda,
+                    escrowBalance: escrowPda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        }
+
+        vaultTokenAccount = (
+            await getOrCreateAssociatedTokenAccount(provider.connection, payer, mint, vaultPda, true)
+        ).address;
+    });
+
+    after(async () => {
+        await setDelay(0);
+        await setFinality(32);
+    });
+
+    it("should hold a SOL payment in the vault and release it on claim", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 2);
+        const paymentId = new anchor.BN(Date.now());
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+        const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+
+        const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+        const vaultBefore = await provider.connection.getBalance(vaultPda);
+
+        await program.methods
+            .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                paymentHistory: paymentPda,
+                blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                systemProgram: SystemProgram.programId,
+                tokenProgram: null,
+                mint: null,
+                payerTokenAccount: null,
+                merchantTokenAccount: null,
+                feeTokenAccount: null,
+                associatedTokenProgram: null,
+                featureFlags: null,
+                vault: vaultPda,
+                escrowBalance: solEscrowPda,
+                merchantBalance: null,
+                vaultTokenAccount: null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+
+        assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), walletBefore);
+        assert.equal(await provider.connection.getBalance(vaultPda), vaultBefore + merchantAmount.toNumber());
+        const escrow = await program.account.escrowBalance.fetch(solEscrowPda);
+        assert.equal(escrow.amount.toString(), merchantAmount.toString());
+        assert.equal(escrow.pendingAmount.toString(), merchantAmount.toString());
+
+        // Nothing is claimable until the payment is final
+        try {
+            await claimSol();
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("NothingToClaim"));
+        }
+
+        await finalize(paymentId, solEscrowPda);
+        const payment = await program.account.payment.fetch(paymentPda);
+        assert.deepEqual(payment.status, { finalized: {} });
+        await claimSol();
+
+        assert.equal(
+            await provider.connection.getBalance(merchantWallet.publicKey),
+            walletBefore + merchantAmount.toNumber()
+        );
+        assert.equal(await provider.connection.getBalance(vaultPda), vaultBefore);
+        const drained = await program.account.escrowBalance.fetch(solEscrowPda);
+        assert.equal(drained.amount.toNumber(), 0);
+    });
+
+    it("should refuse a claim inside the release delay", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+        const paymentId = new anchor.BN(Date.now() + 1);
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+
+        await program.methods
+            .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                paymentHistory: paymentPda,
+                blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                systemProgram: SystemProgram.programId,
+                tokenProgram: null,
+                mint: null,
+                payerTokenAccount: null,
+                merchantTokenAccount: null,
+                feeTokenAccount: null,
+                associatedTokenProgram: null,
+                featureFlags: null,
+                vault: vaultPda,
+                escrowBalance: solEscrowPda,
+                merchantBalance: null,
+                vaultTokenAccount: null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+        await finalize(paymentId, solEscrowPda);
+
+        await setDelay(3600);
+        try {
+            await claimSol();
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("EscrowLocked"));
+        }
+
+        await setDelay(0);
+        await claimSol();
+    });
+
+    it("should hold an SPL payment in the vault token account and release it on claim", async () => {
+        const amount = new anchor.BN(200_000);
+        const paymentId = new anchor.BN(Date.now() + 2);
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+        const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+
+        await program.methods
+            .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(mint)[0],
+                paymentHistory: paymentPda,
+                blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(mint)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                systemProgram: SystemProgram.programId,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                payerTokenAccount: payerTokenAccount,
+                merchantTokenAccount: null,
+                feeTokenAccount: feeTokenAccount,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                featureFlags: null,
+                vault: vaultPda,
+                escrowBalance: splEscrowPda,
+                merchantBalance: null,
+                vaultTokenAccount: vaultTokenAccount,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+
+        const vaultAcct = await getAccount(provider.connection, vaultTokenAccount);
+        assert.equal(vaultAcct.amount.toString(), merchantAmount.toString());
+        await finalize(paymentId, splEscrowPda);
+
+        await program.methods
+            .claimSettlement(merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                merchant: merchantPda,
+                vault: vaultPda,
+                escrowBalance: splEscrowPda,
+                owner: merchantOwner.publicKey,
+                settlementWallet: merchantWallet.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                vaultTokenAccount: vaultTokenAccount,
+                settlementTokenAccount: settlementTokenAccount,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+        const settlementAcct = await getAccount(provider.connection, settlementTokenAccount);
+        assert.equal(settlementAcct.amount.toString(), merchantAmount.toString());
+        const escrow = await program.account.escrowBalance.fetch(splEscrowPda);
+        assert.equal(escrow.amount.toNumber(), 0);
+    });
+
+
+    describe("Disputes", () => {
+        const arbiter = Keypair.generate();
+
+        const setArbiter = (key: PublicKey) =>
+            program.methods
+                .setArbiter(key)
+                .accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi })
+                .signers([admin])
+                .rpc();
+
+        const paySol = async (amount: anchor.BN) => {
+            const paymentId = new anchor.BN(Date.now());
+            await program.methods
+                .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: vaultPda,
+                    escrowBalance: solEscrowPda,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+            await finalize(paymentId, solEscrowPda);
+            return paymentId;
+        };
+
+        const openDispute = (paymentId: anchor.BN) =>
+            program.methods
+                .openDispute()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payment: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    escrowBalance: solEscrowPda,
+                    payer: payer.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+        const resolveDispute = (paymentId: anchor.BN, refund: boolean, signer = arbiter) =>
+            program.methods
+                .resolveDispute(refund)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    arbiter: signer.publicKey,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payment: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    payer: payer.publicKey,
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    vault: vaultPda,
+                    escrowBalance: solEscrowPda,
+                    tokenProgram: null,
+                    mint: null,
+                    vaultTokenAccount: null,
+                    payerTokenAccount: null,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        before(async () => {
+            await setArbiter(arbiter.publicKey);
+        });
+
+        after(async () => {
+            await setArbiter(PublicKey.default);
+        });
+
+        it("should refund a disputed payment from the vault", async () => {
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+            const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+            const paymentId = await paySol(amount);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+
+            await openDispute(paymentId);
+            const disputed = await program.account.payment.fetch(paymentPda);
+            assert.deepEqual(disputed.status, { disputed: {} });
+            const held = await program.account.escrowBalance.fetch(solEscrowPda);
+            assert.equal(held.disputedAmount.toString(), merchantAmount.toString());
+
+            // The disputed share is all that is left, so there is nothing to claim
+            try {
+                await claimSol();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("NothingToClaim"));
+            }
+
+            try {
+                await resolveDispute(paymentId, true, merchantOwner);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+
+            const payerBefore = await provider.connection.getBalance(payer.publicKey);
+            const vaultBefore = await provider.connection.getBalance(vaultPda);
+            await resolveDispute(paymentId, true);
+
+            assert.equal(await provider.connection.getBalance(payer.publicKey), payerBefore + merchantAmount.toNumber());
+            assert.equal(await provider.connection.getBalance(vaultPda), vaultBefore - merchantAmount.toNumber());
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.deepEqual(payment.status, { disputeResolved: {} });
+            // The fee was never in escrow, so only the merchant's share counts as refunded
+            assert.equal(payment.refundedAmount.toString(), merchantAmount.toString());
+            const escrow = await program.account.escrowBalance.fetch(solEscrowPda);
+            assert.equal(escrow.amount.toNumber(), 0);
+            assert.equal(escrow.disputedAmount.toNumber(), 0);
+        });
+
+        it("should release a disputed payment to the merchant", async () => {
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+            const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+            const paymentId = await paySol(amount);
+
+            await openDispute(paymentId);
+            await resolveDispute(paymentId, false);
+
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(payer.publicKey, paymentId)[0]);
+            assert.deepEqual(payment.status, { disputeResolved: {} });
+            assert.equal(payment.refundedAmount.toNumber(), 0);
+
+            // The ruling is final: the payer can't hold the share back again
+            try {
+                await openDispute(paymentId);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidPaymentStatus"));
+            }
+
+            const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+            await claimSol();
+            assert.equal(
+                await provider.connection.getBalance(merchantWallet.publicKey),
+                walletBefore + merchantAmount.toNumber()
+            );
+        });
+
+        it("should reject a dispute once the merchant has claimed", async () => {
+            const paymentId = await paySol(new anchor.BN(LAMPORTS_PER_SOL / 10));
+            await claimSol();
+
+            try {
+                await openDispute(paymentId);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("DisputeWindowClosed"));
+            }
+        });
+    });
+});
@@ -5,7 +5,7 @@ import { OrkiGateway } from "../target/types/orki_gateway";
 import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { assert } from "chai";
 import { TestHelper } from "./utils/helpers";
-import { TOKEN_PROGRAM_ID, getAccount } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, getAccount, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
 
 describe("Orki Gateway - Integration Tests", () => {
     const testId = TestHelper.generateTestId("integration");
@@ -69,10 +69,11 @@ describe("Orki Gateway - Integration Tests", () => {
         // Ensure Global State is Configured correctly for this test suite
         try {
             await program.methods
-                .initialize(150, feeWallet.publicKey) // 1.5% fee
+                .initialize(150, feeWallet.publicKey, 10000, admin.publicKey, null) // 1.5% fee
                 .accountsStrict({
                     globalState: globalStatePda,
-                    admin: admin.publicKey,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
                     systemProgram: SystemProgram.programId,
                 })
                 .signers([admin])
@@ -81,9 +82,9 @@ describe("Orki Gateway - Integration Tests", () => {
             if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
                 console.log("Global State exists. Updating config...");
                 // Update Fee and Wallet to match this test's expectations
-                await program.methods.setFee(150).accountsStrict({ globalState: globalStatePda, admin: admin.publicKey }).signers([admin]).rpc();
-                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: globalStatePda, admin: admin.publicKey }).signers([admin]).rpc();
-                await program.methods.setPaused(false).accountsStrict({ globalState: globalStatePda, admin: admin.publicKey }).signers([admin]).rpc();
+                await helper.setFee(admin, 150);
+                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                await program.methods.setPaused(false).accountsStrict({ globalState: globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
             } else {
                 throw e;
             }
@@ -102,11 +103,17 @@ describe("Orki Gateway - Integration Tests", () => {
         // 2. Register SOL merchant
         console.log("2. Registering SOL merchant...");
         await program.methods
-            .registerMerchant(merchant1Wallet.publicKey, PublicKey.default, merchant1Name)
+            .registerMerchant(merchant1Wallet.publicKey, PublicKey.default, merchant1Name, false, null, 0, true, "", 0)
             .accountsStrict({
                 merchant: merchant1Pda, // Just PublicKey, not tuple
+                merchantStats: helper.getMerchantStatsPda(merchant1Pda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchant1Owner.publicKey, merchant1Name)[0],
                 owner: merchant1Owner.publicKey,
                 systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
             })
             .signers([merchant1Owner])
             .rpc();
@@ -116,18 +123,26 @@ describe("Orki Gateway - Integration Tests", () => {
         splMint = await helper.createTokenMint(customer1);
         customer1TokenAccount = await helper.createTokenAccount(splMint, customer1.publicKey, customer1);
         merchant2TokenAccount = await helper.createTokenAccount(splMint, merchant2Wallet.publicKey, customer1);
-        feeTokenAccount = await helper.createTokenAccount(splMint, feeWallet.publicKey, customer1);
+        feeTokenAccount = await helper.getFeeVaultTokenAccount(splMint, customer1);
 
         await helper.mintTokens(splMint, customer1TokenAccount, 10_000_000, customer1);
+        await helper.openFeeStats(admin, PublicKey.default);
+        await helper.openFeeStats(admin, splMint);
 
         // 4. Register SPL merchant
         console.log("4. Registering SPL merchant...");
         await program.methods
-            .registerMerchant(merchant2Wallet.publicKey, splMint, merchant2Name)
+            .registerMerchant(merchant2Wallet.publicKey, splMint, merchant2Name, false, null, 0, false, "", 0)
             .accountsStrict({
                 merchant: merchant2Pda, // Just PublicKey, not tuple
+                merchantStats: helper.getMerchantStatsPda(merchant2Pda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchant2Owner.publicKey, merchant2Name)[0],
                 owner: merchant2Owner.publicKey,
                 systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
             })
             .signers([merchant2Owner])
             .rpc();
@@ -139,30 +154,59 @@ describe("Orki Gateway - Integration Tests", () => {
         const [solPaymentPda] = helper.getPaymentPda(customer1.publicKey, solPaymentId); // Extract PublicKey
 
         const merchant1BalanceBefore = await provider.connection.getBalance(merchant1Wallet.publicKey);
-        const feeBalanceBefore = await provider.connection.getBalance(feeWallet.publicKey);
+        const [feeVaultPda] = helper.getFeeVaultPda();
+        const feeBalanceBefore = await provider.connection.getBalance(feeVaultPda);
 
         await program.methods
-            .processPayment(solAmount, solPaymentId, merchant1Name)
+            .processPayment(solAmount, solPaymentId, merchant1Name, null, null, null, new anchor.BN(0))
             .accountsStrict({
                 globalState: globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
                 merchant: merchant1Pda,
+                merchantStats: helper.getMerchantStatsPda(merchant1Pda)[0],
                 payer: customer1.publicKey,
                 merchantWallet: merchant1Wallet.publicKey,
-                feeWallet: feeWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
                 paymentHistory: solPaymentPda, // Just PublicKey
+                blocklistEntry: helper.getBlockedPda(customer1.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
                 systemProgram: SystemProgram.programId,
                 tokenProgram: null,
                 mint: null,
                 payerTokenAccount: null,
                 merchantTokenAccount: null,
                 feeTokenAccount: null,
+                associatedTokenProgram: null,
+                featureFlags: null,
+                vault: null,
+                escrowBalance: null,
+                merchantBalance: null,
+                vaultTokenAccount: null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
             })
             .signers([customer1])
             .rpc();
 
         // Verify SOL payment
         const merchant1BalanceAfter = await provider.connection.getBalance(merchant1Wallet.publicKey);
-        const feeBalanceAfter = await provider.connection.getBalance(feeWallet.publicKey);
+        const feeBalanceAfter = await provider.connection.getBalance(feeVaultPda);
 
         const expectedFee = solAmount.mul(new anchor.BN(150)).div(new anchor.BN(10000));
         const expectedMerchantAmount = solAmount.sub(expectedFee);
@@ -186,20 +230,48 @@ describe("Orki Gateway - Integration Tests", () => {
         const feeTokenBalanceBefore = await getAccount(provider.connection, feeTokenAccount);
 
         await program.methods
-            .processPayment(splAmount, splPaymentId, merchant2Name)
+            .processPayment(splAmount, splPaymentId, merchant2Name, null, null, null, new anchor.BN(0))
             .accountsStrict({
                 globalState: globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
                 merchant: merchant2Pda,
+                merchantStats: helper.getMerchantStatsPda(merchant2Pda)[0],
                 payer: customer1.publicKey,
                 merchantWallet: merchant2Wallet.publicKey,
-                feeWallet: feeWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(splMint)[0],
                 paymentHistory: splPaymentPda, // Just PublicKey
+                blocklistEntry: helper.getBlockedPda(customer1.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(splMint)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
                 systemProgram: SystemProgram.programId,
                 tokenProgram: TOKEN_PROGRAM_ID,
                 mint: splMint,
                 payerTokenAccount: customer1TokenAccount,
                 merchantTokenAccount: merchant2TokenAccount,
                 feeTokenAccount: feeTokenAccount,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                featureFlags: null,
+                vault: null,
+                escrowBalance: null,
+                merchantBalance: null,
+                vaultTokenAccount: null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
             })
             .signers([customer1])
             .rpc();
@@ -222,37 +294,55 @@ describe("Orki Gateway - Integration Tests", () => {
 
         // 7. Admin updates fee
         console.log("7. Admin updating fee...");
-        await program.methods
-            .setFee(200) // 2%
-            .accountsStrict({
-                globalState: globalStatePda,
-                admin: admin.publicKey,
-            })
-            .signers([admin])
-            .rpc();
+        await helper.setFee(admin, 200); // 2%
 
         const state = await program.account.globalState.fetch(globalStatePda);
         assert.equal(state.feeBps, 200);
 
-        // 8. Merchant updates their settings
+        // 8. Merchant renames the shop and updates their settings
         console.log("8. Merchant updating settings...");
         const newMerchantName = "UpdatedTokenStore";
+        const [renamedPda] = helper.getMerchantPda(merchant2Owner.publicKey, newMerchantName);
+        await program.methods
+            .renameMerchant(merchant2Name, newMerchantName)
+            .accountsStrict({
+                merchant: merchant2Pda,
+                newMerchant: renamedPda,
+                merchantStats: helper.getMerchantStatsPda(merchant2Pda)[0],
+                newMerchantStats: helper.getMerchantStatsPda(renamedPda)[0],
+                merchantIndex: await helper.getMerchantIndexPdaOf(merchant2Pda),
+                merchantName: helper.getMerchantNamePda(merchant2Owner.publicKey, merchant2Name)[0],
+                newMerchantName: helper.getMerchantNamePda(merchant2Owner.publicKey, newMerchantName)[0],
+                owner: merchant2Owner.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([merchant2Owner])
+            .rpc();
+
         await program.methods
             .updateMerchant(
-                merchant2Name,
                 newMerchantName,
                 null,
                 null,
-                true // Enable swap
+                true, // Enable swap
+                null,
+                null,
+                null,
+                null,
+                null,
+                null
             )
             .accountsStrict({
-                merchant: merchant2Pda,
-                owner: merchant2Owner.publicKey,
+                merchant: renamedPda,
+                authority: merchant2Owner.publicKey,
+                operator: null,
+                payoutBook: null,
+                ...helper.eventCpi,
             })
             .signers([merchant2Owner])
             .rpc();
 
-        const updatedMerchant = await program.account.merchant.fetch(merchant2Pda);
+        const updatedMerchant = await program.account.merchant.fetch(renamedPda);
         assert.equal(updatedMerchant.name, newMerchantName);
         assert.equal(updatedMerchant.swapEnabled, true);
 
@@ -262,13 +352,23 @@ describe("Orki Gateway - Integration Tests", () => {
         await helper.airdrop(newAdmin.publicKey);
 
         await program.methods
-            .updateAdmin(newAdmin.publicKey)
+            .proposeAdmin(newAdmin.publicKey)
             .accountsStrict({
                 globalState: globalStatePda,
                 admin: admin.publicKey,
+                ...helper.eventCpi,
             })
             .signers([admin])
             .rpc();
+        await program.methods
+            .acceptAdmin()
+            .accountsStrict({
+                globalState: globalStatePda,
+                newAdmin: newAdmin.publicKey,
+                ...helper.eventCpi,
+            })
+            .signers([newAdmin])
+            .rpc();
 
         const finalState = await program.account.globalState.fetch(globalStatePda);
         assert.ok(finalState.admin.equals(newAdmin.publicKey));
@@ -276,13 +376,23 @@ describe("Orki Gateway - Integration Tests", () => {
 
         console.log("Restoring admin rights in integration test...");
         await program.methods
-            .updateAdmin(admin.publicKey)
+            .proposeAdmin(admin.publicKey)
             .accountsStrict({
                 globalState: globalStatePda,
                 admin: newAdmin.publicKey,
+                ...helper.eventCpi,
             })
             .signers([newAdmin])
             .rpc();
+        await program.methods
+            .acceptAdmin()
+            .accountsStrict({
+                globalState: globalStatePda,
+                newAdmin: admin.publicKey,
+                ...helper.eventCpi,
+            })
+            .signers([admin])
+            .rpc();
 
         const restoredState = await program.account.globalState.fetch(globalStatePda);
         assert.ok(restoredState.admin.equals(admin.publicKey));
@@ -298,20 +408,48 @@ describe("Orki Gateway - Integration Tests", () => {
 
         // First payment should succeed
         await program.methods
-            .processPayment(amount, paymentId, merchant1Name)
+            .processPayment(amount, paymentId, merchant1Name, null, null, null, new anchor.BN(0))
             .accountsStrict({
                 globalState: globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
                 merchant: merchant1Pda,
+                merchantStats: helper.getMerchantStatsPda(merchant1Pda)[0],
                 payer: customer2.publicKey,
                 merchantWallet: merchant1Wallet.publicKey,
-                feeWallet: feeWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
                 paymentHistory: paymentPda, // Just PublicKey
+                blocklistEntry: helper.getBlockedPda(customer2.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
                 systemProgram: SystemProgram.programId,
                 tokenProgram: null,
                 mint: null,
                 payerTokenAccount: null,
                 merchantTokenAccount: null,
                 feeTokenAccount: null,
+                associatedTokenProgram: null,
+                featureFlags: null,
+                vault: null,
+                escrowBalance: null,
+                merchantBalance: null,
+                vaultTokenAccount: null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
             })
             .signers([customer2])
             .rpc();
@@ -319,26 +457,54 @@ describe("Orki Gateway - Integration Tests", () => {
         // Second payment with same ID should fail
         try {
             await program.methods
-                .processPayment(amount, paymentId, merchant1Name)
+                .processPayment(amount, paymentId, merchant1Name, null, null, null, new anchor.BN(0))
                 .accountsStrict({
                     globalState: globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
                     merchant: merchant1Pda,
+                    merchantStats: helper.getMerchantStatsPda(merchant1Pda)[0],
                     payer: customer2.publicKey,
                     merchantWallet: merchant1Wallet.publicKey,
-                    feeWallet: feeWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
                     paymentHistory: paymentPda, // Just PublicKey
+                    blocklistEntry: helper.getBlockedPda(customer2.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
                     systemProgram: SystemProgram.programId,
                     tokenProgram: null,
                     mint: null,
                     payerTokenAccount: null,
                     merchantTokenAccount: null,
                     feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
                 })
                 .signers([customer2])
                 .rpc();
             assert.fail("Duplicate payment should have failed");
         } catch (e: any) {
-            assert.ok(e.message.includes("already in use"));
+            assert.ok(e.message.includes("DuplicatePayment"));
         }
     });
 });
\ No newline at end of file
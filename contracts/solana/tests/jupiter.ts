@@ -1,11 +1,186 @@
 import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
 
+const JUPITER_PROGRAM_ID = new PublicKey("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
+// Needs `yarn test:swap`, which builds with the swap feature. Localnet has no
+// Jupiter deployment, so these cover the checks made before the route runs.
 describe("Orki Gateway - Jupiter Integration", () => {
+    const testId = TestHelper.generateTestId("swap");
+    console.log(`Running swap tests with ID: ${testId}`);
+
     anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const payer = Keypair.generate();
+
+    const merchantName = "SwapShop";
+    const [featureFlagsPda] = helper.getFeatureFlagsPda();
+    let merchantPda: PublicKey;
+
+    let usdc: PublicKey;
+    let bonk: PublicKey;
+    let payerBonk: PublicKey;
+    let payerUsdc: PublicKey;
+    let merchantUsdc: PublicKey;
+    let vaultBonk: PublicKey;
+    let vaultUsdc: PublicKey;
+
+    const hasSwap = () => program.idl.instructions.some((ix) => ix.name === "processPaymentWithSwap");
+
+    const setSwapSettlement = (enabled: boolean) =>
+        program.methods
+            .setFeature({ swapSettlement: {} }, enabled)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                featureFlags: featureFlagsPda,
+                admin: admin.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([admin])
+            .rpc();
+
+    const setSwapEnabled = (enabled: boolean) =>
+        program.methods
+            .updateMerchant(merchantName, null, usdc, enabled, null, null, null, null, null, null)
+            .accountsStrict({
+                merchant: merchantPda,
+                authority: merchantOwner.publicKey,
+                operator: null,
+                payoutBook: null,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+    const payWithSwap = (mint: PublicKey, payerAta: PublicKey, vaultAta: PublicKey) => {
+        const paymentId = new anchor.BN(Date.now());
+        return (program.methods as any)
+            .processPaymentWithSwap(new anchor.BN(1_000_000), paymentId, merchantName, new anchor.BN(1), Buffer.from([]))
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payer: payer.publicKey,
+                paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(mint)[0],
+                tokenProgram: TOKEN_PROGRAM_ID,
+                inputMint: mint,
+                payerTokenAccount: payerAta,
+                feeTokenAccount: vaultAta,
+                merchantTokenAccount: merchantUsdc,
+                featureFlags: featureFlagsPda,
+                acceptedTokens: null,
+                jupiterProgram: JUPITER_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+    };
+
+    before(async function () {
+        if (!hasSwap()) {
+            this.skip();
+        }
+
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(payer.publicKey, 5 * LAMPORTS_PER_SOL);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                await helper.setFee(admin, 100);
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+
+        // Settle in USDC, pay in BONK
+        usdc = await helper.createTokenMint(payer, 6);
+        bonk = await helper.createTokenMint(payer, 5);
+        payerBonk = await helper.createTokenAccount(bonk, payer.publicKey, payer);
+        payerUsdc = await helper.createTokenAccount(usdc, payer.publicKey, payer);
+        merchantUsdc = await helper.createTokenAccount(usdc, merchantWallet.publicKey, payer);
+        vaultBonk = await helper.getFeeVaultTokenAccount(bonk, payer);
+        vaultUsdc = await helper.getFeeVaultTokenAccount(usdc, payer);
+        await helper.mintTokens(bonk, payerBonk, 10_000_000, payer);
+        await helper.mintTokens(usdc, payerUsdc, 10_000_000, payer);
+        await helper.openFeeStats(admin, bonk);
+        await helper.openFeeStats(admin, usdc);
+
+        [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, usdc, merchantName, false, null, 0, false, "", 0)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+    });
+
+    it("should reject merchants without swap enabled", async () => {
+        await setSwapSettlement(true);
+        try {
+            await payWithSwap(bonk, payerBonk, vaultBonk);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("SwapNotEnabled"));
+        }
+    });
+
+    it("should reject swaps while the feature is off", async () => {
+        await setSwapEnabled(true);
+        await setSwapSettlement(false);
+        try {
+            await payWithSwap(bonk, payerBonk, vaultBonk);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("FeatureDisabled"));
+        }
+    });
 
-    it("Placeholder: Swap Tests", async () => {
-        // TODO: Implement Jupiter swap tests here
-        assert.ok(true);
+    it("should reject paying in the settlement token itself", async () => {
+        await setSwapSettlement(true);
+        try {
+            await payWithSwap(usdc, payerUsdc, vaultUsdc);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("InvalidToken"));
+        }
     });
 });
@@ -0,0 +1,593 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair } from "@solana/web3.js";
+import { assert } from "chai";
+
+// Snapshot of the byte offsets pinned in programs/orki_gateway/src/layout.rs.
+// A failure here means a field moved instead of being carved out of `reserved`.
+describe("Account Layout", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+
+    const keyA = Keypair.generate().publicKey;
+    const keyB = Keypair.generate().publicKey;
+    const keyC = Keypair.generate().publicKey;
+
+    const encode = (name: string, value: any): Promise<Buffer> =>
+        program.coder.accounts.encode(name, value);
+
+    const assertKey = (data: Buffer, offset: number, key: PublicKey) =>
+        assert.ok(data.subarray(offset, offset + 32).equals(key.toBuffer()), `pubkey at ${offset}`);
+
+    const assertU64 = (data: Buffer, offset: number, value: number) =>
+        assert.equal(data.readBigUInt64LE(offset), BigInt(value), `u64 at ${offset}`);
+
+    const assertI64 = (data: Buffer, offset: number, value: number) =>
+        assert.equal(data.readBigInt64LE(offset), BigInt(value), `i64 at ${offset}`);
+
+    it("should keep GlobalState offsets and size", async () => {
+        const data = await encode("GlobalState", {
+            admin: keyA,
+            feeBps: 0x0102,
+            feeWallet: keyB,
+            paused: true,
+            bump: 7,
+            pendingAdmin: keyC,
+            escrowReleaseDelay: new anchor.BN(0),
+            authorizationExpiry: new anchor.BN(0),
+            guardian: keyA,
+            pendingFeeBps: null,
+            feeEffectiveAt: new anchor.BN(0),
+            feeTimelockSeconds: new anchor.BN(0),
+            paymentRetentionSeconds: new anchor.BN(0),
+            arbiter: keyB,
+            version: 1,
+            finalitySlots: new anchor.BN(32),
+            maxFeeBps: 500,
+            feeRounding: { ceil: {} },
+            crankReward: new anchor.BN(0),
+            adminCouncil: false,
+            feeNoticeUntil: new anchor.BN(0),
+            reserved: [],
+        });
+        assertKey(data, 8, keyA);
+        assert.equal(data.readUInt16LE(40), 0x0102);
+        assertKey(data, 42, keyB);
+        assert.equal(data[74], 1);
+        assert.equal(data[75], 7);
+        assert.equal(data[76], 1); // Some tag
+        assertKey(data, 77, keyC);
+        assert.equal(data.length, 245);
+    });
+
+    it("should keep Merchant offsets and size", async () => {
+        const data = await encode("Merchant", {
+            owner: keyA,
+            settlementWallet: keyB,
+            settlementToken: keyC,
+            swapEnabled: true,
+            name: "x".repeat(32),
+            bump: 7,
+            sandbox: false,
+            feeBpsOverride: 250,
+            escrowEnabled: false,
+            active: true,
+            adminLocked: false,
+            previousMerchant: keyA,
+            openInvoices: 0,
+            fundedEscrows: 0,
+            splitEnabled: false,
+            acceptedTokensEnabled: false,
+            minAmount: new anchor.BN(0),
+            maxAmount: new anchor.BN(0),
+            operators: 0,
+            feeExempt: false,
+            referrer: PublicKey.default,
+            referrerBps: 0,
+            acceptsAnyToken: false,
+            tokenRuleSet: false,
+            category: 0,
+            metadataUri: "u".repeat(128),
+            settlementMode: { accrue: {} },
+            version: 1,
+            merchantIndex: new anchor.BN(0),
+            allowSelfPayments: false,
+            dailyLimit: new anchor.BN(0),
+            rewardsEnabled: false,
+            rewardsRequired: false,
+            adminFrozen: false,
+            taxBps: 0,
+            nameIndexed: false,
+            confirmationMessage: "m".repeat(64),
+            minIntervalPerPayer: 0,
+            settlementRoutes: 0,
+            discountCodes: 0,
+            openAuthorizations: 0,
+            openStreams: 0,
+            reserved: [],
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assertKey(data, 72, keyC);
+        assert.equal(data[104], 1);
+        assert.equal(data.readUInt32LE(105), 32);
+        assert.equal(data.length, 494);
+    });
+
+    it("should keep Payment offsets and size", async () => {
+        const data = await encode("Payment", {
+            payer: keyA,
+            merchant: keyB,
+            amount: new anchor.BN(11),
+            paymentId: new anchor.BN(22),
+            timestamp: new anchor.BN(33),
+            bump: 7,
+            sandbox: true,
+            refundedAmount: new anchor.BN(44),
+            status: { captured: {} },
+            mint: keyC,
+            fee: new anchor.BN(55),
+            memo: "m".repeat(64),
+            tip: new anchor.BN(66),
+            preDiscountAmount: new anchor.BN(77),
+            discountBps: 88,
+            receiptMint: keyB,
+            escrowed: true,
+            slot: new anchor.BN(99),
+            orderId: new Array(32).fill(5),
+            taxAmount: new anchor.BN(111),
+            reserved: [],
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assertU64(data, 72, 11);
+        assertU64(data, 80, 22);
+        assertI64(data, 88, 33);
+        assert.equal(data[96], 7);
+        assert.equal(data[97], 1);
+        assertU64(data, 98, 44);
+        assert.equal(data[106], 2); // PaymentStatus::Captured
+        assertKey(data, 107, keyC);
+        assertU64(data, 139, 55);
+        assert.equal(data.readUInt32LE(147), 64);
+        assertU64(data, 215, 66); // after a full memo
+        assertU64(data, 223, 77);
+        assert.equal(data.readUInt16LE(231), 88);
+        assertKey(data, 233, keyB);
+        assert.equal(data[265], 1);
+        assertU64(data, 266, 99);
+        assert.equal(data[274], 5);
+        assert.equal(data[305], 5);
+        assertU64(data, 306, 111);
+        assert.equal(data.length, 314);
+    });
+
+    it("should decode an upgraded pre-memo Payment with an empty memo", async () => {
+        // Records written before memos existed: 170 bytes, reserved all zero,
+        // zero-extended to the current size by upgrade_payment_account
+        const legacy = Buffer.alloc(306);
+        legacy.set(program.coder.accounts.accountDiscriminator("Payment"), 0);
+        keyA.toBuffer().copy(legacy, 8);
+        const payment = program.coder.accounts.decode("Payment", legacy);
+        assert.ok(payment.payer.equals(keyA));
+        assert.equal(payment.memo, "");
+        assert.ok(payment.receiptMint.equals(PublicKey.default));
+        assert.equal(payment.slot.toNumber(), 0);
+        assert.deepEqual(payment.orderId, new Array(32).fill(0));
+    });
+
+    it("should keep Invoice offsets and size", async () => {
+        const data = await encode("Invoice", {
+            merchant: keyA,
+            invoiceId: new anchor.BN(11),
+            amount: new anchor.BN(22),
+            mint: keyB,
+            expiresAt: new anchor.BN(33),
+            memo: "m".repeat(64),
+            status: { open: {} },
+            payer: keyC,
+            paidAt: new anchor.BN(0),
+            bump: 7,
+            reserved: new Array(64).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertU64(data, 40, 11);
+        assertU64(data, 48, 22);
+        assertKey(data, 56, keyB);
+        assertI64(data, 88, 33);
+        assert.equal(data.readUInt32LE(96), 64);
+        assert.equal(data.length, 270);
+    });
+
+    it("should keep PaymentLink offsets and size", async () => {
+        const data = await encode("PaymentLink", {
+            merchant: keyA,
+            linkSeed: new anchor.BN(11),
+            amount: new anchor.BN(22),
+            mint: keyB,
+            maxUses: new anchor.BN(33),
+            uses: new anchor.BN(44),
+            expiresAt: new anchor.BN(55),
+            active: true,
+            bump: 7,
+            label: "l".repeat(32),
+            reserved: new Array(64).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertU64(data, 40, 11);
+        assertU64(data, 48, 22);
+        assertKey(data, 56, keyB);
+        assertU64(data, 88, 33);
+        assertU64(data, 96, 44);
+        assertI64(data, 104, 55);
+        assert.equal(data[112], 1);
+        assert.equal(data[113], 7);
+        assert.equal(data.readUInt32LE(114), 32);
+        assert.equal(data.length, 214);
+    });
+
+    it("should keep Operator offsets and size", async () => {
+        const data = await encode("Operator", {
+            merchant: keyA,
+            operator: keyB,
+            permissions: 0b1010,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assert.equal(data[72], 0b1010);
+        assert.equal(data[73], 7);
+        assert.equal(data.length, 106);
+    });
+
+    it("should keep Discount offsets and size", async () => {
+        const codeHash = Array.from({ length: 32 }, (_, i) => i);
+        const data = await encode("Discount", {
+            merchant: keyA,
+            codeHash,
+            discountBps: 2500,
+            maxRedemptions: new anchor.BN(11),
+            redemptions: new anchor.BN(22),
+            expiresAt: new anchor.BN(33),
+            bump: 7,
+            reserved: new Array(64).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assert.ok(data.subarray(40, 72).equals(Buffer.from(codeHash)));
+        assert.equal(data.readUInt16LE(72), 2500);
+        assertU64(data, 74, 11);
+        assertU64(data, 82, 22);
+        assertI64(data, 90, 33);
+        assert.equal(data[98], 7);
+        assert.equal(data.length, 163);
+    });
+
+    it("should keep Blocked offsets and size", async () => {
+        const data = await encode("Blocked", {
+            address: keyA,
+            blockedAt: new anchor.BN(11),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertI64(data, 40, 11);
+        assert.equal(data[48], 7);
+        assert.equal(data.length, 81);
+    });
+
+    it("should keep TokenFeeConfig offsets and size", async () => {
+        const data = await encode("TokenFeeConfig", {
+            mint: keyA,
+            feeBps: 250,
+            bump: 7,
+            pendingFeeBps: null,
+            feeEffectiveAt: new anchor.BN(0),
+            inactive: false,
+            reserved: new Array(20).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assert.equal(data.readUInt16LE(40), 250);
+        assert.equal(data[42], 7);
+        assert.equal(data.length, 75);
+    });
+
+    it("should keep FeeTiers offsets and size", async () => {
+        const tiers = [1, 2, 3, 4, 5].map((i) => ({ volumeThreshold: new anchor.BN(i * 1000), feeBps: 100 - i }));
+        const data = await encode("FeeTiers", {
+            tiers,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assert.equal(data.readUInt32LE(8), 5);
+        assertU64(data, 12, 1000);
+        assert.equal(data.readUInt16LE(28), 99);
+        assert.equal(data[102], 7);
+        assert.equal(data.length, 135);
+    });
+
+    it("should keep FeatureFlags, Vault, EscrowBalance and MerchantBalance offsets and sizes", async () => {
+        const flags = await encode("FeatureFlags", {
+            enabled: new anchor.BN(5),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertU64(flags, 8, 5);
+        assert.equal(flags[16], 7);
+        assert.equal(flags.length, 49);
+
+        const vault = await encode("Vault", {
+            merchant: keyA,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(vault, 8, keyA);
+        assert.equal(vault[40], 7);
+        assert.equal(vault.length, 73);
+
+        const escrow = await encode("EscrowBalance", {
+            merchant: keyA,
+            mint: keyB,
+            amount: new anchor.BN(11),
+            lastDepositAt: new anchor.BN(22),
+            bump: 7,
+            disputedAmount: new anchor.BN(33),
+            lastClaimAt: new anchor.BN(44),
+            pendingAmount: new anchor.BN(55),
+            reserved: new Array(8).fill(0),
+        });
+        assertKey(escrow, 8, keyA);
+        assertKey(escrow, 40, keyB);
+        assertU64(escrow, 72, 11);
+        assertI64(escrow, 80, 22);
+        assert.equal(escrow[88], 7);
+        assertU64(escrow, 89, 33);
+        assertI64(escrow, 97, 44);
+        assertU64(escrow, 105, 55);
+        assert.equal(escrow.length, 121);
+
+        const balance = await encode("MerchantBalance", {
+            merchant: keyA,
+            mint: keyB,
+            amount: new anchor.BN(11),
+            bump: 7,
+            payoutInterval: 3600,
+            lastPayoutAt: new anchor.BN(22),
+            reserved: new Array(20).fill(0),
+        });
+        assertKey(balance, 8, keyA);
+        assertKey(balance, 40, keyB);
+        assertU64(balance, 72, 11);
+        assert.equal(balance[80], 7);
+        assert.equal(balance.readUInt32LE(81), 3600);
+        assertI64(balance, 85, 22);
+        assert.equal(balance.length, 113);
+    });
+
+    it("should keep Subscription offsets and size", async () => {
+        const data = await encode("Subscription", {
+            merchant: keyA,
+            subscriber: keyB,
+            subId: new anchor.BN(11),
+            mint: keyC,
+            subscriberTokenAccount: keyA,
+            amount: new anchor.BN(22),
+            intervalSeconds: new anchor.BN(33),
+            lastCharged: new anchor.BN(44),
+            chargesCount: new anchor.BN(55),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assertU64(data, 72, 11);
+        assertKey(data, 80, keyC);
+        assertKey(data, 112, keyA);
+        assertU64(data, 144, 22);
+        assertI64(data, 152, 33);
+        assertI64(data, 160, 44);
+        assertU64(data, 168, 55);
+        assert.equal(data[176], 7);
+        assert.equal(data.length, 209);
+    });
+
+    it("should keep DailyVolume offsets and size", async () => {
+        const data = await encode("DailyVolume", {
+            merchant: keyA,
+            dayBucket: new anchor.BN(11),
+            volume: new anchor.BN(22),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertI64(data, 40, 11);
+        assertU64(data, 48, 22);
+        assert.equal(data[56], 7);
+        assert.equal(data.length, 89);
+    });
+
+    it("should keep RewardsHook offsets and size", async () => {
+        const data = await encode("RewardsHook", {
+            merchant: keyA,
+            program: keyB,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assert.equal(data[72], 7);
+        assert.equal(data.length, 105);
+    });
+
+    it("should keep PaymentStream offsets and size", async () => {
+        const data = await encode("PaymentStream", {
+            merchant: keyA,
+            payer: keyB,
+            streamId: new anchor.BN(11),
+            mint: keyC,
+            ratePerSecond: new anchor.BN(22),
+            deposit: new anchor.BN(33),
+            withdrawn: new anchor.BN(44),
+            startAt: new anchor.BN(55),
+            lastWithdrawnAt: new anchor.BN(66),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assertU64(data, 72, 11);
+        assertKey(data, 80, keyC);
+        assertU64(data, 112, 22);
+        assertU64(data, 120, 33);
+        assertU64(data, 128, 44);
+        assertI64(data, 136, 55);
+        assertI64(data, 144, 66);
+        assert.equal(data[152], 7);
+        assert.equal(data.length, 185);
+    });
+
+    it("should keep FeeVault and FeeStats offsets and sizes", async () => {
+        const vault = await encode("FeeVault", {
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assert.equal(vault[8], 7);
+        assert.equal(vault.length, 41);
+
+        const stats = await encode("FeeStats", {
+            mint: keyA,
+            totalFeesCollected: new anchor.BN(11),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(stats, 8, keyA);
+        assertU64(stats, 40, 11);
+        assert.equal(stats[48], 7);
+        assert.equal(stats.length, 81);
+    });
+
+    it("should keep MerchantStats offsets and size", async () => {
+        const data = await encode("MerchantStats", {
+            merchant: keyA,
+            paymentCount: new anchor.BN(11),
+            totalVolume: new anchor.BN(22),
+            totalFeesPaid: new anchor.BN(33),
+            totalRefunded: new anchor.BN(44),
+            lastPaymentAt: new anchor.BN(55),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertU64(data, 40, 11);
+        // u128 counters: low half first
+        assertU64(data, 48, 22);
+        assertU64(data, 64, 33);
+        assertU64(data, 80, 44);
+        assertI64(data, 96, 55);
+        assert.equal(data[104], 7);
+        assert.equal(data.length, 137);
+    });
+
+    it("should keep GlobalStats offsets and size", async () => {
+        const data = await encode("GlobalStats", {
+            merchantsRegistered: new anchor.BN(11),
+            paymentsProcessed: new anchor.BN(22),
+            totalVolume: new anchor.BN(33),
+            totalFeeRevenue: new anchor.BN(44),
+            bump: 7,
+            eventSeq: new anchor.BN(55),
+            reserved: new Array(24).fill(0),
+        });
+        assertU64(data, 8, 11);
+        assertU64(data, 16, 22);
+        // u128 counters: low half first
+        assertU64(data, 24, 33);
+        assertU64(data, 40, 44);
+        assert.equal(data[56], 7);
+        assertU64(data, 57, 55);
+        assert.equal(data.length, 89);
+    });
+
+    it("should keep RewardsHook offsets and size", async () => {
+        const data = await encode("RewardsHook", {
+            merchant: keyA,
+            program: keyB,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assert.equal(data[72], 7);
+        assert.equal(data.length, 105);
+    });
+
+    it("should keep MerchantRegistry offsets and size", async () => {
+        const data = await encode("MerchantRegistry", {
+            merchantCount: new anchor.BN(11),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertU64(data, 8, 11);
+        assert.equal(data[16], 7);
+        assert.equal(data.length, 49);
+    });
+
+    it("should keep MerchantIndex offsets and size", async () => {
+        const data = await encode("MerchantIndex", {
+            index: new anchor.BN(11),
+            merchant: keyA,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertU64(data, 8, 11);
+        assertKey(data, 16, keyA);
+        assert.equal(data[48], 7);
+        assert.equal(data.length, 81);
+    });
+
+    it("should keep MerchantName offsets and size", async () => {
+        const data = await encode("MerchantName", {
+            owner: keyA,
+            merchant: keyB,
+            normalizedName: "n".repeat(32),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assert.equal(data.readUInt32LE(72), 32);
+        assert.equal(data[108], 7);
+        assert.equal(data.length, 141);
+    });
+
+    it("should keep PayerThrottle offsets and size", async () => {
+        const data = await encode("PayerThrottle", {
+            merchant: keyA,
+            payer: keyB,
+            lastPaymentAt: new anchor.BN(11),
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assertI64(data, 72, 11);
+        assert.equal(data[80], 7);
+        assert.equal(data.length, 113);
+    });
+
+    it("should keep SettlementRoute offsets and size", async () => {
+        const data = await encode("SettlementRoute", {
+            merchant: keyA,
+            mint: keyB,
+            destination: keyC,
+            bump: 7,
+            reserved: new Array(32).fill(0),
+        });
+        assertKey(data, 8, keyA);
+        assertKey(data, 40, keyB);
+        assertKey(data, 72, keyC);
+        assert.equal(data[104], 7);
+        assert.equal(data.length, 137);
+    });
+});
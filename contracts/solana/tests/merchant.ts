@@ -28,10 +28,11 @@ describe("Merchant Operations", () => {
         // Initialize global state - use helper.globalStatePda directly
         try {
             await program.methods
-                .initialize(100, merchantWallet.publicKey)
+                .initialize(100, merchantWallet.publicKey, 10000, admin.publicKey, null)
                 .accountsStrict({
                     globalState: helper.globalStatePda, // Use helper.globalStatePda
-                    admin: admin.publicKey,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
                     systemProgram: SystemProgram.programId,
                 })
                 .signers([admin])
@@ -48,11 +49,17 @@ describe("Merchant Operations", () => {
             [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
 
             await program.methods
-                .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName)
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
                 .accountsStrict({
                     merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
                     owner: merchantOwner.publicKey,
                     systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
                 })
                 .signers([merchantOwner])
                 .rpc();
@@ -66,20 +73,40 @@ describe("Merchant Operations", () => {
             assert.equal(merchant.swapEnabled, false);
         });
 
-        it("should fail to register merchant with same name", async () => {
+        const reregister = (settlementWallet: PublicKey, merchantIndex: PublicKey) =>
+            program.methods
+                .registerMerchant(settlementWallet, PublicKey.default, merchantName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex,
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        it("should accept an identical retry of the registration as a no-op", async () => {
+            const before = await provider.connection.getAccountInfo(merchantPda);
+            const slot = await helper.nextMerchantIndexPda();
+            await reregister(merchantWallet.publicKey, slot);
+
+            const after = await provider.connection.getAccountInfo(merchantPda);
+            assert.ok(after!.data.equals(before!.data));
+            // The slot opened for the retry is closed again
+            assert.isNull(await provider.connection.getAccountInfo(slot));
+        });
+
+        it("should fail to register merchant with same name and different settings", async () => {
             try {
-                await program.methods
-                    .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName)
-                    .accountsStrict({
-                        merchant: merchantPda,
-                        owner: merchantOwner.publicKey,
-                        systemProgram: SystemProgram.programId,
-                    })
-                    .signers([merchantOwner])
-                    .rpc();
+                await reregister(Keypair.generate().publicKey, await helper.nextMerchantIndexPda());
                 assert.fail("Should have failed");
             } catch (e: any) {
-                assert.ok(e.message.includes("already in use"));
+                assert.ok(e.message.includes("MerchantAlreadyExists"));
             }
         });
 
@@ -90,11 +117,17 @@ describe("Merchant Operations", () => {
                 const [longMerchantPda] = helper.getMerchantPda(merchantOwner.publicKey, longName);
 
                 await program.methods
-                    .registerMerchant(merchantWallet.publicKey, PublicKey.default, longName)
+                    .registerMerchant(merchantWallet.publicKey, PublicKey.default, longName, false, null, 0, true, "", 0)
                     .accountsStrict({
                         merchant: longMerchantPda,
+                        merchantStats: helper.getMerchantStatsPda(longMerchantPda)[0],
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchantRegistry: helper.getMerchantRegistryPda()[0],
+                        merchantIndex: await helper.nextMerchantIndexPda(),
+                        merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, longName)[0],
                         owner: merchantOwner.publicKey,
                         systemProgram: SystemProgram.programId,
+                        ...helper.eventCpi,
                     })
                     .signers([merchantOwner])
                     .rpc();
@@ -112,49 +145,109 @@ describe("Merchant Operations", () => {
     });
 
     describe("Merchant Updates", () => {
-        it("should update merchant settlement wallet", async () => {
-            const newWallet = Keypair.generate().publicKey;
+        it("should refuse a settlement wallet outside the payout address book", async () => {
+            try {
+                await program.methods
+                    .updateMerchant(
+                        merchantName,
+                        Keypair.generate().publicKey,
+                        null, // token not changed
+                        null, // swap not changed
+                        null, // escrow not changed
+                        null,
+                        null,
+                        null,
+                        null,
+                        null
+                    )
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        authority: merchantOwner.publicKey,
+                        operator: null,
+                        payoutBook: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PayoutAddressNotListed"));
+            }
+
+            const merchant = await program.account.merchant.fetch(merchantPda);
+            assert.ok(merchant.settlementWallet.equals(merchantWallet.publicKey));
+        });
+
+        it("should rename merchant by moving it to the new PDA", async () => {
+            const newName = "UpdatedShop";
+            const [newMerchantPda] = helper.getMerchantPda(merchantOwner.publicKey, newName); // Extract PublicKey
 
             await program.methods
-                .updateMerchant(
-                    merchantName,
-                    null, // name not changed
-                    newWallet,
-                    null, // token not changed
-                    null  // swap not changed
-                )
+                .renameMerchant(merchantName, newName)
                 .accountsStrict({
                     merchant: merchantPda,
+                    newMerchant: newMerchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    newMerchantStats: helper.getMerchantStatsPda(newMerchantPda)[0],
+                    merchantIndex: await helper.getMerchantIndexPdaOf(merchantPda),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                    newMerchantName: helper.getMerchantNamePda(merchantOwner.publicKey, newName)[0],
                     owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
                 })
                 .signers([merchantOwner])
                 .rpc();
 
-            const merchant = await program.account.merchant.fetch(merchantPda);
-            assert.ok(merchant.settlementWallet.equals(newWallet));
-        });
-
-        it("should update merchant name", async () => {
-            const newName = "UpdatedShop";
-            const [newMerchantPda] = helper.getMerchantPda(merchantOwner.publicKey, newName); // Extract PublicKey
+            const merchant = await program.account.merchant.fetch(newMerchantPda);
+            assert.equal(merchant.name, newName);
+            assert.ok(merchant.previousMerchant.equals(merchantPda));
+            assert.isNull(await program.account.merchant.fetchNullable(merchantPda));
+            const stats = await program.account.merchantStats.fetch(helper.getMerchantStatsPda(newMerchantPda)[0]);
+            assert.ok(stats.merchant.equals(newMerchantPda));
+            assert.isNull(await program.account.merchantStats.fetchNullable(helper.getMerchantStatsPda(merchantPda)[0]));
 
+            // Move it back so the remaining tests keep using the original PDA
             await program.methods
-                .updateMerchant(
-                    merchantName,
-                    newName,
-                    null,
-                    null,
-                    null
-                )
+                .renameMerchant(newName, merchantName)
                 .accountsStrict({
-                    merchant: merchantPda,
+                    merchant: newMerchantPda,
+                    newMerchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(newMerchantPda)[0],
+                    newMerchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    merchantIndex: await helper.getMerchantIndexPdaOf(newMerchantPda),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, newName)[0],
+                    newMerchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
                     owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
                 })
                 .signers([merchantOwner])
                 .rpc();
+        });
 
-            const merchant = await program.account.merchant.fetch(merchantPda);
-            assert.equal(merchant.name, newName);
+        it("should reject a new name longer than 32 bytes", async () => {
+            const longName = "a".repeat(33);
+            const unusedPda = Keypair.generate().publicKey;
+            try {
+                await program.methods
+                    .renameMerchant(merchantName, longName)
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        newMerchant: unusedPda,
+                        merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                        newMerchantStats: helper.getMerchantStatsPda(unusedPda)[0],
+                        merchantIndex: await helper.getMerchantIndexPdaOf(merchantPda),
+                        merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                        newMerchantName: helper.getMerchantNamePda(merchantOwner.publicKey, longName)[0],
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                // A 33-byte seed can't derive a PDA at all, so the client may reject it first
+                assert.ok(e.message.includes("NameTooLong") || e.message.includes("Max seed length exceeded") || e.message.includes("ConstraintSeeds"));
+            }
         });
 
         it("should fail if non-owner tries to update", async () => {
@@ -165,11 +258,162 @@ describe("Merchant Operations", () => {
                 await program.methods
                     .updateMerchant(
                         merchantName,
-                        null,
                         Keypair.generate().publicKey,
                         null,
+                        null,
+                        null,
+                        null,
+                        null,
+                        null,
+                        null,
                         null
                     )
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        authority: impostor.publicKey,
+                        operator: null,
+                        payoutBook: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([impostor])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                // Neither the owner nor an operator
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+    });
+
+    describe("Merchant Closing", () => {
+        const closingName = "ClosingShop";
+        let closingPda: PublicKey;
+
+        const register = async () =>
+            program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, closingName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: closingPda,
+                    merchantStats: helper.getMerchantStatsPda(closingPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, closingName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        const close = async () =>
+            program.methods
+                .closeMerchant(closingName)
+                .accountsStrict({
+                    merchant: closingPda,
+                    merchantStats: helper.getMerchantStatsPda(closingPda)[0],
+                    merchantIndex: await helper.getMerchantIndexPdaOf(closingPda),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, closingName)[0],
+                    owner: merchantOwner.publicKey,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        before(async () => {
+            [closingPda] = helper.getMerchantPda(merchantOwner.publicKey, closingName);
+            await register();
+        });
+
+        it("should refuse to close while an invoice is open", async () => {
+            const invoiceId = new anchor.BN(1);
+            const [invoicePda] = helper.getInvoicePda(closingPda, invoiceId);
+            await program.methods
+                .createInvoice(closingName, invoiceId, new anchor.BN(1000), PublicKey.default, new anchor.BN(0), "")
+                .accountsStrict({
+                    merchant: closingPda,
+                    invoice: invoicePda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            try {
+                await close();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantHasOpenInvoices"));
+            }
+
+            await program.methods
+                .cancelInvoice(closingName)
+                .accountsStrict({
+                    merchant: closingPda,
+                    invoice: invoicePda,
+                    owner: merchantOwner.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should close the merchant and return rent to the owner", async () => {
+            const rent = await provider.connection.getBalance(closingPda);
+            const ownerBefore = await provider.connection.getBalance(merchantOwner.publicKey);
+            const slot = await helper.getMerchantIndexPdaOf(closingPda);
+
+            await close();
+
+            assert.isNull(await program.account.merchant.fetchNullable(closingPda));
+            // The directory slot stays, pointing nowhere
+            const entry = await program.account.merchantIndex.fetch(slot);
+            assert.ok(entry.merchant.equals(PublicKey.default));
+            assert.isNull(await program.account.merchantStats.fetchNullable(helper.getMerchantStatsPda(closingPda)[0]));
+            const ownerAfter = await provider.connection.getBalance(merchantOwner.publicKey);
+            // Owner also pays the transaction fee
+            assert.isAbove(ownerAfter, ownerBefore + rent - 10_000);
+        });
+
+        it("should allow re-registering the same name after closing", async () => {
+            await register();
+            const merchant = await program.account.merchant.fetch(closingPda);
+            assert.equal(merchant.name, closingName);
+            assert.equal(merchant.openInvoices, 0);
+        });
+    });
+
+    describe("Merchant Ping", () => {
+        it("should emit MerchantPing with the caller's nonce", async () => {
+            const nonce = new anchor.BN(Date.now());
+            const sig = await program.methods
+                .pingMerchant(merchantName, nonce)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    owner: merchantOwner.publicKey,
+                })
+                .signers([merchantOwner])
+                .rpc({ commitment: "confirmed" });
+
+            const tx = await provider.connection.getTransaction(sig, {
+                commitment: "confirmed",
+                maxSupportedTransactionVersion: 0,
+            });
+            const parser = new anchor.EventParser(program.programId, program.coder);
+            const events = [...parser.parseLogs(tx!.meta!.logMessages!)];
+            const ping = events.find((e) => e.name === "merchantPing");
+            assert.ok(ping, "MerchantPing not emitted");
+            assert.ok(ping!.data.merchant.equals(merchantPda));
+            assert.equal(ping!.data.nonce.toString(), nonce.toString());
+        });
+
+        it("should reject a ping from anyone but the owner", async () => {
+            const impostor = Keypair.generate();
+            await helper.airdrop(impostor.publicKey);
+            try {
+                await program.methods
+                    .pingMerchant(merchantName, new anchor.BN(1))
                     .accountsStrict({
                         merchant: merchantPda,
                         owner: impostor.publicKey,
@@ -178,10 +422,671 @@ describe("Merchant Operations", () => {
                     .rpc();
                 assert.fail("Should have failed");
             } catch (e: any) {
-                // Since PDA is derived from owner, using a different owner triggers a seed constraint violation
-                // before the has_one check can even run.
                 assert.ok(e.message.includes("ConstraintSeeds") || e.message.includes("2006") || e.message.includes("has_one"));
             }
         });
     });
+
+    describe("Payout Address Book", () => {
+        const destination = Keypair.generate().publicKey;
+        let payoutBookPda: PublicKey;
+
+        const addPayoutAddress = (address: PublicKey, label: string) =>
+            program.methods
+                .addPayoutAddress(merchantName, address, label)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    payoutBook: payoutBookPda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        before(() => {
+            [payoutBookPda] = helper.getPayoutBookPda(merchantPda);
+        });
+
+        it("should add a labeled destination behind a 24-hour delay", async () => {
+            const before = Math.floor(Date.now() / 1000);
+            await addPayoutAddress(destination, "Cold wallet");
+
+            const book = await program.account.payoutBook.fetch(payoutBookPda);
+            assert.equal(book.entries.length, 1);
+            assert.ok(book.entries[0].address.equals(destination));
+            assert.equal(book.entries[0].label, "Cold wallet");
+            // Validator time may drift a little from the local clock
+            assert.isAtLeast(book.entries[0].activatesAt.toNumber(), before + 86400 - 60);
+        });
+
+        it("should reject a duplicate destination", async () => {
+            try {
+                await addPayoutAddress(destination, "Again");
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PayoutAddressExists"));
+            }
+        });
+
+        it("should not settle to a destination still inside its delay", async () => {
+            try {
+                await program.methods
+                    .updateMerchant(merchantName, destination, null, null, null, null, null, null, null, null)
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        authority: merchantOwner.publicKey,
+                        operator: null,
+                        payoutBook: payoutBookPda,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PayoutAddressNotActive"));
+            }
+        });
+
+        it("should still allow updates that keep the current settlement wallet", async () => {
+            await program.methods
+                .updateMerchant(merchantName, merchantWallet.publicKey, null, true, null, null, null, null, null, null)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            const merchant = await program.account.merchant.fetch(merchantPda);
+            assert.ok(merchant.settlementWallet.equals(merchantWallet.publicKey));
+            assert.equal(merchant.swapEnabled, true);
+        });
+
+        it("should remove a destination", async () => {
+            await program.methods
+                .removePayoutAddress(merchantName, destination)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    payoutBook: payoutBookPda,
+                    owner: merchantOwner.publicKey,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            const book = await program.account.payoutBook.fetch(payoutBookPda);
+            assert.equal(book.entries.length, 0);
+
+            try {
+                await program.methods
+                    .removePayoutAddress(merchantName, destination)
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        payoutBook: payoutBookPda,
+                        owner: merchantOwner.publicKey,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PayoutAddressNotListed"));
+            }
+        });
+    });
+
+    describe("Operators", () => {
+        // OperatorPermission bit order
+        const UPDATE_SETTLEMENT = 1 << 0;
+        const REFUND = 1 << 1;
+        const CREATE_INVOICES = 1 << 2;
+
+        const cashier = Keypair.generate();
+        let operatorPda: PublicKey;
+
+        const grant = (permissions: number) =>
+            program.methods
+                .grantOperator(merchantName, cashier.publicKey, permissions)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    operatorAccount: operatorPda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        const updateAsCashier = (operator: PublicKey | null) =>
+            program.methods
+                .updateMerchant(merchantName, null, null, null, null, null, null, null, null, null)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    authority: cashier.publicKey,
+                    operator,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([cashier])
+                .rpc();
+
+        const invoiceAsCashier = (invoiceId: anchor.BN, operator: PublicKey | null) =>
+            program.methods
+                .createInvoice(merchantName, invoiceId, new anchor.BN(1000), PublicKey.default, new anchor.BN(0), "")
+                .accountsStrict({
+                    merchant: merchantPda,
+                    invoice: helper.getInvoicePda(merchantPda, invoiceId)[0],
+                    authority: cashier.publicKey,
+                    operator,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([cashier])
+                .rpc();
+
+        before(async () => {
+            await helper.airdrop(cashier.publicKey);
+            [operatorPda] = helper.getOperatorPda(merchantPda, cashier.publicKey);
+        });
+
+        it("should grant an operator its permission bits", async () => {
+            await grant(REFUND);
+
+            const operator = await program.account.operator.fetch(operatorPda);
+            assert.ok(operator.merchant.equals(merchantPda));
+            assert.ok(operator.operator.equals(cashier.publicKey));
+            assert.equal(operator.permissions, REFUND);
+            assert.equal((await program.account.merchant.fetch(merchantPda)).operators, 1);
+        });
+
+        it("should reject a refund-only operator from update_merchant", async () => {
+            try {
+                await updateAsCashier(operatorPda);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should let an operator act once granted the right bit", async () => {
+            // Granting again replaces the bits without counting a second operator
+            await grant(REFUND | CREATE_INVOICES | UPDATE_SETTLEMENT);
+            assert.equal((await program.account.merchant.fetch(merchantPda)).operators, 1);
+
+            await updateAsCashier(operatorPda);
+            const invoiceId = new anchor.BN(Date.now());
+            await invoiceAsCashier(invoiceId, operatorPda);
+
+            const [invoicePda] = helper.getInvoicePda(merchantPda, invoiceId);
+            const invoice = await program.account.invoice.fetch(invoicePda);
+            assert.ok(invoice.merchant.equals(merchantPda));
+
+            // Cancelling stays with the owner
+            await program.methods
+                .cancelInvoice(merchantName)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    invoice: invoicePda,
+                    owner: merchantOwner.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should keep the rest of the profile owner-only for operators", async () => {
+            // Granted UpdateSettlement above, which covers the wallet and token alone
+            try {
+                await program.methods
+                    .updateMerchant(merchantName, null, null, null, null, null, null, null, null, 7)
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        authority: cashier.publicKey,
+                        operator: operatorPda,
+                        payoutBook: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([cashier])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+            assert.notEqual((await program.account.merchant.fetch(merchantPda)).category, 7);
+        });
+
+        it("should reject unknown permission bits", async () => {
+            try {
+                await grant(1 << 7);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidPermissions"));
+            }
+        });
+
+        it("should block renaming while operators exist", async () => {
+            const newName = "OperatedShop";
+            const [newPda] = helper.getMerchantPda(merchantOwner.publicKey, newName);
+            try {
+                await program.methods
+                    .renameMerchant(merchantName, newName)
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        newMerchant: newPda,
+                        merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                        newMerchantStats: helper.getMerchantStatsPda(newPda)[0],
+                        merchantIndex: await helper.getMerchantIndexPdaOf(merchantPda),
+                        merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                        newMerchantName: helper.getMerchantNamePda(merchantOwner.publicKey, newName)[0],
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantHasOperators"));
+            }
+        });
+
+        it("should revoke an operator", async () => {
+            await program.methods
+                .revokeOperator(merchantName)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    operatorAccount: operatorPda,
+                    owner: merchantOwner.publicKey,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            assert.isNull(await provider.connection.getAccountInfo(operatorPda));
+            assert.equal((await program.account.merchant.fetch(merchantPda)).operators, 0);
+
+            try {
+                await invoiceAsCashier(new anchor.BN(Date.now()), null);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+    });
+
+    describe("Merchant Metadata", () => {
+        const profileName = `Profile${testId}`.slice(0, 32);
+        let profilePda: PublicKey;
+
+        const registerProfile = async (name: string, pda: PublicKey, uri: string) =>
+            program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, name, false, null, 0, true, uri, 3)
+                .accountsStrict({
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        const updateProfile = (uri: string | null, category: number | null) =>
+            program.methods
+                .updateMerchant(profileName, null, null, null, null, null, null, null, uri, category)
+                .accountsStrict({
+                    merchant: profilePda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        it("should store metadata set at registration", async () => {
+            [profilePda] = helper.getMerchantPda(merchantOwner.publicKey, profileName);
+            await registerProfile(profileName, profilePda, "https://example.com/shop.json");
+
+            const merchant = await program.account.merchant.fetch(profilePda);
+            assert.equal(merchant.metadataUri, "https://example.com/shop.json");
+            assert.equal(merchant.category, 3);
+        });
+
+        it("should update metadata and leave omitted fields alone", async () => {
+            await updateProfile("ipfs://bafy-profile", null);
+            let merchant = await program.account.merchant.fetch(profilePda);
+            assert.equal(merchant.metadataUri, "ipfs://bafy-profile");
+            assert.equal(merchant.category, 3);
+
+            await updateProfile(null, 9);
+            merchant = await program.account.merchant.fetch(profilePda);
+            assert.equal(merchant.metadataUri, "ipfs://bafy-profile");
+            assert.equal(merchant.category, 9);
+        });
+
+        it("should reject a metadata URI over 128 bytes", async () => {
+            const longUri = "https://example.com/" + "a".repeat(109);
+            try {
+                await updateProfile(longUri, null);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MetadataUriTooLong"));
+            }
+
+            const otherName = `Long${testId}`.slice(0, 32);
+            const [otherPda] = helper.getMerchantPda(merchantOwner.publicKey, otherName);
+            try {
+                await registerProfile(otherName, otherPda, longUri);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MetadataUriTooLong"));
+            }
+        });
+    });
+
+    describe("Merchant Account Upgrade", () => {
+        // Loaded from tests/fixtures/legacy_merchant.json at the size merchants
+        // had before metadata_uri and category
+        const legacyName = "legacy-merchant";
+        const [legacyPda] = helper.getMerchantPda(admin.publicKey, legacyName);
+
+        const upgrade = (owner: Keypair) =>
+            program.methods
+                .upgradeMerchantAccount(legacyName)
+                .accountsStrict({
+                    merchant: helper.getMerchantPda(owner.publicKey, legacyName)[0],
+                    owner: owner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([owner])
+                .rpc();
+
+        it("should not let another signer upgrade the merchant", async () => {
+            try {
+                await program.methods
+                    .upgradeMerchantAccount(legacyName)
+                    .accountsStrict({
+                        merchant: legacyPda,
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("ConstraintSeeds"));
+            }
+        });
+
+        it("should grow a pre-upgrade merchant to the current size", async () => {
+            const before = await provider.connection.getAccountInfo(legacyPda);
+            assert.equal(before.data.length, 277);
+
+            await upgrade(admin);
+
+            const after = await provider.connection.getAccountInfo(legacyPda);
+            assert.equal(after.data.length, 494);
+            assert.equal(
+                after.lamports,
+                await provider.connection.getMinimumBalanceForRentExemption(494)
+            );
+            assert.deepEqual(after.data.subarray(0, 277), before.data);
+
+            const merchant = await program.account.merchant.fetch(legacyPda);
+            assert.equal(merchant.name, legacyName);
+            assert.ok(merchant.owner.equals(admin.publicKey));
+            assert.equal(merchant.metadataUri, "");
+            assert.equal(merchant.category, 0);
+            assert.equal(merchant.confirmationMessage, "");
+            assert.equal(merchant.minIntervalPerPayer, 0);
+            assert.equal(merchant.settlementRoutes, 0);
+            assert.equal(merchant.discountCodes, 0);
+            assert.equal(merchant.openAuthorizations, 0);
+            assert.equal(merchant.openStreams, 0);
+        });
+
+        it("should leave an upgraded merchant unchanged", async () => {
+            await upgrade(admin);
+            const info = await provider.connection.getAccountInfo(legacyPda);
+            assert.equal(info.data.length, 494);
+        });
+
+        it("should require migration before other instructions", async () => {
+            try {
+                await program.methods
+                    .updateMerchant(legacyName, null, null, null, null, null, null, null, null, 1)
+                    .accountsStrict({
+                        merchant: legacyPda,
+                        authority: admin.publicKey,
+                        operator: null,
+                        payoutBook: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([admin])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("AccountNeedsMigration"));
+            }
+        });
+
+        it("should stamp the version through migrate_merchant", async () => {
+            await program.methods
+                .migrateMerchant(legacyName)
+                .accountsStrict({
+                    merchant: legacyPda,
+                    owner: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            const merchant = await program.account.merchant.fetch(legacyPda);
+            assert.equal(merchant.version, 2);
+            assert.equal(merchant.tokenRuleSet, true);
+        });
+
+        it("should accept metadata once migrated", async () => {
+            await program.methods
+                .updateMerchant(legacyName, null, null, null, null, null, null, null, "https://example.com/legacy.json", 1)
+                .accountsStrict({
+                    merchant: legacyPda,
+                    authority: admin.publicKey,
+                    operator: null,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            const merchant = await program.account.merchant.fetch(legacyPda);
+            assert.equal(merchant.metadataUri, "https://example.com/legacy.json");
+            assert.equal(merchant.category, 1);
+        });
+    });
+
+    describe("Merchant Directory", () => {
+        const names = ["DirA", "DirB", "DirC"].map((n) => `${n}${testId}`.slice(0, 32));
+        const pdas = names.map((n) => helper.getMerchantPda(merchantOwner.publicKey, n)[0]);
+
+        before(async () => {
+            for (const [i, name] of names.entries()) {
+                await program.methods
+                    .registerMerchant(merchantWallet.publicKey, PublicKey.default, name, false, null, 0, true, "", 0)
+                    .accountsStrict({
+                        merchant: pdas[i],
+                        merchantStats: helper.getMerchantStatsPda(pdas[i])[0],
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchantRegistry: helper.getMerchantRegistryPda()[0],
+                        merchantIndex: await helper.nextMerchantIndexPda(),
+                        merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+            }
+        });
+
+        it("should list merchants in registration order by index", async () => {
+            const registry = await program.account.merchantRegistry.fetch(helper.getMerchantRegistryPda()[0]);
+            const first = (await program.account.merchant.fetch(pdas[0])).merchantIndex;
+            // Other suites share the validator, so only the three slots are known
+            assert.ok(registry.merchantCount.gte(first.addn(2)));
+
+            for (const [i, pda] of pdas.entries()) {
+                const index = first.addn(i);
+                const entry = await program.account.merchantIndex.fetch(helper.getMerchantIndexPda(index)[0]);
+                assert.ok(entry.index.eq(index));
+                assert.ok(entry.merchant.equals(pda));
+                assert.ok((await program.account.merchant.fetch(pda)).merchantIndex.eq(index));
+            }
+        });
+
+        it("should reject a registration at a stale index", async () => {
+            const name = `DirD${testId}`.slice(0, 32);
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, name);
+            const taken = (await program.account.merchant.fetch(pdas[2])).merchantIndex;
+            try {
+                await program.methods
+                    .registerMerchant(merchantWallet.publicKey, PublicKey.default, name, false, null, 0, true, "", 0)
+                    .accountsStrict({
+                        merchant: pda,
+                        merchantStats: helper.getMerchantStatsPda(pda)[0],
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchantRegistry: helper.getMerchantRegistryPda()[0],
+                        merchantIndex: helper.getMerchantIndexPda(taken)[0],
+                        merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("ConstraintSeeds"));
+            }
+        });
+
+        it("should refuse to index a merchant twice", async () => {
+            try {
+                await program.methods
+                    .indexMerchant(names[0])
+                    .accountsStrict({
+                        merchant: pdas[0],
+                        merchantRegistry: helper.getMerchantRegistryPda()[0],
+                        merchantIndex: await helper.nextMerchantIndexPda(),
+                        payer: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantAlreadyIndexed"));
+            }
+        });
+    });
+
+    describe("Merchant Ownership Transfer", () => {
+        const name = `Sold${testId}`.slice(0, 32);
+        const newOwner = Keypair.generate();
+        const [oldPda] = helper.getMerchantPda(merchantOwner.publicKey, name);
+        const [newPda] = helper.getMerchantPda(newOwner.publicKey, name);
+        const [transferPda] = helper.getMerchantTransferPda(oldPda);
+
+        const accept = async (signer: Keypair) => {
+            await program.methods
+                .acceptMerchantOwnership(name)
+                .accountsStrict({
+                    merchant: oldPda,
+                    merchantTransfer: transferPda,
+                    newMerchant: helper.getMerchantPda(signer.publicKey, name)[0],
+                    merchantStats: helper.getMerchantStatsPda(oldPda)[0],
+                    newMerchantStats: helper.getMerchantStatsPda(helper.getMerchantPda(signer.publicKey, name)[0])[0],
+                    merchantIndex: await helper.getMerchantIndexPdaOf(oldPda),
+                    splitConfig: null,
+                    newSplitConfig: null,
+                    acceptedTokens: null,
+                    newAcceptedTokens: null,
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                    newMerchantName: helper.getMerchantNamePda(signer.publicKey, name)[0],
+                    owner: merchantOwner.publicKey,
+                    newOwner: signer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([signer])
+                .rpc();
+        };
+
+        before(async () => {
+            await helper.airdrop(newOwner.publicKey, 1 * LAMPORTS_PER_SOL);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, name, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: oldPda,
+                    merchantStats: helper.getMerchantStatsPda(oldPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            await program.methods
+                .transferMerchantOwnership(name, newOwner.publicKey)
+                .accountsStrict({
+                    merchant: oldPda,
+                    merchantTransfer: transferPda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should record the proposed owner", async () => {
+            const transfer = await program.account.merchantTransfer.fetch(transferPda);
+            assert.ok(transfer.merchant.equals(oldPda));
+            assert.ok(transfer.newOwner.equals(newOwner.publicKey));
+        });
+
+        it("should reject acceptance by anyone but the proposed owner", async () => {
+            const stranger = Keypair.generate();
+            await helper.airdrop(stranger.publicKey, 1 * LAMPORTS_PER_SOL);
+            try {
+                await accept(stranger);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should move the merchant to the new owner on acceptance", async () => {
+            await accept(newOwner);
+
+            const merchant = await program.account.merchant.fetch(newPda);
+            assert.ok(merchant.owner.equals(newOwner.publicKey));
+            assert.equal(merchant.name, name);
+            assert.ok(merchant.previousMerchant.equals(oldPda));
+            assert.isNull(await program.account.merchant.fetchNullable(oldPda));
+            assert.isNull(await program.account.merchantTransfer.fetchNullable(transferPda));
+            const stats = await program.account.merchantStats.fetch(helper.getMerchantStatsPda(newPda)[0]);
+            assert.ok(stats.merchant.equals(newPda));
+            const entry = await program.account.merchantIndex.fetch(helper.getMerchantIndexPda(merchant.merchantIndex)[0]);
+            assert.ok(entry.merchant.equals(newPda));
+        });
+    });
 });
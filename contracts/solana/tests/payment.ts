@@ -5,7 +5,7 @@ import { OrkiGateway } from "../target/types/orki_gateway";
 import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { assert } from "chai";
 import { TestHelper } from "./utils/helpers";
-import { TOKEN_PROGRAM_ID, getAccount } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, getAccount, getAssociatedTokenAddressSync, ASSOCIATED_TOKEN_PROGRAM_ID, createAccount, closeAccount } from "@solana/spl-token";
 
 describe("Payment Processing", () => {
     const testId = TestHelper.generateTestId("payments");
@@ -14,6 +14,7 @@ describe("Payment Processing", () => {
     anchor.setProvider(anchor.AnchorProvider.env());
     const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
     const helper = new TestHelper(program, testId);
+    const [feeVaultPda] = helper.getFeeVaultPda();
     const provider = anchor.getProvider();
 
     const admin = helper.getAdminKeypair(); // Use shared deterministic admin
@@ -44,10 +45,11 @@ describe("Payment Processing", () => {
         // Initialize global state or update if exists
         try {
             await program.methods
-                .initialize(100, feeWallet.publicKey)
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
-                    admin: admin.publicKey,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
                     systemProgram: SystemProgram.programId,
                 })
                 .signers([admin])
@@ -55,9 +57,9 @@ describe("Payment Processing", () => {
         } catch (e: any) {
             if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
                 console.log("Global State exists. Updating config...");
-                await program.methods.setFee(100).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey }).signers([admin]).rpc();
-                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey }).signers([admin]).rpc();
-                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey }).signers([admin]).rpc();
+                await helper.setFee(admin, 100);
+                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
             } else {
                 throw e;
             }
@@ -66,11 +68,17 @@ describe("Payment Processing", () => {
         // Register merchant
         [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
         await program.methods
-            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName)
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
             .accountsStrict({
                 merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
                 owner: merchantOwner.publicKey,
                 systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
             })
             .signers([merchantOwner])
             .rpc();
@@ -79,9 +87,11 @@ describe("Payment Processing", () => {
         mint = await helper.createTokenMint(payer);
         payerTokenAccount = await helper.createTokenAccount(mint, payer.publicKey, payer);
         merchantTokenAccount = await helper.createTokenAccount(mint, merchantWallet.publicKey, payer);
-        feeTokenAccount = await helper.createTokenAccount(mint, feeWallet.publicKey, payer);
+        feeTokenAccount = await helper.getFeeVaultTokenAccount(mint, payer);
 
         await helper.mintTokens(mint, payerTokenAccount, 1_000_000, payer);
+        await helper.openFeeStats(admin, PublicKey.default);
+        await helper.openFeeStats(admin, mint);
     });
 
     describe("SOL Payments", () => {
@@ -91,33 +101,61 @@ describe("Payment Processing", () => {
             const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId); // Extract PublicKey
 
             const initialMerchantBalance = await provider.connection.getBalance(merchantWallet.publicKey);
-            const initialFeeBalance = await provider.connection.getBalance(feeWallet.publicKey);
+            const initialFeeBalance = await provider.connection.getBalance(feeVaultPda);
 
             // Calculate expected amounts
             const fee = amount.mul(new anchor.BN(100)).div(new anchor.BN(10000));
             const merchantAmount = amount.sub(fee);
 
             await program.methods
-                .processPayment(amount, paymentId, merchantName)
+                .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
                 .accountsStrict({
                     globalState: helper.globalStatePda, // Use helper.globalStatePda
+                    globalStats: helper.getGlobalStatsPda()[0],
                     merchant: merchantPda, // Now accessible at suite level
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
                     payer: payer.publicKey,
                     merchantWallet: merchantWallet.publicKey,
-                    feeWallet: feeWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
                     paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
                     systemProgram: SystemProgram.programId,
                     tokenProgram: null,
                     mint: null,
                     payerTokenAccount: null,
                     merchantTokenAccount: null,
                     feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
                 })
                 .signers([payer])
                 .rpc();
 
             const finalMerchantBalance = await provider.connection.getBalance(merchantWallet.publicKey);
-            const finalFeeBalance = await provider.connection.getBalance(feeWallet.publicKey);
+            const finalFeeBalance = await provider.connection.getBalance(feeVaultPda);
 
             assert.equal(
                 finalMerchantBalance,
@@ -131,7 +169,12 @@ describe("Payment Processing", () => {
             // Verify payment history
             const payment = await program.account.payment.fetch(paymentPda);
             assert.ok(payment.payer.equals(payer.publicKey));
+            assert.ok(payment.merchant.equals(merchantPda));
             assert.equal(payment.amount.toString(), amount.toString());
+            assert.equal(payment.paymentId.toString(), paymentId.toString());
+            assert.equal(payment.fee.toString(), fee.toString());
+            assert.ok(payment.mint.equals(PublicKey.default));
+            assert.ok("completed" in payment.status);
         });
 
         it("should fail if contract is paused", async () => {
@@ -140,7 +183,8 @@ describe("Payment Processing", () => {
                 .setPaused(true)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
-                    admin: admin.publicKey,
+                    authority: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
@@ -151,20 +195,48 @@ describe("Payment Processing", () => {
 
             try {
                 await program.methods
-                    .processPayment(amount, paymentId, merchantName)
+                    .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
                     .accountsStrict({
                         globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
                         merchant: merchantPda,
+                        merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
                         payer: payer.publicKey,
                         merchantWallet: merchantWallet.publicKey,
-                        feeWallet: feeWallet.publicKey,
+                        feeVault: helper.getFeeVaultPda()[0],
+                        feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
                         paymentHistory: paymentPda,
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
                         systemProgram: SystemProgram.programId,
                         tokenProgram: null,
                         mint: null,
                         payerTokenAccount: null,
                         merchantTokenAccount: null,
                         feeTokenAccount: null,
+                        associatedTokenProgram: null,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
                     })
                     .signers([payer])
                     .rpc();
@@ -178,7 +250,8 @@ describe("Payment Processing", () => {
                 .setPaused(false)
                 .accountsStrict({
                     globalState: helper.globalStatePda,
-                    admin: admin.publicKey,
+                    authority: admin.publicKey,
+                    ...helper.eventCpi,
                 })
                 .signers([admin])
                 .rpc();
@@ -191,20 +264,48 @@ describe("Payment Processing", () => {
 
             try {
                 await program.methods
-                    .processPayment(hugeAmount, paymentId, merchantName)
+                    .processPayment(hugeAmount, paymentId, merchantName, null, null, null, new anchor.BN(0))
                     .accountsStrict({
                         globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
                         merchant: merchantPda,
+                        merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
                         payer: payer.publicKey,
                         merchantWallet: merchantWallet.publicKey,
-                        feeWallet: feeWallet.publicKey,
+                        feeVault: helper.getFeeVaultPda()[0],
+                        feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
                         paymentHistory: paymentPda,
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
                         systemProgram: SystemProgram.programId,
                         tokenProgram: null,
                         mint: null,
                         payerTokenAccount: null,
                         merchantTokenAccount: null,
                         feeTokenAccount: null,
+                        associatedTokenProgram: null,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
                     })
                     .signers([payer])
                     .rpc();
@@ -213,6 +314,83 @@ describe("Payment Processing", () => {
                 assert.ok(e.message.includes("InsufficientBalance"));
             }
         });
+
+        describe("Payer Rent", () => {
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+
+            const payFrom = (signer: Keypair) => {
+                const paymentId = new anchor.BN(Date.now());
+                return program.methods
+                    .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchant: merchantPda,
+                        merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                        payer: signer.publicKey,
+                        merchantWallet: merchantWallet.publicKey,
+                        feeVault: feeVaultPda,
+                        feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                        paymentHistory: helper.getPaymentPda(signer.publicKey, paymentId)[0],
+                        blocklistEntry: helper.getBlockedPda(signer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
+                        systemProgram: SystemProgram.programId,
+                        tokenProgram: null,
+                        mint: null,
+                        payerTokenAccount: null,
+                        merchantTokenAccount: null,
+                        feeTokenAccount: null,
+                        associatedTokenProgram: null,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([signer])
+                    .rpc();
+            };
+
+            it("should reject a payer funded with exactly the amount", async () => {
+                const exact = Keypair.generate();
+                await helper.airdrop(exact.publicKey, amount.toNumber());
+                try {
+                    await payFrom(exact);
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.ok(e.message.includes("InsufficientBalance"));
+                }
+            });
+
+            it("should accept a payer funded for the amount, the record's rent and its own reserve", async () => {
+                const funded = Keypair.generate();
+                const recordRent = await provider.connection.getMinimumBalanceForRentExemption(program.account.payment.size);
+                const reserve = await provider.connection.getMinimumBalanceForRentExemption(0);
+                await helper.airdrop(funded.publicKey, amount.toNumber() + recordRent + reserve);
+
+                await payFrom(funded);
+
+                // The payer is left holding exactly its rent-exempt reserve
+                assert.equal(await provider.connection.getBalance(funded.publicKey), reserve);
+            });
+        });
     });
 
     describe("SPL Payments", () => {
@@ -223,11 +401,17 @@ describe("Payment Processing", () => {
             // Register SPL merchant - Extract PublicKey from tuple
             [splMerchantPda] = helper.getMerchantPda(merchantOwner.publicKey, splMerchantName);
             await program.methods
-                .registerMerchant(merchantWallet.publicKey, mint, splMerchantName)
+                .registerMerchant(merchantWallet.publicKey, mint, splMerchantName, false, null, 0, false, "", 0)
                 .accountsStrict({
                     merchant: splMerchantPda,
+                    merchantStats: helper.getMerchantStatsPda(splMerchantPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, splMerchantName)[0],
                     owner: merchantOwner.publicKey,
                     systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
                 })
                 .signers([merchantOwner])
                 .rpc();
@@ -245,20 +429,48 @@ describe("Payment Processing", () => {
             const merchantAmount = amount.sub(fee);
 
             await program.methods
-                .processPayment(amount, paymentId, splMerchantName)
+                .processPayment(amount, paymentId, splMerchantName, null, null, null, new anchor.BN(0))
                 .accountsStrict({
                     globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
                     merchant: splMerchantPda,
+                    merchantStats: helper.getMerchantStatsPda(splMerchantPda)[0],
                     payer: payer.publicKey,
                     merchantWallet: merchantWallet.publicKey,
-                    feeWallet: feeWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(mint)[0],
                     paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(mint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
                     systemProgram: SystemProgram.programId,
                     tokenProgram: TOKEN_PROGRAM_ID,
                     mint: mint,
                     payerTokenAccount: payerTokenAccount,
                     merchantTokenAccount: merchantTokenAccount,
                     feeTokenAccount: feeTokenAccount,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
                 })
                 .signers([payer])
                 .rpc();
@@ -274,6 +486,15 @@ describe("Payment Processing", () => {
                 feeAcctAfter.amount.toString(),
                 (BigInt(feeAcctBefore.amount.toString()) + BigInt(fee.toString())).toString()
             );
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.ok(payment.payer.equals(payer.publicKey));
+            assert.ok(payment.merchant.equals(splMerchantPda));
+            assert.equal(payment.amount.toString(), amount.toString());
+            assert.equal(payment.paymentId.toString(), paymentId.toString());
+            assert.equal(payment.fee.toString(), fee.toString());
+            assert.ok(payment.mint.equals(mint));
+            assert.ok("completed" in payment.status);
         });
 
         it("should fail if wrong token is used", async () => {
@@ -286,20 +507,48 @@ describe("Payment Processing", () => {
 
             try {
                 await program.methods
-                    .processPayment(amount, paymentId, splMerchantName)
+                    .processPayment(amount, paymentId, splMerchantName, null, null, null, new anchor.BN(0))
                     .accountsStrict({
                         globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
                         merchant: splMerchantPda,
+                        merchantStats: helper.getMerchantStatsPda(splMerchantPda)[0],
                         payer: payer.publicKey,
                         merchantWallet: merchantWallet.publicKey,
-                        feeWallet: feeWallet.publicKey,
+                        feeVault: helper.getFeeVaultPda()[0],
+                        feeStats: helper.getFeeStatsPda(mint)[0],
                         paymentHistory: paymentPda,
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(mint)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
                         systemProgram: SystemProgram.programId,
                         tokenProgram: TOKEN_PROGRAM_ID,
                         mint: wrongMint,
                         payerTokenAccount: wrongTokenAccount,
-                        merchantTokenAccount: merchantTokenAccount,
-                        feeTokenAccount: feeTokenAccount,
+                        merchantTokenAccount: getAssociatedTokenAddressSync(wrongMint, merchantWallet.publicKey),
+                        feeTokenAccount: getAssociatedTokenAddressSync(wrongMint, feeVaultPda, true),
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
                     })
                     .signers([payer])
                     .rpc();
@@ -308,5 +557,4042 @@ describe("Payment Processing", () => {
                 assert.ok(e.message.includes("InvalidToken"));
             }
         });
+
+        it("should create the merchant and fee vault token accounts on first use of a mint", async () => {
+            const freshMint = await helper.createTokenMint(payer);
+            const payerFresh = await helper.createTokenAccount(freshMint, payer.publicKey, payer);
+            await helper.mintTokens(freshMint, payerFresh, 100_000, payer);
+            await helper.openFeeStats(admin, freshMint);
+
+            const merchantAta = getAssociatedTokenAddressSync(freshMint, merchantWallet.publicKey);
+            const feeAta = getAssociatedTokenAddressSync(freshMint, feeVaultPda, true);
+            assert.isNull(await provider.connection.getAccountInfo(merchantAta));
+            assert.isNull(await provider.connection.getAccountInfo(feeAta));
+
+            const amount = new anchor.BN(10_000);
+            const fee = amount.muln(100).divn(10000);
+            const paymentId = new anchor.BN(Date.now() + 5);
+            await program.methods
+                .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(freshMint)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(freshMint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    mint: freshMint,
+                    payerTokenAccount: payerFresh,
+                    merchantTokenAccount: merchantAta,
+                    feeTokenAccount: feeAta,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+            const merchantAcct = await getAccount(provider.connection, merchantAta);
+            const feeAcct = await getAccount(provider.connection, feeAta);
+            assert.ok(merchantAcct.owner.equals(merchantWallet.publicKey));
+            assert.equal(merchantAcct.amount.toString(), amount.sub(fee).toString());
+            assert.ok(feeAcct.owner.equals(feeVaultPda));
+            assert.equal(feeAcct.amount.toString(), fee.toString());
+        });
+
+        it("should reject a merchant token account that is not the settlement wallet's ATA", async () => {
+            // A token account the payer controls, for the right mint
+            const attackerAccount = await helper.createTokenAccount(mint, Keypair.generate().publicKey, payer);
+            const paymentId = new anchor.BN(Date.now() + 6);
+            try {
+                await program.methods
+                    .processPayment(new anchor.BN(10_000), paymentId, splMerchantName, null, null, null, new anchor.BN(0))
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchant: splMerchantPda,
+                        merchantStats: helper.getMerchantStatsPda(splMerchantPda)[0],
+                        payer: payer.publicKey,
+                        merchantWallet: merchantWallet.publicKey,
+                        feeVault: feeVaultPda,
+                        feeStats: helper.getFeeStatsPda(mint)[0],
+                        paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(mint)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
+                        systemProgram: SystemProgram.programId,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        mint: mint,
+                        payerTokenAccount: payerTokenAccount,
+                        merchantTokenAccount: attackerAccount,
+                        feeTokenAccount: feeTokenAccount,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([payer])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("ConstraintAssociated") || e.message.includes("ConstraintTokenOwner"));
+            }
+        });
+    });
+
+    describe("Sandbox Payments", () => {
+        const sandboxName = "SandboxShop";
+        let sandboxPda: PublicKey;
+
+        before(async () => {
+            [sandboxPda] = helper.getMerchantPda(merchantOwner.publicKey, sandboxName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, sandboxName, true, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: sandboxPda,
+                    merchantStats: helper.getMerchantStatsPda(sandboxPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, sandboxName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should record a sandbox SOL payment without moving funds", async () => {
+            const amount = new anchor.BN(0.5 * LAMPORTS_PER_SOL);
+            const paymentId = new anchor.BN(Date.now() + 10);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+
+            const merchantBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+            const feeBefore = await provider.connection.getBalance(feeVaultPda);
+
+            await program.methods
+                .processPayment(amount, paymentId, sandboxName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: sandboxPda,
+                    merchantStats: helper.getMerchantStatsPda(sandboxPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+            assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), merchantBefore);
+            assert.equal(await provider.connection.getBalance(feeVaultPda), feeBefore);
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.sandbox, true);
+            assert.equal(payment.amount.toString(), amount.toString());
+        });
+
+        it("should record a sandbox SPL payment without moving tokens", async () => {
+            const amount = new anchor.BN(50_000);
+            const paymentId = new anchor.BN(Date.now() + 11);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+
+            const payerBefore = await getAccount(provider.connection, payerTokenAccount);
+            const merchantBefore = await getAccount(provider.connection, merchantTokenAccount);
+
+            await program.methods
+                .processPayment(amount, paymentId, sandboxName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: sandboxPda,
+                    merchantStats: helper.getMerchantStatsPda(sandboxPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(mint)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(mint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    mint: mint,
+                    payerTokenAccount: payerTokenAccount,
+                    merchantTokenAccount: merchantTokenAccount,
+                    feeTokenAccount: feeTokenAccount,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+            const payerAfter = await getAccount(provider.connection, payerTokenAccount);
+            const merchantAfter = await getAccount(provider.connection, merchantTokenAccount);
+            assert.equal(payerAfter.amount.toString(), payerBefore.amount.toString());
+            assert.equal(merchantAfter.amount.toString(), merchantBefore.amount.toString());
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.sandbox, true);
+        });
+    });
+
+    describe("Partial Refunds", () => {
+        const amount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+        const paymentId = new anchor.BN(Date.now() + 20);
+        let paymentPda: PublicKey;
+
+        const refund = (refundAmount: anchor.BN) =>
+            program.methods
+                .refundPartial(merchantName, refundAmount)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payment: paymentPda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payer: payer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    authorityTokenAccount: null,
+                    payerTokenAccount: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        before(async () => {
+            [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        });
+
+        it("should reject a zero refund", async () => {
+            try {
+                await refund(new anchor.BN(0));
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidRefundAmount"));
+            }
+        });
+
+        it("should accept two partial refunds summing to the payment amount", async () => {
+            const first = new anchor.BN(0.05 * LAMPORTS_PER_SOL);
+            const second = amount.sub(first);
+            const payerBefore = await provider.connection.getBalance(payer.publicKey);
+
+            await refund(first);
+            let payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.refundedAmount.toString(), first.toString());
+
+            await refund(second);
+            payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.refundedAmount.toString(), amount.toString());
+
+            const payerAfter = await provider.connection.getBalance(payer.publicKey);
+            assert.equal(payerAfter, payerBefore + amount.toNumber());
+        });
+
+        it("should reject a refund beyond the original amount", async () => {
+            try {
+                await refund(new anchor.BN(1));
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("RefundExceedsPayment"));
+            }
+        });
+    });
+
+    describe("Merchant Fee Override", () => {
+        const overrideName = "OverrideShop";
+        let overridePda: PublicKey;
+
+        const setMerchantFee = (bps: number | null) =>
+            program.methods
+                .setMerchantFee(overrideName, bps)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: overridePda,
+                    admin: admin.publicKey,
+                    adminProposal: null,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        const paySol = async (amount: anchor.BN, paymentId: anchor.BN) => {
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(amount, paymentId, overrideName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: overridePda,
+                    merchantStats: helper.getMerchantStatsPda(overridePda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        before(async () => {
+            [overridePda] = helper.getMerchantPda(merchantOwner.publicKey, overrideName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, overrideName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: overridePda,
+                    merchantStats: helper.getMerchantStatsPda(overridePda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, overrideName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should reject an override set by a non-admin", async () => {
+            try {
+                await program.methods
+                    .setMerchantFee(overrideName, 10)
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        merchant: overridePda,
+                        admin: merchantOwner.publicKey,
+                        adminProposal: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should reject an override above the fee cap", async () => {
+            try {
+                await setMerchantFee(10001);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("FeeCapExceeded"));
+            }
+        });
+
+        it("should apply the override to SOL payments", async () => {
+            await setMerchantFee(50);
+            const merchant = await program.account.merchant.fetch(overridePda);
+            assert.equal(merchant.feeBpsOverride, 50);
+
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+            const feeBefore = await provider.connection.getBalance(feeVaultPda);
+            await paySol(amount, new anchor.BN(Date.now() + 30));
+            const feeAfter = await provider.connection.getBalance(feeVaultPda);
+
+            assert.equal(feeAfter - feeBefore, amount.muln(50).divn(10000).toNumber());
+        });
+
+        it("should apply the override to SPL payments", async () => {
+            const amount = new anchor.BN(100_000);
+            const paymentId = new anchor.BN(Date.now() + 31);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            const feeBefore = await getAccount(provider.connection, feeTokenAccount);
+
+            await program.methods
+                .processPayment(amount, paymentId, overrideName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: overridePda,
+                    merchantStats: helper.getMerchantStatsPda(overridePda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(mint)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(mint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    mint: mint,
+                    payerTokenAccount: payerTokenAccount,
+                    merchantTokenAccount: merchantTokenAccount,
+                    feeTokenAccount: feeTokenAccount,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+            const feeAfter = await getAccount(provider.connection, feeTokenAccount);
+            assert.equal(
+                (feeAfter.amount - feeBefore.amount).toString(),
+                amount.muln(50).divn(10000).toString()
+            );
+        });
+
+        it("should revert to the global fee once cleared", async () => {
+            await setMerchantFee(null);
+            const merchant = await program.account.merchant.fetch(overridePda);
+            assert.equal(merchant.feeBpsOverride, null);
+
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+            const feeBefore = await provider.connection.getBalance(feeVaultPda);
+            await paySol(amount, new anchor.BN(Date.now() + 32));
+            const feeAfter = await provider.connection.getBalance(feeVaultPda);
+
+            assert.equal(feeAfter - feeBefore, amount.muln(100).divn(10000).toNumber());
+        });
+    });
+
+    describe("SPL Decimals", () => {
+        const decimalsName = "DecimalsShop";
+        let decimalsPda: PublicKey;
+
+        before(async () => {
+            [decimalsPda] = helper.getMerchantPda(merchantOwner.publicKey, decimalsName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, decimalsName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: decimalsPda,
+                    merchantStats: helper.getMerchantStatsPda(decimalsPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, decimalsName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        for (const decimals of [6, 9]) {
+            it(`should settle unchanged amounts for a ${decimals}-decimal mint`, async () => {
+                const decMint = await helper.createTokenMint(payer, decimals);
+                const payerAta = await helper.createTokenAccount(decMint, payer.publicKey, payer);
+                const merchantAta = await helper.createTokenAccount(decMint, merchantWallet.publicKey, payer);
+                const feeAta = await helper.getFeeVaultTokenAccount(decMint, payer);
+                await helper.openFeeStats(admin, decMint);
+                await helper.mintTokens(decMint, payerAta, 10 ** decimals, payer);
+
+                const amount = new anchor.BN(10 ** decimals).divn(2);
+                const paymentId = new anchor.BN(Date.now() + 40 + decimals);
+                const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+                const fee = amount.muln(100).divn(10000);
+
+                await program.methods
+                    .processPayment(amount, paymentId, decimalsName, null, null, null, new anchor.BN(0))
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchant: decimalsPda,
+                        merchantStats: helper.getMerchantStatsPda(decimalsPda)[0],
+                        payer: payer.publicKey,
+                        merchantWallet: merchantWallet.publicKey,
+                        feeVault: helper.getFeeVaultPda()[0],
+                        feeStats: helper.getFeeStatsPda(decMint)[0],
+                        paymentHistory: paymentPda,
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(decMint)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
+                        systemProgram: SystemProgram.programId,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        mint: decMint,
+                        payerTokenAccount: payerAta,
+                        merchantTokenAccount: merchantAta,
+                        feeTokenAccount: feeAta,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([payer])
+                    .rpc();
+
+                const merchantAcct = await getAccount(provider.connection, merchantAta);
+                const feeAcct = await getAccount(provider.connection, feeAta);
+                assert.equal(merchantAcct.amount.toString(), amount.sub(fee).toString());
+                assert.equal(feeAcct.amount.toString(), fee.toString());
+            });
+        }
+    });
+
+    describe("Token-2022 Payments", () => {
+        const t22Name = "Token22Shop";
+        let t22Pda: PublicKey;
+        let t22Mint: PublicKey;
+        let payerAta: PublicKey;
+        let merchantAta: PublicKey;
+        let feeAta: PublicKey;
+        const [featureFlagsPda] = helper.getFeatureFlagsPda();
+
+        const setToken2022 = (enabled: boolean) =>
+            program.methods
+                .setFeature({ token2022: {} }, enabled)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    featureFlags: featureFlagsPda,
+                    admin: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+
+        const payToken2022 = async (amount: anchor.BN, paymentId: anchor.BN) => {
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(amount, paymentId, t22Name, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: t22Pda,
+                    merchantStats: helper.getMerchantStatsPda(t22Pda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(t22Mint)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(t22Mint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: TOKEN_2022_PROGRAM_ID,
+                    mint: t22Mint,
+                    payerTokenAccount: payerAta,
+                    merchantTokenAccount: merchantAta,
+                    feeTokenAccount: feeAta,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: featureFlagsPda,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        before(async () => {
+            t22Mint = await helper.createTokenMint(payer, 6, TOKEN_2022_PROGRAM_ID);
+            payerAta = await helper.createTokenAccount(t22Mint, payer.publicKey, payer, TOKEN_2022_PROGRAM_ID);
+            merchantAta = await helper.createTokenAccount(t22Mint, merchantWallet.publicKey, payer, TOKEN_2022_PROGRAM_ID);
+            feeAta = await helper.getFeeVaultTokenAccount(t22Mint, payer, TOKEN_2022_PROGRAM_ID);
+            await helper.openFeeStats(admin, t22Mint);
+            await helper.mintTokens(t22Mint, payerAta, 1_000_000, payer, TOKEN_2022_PROGRAM_ID);
+
+            [t22Pda] = helper.getMerchantPda(merchantOwner.publicKey, t22Name);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, t22Mint, t22Name, false, null, 0, false, "", 0)
+                .accountsStrict({
+                    merchant: t22Pda,
+                    merchantStats: helper.getMerchantStatsPda(t22Pda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, t22Name)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should reject Token-2022 payments while the feature is off", async () => {
+            await setToken2022(false);
+            try {
+                await payToken2022(new anchor.BN(10_000), new anchor.BN(Date.now() + 50));
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("FeatureDisabled"));
+            }
+        });
+
+        it("should process a Token-2022 payment once enabled", async () => {
+            await setToken2022(true);
+            const amount = new anchor.BN(100_000);
+            const fee = amount.muln(100).divn(10000);
+
+            await payToken2022(amount, new anchor.BN(Date.now() + 51));
+
+            const merchantAcct = await getAccount(provider.connection, merchantAta, undefined, TOKEN_2022_PROGRAM_ID);
+            const feeAcct = await getAccount(provider.connection, feeAta, undefined, TOKEN_2022_PROGRAM_ID);
+            assert.equal(merchantAcct.amount.toString(), amount.sub(fee).toString());
+            assert.equal(feeAcct.amount.toString(), fee.toString());
+
+            await setToken2022(false);
+        });
+    });
+
+    describe("Merchant Status", () => {
+        const statusName = "StatusShop";
+        let statusPda: PublicKey;
+
+        const setActive = (signer: Keypair, active: boolean) =>
+            program.methods
+                .setMerchantActive(statusName, active)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: statusPda,
+                    authority: signer.publicKey,
+                    operator: null,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        const paySol = async () => {
+            const paymentId = new anchor.BN(Date.now());
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(new anchor.BN(LAMPORTS_PER_SOL / 100), paymentId, statusName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: statusPda,
+                    merchantStats: helper.getMerchantStatsPda(statusPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        before(async () => {
+            [statusPda] = helper.getMerchantPda(merchantOwner.publicKey, statusName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, statusName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: statusPda,
+                    merchantStats: helper.getMerchantStatsPda(statusPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, statusName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should register merchants as active", async () => {
+            const merchant = await program.account.merchant.fetch(statusPda);
+            assert.isTrue(merchant.active);
+            assert.isFalse(merchant.adminLocked);
+        });
+
+        it("should reject payments to a merchant the owner paused", async () => {
+            await setActive(merchantOwner, false);
+            try {
+                await paySol();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantInactive"));
+            }
+
+            await setActive(merchantOwner, true);
+            await paySol();
+        });
+
+        it("should not let the owner reactivate after an admin deactivation", async () => {
+            await setActive(admin, false);
+            const merchant = await program.account.merchant.fetch(statusPda);
+            assert.isFalse(merchant.active);
+            assert.isTrue(merchant.adminLocked);
+
+            try {
+                await setActive(merchantOwner, true);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantAdminLocked"));
+            }
+
+            try {
+                await paySol();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantInactive"));
+            }
+
+            await setActive(admin, true);
+            const restored = await program.account.merchant.fetch(statusPda);
+            assert.isTrue(restored.active);
+            assert.isFalse(restored.adminLocked);
+        });
+
+        it("should reject toggles from anyone else", async () => {
+            const stranger = Keypair.generate();
+            await helper.airdrop(stranger.publicKey);
+            try {
+                await setActive(stranger, false);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+    });
+
+    describe("Merchant Rename", () => {
+        const oldName = "RenameShop";
+        const newName = "RenamedShop";
+        let oldPda: PublicKey;
+        let newPda: PublicKey;
+
+        const paySol = async (name: string, pda: PublicKey, paymentId: anchor.BN) => {
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(new anchor.BN(LAMPORTS_PER_SOL / 100), paymentId, name, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+            return paymentPda;
+        };
+
+        before(async () => {
+            [oldPda] = helper.getMerchantPda(merchantOwner.publicKey, oldName);
+            [newPda] = helper.getMerchantPda(merchantOwner.publicKey, newName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, oldName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: oldPda,
+                    merchantStats: helper.getMerchantStatsPda(oldPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, oldName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should resolve payments and refunds after a rename", async () => {
+            const oldPaymentPda = await paySol(oldName, oldPda, new anchor.BN(Date.now() + 60));
+
+            await program.methods
+                .renameMerchant(oldName, newName)
+                .accountsStrict({
+                    merchant: oldPda,
+                    newMerchant: newPda,
+                    merchantStats: helper.getMerchantStatsPda(oldPda)[0],
+                    newMerchantStats: helper.getMerchantStatsPda(newPda)[0],
+                    merchantIndex: await helper.getMerchantIndexPdaOf(oldPda),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, oldName)[0],
+                    newMerchantName: helper.getMerchantNamePda(merchantOwner.publicKey, newName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            // New payments go through the renamed PDA
+            const newPaymentPda = await paySol(newName, newPda, new anchor.BN(Date.now() + 61));
+            const payment = await program.account.payment.fetch(newPaymentPda);
+            assert.ok(payment.merchant.equals(newPda));
+
+            // A payment made before the rename can still be refunded
+            await program.methods
+                .refundPartial(newName, new anchor.BN(1000))
+                .accountsStrict({
+                    merchant: newPda,
+                    merchantStats: helper.getMerchantStatsPda(newPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payment: oldPaymentPda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payer: payer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    authorityTokenAccount: null,
+                    payerTokenAccount: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            const refunded = await program.account.payment.fetch(oldPaymentPda);
+            assert.equal(refunded.refundedAmount.toNumber(), 1000);
+        });
+
+        it("should reject payments addressed to the old name", async () => {
+            try {
+                await paySol(oldName, oldPda, new anchor.BN(Date.now() + 62));
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("AccountNotInitialized") || e.message.includes("3012"));
+            }
+        });
+    });
+
+    describe("Recent Payments", () => {
+        const recentName = "RecentShop";
+        let recentPda: PublicKey;
+        let cachePda: PublicKey;
+
+        const paySol = async (paymentId: anchor.BN, amount: anchor.BN, withCache: boolean) => {
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            return program.methods
+                .processPayment(amount, paymentId, recentName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: recentPda,
+                    merchantStats: helper.getMerchantStatsPda(recentPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: withCache ? cachePda : null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc({ commitment: "confirmed" });
+        };
+
+        const unitsConsumed = async (sig: string) => {
+            const tx = await provider.connection.getTransaction(sig, {
+                commitment: "confirmed",
+                maxSupportedTransactionVersion: 0,
+            });
+            return tx!.meta!.computeUnitsConsumed!;
+        };
+
+        before(async () => {
+            [recentPda] = helper.getMerchantPda(merchantOwner.publicKey, recentName);
+            [cachePda] = helper.getRecentPaymentsPda(recentPda);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, recentName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: recentPda,
+                    merchantStats: helper.getMerchantStatsPda(recentPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, recentName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            await program.methods
+                .initRecentPayments(recentName)
+                .accountsStrict({
+                    merchant: recentPda,
+                    recentPayments: cachePda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should keep only the 8 most recent payments in order", async () => {
+            const baseId = Date.now() + 100;
+            for (let i = 0; i < 10; i++) {
+                await paySol(new anchor.BN(baseId + i), new anchor.BN(1000 + i), true);
+            }
+
+            const cache = await program.account.recentPayments.fetch(cachePda);
+            assert.equal(cache.count, 8);
+
+            // Walk the ring from the oldest slot
+            const ordered = [];
+            for (let i = 0; i < 8; i++) {
+                ordered.push(cache.entries[(cache.next + i) % 8]);
+            }
+            ordered.forEach((entry, i) => {
+                assert.equal(entry.paymentId.toNumber(), baseId + 2 + i);
+                assert.equal(entry.amount.toNumber(), 1000 + 2 + i);
+                assert.ok(entry.mint.equals(PublicKey.default));
+            });
+        });
+
+        it("should report the compute overhead of the cache write", async () => {
+            const baseId = Date.now() + 200;
+            const without = await unitsConsumed(await paySol(new anchor.BN(baseId), new anchor.BN(1000), false));
+            const withCache = await unitsConsumed(await paySol(new anchor.BN(baseId + 1), new anchor.BN(1000), true));
+            console.log(`Recent payments cache overhead: ${withCache - without} CU (${without} -> ${withCache})`);
+            assert.isAbove(withCache, without);
+        });
+    });
+
+    describe("Notification Queue", () => {
+        const queueName = "NotifyShop";
+        const stranger = Keypair.generate();
+        let notifyPda: PublicKey;
+        let queuePda: PublicKey;
+
+        const payWithQueue = (paymentId: anchor.BN, amount: anchor.BN) =>
+            program.methods
+                .processPayment(amount, paymentId, queueName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: notifyPda,
+                    merchantStats: helper.getMerchantStatsPda(notifyPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: queuePda,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+        const clear = (owner: Keypair) =>
+            program.methods
+                .clearNotifications(queueName)
+                .accountsStrict({
+                    merchant: notifyPda,
+                    notificationQueue: queuePda,
+                    owner: owner.publicKey,
+                })
+                .signers([owner])
+                .rpc();
+
+        before(async () => {
+            await helper.airdrop(stranger.publicKey);
+            [notifyPda] = helper.getMerchantPda(merchantOwner.publicKey, queueName);
+            [queuePda] = helper.getNotificationQueuePda(notifyPda);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, queueName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: notifyPda,
+                    merchantStats: helper.getMerchantStatsPda(notifyPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, queueName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            await program.methods
+                .initNotificationQueue(queueName)
+                .accountsStrict({
+                    merchant: notifyPda,
+                    notificationQueue: queuePda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should queue each payment and let only the owner clear it", async () => {
+            const baseId = Date.now() + 300;
+            for (let i = 0; i < 3; i++) {
+                await payWithQueue(new anchor.BN(baseId + i), new anchor.BN(2000 + i));
+            }
+
+            const queue = await program.account.notificationQueue.fetch(queuePda);
+            assert.equal(queue.count, 3);
+            assert.equal(queue.total.toNumber(), 3);
+            queue.entries.slice(0, 3).forEach((entry, i) => {
+                assert.equal(entry.paymentId.toNumber(), baseId + i);
+                assert.equal(entry.amount.toNumber(), 2000 + i);
+                assert.isAbove(entry.timestamp.toNumber(), 0);
+            });
+
+            try {
+                await clear(stranger);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("ConstraintSeeds") || e.message.includes("ConstraintHasOne"));
+            }
+
+            await clear(merchantOwner);
+            const cleared = await program.account.notificationQueue.fetch(queuePda);
+            assert.equal(cleared.count, 0);
+            assert.equal(cleared.next, 0);
+            assert.equal(cleared.total.toNumber(), 3);
+        });
+    });
+
+    describe("Wallet Sanity Checks", () => {
+        const selfName = "SelfPayShop";
+        const feeName = "FeeWalletShop";
+        const shopWallet = Keypair.generate();
+        let selfPda: PublicKey;
+        let feePda: PublicKey;
+        let feeWalletKey: PublicKey;
+
+        const register = async (pda: PublicKey, name: string, settlementWallet: PublicKey) => {
+            await program.methods
+                .registerMerchant(settlementWallet, PublicKey.default, name, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        };
+
+        const pay = (pda: PublicKey, name: string, from: Keypair, settlementWallet: PublicKey) => {
+            const paymentId = new anchor.BN(Date.now());
+            return program.methods
+                .processPayment(new anchor.BN(1000), paymentId, name, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    payer: from.publicKey,
+                    merchantWallet: settlementWallet,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(from.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(from.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([from])
+                .rpc();
+        };
+
+        before(async () => {
+            await helper.airdrop(shopWallet.publicKey);
+            [selfPda] = helper.getMerchantPda(merchantOwner.publicKey, selfName);
+            [feePda] = helper.getMerchantPda(merchantOwner.publicKey, feeName);
+            // Whichever suite initialized the gateway chose the fee wallet
+            feeWalletKey = (await program.account.globalState.fetch(helper.globalStatePda)).feeWallet;
+            await register(selfPda, selfName, shopWallet.publicKey);
+            await register(feePda, feeName, feeWalletKey);
+        });
+
+        it("should reject a merchant whose settlement wallet is the fee wallet", async () => {
+            try {
+                await pay(feePda, feeName, payer, feeWalletKey);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("SettlementIsFeeWallet"));
+            }
+        });
+
+        it("should reject a self-payment unless the merchant allows it", async () => {
+            try {
+                await pay(selfPda, selfName, shopWallet, shopWallet.publicKey);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("SelfPayment"));
+            }
+
+            await program.methods
+                .setAllowSelfPayments(selfName, true)
+                .accountsStrict({
+                    merchant: selfPda,
+                    owner: merchantOwner.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            await pay(selfPda, selfName, shopWallet, shopWallet.publicKey);
+            assert.isTrue((await program.account.merchant.fetch(selfPda)).allowSelfPayments);
+        });
+    });
+
+    describe("Split Payments", () => {
+        const splitName = "SplitShop";
+        const recipients = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+        const shares = [3333, 3333, 3334];
+        let splitPda: PublicKey;
+        let splitConfigPda: PublicKey;
+        let recipientTokenAccounts: PublicKey[];
+
+        const expectedShares = (total: anchor.BN) => {
+            const cuts = shares.map((bps) => total.muln(bps).divn(10000));
+            const dust = cuts.reduce((acc, c) => acc.sub(c), total);
+            cuts[0] = cuts[0].add(dust);
+            return cuts;
+        };
+
+        before(async () => {
+            [splitPda] = helper.getMerchantPda(merchantOwner.publicKey, splitName);
+            [splitConfigPda] = helper.getSplitConfigPda(splitPda);
+            for (const r of recipients) {
+                await helper.airdrop(r.publicKey);
+            }
+
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, splitName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: splitPda,
+                    merchantStats: helper.getMerchantStatsPda(splitPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, splitName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            await program.methods
+                .setSplitConfig(
+                    splitName,
+                    recipients.map((r, i) => ({ recipient: r.publicKey, bps: shares[i] }))
+                )
+                .accountsStrict({
+                    merchant: splitPda,
+                    splitConfig: splitConfigPda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            recipientTokenAccounts = [];
+            for (const r of recipients) {
+                recipientTokenAccounts.push(await helper.createTokenAccount(mint, r.publicKey, payer));
+            }
+        });
+
+        it("should reject shares that do not sum to 10000 bps", async () => {
+            try {
+                await program.methods
+                    .setSplitConfig(splitName, [{ recipient: recipients[0].publicKey, bps: 9999 }])
+                    .accountsStrict({
+                        merchant: splitPda,
+                        splitConfig: splitConfigPda,
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidSplit"));
+            }
+        });
+
+        it("should split a SOL payment across the recipients", async () => {
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10 + 7);
+            const paymentId = new anchor.BN(Date.now() + 300);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+            const before = await Promise.all(recipients.map((r) => provider.connection.getBalance(r.publicKey)));
+
+            await program.methods
+                .processPayment(amount, paymentId, splitName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: splitPda,
+                    merchantStats: helper.getMerchantStatsPda(splitPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: splitConfigPda,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .remainingAccounts(recipients.map((r) => ({ pubkey: r.publicKey, isSigner: false, isWritable: true })))
+                .signers([payer])
+                .rpc();
+
+            const cuts = expectedShares(merchantAmount);
+            for (let i = 0; i < recipients.length; i++) {
+                const after = await provider.connection.getBalance(recipients[i].publicKey);
+                assert.equal(after - before[i], cuts[i].toNumber());
+            }
+        });
+
+        it("should split an SPL payment across the recipients' token accounts", async () => {
+            const amount = new anchor.BN(100_001);
+            const paymentId = new anchor.BN(Date.now() + 301);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+
+            await program.methods
+                .processPayment(amount, paymentId, splitName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: splitPda,
+                    merchantStats: helper.getMerchantStatsPda(splitPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(mint)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(mint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    mint: mint,
+                    payerTokenAccount: payerTokenAccount,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: feeTokenAccount,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: splitConfigPda,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .remainingAccounts(recipientTokenAccounts.map((ta) => ({ pubkey: ta, isSigner: false, isWritable: true })))
+                .signers([payer])
+                .rpc();
+
+            const cuts = expectedShares(merchantAmount);
+            for (let i = 0; i < recipients.length; i++) {
+                const acct = await getAccount(provider.connection, recipientTokenAccounts[i]);
+                assert.equal(acct.amount.toString(), cuts[i].toString());
+            }
+        });
+
+        it("should report a closed recipient token account as missing", async () => {
+            const closed = await createAccount(provider.connection, payer, mint, recipients[0].publicKey, Keypair.generate());
+            await closeAccount(provider.connection, payer, closed, recipients[0].publicKey, recipients[0]);
+            const paymentId = new anchor.BN(Date.now() + 303);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            try {
+                await program.methods
+                    .processPayment(new anchor.BN(10_000), paymentId, splitName, null, null, null, new anchor.BN(0))
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchant: splitPda,
+                        merchantStats: helper.getMerchantStatsPda(splitPda)[0],
+                        payer: payer.publicKey,
+                        merchantWallet: merchantWallet.publicKey,
+                        feeVault: helper.getFeeVaultPda()[0],
+                        feeStats: helper.getFeeStatsPda(mint)[0],
+                        paymentHistory: paymentPda,
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(mint)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
+                        systemProgram: SystemProgram.programId,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        mint: mint,
+                        payerTokenAccount: payerTokenAccount,
+                        merchantTokenAccount: null,
+                        feeTokenAccount: feeTokenAccount,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: splitConfigPda,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
+                    })
+                    .remainingAccounts(
+                        [closed, ...recipientTokenAccounts.slice(1)].map((ta) => ({ pubkey: ta, isSigner: false, isWritable: true }))
+                    )
+                    .signers([payer])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MissingAccount"));
+            }
+        });
+
+        it("should refuse to pay a split merchant without its config", async () => {
+            const paymentId = new anchor.BN(Date.now() + 302);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            try {
+                await program.methods
+                    .processPayment(new anchor.BN(10_000), paymentId, splitName, null, null, null, new anchor.BN(0))
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        merchant: splitPda,
+                        merchantStats: helper.getMerchantStatsPda(splitPda)[0],
+                        payer: payer.publicKey,
+                        merchantWallet: merchantWallet.publicKey,
+                        feeVault: helper.getFeeVaultPda()[0],
+                        feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                        paymentHistory: paymentPda,
+                        blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                        tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                        feeTiers: helper.getFeeTiersPda()[0],
+                        systemProgram: SystemProgram.programId,
+                        tokenProgram: null,
+                        mint: null,
+                        payerTokenAccount: null,
+                        merchantTokenAccount: null,
+                        feeTokenAccount: null,
+                        associatedTokenProgram: null,
+                        featureFlags: null,
+                        vault: null,
+                        escrowBalance: null,
+                        merchantBalance: null,
+                        vaultTokenAccount: null,
+                        recentPayments: null,
+                        splitConfig: null,
+                        acceptedTokens: null,
+                        memoProgram: null,
+                        referrerWallet: null,
+                        referrerTokenAccount: null,
+                        discount: null,
+                        notificationQueue: null,
+                        dailyVolume: null,
+                        rewardsHook: null,
+                        rewardsProgram: null,
+                        payerWsolAccount: null,
+                        payerThrottle: null,
+                        settlementRoute: null,
+                        paymentLink: null,
+                        ...helper.eventCpi,
+                    })
+                    .signers([payer])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MissingAccount"));
+            }
+        });
+    });
+
+    describe("Event CPI", () => {
+        before(function () {
+            if (helper.logEvents()) {
+                this.skip();
+            }
+        });
+
+        it("should carry PaymentProcessed in inner-instruction data, not logs", async () => {
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 100);
+            const paymentId = new anchor.BN(Date.now() + 7000);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+
+            const sig = await program.methods
+                .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc({ commitment: "confirmed" });
+
+            const tx = await provider.connection.getTransaction(sig, {
+                commitment: "confirmed",
+                maxSupportedTransactionVersion: 0,
+            });
+            assert.ok(!tx!.meta!.logMessages!.some((l) => l.startsWith("Program data:")));
+
+            // Self-CPI data is the 8-byte event-ix tag followed by the encoded event
+            const keys = tx!.transaction.message.getAccountKeys();
+            const events = tx!.meta!.innerInstructions!
+                .flatMap((inner) => inner.instructions)
+                .filter((ix) => keys.get(ix.programIdIndex)!.equals(program.programId))
+                .map((ix) => anchor.utils.bytes.bs58.decode(ix.data))
+                .map((data) => program.coder.events.decode(anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))));
+            const processed = events.find((e) => e?.name === "paymentProcessed");
+            assert.ok(processed, "PaymentProcessed not found in inner instructions");
+            assert.ok(processed!.data.merchant.equals(merchantPda));
+            assert.equal(processed!.data.amount.toString(), amount.toString());
+            assert.equal(processed!.data.paymentId.toString(), paymentId.toString());
+            assert.equal(processed!.data.feeBps, 100);
+            assert.deepEqual(processed!.data.feeSource, { global: {} });
+            assert.equal(processed!.data.merchantName, merchantName);
+            assert.ok(processed!.data.paymentAccount.equals(paymentPda));
+        });
+    });
+
+    describe("Accepted Tokens", () => {
+        const acceptedName = "AcceptedShop";
+        let acceptedPda: PublicKey;
+        let acceptedTokensPda: PublicKey;
+        let otherMint: PublicKey;
+        let payerOtherAccount: PublicKey;
+        let merchantOtherAccount: PublicKey;
+        let feeOtherAccount: PublicKey;
+        let nextId = Date.now() + 400;
+
+        const pay = (tokenMint: PublicKey | null, accepted: PublicKey | null) => {
+            const paymentId = new anchor.BN(nextId++);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            const spl = tokenMint !== null;
+            const other = spl && tokenMint.equals(otherMint);
+            return program.methods
+                .processPayment(new anchor.BN(spl ? 10_000 : LAMPORTS_PER_SOL / 100), paymentId, acceptedName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: acceptedPda,
+                    merchantStats: helper.getMerchantStatsPda(acceptedPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(tokenMint ?? PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(tokenMint ?? PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: spl ? TOKEN_PROGRAM_ID : null,
+                    mint: tokenMint,
+                    payerTokenAccount: spl ? (other ? payerOtherAccount : payerTokenAccount) : null,
+                    merchantTokenAccount: spl ? (other ? merchantOtherAccount : merchantTokenAccount) : null,
+                    feeTokenAccount: spl ? (other ? feeOtherAccount : feeTokenAccount) : null,
+                    associatedTokenProgram: spl ? ASSOCIATED_TOKEN_PROGRAM_ID : null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: accepted,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        const expectInvalidToken = async (payment: Promise<string>) => {
+            try {
+                await payment;
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidToken"));
+            }
+        };
+
+        const setAccepted = (method: "addAcceptedToken" | "removeAcceptedToken", tokenMint: PublicKey) => {
+            const accounts: any = {
+                merchant: acceptedPda,
+                acceptedTokens: acceptedTokensPda,
+                owner: merchantOwner.publicKey,
+            };
+            if (method === "addAcceptedToken") {
+                accounts.systemProgram = SystemProgram.programId;
+            }
+            return program.methods[method](acceptedName, tokenMint)
+                .accountsStrict(accounts)
+                .signers([merchantOwner])
+                .rpc();
+        };
+
+        before(async () => {
+            // Pinned to `mint` until an accepted-token list exists
+            [acceptedPda] = helper.getMerchantPda(merchantOwner.publicKey, acceptedName);
+            [acceptedTokensPda] = helper.getAcceptedTokensPda(acceptedPda);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, mint, acceptedName, false, null, 0, false, "", 0)
+                .accountsStrict({
+                    merchant: acceptedPda,
+                    merchantStats: helper.getMerchantStatsPda(acceptedPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, acceptedName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            otherMint = await helper.createTokenMint(payer);
+            payerOtherAccount = await helper.createTokenAccount(otherMint, payer.publicKey, payer);
+            merchantOtherAccount = await helper.createTokenAccount(otherMint, merchantWallet.publicKey, payer);
+            feeOtherAccount = await helper.getFeeVaultTokenAccount(otherMint, payer);
+            await helper.openFeeStats(admin, otherMint);
+            await helper.mintTokens(otherMint, payerOtherAccount, 1_000_000, payer);
+        });
+
+        it("should keep the single settlement token rule without a list", async () => {
+            await pay(mint, null);
+            await expectInvalidToken(pay(otherMint, null));
+            await expectInvalidToken(pay(null, null));
+        });
+
+        it("should accept exactly the listed mints and SOL once a list exists", async () => {
+            await setAccepted("addAcceptedToken", otherMint);
+            await setAccepted("addAcceptedToken", PublicKey.default);
+
+            const accepted = await program.account.acceptedTokens.fetch(acceptedTokensPda);
+            assert.deepEqual(accepted.mints.map((m) => m.toBase58()), [otherMint.toBase58(), PublicKey.default.toBase58()]);
+            const merchant = await program.account.merchant.fetch(acceptedPda);
+            assert.isTrue(merchant.acceptedTokensEnabled);
+
+            await pay(otherMint, acceptedTokensPda);
+            await pay(null, acceptedTokensPda);
+            // The old settlement token is no longer implied
+            await expectInvalidToken(pay(mint, acceptedTokensPda));
+        });
+
+        it("should require the list account while it is active", async () => {
+            try {
+                await pay(otherMint, null);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MissingAccount"));
+            }
+        });
+
+        it("should reject a duplicate mint", async () => {
+            try {
+                await setAccepted("addAcceptedToken", otherMint);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("TokenAlreadyAccepted"));
+            }
+        });
+
+        it("should fall back to the settlement token once the list is emptied", async () => {
+            await setAccepted("removeAcceptedToken", otherMint);
+            await expectInvalidToken(pay(otherMint, acceptedTokensPda));
+
+            await setAccepted("removeAcceptedToken", PublicKey.default);
+            const merchant = await program.account.merchant.fetch(acceptedPda);
+            assert.isFalse(merchant.acceptedTokensEnabled);
+
+            await pay(mint, null);
+            await expectInvalidToken(pay(null, null));
+        });
+    });
+
+    describe("Amount Limits", () => {
+        const limitsName = "LimitsShop";
+        const minAmount = 10_000;
+        const maxAmount = 50_000;
+        let limitsPda: PublicKey;
+        let nextId = Date.now() + 500;
+
+        const setLimits = (min: number | null, max: number | null) =>
+            program.methods
+                .updateMerchant(
+                    limitsName,
+                    null,
+                    null,
+                    null,
+                    null,
+                    min === null ? null : new anchor.BN(min),
+                    max === null ? null : new anchor.BN(max),
+                    null,
+                    null,
+                    null
+                )
+                .accountsStrict({
+                    merchant: limitsPda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        const pay = (amount: number) => {
+            const paymentId = new anchor.BN(nextId++);
+            return program.methods
+                .processPayment(new anchor.BN(amount), paymentId, limitsName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: limitsPda,
+                    merchantStats: helper.getMerchantStatsPda(limitsPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        before(async () => {
+            [limitsPda] = helper.getMerchantPda(merchantOwner.publicKey, limitsName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, limitsName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: limitsPda,
+                    merchantStats: helper.getMerchantStatsPda(limitsPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, limitsName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            await setLimits(minAmount, maxAmount);
+        });
+
+        it("should store the limits on the merchant", async () => {
+            const merchant = await program.account.merchant.fetch(limitsPda);
+            assert.equal(merchant.minAmount.toNumber(), minAmount);
+            assert.equal(merchant.maxAmount.toNumber(), maxAmount);
+        });
+
+        it("should accept payments of exactly the minimum and the maximum", async () => {
+            await pay(minAmount);
+            await pay(maxAmount);
+        });
+
+        it("should reject a payment one below the minimum", async () => {
+            try {
+                await pay(minAmount - 1);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("AmountTooSmall"));
+            }
+        });
+
+        it("should reject a payment one above the maximum", async () => {
+            try {
+                await pay(maxAmount + 1);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("AmountTooLarge"));
+            }
+        });
+
+        it("should treat a zero maximum as unlimited", async () => {
+            await setLimits(null, 0);
+            await pay(maxAmount + 1);
+        });
+
+        it("should reject a minimum above the maximum", async () => {
+            try {
+                await setLimits(maxAmount + 1, maxAmount);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidAmountLimits"));
+            }
+        });
+    });
+
+    describe("Payment Memo", () => {
+        const MEMO_PROGRAM_ID = new PublicKey("MemoSq4gqABAXKb96qnH8TuWKeXJ1KMyMNjXLo1Vnso");
+        let nextId = Date.now() + 9000;
+
+        const pay = (memo: string | null, memoProgram: PublicKey | null) => {
+            const paymentId = new anchor.BN(nextId++);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            const tx = program.methods
+                .processPayment(new anchor.BN(LAMPORTS_PER_SOL / 100), paymentId, merchantName, memo, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer]);
+            return { paymentPda, tx };
+        };
+
+        it("should store the memo and echo it through the SPL Memo program", async () => {
+            const memo = "ORDER-2026-000042";
+            const { paymentPda, tx } = pay(memo, MEMO_PROGRAM_ID);
+            const sig = await tx.rpc({ commitment: "confirmed" });
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.memo, memo);
+
+            const confirmed = await provider.connection.getTransaction(sig, {
+                commitment: "confirmed",
+                maxSupportedTransactionVersion: 0,
+            });
+            const logs = confirmed!.meta!.logMessages!;
+            assert.ok(logs.some((l) => l.startsWith(`Program ${MEMO_PROGRAM_ID.toBase58()} invoke`)));
+            assert.ok(logs.some((l) => l.includes(memo)));
+        });
+
+        it("should leave the memo empty when none is given", async () => {
+            const { paymentPda, tx } = pay(null, null);
+            await tx.rpc();
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.memo, "");
+        });
+
+        it("should accept a memo of exactly 64 bytes", async () => {
+            const memo = "m".repeat(64);
+            const { paymentPda, tx } = pay(memo, MEMO_PROGRAM_ID);
+            await tx.rpc();
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.memo, memo);
+        });
+
+        it("should reject a memo over 64 bytes", async () => {
+            try {
+                await pay("m".repeat(65), MEMO_PROGRAM_ID).tx.rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MemoTooLong"));
+            }
+        });
+
+        it("should require the memo program when a memo is given", async () => {
+            try {
+                await pay("ORDER-1", null).tx.rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MissingAccount"));
+            }
+        });
+    });
+
+    describe("Merchant Stats", () => {
+        const statsName = "StatsShop";
+        const amounts = [LAMPORTS_PER_SOL / 100, LAMPORTS_PER_SOL / 50, LAMPORTS_PER_SOL / 20];
+        let statsPda: PublicKey;
+        let merchantStatsPda: PublicKey;
+        let firstPaymentPda: PublicKey;
+        let nextId = Date.now() + 10_000;
+
+        const pay = async (amount: number) => {
+            const paymentId = new anchor.BN(nextId++);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(new anchor.BN(amount), paymentId, statsName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: statsPda,
+                    merchantStats: merchantStatsPda,
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+            return paymentPda;
+        };
+
+        before(async () => {
+            [statsPda] = helper.getMerchantPda(merchantOwner.publicKey, statsName);
+            [merchantStatsPda] = helper.getMerchantStatsPda(statsPda);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, statsName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: statsPda,
+                    merchantStats: merchantStatsPda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, statsName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should start every counter at zero on registration", async () => {
+            const stats = await program.account.merchantStats.fetch(merchantStatsPda);
+            assert.ok(stats.merchant.equals(statsPda));
+            assert.equal(stats.paymentCount.toNumber(), 0);
+            assert.equal(stats.totalVolume.toString(), "0");
+            assert.equal(stats.lastPaymentAt.toNumber(), 0);
+        });
+
+        it("should count volume and fees across three payments", async () => {
+            for (const amount of amounts) {
+                const paymentPda = await pay(amount);
+                firstPaymentPda = firstPaymentPda ?? paymentPda;
+            }
+
+            const stats = await program.account.merchantStats.fetch(merchantStatsPda);
+            const volume = amounts.reduce((sum, a) => sum + a, 0);
+            const fees = amounts.reduce((sum, a) => sum + Math.floor((a * 100) / 10000), 0);
+            assert.equal(stats.paymentCount.toNumber(), 3);
+            assert.equal(stats.totalVolume.toString(), volume.toString());
+            assert.equal(stats.totalFeesPaid.toString(), fees.toString());
+            assert.equal(stats.totalRefunded.toString(), "0");
+
+            const last = await program.account.payment.fetch(
+                helper.getPaymentPda(payer.publicKey, new anchor.BN(nextId - 1))[0]
+            );
+            assert.equal(stats.lastPaymentAt.toNumber(), last.timestamp.toNumber());
+        });
+
+        it("should tally refunds separately without reducing volume", async () => {
+            const before = await program.account.merchantStats.fetch(merchantStatsPda);
+            const refund = new anchor.BN(1000);
+
+            await program.methods
+                .refundPartial(statsName, refund)
+                .accountsStrict({
+                    merchant: statsPda,
+                    merchantStats: merchantStatsPda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payment: firstPaymentPda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payer: payer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    authorityTokenAccount: null,
+                    payerTokenAccount: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            const after = await program.account.merchantStats.fetch(merchantStatsPda);
+            assert.equal(after.totalRefunded.toString(), refund.toString());
+            assert.equal(after.totalVolume.toString(), before.totalVolume.toString());
+            assert.equal(after.paymentCount.toNumber(), 3);
+        });
+    });
+
+    describe("Global Stats", () => {
+        const globalName = "GlobalStatsShop";
+        const [globalStatsPda] = helper.getGlobalStatsPda();
+        let globalPda: PublicKey;
+
+        it("should count registrations and payments", async () => {
+            const before = await program.account.globalStats.fetch(globalStatsPda);
+
+            [globalPda] = helper.getMerchantPda(merchantOwner.publicKey, globalName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, globalName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: globalPda,
+                    merchantStats: helper.getMerchantStatsPda(globalPda)[0],
+                    globalStats: globalStatsPda,
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, globalName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+            const fee = amount.muln(100).divn(10000);
+            const paymentId = new anchor.BN(Date.now() + 11_000);
+            await program.methods
+                .processPayment(amount, paymentId, globalName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: globalStatsPda,
+                    merchant: globalPda,
+                    merchantStats: helper.getMerchantStatsPda(globalPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+            const after = await program.account.globalStats.fetch(globalStatsPda);
+            assert.equal(after.merchantsRegistered.toNumber(), before.merchantsRegistered.toNumber() + 1);
+            assert.equal(after.paymentsProcessed.toNumber(), before.paymentsProcessed.toNumber() + 1);
+            assert.equal(after.totalVolume.toString(), before.totalVolume.add(amount).toString());
+            assert.equal(after.totalFeeRevenue.toString(), before.totalFeeRevenue.add(fee).toString());
+        });
+
+        it("should return the stats through get_stats", async () => {
+            const stats = await program.methods
+                .getStats()
+                .accountsStrict({ globalStats: globalStatsPda })
+                .view();
+            const stored = await program.account.globalStats.fetch(globalStatsPda);
+            assert.equal(stats.merchantsRegistered.toString(), stored.merchantsRegistered.toString());
+            assert.equal(stats.paymentsProcessed.toString(), stored.paymentsProcessed.toString());
+            assert.equal(stats.totalVolume.toString(), stored.totalVolume.toString());
+            assert.equal(stats.totalFeeRevenue.toString(), stored.totalFeeRevenue.toString());
+        });
+
+        it("should only allow the stats migration once", async () => {
+            try {
+                await program.methods
+                    .initGlobalStats()
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: globalStatsPda,
+                        admin: admin.publicKey,
+                        systemProgram: SystemProgram.programId,
+                    })
+                    .signers([admin])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")));
+            }
+        });
+    });
+
+    describe("Fee Exemption", () => {
+        const exemptName = "CharityShop";
+        let exemptPda: PublicKey;
+
+        const setFeeExempt = (signer: Keypair, exempt: boolean) =>
+            program.methods
+                .setMerchantFeeExempt(exemptName, exempt)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: exemptPda,
+                    admin: signer.publicKey,
+                    adminProposal: null,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        const pay = (amount: anchor.BN, spl: boolean) => {
+            const paymentId = new anchor.BN(Date.now());
+            return program.methods
+                .processPayment(amount, paymentId, exemptName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: exemptPda,
+                    merchantStats: helper.getMerchantStatsPda(exemptPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(spl ? mint : PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(spl ? mint : PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: spl ? TOKEN_PROGRAM_ID : null,
+                    mint: spl ? mint : null,
+                    payerTokenAccount: spl ? payerTokenAccount : null,
+                    merchantTokenAccount: spl ? merchantTokenAccount : null,
+                    // Exempt merchants need no fee token account
+                    feeTokenAccount: null,
+                    associatedTokenProgram: spl ? ASSOCIATED_TOKEN_PROGRAM_ID : null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        before(async () => {
+            [exemptPda] = helper.getMerchantPda(merchantOwner.publicKey, exemptName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, exemptName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: exemptPda,
+                    merchantStats: helper.getMerchantStatsPda(exemptPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, exemptName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should reject exemption set by a non-admin", async () => {
+            try {
+                await setFeeExempt(merchantOwner, true);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should pay the full SOL amount to an exempt merchant", async () => {
+            await setFeeExempt(admin, true);
+            assert.equal((await program.account.merchant.fetch(exemptPda)).feeExempt, true);
+
+            const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+            const vaultBefore = await provider.connection.getBalance(feeVaultPda);
+            const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+
+            await pay(amount, false);
+
+            assert.equal(await provider.connection.getBalance(feeVaultPda), vaultBefore);
+            assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), walletBefore + amount.toNumber());
+        });
+
+        it("should pay the full SPL amount without a fee token account", async () => {
+            const amount = new anchor.BN(10_000);
+            const vaultBefore = (await getAccount(provider.connection, feeTokenAccount)).amount;
+            const merchantBefore = (await getAccount(provider.connection, merchantTokenAccount)).amount;
+
+            await pay(amount, true);
+
+            assert.equal((await getAccount(provider.connection, feeTokenAccount)).amount, vaultBefore);
+            assert.equal(
+                (await getAccount(provider.connection, merchantTokenAccount)).amount,
+                merchantBefore + BigInt(amount.toNumber())
+            );
+        });
+
+        it("should charge the fee again once the exemption is lifted", async () => {
+            await setFeeExempt(admin, false);
+            try {
+                await pay(new anchor.BN(10_000), true);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MissingAccount"));
+            }
+        });
+    });
+
+
+    describe("Referrer Commission", () => {
+        const referredName = "ReferredShop";
+        const referrer = Keypair.generate();
+        let referredPda: PublicKey;
+
+        const register = async (name: string, referrerKey: PublicKey | null, referrerBps: number) => {
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, name);
+            return program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, name, false, referrerKey, referrerBps, true, "", 0)
+                .accountsStrict({
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        };
+
+        const pay = (amount: anchor.BN, referrerWallet: PublicKey | null) => {
+            const paymentId = new anchor.BN(Date.now());
+            return program.methods
+                .processPayment(amount, paymentId, referredName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: referredPda,
+                    merchantStats: helper.getMerchantStatsPda(referredPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc({ commitment: "confirmed" });
+        };
+
+        before(async () => {
+            // Small shares would be lost to rent if the referrer account did not exist yet
+            await helper.airdrop(referrer.publicKey);
+            [referredPda] = helper.getMerchantPda(merchantOwner.publicKey, referredName);
+            await register(referredName, referrer.publicKey, 5000);
+        });
+
+        it("should record the referrer at registration", async () => {
+            const merchant = await program.account.merchant.fetch(referredPda);
+            assert.ok(merchant.referrer.equals(referrer.publicKey));
+            assert.equal(merchant.referrerBps, 5000);
+        });
+
+        it("should reject a registration share above the cap", async () => {
+            try {
+                await register("GreedyReferral", referrer.publicKey, 5001);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("ReferrerShareTooHigh"));
+            }
+        });
+
+        it("should split the fee, rounding the referrer's share down", async () => {
+            // 100 bps of 10_100 lamports is a 101 lamport fee
+            const amount = new anchor.BN(10_100);
+            const vaultBefore = await provider.connection.getBalance(feeVaultPda);
+            const referrerBefore = await provider.connection.getBalance(referrer.publicKey);
+            const walletBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+
+            const sig = await pay(amount, referrer.publicKey);
+
+            assert.equal(await provider.connection.getBalance(referrer.publicKey), referrerBefore + 50);
+            assert.equal(await provider.connection.getBalance(feeVaultPda), vaultBefore + 51);
+            assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), walletBefore + 9_999);
+
+            const tx = await provider.connection.getTransaction(sig, {
+                commitment: "confirmed",
+                maxSupportedTransactionVersion: 0,
+            });
+            const keys = tx!.transaction.message.getAccountKeys();
+            const events = tx!.meta!.innerInstructions!
+                .flatMap((inner) => inner.instructions)
+                .filter((ix) => keys.get(ix.programIdIndex)!.equals(program.programId))
+                .map((ix) => anchor.utils.bytes.bs58.decode(ix.data))
+                .map((data) => program.coder.events.decode(anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))));
+            const processed = events.find((e) => e?.name === "paymentProcessed");
+            assert.ok(processed, "PaymentProcessed not found in inner instructions");
+            assert.equal(processed!.data.fee.toString(), "101");
+            assert.equal(processed!.data.referrerAmount.toString(), "50");
+        });
+
+        it("should reject a referrer wallet that is not the merchant's", async () => {
+            const impostor = Keypair.generate();
+            await helper.airdrop(impostor.publicKey);
+            try {
+                await pay(new anchor.BN(10_100), impostor.publicKey);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidReferrer"));
+            }
+        });
+
+        it("should require the referrer wallet when a share is owed", async () => {
+            try {
+                await pay(new anchor.BN(10_100), null);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MissingAccount"));
+            }
+        });
+
+        it("should send the whole fee to the vault once the admin clears the referrer", async () => {
+            await program.methods
+                .setMerchantReferrer(referredName, null, 0)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: referredPda,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            const vaultBefore = await provider.connection.getBalance(feeVaultPda);
+            await pay(new anchor.BN(10_100), null);
+            assert.equal(await provider.connection.getBalance(feeVaultPda), vaultBefore + 101);
+        });
+    });
+
+
+    describe("Token Rule", () => {
+        let nextId = Date.now() + 900;
+
+        const register = async (name: string, token: PublicKey, acceptsAnyToken: boolean) => {
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, name);
+            return program.methods
+                .registerMerchant(merchantWallet.publicKey, token, name, false, null, 0, acceptsAnyToken, "", 0)
+                .accountsStrict({
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        };
+
+        const pay = (name: string, spl: boolean) => {
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, name);
+            const paymentId = new anchor.BN(nextId++);
+            return program.methods
+                .processPayment(new anchor.BN(spl ? 10_000 : LAMPORTS_PER_SOL / 100), paymentId, name, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: pda,
+                    merchantStats: helper.getMerchantStatsPda(pda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(spl ? mint : PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(spl ? mint : PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: spl ? TOKEN_PROGRAM_ID : null,
+                    mint: spl ? mint : null,
+                    payerTokenAccount: spl ? payerTokenAccount : null,
+                    merchantTokenAccount: spl ? merchantTokenAccount : null,
+                    feeTokenAccount: spl ? feeTokenAccount : null,
+                    associatedTokenProgram: spl ? ASSOCIATED_TOKEN_PROGRAM_ID : null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+        };
+
+        const expectInvalidToken = async (name: string, spl: boolean) => {
+            try {
+                await pay(name, spl);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidToken"));
+            }
+        };
+
+        before(async () => {
+            await register("StrictSplShop", mint, false);
+            await register("StrictSolShop", PublicKey.default, false);
+            await register("AnySplShop", mint, true);
+            await register("AnySolShop", PublicKey.default, true);
+            await register("SwapSplShop", mint, false);
+        });
+
+        it("should hold a strict merchant to its settlement token", async () => {
+            await pay("StrictSplShop", true);
+            await expectInvalidToken("StrictSplShop", false);
+        });
+
+        it("should treat a strict default settlement token as SOL only", async () => {
+            await pay("StrictSolShop", false);
+            await expectInvalidToken("StrictSolShop", true);
+        });
+
+        it("should let an any-token merchant take SOL despite a settlement token", async () => {
+            await pay("AnySplShop", false);
+            await pay("AnySplShop", true);
+        });
+
+        it("should let an any-token merchant take SPL tokens", async () => {
+            await pay("AnySolShop", true);
+            await pay("AnySolShop", false);
+        });
+
+        it("should not let swap_enabled open a strict merchant to SOL", async () => {
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, "SwapSplShop");
+            await program.methods
+                .updateMerchant("SwapSplShop", null, null, true, null, null, null, null, null, null)
+                .accountsStrict({
+                    merchant: pda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+            assert.equal((await program.account.merchant.fetch(pda)).swapEnabled, true);
+
+            try {
+                await pay("SwapSplShop", false);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("SwapNotAvailable"));
+            }
+            await pay("SwapSplShop", true);
+        });
+
+        it("should switch the rule through update_merchant", async () => {
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, "StrictSolShop");
+            await program.methods
+                .updateMerchant("StrictSolShop", null, null, null, null, null, null, true, null, null)
+                .accountsStrict({
+                    merchant: pda,
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    payoutBook: null,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+            assert.equal((await program.account.merchant.fetch(pda)).acceptsAnyToken, true);
+            await pay("StrictSolShop", true);
+        });
+
+        it("should not migrate a merchant registered at the current version", async () => {
+            const [pda] = helper.getMerchantPda(merchantOwner.publicKey, "StrictSplShop");
+            const merchant = await program.account.merchant.fetch(pda);
+            assert.equal(merchant.tokenRuleSet, true);
+            assert.equal(merchant.version, 2);
+            try {
+                await program.methods
+                    .migrateMerchant("StrictSplShop")
+                    .accountsStrict({
+                        merchant: pda,
+                        owner: merchantOwner.publicKey,
+                        systemProgram: SystemProgram.programId,
+                        ...helper.eventCpi,
+                    })
+                    .signers([merchantOwner])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("MerchantAlreadyMigrated"));
+            }
+        });
+    });
+
+
+    describe("Payment Quotes", () => {
+        const quoteName = "QuoteShop";
+        let quotePda: PublicKey;
+
+        const quote = (amount: number) =>
+            program.methods
+                .quotePayment(new anchor.BN(amount), quoteName, merchantOwner.publicKey)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: quotePda,
+                    tokenFee: null,
+                    merchantStats: helper.getMerchantStatsPda(quotePda)[0],
+                    feeTiers: null,
+                });
+
+        before(async () => {
+            [quotePda] = helper.getMerchantPda(merchantOwner.publicKey, quoteName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, quoteName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: quotePda,
+                    merchantStats: helper.getMerchantStatsPda(quotePda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, quoteName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        it("should quote the fee rounded down like process_payment", async () => {
+            // 100 bps of 10_099 is 100.99, charged as 100
+            const result = await quote(10_099).view();
+            assert.equal(result.fee.toNumber(), 100);
+            assert.equal(result.merchantAmount.toNumber(), 9_999);
+            assert.equal(result.feeBpsApplied, 100);
+            assert.deepEqual(result.feeSource, { global: {} });
+        });
+
+        it("should return the quote as Borsh return data in a simulation", async () => {
+            const sim = await quote(1_000_000).simulate();
+            const prefix = `Program return: ${program.programId.toBase58()} `;
+            const line = sim.raw.find((l) => l.startsWith(prefix));
+            assert.ok(line, "no return data in the simulation logs");
+
+            const data = Buffer.from(line!.slice(prefix.length), "base64");
+            const decoded = program.coder.types.decode("PaymentQuote", data);
+            assert.equal(decoded.fee.toNumber(), 10_000);
+            assert.equal(decoded.merchantAmount.toNumber(), 990_000);
+        });
+
+        it("should quote no fee for an exempt merchant", async () => {
+            await program.methods
+                .setMerchantFeeExempt(quoteName, true)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: quotePda,
+                    admin: admin.publicKey,
+                    adminProposal: null,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+            const result = await quote(10_099).view();
+            assert.equal(result.fee.toNumber(), 0);
+            assert.equal(result.merchantAmount.toNumber(), 10_099);
+            assert.equal(result.feeBpsApplied, 0);
+        });
+
+        it("should reject a zero amount", async () => {
+            try {
+                await quote(0).view();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidAmount") || JSON.stringify(e).includes("InvalidAmount"));
+            }
+        });
+    });
+
+    describe("Tips", () => {
+        const feeOn = (amount: anchor.BN) => amount.mul(new anchor.BN(100)).div(new anchor.BN(10000));
+
+        const pay = (
+            signer: Keypair,
+            amount: anchor.BN,
+            tip: anchor.BN | null,
+            paymentId: anchor.BN,
+            spl: { payerTokenAccount: PublicKey } | null,
+        ) =>
+            program.methods
+                .processPayment(amount, paymentId, merchantName, null, tip, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: signer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(spl ? mint : PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(signer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(signer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(spl ? mint : PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: spl ? TOKEN_PROGRAM_ID : null,
+                    mint: spl ? mint : null,
+                    payerTokenAccount: spl ? spl.payerTokenAccount : null,
+                    merchantTokenAccount: spl ? merchantTokenAccount : null,
+                    feeTokenAccount: spl ? feeTokenAccount : null,
+                    associatedTokenProgram: spl ? ASSOCIATED_TOKEN_PROGRAM_ID : null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        it("should pass a SOL tip to the merchant without a fee", async () => {
+            const amount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+            const tip = new anchor.BN(0.02 * LAMPORTS_PER_SOL);
+            const paymentId = new anchor.BN(Date.now() + 430);
+            const fee = feeOn(amount);
+
+            const merchantBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+            const vaultBefore = await provider.connection.getBalance(feeVaultPda);
+
+            await pay(payer, amount, tip, paymentId, null);
+
+            assert.equal(
+                await provider.connection.getBalance(merchantWallet.publicKey),
+                merchantBefore + amount.add(tip).sub(fee).toNumber()
+            );
+            assert.equal(await provider.connection.getBalance(feeVaultPda), vaultBefore + fee.toNumber());
+
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(payer.publicKey, paymentId)[0]);
+            assert.equal(payment.amount.toString(), amount.toString());
+            assert.equal(payment.fee.toString(), fee.toString());
+            assert.equal(payment.tip.toString(), tip.toString());
+        });
+
+        it("should pass an SPL tip to the merchant without a fee", async () => {
+            const amount = new anchor.BN(50_000);
+            const tip = new anchor.BN(7_500);
+            const paymentId = new anchor.BN(Date.now() + 431);
+            const fee = feeOn(amount);
+
+            const merchantBefore = (await getAccount(provider.connection, merchantTokenAccount)).amount;
+            const feeBefore = (await getAccount(provider.connection, feeTokenAccount)).amount;
+            const payerBefore = (await getAccount(provider.connection, payerTokenAccount)).amount;
+
+            await pay(payer, amount, tip, paymentId, { payerTokenAccount });
+
+            const merchantAfter = (await getAccount(provider.connection, merchantTokenAccount)).amount;
+            const feeAfter = (await getAccount(provider.connection, feeTokenAccount)).amount;
+            const payerAfter = (await getAccount(provider.connection, payerTokenAccount)).amount;
+            assert.equal((merchantAfter - merchantBefore).toString(), amount.add(tip).sub(fee).toString());
+            assert.equal((feeAfter - feeBefore).toString(), fee.toString());
+            assert.equal((payerBefore - payerAfter).toString(), amount.add(tip).toString());
+
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(payer.publicKey, paymentId)[0]);
+            assert.equal(payment.tip.toString(), tip.toString());
+        });
+
+        it("should record a null tip as zero", async () => {
+            const amount = new anchor.BN(20_000);
+            const paymentId = new anchor.BN(Date.now() + 432);
+
+            await pay(payer, amount, null, paymentId, { payerTokenAccount });
+
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(payer.publicKey, paymentId)[0]);
+            assert.equal(payment.tip.toString(), "0");
+            assert.equal(payment.fee.toString(), feeOn(amount).toString());
+        });
+
+        it("should count the tip in the payer's balance check", async () => {
+            // Holds exactly the amount, so only the tip pushes it over
+            const tipper = Keypair.generate();
+            await helper.airdrop(tipper.publicKey);
+            const tipperTokenAccount = await helper.createTokenAccount(mint, tipper.publicKey, tipper);
+            await helper.mintTokens(mint, tipperTokenAccount, 10_000, payer);
+
+            try {
+                await pay(tipper, new anchor.BN(10_000), new anchor.BN(1), new anchor.BN(Date.now() + 433), {
+                    payerTokenAccount: tipperTokenAccount,
+                });
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InsufficientBalance"));
+            }
+
+            await pay(tipper, new anchor.BN(10_000), new anchor.BN(0), new anchor.BN(Date.now() + 434), {
+                payerTokenAccount: tipperTokenAccount,
+            });
+            assert.equal((await getAccount(provider.connection, tipperTokenAccount)).amount.toString(), "0");
+        });
+    });
+
+    describe("Discount Codes", () => {
+        // The merchant is new each run, so fixed codes don't collide
+        const code = "SPRING20";
+        const secondPayer = Keypair.generate();
+
+        const createDiscount = (promo: string, bps: number, maxRedemptions: number, expiresAt: number) =>
+            program.methods
+                .createDiscount(
+                    merchantName,
+                    Array.from(TestHelper.hashDiscountCode(promo)),
+                    bps,
+                    new anchor.BN(maxRedemptions),
+                    new anchor.BN(expiresAt)
+                )
+                .accountsStrict({
+                    merchant: merchantPda,
+                    discount: helper.getDiscountPda(merchantPda, promo)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([merchantOwner])
+                .rpc();
+
+        const payWithCode = (signer: Keypair, amount: anchor.BN, paymentId: anchor.BN, promo: string | null, discount: PublicKey | null) =>
+            program.methods
+                .processPayment(amount, paymentId, merchantName, null, null, promo, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: signer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(signer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(signer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        before(async () => {
+            await helper.airdrop(secondPayer.publicKey, 2 * LAMPORTS_PER_SOL);
+            // 20% off, twice in total
+            await createDiscount(code, 2000, 2, 0);
+        });
+
+        it("should reject a discount of 0 or 10000 bps", async () => {
+            for (const bps of [0, 10000]) {
+                try {
+                    await createDiscount(`BAD${bps}`, bps, 0, 0);
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.ok(e.message.includes("InvalidDiscount"));
+                }
+            }
+        });
+
+        it("should charge the discounted amount and record the listed one", async () => {
+            const listed = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+            const charged = listed.muln(8000).divn(10000);
+            const fee = charged.muln(100).divn(10000);
+            const paymentId = new anchor.BN(Date.now() + 440);
+            const [discount] = helper.getDiscountPda(merchantPda, code);
+
+            const merchantBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+            await payWithCode(payer, listed, paymentId, code, discount);
+
+            assert.equal(
+                await provider.connection.getBalance(merchantWallet.publicKey),
+                merchantBefore + charged.sub(fee).toNumber()
+            );
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(payer.publicKey, paymentId)[0]);
+            assert.equal(payment.amount.toString(), charged.toString());
+            assert.equal(payment.fee.toString(), fee.toString());
+            assert.equal(payment.preDiscountAmount.toString(), listed.toString());
+            assert.equal(payment.discountBps, 2000);
+            assert.equal((await program.account.discount.fetch(discount)).redemptions.toNumber(), 1);
+        });
+
+        it("should reject a code that does not match the discount account", async () => {
+            const [discount] = helper.getDiscountPda(merchantPda, code);
+            try {
+                await payWithCode(payer, new anchor.BN(1_000_000), new anchor.BN(Date.now() + 441), "WRONG", discount);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("DiscountCodeMismatch"));
+            }
+        });
+
+        it("should stop redemptions at max_redemptions across payers", async () => {
+            const [discount] = helper.getDiscountPda(merchantPda, code);
+
+            // Second and last redemption, by another payer
+            await payWithCode(secondPayer, new anchor.BN(1_000_000), new anchor.BN(Date.now() + 442), code, discount);
+            assert.equal((await program.account.discount.fetch(discount)).redemptions.toNumber(), 2);
+
+            for (const signer of [payer, secondPayer]) {
+                try {
+                    await payWithCode(signer, new anchor.BN(1_000_000), new anchor.BN(Date.now() + 443), code, discount);
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.ok(e.message.includes("DiscountExhausted"));
+                }
+            }
+
+            // Paying without the code still works, at the listed price
+            const paymentId = new anchor.BN(Date.now() + 444);
+            await payWithCode(secondPayer, new anchor.BN(1_000_000), paymentId, null, null);
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(secondPayer.publicKey, paymentId)[0]);
+            assert.equal(payment.amount.toString(), "1000000");
+            assert.equal(payment.discountBps, 0);
+            assert.equal(payment.preDiscountAmount.toString(), "0");
+        });
+
+        it("should reject an expired code", async () => {
+            const promo = "EXPIRED10";
+            const soon = Math.floor(Date.now() / 1000) + 2;
+            await createDiscount(promo, 1000, 0, soon);
+            await new Promise((resolve) => setTimeout(resolve, 4000));
+
+            try {
+                await payWithCode(payer, new anchor.BN(1_000_000), new anchor.BN(Date.now() + 445), promo, helper.getDiscountPda(merchantPda, promo)[0]);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("DiscountExpired"));
+            }
+        });
+    });
+
+    describe("Payer Blocklist", () => {
+        const flagged = Keypair.generate();
+        const [blockedPda] = helper.getBlockedPda(flagged.publicKey);
+
+        const block = (signer: Keypair) =>
+            program.methods
+                .blockAddress(flagged.publicKey)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    blocked: blockedPda,
+                    admin: signer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        const paySol = (paymentId: anchor.BN, blocklistEntry: PublicKey = blockedPda) =>
+            program.methods
+                .processPayment(new anchor.BN(1_000_000), paymentId, merchantName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: flagged.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(flagged.publicKey, paymentId)[0],
+                    blocklistEntry,
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([flagged])
+                .rpc();
+
+        before(async () => {
+            await helper.airdrop(flagged.publicKey);
+        });
+
+        it("should only let the admin block an address", async () => {
+            try {
+                await block(payer);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should reject payments from a blocked payer", async () => {
+            await block(admin);
+            const entry = await program.account.blocked.fetch(blockedPda);
+            assert.ok(entry.address.equals(flagged.publicKey));
+
+            try {
+                await paySol(new anchor.BN(Date.now() + 450));
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("AddressBlocked"));
+            }
+        });
+
+        it("should not accept another address in place of the payer's entry", async () => {
+            // An empty PDA for someone else would otherwise let the payer skip the check
+            try {
+                await paySol(new anchor.BN(Date.now() + 451), helper.getBlockedPda(payer.publicKey)[0]);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("ConstraintSeeds"));
+            }
+        });
+
+        it("should accept the payer again once unblocked", async () => {
+            await program.methods
+                .unblockAddress()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    blocked: blockedPda,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+            assert.isNull(await provider.connection.getAccountInfo(blockedPda));
+
+            const paymentId = new anchor.BN(Date.now() + 452);
+            await paySol(paymentId);
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(flagged.publicKey, paymentId)[0]);
+            assert.ok(payment.payer.equals(flagged.publicKey));
+        });
+    });
+
+    describe("Duplicate Payments", () => {
+        const pay = (amount: anchor.BN, paymentId: anchor.BN) =>
+            program.methods
+                .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+        it("should reject a replayed payment id with DuplicatePayment", async () => {
+            const amount = new anchor.BN(0.01 * LAMPORTS_PER_SOL);
+            const paymentId = new anchor.BN(Date.now() + 470);
+            const paymentPda = helper.getPaymentPda(payer.publicKey, paymentId)[0];
+
+            await pay(amount, paymentId);
+            const merchantBefore = await provider.connection.getBalance(merchantWallet.publicKey);
+
+            for (const replayAmount of [amount, amount.muln(2)]) {
+                try {
+                    await pay(replayAmount, paymentId);
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.instanceOf(e, anchor.AnchorError);
+                    assert.equal(e.error.errorCode.code, "DuplicatePayment");
+                    assert.notOk(e.logs?.some((l: string) => l.includes("already in use")));
+                }
+            }
+
+            const payment = await program.account.payment.fetch(paymentPda);
+            assert.equal(payment.amount.toString(), amount.toString());
+            assert.equal(await provider.connection.getBalance(merchantWallet.publicKey), merchantBefore);
+        });
+    });
+
+    describe("Token Fees", () => {
+        const tokenFeeName = "TokenFeeShop";
+        const [solFeePda] = helper.getTokenFeePda(PublicKey.default);
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 100);
+        let tokenFeeShopPda: PublicKey;
+        let nextId = Date.now() + 480;
+
+        const setTokenFee = (bps: number, signer: Keypair = admin) =>
+            program.methods
+                .setTokenFee(PublicKey.default, bps)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    tokenFee: solFeePda,
+                    admin: signer.publicKey,
+                    adminProposal: null,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+
+        // The timelock is 0 on the shared validator, so a queued rate applies at once
+        const applyTokenFee = () =>
+            program.methods
+                .applyTokenFee()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    tokenFee: solFeePda,
+                    caller: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        const clearTokenFee = () =>
+            program.methods
+                .clearTokenFee()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    tokenFee: solFeePda,
+                    admin: admin.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        const setMerchantFee = (bps: number | null) =>
+            program.methods
+                .setMerchantFee(tokenFeeName, bps)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: tokenFeeShopPda,
+                    admin: admin.publicKey,
+                    adminProposal: null,
+                    ...helper.eventCpi,
+                })
+                .signers([admin])
+                .rpc();
+
+        // Pays SOL and returns the fee recorded on the Payment
+        const payFee = async () => {
+            const paymentId = new anchor.BN(nextId++);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(amount, paymentId, tokenFeeName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: tokenFeeShopPda,
+                    merchantStats: helper.getMerchantStatsPda(tokenFeeShopPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: solFeePda,
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+            return (await program.account.payment.fetch(paymentPda)).fee.toNumber();
+        };
+
+        const quote = async () => {
+            const configured = (await provider.connection.getAccountInfo(solFeePda)) !== null;
+            return program.methods
+                .quotePayment(amount, tokenFeeName, merchantOwner.publicKey)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: tokenFeeShopPda,
+                    tokenFee: configured ? solFeePda : null,
+                    merchantStats: helper.getMerchantStatsPda(tokenFeeShopPda)[0],
+                    feeTiers: null,
+                })
+                .view();
+        };
+
+        const bpsOf = (bps: number) => amount.toNumber() * bps / 10000;
+
+        before(async () => {
+            [tokenFeeShopPda] = helper.getMerchantPda(merchantOwner.publicKey, tokenFeeName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, tokenFeeName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: tokenFeeShopPda,
+                    merchantStats: helper.getMerchantStatsPda(tokenFeeShopPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, tokenFeeName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        // The SOL config would otherwise apply to every later SOL payment
+        after(async () => {
+            if (await provider.connection.getAccountInfo(solFeePda)) {
+                await clearTokenFee();
+            }
+        });
+
+        it("should charge the global fee while the token has no config", async () => {
+            assert.equal(await payFee(), bpsOf(100));
+            const result = await quote();
+            assert.equal(result.feeBpsApplied, 100);
+            assert.deepEqual(result.feeSource, { global: {} });
+        });
+
+        it("should only let the admin set a valid token fee", async () => {
+            try {
+                await setTokenFee(250, payer);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+            try {
+                await setTokenFee(10001);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("FeeCapExceeded"));
+            }
+        });
+
+        it("should charge the token's fee over the global one", async () => {
+            await setTokenFee(250);
+            let config = await program.account.tokenFeeConfig.fetch(solFeePda);
+            assert.ok(config.mint.equals(PublicKey.default));
+            assert.ok(config.inactive);
+            assert.equal(config.pendingFeeBps, 250);
+            assert.equal(await payFee(), bpsOf(100));
+
+            await applyTokenFee();
+            config = await program.account.tokenFeeConfig.fetch(solFeePda);
+            assert.notOk(config.inactive);
+            assert.equal(config.feeBps, 250);
+
+            assert.equal(await payFee(), bpsOf(250));
+            const result = await quote();
+            assert.equal(result.feeBpsApplied, 250);
+            assert.deepEqual(result.feeSource, { token: {} });
+        });
+
+        it("should charge the merchant's override over the token fee", async () => {
+            await setMerchantFee(50);
+            assert.equal(await payFee(), bpsOf(50));
+            const result = await quote();
+            assert.equal(result.feeBpsApplied, 50);
+            assert.deepEqual(result.feeSource, { merchant: {} });
+            await setMerchantFee(null);
+        });
+
+        it("should return to the global fee once the config is cleared", async () => {
+            await clearTokenFee();
+            assert.isNull(await provider.connection.getAccountInfo(solFeePda));
+            assert.equal(await payFee(), bpsOf(100));
+        });
+    });
+
+    describe("Volume Fee Tiers", () => {
+        const tierName = "TierShop";
+        const [feeTiersPda] = helper.getFeeTiersPda();
+        const [proposalPda] = helper.getFeeTiersProposalPda();
+        const amount = new anchor.BN(0.03 * LAMPORTS_PER_SOL);
+        let tierShopPda: PublicKey;
+        let nextId = Date.now() + 490;
+
+        const tier = (volume: number, feeBps: number) => ({ volumeThreshold: new anchor.BN(volume), feeBps });
+
+        // Proposes the tiers, then applies them; the timelock is 0 on the shared validator
+        const setFeeTiers = async (tiers: { volumeThreshold: anchor.BN; feeBps: number }[], signer: Keypair = admin) => {
+            await program.methods
+                .setFeeTiers(tiers)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    feeTiers: feeTiersPda,
+                    proposal: proposalPda,
+                    admin: signer.publicKey,
+                    adminProposal: null,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+            await program.methods
+                .applyFeeTiers()
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    feeTiers: feeTiersPda,
+                    proposal: proposalPda,
+                    proposer: signer.publicKey,
+                    caller: signer.publicKey,
+                    ...helper.eventCpi,
+                })
+                .signers([signer])
+                .rpc();
+        };
+
+        // Pays SOL and returns the fee recorded on the Payment
+        const payFee = async () => {
+            const paymentId = new anchor.BN(nextId++);
+            const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+            await program.methods
+                .processPayment(amount, paymentId, tierName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: tierShopPda,
+                    merchantStats: helper.getMerchantStatsPda(tierShopPda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: feeVaultPda,
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: paymentPda,
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: feeTiersPda,
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+            return (await program.account.payment.fetch(paymentPda)).fee.toNumber();
+        };
+
+        const quote = () =>
+            program.methods
+                .quotePayment(amount, tierName, merchantOwner.publicKey)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: tierShopPda,
+                    tokenFee: null,
+                    merchantStats: helper.getMerchantStatsPda(tierShopPda)[0],
+                    feeTiers: feeTiersPda,
+                })
+                .view();
+
+        const bpsOf = (bps: number) => amount.toNumber() * bps / 10000;
+
+        before(async () => {
+            [tierShopPda] = helper.getMerchantPda(merchantOwner.publicKey, tierName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, tierName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: tierShopPda,
+                    merchantStats: helper.getMerchantStatsPda(tierShopPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, tierName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        // Tiers apply to every merchant, so leave them off for later suites
+        after(async () => {
+            await setFeeTiers([]);
+        });
+
+        it("should reject unsorted, oversized or out-of-range tiers", async () => {
+            const cases: [{ volumeThreshold: anchor.BN; feeBps: number }[], string][] = [
+                [[tier(200, 50), tier(100, 25)], "InvalidFeeTiers"],
+                [[tier(100, 50), tier(100, 25)], "InvalidFeeTiers"],
+                [[1, 2, 3, 4, 5, 6].map((i) => tier(i * 100, 50)), "InvalidFeeTiers"],
+                [[tier(100, 10001)], "FeeCapExceeded"],
+            ];
+            for (const [tiers, error] of cases) {
+                try {
+                    await setFeeTiers(tiers);
+                    assert.fail("Should have failed");
+                } catch (e: any) {
+                    assert.ok(e.message.includes(error), `${error}: ${e.message}`);
+                }
+            }
+
+            try {
+                await setFeeTiers([tier(100, 50)], payer);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("Unauthorized"));
+            }
+        });
+
+        it("should lower the fee once the merchant's volume crosses a tier", async () => {
+            // First tier sits between the second and third payment's prior volume
+            await setFeeTiers([tier(0.05 * LAMPORTS_PER_SOL, 50), tier(10 * LAMPORTS_PER_SOL, 25)]);
+            const stored = await program.account.feeTiers.fetch(feeTiersPda);
+            assert.equal(stored.tiers.length, 2);
+            assert.isNull(await provider.connection.getAccountInfo(proposalPda));
+
+            assert.equal(await payFee(), bpsOf(100));
+            assert.deepEqual((await quote()).feeSource, { global: {} });
+            assert.equal(await payFee(), bpsOf(100));
+
+            const crossed = await quote();
+            assert.equal(crossed.feeBpsApplied, 50);
+            assert.deepEqual(crossed.feeSource, { tier: {} });
+            assert.equal(await payFee(), bpsOf(50));
+        });
+
+        it("should return to the global fee when tiers are turned off", async () => {
+            await setFeeTiers([]);
+            assert.equal(await payFee(), bpsOf(100));
+        });
+    });
+
+
+    describe("Payment Account Upgrade", () => {
+        // Loaded from tests/fixtures/legacy_payment.json at the size payments
+        // had before receipt_mint, with a memo too long to decode at that size
+        const legacyPda = new PublicKey("5humw2LyWhxYYqdsGZtJksBEqqUBGNkfyZqaxmhyToGc");
+
+        const upgrade = () =>
+            program.methods
+                .upgradePaymentAccount()
+                .accountsStrict({
+                    payment: legacyPda,
+                    payer: payer.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+
+        it("should not decode a long-memo payment before the upgrade", async () => {
+            try {
+                await program.account.payment.fetch(legacyPda);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.notEqual(e.message, "Should have failed");
+            }
+        });
+
+        it("should let any signer grow a pre-upgrade payment", async () => {
+            const before = await provider.connection.getAccountInfo(legacyPda);
+            assert.equal(before.data.length, 234);
+
+            await upgrade();
+
+            const after = await provider.connection.getAccountInfo(legacyPda);
+            assert.equal(after.data.length, 266);
+            assert.equal(
+                after.lamports,
+                await provider.connection.getMinimumBalanceForRentExemption(266)
+            );
+            assert.deepEqual(after.data.subarray(0, 234), before.data);
+
+            const payment = await program.account.payment.fetch(legacyPda);
+            assert.ok(payment.payer.equals(admin.publicKey));
+            assert.equal(payment.paymentId.toNumber(), 424242);
+            assert.equal(payment.memo, "m".repeat(64));
+            assert.ok(payment.receiptMint.equals(PublicKey.default));
+        });
+
+        it("should leave an upgraded payment unchanged", async () => {
+            await upgrade();
+            const info = await provider.connection.getAccountInfo(legacyPda);
+            assert.equal(info.data.length, 266);
+        });
+
+        it("should reject accounts that are not payments", async () => {
+            try {
+                await program.methods
+                    .upgradePaymentAccount()
+                    .accountsStrict({
+                        payment: merchantPda,
+                        payer: payer.publicKey,
+                        systemProgram: SystemProgram.programId,
+                        ...helper.eventCpi,
+                    })
+                    .signers([payer])
+                    .rpc();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvalidAccountData"));
+            }
+        });
     });
 });
\ No newline at end of file
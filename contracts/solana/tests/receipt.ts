@@ -0,0 +1,276 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+import {
+    TOKEN_2022_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    getAccount,
+    getAssociatedTokenAddressSync,
+    getMint,
+    getNonTransferable,
+    getTokenMetadata,
+} from "@solana/spl-token";
+
+// Needs `yarn test:receipts`, which builds with the receipts feature
+describe("Orki Gateway - Payment Receipts", () => {
+    const testId = TestHelper.generateTestId("receipts");
+    console.log(`Running receipt tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+    const provider = anchor.getProvider();
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const payer = Keypair.generate();
+    const other = Keypair.generate();
+
+    const merchantName = "ReceiptShop";
+    const metadataUri = "https://example.com/receipt-shop.json";
+    let merchantPda: PublicKey;
+
+    const hasReceipts = () => program.idl.instructions.some((ix) => ix.name === "mintReceipt");
+
+    const setFinality = (slots: number) =>
+        program.methods
+            .setFinalitySlots(new anchor.BN(slots))
+            .accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi })
+            .signers([admin])
+            .rpc();
+
+    // Receipts are only minted for finalized payments
+    const finalize = async (paymentId: anchor.BN, paymentPda: PublicKey) => {
+        const { slot } = await program.account.payment.fetch(paymentPda);
+        while ((await provider.connection.getSlot()) <= slot.toNumber()) {
+            await new Promise((resolve) => setTimeout(resolve, 400));
+        }
+        await program.methods
+            .finalizePayment(paymentId)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                payer: payer.publicKey,
+                payment: paymentPda,
+                escrowBalance: null,
+                ...helper.eventCpi,
+            })
+            .rpc();
+    };
+
+    const pay = async (amount: anchor.BN, final = true) => {
+        const paymentId = new anchor.BN(Date.now());
+        const [paymentPda] = helper.getPaymentPda(payer.publicKey, paymentId);
+        await program.methods
+            .processPayment(amount, paymentId, merchantName, null, null, null, new anchor.BN(0))
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                paymentHistory: paymentPda,
+                blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                systemProgram: SystemProgram.programId,
+                tokenProgram: null,
+                mint: null,
+                payerTokenAccount: null,
+                merchantTokenAccount: null,
+                feeTokenAccount: null,
+                associatedTokenProgram: null,
+                featureFlags: null,
+                vault: null,
+                escrowBalance: null,
+                merchantBalance: null,
+                vaultTokenAccount: null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+        if (final) {
+            await finalize(paymentId, paymentPda);
+        }
+        return { paymentId, paymentPda };
+    };
+
+    const mintReceipt = (paymentPda: PublicKey, signer: Keypair) => {
+        const [receiptMint] = helper.getReceiptMintPda(paymentPda);
+        return (program.methods as any)
+            .mintReceipt()
+            .accountsStrict({
+                payment: paymentPda,
+                merchant: merchantPda,
+                payer: signer.publicKey,
+                receiptAuthority: helper.getReceiptAuthorityPda()[0],
+                receiptMint,
+                payerReceiptAccount: getAssociatedTokenAddressSync(
+                    receiptMint,
+                    signer.publicKey,
+                    false,
+                    TOKEN_2022_PROGRAM_ID
+                ),
+                tokenProgram: TOKEN_2022_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([signer])
+            .rpc();
+    };
+
+    before(async function () {
+        if (!hasReceipts()) {
+            this.skip();
+        }
+
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(payer.publicKey, 5 * LAMPORTS_PER_SOL);
+        await helper.airdrop(other.publicKey);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                await helper.setFee(admin, 100);
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+        await helper.openFeeStats(admin, PublicKey.default);
+        await setFinality(1);
+
+        [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, metadataUri, 3)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+    });
+
+    after(async function () {
+        if (hasReceipts()) {
+            await setFinality(32);
+        }
+    });
+
+    it("should mint a non-transferable receipt with the payment's metadata", async () => {
+        const amount = new anchor.BN(LAMPORTS_PER_SOL / 10);
+        const { paymentId, paymentPda } = await pay(amount);
+        const [receiptMint] = helper.getReceiptMintPda(paymentPda);
+
+        await mintReceipt(paymentPda, payer);
+
+        const payment = await program.account.payment.fetch(paymentPda);
+        assert.ok(payment.receiptMint.equals(receiptMint));
+
+        const mint = await getMint(provider.connection, receiptMint, "confirmed", TOKEN_2022_PROGRAM_ID);
+        assert.equal(mint.decimals, 0);
+        assert.equal(mint.supply.toString(), "1");
+        assert.isNull(mint.mintAuthority);
+        assert.isNotNull(getNonTransferable(mint));
+
+        const holder = await getAccount(
+            provider.connection,
+            getAssociatedTokenAddressSync(receiptMint, payer.publicKey, false, TOKEN_2022_PROGRAM_ID),
+            "confirmed",
+            TOKEN_2022_PROGRAM_ID
+        );
+        assert.equal(holder.amount.toString(), "1");
+
+        const metadata = await getTokenMetadata(provider.connection, receiptMint, "confirmed", TOKEN_2022_PROGRAM_ID);
+        assert.equal(metadata.name, `${merchantName} receipt`);
+        assert.equal(metadata.symbol, "ORKI");
+        assert.equal(metadata.uri, metadataUri);
+        assert.notOk(metadata.updateAuthority);
+        const fields = Object.fromEntries(metadata.additionalMetadata);
+        assert.equal(fields.merchant, merchantPda.toBase58());
+        assert.equal(fields.amount, amount.toString());
+        assert.equal(fields.mint, PublicKey.default.toBase58());
+        assert.equal(fields.payment_id, paymentId.toString());
+        assert.equal(fields.timestamp, payment.timestamp.toString());
+    });
+
+    it("should mint only one receipt per payment", async () => {
+        const { paymentPda } = await pay(new anchor.BN(LAMPORTS_PER_SOL / 10));
+        await mintReceipt(paymentPda, payer);
+
+        try {
+            await mintReceipt(paymentPda, payer);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("ReceiptAlreadyMinted"));
+        }
+    });
+
+    it("should only mint a receipt for the payer", async () => {
+        const { paymentPda } = await pay(new anchor.BN(LAMPORTS_PER_SOL / 10));
+
+        try {
+            await mintReceipt(paymentPda, other);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("InvalidPayer"));
+        }
+    });
+
+    it("should not mint a receipt before the payment is finalized", async () => {
+        const { paymentId, paymentPda } = await pay(new anchor.BN(LAMPORTS_PER_SOL / 10), false);
+
+        try {
+            await mintReceipt(paymentPda, payer);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("PaymentNotFinalized"));
+        }
+
+        await finalize(paymentId, paymentPda);
+        await mintReceipt(paymentPda, payer);
+    });
+});
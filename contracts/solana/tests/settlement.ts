@@ -0,0 +1,294 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+import { TOKEN_PROGRAM_ID, getAccount, getOrCreateAssociatedTokenAccount, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
+
+describe("Accrued Settlement", () => {
+    const testId = TestHelper.generateTestId("accrue");
+    console.log(`Running accrued settlement tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+    const provider = anchor.getProvider();
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const payer = Keypair.generate();
+
+    const accrueName = "AccrueShop";
+    const instantName = "InstantShop";
+
+    let mint: PublicKey;
+    let payerTokenAccount: PublicKey;
+    let feeTokenAccount: PublicKey;
+    let settlementTokenAccount: PublicKey;
+    let vaultTokenAccount: PublicKey;
+
+    let accruePda: PublicKey;
+    let instantPda: PublicKey;
+    let vaultPda: PublicKey;
+    let balancePda: PublicKey;
+
+    const register = async (name: string) => {
+        const [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, name);
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, name, false, null, 0, true, "", 0)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, name)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+        return merchantPda;
+    };
+
+    const paySpl = (name: string, merchantPda: PublicKey, amount: anchor.BN, accrue: boolean) => {
+        const paymentId = new anchor.BN(Date.now() + Math.floor(Math.random() * 1000));
+        return program.methods
+            .processPayment(amount, paymentId, name, null, null, null, new anchor.BN(0))
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                payer: payer.publicKey,
+                merchantWallet: merchantWallet.publicKey,
+                feeVault: helper.getFeeVaultPda()[0],
+                feeStats: helper.getFeeStatsPda(mint)[0],
+                paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                tokenFee: helper.getTokenFeePda(mint)[0],
+                feeTiers: helper.getFeeTiersPda()[0],
+                systemProgram: SystemProgram.programId,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                payerTokenAccount: payerTokenAccount,
+                merchantTokenAccount: accrue ? null : settlementTokenAccount,
+                feeTokenAccount: feeTokenAccount,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                featureFlags: null,
+                vault: accrue ? vaultPda : null,
+                escrowBalance: null,
+                merchantBalance: accrue ? balancePda : null,
+                vaultTokenAccount: accrue ? vaultTokenAccount : null,
+                recentPayments: null,
+                splitConfig: null,
+                acceptedTokens: null,
+                memoProgram: null,
+                referrerWallet: null,
+                referrerTokenAccount: null,
+                discount: null,
+                notificationQueue: null,
+                dailyVolume: null,
+                rewardsHook: null,
+                rewardsProgram: null,
+                payerWsolAccount: null,
+                payerThrottle: null,
+                settlementRoute: null,
+                paymentLink: null,
+                ...helper.eventCpi,
+            })
+            .signers([payer])
+            .rpc();
+    };
+
+    const withdraw = (amount: anchor.BN) =>
+        program.methods
+            .withdrawBalance(accrueName, mint, amount)
+            .accountsStrict({
+                merchant: accruePda,
+                vault: vaultPda,
+                merchantBalance: balancePda,
+                settlementWallet: merchantWallet.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                vaultTokenAccount: vaultTokenAccount,
+                destinationTokenAccount: settlementTokenAccount,
+                ...helper.eventCpi,
+            })
+            .signers([merchantWallet])
+            .rpc();
+
+    before(async () => {
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(merchantWallet.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(payer.publicKey, 5 * LAMPORTS_PER_SOL);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                console.log("Global State exists. Updating config...");
+                await helper.setFee(admin, 100);
+                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+
+        // One merchant accrues, the other keeps the default instant payouts
+        accruePda = await register(accrueName);
+        instantPda = await register(instantName);
+        [vaultPda] = helper.getVaultPda(accruePda);
+        await program.methods
+            .setSettlementMode(accrueName, { accrue: {} })
+            .accountsStrict({ merchant: accruePda, owner: merchantOwner.publicKey, ...helper.eventCpi })
+            .signers([merchantOwner])
+            .rpc();
+
+        // Setup SPL token
+        mint = await helper.createTokenMint(payer);
+        payerTokenAccount = await helper.createTokenAccount(mint, payer.publicKey, payer);
+        feeTokenAccount = await helper.getFeeVaultTokenAccount(mint, payer);
+        settlementTokenAccount = await helper.createTokenAccount(mint, merchantWallet.publicKey, payer);
+        await helper.mintTokens(mint, payerTokenAccount, 1_000_000, payer);
+        await helper.openFeeStats(admin, mint);
+
+        // Open the SPL balance; the vault is created on first use
+        [balancePda] = helper.getMerchantBalancePda(accruePda, mint);
+        await program.methods
+            .openMerchantBalance(accrueName, mint)
+            .accountsStrict({
+                merchant: accruePda,
+                vault: vaultPda,
+                merchantBalance: balancePda,
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+        vaultTokenAccount = (
+            await getOrCreateAssociatedTokenAccount(provider.connection, payer, mint, vaultPda, true)
+        ).address;
+    });
+
+    it("should accrue payments and withdraw the exact total", async () => {
+        const amounts = [100_000, 50_000, 30_000].map((a) => new anchor.BN(a));
+        const total = amounts
+            .map((a) => a.sub(a.muln(100).divn(10000)))
+            .reduce((sum, a) => sum.add(a), new anchor.BN(0));
+
+        for (const amount of amounts) {
+            await paySpl(accrueName, accruePda, amount, true);
+        }
+
+        const balance = await program.account.merchantBalance.fetch(balancePda);
+        assert.equal(balance.amount.toString(), total.toString());
+        const vaultAcct = await getAccount(provider.connection, vaultTokenAccount);
+        assert.equal(vaultAcct.amount.toString(), total.toString());
+        let settlementAcct = await getAccount(provider.connection, settlementTokenAccount);
+        assert.equal(settlementAcct.amount.toString(), "0");
+
+        try {
+            await withdraw(total.addn(1));
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("InsufficientBalance"));
+        }
+
+        await withdraw(total);
+
+        settlementAcct = await getAccount(provider.connection, settlementTokenAccount);
+        assert.equal(settlementAcct.amount.toString(), total.toString());
+        const drained = await program.account.merchantBalance.fetch(balancePda);
+        assert.equal(drained.amount.toNumber(), 0);
+        const merchant = await program.account.merchant.fetch(accruePda);
+        assert.equal(merchant.fundedEscrows, 0);
+    });
+
+    it("should reject an accrue payment without the merchant balance", async () => {
+        const paymentId = new anchor.BN(Date.now());
+        try {
+            await program.methods
+                .processPayment(new anchor.BN(10_000), paymentId, accrueName, null, null, null, new anchor.BN(0))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: accruePda,
+                    merchantStats: helper.getMerchantStatsPda(accruePda)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(mint)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(mint)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    mint: mint,
+                    payerTokenAccount: payerTokenAccount,
+                    merchantTokenAccount: settlementTokenAccount,
+                    feeTokenAccount: feeTokenAccount,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .signers([payer])
+                .rpc();
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("MissingAccount"));
+        }
+    });
+
+    it("should leave instant merchants paying out directly", async () => {
+        const before = await getAccount(provider.connection, settlementTokenAccount);
+        const amount = new anchor.BN(40_000);
+        await paySpl(instantName, instantPda, amount, false);
+
+        const after = await getAccount(provider.connection, settlementTokenAccount);
+        const merchantAmount = amount.sub(amount.muln(100).divn(10000));
+        assert.equal((after.amount - before.amount).toString(), merchantAmount.toString());
+
+        const merchant = await program.account.merchant.fetch(instantPda);
+        assert.deepEqual(merchant.settlementMode, { instant: {} });
+    });
+});
@@ -0,0 +1,203 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+import { TOKEN_PROGRAM_ID, approve, revoke, getAccount } from "@solana/spl-token";
+
+describe("Subscriptions", () => {
+    const testId = TestHelper.generateTestId("subscription");
+    console.log(`Running subscription tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+    const provider = anchor.getProvider();
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const subscriber = Keypair.generate();
+    const cranker = Keypair.generate();
+
+    const merchantName = "SubscriptionShop";
+    const amount = new anchor.BN(10_000);
+    const interval = 2;
+
+    let merchantPda: PublicKey;
+    let delegatePda: PublicKey;
+    let mint: PublicKey;
+    let subscriberTokenAccount: PublicKey;
+    let merchantTokenAccount: PublicKey;
+    let feeTokenAccount: PublicKey;
+
+    const createSubscription = (subId: anchor.BN) =>
+        program.methods
+            .createSubscription(merchantName, subId, amount, new anchor.BN(interval), mint)
+            .accountsStrict({
+                merchant: merchantPda,
+                subscription: helper.getSubscriptionPda(merchantPda, subscriber.publicKey, subId)[0],
+                subscriber: subscriber.publicKey,
+                mint: mint,
+                subscriberTokenAccount: subscriberTokenAccount,
+                delegate: delegatePda,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([subscriber])
+            .rpc();
+
+    const charge = (subId: anchor.BN) =>
+        program.methods
+            .chargeSubscription(merchantName)
+            .accountsStrict({
+                globalState: helper.globalStatePda,
+                merchant: merchantPda,
+                subscription: helper.getSubscriptionPda(merchantPda, subscriber.publicKey, subId)[0],
+                delegate: delegatePda,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                mint: mint,
+                subscriberTokenAccount: subscriberTokenAccount,
+                merchantTokenAccount: merchantTokenAccount,
+                feeTokenAccount: feeTokenAccount,
+                featureFlags: null,
+                ...helper.eventCpi,
+            })
+            .signers([cranker])
+            .rpc();
+
+    const approveDelegate = (allowance: number) =>
+        approve(provider.connection, subscriber, subscriberTokenAccount, delegatePda, subscriber, allowance);
+
+    const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+    before(async () => {
+        await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(merchantOwner.publicKey);
+        await helper.airdrop(feeWallet.publicKey);
+        await helper.airdrop(subscriber.publicKey, 2 * LAMPORTS_PER_SOL);
+        await helper.airdrop(cranker.publicKey);
+
+        // Initialize global state or update if exists
+        try {
+            await program.methods
+                .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    payer: admin.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([admin])
+                .rpc();
+        } catch (e: any) {
+            if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                console.log("Global State exists. Updating config...");
+                await helper.setFee(admin, 100);
+                await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+            } else {
+                throw e;
+            }
+        }
+
+        [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+        [delegatePda] = helper.getSubscriptionDelegatePda();
+        await program.methods
+            .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
+            .accountsStrict({
+                merchant: merchantPda,
+                merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                globalStats: helper.getGlobalStatsPda()[0],
+                merchantRegistry: helper.getMerchantRegistryPda()[0],
+                merchantIndex: await helper.nextMerchantIndexPda(),
+                merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                owner: merchantOwner.publicKey,
+                systemProgram: SystemProgram.programId,
+                ...helper.eventCpi,
+            })
+            .signers([merchantOwner])
+            .rpc();
+
+        mint = await helper.createTokenMint(subscriber);
+        subscriberTokenAccount = await helper.createTokenAccount(mint, subscriber.publicKey, subscriber);
+        merchantTokenAccount = await helper.createTokenAccount(mint, merchantWallet.publicKey, subscriber);
+        feeTokenAccount = await helper.createTokenAccount(mint, feeWallet.publicKey, subscriber);
+        await helper.mintTokens(mint, subscriberTokenAccount, 1_000_000, subscriber);
+    });
+
+    it("should refuse to create a subscription without a delegate approval", async () => {
+        try {
+            await createSubscription(new anchor.BN(99));
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("DelegateNotApproved"));
+        }
+    });
+
+    it("should charge the first period and reject an early second charge", async () => {
+        const subId = new anchor.BN(1);
+        await approveDelegate(amount.toNumber() * 10);
+        await createSubscription(subId);
+
+        await charge(subId);
+
+        const merchantAcct = await getAccount(provider.connection, merchantTokenAccount);
+        const fee = amount.muln(100).divn(10000);
+        assert.equal(merchantAcct.amount.toString(), amount.sub(fee).toString());
+
+        const [subscriptionPda] = helper.getSubscriptionPda(merchantPda, subscriber.publicKey, subId);
+        const subscription = await program.account.subscription.fetch(subscriptionPda);
+        assert.equal(subscription.chargesCount.toNumber(), 1);
+        assert.ok(subscription.lastCharged.toNumber() > 0);
+
+        try {
+            await charge(subId);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("SubscriptionNotDue"));
+        }
+    });
+
+    it("should charge again once the interval has passed", async () => {
+        const subId = new anchor.BN(1);
+        await sleep((interval + 1) * 1000);
+        await charge(subId);
+
+        const [subscriptionPda] = helper.getSubscriptionPda(merchantPda, subscriber.publicKey, subId);
+        const subscription = await program.account.subscription.fetch(subscriptionPda);
+        assert.equal(subscription.chargesCount.toNumber(), 2);
+    });
+
+    it("should fail gracefully after the delegate is revoked", async () => {
+        const subId = new anchor.BN(1);
+        await revoke(provider.connection, subscriber, subscriberTokenAccount, subscriber);
+        await sleep((interval + 1) * 1000);
+
+        try {
+            await charge(subId);
+            assert.fail("Should have failed");
+        } catch (e: any) {
+            assert.ok(e.message.includes("DelegateNotApproved"));
+        }
+    });
+
+    it("should let the subscriber cancel and reclaim rent", async () => {
+        const subId = new anchor.BN(1);
+        const [subscriptionPda] = helper.getSubscriptionPda(merchantPda, subscriber.publicKey, subId);
+
+        await program.methods
+            .cancelSubscription()
+            .accountsStrict({
+                subscription: subscriptionPda,
+                subscriber: subscriber.publicKey,
+                ...helper.eventCpi,
+            })
+            .signers([subscriber])
+            .rpc();
+
+        assert.isNull(await program.account.subscription.fetchNullable(subscriptionPda));
+    });
+});
@@ -0,0 +1,265 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { OrkiGateway } from "../target/types/orki_gateway";
+import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import { TestHelper } from "./utils/helpers";
+
+// Deterministic scenarios need `yarn test:clock`, which builds with the test-clock feature.
+describe("Test Clock", () => {
+    const testId = TestHelper.generateTestId("clock");
+    console.log(`Running test clock tests with ID: ${testId}`);
+
+    anchor.setProvider(anchor.AnchorProvider.env());
+    const program = anchor.workspace.OrkiGateway as Program<OrkiGateway>;
+    const helper = new TestHelper(program, testId);
+
+    const admin = helper.getAdminKeypair(); // Use shared deterministic admin
+    const merchantOwner = Keypair.generate();
+    const merchantWallet = Keypair.generate();
+    const feeWallet = Keypair.generate();
+    const payer = Keypair.generate();
+
+    const merchantName = "ClockShop";
+    let merchantPda: PublicKey;
+
+    const T0 = 1_000_000;
+    const clockAccounts = () => [
+        { pubkey: helper.getTestClockPda()[0], isSigner: false, isWritable: false },
+    ];
+
+    it("should only expose set_test_clock in test-clock builds", () => {
+        assert.equal(helper.hasTestClock(), process.env.ORKI_TEST_CLOCK === "1");
+    });
+
+    describe("Pinned time", () => {
+        before(async function () {
+            if (!helper.hasTestClock()) {
+                this.skip();
+            }
+
+            await helper.airdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
+            await helper.airdrop(merchantOwner.publicKey);
+            await helper.airdrop(feeWallet.publicKey);
+            await helper.airdrop(payer.publicKey, 2 * LAMPORTS_PER_SOL);
+
+            // Initialize global state or update if exists
+            try {
+                await program.methods
+                    .initialize(100, feeWallet.publicKey, 10000, admin.publicKey, null)
+                    .accountsStrict({
+                        globalState: helper.globalStatePda,
+                        globalStats: helper.getGlobalStatsPda()[0],
+                        payer: admin.publicKey,
+                        systemProgram: SystemProgram.programId,
+                    })
+                    .signers([admin])
+                    .rpc();
+            } catch (e: any) {
+                if (e.message.includes("already in use") || e.logs?.some((l: string) => l.includes("already in use")) || e.toString().includes("already in use")) {
+                    await program.methods.setFeeWallet(feeWallet.publicKey).accountsStrict({ globalState: helper.globalStatePda, admin: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                    await program.methods.setPaused(false).accountsStrict({ globalState: helper.globalStatePda, authority: admin.publicKey, ...helper.eventCpi }).signers([admin]).rpc();
+                } else {
+                    throw e;
+                }
+            }
+            await helper.openFeeStats(admin, PublicKey.default);
+
+            [merchantPda] = helper.getMerchantPda(merchantOwner.publicKey, merchantName);
+            await program.methods
+                .registerMerchant(merchantWallet.publicKey, PublicKey.default, merchantName, false, null, 0, true, "", 0)
+                .accountsStrict({
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchantRegistry: helper.getMerchantRegistryPda()[0],
+                    merchantIndex: await helper.nextMerchantIndexPda(),
+                    merchantName: helper.getMerchantNamePda(merchantOwner.publicKey, merchantName)[0],
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .signers([merchantOwner])
+                .rpc();
+        });
+
+        const createInvoice = (invoiceId: anchor.BN, expiresAt: number) =>
+            program.methods
+                .createInvoice(merchantName, invoiceId, new anchor.BN(1000), PublicKey.default, new anchor.BN(expiresAt), "")
+                .accountsStrict({
+                    merchant: merchantPda,
+                    invoice: helper.getInvoicePda(merchantPda, invoiceId)[0],
+                    authority: merchantOwner.publicKey,
+                    operator: null,
+                    systemProgram: SystemProgram.programId,
+                    ...helper.eventCpi,
+                })
+                .remainingAccounts(clockAccounts())
+                .signers([merchantOwner])
+                .rpc();
+
+        const payInvoice = (invoiceId: anchor.BN) =>
+            program.methods
+                .payInvoice(merchantName)
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    merchant: merchantPda,
+                    invoice: helper.getInvoicePda(merchantPda, invoiceId)[0],
+                    payer: payer.publicKey,
+                    merchantWallet: merchantWallet.publicKey,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    featureFlags: null,
+                    ...helper.eventCpi,
+                })
+                .remainingAccounts(clockAccounts())
+                .signers([payer])
+                .rpc();
+
+        it("should accept an invoice paid right at its deadline", async () => {
+            const invoiceId = new anchor.BN(1);
+            await helper.setTestClock(admin, T0);
+            await createInvoice(invoiceId, T0 + 100);
+
+            await helper.setTestClock(admin, T0 + 100);
+            await payInvoice(invoiceId);
+
+            const invoice = await program.account.invoice.fetch(helper.getInvoicePda(merchantPda, invoiceId)[0]);
+            assert.equal(invoice.paidAt.toNumber(), T0 + 100);
+        });
+
+        it("should reject an invoice one second past its deadline", async () => {
+            const invoiceId = new anchor.BN(2);
+            await helper.setTestClock(admin, T0);
+            await createInvoice(invoiceId, T0 + 100);
+
+            await helper.setTestClock(admin, T0 + 101);
+            try {
+                await payInvoice(invoiceId);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("InvoiceExpired"));
+            }
+        });
+
+        it("should switch settlement to a payout address only once its delay has passed", async () => {
+            const destination = Keypair.generate().publicKey;
+            const [payoutBookPda] = helper.getPayoutBookPda(merchantPda);
+            const updateWallet = () =>
+                program.methods
+                    .updateMerchant(merchantName, destination, null, null, null, null, null, null, null, null)
+                    .accountsStrict({
+                        merchant: merchantPda,
+                        authority: merchantOwner.publicKey,
+                        operator: null,
+                        payoutBook: payoutBookPda,
+                        ...helper.eventCpi,
+                    })
+                    .remainingAccounts(clockAccounts())
+                    .signers([merchantOwner])
+                    .rpc();
+
+            await helper.setTestClock(admin, T0);
+            await program.methods
+                .addPayoutAddress(merchantName, destination, "Treasury")
+                .accountsStrict({
+                    merchant: merchantPda,
+                    payoutBook: payoutBookPda,
+                    owner: merchantOwner.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .remainingAccounts(clockAccounts())
+                .signers([merchantOwner])
+                .rpc();
+
+            await helper.setTestClock(admin, T0 + 86400 - 1);
+            try {
+                await updateWallet();
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PayoutAddressNotActive"));
+            }
+
+            await helper.setTestClock(admin, T0 + 86400);
+            await updateWallet();
+
+            const merchant = await program.account.merchant.fetch(merchantPda);
+            assert.ok(merchant.settlementWallet.equals(destination));
+        });
+
+        const payUntil = async (paymentId: anchor.BN, validUntil: number) =>
+            program.methods
+                .processPayment(new anchor.BN(LAMPORTS_PER_SOL / 100), paymentId, merchantName, null, null, null, new anchor.BN(validUntil))
+                .accountsStrict({
+                    globalState: helper.globalStatePda,
+                    globalStats: helper.getGlobalStatsPda()[0],
+                    merchant: merchantPda,
+                    merchantStats: helper.getMerchantStatsPda(merchantPda)[0],
+                    payer: payer.publicKey,
+                    // The payout test above may have moved settlement
+                    merchantWallet: (await program.account.merchant.fetch(merchantPda)).settlementWallet,
+                    feeVault: helper.getFeeVaultPda()[0],
+                    feeStats: helper.getFeeStatsPda(PublicKey.default)[0],
+                    paymentHistory: helper.getPaymentPda(payer.publicKey, paymentId)[0],
+                    blocklistEntry: helper.getBlockedPda(payer.publicKey)[0],
+                    tokenFee: helper.getTokenFeePda(PublicKey.default)[0],
+                    feeTiers: helper.getFeeTiersPda()[0],
+                    systemProgram: SystemProgram.programId,
+                    tokenProgram: null,
+                    mint: null,
+                    payerTokenAccount: null,
+                    merchantTokenAccount: null,
+                    feeTokenAccount: null,
+                    associatedTokenProgram: null,
+                    featureFlags: null,
+                    vault: null,
+                    escrowBalance: null,
+                    merchantBalance: null,
+                    vaultTokenAccount: null,
+                    recentPayments: null,
+                    splitConfig: null,
+                    acceptedTokens: null,
+                    memoProgram: null,
+                    referrerWallet: null,
+                    referrerTokenAccount: null,
+                    discount: null,
+                    notificationQueue: null,
+                    dailyVolume: null,
+                    rewardsHook: null,
+                    rewardsProgram: null,
+                    payerWsolAccount: null,
+                    payerThrottle: null,
+                    settlementRoute: null,
+                    paymentLink: null,
+                    ...helper.eventCpi,
+                })
+                .remainingAccounts(clockAccounts())
+                .signers([payer])
+                .rpc();
+
+        it("should accept a payment landing before its deadline", async () => {
+            await helper.setTestClock(admin, T0);
+            const paymentId = new anchor.BN(Date.now());
+            await payUntil(paymentId, T0 + 120);
+
+            const payment = await program.account.payment.fetch(helper.getPaymentPda(payer.publicKey, paymentId)[0]);
+            assert.equal(payment.timestamp.toNumber(), T0);
+        });
+
+        it("should reject a payment landing after its deadline", async () => {
+            await helper.setTestClock(admin, T0 + 600);
+            try {
+                await payUntil(new anchor.BN(Date.now()), T0 + 120);
+                assert.fail("Should have failed");
+            } catch (e: any) {
+                assert.ok(e.message.includes("PaymentExpired"));
+            }
+        });
+    });
+});
@@ -3,7 +3,8 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { OrkiGateway } from "../../target/types/orki_gateway";
 import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
-import { createMint, createAssociatedTokenAccount, mintTo, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createMint, createAssociatedTokenAccount, getOrCreateAssociatedTokenAccount, mintTo, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createHash } from "crypto";
 
 export class TestHelper {
     program: Program<OrkiGateway>;
@@ -42,6 +43,13 @@ export class TestHelper {
         return [this.globalStatePda, this.globalStateBump];
     }
 
+    getGlobalStatsPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("global_stats")],
+            this.program.programId
+        );
+    }
+
     getMerchantPda(owner: PublicKey, name: string): [PublicKey, number] {
         return PublicKey.findProgramAddressSync(
             [
@@ -64,26 +72,334 @@ export class TestHelper {
         );
     }
 
-    async createTokenMint(admin: Keypair, decimals = 6): Promise<PublicKey> {
+    getMerchantTransferPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("merchant_transfer"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getMerchantStatsPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("stats"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    // An owner's claim on a merchant name, in any casing
+    getMerchantNamePda(owner: PublicKey, name: string): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("merchant_name"), owner.toBuffer(), Buffer.from(name.toLowerCase())],
+            this.program.programId
+        );
+    }
+
+    getMerchantRegistryPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("merchant_registry")],
+            this.program.programId
+        );
+    }
+
+    getMerchantIndexPda(index: number | anchor.BN): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("merchant_index"), new anchor.BN(index).toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+    }
+
+    // Directory slot a merchant holds, or null if it was never indexed
+    async getMerchantIndexPdaOf(merchant: PublicKey): Promise<PublicKey | null> {
+        const { merchantIndex } = await this.program.account.merchant.fetch(merchant);
+        return merchantIndex.isZero() ? null : this.getMerchantIndexPda(merchantIndex)[0];
+    }
+
+    // Directory slot the next registration takes; the first one creates the registry
+    async nextMerchantIndexPda(): Promise<PublicKey> {
+        const registry = await this.program.account.merchantRegistry.fetchNullable(this.getMerchantRegistryPda()[0]);
+        const count = registry ? registry.merchantCount : new anchor.BN(0);
+        return this.getMerchantIndexPda(count.addn(1))[0];
+    }
+
+    getVaultPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("vault"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    // Use PublicKey.default as the mint for SOL escrow
+    getEscrowBalancePda(merchant: PublicKey, mint: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("escrow_balance"), merchant.toBuffer(), mint.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getMerchantBalancePda(merchant: PublicKey, mint: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("merchant_balance"), merchant.toBuffer(), mint.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getFeeVaultPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("fee_vault")],
+            this.program.programId
+        );
+    }
+
+    // Use PublicKey.default as the mint for SOL fees
+    getFeeStatsPda(mint: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("fee_stats"), mint.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    // Fee stats are global per mint, so suites share them; opens only if missing
+    async openFeeStats(admin: Keypair, mint: PublicKey) {
+        const [feeStatsPda] = this.getFeeStatsPda(mint);
+        if (await this.provider.connection.getAccountInfo(feeStatsPda)) {
+            return;
+        }
+        await this.program.methods
+            .openFeeStats(mint)
+            .accountsStrict({
+                globalState: this.globalStatePda,
+                feeVault: this.getFeeVaultPda()[0],
+                feeStats: feeStatsPda,
+                admin: admin.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([admin])
+            .rpc();
+    }
+
+    // The fee vault's token account for `mint`, where process_payment sends SPL fees
+    async getFeeVaultTokenAccount(
+        mint: PublicKey,
+        payer: Keypair,
+        tokenProgram = TOKEN_PROGRAM_ID
+    ): Promise<PublicKey> {
+        const account = await getOrCreateAssociatedTokenAccount(
+            this.provider.connection,
+            payer,
+            mint,
+            this.getFeeVaultPda()[0],
+            true,
+            undefined,
+            undefined,
+            tokenProgram
+        );
+        return account.address;
+    }
+
+    getInvoicePda(merchant: PublicKey, invoiceId: anchor.BN): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("invoice"), merchant.toBuffer(), invoiceId.toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+    }
+
+    getOperatorPda(merchant: PublicKey, operator: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("operator"), merchant.toBuffer(), operator.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getLinkPda(merchant: PublicKey, linkSeed: anchor.BN): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("link"), merchant.toBuffer(), linkSeed.toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+    }
+
+    getBlockedPda(address: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync([Buffer.from("blocked"), address.toBuffer()], this.program.programId);
+    }
+
+    getTokenFeePda(mint: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync([Buffer.from("token_fee"), mint.toBuffer()], this.program.programId);
+    }
+
+    getFeeTiersPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync([Buffer.from("fee_tiers")], this.program.programId);
+    }
+
+    getFeeTiersProposalPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync([Buffer.from("fee_tiers_proposal")], this.program.programId);
+    }
+
+    getReceiptMintPda(payment: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync([Buffer.from("receipt"), payment.toBuffer()], this.program.programId);
+    }
+
+    getReceiptAuthorityPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync([Buffer.from("receipt_authority")], this.program.programId);
+    }
+
+    getDiscountPda(merchant: PublicKey, code: string): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("discount"), merchant.toBuffer(), TestHelper.hashDiscountCode(code)],
+            this.program.programId
+        );
+    }
+
+    static hashDiscountCode(code: string): Buffer {
+        return createHash("sha256").update(code).digest();
+    }
+
+    // Keyed like a v2 payment, by the hash of the link and the use it took
+    getLinkPaymentPda(link: PublicKey, payer: PublicKey, useIndex: anchor.BN): [PublicKey, number] {
+        const orderId = createHash("sha256")
+            .update(Buffer.from("link"))
+            .update(link.toBuffer())
+            .update(useIndex.toArrayLike(Buffer, "le", 8))
+            .digest();
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("payment"), payer.toBuffer(), orderId],
+            this.program.programId
+        );
+    }
+
+    getRecentPaymentsPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("recent_payments"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getNotificationQueuePda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("notification_queue"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+        getSubscriptionPda(merchant: PublicKey, subscriber: PublicKey, subId: anchor.BN): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("subscription"), merchant.toBuffer(), subscriber.toBuffer(), subId.toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+    }
+
+    getSubscriptionDelegatePda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("subscription_delegate")],
+            this.program.programId
+        );
+    }
+
+    getSplitConfigPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("split"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getAcceptedTokensPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("accepted"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getPayoutBookPda(merchant: PublicKey): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("payout_book"), merchant.toBuffer()],
+            this.program.programId
+        );
+    }
+
+    getTestClockPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("test_clock")],
+            this.program.programId
+        );
+    }
+
+    // Only available when the program is built with `--features test-clock`
+    hasTestClock(): boolean {
+        return this.program.idl.instructions.some((ix) => ix.name === "setTestClock");
+    }
+
+    async setTestClock(admin: Keypair, unixTimestamp: number) {
+        await (this.program.methods as any)
+            .setTestClock(new anchor.BN(unixTimestamp))
+            .accountsStrict({
+                globalState: this.globalStatePda,
+                testClock: this.getTestClockPda()[0],
+                admin: admin.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([admin])
+            .rpc();
+    }
+
+    // Extra accounts every #[event_cpi] instruction takes for its self-CPI
+    get eventCpi() {
+        const [eventAuthority] = PublicKey.findProgramAddressSync(
+            [Buffer.from("__event_authority")],
+            this.program.programId
+        );
+        return { eventAuthority, program: this.program.programId };
+    }
+
+    // Built with `--features log-events`: events go to program logs instead
+    logEvents(): boolean {
+        return process.env.ORKI_LOG_EVENTS === "1";
+    }
+
+    // Proposes and applies a global fee; only immediate while fee_timelock_seconds is 0
+    async setFee(admin: Keypair, feeBps: number) {
+        await this.program.methods
+            .proposeFee(feeBps)
+            .accountsStrict({ globalState: this.globalStatePda, admin: admin.publicKey, ...this.eventCpi })
+            .signers([admin])
+            .rpc();
+        await this.program.methods
+            .applyFee()
+            .accountsStrict({ globalState: this.globalStatePda, caller: admin.publicKey, ...this.eventCpi })
+            .signers([admin])
+            .rpc();
+    }
+
+    getFeatureFlagsPda(): [PublicKey, number] {
+        return PublicKey.findProgramAddressSync(
+            [Buffer.from("feature_flags")],
+            this.program.programId
+        );
+    }
+
+    async createTokenMint(admin: Keypair, decimals = 6, tokenProgram = TOKEN_PROGRAM_ID): Promise<PublicKey> {
         return await createMint(
             this.provider.connection,
             admin,
             admin.publicKey,
             null,
-            decimals
+            decimals,
+            undefined,
+            undefined,
+            tokenProgram
         );
     }
 
     async createTokenAccount(
         mint: PublicKey,
         owner: PublicKey,
-        payer: Keypair
+        payer: Keypair,
+        tokenProgram = TOKEN_PROGRAM_ID
     ): Promise<PublicKey> {
         return await createAssociatedTokenAccount(
             this.provider.connection,
             payer,
             mint,
-            owner
+            owner,
+            undefined,
+            tokenProgram
         );
     }
 
@@ -91,7 +407,8 @@ export class TestHelper {
         mint: PublicKey,
         to: PublicKey,
         amount: number,
-        payer: Keypair
+        payer: Keypair,
+        tokenProgram = TOKEN_PROGRAM_ID
     ) {
         await mintTo(
             this.provider.connection,
@@ -99,7 +416,10 @@ export class TestHelper {
             mint,
             to,
             payer,
-            amount
+            amount,
+            [],
+            undefined,
+            tokenProgram
         );
     }
 
@@ -0,0 +1,63 @@
+//! Callback routing, read from a TOML file:
+//!
+//! ```toml
+//! # Shared with every merchant; signs each body (see crate::delivery)
+//! secret = "whsec_..."
+//!
+//! [merchants]
+//! # Merchant PDA = callback URL
+//! "7Qx...Shop" = "https://shop.example/orki/webhook"
+//! ```
+//!
+//! Merchants without an entry are skipped.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::Error;
+
+pub struct Config {
+    pub secret: String,
+    pub merchants: HashMap<Pubkey, String>,
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+    secret: String,
+    #[serde(default)]
+    merchants: HashMap<String, String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::Other(format!("reading config {}: {e}", path.display())))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let raw: RawConfig = toml::from_str(text).map_err(|e| Error::Other(format!("config: {e}")))?;
+        if raw.secret.is_empty() {
+            return Err(Error::Other("config: secret is empty".to_string()));
+        }
+        let merchants = raw
+            .merchants
+            .into_iter()
+            .map(|(merchant, url)| {
+                let merchant = merchant
+                    .parse()
+                    .map_err(|e| Error::Other(format!("config: merchant {merchant}: {e}")))?;
+                Ok((merchant, url))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Config { secret: raw.secret, merchants })
+    }
+
+    /// Callback URL for `merchant`, if it has one.
+    pub fn url(&self, merchant: &Pubkey) -> Option<&str> {
+        self.merchants.get(merchant).map(String::as_str)
+    }
+}
@@ -0,0 +1,73 @@
+//! Where to resume after a restart: the newest signature whose events were
+//! handled, in a one-line file. Written through a temporary file and a
+//! rename, so a crash mid-write leaves the previous cursor in place.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+
+use solana_sdk::signature::Signature;
+
+use crate::Error;
+
+pub struct Cursor {
+    path: PathBuf,
+    last: Option<Signature>,
+}
+
+impl Cursor {
+    /// Reads the cursor at `path`; a missing file means no cursor yet.
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let last = match std::fs::read_to_string(&path) {
+            Ok(text) => Some(
+                text.trim()
+                    .parse()
+                    .map_err(|e| Error::Other(format!("cursor {}: {e}", path.display())))?,
+            ),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Cursor { path, last })
+    }
+
+    pub fn last(&self) -> Option<Signature> {
+        self.last
+    }
+
+    pub fn advance(&mut self, signature: Signature) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, format!("{signature}\n"))?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.last = Some(signature);
+        Ok(())
+    }
+}
+
+/// Signatures handled recently, so a transaction that shows up twice, say
+/// across a reconnect, is posted once. Bounded; the cursor covers older ones.
+pub struct Seen {
+    order: VecDeque<Signature>,
+    set: HashSet<Signature>,
+    capacity: usize,
+}
+
+impl Seen {
+    pub fn new(capacity: usize) -> Self {
+        Seen { order: VecDeque::with_capacity(capacity), set: HashSet::with_capacity(capacity), capacity }
+    }
+
+    pub fn contains(&self, signature: &Signature) -> bool {
+        self.set.contains(signature)
+    }
+
+    pub fn insert(&mut self, signature: Signature) {
+        if !self.set.insert(signature) {
+            return;
+        }
+        self.order.push_back(signature);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.set.remove(&oldest);
+            }
+        }
+    }
+}
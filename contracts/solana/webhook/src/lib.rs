@@ -0,0 +1,55 @@
+//! Merchant webhooks for the gateway: tails the program's transactions,
+//! decodes PaymentProcessed and PaymentRefunded, and POSTs each one as signed
+//! JSON to the merchant's callback URL.
+//!
+//! - [`config`] maps merchants to callback URLs and holds the shared secret.
+//! - [`payload`] turns a decoded event into the JSON body.
+//! - [`delivery`] signs and posts bodies; receivers check them with [`delivery::verify`].
+//! - [`cursor`] persists the last handled signature so restarts resume there.
+//! - [`listener`] ties them together over logsSubscribe, polling when the
+//!   websocket is down.
+
+pub mod config;
+pub mod cursor;
+pub mod delivery;
+pub mod listener;
+pub mod payload;
+
+use solana_rpc_client_api::client_error::Error as ClientError;
+
+#[derive(Debug)]
+pub enum Error {
+    Rpc(Box<ClientError>),
+    Http(reqwest::Error),
+    Io(std::io::Error),
+    Other(String),
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Error::Rpc(Box::new(err))
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Rpc(err) => write!(f, "{err}"),
+            Error::Http(err) => write!(f, "{err}"),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Other(message) => f.write_str(message),
+        }
+    }
+}
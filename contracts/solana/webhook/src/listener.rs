@@ -0,0 +1,154 @@
+//! The event loop. Every wake-up, whether a logsSubscribe notification or a
+//! poll tick, runs the same catch-up over getSignaturesForAddress from the
+//! cursor, so a dropped websocket or a restart loses nothing and the two
+//! sources never double-post.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use orki_gateway_client::orki_gateway;
+use orki_gateway_client::rpc::{events_from_transaction, transaction_config};
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_rpc_client_api::config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+
+use crate::config::Config;
+use crate::cursor::{Cursor, Seen};
+use crate::delivery::Deliverer;
+use crate::payload::notification;
+use crate::Error;
+
+// getSignaturesForAddress returns at most this many per call
+const PAGE: usize = 1000;
+
+pub struct Listener {
+    pub rpc: RpcClient,
+    pub ws_url: String,
+    pub config: Config,
+    pub deliverer: Deliverer,
+    pub cursor: Cursor,
+    pub seen: Seen,
+    /// How often to poll, and how long to wait before reconnecting
+    pub poll_interval: Duration,
+}
+
+impl Listener {
+    /// Runs until the process is stopped. Without a cursor it starts from
+    /// the newest gateway transaction rather than replaying history.
+    pub async fn run(&mut self) -> Result<(), Error> {
+        if self.cursor.last().is_none() {
+            self.start_from_newest().await?;
+        }
+        loop {
+            self.catch_up_logged().await;
+            match PubsubClient::new(&self.ws_url).await {
+                Ok(pubsub) => self.follow(&pubsub).await,
+                Err(err) => eprintln!("websocket {}: {err}; polling", self.ws_url),
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    // Catches up on every notification, and every poll_interval without one,
+    // until the subscription ends
+    async fn follow(&mut self, pubsub: &PubsubClient) {
+        let filter = RpcTransactionLogsFilter::Mentions(vec![orki_gateway::ID.to_string()]);
+        let config = RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::confirmed()) };
+        let (mut logs, unsubscribe) = match pubsub.logs_subscribe(filter, config).await {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                eprintln!("logsSubscribe: {err}; polling");
+                return;
+            }
+        };
+        while let Ok(Some(_)) | Err(_) = tokio::time::timeout(self.poll_interval, logs.next()).await {
+            self.catch_up_logged().await;
+        }
+        unsubscribe().await;
+        eprintln!("subscription closed; reconnecting");
+    }
+
+    async fn start_from_newest(&mut self) -> Result<(), Error> {
+        let newest = self
+            .rpc
+            .get_signatures_for_address_with_config(
+                &orki_gateway::ID,
+                GetConfirmedSignaturesForAddress2Config {
+                    limit: Some(1),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        if let Some(status) = newest.first() {
+            self.cursor.advance(parse_signature(&status.signature)?)?;
+        }
+        Ok(())
+    }
+
+    async fn catch_up_logged(&mut self) {
+        if let Err(err) = self.catch_up().await {
+            eprintln!("catching up: {err}");
+        }
+    }
+
+    /// Handles every gateway transaction after the cursor, oldest first,
+    /// advancing the cursor past each. An RPC error stops short of the
+    /// failing transaction so the next call retries it.
+    pub async fn catch_up(&mut self) -> Result<(), Error> {
+        let mut pending = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    &orki_gateway::ID,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: self.cursor.last(),
+                        limit: Some(PAGE),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    },
+                )
+                .await?;
+            let full = page.len() == PAGE;
+            before = page.last().map(|status| parse_signature(&status.signature)).transpose()?;
+            pending.extend(page);
+            if !full {
+                break;
+            }
+        }
+
+        for status in pending.into_iter().rev() {
+            let signature = parse_signature(&status.signature)?;
+            // Failed transactions emit nothing
+            if status.err.is_none() && !self.seen.contains(&signature) {
+                self.handle(&signature, status.slot).await?;
+            }
+            self.seen.insert(signature);
+            self.cursor.advance(signature)?;
+        }
+        Ok(())
+    }
+
+    // Posts the transaction's notifications. A callback that keeps failing
+    // is logged and skipped so one merchant's outage cannot stall the rest.
+    async fn handle(&self, signature: &Signature, slot: u64) -> Result<(), Error> {
+        let tx = self.rpc.get_transaction_with_config(signature, transaction_config()).await?;
+        for event in events_from_transaction(&tx) {
+            let Some(notification) = notification(signature, slot, &event) else { continue };
+            let Some(url) = self.config.url(&notification.merchant) else { continue };
+            if let Err(err) = self.deliverer.deliver(url, &notification.body).await {
+                eprintln!("giving up on {} for {}: {err}", event.name(), notification.merchant);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_signature(signature: &str) -> Result<Signature, Error> {
+    signature.parse().map_err(|e| Error::Other(format!("signature {signature}: {e}")))
+}
@@ -0,0 +1,79 @@
+//! `orki-webhook`: posts signed payment callbacks to merchants.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+use orki_webhook::config::Config;
+use orki_webhook::cursor::{Cursor, Seen};
+use orki_webhook::delivery::Deliverer;
+use orki_webhook::listener::Listener;
+use orki_webhook::Error;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+#[derive(Parser)]
+#[command(name = "orki-webhook", about = "Post signed payment callbacks to Orki merchants")]
+struct Cli {
+    /// RPC endpoint
+    #[arg(long, short, env = "ORKI_RPC_URL", default_value = "http://127.0.0.1:8899")]
+    url: String,
+
+    /// Websocket endpoint; derived from --url when omitted
+    #[arg(long, env = "ORKI_WS_URL")]
+    ws_url: Option<String>,
+
+    /// Secret and merchant callback URLs
+    #[arg(long, short, default_value = "orki-webhook.toml")]
+    config: PathBuf,
+
+    /// Where the last handled signature is kept between runs
+    #[arg(long, default_value = "orki-webhook.cursor")]
+    cursor: PathBuf,
+
+    /// Seconds between polls when no notification arrives
+    #[arg(long, default_value_t = 10)]
+    poll_seconds: u64,
+
+    /// Tries per callback before giving up on it
+    #[arg(long, default_value_t = 5)]
+    attempts: u32,
+}
+
+// The validator's default layout: websocket on the RPC port plus one
+fn ws_url(url: &str) -> String {
+    let url = match url.strip_prefix("https://") {
+        Some(rest) => format!("wss://{rest}"),
+        None => url.replacen("http://", "ws://", 1),
+    };
+    url.replace(":8899", ":8900")
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let config = Config::load(&cli.config)?;
+    let deliverer = Deliverer::new(config.secret.clone(), cli.attempts, Duration::from_secs(1))?;
+    let mut listener = Listener {
+        ws_url: cli.ws_url.unwrap_or_else(|| ws_url(&cli.url)),
+        rpc: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        config,
+        deliverer,
+        cursor: Cursor::load(cli.cursor)?,
+        seen: Seen::new(10_000),
+        poll_interval: Duration::from_secs(cli.poll_seconds.max(1)),
+    };
+    println!("watching {} for {} merchants", listener.ws_url, listener.config.merchants.len());
+    listener.run().await
+}
@@ -0,0 +1,68 @@
+//! JSON bodies for the events merchants are notified of. Token amounts are
+//! strings, since u64 base units overflow a JavaScript number; `id` is the
+//! event's `event_seq`, unique across the program, for receivers to dedupe on.
+
+use orki_gateway_client::events::GatewayEvent;
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+/// A body ready to sign and post to `merchant`'s callback.
+pub struct Notification {
+    pub merchant: Pubkey,
+    pub body: String,
+}
+
+/// The notification for `event`, found in transaction `signature` at `slot`.
+/// None for events merchants are not notified of.
+pub fn notification(signature: &Signature, slot: u64, event: &GatewayEvent) -> Option<Notification> {
+    let (kind, merchant, event_seq, data) = match event {
+        GatewayEvent::PaymentProcessed(e) => (
+            "payment.processed",
+            e.merchant,
+            e.event_seq,
+            json!({
+                "payer": e.payer.to_string(),
+                "merchant": e.merchant.to_string(),
+                "merchant_name": e.merchant_name,
+                "payment_account": e.payment_account.to_string(),
+                "token": e.token.to_string(),
+                "amount": e.amount.to_string(),
+                "fee": e.fee.to_string(),
+                "tip": e.tip.to_string(),
+                "referrer_amount": e.referrer_amount.to_string(),
+                "payment_id": e.payment_id.to_string(),
+                "memo": e.memo,
+                "sandbox": e.sandbox,
+                "fee_bps": e.fee_bps,
+                "fee_source": format!("{:?}", e.fee_source).to_lowercase(),
+                "valid_until": e.valid_until,
+                "timestamp": e.timestamp,
+            }),
+        ),
+        GatewayEvent::PaymentRefunded(e) => (
+            "payment.refunded",
+            e.merchant,
+            e.event_seq,
+            json!({
+                "payer": e.payer.to_string(),
+                "merchant": e.merchant.to_string(),
+                "merchant_name": e.merchant_name,
+                "payment_account": e.payment_account.to_string(),
+                "payment_id": e.payment_id.to_string(),
+                "amount": e.amount.to_string(),
+                "refunded_amount": e.refunded_amount.to_string(),
+                "timestamp": e.timestamp,
+            }),
+        ),
+        _ => return None,
+    };
+    let body = json!({
+        "id": event_seq,
+        "type": kind,
+        "signature": signature.to_string(),
+        "slot": slot,
+        "data": data,
+    });
+    Some(Notification { merchant, body: body.to_string() })
+}
@@ -0,0 +1,244 @@
+//! Delivery against a mock HTTP receiver, and the whole listener against a
+//! local validator with the gateway deployed (`anchor localnet`). Run the
+//! latter with `cargo test -p orki-webhook -- --ignored`; `RPC_URL` and
+//! `ANCHOR_WALLET` override the localnet defaults.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use orki_gateway_client::instructions::{self, PaymentAsset, ProcessPaymentArgs, RegisterMerchantArgs};
+use orki_gateway_client::orki_gateway::constants::MAX_FEE_BPS;
+use orki_gateway_client::{pda, rpc};
+use orki_webhook::config::Config;
+use orki_webhook::cursor::{Cursor, Seen};
+use orki_webhook::delivery::{self, Deliverer, SIGNATURE_HEADER};
+use orki_webhook::listener::Listener;
+use serde_json::Value;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const SECRET: &str = "whsec_test";
+
+struct Request {
+    signature: String,
+    body: String,
+}
+
+/// An HTTP receiver on a free port that answers 500 to the first `failures`
+/// requests and 200 after, passing every request it reads to the returned
+/// channel.
+async fn receiver(failures: usize) -> (String, mpsc::UnboundedReceiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/orki", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut served = 0;
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let Some(request) = read_request(&mut stream).await else { continue };
+            let status = if served < failures { "500 Internal Server Error" } else { "200 OK" };
+            served += 1;
+            let response = format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+            stream.write_all(response.as_bytes()).await.unwrap();
+            let _ = tx.send(request);
+        }
+    });
+    (url, rx)
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<Request> {
+    let mut data = Vec::new();
+    let mut buf = [0; 4096];
+    let head_end = loop {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(i) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&data[..head_end]).into_owned();
+    let header = |name: &str| {
+        head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+        })
+    };
+    let length: usize = header("content-length")?.parse().ok()?;
+    while data.len() < head_end + length {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    Some(Request {
+        signature: header(SIGNATURE_HEADER)?,
+        body: String::from_utf8(data[head_end..head_end + length].to_vec()).ok()?,
+    })
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+#[test]
+fn signatures_verify_only_for_the_signed_body_and_secret() {
+    let header = delivery::sign(SECRET, 1_700_000_000, r#"{"id":1}"#);
+    assert_eq!(delivery::verify(SECRET, &header, r#"{"id":1}"#), Some(1_700_000_000));
+    assert_eq!(delivery::verify(SECRET, &header, r#"{"id":2}"#), None);
+    assert_eq!(delivery::verify("another", &header, r#"{"id":1}"#), None);
+
+    // The timestamp is covered too
+    let forged = header.replace("t=1700000000", "t=1800000000");
+    assert_eq!(delivery::verify(SECRET, &forged, r#"{"id":1}"#), None);
+}
+
+#[test]
+fn config_maps_merchants_to_urls() {
+    let merchant = Pubkey::new_unique();
+    let config = Config::parse(&format!(
+        "secret = \"{SECRET}\"\n[merchants]\n\"{merchant}\" = \"https://shop.example/hook\"\n"
+    ))
+    .unwrap();
+    assert_eq!(config.url(&merchant), Some("https://shop.example/hook"));
+    assert_eq!(config.url(&Pubkey::new_unique()), None);
+
+    assert!(Config::parse("secret = \"\"").is_err());
+    assert!(Config::parse("secret = \"s\"\n[merchants]\nnot-a-key = \"https://x\"\n").is_err());
+}
+
+#[test]
+fn cursor_survives_a_restart() {
+    let path = std::env::temp_dir().join(format!("orki-webhook-{}.cursor", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut cursor = Cursor::load(path.clone()).unwrap();
+    assert_eq!(cursor.last(), None);
+
+    let signature = Keypair::new().sign_message(b"tx");
+    cursor.advance(signature).unwrap();
+    assert_eq!(Cursor::load(path.clone()).unwrap().last(), Some(signature));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn delivery_retries_until_the_receiver_accepts() {
+    let (url, mut requests) = receiver(2).await;
+    let deliverer = Deliverer::new(SECRET.to_string(), 3, Duration::from_millis(10)).unwrap();
+    let body = r#"{"id":7,"type":"payment.processed"}"#;
+    deliverer.deliver(&url, body).await.unwrap();
+
+    for _ in 0..3 {
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.body, body);
+        let signed_at = delivery::verify(SECRET, &request.signature, &request.body).expect("valid signature");
+        assert!((now() - signed_at).abs() < 60);
+    }
+}
+
+#[tokio::test]
+async fn delivery_gives_up_after_the_last_attempt() {
+    let (url, mut requests) = receiver(usize::MAX).await;
+    let deliverer = Deliverer::new(SECRET.to_string(), 2, Duration::from_millis(10)).unwrap();
+    assert!(deliverer.deliver(&url, "{}").await.is_err());
+    assert!(requests.recv().await.is_some());
+    assert!(requests.recv().await.is_some());
+    assert!(requests.try_recv().is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a local validator"]
+async fn posts_a_signed_callback_for_a_payment() {
+    let url = std::env::var("RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let wallet = std::env::var("ANCHOR_WALLET")
+        .unwrap_or_else(|_| format!("{}/.config/solana/id.json", std::env::var("HOME").unwrap()));
+    let payer = read_keypair_file(&wallet).expect("wallet keypair");
+    let blocking = solana_rpc_client::rpc_client::RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+    if blocking.get_account(&pda::find_global_state_address().0).is_err() {
+        rpc::send(&blocking, &[instructions::initialize(&payer.pubkey(), payer.pubkey(), 100, payer.pubkey(), MAX_FEE_BPS, None)], &payer, &[]).unwrap();
+    }
+    if blocking.get_account(&pda::find_fee_stats_address(&Pubkey::default()).0).is_err() {
+        rpc::send(&blocking, &[instructions::open_fee_stats(&payer.pubkey(), Pubkey::default())], &payer, &[]).unwrap();
+    }
+
+    let (hook_url, mut requests) = receiver(0).await;
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("webhook-{}", nanos % 1_000_000_000_000);
+    let merchant = pda::find_merchant_address(&payer.pubkey(), &name).0;
+    let config = Config::parse(&format!("secret = \"{SECRET}\"\n[merchants]\n\"{merchant}\" = \"{hook_url}\"\n")).unwrap();
+
+    // Start the cursor at the newest gateway transaction so only ours follow
+    let cursor_path = std::env::temp_dir().join(format!("orki-webhook-localnet-{nanos}.cursor"));
+    let mut cursor = Cursor::load(cursor_path.clone()).unwrap();
+    let newest = blocking.get_signatures_for_address(&orki_gateway_client::orki_gateway::ID).unwrap();
+    if let Some(status) = newest.first() {
+        cursor.advance(status.signature.parse().unwrap()).unwrap();
+    }
+
+    let settlement_wallet = Keypair::new().pubkey();
+    let register = instructions::register_merchant(
+        &payer.pubkey(),
+        RegisterMerchantArgs {
+            settlement_wallet,
+            settlement_token: Pubkey::default(),
+            name: name.clone(),
+            sandbox: false,
+            referrer: None,
+            referrer_bps: 0,
+            accepts_any_token: true,
+            metadata_uri: String::new(),
+            category: 0,
+            merchant_index: rpc::next_merchant_index(&blocking).unwrap(),
+        },
+    );
+    rpc::send(&blocking, &[register], &payer, &[]).unwrap();
+    let payment_id = nanos as u64;
+    let pay = instructions::process_payment(
+        &payer.pubkey(),
+        ProcessPaymentArgs {
+            merchant_owner: payer.pubkey(),
+            merchant_name: name.clone(),
+            settlement_wallet,
+            asset: PaymentAsset::Sol,
+            amount: 100_000_000,
+            payment_id,
+            memo: None,
+            tip: None,
+            discount_code: None,
+            valid_until: 0,
+        },
+    );
+    let signature = rpc::send(&blocking, &[pay], &payer, &[]).unwrap();
+
+    let mut listener = Listener {
+        rpc: solana_rpc_client::nonblocking::rpc_client::RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        ws_url: String::new(),
+        config,
+        deliverer: Deliverer::new(SECRET.to_string(), 3, Duration::from_millis(100)).unwrap(),
+        cursor,
+        seen: Seen::new(100),
+        poll_interval: Duration::from_secs(1),
+    };
+    listener.catch_up().await.unwrap();
+    assert_eq!(listener.cursor.last(), Some(signature));
+
+    let request = requests.recv().await.unwrap();
+    assert!(delivery::verify(SECRET, &request.signature, &request.body).is_some());
+    let body: Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["type"], "payment.processed");
+    assert_eq!(body["signature"], signature.to_string());
+    assert_eq!(body["data"]["merchant"], merchant.to_string());
+    assert_eq!(body["data"]["merchant_name"], name);
+    assert_eq!(body["data"]["payment_id"], payment_id.to_string());
+    assert_eq!(body["data"]["amount"], "100000000");
+
+    // Running again posts nothing new
+    listener.catch_up().await.unwrap();
+    assert!(requests.try_recv().is_err());
+    std::fs::remove_file(&cursor_path).unwrap();
+}